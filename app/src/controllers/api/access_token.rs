@@ -0,0 +1,153 @@
+use actix_http::StatusCode;
+use actix_web::{
+    web::{self, Json, ServiceConfig},
+    HttpResponse,
+};
+use chrono::Utc;
+use shared::models::user::{
+    AccessTokenSummary, CreateAccessToken, CreatedAccessToken,
+};
+
+use crate::{
+    controllers::common::{access_token, AuthUser},
+    repository::access_token::AccessTokenRepository,
+    util::{error::Error, error_or::ErrorOr},
+};
+
+pub fn service<R: AccessTokenRepository>(cfg: &mut ServiceConfig) {
+    cfg.service(
+        web::scope("/v1/users/tokens")
+            .route("", web::get().to(get_all::<R>))
+            .route("", web::post().to(post::<R>))
+            .route("/{token_id}", web::delete().to(delete::<R>)),
+    );
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/users/tokens",
+    operation_id = "get_access_tokens",
+    tag = "users",
+    responses(
+        (
+            status = 200,
+            description = "The access tokens of the session user",
+            body = [AccessTokenSummary]
+        ),
+        (status = 401, description = "Not logged in", body = String),
+        (
+            status = 403,
+            description = "Used with an access token",
+            body = String
+        ),
+    ),
+    security(("session_cookie" = []))
+)]
+async fn get_all<R: AccessTokenRepository>(
+    repo: web::Data<R>,
+    user: AuthUser,
+) -> ErrorOr<Json<Vec<AccessTokenSummary>>> {
+    user.require_session()?;
+    let tokens = repo.get_access_tokens(&user.id).await?;
+
+    Json(tokens.into_iter().map(AccessTokenSummary::from).collect::<Vec<_>>())
+        .into()
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/users/tokens",
+    operation_id = "create_access_token",
+    tag = "users",
+    request_body = CreateAccessToken,
+    responses(
+        (
+            status = 200,
+            description = "The new token, it is only shown this once",
+            body = CreatedAccessToken
+        ),
+        (
+            status = 400,
+            description = "The name or the scopes are empty or the expiry is \
+                           in the past",
+            body = String
+        ),
+        (status = 401, description = "Not logged in", body = String),
+        (
+            status = 403,
+            description = "Used with an access token",
+            body = String
+        ),
+    ),
+    security(("session_cookie" = []))
+)]
+async fn post<R: AccessTokenRepository>(
+    create_token: web::Json<CreateAccessToken>,
+    repo: web::Data<R>,
+    user: AuthUser,
+) -> ErrorOr<Json<CreatedAccessToken>> {
+    user.require_session()?;
+    let bad_request = |message: &'static str| {
+        Error::External(StatusCode::BAD_REQUEST, message.into())
+    };
+
+    let name = create_token.name.trim();
+    if name.is_empty() {
+        Err(bad_request("The name of the token must not be empty."))?;
+    }
+    if create_token.scopes.is_empty() {
+        Err(bad_request("The token needs at least one scope."))?;
+    }
+    if create_token.expires_at.is_some_and(|at| at <= Utc::now()) {
+        Err(bad_request("The token must expire in the future."))?;
+    }
+
+    let mut scopes = create_token.scopes.clone();
+    scopes.sort();
+    scopes.dedup();
+    let (token, token_hash) = access_token::generate_access_token();
+    let access_token = repo
+        .create_access_token(
+            &user.id,
+            &token_hash,
+            name,
+            &scopes,
+            create_token.expires_at,
+        )
+        .await?;
+
+    Json(CreatedAccessToken { token, access_token: access_token.into() }).into()
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/users/tokens/{token_id}",
+    operation_id = "delete_access_token",
+    tag = "users",
+    params(("token_id" = i64, Path, description = "Id of the token")),
+    responses(
+        (status = 200, description = "The token was revoked"),
+        (status = 401, description = "Not logged in", body = String),
+        (
+            status = 403,
+            description = "Used with an access token",
+            body = String
+        ),
+        (
+            status = 404,
+            description = "The user has no such token",
+            body = String
+        ),
+    ),
+    security(("session_cookie" = []))
+)]
+async fn delete<R: AccessTokenRepository>(
+    token_id: web::Path<i64>,
+    repo: web::Data<R>,
+    user: AuthUser,
+) -> ErrorOr<HttpResponse> {
+    user.require_session()?;
+    repo.delete_access_token(&token_id, &user.id).await?;
+
+    HttpResponse::Ok().finish().into()
+}
@@ -0,0 +1,358 @@
+use actix_http::StatusCode;
+use actix_web::{
+    web::{self, Json, ServiceConfig},
+    HttpResponse,
+};
+use serde::Deserialize;
+use shared::models::{
+    audit::SecurityEventKind,
+    user::{AdminStats, SetRole, UserAccount},
+};
+use utoipa::IntoParams;
+
+use super::user::send_token;
+use crate::{
+    controllers::common::{self, request_id::RequestContext, token, AdminUser},
+    mail::{self, Mailer},
+    repository::{
+        access_token::AccessTokenRepository,
+        audit::AuditRepository,
+        session::SessionRepository,
+        todo::TodoRepository,
+        user::UserRepository,
+        user_token::{TokenPurpose, UserTokenRepository},
+        Backend,
+    },
+    util::{error::Error, error_or::ErrorOr},
+};
+
+/// Users listed at once if the search sets no limit.
+const DEFAULT_LIMIT: i64 = 50;
+const MAX_LIMIT: i64 = 200;
+
+pub fn service<B: Backend>(cfg: &mut ServiceConfig) {
+    cfg.service(
+        web::scope("/v1/admin")
+            .route("/users", web::get().to(get_users::<B::User>))
+            .route("/users/{user_id}", web::get().to(get_user::<B::User>))
+            .route("/users/{user_id}/role", web::put().to(set_role::<B::User>))
+            .route(
+                "/users/{user_id}/disable",
+                web::post().to(disable::<B::User, B::Session, B::Audit>),
+            )
+            .route("/users/{user_id}/enable", web::post().to(enable::<B::User>))
+            .route(
+                "/users/{user_id}/password-reset",
+                web::post().to(force_password_reset::<
+                    B::User,
+                    B::UserToken,
+                    B::Session,
+                    B::AccessToken,
+                    B::Audit,
+                >),
+            )
+            .route(
+                "/stats",
+                web::get().to(stats::<B::User, B::Todo, B::Session>),
+            ),
+    );
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct UserSearch {
+    /// Part of the name or email, all users are listed without it.
+    query: Option<String>,
+    /// Number of users to skip.
+    #[serde(default)]
+    offset: i64,
+    /// Number of users to list, 50 by default and at most 200.
+    limit: Option<i64>,
+}
+
+/// Administrators cannot lock themselves out, so there is always one left.
+fn not_yourself(admin: &AdminUser, user_id: i64) -> Result<(), Error> {
+    match admin.id == user_id {
+        true => Err(Error::External(
+            StatusCode::CONFLICT,
+            "Administrators cannot do this to their own account.".into(),
+        )),
+        false => Ok(()),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/users",
+    operation_id = "admin_get_users",
+    tag = "admin",
+    params(UserSearch),
+    responses(
+        (
+            status = 200,
+            description = "The matching users",
+            body = [UserAccount]
+        ),
+        (status = 400, description = "Invalid offset or limit", body = String),
+        (status = 401, description = "Not logged in", body = String),
+        (status = 403, description = "Not an administrator", body = String),
+    ),
+    security(("session_cookie" = []))
+)]
+async fn get_users<R: UserRepository>(
+    search: web::Query<UserSearch>,
+    repo: web::Data<R>,
+    _admin: AdminUser,
+) -> ErrorOr<Json<Vec<UserAccount>>> {
+    let limit = search.limit.unwrap_or(DEFAULT_LIMIT);
+    if search.offset < 0 || !(1..=MAX_LIMIT).contains(&limit) {
+        Err(Error::External(
+            StatusCode::BAD_REQUEST,
+            format!(
+                "The offset must not be negative and the limit has to be \
+                 between 1 and {MAX_LIMIT}."
+            )
+            .into(),
+        ))?;
+    }
+
+    let query = search.query.as_deref().map(str::trim);
+    let users = repo
+        .search_users(
+            query.filter(|query| !query.is_empty()),
+            search.offset,
+            limit,
+        )
+        .await?;
+
+    Json(users.into_iter().map(UserAccount::from).collect::<Vec<_>>()).into()
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/users/{user_id}",
+    operation_id = "admin_get_user",
+    tag = "admin",
+    params(("user_id" = i64, Path, description = "Id of the user")),
+    responses(
+        (status = 200, description = "The user", body = UserAccount),
+        (status = 401, description = "Not logged in", body = String),
+        (status = 403, description = "Not an administrator", body = String),
+        (status = 404, description = "User does not exist", body = String),
+    ),
+    security(("session_cookie" = []))
+)]
+async fn get_user<R: UserRepository>(
+    user_id: web::Path<i64>,
+    repo: web::Data<R>,
+    _admin: AdminUser,
+) -> ErrorOr<Json<UserAccount>> {
+    let user = repo.get_session_user(&user_id).await?;
+
+    Json(UserAccount::from(user)).into()
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/v1/admin/users/{user_id}/role",
+    operation_id = "admin_set_role",
+    tag = "admin",
+    params(("user_id" = i64, Path, description = "Id of the user")),
+    request_body = SetRole,
+    responses(
+        (status = 200, description = "The role was changed"),
+        (status = 401, description = "Not logged in", body = String),
+        (status = 403, description = "Not an administrator", body = String),
+        (status = 404, description = "User does not exist", body = String),
+        (status = 409, description = "The own account", body = String),
+    ),
+    security(("session_cookie" = []))
+)]
+async fn set_role<R: UserRepository>(
+    user_id: web::Path<i64>,
+    set_role: web::Json<SetRole>,
+    repo: web::Data<R>,
+    admin: AdminUser,
+    context: RequestContext,
+) -> ErrorOr<HttpResponse> {
+    not_yourself(&admin, *user_id)?;
+    let user = repo.get_session_user(&user_id).await?;
+    repo.set_role(&user.id, set_role.role, &context.audit(admin.id)).await?;
+
+    HttpResponse::Ok().finish().into()
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/users/{user_id}/disable",
+    operation_id = "admin_disable_user",
+    tag = "admin",
+    params(("user_id" = i64, Path, description = "Id of the user")),
+    responses(
+        (
+            status = 200,
+            description = "The user was disabled and their sessions revoked"
+        ),
+        (status = 401, description = "Not logged in", body = String),
+        (status = 403, description = "Not an administrator", body = String),
+        (status = 404, description = "User does not exist", body = String),
+        (status = 409, description = "The own account", body = String),
+    ),
+    security(("session_cookie" = []))
+)]
+async fn disable<
+    R: UserRepository,
+    S: SessionRepository,
+    E: AuditRepository,
+>(
+    user_id: web::Path<i64>,
+    repo: web::Data<R>,
+    sessions: web::Data<S>,
+    events: web::Data<E>,
+    admin: AdminUser,
+    context: RequestContext,
+) -> ErrorOr<HttpResponse> {
+    not_yourself(&admin, *user_id)?;
+    let user = repo.get_session_user(&user_id).await?;
+    repo.set_disabled(&user.id, true, &context.audit(admin.id)).await?;
+    // rejected anyway, but they should not linger until they expire
+    sessions.delete_user_sessions(user.id).await?;
+    let event = context.security_event(
+        SecurityEventKind::SessionsRevoked,
+        Some(user.id),
+        Some(admin.id),
+    );
+    events.record_security_event(&event).await?;
+
+    HttpResponse::Ok().finish().into()
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/users/{user_id}/enable",
+    operation_id = "admin_enable_user",
+    tag = "admin",
+    params(("user_id" = i64, Path, description = "Id of the user")),
+    responses(
+        (status = 200, description = "The user was enabled"),
+        (status = 401, description = "Not logged in", body = String),
+        (status = 403, description = "Not an administrator", body = String),
+        (status = 404, description = "User does not exist", body = String),
+    ),
+    security(("session_cookie" = []))
+)]
+async fn enable<R: UserRepository>(
+    user_id: web::Path<i64>,
+    repo: web::Data<R>,
+    admin: AdminUser,
+    context: RequestContext,
+) -> ErrorOr<HttpResponse> {
+    let user = repo.get_session_user(&user_id).await?;
+    repo.set_disabled(&user.id, false, &context.audit(admin.id)).await?;
+
+    HttpResponse::Ok().finish().into()
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/users/{user_id}/password-reset",
+    operation_id = "admin_force_password_reset",
+    tag = "admin",
+    params(("user_id" = i64, Path, description = "Id of the user")),
+    responses(
+        (
+            status = 200,
+            description = "The password was replaced by a random one, the \
+                           sessions and access tokens of the user were \
+                           revoked and a reset token was mailed to them"
+        ),
+        (status = 401, description = "Not logged in", body = String),
+        (status = 403, description = "Not an administrator", body = String),
+        (status = 404, description = "User does not exist", body = String),
+    ),
+    security(("session_cookie" = []))
+)]
+#[allow(clippy::too_many_arguments)]
+async fn force_password_reset<
+    R: UserRepository,
+    T: UserTokenRepository,
+    S: SessionRepository,
+    A: AccessTokenRepository,
+    E: AuditRepository,
+>(
+    user_id: web::Path<i64>,
+    repo: web::Data<R>,
+    tokens: web::Data<T>,
+    sessions: web::Data<S>,
+    access_tokens: web::Data<A>,
+    events: web::Data<E>,
+    mailer: web::Data<dyn Mailer>,
+    admin: AdminUser,
+    context: RequestContext,
+) -> ErrorOr<HttpResponse> {
+    let user = repo.get_session_user(&user_id).await?;
+    // nobody knows it, the old password stops working right away
+    let (password, _) = token::generate_token();
+    let password_hash = common::hash_password(&password).await?;
+    repo.set_password(&user.id, &password_hash, &context.audit(admin.id))
+        .await?;
+    sessions.delete_user_sessions(user.id).await?;
+    access_tokens.delete_user_access_tokens(&user.id).await?;
+    for kind in [
+        SecurityEventKind::PasswordChanged,
+        SecurityEventKind::SessionsRevoked,
+        SecurityEventKind::AccessTokensRevoked,
+    ] {
+        let event = context.security_event(kind, Some(user.id), Some(admin.id));
+        events.record_security_event(&event).await?;
+    }
+
+    mail::send_in_background(async move {
+        send_token(
+            tokens.get_ref(),
+            mailer.get_ref(),
+            &user,
+            TokenPurpose::ResetPassword,
+        )
+        .await
+    });
+
+    HttpResponse::Ok().finish().into()
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/stats",
+    operation_id = "admin_get_stats",
+    tag = "admin",
+    responses(
+        (
+            status = 200,
+            description = "Numbers across all users",
+            body = AdminStats
+        ),
+        (status = 401, description = "Not logged in", body = String),
+        (status = 403, description = "Not an administrator", body = String),
+    ),
+    security(("session_cookie" = []))
+)]
+async fn stats<R: UserRepository, T: TodoRepository, S: SessionRepository>(
+    repo: web::Data<R>,
+    todos: web::Data<T>,
+    sessions: web::Data<S>,
+    _admin: AdminUser,
+) -> ErrorOr<Json<AdminStats>> {
+    let user_stats = repo.user_stats().await?;
+    let todo_stats = todos.todo_stats().await?;
+    let active_sessions = sessions.count_sessions().await?;
+
+    Json(AdminStats {
+        users: user_stats.total,
+        admins: user_stats.admins,
+        disabled_users: user_stats.disabled,
+        todos: todo_stats.total,
+        done_todos: todo_stats.done,
+        active_sessions,
+    })
+    .into()
+}
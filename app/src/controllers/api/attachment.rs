@@ -0,0 +1,489 @@
+use actix_files::NamedFile;
+use actix_http::StatusCode;
+use actix_multipart::Multipart;
+use actix_web::{
+    http::header::{
+        Charset, ContentDisposition, DispositionParam, DispositionType,
+        ExtendedValue, CONTENT_TYPE, X_CONTENT_TYPE_OPTIONS,
+    },
+    web::{self, Json, ServiceConfig},
+    HttpRequest, HttpResponse,
+};
+use bytes::{Bytes, BytesMut};
+use futures_util::TryStreamExt;
+use sha2::{Digest, Sha256};
+use shared::models::{
+    attachment::{Attachment, StorageUsage},
+    user::TokenScope,
+};
+use utoipa::ToSchema;
+
+use super::todo::ensure_editor;
+use crate::{
+    blob::{self, Blob, BlobStore},
+    controllers::common::AuthUser,
+    repository::{
+        attachment::{AttachmentRepository, NewAttachment},
+        list::ListRepository,
+        todo::TodoRepository,
+        Backend,
+    },
+    util::{error::Error, error_or::ErrorOr},
+};
+
+/// Orphaned blobs removed from the blob store per query.
+const PURGE_BATCH: i64 = 100;
+/// Characters of a file name, longer ones are cut.
+const MAX_FILE_NAME_LENGTH: usize = 255;
+const DEFAULT_CONTENT_TYPE: &str = "application/octet-stream";
+
+/// How large attachments may get.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AttachmentLimits {
+    /// Bytes of a single file.
+    pub max_file_size: i64,
+    /// Bytes of all attachments a user uploaded.
+    pub quota: i64,
+}
+
+impl Default for AttachmentLimits {
+    fn default() -> Self {
+        Self { max_file_size: 10 << 20, quota: 100 << 20 }
+    }
+}
+
+/// Registers paths within the todos and users scopes, so it has to be
+/// configured before them.
+pub fn service<B: Backend>(cfg: &mut ServiceConfig) {
+    cfg.service(
+        web::scope("/v1/todos/{todo_id}/attachments")
+            .route("", web::get().to(get_all::<B::Todo, B::Attachment>))
+            .route("", web::post().to(post::<B::Todo, B::List, B::Attachment>))
+            .route(
+                "/{attachment_id}",
+                web::get().to(download::<B::Todo, B::Attachment>),
+            )
+            .route(
+                "/{attachment_id}",
+                web::delete().to(delete::<B::Todo, B::List, B::Attachment>),
+            ),
+    )
+    .route("/v1/users/storage", web::get().to(usage::<B::Attachment>));
+}
+
+/// Removes the content of deleted attachments from the blob store, also the
+/// content that an earlier purge failed to remove.
+pub(crate) async fn purge_orphaned_blobs<A: AttachmentRepository>(
+    repo: web::Data<A>,
+    store: web::Data<dyn BlobStore>,
+) -> ErrorOr<()> {
+    loop {
+        let storage_keys = repo.get_orphaned_blobs(PURGE_BATCH).await?;
+        if storage_keys.is_empty() {
+            return ().into();
+        }
+        for storage_key in storage_keys {
+            store.delete(&storage_key).await?;
+            repo.forget_orphaned_blob(&storage_key).await?;
+        }
+    }
+}
+
+/// The form of an upload, only documents the multipart body.
+#[allow(dead_code)]
+#[derive(ToSchema)]
+pub(super) struct Upload {
+    #[schema(value_type = String, format = Binary)]
+    file: Vec<u8>,
+}
+
+/// A file of an upload, read into memory.
+struct UploadedFile {
+    file_name: String,
+    content_type: String,
+    content: Bytes,
+}
+
+/// Reads the `file` field of the form, fails if it is missing or larger
+/// than `max_file_size`.
+async fn read_file(
+    mut form: Multipart,
+    max_file_size: i64,
+) -> Result<UploadedFile, Error> {
+    let invalid = |_| {
+        Error::External(
+            StatusCode::BAD_REQUEST,
+            "The body is not a valid multipart form.".into(),
+        )
+    };
+
+    while let Some(mut field) = form.try_next().await.map_err(invalid)? {
+        if field.name() != Some("file") {
+            continue;
+        }
+        let file_name = sanitize_file_name(
+            field
+                .content_disposition()
+                .and_then(ContentDisposition::get_filename)
+                .unwrap_or_default(),
+        );
+        let content_type = field
+            .content_type()
+            .map(ToString::to_string)
+            .unwrap_or_else(|| DEFAULT_CONTENT_TYPE.to_string());
+
+        let mut content = BytesMut::new();
+        while let Some(chunk) = field.try_next().await.map_err(invalid)? {
+            if (content.len() + chunk.len()) as i64 > max_file_size {
+                Err(Error::External(
+                    StatusCode::PAYLOAD_TOO_LARGE,
+                    format!(
+                        "A file must not be larger than {max_file_size} bytes."
+                    )
+                    .into(),
+                ))?;
+            }
+            content.extend_from_slice(&chunk);
+        }
+
+        return Ok(UploadedFile {
+            file_name,
+            content_type,
+            content: content.freeze(),
+        });
+    }
+
+    Err(Error::External(
+        StatusCode::BAD_REQUEST,
+        "The form has no `file` field.".into(),
+    ))
+}
+
+/// Keeps the last component of a path without control characters, clients
+/// may send the full path of the file.
+fn sanitize_file_name(file_name: &str) -> String {
+    let file_name = file_name
+        .rsplit(['/', '\\'])
+        .next()
+        .unwrap_or_default()
+        .chars()
+        .filter(|c| !c.is_control())
+        .take(MAX_FILE_NAME_LENGTH)
+        .collect::<String>();
+
+    match file_name.trim() {
+        "" | "." | ".." => "attachment".to_string(),
+        file_name => file_name.to_string(),
+    }
+}
+
+/// Makes browsers save the file under its name instead of displaying it,
+/// non-ASCII names have an ASCII fallback for older clients.
+fn content_disposition(file_name: &str) -> ContentDisposition {
+    let fallback = file_name
+        .chars()
+        .map(|c| if c.is_ascii() && c != '"' { c } else { '_' })
+        .collect::<String>();
+    let mut parameters = vec![DispositionParam::Filename(fallback)];
+    if !file_name.is_ascii() {
+        parameters.push(DispositionParam::FilenameExt(ExtendedValue {
+            charset: Charset::Ext("UTF-8".to_string()),
+            language_tag: None,
+            value: file_name.as_bytes().to_vec(),
+        }));
+    }
+
+    ContentDisposition { disposition: DispositionType::Attachment, parameters }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/todos/{todo_id}/attachments",
+    operation_id = "get_attachments",
+    tag = "attachments",
+    params(("todo_id" = i64, Path, description = "Id of the todo")),
+    responses(
+        (
+            status = 200,
+            description = "The attachments of the todo, oldest first",
+            body = [Attachment]
+        ),
+        (status = 401, description = "Not logged in", body = String),
+        (
+            status = 403,
+            description = "The user cannot see the todo or the access token \
+                           lacks the scope",
+            body = String
+        ),
+        (status = 404, description = "Todo does not exist", body = String),
+    ),
+    security(("session_cookie" = []), ("access_token" = ["read_todos"]))
+)]
+async fn get_all<R: TodoRepository, A: AttachmentRepository>(
+    todo_id: web::Path<i64>,
+    todos: web::Data<R>,
+    repo: web::Data<A>,
+    user: AuthUser,
+) -> ErrorOr<Json<Vec<Attachment>>> {
+    user.require(TokenScope::ReadTodos)?;
+    let todo = todos.get_todo(&todo_id, &user.id).await?;
+    let attachments = repo.get_attachments(&todo.id).await?;
+
+    Json(attachments).into()
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/todos/{todo_id}/attachments",
+    operation_id = "create_attachment",
+    tag = "attachments",
+    params(("todo_id" = i64, Path, description = "Id of the todo")),
+    request_body(content = Upload, content_type = "multipart/form-data"),
+    responses(
+        (status = 200, description = "The new attachment", body = Attachment),
+        (status = 400, description = "No file in the form", body = String),
+        (status = 401, description = "Not logged in", body = String),
+        (
+            status = 403,
+            description = "The user may not edit the todo or the access \
+                           token lacks the scope",
+            body = String
+        ),
+        (status = 404, description = "Todo does not exist", body = String),
+        (
+            status = 413,
+            description = "The file is too large or would exceed the quota \
+                           of the user",
+            body = String
+        ),
+    ),
+    security(("session_cookie" = []), ("access_token" = ["write_todos"]))
+)]
+#[allow(clippy::too_many_arguments)]
+async fn post<R: TodoRepository, L: ListRepository, A: AttachmentRepository>(
+    todo_id: web::Path<i64>,
+    form: Multipart,
+    todos: web::Data<R>,
+    lists: web::Data<L>,
+    repo: web::Data<A>,
+    store: web::Data<dyn BlobStore>,
+    limits: web::Data<AttachmentLimits>,
+    user: AuthUser,
+) -> ErrorOr<Json<Attachment>> {
+    user.require(TokenScope::WriteTodos)?;
+    let todo = todos.get_todo(&todo_id, &user.id).await?;
+    ensure_editor(lists.get_ref(), &todo, user.id).await?;
+
+    let file = read_file(form, limits.max_file_size).await?;
+    let attachment = NewAttachment {
+        todo_id: todo.id,
+        uploader_id: user.id,
+        file_name: file.file_name,
+        size: file.content.len() as i64,
+        content_type: file.content_type,
+        checksum: hex::encode(Sha256::digest(&file.content)),
+        storage_key: blob::new_key(),
+    };
+    // stored first, a failed upload must not leave an attachment without
+    // content behind
+    store.put(&attachment.storage_key, file.content).await?;
+
+    let created = repo.create_attachment(&attachment, limits.quota).await.0;
+    match created {
+        Ok(Some(attachment)) => Json(attachment).into(),
+        Ok(None) => {
+            store.delete(&attachment.storage_key).await?;
+            Err(Error::External(
+                StatusCode::PAYLOAD_TOO_LARGE,
+                format!(
+                    "The file would exceed your quota of {} bytes.",
+                    limits.quota
+                )
+                .into(),
+            ))?
+        }
+        Err(error) => {
+            store.delete(&attachment.storage_key).await?;
+            Err(error)?
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/todos/{todo_id}/attachments/{attachment_id}",
+    operation_id = "download_attachment",
+    tag = "attachments",
+    params(
+        ("todo_id" = i64, Path, description = "Id of the todo"),
+        ("attachment_id" = i64, Path, description = "Id of the attachment")
+    ),
+    responses(
+        (
+            status = 200,
+            description = "The content of the attachment, to be saved under \
+                           its file name",
+            content_type = "application/octet-stream",
+            body = Vec<u8>
+        ),
+        (status = 401, description = "Not logged in", body = String),
+        (
+            status = 403,
+            description = "The user cannot see the todo or the access token \
+                           lacks the scope",
+            body = String
+        ),
+        (
+            status = 404,
+            description = "Todo or attachment does not exist",
+            body = String
+        ),
+    ),
+    security(("session_cookie" = []), ("access_token" = ["read_todos"]))
+)]
+async fn download<R: TodoRepository, A: AttachmentRepository>(
+    path: web::Path<(i64, i64)>,
+    request: HttpRequest,
+    todos: web::Data<R>,
+    repo: web::Data<A>,
+    store: web::Data<dyn BlobStore>,
+    user: AuthUser,
+) -> ErrorOr<HttpResponse> {
+    user.require(TokenScope::ReadTodos)?;
+    let (todo_id, attachment_id) = path.into_inner();
+    let todo = todos.get_todo(&todo_id, &user.id).await?;
+    let attachment = repo.get_attachment(&todo.id, &attachment_id).await?;
+
+    let content_type = attachment
+        .content_type
+        .parse::<mime::Mime>()
+        .unwrap_or(mime::APPLICATION_OCTET_STREAM);
+    let disposition = content_disposition(&attachment.file_name);
+    let mut response = match store.get(&attachment.storage_key).await? {
+        // supports range requests and conditional downloads
+        Blob::File(path) => NamedFile::open_async(path)
+            .await?
+            .set_content_type(content_type)
+            .set_content_disposition(disposition)
+            .into_response(&request),
+        Blob::Bytes(content) => HttpResponse::Ok()
+            .insert_header((CONTENT_TYPE, content_type))
+            .insert_header(disposition)
+            .body(content),
+    };
+    // browsers must not render the file as some other type, e.g. as html
+    response
+        .headers_mut()
+        .insert(X_CONTENT_TYPE_OPTIONS, "nosniff".parse().expect("valid"));
+
+    response.into()
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/todos/{todo_id}/attachments/{attachment_id}",
+    operation_id = "delete_attachment",
+    tag = "attachments",
+    params(
+        ("todo_id" = i64, Path, description = "Id of the todo"),
+        ("attachment_id" = i64, Path, description = "Id of the attachment")
+    ),
+    responses(
+        (status = 200, description = "Attachment was deleted"),
+        (status = 401, description = "Not logged in", body = String),
+        (
+            status = 403,
+            description = "The user may not edit the todo or the access \
+                           token lacks the scope",
+            body = String
+        ),
+        (
+            status = 404,
+            description = "Todo or attachment does not exist",
+            body = String
+        ),
+    ),
+    security(("session_cookie" = []), ("access_token" = ["write_todos"]))
+)]
+async fn delete<
+    R: TodoRepository,
+    L: ListRepository,
+    A: AttachmentRepository,
+>(
+    path: web::Path<(i64, i64)>,
+    todos: web::Data<R>,
+    lists: web::Data<L>,
+    repo: web::Data<A>,
+    store: web::Data<dyn BlobStore>,
+    user: AuthUser,
+) -> ErrorOr<HttpResponse> {
+    user.require(TokenScope::WriteTodos)?;
+    let (todo_id, attachment_id) = path.into_inner();
+    let todo = todos.get_todo(&todo_id, &user.id).await?;
+    ensure_editor(lists.get_ref(), &todo, user.id).await?;
+    let attachment = repo.get_attachment(&todo.id, &attachment_id).await?;
+
+    repo.delete_attachment(&attachment.id).await?;
+    blob::delete_in_background(purge_orphaned_blobs(repo, store));
+
+    HttpResponse::Ok().finish().into()
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/users/storage",
+    operation_id = "get_storage_usage",
+    tag = "attachments",
+    responses(
+        (
+            status = 200,
+            description = "How much of their quota the attachments of the \
+                           user take up",
+            body = StorageUsage
+        ),
+        (status = 401, description = "Not logged in", body = String),
+        (
+            status = 403,
+            description = "The access token lacks the scope",
+            body = String
+        ),
+    ),
+    security(("session_cookie" = []), ("access_token" = ["read_todos"]))
+)]
+async fn usage<A: AttachmentRepository>(
+    repo: web::Data<A>,
+    limits: web::Data<AttachmentLimits>,
+    user: AuthUser,
+) -> ErrorOr<Json<StorageUsage>> {
+    user.require(TokenScope::ReadTodos)?;
+    let used = repo.get_usage(&user.id).await?;
+
+    Json(StorageUsage { used, quota: limits.quota }).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file_names_are_sanitized() {
+        assert_eq!(sanitize_file_name("C:\\Users\\jane\\cv.pdf"), "cv.pdf");
+        assert_eq!(sanitize_file_name("../../etc/passwd"), "passwd");
+        assert_eq!(sanitize_file_name("a\r\nb.txt"), "ab.txt");
+        assert_eq!(sanitize_file_name(".."), "attachment");
+        assert_eq!(sanitize_file_name(""), "attachment");
+    }
+
+    #[test]
+    fn non_ascii_file_names_have_a_fallback() {
+        assert_eq!(
+            content_disposition("notes.txt").to_string(),
+            "attachment; filename=\"notes.txt\""
+        );
+        assert_eq!(
+            content_disposition("bücher.txt").to_string(),
+            "attachment; filename=\"b_cher.txt\"; \
+             filename*=UTF-8''b%C3%BCcher.txt"
+        );
+    }
+}
@@ -0,0 +1,220 @@
+use actix_http::StatusCode;
+use actix_web::web::{self, Json, ServiceConfig};
+use serde::Deserialize;
+use shared::models::{
+    audit::{AuditEntity, AuditEvent, SecurityEvent},
+    user::TokenScope,
+};
+use utoipa::IntoParams;
+
+use crate::{
+    controllers::common::{AdminUser, AuthUser},
+    repository::{audit::AuditRepository, todo::TodoRepository, Backend},
+    util::{error::Error, error_or::ErrorOr},
+};
+
+/// Events listed at once if the page sets no limit.
+const DEFAULT_LIMIT: i64 = 50;
+const MAX_LIMIT: i64 = 200;
+
+/// Registers paths within the todos, users and admin scopes, so it has to
+/// be configured before them.
+pub fn service<B: Backend>(cfg: &mut ServiceConfig) {
+    cfg.route(
+        "/v1/todos/{todo_id}/history",
+        web::get().to(todo_history::<B::Todo, B::Audit>),
+    )
+    .route("/v1/users/activity", web::get().to(account_activity::<B::Audit>))
+    .route(
+        "/v1/users/security-events",
+        web::get().to(security_events::<B::Audit>),
+    )
+    .route(
+        "/v1/admin/security-events",
+        web::get().to(admin_security_events::<B::Audit>),
+    );
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct EventPage {
+    /// Number of events to skip.
+    #[serde(default)]
+    offset: i64,
+    /// Number of events to list, 50 by default and at most 200.
+    limit: Option<i64>,
+}
+
+impl EventPage {
+    /// Returns the limit, fails for a negative offset or a limit out of
+    /// range.
+    fn limit(&self) -> Result<i64, Error> {
+        let limit = self.limit.unwrap_or(DEFAULT_LIMIT);
+        if self.offset < 0 || !(1..=MAX_LIMIT).contains(&limit) {
+            Err(Error::External(
+                StatusCode::BAD_REQUEST,
+                format!(
+                    "The offset must not be negative and the limit has to be \
+                     between 1 and {MAX_LIMIT}."
+                )
+                .into(),
+            ))?;
+        }
+
+        Ok(limit)
+    }
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct SecurityEventFilter {
+    /// Only list the events of this account.
+    user_id: Option<i64>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/todos/{todo_id}/history",
+    operation_id = "get_todo_history",
+    tag = "audit",
+    params(
+        ("todo_id" = i64, Path, description = "Id of the todo"),
+        EventPage
+    ),
+    responses(
+        (
+            status = 200,
+            description = "A page of the changes of the todo, oldest first",
+            body = [AuditEvent]
+        ),
+        (status = 400, description = "Invalid offset or limit", body = String),
+        (status = 401, description = "Not logged in", body = String),
+        (
+            status = 403,
+            description = "The user cannot see the todo or the access token \
+                           lacks the scope",
+            body = String
+        ),
+        (status = 404, description = "Todo does not exist", body = String),
+    ),
+    security(("session_cookie" = []), ("access_token" = ["read_todos"]))
+)]
+async fn todo_history<R: TodoRepository, A: AuditRepository>(
+    todo_id: web::Path<i64>,
+    page: web::Query<EventPage>,
+    todos: web::Data<R>,
+    repo: web::Data<A>,
+    user: AuthUser,
+) -> ErrorOr<Json<Vec<AuditEvent>>> {
+    user.require(TokenScope::ReadTodos)?;
+    let limit = page.limit()?;
+    let todo = todos.get_todo(&todo_id, &user.id).await?;
+
+    let events = repo
+        .get_history(AuditEntity::Todo, &todo.id, page.offset, limit)
+        .await?;
+    Json(events).into()
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/users/activity",
+    operation_id = "get_account_activity",
+    tag = "audit",
+    params(EventPage),
+    responses(
+        (
+            status = 200,
+            description = "A page of the changes the user made and of the \
+                           changes of their account, newest first",
+            body = [AuditEvent]
+        ),
+        (status = 400, description = "Invalid offset or limit", body = String),
+        (status = 401, description = "Not logged in", body = String),
+        (
+            status = 403,
+            description = "The access token lacks the scope",
+            body = String
+        ),
+    ),
+    security(("session_cookie" = []), ("access_token" = ["manage_account"]))
+)]
+async fn account_activity<A: AuditRepository>(
+    page: web::Query<EventPage>,
+    repo: web::Data<A>,
+    user: AuthUser,
+) -> ErrorOr<Json<Vec<AuditEvent>>> {
+    user.require(TokenScope::ManageAccount)?;
+    let limit = page.limit()?;
+
+    let events =
+        repo.get_account_activity(&user.id, page.offset, limit).await?;
+    Json(events).into()
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/users/security-events",
+    operation_id = "get_security_events",
+    tag = "audit",
+    params(EventPage),
+    responses(
+        (
+            status = 200,
+            description = "A page of the logins and credential changes of the \
+                           account, newest first",
+            body = [SecurityEvent]
+        ),
+        (status = 400, description = "Invalid offset or limit", body = String),
+        (status = 401, description = "Not logged in", body = String),
+        (
+            status = 403,
+            description = "Used with an access token",
+            body = String
+        ),
+    ),
+    security(("session_cookie" = []))
+)]
+async fn security_events<A: AuditRepository>(
+    page: web::Query<EventPage>,
+    repo: web::Data<A>,
+    user: AuthUser,
+) -> ErrorOr<Json<Vec<SecurityEvent>>> {
+    user.require_session()?;
+    let limit = page.limit()?;
+
+    let events =
+        repo.get_security_events(Some(user.id), page.offset, limit).await?;
+    Json(events).into()
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/security-events",
+    operation_id = "admin_get_security_events",
+    tag = "admin",
+    params(SecurityEventFilter, EventPage),
+    responses(
+        (
+            status = 200,
+            description = "A page of the security stream, newest first",
+            body = [SecurityEvent]
+        ),
+        (status = 400, description = "Invalid offset or limit", body = String),
+        (status = 401, description = "Not logged in", body = String),
+        (status = 403, description = "Not an administrator", body = String),
+    ),
+    security(("session_cookie" = []))
+)]
+async fn admin_security_events<A: AuditRepository>(
+    filter: web::Query<SecurityEventFilter>,
+    page: web::Query<EventPage>,
+    repo: web::Data<A>,
+    _admin: AdminUser,
+) -> ErrorOr<Json<Vec<SecurityEvent>>> {
+    let limit = page.limit()?;
+
+    let events =
+        repo.get_security_events(filter.user_id, page.offset, limit).await?;
+    Json(events).into()
+}
@@ -0,0 +1,155 @@
+use actix_http::StatusCode;
+use actix_web::{
+    web::{self, Json, ServiceConfig},
+    HttpResponse,
+};
+use shared::models::{audit::SecurityEventKind, transfer::CalendarFeed};
+
+use super::transfer;
+use crate::{
+    controllers::common::{
+        ensure_enabled, request_id::RequestContext, token, AuthUser,
+    },
+    ical,
+    repository::{
+        audit::AuditRepository, list::ListRepository, todo::TodoRepository,
+        user::UserRepository, Backend,
+    },
+    util::{error::Error, error_or::ErrorOr},
+};
+
+/// Registers paths within the users scope, so it has to be configured before
+/// it.
+pub fn service<B: Backend>(cfg: &mut ServiceConfig) {
+    cfg.route(
+        "/v1/calendar/{token}.ics",
+        web::get().to(feed::<B::Todo, B::List, B::User>),
+    )
+    .service(
+        web::resource("/v1/users/calendar")
+            .route(web::post().to(post::<B::Todo, B::Audit>))
+            .route(web::delete().to(delete::<B::Todo, B::Audit>)),
+    );
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/calendar/{token}.ics",
+    operation_id = "get_calendar_feed",
+    tag = "calendar",
+    params(("token" = String, Path, description = "Token of the feed")),
+    responses(
+        (
+            status = 200,
+            description = "The personal todos of the user and the todos of \
+                           their lists as VTODO components",
+            content_type = "text/calendar",
+            body = String
+        ),
+        (
+            status = 403,
+            description = "The account of the user is disabled",
+            body = String
+        ),
+        (status = 404, description = "There is no such feed", body = String),
+    )
+)]
+async fn feed<R: TodoRepository, L: ListRepository, U: UserRepository>(
+    feed_token: web::Path<String>,
+    repo: web::Data<R>,
+    lists: web::Data<L>,
+    users: web::Data<U>,
+) -> ErrorOr<HttpResponse> {
+    let user_id = repo
+        .get_feed_user(&token::hash_token(&feed_token))
+        .await?
+        .ok_or_else(|| {
+            Error::External(
+                StatusCode::NOT_FOUND,
+                "There is no such calendar feed.".into(),
+            )
+        })?;
+    ensure_enabled(&users.get_session_user(&user_id).await?)?;
+
+    let todos = transfer::exported_todos(repo.get_ref(), &user_id).await?;
+    let calendar =
+        transfer::calendar(lists.get_ref(), &todos, &user_id).await?;
+
+    HttpResponse::Ok().content_type(ical::CONTENT_TYPE).body(calendar).into()
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/users/calendar",
+    operation_id = "create_calendar_feed",
+    tag = "calendar",
+    responses(
+        (
+            status = 200,
+            description = "The feed with a new token, the old one stops \
+                           working",
+            body = CalendarFeed
+        ),
+        (status = 401, description = "Not logged in", body = String),
+        (
+            status = 403,
+            description = "Used with an access token",
+            body = String
+        ),
+    ),
+    security(("session_cookie" = []))
+)]
+async fn post<R: TodoRepository, E: AuditRepository>(
+    repo: web::Data<R>,
+    events: web::Data<E>,
+    user: AuthUser,
+    context: RequestContext,
+) -> ErrorOr<Json<CalendarFeed>> {
+    user.require_session()?;
+    let (token, token_hash) = token::generate_token();
+    repo.set_feed_token(&user.id, Some(&token_hash), &context.audit(user.id))
+        .await?;
+    let event = context.security_event(
+        SecurityEventKind::FeedTokenChanged,
+        Some(user.id),
+        Some(user.id),
+    );
+    events.record_security_event(&event).await?;
+
+    Json(CalendarFeed { path: format!("/api/v1/calendar/{token}.ics"), token })
+        .into()
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/users/calendar",
+    operation_id = "delete_calendar_feed",
+    tag = "calendar",
+    responses(
+        (status = 200, description = "The feed stopped working"),
+        (status = 401, description = "Not logged in", body = String),
+        (
+            status = 403,
+            description = "Used with an access token",
+            body = String
+        ),
+    ),
+    security(("session_cookie" = []))
+)]
+async fn delete<R: TodoRepository, E: AuditRepository>(
+    repo: web::Data<R>,
+    events: web::Data<E>,
+    user: AuthUser,
+    context: RequestContext,
+) -> ErrorOr<HttpResponse> {
+    user.require_session()?;
+    repo.set_feed_token(&user.id, None, &context.audit(user.id)).await?;
+    let event = context.security_event(
+        SecurityEventKind::FeedTokenChanged,
+        Some(user.id),
+        Some(user.id),
+    );
+    events.record_security_event(&event).await?;
+
+    HttpResponse::Ok().finish().into()
+}
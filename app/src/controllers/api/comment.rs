@@ -0,0 +1,287 @@
+use actix_http::StatusCode;
+use actix_web::{
+    web::{self, Json, ServiceConfig},
+    HttpResponse,
+};
+use serde::Deserialize;
+use shared::models::{
+    comment::{Comment, CreateComment, UpdateComment},
+    todo::Todo,
+    user::TokenScope,
+};
+use utoipa::IntoParams;
+
+use super::todo::readers;
+use crate::{
+    controllers::common::AuthUser,
+    repository::{
+        comment::CommentRepository, list::ListRepository, todo::TodoRepository,
+        Backend,
+    },
+    util::{error::Error, error_or::ErrorOr},
+};
+
+/// Comments listed at once if the page sets no limit.
+const DEFAULT_LIMIT: i64 = 50;
+const MAX_LIMIT: i64 = 200;
+/// Characters of the markdown body of a comment.
+const MAX_BODY_LENGTH: usize = 10_000;
+
+pub fn service<B: Backend>(cfg: &mut ServiceConfig) {
+    cfg.service(
+        web::scope("/v1/todos/{todo_id}/comments")
+            .route("", web::get().to(get_all::<B::Todo, B::Comment>))
+            .route("", web::post().to(post::<B::Todo, B::List, B::Comment>))
+            .route(
+                "/{comment_id}",
+                web::put().to(put::<B::Todo, B::List, B::Comment>),
+            )
+            .route(
+                "/{comment_id}",
+                web::delete().to(delete::<B::Todo, B::Comment>),
+            ),
+    );
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct CommentPage {
+    /// Number of comments to skip.
+    #[serde(default)]
+    offset: i64,
+    /// Number of comments to list, 50 by default and at most 200.
+    limit: Option<i64>,
+}
+
+/// Fails unless the body has some text and every mentioned user can see the
+/// todo.
+async fn validate<L: ListRepository>(
+    lists: &L,
+    todo: &Todo,
+    body: &str,
+    mentions: &[i64],
+) -> Result<(), Error> {
+    if body.trim().is_empty() || body.chars().count() > MAX_BODY_LENGTH {
+        Err(Error::External(
+            StatusCode::BAD_REQUEST,
+            format!(
+                "A comment must not be empty or longer than \
+                 {MAX_BODY_LENGTH} characters."
+            )
+            .into(),
+        ))?;
+    }
+
+    if !mentions.is_empty() {
+        let readers = readers(lists, todo).await?;
+        if mentions.iter().any(|user_id| !readers.contains(user_id)) {
+            Err(Error::External(
+                StatusCode::BAD_REQUEST,
+                "Only users who can see the todo can be mentioned.".into(),
+            ))?;
+        }
+    }
+
+    Ok(())
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/todos/{todo_id}/comments",
+    operation_id = "get_comments",
+    tag = "comments",
+    params(
+        ("todo_id" = i64, Path, description = "Id of the todo"),
+        CommentPage
+    ),
+    responses(
+        (
+            status = 200,
+            description = "A page of the comments, oldest first",
+            body = [Comment]
+        ),
+        (status = 400, description = "Invalid offset or limit", body = String),
+        (status = 401, description = "Not logged in", body = String),
+        (
+            status = 403,
+            description = "The user cannot see the todo or the access token \
+                           lacks the scope",
+            body = String
+        ),
+        (status = 404, description = "Todo does not exist", body = String),
+    ),
+    security(("session_cookie" = []), ("access_token" = ["read_todos"]))
+)]
+async fn get_all<R: TodoRepository, C: CommentRepository>(
+    todo_id: web::Path<i64>,
+    page: web::Query<CommentPage>,
+    todos: web::Data<R>,
+    repo: web::Data<C>,
+    user: AuthUser,
+) -> ErrorOr<Json<Vec<Comment>>> {
+    user.require(TokenScope::ReadTodos)?;
+    let limit = page.limit.unwrap_or(DEFAULT_LIMIT);
+    if page.offset < 0 || !(1..=MAX_LIMIT).contains(&limit) {
+        Err(Error::External(
+            StatusCode::BAD_REQUEST,
+            format!(
+                "The offset must not be negative and the limit has to be \
+                 between 1 and {MAX_LIMIT}."
+            )
+            .into(),
+        ))?;
+    }
+
+    let todo = todos.get_todo(&todo_id, &user.id).await?;
+    let comments = repo.get_comments(&todo.id, page.offset, limit).await?;
+
+    Json(comments).into()
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/todos/{todo_id}/comments",
+    operation_id = "create_comment",
+    tag = "comments",
+    params(("todo_id" = i64, Path, description = "Id of the todo")),
+    request_body = CreateComment,
+    responses(
+        (status = 200, description = "The new comment", body = Comment),
+        (
+            status = 400,
+            description = "Empty or too long body, or a mentioned user cannot \
+                           see the todo",
+            body = String
+        ),
+        (status = 401, description = "Not logged in", body = String),
+        (
+            status = 403,
+            description = "The user cannot see the todo or the access token \
+                           lacks the scope",
+            body = String
+        ),
+        (status = 404, description = "Todo does not exist", body = String),
+    ),
+    security(("session_cookie" = []), ("access_token" = ["write_todos"]))
+)]
+async fn post<R: TodoRepository, L: ListRepository, C: CommentRepository>(
+    todo_id: web::Path<i64>,
+    create_comment: web::Json<CreateComment>,
+    todos: web::Data<R>,
+    lists: web::Data<L>,
+    repo: web::Data<C>,
+    user: AuthUser,
+) -> ErrorOr<Json<Comment>> {
+    user.require(TokenScope::WriteTodos)?;
+    // everybody who can see a todo may discuss it, viewers of a list too
+    let todo = todos.get_todo(&todo_id, &user.id).await?;
+    validate(
+        lists.get_ref(),
+        &todo,
+        &create_comment.body,
+        &create_comment.mentions,
+    )
+    .await?;
+
+    let comment =
+        repo.create_comment(&todo.id, &create_comment, &user.id).await?;
+    Json(comment).into()
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/v1/todos/{todo_id}/comments/{comment_id}",
+    operation_id = "update_comment",
+    tag = "comments",
+    params(
+        ("todo_id" = i64, Path, description = "Id of the todo"),
+        ("comment_id" = i64, Path, description = "Id of the comment")
+    ),
+    request_body = UpdateComment,
+    responses(
+        (status = 200, description = "The edited comment", body = Comment),
+        (
+            status = 400,
+            description = "Empty or too long body, or a mentioned user cannot \
+                           see the todo",
+            body = String
+        ),
+        (status = 401, description = "Not logged in", body = String),
+        (
+            status = 403,
+            description = "The comment of another user, the user cannot see \
+                           the todo, or the access token lacks the scope",
+            body = String
+        ),
+        (
+            status = 404,
+            description = "Todo or comment does not exist",
+            body = String
+        ),
+    ),
+    security(("session_cookie" = []), ("access_token" = ["write_todos"]))
+)]
+async fn put<R: TodoRepository, L: ListRepository, C: CommentRepository>(
+    path: web::Path<(i64, i64)>,
+    update_comment: web::Json<UpdateComment>,
+    todos: web::Data<R>,
+    lists: web::Data<L>,
+    repo: web::Data<C>,
+    user: AuthUser,
+) -> ErrorOr<Json<Comment>> {
+    user.require(TokenScope::WriteTodos)?;
+    let (todo_id, comment_id) = path.into_inner();
+    let todo = todos.get_todo(&todo_id, &user.id).await?;
+    validate(
+        lists.get_ref(),
+        &todo,
+        &update_comment.body,
+        &update_comment.mentions,
+    )
+    .await?;
+
+    let comment = repo
+        .update_comment(&todo.id, &comment_id, &update_comment, &user.id)
+        .await?;
+    Json(comment).into()
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/todos/{todo_id}/comments/{comment_id}",
+    operation_id = "delete_comment",
+    tag = "comments",
+    params(
+        ("todo_id" = i64, Path, description = "Id of the todo"),
+        ("comment_id" = i64, Path, description = "Id of the comment")
+    ),
+    responses(
+        (status = 200, description = "The comment was deleted"),
+        (status = 401, description = "Not logged in", body = String),
+        (
+            status = 403,
+            description = "The comment of another user, the user cannot see \
+                           the todo, or the access token lacks the scope",
+            body = String
+        ),
+        (
+            status = 404,
+            description = "Todo or comment does not exist",
+            body = String
+        ),
+    ),
+    security(("session_cookie" = []), ("access_token" = ["write_todos"]))
+)]
+async fn delete<R: TodoRepository, C: CommentRepository>(
+    path: web::Path<(i64, i64)>,
+    todos: web::Data<R>,
+    repo: web::Data<C>,
+    user: AuthUser,
+) -> ErrorOr<HttpResponse> {
+    user.require(TokenScope::WriteTodos)?;
+    let (todo_id, comment_id) = path.into_inner();
+    let todo = todos.get_todo(&todo_id, &user.id).await?;
+    repo.delete_comment(&todo.id, &comment_id, &user.id).await?;
+
+    HttpResponse::Ok().finish().into()
+}
@@ -2,6 +2,12 @@ use actix_web::{web, HttpResponse};
 
 pub const API_VERSION: &str = "v0.0.1";
 
+#[utoipa::path(
+    get,
+    path = "/api/v1/checks/health",
+    tag = "checks",
+    responses((status = 200, description = "The server is up and running"))
+)]
 async fn health() -> HttpResponse {
     HttpResponse::Ok().append_header(("version", API_VERSION)).finish()
 }
@@ -0,0 +1,572 @@
+use actix_http::StatusCode;
+use actix_web::{
+    web::{self, Json, ServiceConfig},
+    HttpResponse,
+};
+use shared::models::{
+    list::{
+        CreateList, InviteMember, ListInvitation, ListMember, ListPermission,
+        SetPermission, TodoList, UpdateList,
+    },
+    user::TokenScope,
+};
+
+use super::attachment::purge_orphaned_blobs;
+use crate::{
+    blob::{self, BlobStore},
+    controllers::common::AuthUser,
+    repository::{
+        attachment::AttachmentRepository, list::ListRepository,
+        user::UserRepository, Backend,
+    },
+    util::{error::Error, error_or::ErrorOr},
+};
+
+pub fn service<B: Backend>(cfg: &mut ServiceConfig) {
+    cfg.service(
+        web::scope("/v1/lists")
+            .route("", web::get().to(get_all::<B::List>))
+            .route("", web::post().to(post::<B::List>))
+            // before `/{list_id}`, which would otherwise match them
+            .route("/invitations", web::get().to(get_invitations::<B::List>))
+            .route(
+                "/invitations/{invitation_id}/accept",
+                web::post().to(accept_invitation::<B::List>),
+            )
+            .route(
+                "/invitations/{invitation_id}/decline",
+                web::post().to(decline_invitation::<B::List>),
+            )
+            .route("/{list_id}", web::get().to(get::<B::List>))
+            .route("/{list_id}", web::put().to(put::<B::List>))
+            .route(
+                "/{list_id}",
+                web::delete().to(delete::<B::List, B::Attachment>),
+            )
+            .route("/{list_id}/members", web::get().to(get_members::<B::List>))
+            .route(
+                "/{list_id}/members/{user_id}",
+                web::put().to(set_permission::<B::List>),
+            )
+            .route(
+                "/{list_id}/members/{user_id}",
+                web::delete().to(remove_member::<B::List>),
+            )
+            .route(
+                "/{list_id}/invitations",
+                web::post().to(invite::<B::List, B::User>),
+            ),
+    );
+}
+
+/// Fails unless the session user has at least `permission` on the list.
+pub(super) fn require(
+    list: &TodoList,
+    permission: ListPermission,
+) -> Result<(), Error> {
+    match list.permission >= permission {
+        true => Ok(()),
+        false => Err(Error::External(
+            StatusCode::FORBIDDEN,
+            format!(
+                "This needs the {} permission of the list.",
+                permission.as_str()
+            )
+            .into(),
+        )),
+    }
+}
+
+fn valid_name(name: &str) -> Result<&str, Error> {
+    match name.trim() {
+        "" => Err(Error::External(
+            StatusCode::BAD_REQUEST,
+            "The name of a list must not be empty.".into(),
+        )),
+        name => Ok(name),
+    }
+}
+
+/// The owner always stays an admin of their list, so it cannot be orphaned.
+fn not_the_owner(list: &TodoList, user_id: i64) -> Result<(), Error> {
+    match list.owner == user_id {
+        true => Err(Error::External(
+            StatusCode::CONFLICT,
+            "The owner of a list cannot leave it or lose their permission."
+                .into(),
+        )),
+        false => Ok(()),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/lists",
+    operation_id = "get_lists",
+    tag = "lists",
+    responses(
+        (
+            status = 200,
+            description = "The lists the user is a member of",
+            body = [TodoList]
+        ),
+        (status = 401, description = "Not logged in", body = String),
+        (
+            status = 403,
+            description = "The access token lacks the scope",
+            body = String
+        ),
+    ),
+    security(("session_cookie" = []), ("access_token" = ["read_todos"]))
+)]
+async fn get_all<R: ListRepository>(
+    repo: web::Data<R>,
+    user: AuthUser,
+) -> ErrorOr<Json<Vec<TodoList>>> {
+    user.require(TokenScope::ReadTodos)?;
+    let lists = repo.get_lists(&user.id).await?;
+
+    Json(lists).into()
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/lists",
+    operation_id = "create_list",
+    tag = "lists",
+    request_body = CreateList,
+    responses(
+        (
+            status = 200,
+            description = "The new list, owned by the user",
+            body = TodoList
+        ),
+        (status = 400, description = "The name is empty", body = String),
+        (status = 401, description = "Not logged in", body = String),
+        (
+            status = 403,
+            description = "The access token lacks the scope",
+            body = String
+        ),
+    ),
+    security(("session_cookie" = []), ("access_token" = ["write_todos"]))
+)]
+async fn post<R: ListRepository>(
+    repo: web::Data<R>,
+    create_list: web::Json<CreateList>,
+    user: AuthUser,
+) -> ErrorOr<Json<TodoList>> {
+    user.require(TokenScope::WriteTodos)?;
+    let name = valid_name(&create_list.name)?.to_string();
+    let list = repo.create_list(&CreateList { name }, &user.id).await?;
+
+    Json(list).into()
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/lists/{list_id}",
+    operation_id = "get_list",
+    tag = "lists",
+    params(("list_id" = i64, Path, description = "Id of the list")),
+    responses(
+        (status = 200, description = "The requested list", body = TodoList),
+        (status = 401, description = "Not logged in", body = String),
+        (
+            status = 403,
+            description = "The access token lacks the scope",
+            body = String
+        ),
+        (
+            status = 404,
+            description = "List does not exist or the user is no member",
+            body = String
+        ),
+    ),
+    security(("session_cookie" = []), ("access_token" = ["read_todos"]))
+)]
+async fn get<R: ListRepository>(
+    list_id: web::Path<i64>,
+    repo: web::Data<R>,
+    user: AuthUser,
+) -> ErrorOr<Json<TodoList>> {
+    user.require(TokenScope::ReadTodos)?;
+    let list = repo.get_list(&list_id, &user.id).await?;
+
+    Json(list).into()
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/v1/lists/{list_id}",
+    operation_id = "update_list",
+    tag = "lists",
+    params(("list_id" = i64, Path, description = "Id of the list")),
+    request_body = UpdateList,
+    responses(
+        (status = 200, description = "The list was renamed"),
+        (status = 400, description = "The name is empty", body = String),
+        (status = 401, description = "Not logged in", body = String),
+        (
+            status = 403,
+            description = "Not an admin of the list or the access token \
+                           lacks the scope",
+            body = String
+        ),
+        (
+            status = 404,
+            description = "List does not exist or the user is no member",
+            body = String
+        ),
+    ),
+    security(("session_cookie" = []), ("access_token" = ["write_todos"]))
+)]
+async fn put<R: ListRepository>(
+    list_id: web::Path<i64>,
+    update_list: web::Json<UpdateList>,
+    repo: web::Data<R>,
+    user: AuthUser,
+) -> ErrorOr<HttpResponse> {
+    user.require(TokenScope::WriteTodos)?;
+    let list = repo.get_list(&list_id, &user.id).await?;
+    require(&list, ListPermission::Admin)?;
+    repo.rename_list(&list.id, valid_name(&update_list.name)?).await?;
+
+    HttpResponse::Ok().finish().into()
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/lists/{list_id}",
+    operation_id = "delete_list",
+    tag = "lists",
+    params(("list_id" = i64, Path, description = "Id of the list")),
+    responses(
+        (
+            status = 200,
+            description = "The list was deleted with all its todos"
+        ),
+        (status = 401, description = "Not logged in", body = String),
+        (
+            status = 403,
+            description = "Not the owner of the list or the access token \
+                           lacks the scope",
+            body = String
+        ),
+        (
+            status = 404,
+            description = "List does not exist or the user is no member",
+            body = String
+        ),
+    ),
+    security(("session_cookie" = []), ("access_token" = ["write_todos"]))
+)]
+async fn delete<R: ListRepository, A: AttachmentRepository>(
+    list_id: web::Path<i64>,
+    repo: web::Data<R>,
+    attachments: web::Data<A>,
+    store: web::Data<dyn BlobStore>,
+    user: AuthUser,
+) -> ErrorOr<HttpResponse> {
+    user.require(TokenScope::WriteTodos)?;
+    let list = repo.get_list(&list_id, &user.id).await?;
+    if list.owner != user.id {
+        Err(Error::External(
+            StatusCode::FORBIDDEN,
+            "Only the owner may delete a list.".into(),
+        ))?;
+    }
+    repo.delete_list(&list.id).await?;
+    blob::delete_in_background(purge_orphaned_blobs(attachments, store));
+
+    HttpResponse::Ok().finish().into()
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/lists/{list_id}/members",
+    operation_id = "get_list_members",
+    tag = "lists",
+    params(("list_id" = i64, Path, description = "Id of the list")),
+    responses(
+        (
+            status = 200,
+            description = "The members of the list, including the owner",
+            body = [ListMember]
+        ),
+        (status = 401, description = "Not logged in", body = String),
+        (
+            status = 403,
+            description = "The access token lacks the scope",
+            body = String
+        ),
+        (
+            status = 404,
+            description = "List does not exist or the user is no member",
+            body = String
+        ),
+    ),
+    security(("session_cookie" = []), ("access_token" = ["read_todos"]))
+)]
+async fn get_members<R: ListRepository>(
+    list_id: web::Path<i64>,
+    repo: web::Data<R>,
+    user: AuthUser,
+) -> ErrorOr<Json<Vec<ListMember>>> {
+    user.require(TokenScope::ReadTodos)?;
+    let list = repo.get_list(&list_id, &user.id).await?;
+    let members = repo.get_members(&list.id).await?;
+
+    Json(members).into()
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/v1/lists/{list_id}/members/{user_id}",
+    operation_id = "set_list_member_permission",
+    tag = "lists",
+    params(
+        ("list_id" = i64, Path, description = "Id of the list"),
+        ("user_id" = i64, Path, description = "Id of the member"),
+    ),
+    request_body = SetPermission,
+    responses(
+        (status = 200, description = "The permission was changed"),
+        (status = 401, description = "Not logged in", body = String),
+        (
+            status = 403,
+            description = "Not an admin of the list or used with an access \
+                           token",
+            body = String
+        ),
+        (
+            status = 404,
+            description = "List or member does not exist",
+            body = String
+        ),
+        (status = 409, description = "The owner of the list", body = String),
+    ),
+    security(("session_cookie" = []))
+)]
+async fn set_permission<R: ListRepository>(
+    path: web::Path<(i64, i64)>,
+    set_permission: web::Json<SetPermission>,
+    repo: web::Data<R>,
+    user: AuthUser,
+) -> ErrorOr<HttpResponse> {
+    user.require_session()?;
+    let (list_id, member_id) = path.into_inner();
+    let list = repo.get_list(&list_id, &user.id).await?;
+    require(&list, ListPermission::Admin)?;
+    not_the_owner(&list, member_id)?;
+    repo.set_member_permission(&list.id, &member_id, set_permission.permission)
+        .await?;
+
+    HttpResponse::Ok().finish().into()
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/lists/{list_id}/members/{user_id}",
+    operation_id = "remove_list_member",
+    tag = "lists",
+    params(
+        ("list_id" = i64, Path, description = "Id of the list"),
+        ("user_id" = i64, Path, description = "Id of the member"),
+    ),
+    responses(
+        (
+            status = 200,
+            description = "The member was removed or left the list"
+        ),
+        (status = 401, description = "Not logged in", body = String),
+        (
+            status = 403,
+            description = "Removing another member without being an admin \
+                           of the list, or used with an access token",
+            body = String
+        ),
+        (
+            status = 404,
+            description = "List or member does not exist",
+            body = String
+        ),
+        (status = 409, description = "The owner of the list", body = String),
+    ),
+    security(("session_cookie" = []))
+)]
+async fn remove_member<R: ListRepository>(
+    path: web::Path<(i64, i64)>,
+    repo: web::Data<R>,
+    user: AuthUser,
+) -> ErrorOr<HttpResponse> {
+    user.require_session()?;
+    let (list_id, member_id) = path.into_inner();
+    let list = repo.get_list(&list_id, &user.id).await?;
+    // every member may leave on their own
+    if member_id != user.id {
+        require(&list, ListPermission::Admin)?;
+    }
+    not_the_owner(&list, member_id)?;
+    repo.remove_member(&list.id, &member_id, &user.id).await?;
+
+    HttpResponse::Ok().finish().into()
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/lists/{list_id}/invitations",
+    operation_id = "invite_list_member",
+    tag = "lists",
+    params(("list_id" = i64, Path, description = "Id of the list")),
+    request_body = InviteMember,
+    responses(
+        (
+            status = 200,
+            description = "The user was invited if the email is registered \
+                           and no member yet"
+        ),
+        (status = 401, description = "Not logged in", body = String),
+        (
+            status = 403,
+            description = "Not an admin of the list or used with an access \
+                           token",
+            body = String
+        ),
+        (
+            status = 404,
+            description = "List does not exist or the user is no member",
+            body = String
+        ),
+    ),
+    security(("session_cookie" = []))
+)]
+async fn invite<R: ListRepository, U: UserRepository>(
+    list_id: web::Path<i64>,
+    invite: web::Json<InviteMember>,
+    repo: web::Data<R>,
+    users: web::Data<U>,
+    user: AuthUser,
+) -> ErrorOr<HttpResponse> {
+    user.require_session()?;
+    let list = repo.get_list(&list_id, &user.id).await?;
+    require(&list, ListPermission::Admin)?;
+
+    // answers the same whether the email is unknown, registered or already
+    // used by a member
+    let invitee = match users.get_user_by_email(invite.email.trim()).await.0 {
+        Ok(invitee) => invitee,
+        Err(Error::External(StatusCode::NOT_FOUND, _)) => {
+            return HttpResponse::Ok().finish().into();
+        }
+        Err(error) => Err(error)?,
+    };
+    let members = repo.get_members(&list.id).await?;
+    if members.iter().any(|member| member.user_id == invitee.id) {
+        return HttpResponse::Ok().finish().into();
+    }
+    repo.create_invitation(&list.id, &invitee.id, &user.id, invite.permission)
+        .await?;
+
+    HttpResponse::Ok().finish().into()
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/lists/invitations",
+    operation_id = "get_list_invitations",
+    tag = "lists",
+    responses(
+        (
+            status = 200,
+            description = "The pending invitations of the session user",
+            body = [ListInvitation]
+        ),
+        (status = 401, description = "Not logged in", body = String),
+        (
+            status = 403,
+            description = "Used with an access token",
+            body = String
+        ),
+    ),
+    security(("session_cookie" = []))
+)]
+async fn get_invitations<R: ListRepository>(
+    repo: web::Data<R>,
+    user: AuthUser,
+) -> ErrorOr<Json<Vec<ListInvitation>>> {
+    user.require_session()?;
+    let invitations = repo.get_invitations(&user.id).await?;
+
+    Json(invitations).into()
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/lists/invitations/{invitation_id}/accept",
+    operation_id = "accept_list_invitation",
+    tag = "lists",
+    params(
+        ("invitation_id" = i64, Path, description = "Id of the invitation"),
+    ),
+    responses(
+        (status = 200, description = "The user joined the list"),
+        (status = 401, description = "Not logged in", body = String),
+        (
+            status = 403,
+            description = "Used with an access token",
+            body = String
+        ),
+        (
+            status = 404,
+            description = "No invitation of the session user",
+            body = String
+        ),
+    ),
+    security(("session_cookie" = []))
+)]
+async fn accept_invitation<R: ListRepository>(
+    invitation_id: web::Path<i64>,
+    repo: web::Data<R>,
+    user: AuthUser,
+) -> ErrorOr<HttpResponse> {
+    user.require_session()?;
+    repo.accept_invitation(&invitation_id, &user.id).await?;
+
+    HttpResponse::Ok().finish().into()
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/lists/invitations/{invitation_id}/decline",
+    operation_id = "decline_list_invitation",
+    tag = "lists",
+    params(
+        ("invitation_id" = i64, Path, description = "Id of the invitation"),
+    ),
+    responses(
+        (status = 200, description = "The invitation was declined"),
+        (status = 401, description = "Not logged in", body = String),
+        (
+            status = 403,
+            description = "Used with an access token",
+            body = String
+        ),
+        (
+            status = 404,
+            description = "No invitation of the session user",
+            body = String
+        ),
+    ),
+    security(("session_cookie" = []))
+)]
+async fn decline_invitation<R: ListRepository>(
+    invitation_id: web::Path<i64>,
+    repo: web::Data<R>,
+    user: AuthUser,
+) -> ErrorOr<HttpResponse> {
+    user.require_session()?;
+    repo.decline_invitation(&invitation_id, &user.id).await?;
+
+    HttpResponse::Ok().finish().into()
+}
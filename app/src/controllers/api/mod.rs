@@ -1,18 +1,43 @@
 use actix_web::web;
 
-use crate::repository::{
-    todo::PostgresTodoRepository, user::PostgresUserRepository,
-};
+use crate::repository::Backend;
 
+pub mod access_token;
+pub mod admin;
+pub mod attachment;
+pub mod audit;
+pub mod calendar;
+pub mod comment;
 pub mod health;
+pub mod list;
+pub mod oidc;
+pub mod openapi;
+pub mod passkey;
 pub mod todo;
+pub mod totp;
+pub mod transfer;
 pub mod user;
 
-pub fn service(cfg: &mut actix_web::web::ServiceConfig) {
+pub fn service<B: Backend>(cfg: &mut actix_web::web::ServiceConfig) {
     cfg.service(
         web::scope("/api")
             .configure(health::service)
-            .configure(todo::service::<PostgresTodoRepository>)
-            .configure(user::service::<PostgresUserRepository>),
+            .configure(openapi::service)
+            // before the todos, users and admin scopes, which would otherwise
+            // match their paths
+            .configure(audit::service::<B>)
+            .configure(attachment::service::<B>)
+            .configure(calendar::service::<B>)
+            .configure(comment::service::<B>)
+            .configure(transfer::service::<B>)
+            .configure(todo::service::<B>)
+            .configure(list::service::<B>)
+            // before the users scope, which would otherwise match their paths
+            .configure(totp::service::<B>)
+            .configure(passkey::service::<B>)
+            .configure(oidc::service::<B>)
+            .configure(access_token::service::<B::AccessToken>)
+            .configure(admin::service::<B>)
+            .configure(user::service::<B>),
     );
 }
@@ -0,0 +1,164 @@
+use actix_http::{header::LOCATION, StatusCode};
+use actix_web::{
+    web::{self, ServiceConfig},
+    HttpRequest, HttpResponse,
+};
+use serde::Deserialize;
+use shared::models::audit::SecurityEventKind;
+use utoipa::IntoParams;
+
+use crate::{
+    controllers::common::{
+        oidc::{self, OidcProvider},
+        request_id::RequestContext,
+    },
+    repository::{
+        audit::AuditRepository, oidc::OidcIdentityRepository,
+        totp::TotpRepository, user::UserRepository, Backend,
+    },
+    util::{error::Error, error_or::ErrorOr},
+};
+
+pub fn service<B: Backend>(cfg: &mut ServiceConfig) {
+    cfg.service(
+        web::scope("/v1/users/oidc")
+            .route("/login", web::get().to(login))
+            .route(
+                "/callback",
+                web::get().to(callback::<
+                    B::User,
+                    B::OidcIdentity,
+                    B::Totp,
+                    B::Audit,
+                >),
+            ),
+    );
+}
+
+/// The query of the redirect back from the provider.
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct Callback {
+    code: Option<String>,
+    state: Option<String>,
+    /// Set instead of `code` if the login failed or was cancelled.
+    error: Option<String>,
+}
+
+/// Single sign-on is only available if a provider is configured.
+fn configured(
+    provider: Option<web::Data<OidcProvider>>,
+) -> Result<web::Data<OidcProvider>, Error> {
+    provider.ok_or_else(|| {
+        Error::External(
+            StatusCode::NOT_FOUND,
+            "Single sign-on is not configured.".into(),
+        )
+    })
+}
+
+fn redirect(location: &str) -> HttpResponse {
+    HttpResponse::SeeOther().insert_header((LOCATION, location)).finish()
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/users/oidc/login",
+    operation_id = "start_oidc_login",
+    tag = "users",
+    responses(
+        (status = 303, description = "Redirects to the identity provider"),
+        (
+            status = 404,
+            description = "Single sign-on is not configured",
+            body = String
+        ),
+    )
+)]
+async fn login(
+    request: HttpRequest,
+    provider: Option<web::Data<OidcProvider>>,
+) -> ErrorOr<HttpResponse> {
+    let provider = configured(provider)?;
+    let url = oidc::start_login(&request, &provider).await?;
+
+    redirect(url.as_str()).into()
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/users/oidc/callback",
+    operation_id = "finish_oidc_login",
+    tag = "users",
+    params(Callback),
+    responses(
+        (
+            status = 303,
+            description = "Logged in, the session cookie is set and the \
+                           browser is sent back to the frontend"
+        ),
+        (
+            status = 401,
+            description = "The login failed or was cancelled",
+            body = String
+        ),
+        (
+            status = 403,
+            description = "The provider shared no verified email to link \
+                           the account with, the user with the email has to \
+                           log in first to link it, or the account is \
+                           disabled",
+            body = String
+        ),
+        (
+            status = 404,
+            description = "Single sign-on is not configured",
+            body = String
+        ),
+    )
+)]
+#[allow(clippy::too_many_arguments)]
+async fn callback<
+    R: UserRepository,
+    O: OidcIdentityRepository,
+    T: TotpRepository,
+    E: AuditRepository,
+>(
+    request: HttpRequest,
+    context: RequestContext,
+    query: web::Query<Callback>,
+    provider: Option<web::Data<OidcProvider>>,
+    repo: web::Data<R>,
+    identities: web::Data<O>,
+    totp_repo: web::Data<T>,
+    events: web::Data<E>,
+) -> ErrorOr<HttpResponse> {
+    let provider = configured(provider)?;
+    let (code, state) = match (&query.code, &query.state, &query.error) {
+        (Some(code), Some(state), None) => (code, state),
+        _ => Err(Error::External(
+            StatusCode::UNAUTHORIZED,
+            "The single sign-on was cancelled.".into(),
+        ))?,
+    };
+
+    let user_id = oidc::login(
+        &request,
+        &provider,
+        repo.get_ref(),
+        identities.get_ref(),
+        totp_repo.get_ref(),
+        code,
+        state,
+        &context.anonymous_audit(),
+    )
+    .await?;
+    let event = context.security_event(
+        SecurityEventKind::LoginSucceeded,
+        Some(user_id),
+        None,
+    );
+    events.record_security_event(&event).await?;
+
+    redirect(&provider.settings().post_login_redirect).into()
+}
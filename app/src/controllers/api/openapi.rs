@@ -0,0 +1,409 @@
+use actix_web::{web, HttpResponse};
+use utoipa::{
+    openapi::{
+        security::{ApiKey, ApiKeyValue, Http, HttpAuthScheme, SecurityScheme},
+        RefOr,
+    },
+    Modify, OpenApi,
+};
+use utoipa_redoc::{Redoc, Servable};
+
+use shared::models::{
+    attachment::{Attachment, StorageUsage},
+    audit::{
+        AuditAction, AuditEntity, AuditEvent, SecurityEvent, SecurityEventKind,
+    },
+    comment::{Comment, CreateComment, UpdateComment},
+    list::{
+        CreateList, InviteMember, ListInvitation, ListMember, ListPermission,
+        SetPermission, TodoList, UpdateList,
+    },
+    todo::{
+        AssignTodo, CreateTodo, Todo, TodoAction, TodoActivity, UpdateTodo,
+    },
+    transfer::{
+        CalendarFeed, ExportedTodo, ImportError, ImportReport, ImportTodos,
+        TransferFormat,
+    },
+    user::{
+        AccessTokenSummary, AdminStats, CreateAccessToken, CreateUser,
+        CreatedAccessToken, PasskeySummary, RecoveryCodes,
+        RequestPasswordReset, ResetPassword, Role, SetRole, SignInResponse,
+        SignInUser, StartPasskeyLogin, StartPasskeyRegistration, TokenScope,
+        TotpCode, TotpEnrollment, UpdateUser, User, UserAccount, VerifyEmail,
+    },
+};
+
+use super::{
+    access_token, admin,
+    attachment::{self, Upload},
+    audit, calendar, comment, health, list, oidc, passkey, todo, totp,
+    transfer, user,
+};
+use crate::util::error;
+
+/// OpenAPI document of the lentos api.
+///
+/// Every handler that is registered in one of the `service` functions of this
+/// module has to be listed in `paths` and every model it uses in `schemas`.
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "lentos", description = "The lentos todo api."),
+    paths(
+        health::health,
+        todo::get_all,
+        todo::get,
+        todo::post,
+        todo::put,
+        todo::delete,
+        todo::assign,
+        todo::activity,
+        transfer::export,
+        transfer::import,
+        calendar::feed,
+        calendar::post,
+        calendar::delete,
+        audit::todo_history,
+        audit::account_activity,
+        audit::security_events,
+        audit::admin_security_events,
+        comment::get_all,
+        comment::post,
+        comment::put,
+        comment::delete,
+        attachment::get_all,
+        attachment::post,
+        attachment::download,
+        attachment::delete,
+        attachment::usage,
+        list::get_all,
+        list::post,
+        list::get,
+        list::put,
+        list::delete,
+        list::get_members,
+        list::set_permission,
+        list::remove_member,
+        list::invite,
+        list::get_invitations,
+        list::accept_invitation,
+        list::decline_invitation,
+        user::login,
+        user::login_totp,
+        user::logout,
+        user::register,
+        user::request_email_verification,
+        user::verify_email,
+        user::request_password_reset,
+        user::reset_password,
+        user::get,
+        user::put,
+        user::delete,
+        totp::enroll,
+        totp::confirm,
+        totp::disable,
+        passkey::get_all,
+        passkey::start_registration,
+        passkey::finish_registration,
+        passkey::start_login,
+        passkey::finish_login,
+        passkey::delete,
+        oidc::login,
+        oidc::callback,
+        access_token::get_all,
+        access_token::post,
+        access_token::delete,
+        admin::get_users,
+        admin::get_user,
+        admin::set_role,
+        admin::disable,
+        admin::enable,
+        admin::force_password_reset,
+        admin::stats,
+    ),
+    components(schemas(
+        Todo,
+        CreateTodo,
+        UpdateTodo,
+        AssignTodo,
+        TodoAction,
+        TodoActivity,
+        TransferFormat,
+        ExportedTodo,
+        ImportTodos,
+        ImportError,
+        ImportReport,
+        CalendarFeed,
+        AuditEntity,
+        AuditAction,
+        AuditEvent,
+        SecurityEventKind,
+        SecurityEvent,
+        Comment,
+        CreateComment,
+        UpdateComment,
+        Attachment,
+        Upload,
+        StorageUsage,
+        ListPermission,
+        TodoList,
+        CreateList,
+        UpdateList,
+        ListMember,
+        InviteMember,
+        SetPermission,
+        ListInvitation,
+        User,
+        CreateUser,
+        UpdateUser,
+        SignInUser,
+        SignInResponse,
+        TotpCode,
+        TotpEnrollment,
+        RecoveryCodes,
+        PasskeySummary,
+        StartPasskeyRegistration,
+        StartPasskeyLogin,
+        VerifyEmail,
+        RequestPasswordReset,
+        ResetPassword,
+        TokenScope,
+        CreateAccessToken,
+        AccessTokenSummary,
+        CreatedAccessToken,
+        Role,
+        UserAccount,
+        SetRole,
+        AdminStats
+    )),
+    modifiers(&SecuritySchemes, &ErrorResponses),
+    tags(
+        (name = "checks", description = "Server health checks"),
+        (name = "todos", description = "Todos of the session user"),
+        (name = "comments", description = "Discussions of todos"),
+        (
+            name = "attachments",
+            description = "Files attached to todos and the storage quota"
+        ),
+        (
+            name = "audit",
+            description = "Changes of the todos and accounts and the \
+                           security events of the session user"
+        ),
+        (
+            name = "calendar",
+            description = "The iCalendar feed of the todos for calendar apps"
+        ),
+        (name = "lists", description = "Lists shared with other users"),
+        (name = "users", description = "Authentication and user accounts"),
+        (name = "admin", description = "Management of all users"),
+    )
+)]
+pub struct ApiDoc;
+
+/// Registers the session cookie set by the session middleware and the
+/// personal access tokens as security schemes.
+struct SecuritySchemes;
+
+impl Modify for SecuritySchemes {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components =
+            openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "session_cookie",
+            SecurityScheme::ApiKey(ApiKey::Cookie(ApiKeyValue::new("id"))),
+        );
+        components.add_security_scheme(
+            "access_token",
+            SecurityScheme::Http(Http::new(HttpAuthScheme::Bearer)),
+        );
+    }
+}
+
+/// Documents the content type that the messages of the errors are sent
+/// with, the paths declare them as plain text.
+struct ErrorResponses;
+
+impl Modify for ErrorResponses {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let responses = openapi
+            .paths
+            .paths
+            .values_mut()
+            .flat_map(|path| path.operations.values_mut())
+            .flat_map(|operation| operation.responses.responses.iter_mut())
+            .filter(|(status, _)| !status.starts_with('2'));
+        for (_, response) in responses {
+            let RefOr::T(response) = response else {
+                continue;
+            };
+            if let Some(content) = response.content.shift_remove("text/plain") {
+                response
+                    .content
+                    .insert(error::CONTENT_TYPE.to_string(), content);
+            }
+        }
+    }
+}
+
+async fn openapi_json() -> HttpResponse {
+    HttpResponse::Ok().json(ApiDoc::openapi())
+}
+
+pub fn service(cfg: &mut web::ServiceConfig) {
+    cfg.route("/openapi.json", web::get().to(openapi_json))
+        .service(Redoc::with_url("/docs", ApiDoc::openapi()));
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use syn::{Expr, ExprLit, Item, Lit, Stmt};
+
+    use super::*;
+
+    const SPEC_PATH: &str =
+        concat!(env!("CARGO_MANIFEST_DIR"), "/openapi.json");
+    const API_DIR: &str =
+        concat!(env!("CARGO_MANIFEST_DIR"), "/src/controllers/api");
+
+    /// The method and path of a route, like `("get", "/api/v1/todos")`.
+    type Route = (String, String);
+
+    fn literal(expr: &Expr) -> String {
+        match expr {
+            Expr::Lit(ExprLit { lit: Lit::Str(lit), .. }) => lit.value(),
+            _ => panic!("routes are registered with literal paths"),
+        }
+    }
+
+    /// The name of the function a chain of calls starts with, e.g. `get`
+    /// for `web::get().to(handler)`, and its arguments.
+    fn chain_start(mut expr: &Expr) -> Option<(String, Vec<&Expr>)> {
+        while let Expr::MethodCall(call) = expr {
+            expr = &call.receiver;
+        }
+        let Expr::Call(call) = expr else {
+            return None;
+        };
+        let Expr::Path(function) = &*call.func else {
+            return None;
+        };
+        let name = function.path.segments.last()?.ident.to_string();
+
+        Some((name, call.args.iter().collect()))
+    }
+
+    /// The path of the scope or resource a chain of calls starts with, empty
+    /// for `cfg`.
+    fn chain_path(expr: &Expr) -> String {
+        match chain_start(expr) {
+            Some((name, args)) if name == "scope" || name == "resource" => {
+                literal(args[0])
+            }
+            _ => String::new(),
+        }
+    }
+
+    fn collect_routes(expr: &Expr, routes: &mut BTreeSet<Route>) {
+        let Expr::MethodCall(call) = expr else {
+            return;
+        };
+        collect_routes(&call.receiver, routes);
+        let path = format!("/api{}", chain_path(&call.receiver));
+        let args = call.args.iter().collect::<Vec<_>>();
+        match (call.method.to_string().as_str(), args.as_slice()) {
+            ("route", [route, handler]) => {
+                let (method, _) = chain_start(handler).unwrap();
+                routes.insert((method, path + &literal(route)));
+            }
+            // a route of a resource
+            ("route", [handler]) => {
+                let (method, _) = chain_start(handler).unwrap();
+                routes.insert((method, path));
+            }
+            ("service", [service]) => collect_routes(service, routes),
+            _ => {}
+        }
+    }
+
+    /// Returns the routes that the `service` functions of the api modules
+    /// register. Actix cannot list its routes, so they are read from the
+    /// source of the modules.
+    fn registered_routes() -> BTreeSet<Route> {
+        let mut routes = BTreeSet::new();
+        for entry in std::fs::read_dir(API_DIR).unwrap() {
+            let source =
+                std::fs::read_to_string(entry.unwrap().path()).unwrap();
+            for item in syn::parse_file(&source).unwrap().items {
+                let Item::Fn(function) = item else {
+                    continue;
+                };
+                if function.sig.ident != "service" {
+                    continue;
+                }
+                for statement in &function.block.stmts {
+                    if let Stmt::Expr(expr, _) = statement {
+                        collect_routes(expr, &mut routes);
+                    }
+                }
+            }
+        }
+
+        routes
+    }
+
+    /// Fails when a route is registered without being listed in `paths` or
+    /// a listed one is not registered, which the snapshot cannot tell.
+    #[test]
+    fn every_route_is_documented() {
+        let registered = registered_routes()
+            .into_iter()
+            .filter(|(_, path)| path.starts_with("/api/v1/"))
+            .collect::<BTreeSet<_>>();
+        let documented = ApiDoc::openapi()
+            .paths
+            .paths
+            .into_iter()
+            .flat_map(|(path, item)| {
+                item.operations.into_keys().map(move |method| {
+                    let method = serde_json::to_value(method).unwrap();
+                    (method.as_str().unwrap().to_string(), path.clone())
+                })
+            })
+            .collect::<BTreeSet<_>>();
+
+        let undocumented =
+            registered.difference(&documented).collect::<Vec<_>>();
+        assert!(undocumented.is_empty(), "not in `paths`: {undocumented:?}");
+        let unregistered =
+            documented.difference(&registered).collect::<Vec<_>>();
+        assert!(unregistered.is_empty(), "not registered: {unregistered:?}");
+        // the parsing finds the routes at all
+        assert!(registered.contains(&(
+            "put".to_string(),
+            "/api/v1/todos/{todo_id}/assignee".to_string()
+        )));
+    }
+
+    /// Fails as soon as the generated spec differs from the checked in
+    /// `app/openapi.json`. Run with `UPDATE_OPENAPI=1` to regenerate it after
+    /// an intended api change.
+    #[test]
+    fn openapi_spec_is_up_to_date() {
+        let generated = ApiDoc::openapi().to_pretty_json().unwrap() + "\n";
+
+        if std::env::var_os("UPDATE_OPENAPI").is_some() {
+            std::fs::write(SPEC_PATH, &generated).unwrap();
+            return;
+        }
+
+        let checked_in = std::fs::read_to_string(SPEC_PATH).unwrap_or_default();
+        assert!(
+            generated == checked_in,
+            "app/openapi.json is out of date, regenerate it with \
+             `UPDATE_OPENAPI=1 cargo test -p app openapi`"
+        );
+    }
+}
@@ -0,0 +1,306 @@
+use actix_http::StatusCode;
+use actix_web::{
+    web::{self, Json, ServiceConfig},
+    HttpRequest, HttpResponse,
+};
+use shared::models::{
+    audit::SecurityEventKind,
+    user::{PasskeySummary, StartPasskeyLogin, StartPasskeyRegistration},
+};
+use webauthn_rs::{
+    prelude::{
+        CreationChallengeResponse, PublicKeyCredential,
+        RegisterPublicKeyCredential, RequestChallengeResponse,
+    },
+    Webauthn,
+};
+
+use crate::{
+    controllers::common::{request_id::RequestContext, webauthn, AuthUser},
+    repository::{
+        audit::AuditRepository, passkey::PasskeyRepository,
+        user::UserRepository, Backend,
+    },
+    util::{error::Error, error_or::ErrorOr},
+};
+
+pub fn service<B: Backend>(cfg: &mut ServiceConfig) {
+    cfg.service(
+        web::scope("/v1/users/passkeys")
+            .route("", web::get().to(get_all::<B::Passkey>))
+            .route(
+                "/register/start",
+                web::post().to(start_registration::<B::User, B::Passkey>),
+            )
+            .route(
+                "/register/finish",
+                web::post().to(finish_registration::<B::Passkey>),
+            )
+            .route(
+                "/login/start",
+                web::post().to(start_login::<B::User, B::Passkey>),
+            )
+            .route(
+                "/login/finish",
+                web::post().to(finish_login::<B::User, B::Passkey, B::Audit>),
+            )
+            .route("/{passkey_id}", web::delete().to(delete::<B::Passkey>)),
+    );
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/users/passkeys",
+    operation_id = "get_passkeys",
+    tag = "users",
+    responses(
+        (
+            status = 200,
+            description = "The passkeys of the session user",
+            body = [PasskeySummary]
+        ),
+        (status = 401, description = "Not logged in", body = String),
+        (
+            status = 403,
+            description = "Used with an access token",
+            body = String
+        ),
+    ),
+    security(("session_cookie" = []))
+)]
+async fn get_all<P: PasskeyRepository>(
+    repo: web::Data<P>,
+    user: AuthUser,
+) -> ErrorOr<Json<Vec<PasskeySummary>>> {
+    user.require_session()?;
+    let passkeys = repo.get_passkeys(&user.id).await?;
+
+    Json(passkeys.into_iter().map(PasskeySummary::from).collect::<Vec<_>>())
+        .into()
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/users/passkeys/register/start",
+    operation_id = "start_passkey_registration",
+    tag = "users",
+    request_body = StartPasskeyRegistration,
+    responses(
+        (
+            status = 200,
+            description = "The options for `navigator.credentials.create()`",
+            body = Object
+        ),
+        (status = 400, description = "The name is empty", body = String),
+        (status = 401, description = "Not logged in", body = String),
+        (
+            status = 403,
+            description = "Used with an access token",
+            body = String
+        ),
+    ),
+    security(("session_cookie" = []))
+)]
+async fn start_registration<R: UserRepository, P: PasskeyRepository>(
+    request: HttpRequest,
+    start: web::Json<StartPasskeyRegistration>,
+    repo: web::Data<R>,
+    passkey_repo: web::Data<P>,
+    webauthn: web::Data<Webauthn>,
+    user: AuthUser,
+) -> ErrorOr<Json<CreationChallengeResponse>> {
+    user.require_session()?;
+    let name = start.name.trim();
+    if name.is_empty() {
+        Err(Error::External(
+            StatusCode::BAD_REQUEST,
+            "The name of the passkey must not be empty.".into(),
+        ))?;
+    }
+
+    let user = repo.get_session_user(&user.id).await?;
+    webauthn::start_registration(
+        &request,
+        webauthn.get_ref(),
+        passkey_repo.get_ref(),
+        &user,
+        name,
+    )
+    .await
+    .0
+    .map(Json)
+    .into()
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/users/passkeys/register/finish",
+    operation_id = "finish_passkey_registration",
+    tag = "users",
+    request_body(
+        content = Object,
+        description = "The credential of `navigator.credentials.create()`"
+    ),
+    responses(
+        (status = 200, description = "The new passkey", body = PasskeySummary),
+        (
+            status = 400,
+            description = "Invalid credential or no registration to finish",
+            body = String
+        ),
+        (status = 401, description = "Not logged in", body = String),
+        (
+            status = 403,
+            description = "Used with an access token",
+            body = String
+        ),
+    ),
+    security(("session_cookie" = []))
+)]
+async fn finish_registration<P: PasskeyRepository>(
+    request: HttpRequest,
+    credential: web::Json<RegisterPublicKeyCredential>,
+    passkey_repo: web::Data<P>,
+    webauthn: web::Data<Webauthn>,
+    user: AuthUser,
+) -> ErrorOr<Json<PasskeySummary>> {
+    user.require_session()?;
+    let passkey = webauthn::finish_registration(
+        &request,
+        webauthn.get_ref(),
+        passkey_repo.get_ref(),
+        user.id,
+        &credential,
+    )
+    .await?;
+
+    Json(PasskeySummary::from(passkey)).into()
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/users/passkeys/login/start",
+    operation_id = "start_passkey_login",
+    tag = "users",
+    request_body = StartPasskeyLogin,
+    responses(
+        (
+            status = 200,
+            description = "The options for `navigator.credentials.get()`",
+            body = Object
+        ),
+        (
+            status = 401,
+            description = "There is no passkey for the email",
+            body = String
+        ),
+    )
+)]
+async fn start_login<R: UserRepository, P: PasskeyRepository>(
+    request: HttpRequest,
+    start: web::Json<StartPasskeyLogin>,
+    repo: web::Data<R>,
+    passkey_repo: web::Data<P>,
+    webauthn: web::Data<Webauthn>,
+) -> ErrorOr<Json<RequestChallengeResponse>> {
+    // unknown emails get the same answer as those without passkeys
+    let user = repo
+        .get_user_by_email(&start.email)
+        .await
+        .0
+        .map_err(|_| webauthn::no_passkey())?;
+
+    webauthn::start_authentication(
+        &request,
+        webauthn.get_ref(),
+        passkey_repo.get_ref(),
+        user.id,
+    )
+    .await
+    .0
+    .map(Json)
+    .into()
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/users/passkeys/login/finish",
+    operation_id = "finish_passkey_login",
+    tag = "users",
+    request_body(
+        content = Object,
+        description = "The credential of `navigator.credentials.get()`"
+    ),
+    responses(
+        (status = 200, description = "Logged in, the session cookie is set"),
+        (
+            status = 401,
+            description = "Invalid credential or no login to finish",
+            body = String
+        ),
+        (status = 403, description = "The account is disabled", body = String),
+    )
+)]
+async fn finish_login<
+    R: UserRepository,
+    P: PasskeyRepository,
+    E: AuditRepository,
+>(
+    request: HttpRequest,
+    context: RequestContext,
+    credential: web::Json<PublicKeyCredential>,
+    repo: web::Data<R>,
+    passkey_repo: web::Data<P>,
+    events: web::Data<E>,
+    webauthn: web::Data<Webauthn>,
+) -> ErrorOr<HttpResponse> {
+    let user_id = webauthn::login_passkey(
+        &request,
+        webauthn.get_ref(),
+        repo.get_ref(),
+        passkey_repo.get_ref(),
+        &credential,
+    )
+    .await?;
+    let event = context.security_event(
+        SecurityEventKind::LoginSucceeded,
+        Some(user_id),
+        None,
+    );
+    events.record_security_event(&event).await?;
+
+    HttpResponse::Ok().finish().into()
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/users/passkeys/{passkey_id}",
+    operation_id = "delete_passkey",
+    tag = "users",
+    params(("passkey_id" = i64, Path, description = "Id of the passkey")),
+    responses(
+        (status = 200, description = "The passkey was removed"),
+        (status = 401, description = "Not logged in", body = String),
+        (
+            status = 403,
+            description = "Used with an access token",
+            body = String
+        ),
+        (
+            status = 404,
+            description = "The user has no such passkey",
+            body = String
+        ),
+    ),
+    security(("session_cookie" = []))
+)]
+async fn delete<P: PasskeyRepository>(
+    passkey_id: web::Path<i64>,
+    passkey_repo: web::Data<P>,
+    user: AuthUser,
+) -> ErrorOr<HttpResponse> {
+    user.require_session()?;
+    passkey_repo.delete_passkey(&passkey_id, &user.id).await?;
+
+    HttpResponse::Ok().finish().into()
+}
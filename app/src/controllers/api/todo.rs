@@ -1,64 +1,343 @@
+use super::attachment::purge_orphaned_blobs;
 use crate::{
-    controllers::common::AuthUser, repository::todo::TodoRepository,
-    util::error_or::ErrorOr,
+    blob::{self, BlobStore},
+    controllers::common::{request_id::RequestContext, AuthUser},
+    repository::{
+        attachment::AttachmentRepository,
+        error::{Operation, RepositoryError},
+        list::ListRepository,
+        todo::TodoRepository,
+        Backend,
+    },
+    util::{error::Error, error_or::ErrorOr},
 };
+use actix_http::StatusCode;
 use actix_web::{
     web::{self, Json, ServiceConfig},
     HttpResponse,
 };
-use shared::models::todo::{CreateTodo, Todo, UpdateTodo};
+use serde::Deserialize;
+use shared::models::{
+    list::ListPermission,
+    todo::{AssignTodo, CreateTodo, Todo, TodoActivity, UpdateTodo},
+    user::TokenScope,
+};
+use utoipa::IntoParams;
 
-pub fn service<R: TodoRepository>(cfg: &mut ServiceConfig) {
+pub fn service<B: Backend>(cfg: &mut ServiceConfig) {
     cfg.service(
         web::scope("/v1/todos")
-            .route("/{todo_id}", web::get().to(get::<R>))
-            .route("", web::get().to(get_all::<R>))
-            .route("", web::put().to(put::<R>))
-            .route("/{todo_id}", web::delete().to(delete::<R>))
-            .route("", web::post().to(post::<R>)),
+            .route("/{todo_id}", web::get().to(get::<B::Todo>))
+            .route("", web::get().to(get_all::<B::Todo>))
+            .route("", web::put().to(put::<B::Todo>))
+            .route(
+                "/{todo_id}",
+                web::delete().to(delete::<B::Todo, B::Attachment>),
+            )
+            .route("", web::post().to(post::<B::Todo>))
+            .route("/{todo_id}/assignee", web::put().to(assign::<B::Todo>))
+            .route("/{todo_id}/activity", web::get().to(activity::<B::Todo>)),
     );
 }
 
+/// Returns the users who can see the todo, its owner if it is personal and
+/// the members of its list otherwise.
+pub(super) async fn readers<L: ListRepository>(
+    lists: &L,
+    todo: &Todo,
+) -> Result<Vec<i64>, Error> {
+    match todo.list_id {
+        Some(list_id) => Ok(lists
+            .get_members(&list_id)
+            .await?
+            .into_iter()
+            .map(|member| member.user_id)
+            .collect()),
+        None => Ok(vec![todo.owner]),
+    }
+}
+
+/// Whether the user may change the todo, which takes the ownership of a
+/// personal todo and at least the editor permission for a list todo.
+pub(super) async fn may_edit<L: ListRepository>(
+    lists: &L,
+    todo: &Todo,
+    user_id: i64,
+) -> Result<bool, Error> {
+    match todo.list_id {
+        Some(list_id) => {
+            Ok(lists.get_members(&list_id).await?.iter().any(|member| {
+                member.user_id == user_id
+                    && member.permission >= ListPermission::Editor
+            }))
+        }
+        None => Ok(todo.owner == user_id),
+    }
+}
+
+/// Fails unless the user [may edit](may_edit) the todo.
+pub(crate) async fn ensure_editor<L: ListRepository>(
+    lists: &L,
+    todo: &Todo,
+    user_id: i64,
+) -> Result<(), Error> {
+    if !may_edit(lists, todo, user_id).await? {
+        Err(RepositoryError::Forbidden {
+            operation: Operation::Update,
+            relation_name: "Todo".to_string(),
+        })?;
+    }
+
+    Ok(())
+}
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct TodoFilter {
+    /// Only list the todos assigned to the user.
+    #[serde(default)]
+    assigned_to_me: bool,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/todos",
+    operation_id = "get_todos",
+    tag = "todos",
+    params(TodoFilter),
+    responses(
+        (
+            status = 200,
+            description = "The personal todos of the user and the todos of \
+                           their lists",
+            body = [Todo]
+        ),
+        (status = 401, description = "Not logged in", body = String),
+        (
+            status = 403,
+            description = "The access token lacks the scope",
+            body = String
+        ),
+    ),
+    security(("session_cookie" = []), ("access_token" = ["read_todos"]))
+)]
 async fn get_all<R: TodoRepository>(
+    filter: web::Query<TodoFilter>,
     repo: web::Data<R>,
     user: AuthUser,
 ) -> ErrorOr<Json<Vec<Todo>>> {
-    let res = repo.get_todos(&user.id).await?;
+    user.require(TokenScope::ReadTodos)?;
+    let mut res = repo.get_todos(&user.id).await?;
+    if filter.assigned_to_me {
+        res.retain(|todo| todo.assignee_id == Some(user.id));
+    }
     Json(res).into()
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/v1/todos/{todo_id}",
+    operation_id = "get_todo",
+    tag = "todos",
+    params(("todo_id" = i64, Path, description = "Id of the todo")),
+    responses(
+        (status = 200, description = "The requested todo", body = Todo),
+        (status = 401, description = "Not logged in", body = String),
+        (
+            status = 403,
+            description = "Personal todo of another user, todo of a list the \
+                           user is no member of, or the access token lacks \
+                           the scope",
+            body = String
+        ),
+        (status = 404, description = "Todo does not exist", body = String),
+    ),
+    security(("session_cookie" = []), ("access_token" = ["read_todos"]))
+)]
 async fn get<R: TodoRepository>(
     todo_id: web::Path<i64>,
     repo: web::Data<R>,
     user: AuthUser,
 ) -> ErrorOr<Json<Todo>> {
+    user.require(TokenScope::ReadTodos)?;
     let todo = repo.get_todo(&todo_id, &user.id).await?;
     Json(todo).into()
 }
 
+#[utoipa::path(
+    post,
+    path = "/api/v1/todos",
+    operation_id = "create_todo",
+    tag = "todos",
+    request_body = CreateTodo,
+    responses(
+        (status = 200, description = "Todo was created"),
+        (status = 401, description = "Not logged in", body = String),
+        (
+            status = 403,
+            description = "Not an editor of the list or the access token \
+                           lacks the scope",
+            body = String
+        ),
+    ),
+    security(("session_cookie" = []), ("access_token" = ["write_todos"]))
+)]
 async fn post<R: TodoRepository>(
     repo: web::Data<R>,
     create_todo: web::Json<CreateTodo>,
     user: AuthUser,
+    context: RequestContext,
 ) -> ErrorOr<HttpResponse> {
-    repo.create_todo(&create_todo, &user.id).await?;
+    user.require(TokenScope::WriteTodos)?;
+    repo.create_todo(&create_todo, &user.id, &context.audit(user.id)).await?;
     HttpResponse::Ok().finish().into()
 }
 
+#[utoipa::path(
+    put,
+    path = "/api/v1/todos",
+    operation_id = "update_todo",
+    tag = "todos",
+    request_body = UpdateTodo,
+    responses(
+        (status = 200, description = "Todo was updated"),
+        (status = 401, description = "Not logged in", body = String),
+        (
+            status = 403,
+            description = "Personal todo of another user, todo of a list the \
+                           user may not edit, or the access token lacks the \
+                           scope",
+            body = String
+        ),
+    ),
+    security(("session_cookie" = []), ("access_token" = ["write_todos"]))
+)]
 async fn put<R: TodoRepository>(
     repo: web::Data<R>,
     update_todo: web::Json<UpdateTodo>,
     user: AuthUser,
+    context: RequestContext,
 ) -> ErrorOr<HttpResponse> {
-    repo.update_todo(&update_todo, &user.id).await?;
+    user.require(TokenScope::WriteTodos)?;
+    repo.update_todo(&update_todo, &user.id, &context.audit(user.id)).await?;
     HttpResponse::Ok().finish().into()
 }
 
-async fn delete<R: TodoRepository>(
+#[utoipa::path(
+    delete,
+    path = "/api/v1/todos/{todo_id}",
+    operation_id = "delete_todo",
+    tag = "todos",
+    params(("todo_id" = i64, Path, description = "Id of the todo")),
+    responses(
+        (status = 200, description = "Todo was deleted"),
+        (status = 401, description = "Not logged in", body = String),
+    ),
+    security(("session_cookie" = []), ("access_token" = ["write_todos"]))
+)]
+async fn delete<R: TodoRepository, A: AttachmentRepository>(
     todo_id: web::Path<i64>,
     repo: web::Data<R>,
+    attachments: web::Data<A>,
+    store: web::Data<dyn BlobStore>,
     user: AuthUser,
+    context: RequestContext,
 ) -> ErrorOr<HttpResponse> {
-    repo.delete_todo(&todo_id, &user.id).await?;
+    user.require(TokenScope::WriteTodos)?;
+    repo.delete_todo(&todo_id, &user.id, &context.audit(user.id)).await?;
+    blob::delete_in_background(purge_orphaned_blobs(attachments, store));
     HttpResponse::Ok().finish().into()
 }
+
+#[utoipa::path(
+    put,
+    path = "/api/v1/todos/{todo_id}/assignee",
+    operation_id = "assign_todo",
+    tag = "todos",
+    params(("todo_id" = i64, Path, description = "Id of the todo")),
+    request_body = AssignTodo,
+    responses(
+        (status = 200, description = "The assigned todo", body = Todo),
+        (
+            status = 400,
+            description = "The assignee cannot see the todo",
+            body = String
+        ),
+        (status = 401, description = "Not logged in", body = String),
+        (
+            status = 403,
+            description = "Personal todo of another user, todo of a list the \
+                           user may not edit, or the access token lacks the \
+                           scope",
+            body = String
+        ),
+        (status = 404, description = "Todo does not exist", body = String),
+    ),
+    security(("session_cookie" = []), ("access_token" = ["write_todos"]))
+)]
+async fn assign<R: TodoRepository>(
+    todo_id: web::Path<i64>,
+    assign_todo: web::Json<AssignTodo>,
+    repo: web::Data<R>,
+    user: AuthUser,
+    context: RequestContext,
+) -> ErrorOr<Json<Todo>> {
+    user.require(TokenScope::WriteTodos)?;
+    let todo = repo.get_todo(&todo_id, &user.id).await?;
+
+    // the repository checks the assignee along with the write, so a member
+    // who leaves the list meanwhile is not assigned
+    let todo = match repo
+        .assign_todo(
+            &todo.id,
+            assign_todo.assignee_id,
+            &user.id,
+            &context.audit(user.id),
+        )
+        .await
+        .0
+    {
+        Ok(todo) => todo,
+        Err(Error::External(StatusCode::CONFLICT, _)) => Err(Error::External(
+            StatusCode::BAD_REQUEST,
+            "The assignee has no access to the todo.".into(),
+        ))?,
+        Err(error) => Err(error)?,
+    };
+    Json(todo).into()
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/todos/{todo_id}/activity",
+    operation_id = "get_todo_activity",
+    tag = "todos",
+    params(("todo_id" = i64, Path, description = "Id of the todo")),
+    responses(
+        (
+            status = 200,
+            description = "What happened to the todo, oldest first",
+            body = [TodoActivity]
+        ),
+        (status = 401, description = "Not logged in", body = String),
+        (
+            status = 403,
+            description = "Personal todo of another user, todo of a list the \
+                           user is no member of, or the access token lacks \
+                           the scope",
+            body = String
+        ),
+        (status = 404, description = "Todo does not exist", body = String),
+    ),
+    security(("session_cookie" = []), ("access_token" = ["read_todos"]))
+)]
+async fn activity<R: TodoRepository>(
+    todo_id: web::Path<i64>,
+    repo: web::Data<R>,
+    user: AuthUser,
+) -> ErrorOr<Json<Vec<TodoActivity>>> {
+    user.require(TokenScope::ReadTodos)?;
+    let todo = repo.get_todo(&todo_id, &user.id).await?;
+    let activities = repo.get_activities(&todo.id).await?;
+    Json(activities).into()
+}
@@ -0,0 +1,187 @@
+use actix_http::StatusCode;
+use actix_web::{
+    web::{self, Json, ServiceConfig},
+    HttpResponse,
+};
+use chrono::Utc;
+use shared::models::user::{RecoveryCodes, TotpCode, TotpEnrollment};
+
+use crate::{
+    controllers::common::{totp, AuthUser},
+    repository::{totp::TotpRepository, user::UserRepository, Backend},
+    util::{error::Error, error_or::ErrorOr},
+};
+
+pub fn service<B: Backend>(cfg: &mut ServiceConfig) {
+    cfg.service(
+        web::scope("/v1/users/totp")
+            .route("", web::post().to(enroll::<B::User, B::Totp>))
+            .route("/confirm", web::post().to(confirm::<B::Totp>))
+            .route("", web::delete().to(disable::<B::Totp>)),
+    );
+}
+
+fn invalid_code() -> Error {
+    Error::External(
+        StatusCode::BAD_REQUEST,
+        "Invalid code provided. Try again.".into(),
+    )
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/users/totp",
+    operation_id = "enroll_totp",
+    tag = "users",
+    responses(
+        (
+            status = 200,
+            description = "A new secret to add to an authenticator app, it is \
+                           enabled once confirmed with a first code",
+            body = TotpEnrollment
+        ),
+        (status = 401, description = "Not logged in", body = String),
+        (
+            status = 403,
+            description = "Used with an access token",
+            body = String
+        ),
+        (
+            status = 409,
+            description = "Two-factor authentication is already enabled",
+            body = String
+        ),
+    ),
+    security(("session_cookie" = []))
+)]
+async fn enroll<R: UserRepository, T: TotpRepository>(
+    repo: web::Data<R>,
+    totp_repo: web::Data<T>,
+    user: AuthUser,
+) -> ErrorOr<Json<TotpEnrollment>> {
+    user.require_session()?;
+    let existing = totp_repo.get_totp(&user.id).await?;
+    if existing.is_some_and(|totp| totp.confirmed_at.is_some()) {
+        // replacing the secret must not skip the code of the old one
+        Err(Error::External(
+            StatusCode::CONFLICT,
+            "Two-factor authentication is already enabled. Disable it first."
+                .into(),
+        ))?;
+    }
+
+    let user = repo.get_session_user(&user.id).await?;
+    let secret = totp::generate_secret();
+    totp_repo.set_pending_totp(&user.id, &secret).await?;
+
+    let otpauth_uri = totp::otpauth_uri(&user.email, &secret);
+    let qr_code_svg = totp::qr_code_svg(&otpauth_uri)?;
+
+    Json(TotpEnrollment { secret, otpauth_uri, qr_code_svg }).into()
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/users/totp/confirm",
+    operation_id = "confirm_totp",
+    tag = "users",
+    request_body = TotpCode,
+    responses(
+        (
+            status = 200,
+            description = "Two-factor authentication is enabled, the recovery \
+                           codes are only shown this once",
+            body = RecoveryCodes
+        ),
+        (
+            status = 400,
+            description = "Invalid code or no enrollment to confirm",
+            body = String
+        ),
+        (status = 401, description = "Not logged in", body = String),
+        (
+            status = 403,
+            description = "Used with an access token",
+            body = String
+        ),
+    ),
+    security(("session_cookie" = []))
+)]
+async fn confirm<T: TotpRepository>(
+    totp_code: web::Json<TotpCode>,
+    totp_repo: web::Data<T>,
+    user: AuthUser,
+) -> ErrorOr<Json<RecoveryCodes>> {
+    user.require_session()?;
+    let pending = totp_repo
+        .get_totp(&user.id)
+        .await?
+        .filter(|totp| totp.confirmed_at.is_none())
+        .ok_or_else(|| {
+            Error::External(
+                StatusCode::BAD_REQUEST,
+                "There is no enrollment to confirm. Start a new one.".into(),
+            )
+        })?;
+
+    let step = totp::verify_code(&pending.secret, &totp_code.code, Utc::now())
+        .ok_or_else(invalid_code)?;
+    let (codes, code_hashes) = totp::generate_recovery_codes();
+    totp_repo.confirm_totp(&user.id, step, &code_hashes).await?;
+
+    Json(RecoveryCodes { codes }).into()
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/users/totp",
+    operation_id = "disable_totp",
+    tag = "users",
+    request_body = TotpCode,
+    responses(
+        (
+            status = 200,
+            description = "Two-factor authentication and the recovery codes \
+                           were removed"
+        ),
+        (
+            status = 400,
+            description = "Invalid code or two-factor authentication is not \
+                           enabled",
+            body = String
+        ),
+        (status = 401, description = "Not logged in", body = String),
+        (
+            status = 403,
+            description = "Used with an access token",
+            body = String
+        ),
+    ),
+    security(("session_cookie" = []))
+)]
+async fn disable<T: TotpRepository>(
+    totp_code: web::Json<TotpCode>,
+    totp_repo: web::Data<T>,
+    user: AuthUser,
+) -> ErrorOr<HttpResponse> {
+    user.require_session()?;
+    // a stolen session alone must not turn off the second factor
+    let valid = match totp_repo.get_totp(&user.id).await? {
+        Some(existing) => {
+            totp::verify_second_factor(
+                totp_repo.get_ref(),
+                &existing,
+                &totp_code.code,
+            )
+            .await?
+        }
+        None => false,
+    };
+    if !valid {
+        Err(invalid_code())?;
+    }
+
+    totp_repo.delete_totp(&user.id).await?;
+
+    HttpResponse::Ok().finish().into()
+}
@@ -0,0 +1,568 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use actix_http::StatusCode;
+use actix_web::{
+    http::header::{ContentDisposition, DispositionParam, DispositionType},
+    web::{self, Json, ServiceConfig},
+    HttpResponse,
+};
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
+use serde::Deserialize;
+use shared::models::{
+    list::ListPermission,
+    todo::{CreateTodo, Todo, UpdateTodo},
+    transfer::{
+        ExportedTodo, ImportError, ImportReport, ImportTodos, TransferFormat,
+        EXTERNAL_ID_PREFIX, IMPORTED_FIELDS,
+    },
+    user::TokenScope,
+};
+use utoipa::IntoParams;
+
+use super::{list, todo::may_edit};
+use crate::{
+    controllers::common::{request_id::RequestContext, AuthUser},
+    ical::{self, VTodo},
+    repository::{
+        list::ListRepository,
+        todo::{CreateImportedTodo, TodoRepository},
+        Backend,
+    },
+    util::{error::Error, error_or::ErrorOr},
+};
+
+/// Rows of a single import.
+const MAX_ROWS: usize = 1000;
+
+/// Registers paths within the todos scope, so it has to be configured before
+/// it.
+pub fn service<B: Backend>(cfg: &mut ServiceConfig) {
+    cfg.route("/v1/todos/export", web::get().to(export::<B::Todo, B::List>))
+        .route("/v1/todos/import", web::post().to(import::<B::Todo, B::List>));
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct ExportQuery {
+    /// `csv` by default.
+    #[serde(default)]
+    #[param(inline)]
+    format: TransferFormat,
+}
+
+/// The cells of a row by the names of their columns.
+type Record = HashMap<String, String>;
+
+/// The names of all columns and the rows of a file, which fail on their own
+/// if they are malformed.
+type Records = (HashSet<String>, Vec<Result<Record, String>>);
+
+/// A row of an imported file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ImportedTodo {
+    external_id: Option<String>,
+    title: String,
+    description: String,
+    is_done: bool,
+    due_at: Option<DateTime<Utc>>,
+}
+
+/// What the import does with a row.
+enum Plan {
+    Create(ImportedTodo),
+    Update(i64, ImportedTodo),
+    Unchanged,
+}
+
+fn bad_request(message: String) -> Error {
+    Error::External(StatusCode::BAD_REQUEST, message.into())
+}
+
+fn read_records(
+    format: TransferFormat,
+    content: &str,
+) -> Result<Records, Error> {
+    match format {
+        TransferFormat::Csv => {
+            let mut reader = csv::Reader::from_reader(content.as_bytes());
+            let headers = reader
+                .headers()
+                .map_err(|e| bad_request(format!("Invalid CSV header: {e}")))?
+                .iter()
+                .map(|header| header.trim().to_string())
+                .collect::<Vec<_>>();
+            let records = reader
+                .records()
+                .map(|record| match record {
+                    Ok(record) => Ok(headers
+                        .iter()
+                        .cloned()
+                        .zip(record.iter().map(ToString::to_string))
+                        .collect()),
+                    Err(e) => Err(format!("Invalid CSV: {e}")),
+                })
+                .collect();
+
+            Ok((headers.into_iter().collect(), records))
+        }
+        TransferFormat::Json => {
+            let rows: Vec<serde_json::Value> = serde_json::from_str(content)
+                .map_err(|e| {
+                    bad_request(format!("The file is no JSON array: {e}"))
+                })?;
+            let records = rows
+                .into_iter()
+                .map(|row| match row {
+                    serde_json::Value::Object(fields) => fields
+                        .into_iter()
+                        .map(|(name, value)| Ok((name, cell(value)?)))
+                        .collect(),
+                    _ => Err("The row is no JSON object.".to_string()),
+                })
+                .collect::<Vec<Result<Record, String>>>();
+            let columns = records
+                .iter()
+                .flatten()
+                .flat_map(|record| record.keys().cloned())
+                .collect();
+
+            Ok((columns, records))
+        }
+        TransferFormat::Ical => {
+            let records = ical::parse(content)
+                .map_err(bad_request)?
+                .into_iter()
+                .map(|vtodo| {
+                    let vtodo = vtodo?;
+                    let fields = [
+                        vtodo.uid.unwrap_or_default(),
+                        vtodo.summary,
+                        vtodo.description,
+                        vtodo.is_done.to_string(),
+                        vtodo
+                            .due_at
+                            .map(|due_at| due_at.to_rfc3339())
+                            .unwrap_or_default(),
+                    ];
+                    Ok(IMPORTED_FIELDS
+                        .iter()
+                        .map(ToString::to_string)
+                        .zip(fields)
+                        .collect())
+                })
+                .collect();
+            let columns =
+                IMPORTED_FIELDS.iter().map(ToString::to_string).collect();
+
+            Ok((columns, records))
+        }
+    }
+}
+
+/// Reads a field of a JSON object like a cell of a CSV file.
+fn cell(value: serde_json::Value) -> Result<String, String> {
+    match value {
+        serde_json::Value::Null => Ok(String::new()),
+        serde_json::Value::String(value) => Ok(value),
+        serde_json::Value::Bool(value) => Ok(value.to_string()),
+        serde_json::Value::Number(value) => Ok(value.to_string()),
+        _ => Err("Fields must not be arrays or objects.".to_string()),
+    }
+}
+
+fn parse_row(
+    record: &Record,
+    columns: &BTreeMap<String, String>,
+) -> Result<ImportedTodo, String> {
+    let field = |name: &str| {
+        let column = columns.get(name).map(String::as_str).unwrap_or(name);
+        record.get(column).map(|value| value.trim()).unwrap_or_default()
+    };
+
+    let title = field("title");
+    if title.is_empty() {
+        Err("The title must not be empty.".to_string())?;
+    }
+    let is_done = match field("is_done").to_lowercase().as_str() {
+        "" | "false" | "no" | "0" => false,
+        "true" | "yes" | "1" | "x" => true,
+        value => Err(format!("`{value}` is neither true nor false."))?,
+    };
+    let due_at = match field("due_at") {
+        "" => None,
+        value => Some(parse_date(value).ok_or_else(|| {
+            format!(
+                "`{value}` is no date, use e.g. 2024-01-31 or \
+                 2024-01-31T12:00:00Z."
+            )
+        })?),
+    };
+
+    Ok(ImportedTodo {
+        external_id: Some(field("external_id"))
+            .filter(|id| !id.is_empty())
+            .map(ToString::to_string),
+        title: title.to_string(),
+        description: field("description").to_string(),
+        is_done,
+        due_at,
+    })
+}
+
+/// Reads an RFC 3339 timestamp or a date, which is taken as its midnight in
+/// UTC.
+fn parse_date(value: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|at| at.with_timezone(&Utc))
+        .or_else(|_| {
+            NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                .map(|date| date.and_time(NaiveTime::MIN).and_utc())
+        })
+        .ok()
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/todos/export",
+    operation_id = "export_todos",
+    tag = "todos",
+    params(ExportQuery),
+    responses(
+        (
+            status = 200,
+            description = "The personal todos of the user and the todos of \
+                           their lists as a file, JSON as an array of \
+                           ExportedTodo",
+            content_type = "text/csv",
+            body = String
+        ),
+        (status = 401, description = "Not logged in", body = String),
+        (
+            status = 403,
+            description = "The access token lacks the scope",
+            body = String
+        ),
+    ),
+    security(("session_cookie" = []), ("access_token" = ["read_todos"]))
+)]
+async fn export<R: TodoRepository, L: ListRepository>(
+    query: web::Query<ExportQuery>,
+    repo: web::Data<R>,
+    lists: web::Data<L>,
+    user: AuthUser,
+) -> ErrorOr<HttpResponse> {
+    user.require(TokenScope::ReadTodos)?;
+    let todos = exported_todos(repo.get_ref(), &user.id).await?;
+    let exported = || {
+        todos.iter().cloned().map(|(external_id, todo)| ExportedTodo {
+            external_id,
+            title: todo.title,
+            description: todo.description,
+            is_done: todo.is_done,
+            due_at: todo.due_at,
+            completed_at: todo.completed_at,
+            list_id: todo.list_id,
+            created_at: todo.created_at,
+            updated_at: todo.updated_at,
+        })
+    };
+
+    let format = query.format;
+    let body = match format {
+        TransferFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(Vec::new());
+            for todo in exported() {
+                writer
+                    .serialize(todo)
+                    .map_err(|e| Error::Internal(e.into()))?;
+            }
+            writer.into_inner().map_err(|e| Error::Internal(e.into()))?
+        }
+        TransferFormat::Json => {
+            serde_json::to_vec_pretty(&exported().collect::<Vec<_>>())
+                .map_err(|e| Error::Internal(e.into()))?
+        }
+        TransferFormat::Ical => {
+            calendar(lists.get_ref(), &todos, &user.id).await?.into_bytes()
+        }
+    };
+
+    HttpResponse::Ok()
+        .content_type(format.content_type())
+        .insert_header(ContentDisposition {
+            disposition: DispositionType::Attachment,
+            parameters: vec![DispositionParam::Filename(format!(
+                "lentos-todos.{}",
+                format.file_extension()
+            ))],
+        })
+        .body(body)
+        .into()
+}
+
+/// Returns the personal todos of the user and the todos of their lists with
+/// the external ids they are exported under.
+pub(crate) async fn exported_todos<R: TodoRepository>(
+    repo: &R,
+    user_id: &i64,
+) -> Result<Vec<(String, Todo)>, Error> {
+    let mut external_ids = HashMap::new();
+    let mut imported = repo.get_external_ids(user_id).await?;
+    // the first one wins if a todo was imported under several ids
+    imported.sort_by(|a, b| b.external_id.cmp(&a.external_id));
+    for imported in imported {
+        external_ids.insert(imported.todo_id, imported.external_id);
+    }
+
+    let todos = repo
+        .get_todos(user_id)
+        .await?
+        .into_iter()
+        .map(|todo| {
+            let external_id = external_ids
+                .remove(&todo.id)
+                .unwrap_or_else(|| format!("{EXTERNAL_ID_PREFIX}{}", todo.id));
+            (external_id, todo)
+        })
+        .collect();
+
+    Ok(todos)
+}
+
+/// Returns the names of the lists of the user by their id.
+pub(crate) async fn list_names<L: ListRepository>(
+    lists: &L,
+    user_id: &i64,
+) -> Result<HashMap<i64, String>, Error> {
+    let names = lists
+        .get_lists(user_id)
+        .await?
+        .into_iter()
+        .map(|list| (list.id, list.name))
+        .collect();
+
+    Ok(names)
+}
+
+/// Returns a todo as VTODO component, the todos of a list are in the
+/// category of its name.
+pub(crate) fn vtodo<'a>(
+    external_id: &'a str,
+    todo: &'a Todo,
+    list_names: &'a HashMap<i64, String>,
+) -> VTodo<'a> {
+    VTodo {
+        uid: external_id,
+        todo,
+        category: todo
+            .list_id
+            .and_then(|list_id| list_names.get(&list_id))
+            .map(String::as_str),
+    }
+}
+
+/// Serializes todos to an iCalendar file.
+pub(super) async fn calendar<L: ListRepository>(
+    lists: &L,
+    todos: &[(String, Todo)],
+    user_id: &i64,
+) -> Result<String, Error> {
+    let names = list_names(lists, user_id).await?;
+
+    Ok(ical::calendar(
+        todos
+            .iter()
+            .map(|(external_id, todo)| vtodo(external_id, todo, &names)),
+    ))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/todos/import",
+    operation_id = "import_todos",
+    tag = "todos",
+    request_body = ImportTodos,
+    responses(
+        (
+            status = 200,
+            description = "What the import did, nothing for a dry run or if a \
+                           row has an error",
+            body = ImportReport
+        ),
+        (
+            status = 400,
+            description = "The file cannot be read, has too many rows or no \
+                           title column, or a column is mapped to an unknown \
+                           field",
+            body = String
+        ),
+        (status = 401, description = "Not logged in", body = String),
+        (
+            status = 403,
+            description = "The user may not edit the list or the access token \
+                           lacks the scope",
+            body = String
+        ),
+        (status = 404, description = "List does not exist", body = String),
+    ),
+    security(("session_cookie" = []), ("access_token" = ["write_todos"]))
+)]
+async fn import<R: TodoRepository, L: ListRepository>(
+    import: web::Json<ImportTodos>,
+    repo: web::Data<R>,
+    lists: web::Data<L>,
+    user: AuthUser,
+    context: RequestContext,
+) -> ErrorOr<Json<ImportReport>> {
+    user.require(TokenScope::WriteTodos)?;
+    if let Some(field) = import
+        .columns
+        .keys()
+        .find(|field| !IMPORTED_FIELDS.contains(&field.as_str()))
+    {
+        Err(bad_request(format!(
+            "`{field}` is not a field of a todo, map {} instead.",
+            IMPORTED_FIELDS.join(", ")
+        )))?;
+    }
+    if let Some(list_id) = import.list_id {
+        let list = lists.get_list(&list_id, &user.id).await?;
+        list::require(&list, ListPermission::Editor)?;
+    }
+
+    let (columns, records) = read_records(import.format, &import.content)?;
+    // the fields of iCalendar files are always read from the same properties
+    let mapping = match import.format {
+        TransferFormat::Ical => BTreeMap::new(),
+        _ => import.columns.clone(),
+    };
+    let title = mapping.get("title").map_or("title", String::as_str);
+    if !columns.contains(title) {
+        Err(bad_request(format!("The file has no `{title}` column.")))?;
+    }
+    if records.len() > MAX_ROWS {
+        Err(bad_request(format!(
+            "A file must not have more than {MAX_ROWS} rows."
+        )))?;
+    }
+
+    let todos = repo
+        .get_todos(&user.id)
+        .await?
+        .into_iter()
+        .map(|todo| (todo.id, todo))
+        .collect::<HashMap<i64, Todo>>();
+    let imported = repo
+        .get_external_ids(&user.id)
+        .await?
+        .into_iter()
+        .map(|imported| (imported.external_id, imported.todo_id))
+        .collect::<HashMap<_, _>>();
+
+    let mut report = ImportReport::default();
+    let mut plans = Vec::new();
+    let mut rows_by_external_id = HashMap::new();
+    let mut rows_by_todo = HashMap::new();
+    for (index, record) in records.into_iter().enumerate() {
+        let row = index + 1;
+        let todo = match record.and_then(|record| parse_row(&record, &mapping))
+        {
+            Ok(todo) => todo,
+            Err(message) => {
+                report.errors.push(ImportError { row, message });
+                continue;
+            }
+        };
+
+        let existing = todo.external_id.as_ref().and_then(|external_id| {
+            let todo_id = imported.get(external_id).copied().or_else(|| {
+                external_id.strip_prefix(EXTERNAL_ID_PREFIX)?.parse().ok()
+            })?;
+            todos.get(&todo_id)
+        });
+        let duplicate = match (&todo.external_id, existing) {
+            (_, Some(existing)) => rows_by_todo.insert(existing.id, row),
+            (Some(external_id), None) => {
+                rows_by_external_id.insert(external_id.clone(), row)
+            }
+            (None, None) => None,
+        };
+        if let Some(first) = duplicate {
+            report.errors.push(ImportError {
+                row,
+                message: format!("Row {first} imports the same todo."),
+            });
+            continue;
+        }
+
+        let plan = match existing {
+            None => Plan::Create(todo),
+            Some(existing)
+                if (
+                    &existing.title,
+                    &existing.description,
+                    existing.is_done,
+                    existing.due_at,
+                ) == (
+                    &todo.title,
+                    &todo.description,
+                    todo.is_done,
+                    todo.due_at,
+                ) =>
+            {
+                Plan::Unchanged
+            }
+            Some(existing) => {
+                if !may_edit(lists.get_ref(), existing, user.id).await? {
+                    report.errors.push(ImportError {
+                        row,
+                        message: "You may not edit the todo of this row."
+                            .to_string(),
+                    });
+                    continue;
+                }
+                Plan::Update(existing.id, todo)
+            }
+        };
+        match plan {
+            Plan::Create(_) => report.created += 1,
+            Plan::Update(..) => report.updated += 1,
+            Plan::Unchanged => report.unchanged += 1,
+        }
+        plans.push(plan);
+    }
+    if import.dry_run || !report.errors.is_empty() {
+        return Json(report).into();
+    }
+
+    let mut create = Vec::new();
+    let mut update = Vec::new();
+    for plan in plans {
+        match plan {
+            Plan::Create(todo) => create.push(CreateImportedTodo {
+                create_todo: CreateTodo {
+                    title: todo.title,
+                    description: todo.description,
+                    list_id: import.list_id,
+                    due_at: todo.due_at,
+                },
+                is_done: todo.is_done,
+                external_id: todo.external_id,
+            }),
+            Plan::Update(todo_id, todo) => update.push(UpdateTodo {
+                id: todo_id,
+                title: Some(todo.title),
+                description: Some(todo.description),
+                is_done: Some(todo.is_done),
+                due_at: Some(todo.due_at),
+            }),
+            Plan::Unchanged => {}
+        }
+    }
+    // all rows or none, a failing one must not leave the others half imported
+    repo.import_todos(&create, &update, &user.id, &context.audit(user.id))
+        .await?;
+    report.applied = true;
+
+    Json(report).into()
+}
@@ -1,15 +1,43 @@
 use actix_http::StatusCode;
+use actix_identity::Identity;
+use actix_session::SessionExt;
 
 use actix_web::{
     web::{self, Json, ServiceConfig},
-    HttpRequest, HttpResponse,
+    HttpRequest, HttpResponse, ResponseError,
 };
 
-use shared::models::user::{CreateUser, SignInUser, UpdateUser, User};
+use chrono::Utc;
+use shared::models::{
+    audit::SecurityEventKind,
+    user::{
+        CreateUser, RequestPasswordReset, ResetPassword, SignInResponse,
+        SignInUser, TokenScope, TotpCode, UpdateUser, User, UserAccount,
+        VerifyEmail,
+    },
+};
 
+use super::attachment::purge_orphaned_blobs;
 use crate::{
-    controllers::common::{self, AuthUser},
-    repository::user::UserRepository,
+    blob::{self, BlobStore},
+    controllers::common::{
+        self,
+        login_throttle::{self, LoginThrottle},
+        request_id::RequestContext,
+        token, totp, AuthUser, LoginStep,
+    },
+    mail::{self, Mail, Mailer},
+    repository::{
+        access_token::AccessTokenRepository,
+        attachment::AttachmentRepository,
+        audit::AuditRepository,
+        login_attempt::LoginAttemptRepository,
+        session::SessionRepository,
+        totp::TotpRepository,
+        user::UserRepository,
+        user_token::{TokenPurpose, UserTokenRepository},
+        Backend,
+    },
     util::{error::Error, error_or::ErrorOr},
 };
 
@@ -18,79 +46,739 @@ use crate::{
 pub enum UserError {
     #[display(fmt = "Invalid email or password provided. Try again.")]
     InvalidEmailOrPassword,
+    #[display(fmt = "Invalid code provided. Try again.")]
+    InvalidCode,
+    #[display(fmt = "The current password is wrong. Try again.")]
+    WrongCurrentPassword,
+    #[display(fmt = "This account is disabled. Contact an administrator.")]
+    AccountDisabled,
 }
 
 impl From<UserError> for Error {
     fn from(error: UserError) -> Self {
         match error {
-            UserError::InvalidEmailOrPassword => Error::External(
-                StatusCode::UNAUTHORIZED,
-                error.to_string().into(),
-            ),
+            UserError::InvalidEmailOrPassword | UserError::InvalidCode => {
+                Error::External(
+                    StatusCode::UNAUTHORIZED,
+                    error.to_string().into(),
+                )
+            }
+            UserError::AccountDisabled | UserError::WrongCurrentPassword => {
+                Error::External(StatusCode::FORBIDDEN, error.to_string().into())
+            }
         }
     }
 }
 
-pub fn service<R: UserRepository>(cfg: &mut ServiceConfig) {
+pub fn service<B: Backend>(cfg: &mut ServiceConfig) {
     cfg.service(
         web::scope("/v1/users")
-            .route("/login", web::post().to(login::<R>))
-            .route("/register", web::post().to(register::<R>))
-            .route("", web::get().to(get::<R>))
-            .route("", web::put().to(put::<R>))
-            .route("", web::delete().to(delete::<R>)),
+            .route(
+                "/login",
+                web::post().to(login::<
+                    B::User,
+                    B::Totp,
+                    B::LoginAttempt,
+                    B::Audit,
+                >),
+            )
+            .route(
+                "/login/totp",
+                web::post().to(login_totp::<
+                    B::User,
+                    B::Totp,
+                    B::LoginAttempt,
+                    B::Audit,
+                >),
+            )
+            .route("/logout", web::post().to(logout::<B::Audit>))
+            .route(
+                "/register",
+                web::post().to(register::<B::User, B::UserToken>),
+            )
+            .route(
+                "/verify-email/request",
+                web::post()
+                    .to(request_email_verification::<B::User, B::UserToken>),
+            )
+            .route(
+                "/verify-email",
+                web::post().to(verify_email::<B::User, B::UserToken>),
+            )
+            .route(
+                "/password-reset/request",
+                web::post().to(request_password_reset::<B::User, B::UserToken>),
+            )
+            .route(
+                "/password-reset",
+                web::post().to(reset_password::<
+                    B::User,
+                    B::UserToken,
+                    B::Session,
+                    B::AccessToken,
+                    B::Audit,
+                >),
+            )
+            .route("", web::get().to(get::<B::User>))
+            .route(
+                "",
+                web::put().to(put::<
+                    B::User,
+                    B::Session,
+                    B::AccessToken,
+                    B::Audit,
+                >),
+            )
+            .route("", web::delete().to(delete::<B::User, B::Attachment>)),
     );
 }
 
-async fn login<R: UserRepository>(
+#[utoipa::path(
+    post,
+    path = "/api/v1/users/login",
+    operation_id = "login",
+    tag = "users",
+    request_body = SignInUser,
+    responses(
+        (
+            status = 200,
+            description = "Logged in and the session cookie is set, or the \
+                           login waits for a code of the second factor",
+            body = SignInResponse
+        ),
+        (status = 401, description = "Invalid credentials", body = String),
+        (status = 403, description = "The account is disabled", body = String),
+        (
+            status = 429,
+            description = "Too many failed logins for this email or ip, \
+                           retry after the seconds in `Retry-After`",
+            body = String
+        ),
+    )
+)]
+#[allow(clippy::too_many_arguments)]
+async fn login<
+    R: UserRepository,
+    T: TotpRepository,
+    A: LoginAttemptRepository,
+    E: AuditRepository,
+>(
     request: HttpRequest,
+    context: RequestContext,
     login_user: web::Json<SignInUser>,
     repo: web::Data<R>,
-) -> ErrorOr<HttpResponse> {
-    let user = repo
+    totp_repo: web::Data<T>,
+    attempts: web::Data<A>,
+    events: web::Data<E>,
+    throttle: web::Data<LoginThrottle>,
+) -> ErrorOr<Json<SignInResponse>> {
+    let keys = login_throttle::login_keys(&request, &login_user.email);
+    throttle.reserve(attempts.get_ref(), &keys).await?;
+
+    let (user_id, logged_in) = match repo
         .get_user_by_email(&login_user.email)
         .await
         .0
-        .map_err(|_| UserError::InvalidEmailOrPassword)?;
+    {
+        Ok(user) => (
+            Some(user.id),
+            common::login(&request, totp_repo.get_ref(), &user, &login_user)
+                .await
+                .0,
+        ),
+        Err(_) => {
+            // as slow as a wrong password, the timing must not reveal
+            // which emails are registered
+            common::verify_dummy_password(&login_user.password);
+            (None, Err(UserError::InvalidEmailOrPassword.into()))
+        }
+    };
 
-    common::login(&request, &user, &login_user).await?;
+    let step = match logged_in {
+        Ok(step) => {
+            attempts.clear_failed_logins(&keys[0]).await?;
+            throttle.release(attempts.get_ref(), &keys[1..]).await?;
+            // a login waiting for the second factor is not complete yet
+            if step == LoginStep::LoggedIn {
+                let event = context.security_event(
+                    SecurityEventKind::LoginSucceeded,
+                    user_id,
+                    None,
+                );
+                events.record_security_event(&event).await?;
+            }
+            step
+        }
+        // the failure was already counted by the reservation
+        Err(error) if error.status_code() == StatusCode::UNAUTHORIZED => {
+            let event = context.security_event(
+                SecurityEventKind::LoginFailed,
+                user_id,
+                None,
+            );
+            events.record_security_event(&event).await?;
+            Err(error)?
+        }
+        Err(error) => {
+            throttle.release(attempts.get_ref(), &keys).await?;
+            Err(error)?
+        }
+    };
+
+    Json(SignInResponse {
+        two_factor_required: step == LoginStep::SecondFactorRequired,
+    })
+    .into()
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/users/login/totp",
+    operation_id = "login_totp",
+    tag = "users",
+    request_body = TotpCode,
+    responses(
+        (status = 200, description = "Logged in, the session cookie is set"),
+        (
+            status = 401,
+            description = "Invalid code or no login waits for one",
+            body = String
+        ),
+        (status = 403, description = "The account is disabled", body = String),
+        (
+            status = 429,
+            description = "Too many invalid codes for this user or ip, retry \
+                           after the seconds in `Retry-After`",
+            body = String
+        ),
+    )
+)]
+#[allow(clippy::too_many_arguments)]
+async fn login_totp<
+    R: UserRepository,
+    T: TotpRepository,
+    A: LoginAttemptRepository,
+    E: AuditRepository,
+>(
+    request: HttpRequest,
+    context: RequestContext,
+    totp_code: web::Json<TotpCode>,
+    repo: web::Data<R>,
+    totp_repo: web::Data<T>,
+    attempts: web::Data<A>,
+    events: web::Data<E>,
+    throttle: web::Data<LoginThrottle>,
+) -> ErrorOr<HttpResponse> {
+    let user_id = totp::pending_login(&request)?;
+    // six digits are guessed quickly, the codes are throttled like passwords
+    let keys = login_throttle::second_factor_keys(&request, user_id);
+    throttle.reserve(attempts.get_ref(), &keys).await?;
+
+    let logged_in = common::login_second_factor(
+        &request,
+        repo.get_ref(),
+        totp_repo.get_ref(),
+        &totp_code.code,
+    )
+    .await
+    .0;
+
+    match logged_in {
+        Ok(_) => {
+            attempts.clear_failed_logins(&keys[0]).await?;
+            throttle.release(attempts.get_ref(), &keys[1..]).await?;
+            let event = context.security_event(
+                SecurityEventKind::LoginSucceeded,
+                Some(user_id),
+                None,
+            );
+            events.record_security_event(&event).await?;
+        }
+        Err(error) if error.status_code() == StatusCode::UNAUTHORIZED => {
+            let event = context.security_event(
+                SecurityEventKind::LoginFailed,
+                Some(user_id),
+                None,
+            );
+            events.record_security_event(&event).await?;
+            Err(error)?
+        }
+        Err(error) => {
+            throttle.release(attempts.get_ref(), &keys).await?;
+            Err(error)?
+        }
+    }
 
     HttpResponse::Ok().finish().into()
 }
 
-async fn register<R: UserRepository>(
+#[utoipa::path(
+    post,
+    path = "/api/v1/users/logout",
+    operation_id = "logout",
+    tag = "users",
+    responses(
+        (status = 200, description = "Logged out, the session is purged"),
+        (status = 401, description = "Not logged in", body = String),
+        (
+            status = 403,
+            description = "Used with an access token",
+            body = String
+        ),
+    ),
+    security(("session_cookie" = []))
+)]
+async fn logout<E: AuditRepository>(
+    user: AuthUser,
+    identity: Identity,
+    context: RequestContext,
+    events: web::Data<E>,
+) -> ErrorOr<HttpResponse> {
+    // the AuthUser extractor already answers with 401 if nobody is logged in
+    user.require_session()?;
+    identity.logout();
+    let event = context.security_event(
+        SecurityEventKind::SessionsRevoked,
+        Some(user.id),
+        Some(user.id),
+    );
+    events.record_security_event(&event).await?;
+
+    HttpResponse::Ok().finish().into()
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/users/register",
+    operation_id = "register",
+    tag = "users",
+    request_body = CreateUser,
+    responses((
+        status = 200,
+        description = "User was registered and a verification token mailed. \
+                       If the email is already taken, its owner is notified \
+                       by mail instead."
+    ))
+)]
+async fn register<R: UserRepository, T: UserTokenRepository>(
     mut create_user: web::Json<CreateUser>,
     repo: web::Data<R>,
+    tokens: web::Data<T>,
+    mailer: web::Data<dyn Mailer>,
+    context: RequestContext,
 ) -> ErrorOr<HttpResponse> {
+    // hashed in any case, the timing must not reveal whether the email is
+    // taken
     create_user.password = common::hash_password(&create_user.password).await?;
 
-    repo.create_user(&create_user).await?;
+    let registered = match repo.get_user_by_email(&create_user.email).await.0 {
+        Ok(_) => false,
+        Err(Error::External(StatusCode::NOT_FOUND, _)) => {
+            let audit = context.anonymous_audit();
+            match repo.create_user(&create_user, &audit).await.0 {
+                Ok(()) => true,
+                // a parallel registration took the email in the meantime
+                Err(Error::External(StatusCode::CONFLICT, _)) => false,
+                Err(error) => Err(error)?,
+            }
+        }
+        Err(error) => Err(error)?,
+    };
+
+    mail::send_in_background(async move {
+        let user = repo.get_user_by_email(&create_user.email).await?;
+        if registered {
+            send_token(
+                tokens.get_ref(),
+                mailer.get_ref(),
+                &user,
+                TokenPurpose::VerifyEmail,
+            )
+            .await
+        } else {
+            mailer.send(already_registered_mail(&user)).await
+        }
+    });
+
+    HttpResponse::Ok().finish().into()
+}
+
+fn already_registered_mail(user: &User) -> Mail {
+    Mail {
+        to: user.email.clone(),
+        subject: "Your lentos account".to_string(),
+        body: format!(
+            "Hi {},\n\n\
+             someone tried to register a new account with this email \
+             address, but it already belongs to your account. If that was \
+             you, just log in or reset your password. Otherwise you can \
+             ignore this mail.\n",
+            user.name
+        ),
+    }
+}
+
+/// Issues a new token of `purpose` for `user` and mails it to them.
+pub(super) async fn send_token<T: UserTokenRepository>(
+    tokens: &T,
+    mailer: &dyn Mailer,
+    user: &User,
+    purpose: TokenPurpose,
+) -> ErrorOr<()> {
+    let (token, token_hash) = token::generate_token();
+    let expires_at = Utc::now() + purpose.lifetime();
+    tokens
+        .create_token(&token_hash, purpose, &user.id, &user.email, expires_at)
+        .await?;
+
+    let (subject, action) = match purpose {
+        TokenPurpose::VerifyEmail => {
+            ("Verify your email address", "verify your email address")
+        }
+        TokenPurpose::ResetPassword => {
+            ("Reset your password", "set a new password")
+        }
+    };
+    mailer
+        .send(Mail {
+            to: user.email.clone(),
+            subject: subject.to_string(),
+            body: format!(
+                "Hi {},\n\n\
+                 use the following token to {action}:\n\n{token}\n\n\
+                 It expires at {}. If you did not ask for it, you can \
+                 ignore this mail.\n",
+                user.name,
+                expires_at.format("%Y-%m-%d %H:%M UTC")
+            ),
+        })
+        .await
+}
+
+/// Consumes a token and returns its user, as long as the token was mailed to
+/// their current address.
+async fn consume_token<R: UserRepository, T: UserTokenRepository>(
+    repo: &R,
+    tokens: &T,
+    token: &str,
+    purpose: TokenPurpose,
+) -> ErrorOr<User> {
+    let invalid_token = || {
+        Error::External(
+            StatusCode::BAD_REQUEST,
+            "The token is invalid or has expired.".into(),
+        )
+    };
+
+    let token = tokens
+        .consume_token(&token::hash_token(token), purpose)
+        .await
+        .0
+        .map_err(|_| invalid_token())?;
+    let user = repo.get_session_user(&token.user_id).await?;
+    if user.email != token.email {
+        Err(invalid_token())?;
+    }
+
+    user.into()
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/users/verify-email/request",
+    operation_id = "request_email_verification",
+    tag = "users",
+    responses(
+        (status = 200, description = "A verification token was mailed"),
+        (status = 401, description = "Not logged in", body = String),
+        (
+            status = 403,
+            description = "The access token lacks the scope",
+            body = String
+        ),
+    ),
+    security(("session_cookie" = []), ("access_token" = ["manage_account"]))
+)]
+async fn request_email_verification<
+    R: UserRepository,
+    T: UserTokenRepository,
+>(
+    repo: web::Data<R>,
+    tokens: web::Data<T>,
+    mailer: web::Data<dyn Mailer>,
+    user: AuthUser,
+) -> ErrorOr<HttpResponse> {
+    user.require(TokenScope::ManageAccount)?;
+    let user = repo.get_session_user(&user.id).await?;
+    send_token(
+        tokens.get_ref(),
+        mailer.get_ref(),
+        &user,
+        TokenPurpose::VerifyEmail,
+    )
+    .await?;
+
+    HttpResponse::Ok().finish().into()
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/users/verify-email",
+    operation_id = "verify_email",
+    tag = "users",
+    request_body = VerifyEmail,
+    responses(
+        (status = 200, description = "The email address is verified"),
+        (status = 400, description = "Invalid or expired token", body = String),
+    )
+)]
+async fn verify_email<R: UserRepository, T: UserTokenRepository>(
+    verify_email: web::Json<VerifyEmail>,
+    repo: web::Data<R>,
+    tokens: web::Data<T>,
+    context: RequestContext,
+) -> ErrorOr<HttpResponse> {
+    let user = consume_token(
+        repo.get_ref(),
+        tokens.get_ref(),
+        &verify_email.token,
+        TokenPurpose::VerifyEmail,
+    )
+    .await?;
+    repo.set_email_verified(&user.id, &context.anonymous_audit()).await?;
 
     HttpResponse::Ok().finish().into()
 }
 
+#[utoipa::path(
+    post,
+    path = "/api/v1/users/password-reset/request",
+    operation_id = "request_password_reset",
+    tag = "users",
+    request_body = RequestPasswordReset,
+    responses((
+        status = 200,
+        description = "A reset token was mailed, if the email is registered"
+    ))
+)]
+async fn request_password_reset<R: UserRepository, T: UserTokenRepository>(
+    request_reset: web::Json<RequestPasswordReset>,
+    repo: web::Data<R>,
+    tokens: web::Data<T>,
+    mailer: web::Data<dyn Mailer>,
+) -> ErrorOr<HttpResponse> {
+    // answers the same whether or not the email is registered
+    mail::send_in_background(async move {
+        match repo.get_user_by_email(&request_reset.email).await.0 {
+            Ok(user) => {
+                send_token(
+                    tokens.get_ref(),
+                    mailer.get_ref(),
+                    &user,
+                    TokenPurpose::ResetPassword,
+                )
+                .await
+            }
+            Err(Error::External(StatusCode::NOT_FOUND, _)) => ().into(),
+            Err(error) => Err(error)?,
+        }
+    });
+
+    HttpResponse::Ok().finish().into()
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/users/password-reset",
+    operation_id = "reset_password",
+    tag = "users",
+    request_body = ResetPassword,
+    responses(
+        (
+            status = 200,
+            description = "The password was replaced and all sessions and \
+                           access tokens of the user were revoked"
+        ),
+        (status = 400, description = "Invalid or expired token", body = String),
+    )
+)]
+async fn reset_password<
+    R: UserRepository,
+    T: UserTokenRepository,
+    S: SessionRepository,
+    A: AccessTokenRepository,
+    E: AuditRepository,
+>(
+    reset_password: web::Json<ResetPassword>,
+    repo: web::Data<R>,
+    tokens: web::Data<T>,
+    sessions: web::Data<S>,
+    access_tokens: web::Data<A>,
+    events: web::Data<E>,
+    context: RequestContext,
+) -> ErrorOr<HttpResponse> {
+    let user = consume_token(
+        repo.get_ref(),
+        tokens.get_ref(),
+        &reset_password.token,
+        TokenPurpose::ResetPassword,
+    )
+    .await?;
+    let password_hash = common::hash_password(&reset_password.password).await?;
+    repo.set_password(&user.id, &password_hash, &context.anonymous_audit())
+        .await?;
+    // whoever knew the old password must not stay logged in
+    sessions.delete_user_sessions(user.id).await?;
+    access_tokens.delete_user_access_tokens(&user.id).await?;
+    for kind in [
+        SecurityEventKind::PasswordChanged,
+        SecurityEventKind::SessionsRevoked,
+        SecurityEventKind::AccessTokensRevoked,
+    ] {
+        let event = context.security_event(kind, Some(user.id), None);
+        events.record_security_event(&event).await?;
+    }
+
+    HttpResponse::Ok().finish().into()
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/users",
+    operation_id = "get_user",
+    tag = "users",
+    responses(
+        (status = 200, description = "The session user", body = UserAccount),
+        (status = 401, description = "Not logged in", body = String),
+        (
+            status = 403,
+            description = "The access token lacks the scope",
+            body = String
+        ),
+    ),
+    security(("session_cookie" = []), ("access_token" = ["manage_account"]))
+)]
 async fn get<R: UserRepository>(
     repo: web::Data<R>,
     user: AuthUser,
-) -> ErrorOr<Json<User>> {
-    repo.get_session_user(&user.id).await.0.map(Json).into()
+) -> ErrorOr<Json<UserAccount>> {
+    user.require(TokenScope::ManageAccount)?;
+    let user = repo.get_session_user(&user.id).await?;
+
+    Json(UserAccount::from(user)).into()
 }
 
-async fn put<R: UserRepository>(
+#[utoipa::path(
+    put,
+    path = "/api/v1/users",
+    operation_id = "update_user",
+    tag = "users",
+    request_body = UpdateUser,
+    responses(
+        (
+            status = 200,
+            description = "User was updated, a new password revokes the \
+                           other sessions and all access tokens of the user"
+        ),
+        (status = 401, description = "Not logged in", body = String),
+        (
+            status = 403,
+            description = "The access token lacks the scope, or it tries to \
+                           change the password or email, or the current \
+                           password is wrong",
+            body = String
+        ),
+        (
+            status = 409,
+            description = "The email belongs to another user",
+            body = String
+        ),
+    ),
+    security(("session_cookie" = []), ("access_token" = ["manage_account"]))
+)]
+#[allow(clippy::too_many_arguments)]
+async fn put<
+    R: UserRepository,
+    S: SessionRepository,
+    A: AccessTokenRepository,
+    E: AuditRepository,
+>(
+    request: HttpRequest,
     update_user: web::Json<UpdateUser>,
     repo: web::Data<R>,
+    sessions: web::Data<S>,
+    access_tokens: web::Data<A>,
+    events: web::Data<E>,
     user: AuthUser,
+    context: RequestContext,
 ) -> ErrorOr<HttpResponse> {
-    repo.update_user(&update_user, &user.id).await?;
+    user.require(TokenScope::ManageAccount)?;
+    // the credentials stay with the sessions, a leaked token must not be
+    // enough to take over the account
+    if update_user.password.is_some() || update_user.email.is_some() {
+        user.require_session()?;
+    }
+    // nor is a session that was left open
+    if update_user.password.is_some() {
+        let current_password =
+            update_user.current_password.as_deref().unwrap_or_default();
+        let session_user = repo.get_session_user(&user.id).await?;
+        if !common::is_password(&session_user, current_password)? {
+            Err(UserError::WrongCurrentPassword)?;
+        }
+    }
+
+    let audit = context.audit(user.id);
+    repo.update_user(&update_user, &user.id, &audit).await?;
+    if let Some(password) = &update_user.password {
+        let password_hash = common::hash_password(password).await?;
+        repo.set_password(&user.id, &password_hash, &audit).await?;
+        // whoever knew the old password must not stay logged in, this
+        // session goes on under a new key
+        sessions.delete_user_sessions(user.id).await?;
+        request.get_session().renew();
+        access_tokens.delete_user_access_tokens(&user.id).await?;
+        for kind in [
+            SecurityEventKind::PasswordChanged,
+            SecurityEventKind::SessionsRevoked,
+            SecurityEventKind::AccessTokensRevoked,
+        ] {
+            let event =
+                context.security_event(kind, Some(user.id), Some(user.id));
+            events.record_security_event(&event).await?;
+        }
+    }
 
     HttpResponse::Ok().finish().into()
 }
 
-async fn delete<R: UserRepository>(
+#[utoipa::path(
+    delete,
+    path = "/api/v1/users",
+    operation_id = "delete_user",
+    tag = "users",
+    responses(
+        (status = 200, description = "User was deleted"),
+        (status = 401, description = "Not logged in", body = String),
+        (
+            status = 403,
+            description = "The access token lacks the scope",
+            body = String
+        ),
+    ),
+    security(("session_cookie" = []), ("access_token" = ["manage_account"]))
+)]
+async fn delete<R: UserRepository, A: AttachmentRepository>(
     repo: web::Data<R>,
+    attachments: web::Data<A>,
+    store: web::Data<dyn BlobStore>,
     user: AuthUser,
+    context: RequestContext,
 ) -> ErrorOr<HttpResponse> {
-    repo.delete_user(&user.id).await?;
+    user.require(TokenScope::ManageAccount)?;
+    repo.delete_user(&user.id, &context.audit(user.id)).await?;
+    // the todos of the user went with them
+    blob::delete_in_background(purge_orphaned_blobs(attachments, store));
 
     HttpResponse::Ok().finish().into()
 }
@@ -0,0 +1,91 @@
+//! Personal access tokens, sent as `Authorization: Bearer <token>` by
+//! scripts and integrations that cannot keep a session cookie, or as the
+//! password of HTTP Basic auth by CalDAV clients, which know nothing else.
+
+use actix_http::StatusCode;
+use data_encoding::BASE64;
+
+use super::token;
+use crate::{
+    repository::access_token::{AccessToken, AccessTokenRepository},
+    util::{error::Error, error_or::ErrorOr},
+};
+
+/// Marks the tokens, so that secret scanners and humans recognize leaked
+/// ones.
+pub const PREFIX: &str = "lentos_pat_";
+
+/// Generates a new token and returns it together with the hash to store in
+/// its place.
+pub fn generate_access_token() -> (String, String) {
+    let (secret, _) = token::generate_token();
+    let token = format!("{PREFIX}{secret}");
+    let token_hash = token::hash_token(&token);
+
+    (token, token_hash)
+}
+
+fn invalid_token() -> Error {
+    Error::External(
+        StatusCode::UNAUTHORIZED,
+        "The access token is invalid or expired.".into(),
+    )
+}
+
+/// Returns the token of an `Authorization` header. The user name of Basic
+/// auth is ignored, the token alone identifies the user.
+fn token_of(authorization: &str) -> Option<String> {
+    let token = match authorization.split_once(' ')? {
+        ("Bearer", token) => token.trim().to_string(),
+        ("Basic", credentials) => {
+            let credentials =
+                BASE64.decode(credentials.trim().as_bytes()).ok()?;
+            let credentials = String::from_utf8(credentials).ok()?;
+            credentials.split_once(':')?.1.to_string()
+        }
+        _ => None?,
+    };
+
+    Some(token).filter(|token| token.starts_with(PREFIX))
+}
+
+/// Resolves the value of an `Authorization` header to the unexpired token it
+/// carries.
+pub async fn authenticate(
+    repo: &dyn AccessTokenRepository,
+    authorization: &str,
+) -> ErrorOr<AccessToken> {
+    let token = token_of(authorization).ok_or_else(invalid_token)?;
+
+    repo.use_access_token(&token::hash_token(&token))
+        .await?
+        .ok_or_else(invalid_token)
+        .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn access_tokens_are_prefixed_and_match_their_hash() {
+        let (token, token_hash) = generate_access_token();
+
+        assert!(token.starts_with(PREFIX));
+        assert_eq!(token.len(), PREFIX.len() + 64);
+        assert_eq!(token::hash_token(&token), token_hash);
+    }
+
+    #[test]
+    fn tokens_are_sent_as_bearer_or_basic_auth_password() {
+        let (token, _) = generate_access_token();
+        let basic =
+            BASE64.encode(format!("jane@example.com:{token}").as_bytes());
+
+        assert_eq!(token_of(&format!("Bearer {token}")), Some(token.clone()));
+        assert_eq!(token_of(&format!("Basic {basic}")), Some(token));
+        assert_eq!(token_of("Bearer secret"), None);
+        assert_eq!(token_of(&format!("Basic {}", BASE64.encode(b"a:b"))), None);
+        assert_eq!(token_of("Basic not base64"), None);
+    }
+}
@@ -0,0 +1,225 @@
+use std::time::Duration;
+
+use actix_web::HttpRequest;
+use chrono::{DateTime, Utc};
+
+use crate::{
+    repository::login_attempt::{FailedLogins, LoginAttemptRepository},
+    util::{error::Error, error_or::ErrorOr},
+};
+
+/// Decides for how long logins are refused after failed attempts.
+///
+/// Failures are counted per email address and per [ip](client_key). The first
+/// `free_attempts` failures are free, every further one doubles the delay
+/// starting at `base_delay` up to `max_delay`. After `lockout_after` failures
+/// logins are locked for `lockout`.
+#[derive(Debug, Clone)]
+pub struct LoginThrottle {
+    pub free_attempts: i32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    pub lockout_after: i32,
+    pub lockout: Duration,
+    /// Failures are forgotten once there was none for this long.
+    pub reset_after: Duration,
+}
+
+impl Default for LoginThrottle {
+    fn default() -> Self {
+        Self {
+            free_attempts: 3,
+            base_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(5 * 60),
+            lockout_after: 10,
+            lockout: Duration::from_secs(15 * 60),
+            reset_after: Duration::from_secs(60 * 60),
+        }
+    }
+}
+
+/// Returns the key under which the requests of a client are counted by its
+/// ip, by the login throttle as well as the rate limiter.
+///
+/// It is the peer address of the connection, which cannot be spoofed unlike
+/// forwarding headers. Behind a reverse proxy all clients have the address of
+/// the proxy and share its counters, so counting by ip is meant for
+/// deployments that serve the api without one.
+pub fn client_key(request: &HttpRequest) -> Option<String> {
+    request.peer_addr().map(|address| format!("ip:{}", address.ip()))
+}
+
+/// Returns the keys under which the failed logins of a request are counted.
+///
+/// The email is taken as submitted, so unknown addresses are throttled just
+/// like existing ones and the responses do not reveal which exist.
+pub fn login_keys(request: &HttpRequest, email: &str) -> Vec<String> {
+    let mut keys = vec![format!("email:{}", email.trim().to_lowercase())];
+    keys.extend(client_key(request));
+
+    keys
+}
+
+/// Returns the keys under which the invalid codes of the second factor of
+/// `user_id` are counted.
+pub fn second_factor_keys(request: &HttpRequest, user_id: i64) -> Vec<String> {
+    let mut keys = vec![format!("totp:{user_id}")];
+    keys.extend(client_key(request));
+
+    keys
+}
+
+impl LoginThrottle {
+    /// Delay after the last of `failures` failed logins.
+    pub fn delay(&self, failures: i32) -> Duration {
+        if failures >= self.lockout_after {
+            return self.lockout;
+        }
+
+        match u32::try_from(failures - self.free_attempts) {
+            Ok(exponent @ 1..) => self
+                .base_delay
+                .saturating_mul(2u32.saturating_pow(exponent - 1))
+                .min(self.max_delay),
+            _ => Duration::ZERO,
+        }
+    }
+
+    /// Returns until when logins are refused after `failed_logins`.
+    pub fn blocked_until(
+        &self,
+        failed_logins: &FailedLogins,
+        now: DateTime<Utc>,
+    ) -> Option<DateTime<Utc>> {
+        let delay =
+            chrono::Duration::from_std(self.delay(failed_logins.failures))
+                .unwrap_or(chrono::Duration::MAX);
+        let until = failed_logins.last_failure_at.checked_add_signed(delay)?;
+
+        (until > now).then_some(until)
+    }
+
+    /// Refuses the login with `429 Too Many Requests` while one of `keys` is
+    /// blocked, otherwise counts it as failed under all of them until it is
+    /// released.
+    ///
+    /// The failure is counted before the login is verified, checking first and
+    /// recording afterwards would let parallel guesses all pass the check.
+    pub async fn reserve<A: LoginAttemptRepository>(
+        &self,
+        repo: &A,
+        keys: &[String],
+    ) -> ErrorOr<()> {
+        let mut reserved = Vec::new();
+        let mut blocked_until = None;
+
+        for key in keys {
+            match self.reserve_key(repo, key).await? {
+                Some(until) => blocked_until = blocked_until.max(Some(until)),
+                None => reserved.push(key.clone()),
+            }
+        }
+
+        if let Some(until) = blocked_until {
+            self.release(repo, &reserved).await?;
+            // round up, clients that retry on time must not be refused again
+            let retry_after =
+                (until - Utc::now()).num_milliseconds().max(0) as u64;
+            Err(Error::TooManyRequests {
+                message: "Too many failed logins. Try again later.".into(),
+                retry_after: retry_after.div_ceil(1000),
+            })?;
+        }
+
+        ().into()
+    }
+
+    /// Takes back the failures counted by `reserve` for a login that did not
+    /// fail.
+    pub async fn release<A: LoginAttemptRepository>(
+        &self,
+        repo: &A,
+        keys: &[String],
+    ) -> ErrorOr<()> {
+        for key in keys {
+            repo.release_login_attempt(key).await?;
+        }
+
+        ().into()
+    }
+
+    /// Counts a failure under `key` unless it is blocked, returns until when
+    /// it is.
+    async fn reserve_key<A: LoginAttemptRepository>(
+        &self,
+        repo: &A,
+        key: &str,
+    ) -> ErrorOr<Option<DateTime<Utc>>> {
+        let reset_after = chrono::Duration::from_std(self.reset_after)
+            .unwrap_or(chrono::Duration::MAX);
+
+        // if the reservation fails another login changed the failures in
+        // between, the new count is checked again
+        loop {
+            let now = Utc::now();
+            let previous = repo.get_failed_logins(key).await?;
+            let blocked_until = previous
+                .as_ref()
+                .and_then(|previous| self.blocked_until(previous, now));
+            if blocked_until.is_some() {
+                return blocked_until.into();
+            }
+
+            let reset_before = now - reset_after;
+            if repo
+                .reserve_login_attempt(key, previous.as_ref(), reset_before)
+                .await?
+            {
+                return None.into();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn delay_grows_exponentially_until_the_lockout() {
+        let throttle = LoginThrottle::default();
+        let delays = (0..=11)
+            .map(|failures| throttle.delay(failures).as_secs())
+            .collect::<Vec<_>>();
+
+        assert_eq!(delays, [0, 0, 0, 0, 1, 2, 4, 8, 16, 32, 900, 900]);
+    }
+
+    #[test]
+    fn delay_is_capped() {
+        let throttle =
+            LoginThrottle { lockout_after: i32::MAX, ..Default::default() };
+
+        assert_eq!(throttle.delay(40), throttle.max_delay);
+        assert_eq!(throttle.delay(i32::MAX - 1), throttle.max_delay);
+    }
+
+    #[test]
+    fn blocked_until_ends_after_the_delay() {
+        let throttle = LoginThrottle::default();
+        let last_failure_at = Utc::now();
+        let failed_logins = FailedLogins { failures: 5, last_failure_at };
+
+        assert_eq!(
+            throttle.blocked_until(&failed_logins, last_failure_at),
+            Some(last_failure_at + chrono::Duration::seconds(2))
+        );
+        assert_eq!(
+            throttle.blocked_until(
+                &failed_logins,
+                last_failure_at + chrono::Duration::seconds(2)
+            ),
+            None
+        );
+    }
+}
@@ -1,8 +1,17 @@
-use std::pin::Pin;
+use std::{pin::Pin, sync::OnceLock};
 
-use actix_http::{HttpMessage, Payload, StatusCode};
+pub mod access_token;
+pub mod login_throttle;
+pub mod oidc;
+pub mod rate_limit;
+pub mod request_id;
+pub mod token;
+pub mod totp;
+pub mod webauthn;
+
+use actix_http::{header::AUTHORIZATION, HttpMessage, Payload, StatusCode};
 use actix_identity::Identity;
-use actix_web::{FromRequest, HttpRequest};
+use actix_web::{web, FromRequest, HttpRequest};
 use argon2::{
     password_hash::SaltString, Argon2, PasswordHash, PasswordHasher,
     PasswordVerifier,
@@ -10,33 +19,135 @@ use argon2::{
 
 use futures_core::Future;
 use rand::rngs::OsRng;
-use shared::models::user::{SignInUser, User};
+use shared::models::user::{Role, SignInUser, TokenScope, User};
 
-use crate::util::{error::Error, error_or::ErrorOr};
+use crate::{
+    repository::{
+        access_token::AccessTokenRepository, totp::TotpRepository,
+        user::UserRepository,
+    },
+    util::{error::Error, error_or::ErrorOr},
+};
 
 use super::api::user::UserError;
 // TODO maybe create auth trait
 
+/// How far a login got with a valid password.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoginStep {
+    LoggedIn,
+    /// The user has two-factor authentication enabled, the login has to be
+    /// completed with [`login_second_factor`].
+    SecondFactorRequired,
+}
+
 /// Logs in a user by verifying their password and setting a session cookie.
-pub async fn login(
+///
+/// Users with two-factor authentication are not logged in yet, only the
+/// pending login is stored in their session.
+pub async fn login<T: TotpRepository>(
     request: &HttpRequest,
+    totp_repo: &T,
     db_user: &User,
     req_user: &SignInUser,
-) -> ErrorOr<()> {
-    let argon2 = Argon2::default();
-    let parsed_hash = PasswordHash::new(&db_user.password)?;
+) -> ErrorOr<LoginStep> {
+    if !is_password(db_user, &req_user.password)? {
+        Err(UserError::InvalidEmailOrPassword)?;
+    }
+    ensure_enabled(db_user)?;
+
+    let totp = totp_repo.get_totp(&db_user.id).await?;
+    if totp.is_some_and(|totp| totp.confirmed_at.is_some()) {
+        totp::start_pending_login(request, db_user.id)?;
+
+        return LoginStep::SecondFactorRequired.into();
+    }
+
+    login_identity(request, db_user)?;
+
+    LoginStep::LoggedIn.into()
+}
+
+/// Completes a pending login with a code of the authenticator app or a
+/// recovery code and returns the id of the user.
+pub async fn login_second_factor<R: UserRepository, T: TotpRepository>(
+    request: &HttpRequest,
+    repo: &R,
+    totp_repo: &T,
+    code: &str,
+) -> ErrorOr<i64> {
+    let user_id = totp::pending_login(request)?;
+    let totp = totp_repo.get_totp(&user_id).await?;
+
+    let valid = match &totp {
+        Some(totp) => totp::verify_second_factor(totp_repo, totp, code).await?,
+        None => false,
+    };
+    if !valid {
+        Err(UserError::InvalidCode)?;
+    }
 
-    argon2
-        .verify_password(req_user.password.as_bytes(), &parsed_hash)
-        .map_err(|_| UserError::InvalidEmailOrPassword)?;
+    totp::end_pending_login(request);
+    login_identity(request, &repo.get_session_user(&user_id).await?)?;
 
-    Identity::login(&request.extensions(), db_user.id.to_string())
+    user_id.into()
+}
+
+/// Whether `password` is the one the user has set.
+pub fn is_password(user: &User, password: &str) -> ErrorOr<bool> {
+    let parsed_hash = PasswordHash::new(&user.password)?;
+
+    Argon2::default()
+        .verify_password(password.as_bytes(), &parsed_hash)
+        .is_ok()
+        .into()
+}
+
+/// Fails for disabled users.
+pub fn ensure_enabled(user: &User) -> Result<(), Error> {
+    match user.disabled_at {
+        Some(_) => Err(UserError::AccountDisabled.into()),
+        None => Ok(()),
+    }
+}
+
+/// Every way to log in ends here, so disabled users cannot log in at all.
+fn login_identity(request: &HttpRequest, user: &User) -> ErrorOr<()> {
+    ensure_enabled(user)?;
+
+    Identity::login(&request.extensions(), user.id.to_string())
         .map(|_| ())
         .map_err(Into::into)
         .map_err(Error::Internal)
         .into()
 }
 
+/// Verifies `password` against the hash of a random password, so that logins
+/// with an unknown email take as long as those with a wrong password.
+pub fn verify_dummy_password(password: &str) {
+    let parsed_hash =
+        PasswordHash::new(dummy_password_hash()).expect("a valid hash");
+
+    // always fails, only the time it takes matters
+    let _ =
+        Argon2::default().verify_password(password.as_bytes(), &parsed_hash);
+}
+
+/// Returns the hash for [`verify_dummy_password`]. It has the parameters of
+/// [`hash_password`], so verifying it is just as costly as verifying a real
+/// one.
+pub fn dummy_password_hash() -> &'static str {
+    static DUMMY_HASH: OnceLock<String> = OnceLock::new();
+
+    DUMMY_HASH.get_or_init(|| {
+        let salt = SaltString::generate(&mut OsRng);
+        Argon2::default()
+            .hash_password(salt.as_str().as_bytes(), &salt)
+            .expect("hashing a random password")
+            .to_string()
+    })
+}
+
 /// Hashes a password using the Argon2 password hashing algorithm.
 ///
 /// # Arguments
@@ -55,8 +166,18 @@ pub async fn hash_password(password: &str) -> ErrorOr<String> {
     password_hash.to_string().into()
 }
 
+/// The user a request is authenticated as, either by the session cookie or
+/// by a personal access token in the `Authorization` header.
+///
+/// Tokens are limited to their scopes, so every handler has to check the
+/// scope it needs with [`AuthUser::require`] or reject tokens altogether
+/// with [`AuthUser::require_session`]. Disabled users are rejected.
 pub struct AuthUser {
     pub id: i64,
+    pub role: Role,
+    /// The scopes of the access token, `None` for sessions, which may do
+    /// everything.
+    pub scopes: Option<Vec<TokenScope>>,
 }
 impl AuthUser {
     async fn parse_identity_id(identity: Identity) -> ErrorOr<i64> {
@@ -65,24 +186,149 @@ impl AuthUser {
 
         identity_id.parse::<i64>().map_err(|e| Error::Internal(e.into())).into()
     }
+
+    /// Fails for access tokens without `scope`.
+    pub fn require(&self, scope: TokenScope) -> Result<(), Error> {
+        match &self.scopes {
+            Some(scopes) if !scopes.contains(&scope) => Err(Error::External(
+                StatusCode::FORBIDDEN,
+                "The access token does not grant access to this resource."
+                    .into(),
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Fails for access tokens, e.g. for managing the credentials of the
+    /// user, which no token may escalate to.
+    pub fn require_session(&self) -> Result<(), Error> {
+        match self.scopes {
+            Some(_) => Err(Error::External(
+                StatusCode::FORBIDDEN,
+                "Access tokens cannot be used for this. Please log in.".into(),
+            )),
+            None => Ok(()),
+        }
+    }
+}
+
+fn not_logged_in() -> Error {
+    Error::External(
+        StatusCode::UNAUTHORIZED,
+        "You do not seem to be logged in. Please log in first.".into(),
+    )
+}
+
+/// Returns the repository the server registered for the extractors, which
+/// are not generic over the backend.
+fn registered<T: ?Sized + 'static>(
+    req: &HttpRequest,
+) -> Result<web::Data<T>, Error> {
+    req.app_data::<web::Data<T>>().cloned().ok_or_else(|| {
+        Error::Internal(color_eyre::eyre::eyre!(
+            "No {} is registered",
+            std::any::type_name::<T>()
+        ))
+    })
+}
+
+/// Who a request is authenticated as, before the account of the user is
+/// checked.
+#[derive(Debug, Clone)]
+pub struct Credentials {
+    pub user_id: i64,
+    /// The scopes of the access token, `None` for sessions.
+    pub scopes: Option<Vec<TokenScope>>,
+}
+
+/// Resolves the access token of a request or, without one, its session. A
+/// request with a token is never authenticated by its session.
+///
+/// The rate limiter resolves them before [`AuthUser`] does, so they are kept
+/// in the extensions of the request and a token is looked up only once.
+pub async fn credentials(req: &HttpRequest) -> Result<Credentials, Error> {
+    if let Some(credentials) = req.extensions().get::<Credentials>() {
+        return Ok(credentials.clone());
+    }
+
+    let authorization = req
+        .headers()
+        .get(AUTHORIZATION)
+        .map(|value| value.to_str().unwrap_or_default().to_string());
+    let credentials = match authorization {
+        Some(authorization) => {
+            let tokens = registered::<dyn AccessTokenRepository>(req)?;
+            let token =
+                access_token::authenticate(tokens.get_ref(), &authorization)
+                    .await?;
+
+            Credentials { user_id: token.user_id, scopes: Some(token.scopes.0) }
+        }
+        None => {
+            let identity = Identity::from_request(req, &mut Payload::None)
+                .await
+                .map_err(|_| not_logged_in())?;
+            let user_id = AuthUser::parse_identity_id(identity).await?;
+
+            Credentials { user_id, scopes: None }
+        }
+    };
+    req.extensions_mut().insert(credentials.clone());
+
+    Ok(credentials)
 }
 
 impl FromRequest for AuthUser {
     type Error = Error;
     type Future = Pin<Box<dyn Future<Output = Result<Self, Error>>>>;
     fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
-        let identity = Identity::from_request(req, &mut Payload::None);
+        let req = req.clone();
+        let users = registered::<dyn UserRepository>(&req);
 
         let future = async move {
-            let identity = identity.await.map_err(|_| {
-                Error::External(
-                    StatusCode::UNAUTHORIZED,
-                    "You do not seem to be logged in. Please log in first."
-                        .into(),
-                )
-            })?;
-            let id = Self::parse_identity_id(identity).await?;
-            Ok(Self { id })
+            let Credentials { user_id: id, scopes } = credentials(&req).await?;
+
+            // disabling a user takes effect immediately, not just at the next
+            // login
+            let user = match users?.get_session_user(&id).await.0 {
+                Ok(user) => user,
+                Err(Error::External(StatusCode::NOT_FOUND, _)) => {
+                    Err(not_logged_in())?
+                }
+                Err(error) => Err(error)?,
+            };
+            ensure_enabled(&user)?;
+
+            Ok(Self { id, role: user.role, scopes })
+        };
+
+        Pin::from(Box::new(future))
+    }
+}
+
+/// An administrator, authenticated by their session. Access tokens have no
+/// scope for the admin api.
+pub struct AdminUser {
+    pub id: i64,
+}
+
+impl FromRequest for AdminUser {
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self, Error>>>>;
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let user = AuthUser::from_request(req, payload);
+
+        let future = async move {
+            let user = user.await?;
+            user.require_session()?;
+            if user.role != Role::Admin {
+                Err(Error::External(
+                    StatusCode::FORBIDDEN,
+                    "Only administrators may do this.".into(),
+                ))?;
+            }
+
+            Ok(Self { id: user.id })
         };
 
         Pin::from(Box::new(future))
@@ -0,0 +1,408 @@
+//! Single sign-on with an OpenID Connect provider, using the authorization
+//! code flow with PKCE (RFC 7636).
+//!
+//! The state, nonce and code verifier of a login are kept in the session
+//! between the redirect to the provider and the callback.
+
+use std::{sync::OnceLock, time::Duration};
+
+use actix_http::StatusCode;
+use actix_identity::IdentityExt;
+use actix_session::SessionExt;
+use actix_web::HttpRequest;
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::{self, WrapErr};
+use data_encoding::BASE64URL_NOPAD;
+use jsonwebtoken::{jwk::JwkSet, Algorithm, DecodingKey, Validation};
+use rand::{rngs::OsRng, RngCore};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use shared::models::user::{CreateUser, User};
+use url::Url;
+
+use super::{hash_password, login_identity};
+use crate::{
+    repository::{
+        audit::AuditContext, oidc::OidcIdentityRepository,
+        totp::TotpRepository, user::UserRepository,
+    },
+    util::{error::Error, error_or::ErrorOr},
+};
+
+/// Session key of a login that waits for the callback of the provider.
+const PENDING_LOGIN: &str = "oidc_login";
+/// Time to log in at the provider.
+const PENDING_LOGIN_LIFETIME: chrono::Duration = chrono::Duration::minutes(10);
+
+const HTTP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Settings of the provider and of this client as registered with it.
+#[derive(Debug, Clone)]
+pub struct OidcSettings {
+    /// The issuer of the provider, e.g. `https://sso.example.com/realms/acme`.
+    /// Its metadata is discovered at `/.well-known/openid-configuration`.
+    pub issuer: String,
+    pub client_id: String,
+    /// Public clients without a secret are protected by PKCE alone.
+    pub client_secret: Option<String>,
+    /// The url of the callback endpoint, `…/api/v1/users/oidc/callback`.
+    pub redirect_url: Url,
+    pub scopes: Vec<String>,
+    /// Where the browser is sent after a successful login.
+    pub post_login_redirect: String,
+}
+
+/// The parts of the provider metadata the login needs.
+#[derive(Debug, Deserialize)]
+struct ProviderMetadata {
+    issuer: String,
+    authorization_endpoint: Url,
+    token_endpoint: Url,
+    jwks_uri: Url,
+}
+
+/// An OpenID Connect provider, its metadata is discovered on the first
+/// login.
+pub struct OidcProvider {
+    settings: OidcSettings,
+    http: reqwest::Client,
+    metadata: OnceLock<ProviderMetadata>,
+}
+
+impl OidcProvider {
+    pub fn new(settings: OidcSettings) -> eyre::Result<Self> {
+        let http = reqwest::Client::builder()
+            .timeout(HTTP_TIMEOUT)
+            .build()
+            .wrap_err("Failed to build the http client")?;
+
+        Ok(Self { settings, http, metadata: OnceLock::new() })
+    }
+
+    pub fn settings(&self) -> &OidcSettings {
+        &self.settings
+    }
+
+    async fn metadata(&self) -> ErrorOr<&ProviderMetadata> {
+        if let Some(metadata) = self.metadata.get() {
+            return metadata.into();
+        }
+
+        let url = format!(
+            "{}/.well-known/openid-configuration",
+            self.settings.issuer.trim_end_matches('/')
+        );
+        let metadata: ProviderMetadata = self.get_json(&url).await?;
+        // prevents a provider from issuing tokens in the name of another one
+        if metadata.issuer != self.settings.issuer {
+            Err(Error::Internal(eyre::eyre!(
+                "The provider metadata is for the issuer `{}`",
+                metadata.issuer
+            )))?;
+        }
+
+        // a concurrent login may have won the race, both fetched the same
+        self.metadata.get_or_init(|| metadata).into()
+    }
+
+    async fn get_json<T: DeserializeOwned>(&self, url: &str) -> ErrorOr<T> {
+        self.http
+            .get(url)
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .map_err(|e| Error::Internal(e.into()))?
+            .json()
+            .await
+            .map_err(|e| Error::Internal(e.into()))
+            .into()
+    }
+}
+
+/// A login that waits for the callback of the provider.
+#[derive(Debug, Serialize, Deserialize)]
+struct PendingLogin {
+    state: String,
+    nonce: String,
+    code_verifier: String,
+    expires_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    id_token: String,
+}
+
+/// The claims of an ID token that identify the account.
+#[derive(Debug, Deserialize)]
+struct IdTokenClaims {
+    iss: String,
+    sub: String,
+    nonce: Option<String>,
+    email: Option<String>,
+    #[serde(default)]
+    email_verified: bool,
+    name: Option<String>,
+}
+
+fn random_string() -> String {
+    let mut bytes = [0; 32];
+    OsRng.fill_bytes(&mut bytes);
+
+    BASE64URL_NOPAD.encode(&bytes)
+}
+
+fn sso_failed() -> Error {
+    Error::External(
+        StatusCode::UNAUTHORIZED,
+        "The single sign-on failed. Try again.".into(),
+    )
+}
+
+/// Starts a login and returns the url of the provider to send the browser
+/// to.
+pub async fn start_login(
+    request: &HttpRequest,
+    provider: &OidcProvider,
+) -> ErrorOr<Url> {
+    let metadata = provider.metadata().await?;
+    let settings = provider.settings();
+    let pending = PendingLogin {
+        state: random_string(),
+        nonce: random_string(),
+        code_verifier: random_string(),
+        expires_at: Utc::now() + PENDING_LOGIN_LIFETIME,
+    };
+    let code_challenge =
+        BASE64URL_NOPAD.encode(&Sha256::digest(&pending.code_verifier));
+
+    let mut url = metadata.authorization_endpoint.clone();
+    url.query_pairs_mut()
+        .append_pair("response_type", "code")
+        .append_pair("client_id", &settings.client_id)
+        .append_pair("redirect_uri", settings.redirect_url.as_str())
+        .append_pair("scope", &settings.scopes.join(" "))
+        .append_pair("state", &pending.state)
+        .append_pair("nonce", &pending.nonce)
+        .append_pair("code_challenge", &code_challenge)
+        .append_pair("code_challenge_method", "S256");
+
+    request
+        .get_session()
+        .insert(PENDING_LOGIN, pending)
+        .map_err(|e| Error::Internal(e.into()))?;
+
+    url.into()
+}
+
+/// Completes a login with the code of the callback, logs in the user linked
+/// to the account and returns their id.
+///
+/// Accounts without a linked user are linked to the user with their email,
+/// or to a new user, as long as the provider verified the email. Once linked
+/// the provider is trusted with the second factor, like passkeys are.
+#[allow(clippy::too_many_arguments)]
+pub async fn login<
+    R: UserRepository,
+    O: OidcIdentityRepository,
+    T: TotpRepository,
+>(
+    request: &HttpRequest,
+    provider: &OidcProvider,
+    repo: &R,
+    identities: &O,
+    totp_repo: &T,
+    code: &str,
+    state: &str,
+    audit: &AuditContext,
+) -> ErrorOr<i64> {
+    // every login can only be completed once
+    let pending = request
+        .get_session()
+        .remove_as::<PendingLogin>(PENDING_LOGIN)
+        .and_then(Result::ok)
+        .filter(|pending| pending.state == state)
+        .filter(|pending| pending.expires_at > Utc::now())
+        .ok_or_else(sso_failed)?;
+
+    let claims = verified_claims(provider, code, &pending).await?;
+    let user_id =
+        match identities.get_oidc_user_id(&claims.iss, &claims.sub).await? {
+            Some(user_id) => user_id,
+            None => {
+                link_user(request, repo, identities, totp_repo, &claims, audit)
+                    .await?
+            }
+        };
+
+    login_identity(request, &repo.get_session_user(&user_id).await?)?;
+
+    user_id.into()
+}
+
+/// Redeems the code at the provider and verifies the ID token it returns.
+async fn verified_claims(
+    provider: &OidcProvider,
+    code: &str,
+    pending: &PendingLogin,
+) -> ErrorOr<IdTokenClaims> {
+    let metadata = provider.metadata().await?;
+    let settings = provider.settings();
+
+    let mut token_request = provider.http.post(metadata.token_endpoint.clone());
+    let mut form = vec![
+        ("grant_type", "authorization_code"),
+        ("code", code),
+        ("redirect_uri", settings.redirect_url.as_str()),
+        ("code_verifier", &pending.code_verifier),
+    ];
+    match &settings.client_secret {
+        Some(secret) => {
+            token_request =
+                token_request.basic_auth(&settings.client_id, Some(secret));
+        }
+        None => form.push(("client_id", &settings.client_id)),
+    }
+
+    let response = token_request
+        .form(&form)
+        .send()
+        .await
+        .map_err(|e| Error::Internal(e.into()))?;
+    if !response.status().is_success() {
+        // e.g. an expired code, the user can simply start over
+        tracing::warn!("The provider refused the code: {}", response.status());
+        Err(sso_failed())?;
+    }
+    let tokens: TokenResponse =
+        response.json().await.map_err(|e| Error::Internal(e.into()))?;
+
+    let header = jsonwebtoken::decode_header(&tokens.id_token)
+        .map_err(|_| sso_failed())?;
+    let key = match header.alg {
+        // signed with the client secret, see OpenID Connect Core 10.1
+        Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512 => {
+            let secret =
+                settings.client_secret.as_deref().ok_or_else(sso_failed)?;
+            DecodingKey::from_secret(secret.as_bytes())
+        }
+        _ => {
+            let jwks: JwkSet =
+                provider.get_json(metadata.jwks_uri.as_str()).await?;
+            let jwk = match &header.kid {
+                Some(kid) => jwks.find(kid),
+                None => jwks.keys.first(),
+            }
+            .ok_or_else(sso_failed)?;
+            DecodingKey::from_jwk(jwk).map_err(|_| sso_failed())?
+        }
+    };
+
+    let mut validation = Validation::new(header.alg);
+    validation.set_audience(&[&settings.client_id]);
+    validation.set_issuer(&[&metadata.issuer]);
+    validation.set_required_spec_claims(&["exp", "iss", "aud", "sub"]);
+    let claims = jsonwebtoken::decode::<IdTokenClaims>(
+        &tokens.id_token,
+        &key,
+        &validation,
+    )
+    .map_err(|e| {
+        tracing::warn!("The provider returned an invalid ID token: {e}");
+        sso_failed()
+    })?
+    .claims;
+
+    // the token was issued for this login and not replayed from another one
+    if claims.nonce.as_deref() != Some(pending.nonce.as_str()) {
+        Err(sso_failed())?;
+    }
+
+    claims.into()
+}
+
+/// Links the account to the user with its email, who is created if there is
+/// none yet.
+async fn link_user<
+    R: UserRepository,
+    O: OidcIdentityRepository,
+    T: TotpRepository,
+>(
+    request: &HttpRequest,
+    repo: &R,
+    identities: &O,
+    totp_repo: &T,
+    claims: &IdTokenClaims,
+    audit: &AuditContext,
+) -> ErrorOr<i64> {
+    // an unverified email could be anyone's, linking it would hand over
+    // their account
+    let email = match &claims.email {
+        Some(email) if claims.email_verified => email,
+        _ => Err(Error::External(
+            StatusCode::FORBIDDEN,
+            "Your account at the identity provider has no verified email \
+             address."
+                .into(),
+        ))?,
+    };
+
+    let user = match repo.get_user_by_email(email).await.0 {
+        Ok(user) => {
+            ensure_linkable(request, totp_repo, &user).await?;
+            user
+        }
+        Err(Error::External(StatusCode::NOT_FOUND, _)) => {
+            let name = claims.name.clone().unwrap_or_else(|| {
+                email.split('@').next().unwrap_or(email).to_string()
+            });
+            // nobody knows it, a password can be set with a password reset
+            let password = hash_password(&random_string()).await?;
+            let create_user =
+                CreateUser { name, email: email.clone(), password };
+            repo.create_user(&create_user, audit).await?;
+
+            repo.get_user_by_email(email).await?
+        }
+        Err(error) => Err(error)?,
+    };
+    if user.email_verified_at.is_none() {
+        repo.set_email_verified(&user.id, audit).await?;
+    }
+
+    identities.link_oidc_identity(&claims.iss, &claims.sub, &user.id).await?;
+
+    user.id.into()
+}
+
+/// Linking must not grant more than the password of the user does. Unless
+/// the user links from their own session, their email has to be verified,
+/// as anyone could have registered it, and they must not have a second
+/// factor, which the provider does not know about.
+async fn ensure_linkable<T: TotpRepository>(
+    request: &HttpRequest,
+    totp_repo: &T,
+    user: &User,
+) -> ErrorOr<()> {
+    let session_user_id = request
+        .get_identity()
+        .and_then(|identity| identity.id())
+        .ok()
+        .and_then(|id| id.parse::<i64>().ok());
+    if session_user_id == Some(user.id) {
+        return ().into();
+    }
+
+    let totp = totp_repo.get_totp(&user.id).await?;
+    let second_factor = totp.is_some_and(|totp| totp.confirmed_at.is_some());
+    if user.email_verified_at.is_none() || second_factor {
+        Err(Error::External(
+            StatusCode::FORBIDDEN,
+            "An account with your email address already exists. Log in to it \
+             first, then sign in with the identity provider to link them."
+                .into(),
+        ))?;
+    }
+
+    ().into()
+}
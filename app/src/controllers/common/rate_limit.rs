@@ -0,0 +1,367 @@
+use std::{
+    collections::HashMap,
+    future::{ready, Ready},
+    rc::Rc,
+    sync::{Arc, Mutex, MutexGuard},
+    time::{Duration, Instant},
+};
+
+use actix_web::{
+    body::{EitherBody, MessageBody},
+    dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+    http::{
+        header::{HeaderName, HeaderValue},
+        Method,
+    },
+    ResponseError,
+};
+use futures_core::future::LocalBoxFuture;
+
+use super::{credentials, login_throttle::client_key};
+use crate::util::error::Error;
+
+const LIMIT: HeaderName = HeaderName::from_static("x-ratelimit-limit");
+const REMAINING: HeaderName = HeaderName::from_static("x-ratelimit-remaining");
+const RESET: HeaderName = HeaderName::from_static("x-ratelimit-reset");
+
+/// Buckets that were full for this long are dropped to bound the memory.
+const PRUNE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// A token bucket that holds up to `burst` requests and refills with
+/// `per_minute` requests per minute.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimit {
+    pub burst: u32,
+    pub per_minute: u32,
+}
+
+impl Default for RateLimit {
+    fn default() -> Self {
+        Self { burst: 60, per_minute: 120 }
+    }
+}
+
+impl RateLimit {
+    fn tokens_per_sec(&self) -> f64 {
+        f64::from(self.per_minute) / 60.0
+    }
+
+    /// Time it takes to refill `tokens`.
+    fn refill_time(&self, tokens: f64) -> Duration {
+        Duration::from_secs_f64((tokens / self.tokens_per_sec()).max(0.0))
+    }
+}
+
+/// Overrides the default limit for all requests whose path starts with
+/// `path` and, if given, that use `method`.
+#[derive(Debug, Clone)]
+pub struct RouteRateLimit {
+    pub path: String,
+    pub method: Option<Method>,
+    pub limit: RateLimit,
+}
+
+impl RouteRateLimit {
+    fn matches(&self, request: &ServiceRequest) -> bool {
+        request.path().starts_with(&self.path)
+            && self.method.as_ref().is_none_or(|m| m == request.method())
+    }
+}
+
+/// Middleware that limits the requests of every client with a token bucket.
+///
+/// Users are counted by their id, whether they send an access token or a
+/// session cookie, everyone else by [ip](client_key). Every route
+/// override has buckets of its own, so hammering one route does not lock a
+/// client out of the others. Clones share their buckets, build the limiter
+/// once outside of the `HttpServer::new` closure to share it between the
+/// workers.
+#[derive(Clone)]
+pub struct RateLimiter {
+    default: RateLimit,
+    routes: Arc<[RouteRateLimit]>,
+    buckets: Arc<Mutex<Buckets>>,
+}
+
+struct Buckets {
+    buckets: HashMap<BucketKey, Bucket>,
+    last_pruned: Instant,
+}
+
+/// The index of the route override, if any, and the client.
+type BucketKey = (Option<usize>, String);
+
+struct Bucket {
+    tokens: f64,
+    updated_at: Instant,
+}
+
+/// Outcome of taking a token for a request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Decision {
+    pub limit: u32,
+    pub remaining: u32,
+    /// Seconds until the bucket is full again.
+    pub reset: u64,
+    /// Seconds until the next request is allowed, if this one was refused.
+    pub retry_after: Option<u64>,
+}
+
+impl Default for RateLimiter {
+    fn default() -> Self {
+        Self::new(RateLimit::default(), Vec::new())
+    }
+}
+
+impl RateLimiter {
+    /// Creates a limiter that applies the first matching of `routes` or
+    /// `default` to a request.
+    pub fn new(default: RateLimit, routes: Vec<RouteRateLimit>) -> Self {
+        Self {
+            default,
+            routes: routes.into(),
+            buckets: Arc::new(Mutex::new(Buckets {
+                buckets: HashMap::new(),
+                last_pruned: Instant::now(),
+            })),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Buckets> {
+        // the buckets stay consistent even if a holder panicked
+        self.buckets.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Takes a token from the bucket of `client` for requests to `route`,
+    /// the index of a route override or `None` for the default limit.
+    pub fn acquire(
+        &self,
+        route: Option<usize>,
+        client: &str,
+        now: Instant,
+    ) -> Decision {
+        let limit = route.map_or(self.default, |i| self.routes[i].limit);
+        let burst = f64::from(limit.burst);
+        let mut buckets = self.lock();
+
+        if now.saturating_duration_since(buckets.last_pruned) >= PRUNE_INTERVAL
+        {
+            buckets.prune(&self.default, &self.routes, now);
+        }
+
+        let bucket = buckets
+            .buckets
+            .entry((route, client.to_string()))
+            .or_insert(Bucket { tokens: burst, updated_at: now });
+        let elapsed = now.saturating_duration_since(bucket.updated_at);
+        bucket.tokens = (bucket.tokens
+            + elapsed.as_secs_f64() * limit.tokens_per_sec())
+        .min(burst);
+        bucket.updated_at = now;
+
+        let retry_after = if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            None
+        } else {
+            Some(ceil_secs(limit.refill_time(1.0 - bucket.tokens)))
+        };
+
+        Decision {
+            limit: limit.burst,
+            remaining: bucket.tokens as u32,
+            reset: ceil_secs(limit.refill_time(burst - bucket.tokens)),
+            retry_after,
+        }
+    }
+
+    async fn check(&self, request: &ServiceRequest) -> Decision {
+        let route = self.routes.iter().position(|r| r.matches(request));
+        // the user of the token or session, like `AuthUser`
+        let client = match credentials(request.request()).await {
+            Ok(credentials) => format!("user:{}", credentials.user_id),
+            Err(_) => client_key(request.request())
+                .unwrap_or_else(|| "ip:unknown".to_string()),
+        };
+
+        self.acquire(route, &client, Instant::now())
+    }
+}
+
+impl Buckets {
+    /// Drops the buckets that have refilled completely, they are recreated
+    /// full on the next request anyway.
+    fn prune(
+        &mut self,
+        default: &RateLimit,
+        routes: &[RouteRateLimit],
+        now: Instant,
+    ) {
+        self.buckets.retain(|(route, _), bucket| {
+            let limit = route.map_or(default, |i| &routes[i].limit);
+            let missing = f64::from(limit.burst) - bucket.tokens;
+
+            bucket.updated_at + limit.refill_time(missing) > now
+        });
+        self.last_pruned = now;
+    }
+}
+
+fn ceil_secs(duration: Duration) -> u64 {
+    duration.as_secs() + u64::from(duration.subsec_nanos() > 0)
+}
+
+impl Decision {
+    fn insert_headers<B>(&self, response: &mut ServiceResponse<B>) {
+        let headers = response.headers_mut();
+        for (name, value) in [
+            (LIMIT, u64::from(self.limit)),
+            (REMAINING, u64::from(self.remaining)),
+            (RESET, self.reset),
+        ] {
+            headers.insert(name, HeaderValue::from(value));
+        }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for RateLimiter
+where
+    S: Service<
+            ServiceRequest,
+            Response = ServiceResponse<B>,
+            Error = actix_web::Error,
+        > + 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = actix_web::Error;
+    type Transform = RateLimiterMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RateLimiterMiddleware {
+            service: Rc::new(service),
+            limiter: self.clone(),
+        }))
+    }
+}
+
+pub struct RateLimiterMiddleware<S> {
+    service: Rc<S>,
+    limiter: RateLimiter,
+}
+
+impl<S, B> Service<ServiceRequest> for RateLimiterMiddleware<S>
+where
+    S: Service<
+            ServiceRequest,
+            Response = ServiceResponse<B>,
+            Error = actix_web::Error,
+        > + 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, request: ServiceRequest) -> Self::Future {
+        let service = Rc::clone(&self.service);
+        let limiter = self.limiter.clone();
+
+        Box::pin(async move {
+            let decision = limiter.check(&request).await;
+
+            if let Some(retry_after) = decision.retry_after {
+                let error = Error::TooManyRequests {
+                    message: "Too many requests. Try again later.".into(),
+                    retry_after,
+                };
+                let mut response =
+                    request.into_response(error.error_response());
+                decision.insert_headers(&mut response);
+
+                return Ok(response.map_into_right_body());
+            }
+
+            let mut response = service.call(request).await?;
+            decision.insert_headers(&mut response);
+
+            Ok(response.map_into_left_body())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limiter() -> RateLimiter {
+        RateLimiter::new(
+            RateLimit { burst: 2, per_minute: 60 },
+            vec![RouteRateLimit {
+                path: "/api/v1/todos".to_string(),
+                method: Some(Method::POST),
+                limit: RateLimit { burst: 1, per_minute: 6 },
+            }],
+        )
+    }
+
+    #[test]
+    fn buckets_drain_and_refill() {
+        let limiter = limiter();
+        let now = Instant::now();
+
+        let decisions = [
+            limiter.acquire(None, "ip:192.0.2.1", now),
+            limiter.acquire(None, "ip:192.0.2.1", now),
+            limiter.acquire(None, "ip:192.0.2.1", now),
+        ];
+        assert_eq!(
+            decisions.map(|d| (d.remaining, d.retry_after)),
+            [(1, None), (0, None), (0, Some(1))]
+        );
+        assert_eq!(decisions[2].reset, 2);
+
+        let later = now + Duration::from_secs(1);
+        assert_eq!(
+            limiter.acquire(None, "ip:192.0.2.1", later).retry_after,
+            None
+        );
+    }
+
+    #[test]
+    fn clients_and_routes_have_separate_buckets() {
+        let limiter = limiter();
+        let now = Instant::now();
+
+        assert_eq!(limiter.acquire(Some(0), "user:1", now).retry_after, None);
+        assert_eq!(
+            limiter.acquire(Some(0), "user:1", now).retry_after,
+            Some(10)
+        );
+        assert_eq!(limiter.acquire(Some(0), "user:2", now).retry_after, None);
+        assert_eq!(limiter.acquire(None, "user:1", now).retry_after, None);
+    }
+
+    #[test]
+    fn full_buckets_are_pruned() {
+        let limiter = limiter();
+        let now = Instant::now();
+        limiter.acquire(None, "user:1", now);
+        limiter.acquire(Some(0), "user:2", now + Duration::from_secs(55));
+
+        // user 1 has refilled after a second, user 2 needs ten
+        limiter.acquire(None, "user:3", now + PRUNE_INTERVAL);
+        let buckets = limiter.lock();
+        let mut clients = buckets
+            .buckets
+            .keys()
+            .map(|(_, client)| client.as_str())
+            .collect::<Vec<_>>();
+        clients.sort();
+
+        assert_eq!(clients, ["user:2", "user:3"]);
+    }
+}
@@ -0,0 +1,124 @@
+use std::future::{ready, Ready};
+
+use actix_http::{
+    header::{HeaderName, HeaderValue},
+    HttpMessage, Payload,
+};
+use actix_web::{
+    dev::{ServiceRequest, ServiceResponse},
+    FromRequest, HttpRequest,
+};
+use shared::models::audit::SecurityEventKind;
+
+use crate::{
+    repository::audit::{AuditContext, NewSecurityEvent},
+    util::error::Error,
+};
+
+pub const REQUEST_ID: HeaderName = HeaderName::from_static("x-request-id");
+/// Longest id taken over from a client.
+const MAX_LENGTH: usize = 64;
+
+/// The id of a request, the `X-Request-Id` the client sent or a random one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestId(pub String);
+
+impl RequestId {
+    /// Tags a request with its id, which the middleware in
+    /// [`crate::server::app`] does for every request.
+    pub fn assign(request: &ServiceRequest) -> Self {
+        let sent = request
+            .headers()
+            .get(&REQUEST_ID)
+            .and_then(|value| value.to_str().ok())
+            .filter(|id| is_usable(id));
+        let request_id = match sent {
+            Some(id) => Self(id.to_string()),
+            None => Self::random(),
+        };
+        request.extensions_mut().insert(request_id.clone());
+
+        request_id
+    }
+
+    fn random() -> Self {
+        Self(hex::encode(rand::random::<[u8; 16]>()))
+    }
+
+    /// Answers with the id, so clients can refer to it.
+    pub fn answer<B>(&self, response: &mut ServiceResponse<B>) {
+        if let Ok(value) = HeaderValue::from_str(&self.0) {
+            response.headers_mut().insert(REQUEST_ID, value);
+        }
+    }
+}
+
+/// Ids of clients end up in the logs and events, so only short ones without
+/// special characters are taken over.
+fn is_usable(id: &str) -> bool {
+    (1..=MAX_LENGTH).contains(&id.len())
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+}
+
+/// What the audit and security events record about a request.
+pub struct RequestContext {
+    pub request_id: String,
+    /// The peer address, forwarding headers can be spoofed.
+    pub ip: Option<String>,
+}
+
+impl RequestContext {
+    /// The context of a change made by `actor_id` in this request.
+    pub fn audit(&self, actor_id: i64) -> AuditContext {
+        AuditContext {
+            actor_id: Some(actor_id),
+            request_id: Some(self.request_id.clone()),
+        }
+    }
+
+    /// The context of a change made in this request without a logged in
+    /// user, e.g. by a mailed token.
+    pub fn anonymous_audit(&self) -> AuditContext {
+        AuditContext {
+            actor_id: None,
+            request_id: Some(self.request_id.clone()),
+        }
+    }
+
+    pub fn security_event(
+        &self,
+        kind: SecurityEventKind,
+        user_id: Option<i64>,
+        actor_id: Option<i64>,
+    ) -> NewSecurityEvent {
+        NewSecurityEvent {
+            kind,
+            user_id,
+            actor_id,
+            ip: self.ip.clone(),
+            request_id: Some(self.request_id.clone()),
+        }
+    }
+}
+
+impl FromRequest for RequestContext {
+    type Error = Error;
+    type Future = Ready<Result<Self, Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        // only requests that bypass the middleware, like in unit tests, have
+        // no id yet
+        let request_id = req
+            .extensions()
+            .get::<RequestId>()
+            .cloned()
+            .unwrap_or_else(RequestId::random);
+
+        ready(Ok(Self {
+            request_id: request_id.0,
+            ip: req.peer_addr().map(|address| address.ip().to_string()),
+        }))
+    }
+}
@@ -0,0 +1,37 @@
+use rand::{rngs::OsRng, RngCore};
+use sha2::{Digest, Sha256};
+
+/// Generates a random token to mail to a user and returns it together with
+/// the hash to store in its place.
+pub fn generate_token() -> (String, String) {
+    let mut bytes = [0; 32];
+    OsRng.fill_bytes(&mut bytes);
+    let token = hex::encode(bytes);
+    let token_hash = hash_token(&token);
+
+    (token, token_hash)
+}
+
+/// Hashes a token for storage and lookup.
+///
+/// The tokens are random, so unlike passwords they need neither a salt nor a
+/// slow hash.
+pub fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.trim().as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokens_match_their_hash_only() {
+        let (token, token_hash) = generate_token();
+        let (other_token, _) = generate_token();
+
+        assert_eq!(token.len(), 64);
+        assert_eq!(hash_token(&token), token_hash);
+        assert_eq!(hash_token(&format!(" {token}\n")), token_hash);
+        assert_ne!(hash_token(&other_token), token_hash);
+    }
+}
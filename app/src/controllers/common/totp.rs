@@ -0,0 +1,271 @@
+//! Time-based one-time passwords (RFC 6238) as a second factor.
+
+use actix_http::StatusCode;
+use actix_session::SessionExt;
+use actix_web::HttpRequest;
+use chrono::{DateTime, Duration, Utc};
+use data_encoding::BASE32_NOPAD;
+use hmac::{Hmac, Mac};
+use qrcode::{render::svg, QrCode};
+use rand::{rngs::OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use sha1::Sha1;
+use url::Url;
+
+use super::token;
+use crate::{
+    repository::totp::{Totp, TotpRepository},
+    util::{error::Error, error_or::ErrorOr},
+};
+
+/// Shown as the account name in authenticator apps.
+pub const ISSUER: &str = "lentos";
+
+/// Seconds a code is valid, the default of all common authenticator apps.
+const PERIOD: i64 = 30;
+const DIGITS: u32 = 6;
+/// Codes of this many steps before or after the current one are accepted,
+/// to allow for clock drift and slow typing.
+const SKEW: i64 = 1;
+
+const RECOVERY_CODES: usize = 10;
+
+/// Session key of a login that still waits for the second factor.
+const PENDING_LOGIN: &str = "pending_login";
+/// Time to enter the code after the password.
+const PENDING_LOGIN_LIFETIME: Duration = Duration::minutes(5);
+
+/// Generates a random secret, base32 encoded like authenticator apps
+/// expect it.
+pub fn generate_secret() -> String {
+    // 160 bits, the size of an HMAC-SHA1 hash as recommended by RFC 4226
+    let mut bytes = [0; 20];
+    OsRng.fill_bytes(&mut bytes);
+
+    BASE32_NOPAD.encode(&bytes)
+}
+
+/// Returns the `otpauth://` URI that authenticator apps import the secret
+/// from.
+pub fn otpauth_uri(account: &str, secret: &str) -> String {
+    let mut uri = Url::parse("otpauth://totp/").expect("a valid url");
+    uri.path_segments_mut()
+        .expect("a url with a path")
+        .pop_if_empty()
+        .push(&format!("{ISSUER}:{account}"));
+    uri.query_pairs_mut()
+        .append_pair("secret", secret)
+        .append_pair("issuer", ISSUER)
+        .append_pair("algorithm", "SHA1")
+        .append_pair("digits", &DIGITS.to_string())
+        .append_pair("period", &PERIOD.to_string());
+
+    uri.into()
+}
+
+/// Renders `uri` as a QR code to scan with an authenticator app.
+pub fn qr_code_svg(uri: &str) -> ErrorOr<String> {
+    let code = QrCode::new(uri).map_err(|e| Error::Internal(e.into()))?;
+
+    code.render::<svg::Color>().min_dimensions(200, 200).build().into()
+}
+
+/// Computes the HOTP code (RFC 4226) of `counter`.
+fn hotp(secret: &[u8], counter: u64) -> u32 {
+    let mut mac =
+        Hmac::<Sha1>::new_from_slice(secret).expect("keys of any length");
+    mac.update(&counter.to_be_bytes());
+    let hash = mac.finalize().into_bytes();
+
+    // dynamic truncation, the last nibble picks four bytes of the hash
+    let offset = usize::from(hash[hash.len() - 1] & 0x0f);
+    let bytes = [
+        hash[offset] & 0x7f,
+        hash[offset + 1],
+        hash[offset + 2],
+        hash[offset + 3],
+    ];
+
+    u32::from_be_bytes(bytes) % 10u32.pow(DIGITS)
+}
+
+/// Returns the time step of `now`.
+pub fn step(now: DateTime<Utc>) -> i64 {
+    now.timestamp().div_euclid(PERIOD)
+}
+
+/// Returns the code an authenticator app shows during `step`.
+pub fn code(secret: &str, step: i64) -> Option<String> {
+    let secret = BASE32_NOPAD.decode(secret.as_bytes()).ok()?;
+    let code = hotp(&secret, u64::try_from(step).ok()?);
+
+    Some(format!("{code:0width$}", width = DIGITS as usize))
+}
+
+/// Checks `code` against the steps around `now` and returns the step it
+/// belongs to.
+pub fn verify_code(
+    secret: &str,
+    code: &str,
+    now: DateTime<Utc>,
+) -> Option<i64> {
+    let code = normalize(code);
+    let current = step(now);
+
+    (current - SKEW..=current + SKEW)
+        .find(|step| self::code(secret, *step).is_some_and(|c| c == code))
+}
+
+/// Generates the recovery codes and returns them together with the hashes
+/// to store in their place.
+pub fn generate_recovery_codes() -> (Vec<String>, Vec<String>) {
+    (0..RECOVERY_CODES)
+        .map(|_| {
+            let mut bytes = [0; 5];
+            OsRng.fill_bytes(&mut bytes);
+            let code = hex::encode(bytes);
+            let code = format!("{}-{}", &code[..5], &code[5..]);
+            let code_hash = hash_recovery_code(&code);
+
+            (code, code_hash)
+        })
+        .unzip()
+}
+
+/// Hashes a recovery code, regardless of its case, spaces and dashes.
+fn hash_recovery_code(code: &str) -> String {
+    token::hash_token(&normalize(code).to_lowercase())
+}
+
+fn normalize(code: &str) -> String {
+    code.chars().filter(|c| !c.is_whitespace() && *c != '-').collect()
+}
+
+/// Accepts a code of the authenticator app or a recovery code as second
+/// factor of `totp`. Every code is only accepted once.
+pub async fn verify_second_factor<T: TotpRepository>(
+    repo: &T,
+    totp: &Totp,
+    code: &str,
+) -> ErrorOr<bool> {
+    if totp.confirmed_at.is_none() {
+        return false.into();
+    }
+
+    match verify_code(&totp.secret, code, Utc::now()) {
+        Some(step) => repo.use_step(&totp.user_id, step).await,
+        None => {
+            repo.use_recovery_code(&totp.user_id, &hash_recovery_code(code))
+                .await
+        }
+    }
+}
+
+/// A login with a valid password that waits for the second factor.
+#[derive(Debug, Serialize, Deserialize)]
+struct PendingLogin {
+    user_id: i64,
+    expires_at: DateTime<Utc>,
+}
+
+/// Remembers in the session that `user_id` entered their password, without
+/// logging them in.
+pub fn start_pending_login(request: &HttpRequest, user_id: i64) -> ErrorOr<()> {
+    let pending_login = PendingLogin {
+        user_id,
+        expires_at: Utc::now() + PENDING_LOGIN_LIFETIME,
+    };
+
+    request
+        .get_session()
+        .insert(PENDING_LOGIN, pending_login)
+        .map_err(|e| Error::Internal(e.into()))
+        .into()
+}
+
+/// Returns the user whose login waits for the second factor.
+pub fn pending_login(request: &HttpRequest) -> ErrorOr<i64> {
+    let pending_login = request
+        .get_session()
+        .get::<PendingLogin>(PENDING_LOGIN)
+        .map_err(|e| Error::Internal(e.into()))?;
+
+    match pending_login {
+        Some(login) if login.expires_at > Utc::now() => login.user_id.into(),
+        _ => Err(Error::External(
+            StatusCode::UNAUTHORIZED,
+            "Please log in with your password first.".into(),
+        ))?,
+    }
+}
+
+pub fn end_pending_login(request: &HttpRequest) {
+    request.get_session().remove(PENDING_LOGIN);
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    /// The SHA-1 secret of the RFC 6238 test vectors.
+    fn rfc_secret() -> String {
+        BASE32_NOPAD.encode(b"12345678901234567890")
+    }
+
+    fn at(timestamp: i64) -> DateTime<Utc> {
+        Utc.timestamp_opt(timestamp, 0).unwrap()
+    }
+
+    #[test]
+    fn codes_match_the_rfc_test_vectors() {
+        let secret = b"12345678901234567890";
+
+        // the last six digits of the eight digit codes in RFC 6238
+        for (timestamp, code) in [
+            (59, 287_082),
+            (1_111_111_109, 81_804),
+            (1_234_567_890, 5_924),
+            (2_000_000_000, 279_037),
+        ] {
+            assert_eq!(hotp(secret, step(at(timestamp)) as u64), code);
+        }
+    }
+
+    #[test]
+    fn codes_are_accepted_around_their_step() {
+        let secret = rfc_secret();
+
+        assert_eq!(verify_code(&secret, "287082", at(59)), Some(1));
+        assert_eq!(verify_code(&secret, "287 082", at(80)), Some(1));
+        assert_eq!(verify_code(&secret, "287082", at(30)), Some(1));
+        assert_eq!(verify_code(&secret, "287082", at(90)), None);
+        assert_eq!(verify_code(&secret, "287083", at(59)), None);
+        assert_eq!(verify_code(&secret, "0287082", at(59)), None);
+        assert_eq!(code(&secret, 41_152_263).as_deref(), Some("005924"));
+    }
+
+    #[test]
+    fn otpauth_uris_encode_the_account() {
+        assert_eq!(
+            otpauth_uri("jane doe@example.com", "ABC"),
+            "otpauth://totp/lentos:jane%20doe@example.com?secret=ABC\
+             &issuer=lentos&algorithm=SHA1&digits=6&period=30"
+        );
+    }
+
+    #[test]
+    fn recovery_codes_match_their_hash_only() {
+        let (codes, hashes) = generate_recovery_codes();
+
+        assert_eq!(codes.len(), RECOVERY_CODES);
+        assert_eq!(codes[0].len(), 11);
+        assert_eq!(hash_recovery_code(&codes[0]), hashes[0]);
+        assert_eq!(
+            hash_recovery_code(&codes[0].to_uppercase().replace('-', " ")),
+            hashes[0]
+        );
+        assert_ne!(hash_recovery_code(&codes[1]), hashes[0]);
+    }
+}
@@ -0,0 +1,230 @@
+//! Passkey (WebAuthn) registration and login ceremonies.
+//!
+//! The state between the start and the finish of a ceremony is kept in the
+//! session, which is stored on the server, so clients cannot tamper with it.
+
+use actix_http::StatusCode;
+use actix_session::SessionExt;
+use actix_web::HttpRequest;
+use color_eyre::eyre::{self, WrapErr};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use shared::models::user::User;
+use url::Url;
+pub use webauthn_rs::Webauthn;
+use webauthn_rs::{
+    prelude::{
+        CreationChallengeResponse, PasskeyAuthentication, PasskeyRegistration,
+        PublicKeyCredential, RegisterPublicKeyCredential,
+        RequestChallengeResponse, Uuid,
+    },
+    WebauthnBuilder,
+};
+
+use super::login_identity;
+use crate::{
+    repository::{
+        passkey::{PasskeyRepository, StoredPasskey},
+        user::UserRepository,
+    },
+    util::{error::Error, error_or::ErrorOr},
+};
+
+const REGISTRATION: &str = "passkey_registration";
+const AUTHENTICATION: &str = "passkey_authentication";
+
+/// Builds the relying party for the site at `origin`, e.g.
+/// `https://lentos.example.com`. Passkeys are bound to `rp_id`, the domain of
+/// the origin or one of its parents, and stop working if it changes.
+pub fn relying_party(
+    rp_id: &str,
+    origin: &str,
+    rp_name: &str,
+) -> eyre::Result<Webauthn> {
+    let origin = Url::parse(origin)
+        .wrap_err_with(|| format!("Invalid origin `{origin}`"))?;
+
+    WebauthnBuilder::new(rp_id, &origin)
+        .and_then(|builder| builder.rp_name(rp_name).build())
+        .wrap_err("Invalid relying party, rp_id has to match the origin")
+}
+
+/// The WebAuthn user handle of a user, it must not change as long as the
+/// user has passkeys.
+fn user_handle(user_id: i64) -> Uuid {
+    Uuid::from_u64_pair(0, user_id as u64)
+}
+
+#[derive(Serialize, Deserialize)]
+struct PendingRegistration {
+    user_id: i64,
+    name: String,
+    state: PasskeyRegistration,
+}
+
+#[derive(Serialize, Deserialize)]
+struct PendingAuthentication {
+    user_id: i64,
+    state: PasskeyAuthentication,
+}
+
+fn save_ceremony(
+    request: &HttpRequest,
+    key: &str,
+    state: impl Serialize,
+) -> ErrorOr<()> {
+    request
+        .get_session()
+        .insert(key, state)
+        .map_err(|e| Error::Internal(e.into()))
+        .into()
+}
+
+/// Removes the state of a ceremony from the session, every challenge can
+/// only be answered once.
+fn take_ceremony<T: DeserializeOwned>(
+    request: &HttpRequest,
+    key: &str,
+) -> Option<T> {
+    request.get_session().remove_as(key)?.ok()
+}
+
+fn invalid_passkey() -> Error {
+    Error::External(
+        StatusCode::UNAUTHORIZED,
+        "The passkey could not be verified. Try again.".into(),
+    )
+}
+
+/// Starts the registration of a passkey called `name` for a user and
+/// returns the options for `navigator.credentials.create()`.
+pub async fn start_registration<P: PasskeyRepository>(
+    request: &HttpRequest,
+    webauthn: &Webauthn,
+    repo: &P,
+    user: &User,
+    name: &str,
+) -> ErrorOr<CreationChallengeResponse> {
+    // the authenticator refuses to register a second passkey for the site
+    let exclude_credentials = repo
+        .get_passkeys(&user.id)
+        .await?
+        .iter()
+        .map(|stored| stored.passkey.cred_id().clone())
+        .collect();
+
+    let (challenge, state) = webauthn
+        .start_passkey_registration(
+            user_handle(user.id),
+            &user.email,
+            &user.name,
+            Some(exclude_credentials),
+        )
+        .map_err(|e| Error::Internal(e.into()))?;
+    let pending =
+        PendingRegistration { user_id: user.id, name: name.to_string(), state };
+    save_ceremony(request, REGISTRATION, pending)?;
+
+    challenge.into()
+}
+
+/// Verifies the new credential of the authenticator and stores it.
+pub async fn finish_registration<P: PasskeyRepository>(
+    request: &HttpRequest,
+    webauthn: &Webauthn,
+    repo: &P,
+    user_id: i64,
+    credential: &RegisterPublicKeyCredential,
+) -> ErrorOr<StoredPasskey> {
+    let pending = take_ceremony::<PendingRegistration>(request, REGISTRATION)
+        .filter(|pending| pending.user_id == user_id)
+        .ok_or_else(|| {
+            Error::External(
+                StatusCode::BAD_REQUEST,
+                "There is no passkey registration to finish. Start a new one."
+                    .into(),
+            )
+        })?;
+
+    let passkey = webauthn
+        .finish_passkey_registration(credential, &pending.state)
+        .map_err(|_| {
+            Error::External(
+                StatusCode::BAD_REQUEST,
+                "The passkey could not be verified. Try again.".into(),
+            )
+        })?;
+
+    repo.create_passkey(&user_id, &pending.name, &passkey).await
+}
+
+/// Starts a login with one of the passkeys of a user and returns the options
+/// for `navigator.credentials.get()`.
+pub async fn start_authentication<P: PasskeyRepository>(
+    request: &HttpRequest,
+    webauthn: &Webauthn,
+    repo: &P,
+    user_id: i64,
+) -> ErrorOr<RequestChallengeResponse> {
+    let passkeys = repo
+        .get_passkeys(&user_id)
+        .await?
+        .into_iter()
+        .map(|stored| stored.passkey.0)
+        .collect::<Vec<_>>();
+    if passkeys.is_empty() {
+        Err(no_passkey())?;
+    }
+
+    let (challenge, state) = webauthn
+        .start_passkey_authentication(&passkeys)
+        .map_err(|e| Error::Internal(e.into()))?;
+    save_ceremony(
+        request,
+        AUTHENTICATION,
+        PendingAuthentication { user_id, state },
+    )?;
+
+    challenge.into()
+}
+
+/// Answers the start of a login for an email without passkeys, whether or
+/// not it is registered.
+pub fn no_passkey() -> Error {
+    Error::External(
+        StatusCode::UNAUTHORIZED,
+        "There is no passkey for this email. Log in with your password.".into(),
+    )
+}
+
+/// Verifies the signature of the authenticator, logs in its user and
+/// returns their id.
+///
+/// Passkeys require user verification on the device, so they replace the
+/// password and the second factor alike.
+pub async fn login_passkey<R: UserRepository, P: PasskeyRepository>(
+    request: &HttpRequest,
+    webauthn: &Webauthn,
+    users: &R,
+    repo: &P,
+    credential: &PublicKeyCredential,
+) -> ErrorOr<i64> {
+    let pending =
+        take_ceremony::<PendingAuthentication>(request, AUTHENTICATION)
+            .ok_or_else(invalid_passkey)?;
+    let result = webauthn
+        .finish_passkey_authentication(credential, &pending.state)
+        .map_err(|_| invalid_passkey())?;
+
+    // keeps the signature counter, which reveals cloned authenticators
+    for stored in repo.get_passkeys(&pending.user_id).await? {
+        let mut passkey = stored.passkey.0;
+        if passkey.cred_id() == result.cred_id() {
+            passkey.update_credential(&result);
+            repo.record_passkey_use(&stored.id, &passkey).await?;
+        }
+    }
+
+    login_identity(request, &users.get_session_user(&pending.user_id).await?)?;
+
+    pending.user_id.into()
+}
@@ -0,0 +1,693 @@
+//! A CalDAV (RFC 4791) subset, so that calendar apps sync the todos both
+//! ways. The personal todos of a user and the todos of their lists are the
+//! VTODO resources of a single calendar, named after the external ids they
+//! are exported under, todos that apps create are added to the list their
+//! category names or are personal ones.
+//!
+//! Apps authenticate with an access token as the password of Basic auth.
+
+pub mod xml;
+
+use std::{borrow::Cow, collections::HashMap};
+
+use actix_http::{
+    header::{self, HeaderName, HeaderValue},
+    Method, StatusCode,
+};
+use actix_web::{
+    dev::ServiceResponse,
+    middleware::{ErrorHandlerResponse, ErrorHandlers},
+    web::{self, ServiceConfig},
+    HttpRequest, HttpResponse, Route,
+};
+use percent_encoding::{
+    percent_decode_str, utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC,
+};
+use sha2::{Digest, Sha256};
+use shared::models::{
+    list::ListPermission,
+    todo::{CreateTodo, Todo, UpdateTodo},
+    user::TokenScope,
+};
+
+use self::xml::{
+    Properties, PropertyName, Report, CALDAV, CALENDAR_SERVER, DAV,
+};
+use super::{
+    api::{attachment::purge_orphaned_blobs, todo::ensure_editor, transfer},
+    common::{request_id::RequestContext, AuthUser},
+};
+use crate::{
+    blob::{self, BlobStore},
+    ical::{self, ImportedVTodo},
+    repository::{
+        attachment::AttachmentRepository,
+        list::ListRepository,
+        todo::{CreateImportedTodo, TodoRepository},
+        Backend,
+    },
+    util::{error::Error, error_or::ErrorOr},
+};
+
+/// The principal of the user, who is the one of the credentials.
+const PRINCIPAL: &str = "/dav/";
+const HOME: &str = "/dav/calendars/";
+const CALENDAR: &str = "/dav/calendars/todos/";
+const CALENDAR_NAME: &str = "Todos";
+const SYNC_TOKEN_PREFIX: &str = "urn:lentos:sync:";
+const CONTENT_TYPE: &str = "text/calendar; charset=utf-8; component=VTODO";
+const XML_CONTENT_TYPE: &str = "application/xml; charset=utf-8";
+/// The characters of names that their hrefs keep as they are.
+const NAME: &AsciiSet =
+    &NON_ALPHANUMERIC.remove(b'-').remove(b'_').remove(b'.').remove(b'~');
+
+/// The properties of `allprop`, which leaves out the calendar data.
+const DEFAULT_PROPERTIES: [(&str, &str); 11] = [
+    (DAV, "resourcetype"),
+    (DAV, "displayname"),
+    (DAV, "current-user-principal"),
+    (DAV, "principal-URL"),
+    (CALDAV, "calendar-home-set"),
+    (CALDAV, "supported-calendar-component-set"),
+    (DAV, "sync-token"),
+    (CALENDAR_SERVER, "getctag"),
+    (DAV, "getetag"),
+    (DAV, "getcontenttype"),
+    (DAV, "current-user-privilege-set"),
+];
+
+pub fn service<B: Backend>(cfg: &mut ServiceConfig) {
+    cfg.route("/.well-known/caldav", web::route().to(well_known)).service(
+        web::scope("/dav")
+            .wrap(
+                ErrorHandlers::new()
+                    .handler(StatusCode::UNAUTHORIZED, challenge),
+            )
+            .service(
+                web::resource(["", "/"])
+                    .route(options("OPTIONS, PROPFIND"))
+                    .route(propfind().to(principal)),
+            )
+            .service(
+                web::resource(["/calendars", "/calendars/"])
+                    .route(options("OPTIONS, PROPFIND"))
+                    .route(propfind().to(home::<B::Todo, B::List>)),
+            )
+            .service(
+                web::resource(["/calendars/todos", "/calendars/todos/"])
+                    .route(options("OPTIONS, PROPFIND, REPORT"))
+                    .route(propfind().to(calendar::<B::Todo, B::List>))
+                    .route(report().to(report_calendar::<B::Todo, B::List>)),
+            )
+            .service(
+                web::resource("/calendars/todos/{name}.ics")
+                    .route(options("OPTIONS, PROPFIND, GET, PUT, DELETE"))
+                    .route(propfind().to(propfind_todo::<B::Todo, B::List>))
+                    .route(web::get().to(get::<B::Todo, B::List>))
+                    .route(web::put().to(put::<B::Todo, B::List>))
+                    .route(web::delete().to(delete::<
+                        B::Todo,
+                        B::List,
+                        B::Attachment,
+                    >)),
+            ),
+    );
+}
+
+fn propfind() -> Route {
+    web::method(Method::from_bytes(b"PROPFIND").expect("valid method"))
+}
+
+fn report() -> Route {
+    web::method(Method::from_bytes(b"REPORT").expect("valid method"))
+}
+
+/// Announces CalDAV support, which clients check before anything else.
+fn options(allow: &'static str) -> Route {
+    web::method(Method::OPTIONS).to(move || async move {
+        HttpResponse::Ok()
+            .insert_header(("DAV", "1, 3, calendar-access"))
+            .insert_header((header::ALLOW, allow))
+            .finish()
+    })
+}
+
+/// Asks for credentials, which clients only send once they are asked to.
+fn challenge<B>(
+    mut response: ServiceResponse<B>,
+) -> actix_web::Result<ErrorHandlerResponse<B>> {
+    response.response_mut().headers_mut().insert(
+        header::WWW_AUTHENTICATE,
+        HeaderValue::from_static(r#"Basic realm="lentos", charset="UTF-8""#),
+    );
+
+    Ok(ErrorHandlerResponse::Response(response.map_into_left_body()))
+}
+
+/// Points clients that only know the host to the principal (RFC 6764).
+async fn well_known() -> HttpResponse {
+    HttpResponse::MovedPermanently()
+        .insert_header((header::LOCATION, PRINCIPAL))
+        .finish()
+}
+
+/// A todo as resource of the calendar.
+struct TodoResource {
+    name: String,
+    todo: Todo,
+    calendar: String,
+    etag: String,
+}
+
+impl TodoResource {
+    fn new(
+        name: String,
+        todo: Todo,
+        list_names: &HashMap<i64, String>,
+    ) -> Self {
+        let calendar =
+            ical::calendar([transfer::vtodo(&name, &todo, list_names)]);
+        let etag = format!("\"{}\"", hex::encode(Sha256::digest(&calendar)));
+
+        Self { name, todo, calendar, etag }
+    }
+
+    fn href(&self) -> String {
+        format!("{CALENDAR}{}.ics", utf8_percent_encode(&self.name, NAME))
+    }
+}
+
+/// The todos of a user, who has a single calendar.
+struct Calendar {
+    todos: Vec<TodoResource>,
+}
+
+impl Calendar {
+    async fn load<R: TodoRepository, L: ListRepository>(
+        repo: &R,
+        lists: &L,
+        user_id: &i64,
+    ) -> Result<Self, Error> {
+        let list_names = transfer::list_names(lists, user_id).await?;
+        let todos = transfer::exported_todos(repo, user_id)
+            .await?
+            .into_iter()
+            .map(|(name, todo)| TodoResource::new(name, todo, &list_names))
+            .collect();
+
+        Ok(Self { todos })
+    }
+
+    fn todo(&self, name: &str) -> Option<&TodoResource> {
+        self.todos.iter().find(|todo| todo.name == name)
+    }
+
+    /// Identifies the state of the calendar by its latest change and the
+    /// todos that existed then.
+    fn sync_token(&self) -> String {
+        let at = self
+            .todos
+            .iter()
+            .map(|todo| todo.todo.updated_at.timestamp_micros())
+            .max()
+            .unwrap_or_default();
+
+        format!("{SYNC_TOKEN_PREFIX}{at}-{:016x}", self.fingerprint(at))
+    }
+
+    /// Combines the ids of the todos that were created until `at`, so that
+    /// the fingerprint changes when one of them is gone.
+    fn fingerprint(&self, at: i64) -> u64 {
+        self.todos
+            .iter()
+            .filter(|todo| todo.todo.created_at.timestamp_micros() <= at)
+            .map(|todo| {
+                let digest = Sha256::digest(todo.todo.id.to_be_bytes());
+                u64::from_be_bytes(digest[..8].try_into().expect("8 bytes"))
+            })
+            .fold(0, |fingerprint, id| fingerprint ^ id)
+    }
+
+    /// Returns the todos that changed since the state of the token, `None`
+    /// if todos are gone since, which clients only learn by syncing all
+    /// todos again.
+    fn changed_since(&self, sync_token: &str) -> Option<Vec<&TodoResource>> {
+        let (at, fingerprint) =
+            sync_token.strip_prefix(SYNC_TOKEN_PREFIX)?.split_once('-')?;
+        let at = at.parse::<i64>().ok()?;
+        let fingerprint = u64::from_str_radix(fingerprint, 16).ok()?;
+
+        (self.fingerprint(at) == fingerprint).then(|| {
+            self.todos
+                .iter()
+                .filter(|todo| todo.todo.updated_at.timestamp_micros() > at)
+                .collect()
+        })
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Resource<'a> {
+    Principal,
+    Home,
+    Calendar(&'a Calendar),
+    Todo(&'a TodoResource),
+}
+
+impl Resource<'_> {
+    fn href(&self) -> String {
+        match self {
+            Resource::Principal => PRINCIPAL.to_string(),
+            Resource::Home => HOME.to_string(),
+            Resource::Calendar(_) => CALENDAR.to_string(),
+            Resource::Todo(todo) => todo.href(),
+        }
+    }
+
+    /// Returns the content of a property, `None` if the resource does not
+    /// have it.
+    fn property(&self, name: &PropertyName, may_write: bool) -> Option<String> {
+        let namespace = name.namespace.as_str();
+        let content = match (namespace, name.name.as_str(), self) {
+            (DAV, "resourcetype", Resource::Principal) => {
+                "<d:collection/><d:principal/>".to_string()
+            }
+            (DAV, "resourcetype", Resource::Home) => "<d:collection/>".into(),
+            (DAV, "resourcetype", Resource::Calendar(_)) => {
+                "<d:collection/><c:calendar/>".to_string()
+            }
+            (DAV, "resourcetype", Resource::Todo(_)) => String::new(),
+            (DAV, "current-user-principal", _)
+            | (DAV, "principal-URL", Resource::Principal) => {
+                xml::href(PRINCIPAL)
+            }
+            (CALDAV, "calendar-home-set", Resource::Principal) => {
+                xml::href(HOME)
+            }
+            (DAV, "current-user-privilege-set", _) => {
+                let privileges: &[_] =
+                    if may_write { &["read", "write"] } else { &["read"] };
+                privileges
+                    .iter()
+                    .map(|privilege| {
+                        format!("<d:privilege><d:{privilege}/></d:privilege>")
+                    })
+                    .collect()
+            }
+            (DAV, "displayname", Resource::Calendar(_)) => {
+                CALENDAR_NAME.to_string()
+            }
+            (
+                CALDAV,
+                "supported-calendar-component-set",
+                Resource::Calendar(_),
+            ) => r#"<c:comp name="VTODO"/>"#.to_string(),
+            (DAV, "supported-report-set", Resource::Calendar(_)) => [
+                "<c:calendar-query/>",
+                "<c:calendar-multiget/>",
+                "<d:sync-collection/>",
+            ]
+            .map(|report| {
+                format!(
+                    "<d:supported-report><d:report>{report}</d:report>\
+                     </d:supported-report>"
+                )
+            })
+            .concat(),
+            (DAV, "sync-token", Resource::Calendar(calendar))
+            | (CALENDAR_SERVER, "getctag", Resource::Calendar(calendar)) => {
+                xml::escape(&calendar.sync_token())
+            }
+            (DAV, "getetag", Resource::Todo(todo)) => xml::escape(&todo.etag),
+            (DAV, "getcontenttype", Resource::Todo(_)) => CONTENT_TYPE.into(),
+            (CALDAV, "calendar-data", Resource::Todo(todo)) => {
+                xml::escape(&todo.calendar)
+            }
+            _ => return None,
+        };
+
+        Some(content)
+    }
+
+    fn response(
+        &self,
+        properties: &Properties,
+        may_write: bool,
+    ) -> xml::Response {
+        let names = match properties {
+            Properties::All => DEFAULT_PROPERTIES
+                .iter()
+                .map(|(namespace, name)| PropertyName::new(namespace, name))
+                .collect(),
+            Properties::Named(names) => names.clone(),
+        };
+
+        let mut response = xml::Response {
+            href: self.href(),
+            found: Vec::new(),
+            missing: Vec::new(),
+        };
+        for name in names {
+            match self.property(&name, may_write) {
+                Some(content) => response.found.push((name, content)),
+                // all properties are only those the resource has
+                None if properties == &Properties::All => {}
+                None => response.missing.push(name),
+            }
+        }
+
+        response
+    }
+}
+
+fn multistatus(
+    resources: &[Resource],
+    properties: &Properties,
+    user: &AuthUser,
+    missing_hrefs: &[String],
+    sync_token: Option<&str>,
+) -> ErrorOr<HttpResponse> {
+    let may_write = user.require(TokenScope::WriteTodos).is_ok();
+    let responses = resources
+        .iter()
+        .map(|resource| resource.response(properties, may_write))
+        .collect::<Vec<_>>();
+
+    HttpResponse::build(StatusCode::MULTI_STATUS)
+        .content_type(XML_CONTENT_TYPE)
+        .body(xml::multistatus(&responses, missing_hrefs, sync_token))
+        .into()
+}
+
+fn bad_request(message: impl Into<Cow<'static, str>>) -> Error {
+    Error::External(StatusCode::BAD_REQUEST, message.into())
+}
+
+fn not_found() -> Error {
+    Error::External(StatusCode::NOT_FOUND, "There is no such todo.".into())
+}
+
+/// Whether a PROPFIND asks for the members of a collection too, an infinite
+/// depth is answered like a depth of 1.
+fn with_members(request: &HttpRequest) -> bool {
+    request.headers().get("Depth").is_none_or(|depth| depth.as_bytes() != b"0")
+}
+
+/// Returns the name of the todo at an href, which may be a full URL.
+fn name_of(href: &str) -> Option<String> {
+    let path = match href.split_once("://") {
+        Some((_, url)) => &url[url.find('/')?..],
+        None => href,
+    };
+    let name = path.strip_prefix(CALENDAR)?.strip_suffix(".ics")?;
+
+    percent_decode_str(name).decode_utf8().ok().map(Cow::into_owned)
+}
+
+/// Checks the `If-Match` and `If-None-Match` headers, with which apps avoid
+/// overwriting changes they have not seen yet.
+fn check_preconditions(
+    request: &HttpRequest,
+    existing: Option<&TodoResource>,
+) -> Result<(), Error> {
+    let etag = existing.map(|todo| todo.etag.as_str());
+    let matches = |name: HeaderName| {
+        let value = request.headers().get(name)?.to_str().ok()?;
+        Some(value.split(',').map(str::trim).any(|tag| match tag {
+            "*" => etag.is_some(),
+            tag => Some(tag) == etag,
+        }))
+    };
+
+    if matches(header::IF_MATCH) == Some(false)
+        || matches(header::IF_NONE_MATCH) == Some(true)
+    {
+        Err(Error::External(
+            StatusCode::PRECONDITION_FAILED,
+            "The todo was changed in the meantime.".into(),
+        ))?;
+    }
+
+    Ok(())
+}
+
+/// Returns the first list among the categories of a todo that the user may
+/// add todos to, other categories are not lists of the calendar.
+async fn category_list<L: ListRepository>(
+    lists: &L,
+    categories: &[String],
+    user_id: &i64,
+) -> Result<Option<i64>, Error> {
+    let editable = lists
+        .get_lists(user_id)
+        .await?
+        .into_iter()
+        .filter(|list| list.permission >= ListPermission::Editor)
+        .collect::<Vec<_>>();
+
+    Ok(categories.iter().find_map(|category| {
+        editable.iter().find(|list| &list.name == category).map(|list| list.id)
+    }))
+}
+
+/// Reads the body of a PUT, a resource holds exactly one todo.
+fn single_vtodo(body: &str) -> Result<ImportedVTodo, Error> {
+    let mut todos = ical::parse(body).map_err(bad_request)?;
+    if todos.len() != 1 {
+        Err(bad_request("A resource has to hold exactly one VTODO."))?;
+    }
+    let todo = todos.remove(0).map_err(bad_request)?;
+    if todo.summary.trim().is_empty() {
+        Err(bad_request("The VTODO has no SUMMARY."))?;
+    }
+
+    Ok(todo)
+}
+
+async fn principal(
+    request: HttpRequest,
+    body: String,
+    user: AuthUser,
+) -> ErrorOr<HttpResponse> {
+    user.require(TokenScope::ReadTodos)?;
+    let properties = xml::parse_propfind(&body).map_err(bad_request)?;
+
+    let mut resources = vec![Resource::Principal];
+    if with_members(&request) {
+        resources.push(Resource::Home);
+    }
+
+    multistatus(&resources, &properties, &user, &[], None)
+}
+
+async fn home<R: TodoRepository, L: ListRepository>(
+    request: HttpRequest,
+    body: String,
+    repo: web::Data<R>,
+    lists: web::Data<L>,
+    user: AuthUser,
+) -> ErrorOr<HttpResponse> {
+    user.require(TokenScope::ReadTodos)?;
+    let properties = xml::parse_propfind(&body).map_err(bad_request)?;
+
+    let calendar =
+        Calendar::load(repo.get_ref(), lists.get_ref(), &user.id).await?;
+    let mut resources = vec![Resource::Home];
+    if with_members(&request) {
+        resources.push(Resource::Calendar(&calendar));
+    }
+
+    multistatus(&resources, &properties, &user, &[], None)
+}
+
+async fn calendar<R: TodoRepository, L: ListRepository>(
+    request: HttpRequest,
+    body: String,
+    repo: web::Data<R>,
+    lists: web::Data<L>,
+    user: AuthUser,
+) -> ErrorOr<HttpResponse> {
+    user.require(TokenScope::ReadTodos)?;
+    let properties = xml::parse_propfind(&body).map_err(bad_request)?;
+
+    let calendar =
+        Calendar::load(repo.get_ref(), lists.get_ref(), &user.id).await?;
+    let mut resources = vec![Resource::Calendar(&calendar)];
+    if with_members(&request) {
+        resources.extend(calendar.todos.iter().map(Resource::Todo));
+    }
+
+    multistatus(&resources, &properties, &user, &[], None)
+}
+
+async fn report_calendar<R: TodoRepository, L: ListRepository>(
+    body: String,
+    repo: web::Data<R>,
+    lists: web::Data<L>,
+    user: AuthUser,
+) -> ErrorOr<HttpResponse> {
+    user.require(TokenScope::ReadTodos)?;
+    let report = xml::parse_report(&body).map_err(bad_request)?;
+
+    let calendar =
+        Calendar::load(repo.get_ref(), lists.get_ref(), &user.id).await?;
+    match report {
+        Report::CalendarQuery { properties, todos } => {
+            let resources = match todos {
+                true => calendar.todos.iter().map(Resource::Todo).collect(),
+                false => Vec::new(),
+            };
+
+            multistatus(&resources, &properties, &user, &[], None)
+        }
+        Report::CalendarMultiget { properties, hrefs } => {
+            let mut resources = Vec::new();
+            let mut missing_hrefs = Vec::new();
+            for href in hrefs {
+                match name_of(&href).and_then(|name| calendar.todo(&name)) {
+                    Some(todo) => resources.push(Resource::Todo(todo)),
+                    None => missing_hrefs.push(href),
+                }
+            }
+
+            multistatus(&resources, &properties, &user, &missing_hrefs, None)
+        }
+        Report::SyncCollection { properties, sync_token } => {
+            let changed = match sync_token {
+                Some(sync_token) => calendar.changed_since(&sync_token),
+                None => Some(calendar.todos.iter().collect()),
+            };
+            let Some(changed) = changed else {
+                return HttpResponse::Forbidden()
+                    .content_type(XML_CONTENT_TYPE)
+                    .body(xml::error(DAV, "valid-sync-token"))
+                    .into();
+            };
+            let resources =
+                changed.into_iter().map(Resource::Todo).collect::<Vec<_>>();
+
+            multistatus(
+                &resources,
+                &properties,
+                &user,
+                &[],
+                Some(&calendar.sync_token()),
+            )
+        }
+    }
+}
+
+async fn propfind_todo<R: TodoRepository, L: ListRepository>(
+    name: web::Path<String>,
+    body: String,
+    repo: web::Data<R>,
+    lists: web::Data<L>,
+    user: AuthUser,
+) -> ErrorOr<HttpResponse> {
+    user.require(TokenScope::ReadTodos)?;
+    let properties = xml::parse_propfind(&body).map_err(bad_request)?;
+
+    let calendar =
+        Calendar::load(repo.get_ref(), lists.get_ref(), &user.id).await?;
+    let todo = calendar.todo(&name).ok_or_else(not_found)?;
+
+    multistatus(&[Resource::Todo(todo)], &properties, &user, &[], None)
+}
+
+async fn get<R: TodoRepository, L: ListRepository>(
+    name: web::Path<String>,
+    repo: web::Data<R>,
+    lists: web::Data<L>,
+    user: AuthUser,
+) -> ErrorOr<HttpResponse> {
+    user.require(TokenScope::ReadTodos)?;
+    let calendar =
+        Calendar::load(repo.get_ref(), lists.get_ref(), &user.id).await?;
+    let todo = calendar.todo(&name).ok_or_else(not_found)?;
+
+    HttpResponse::Ok()
+        .content_type(CONTENT_TYPE)
+        .insert_header((header::ETAG, todo.etag.clone()))
+        .body(todo.calendar.clone())
+        .into()
+}
+
+/// Creates or replaces a todo. There is no ETag in the response, since the
+/// stored todo differs from the uploaded one, which apps get to see with
+/// their next GET. The categories only pick the list of new todos, a todo
+/// stays in its list.
+async fn put<R: TodoRepository, L: ListRepository>(
+    name: web::Path<String>,
+    request: HttpRequest,
+    body: String,
+    repo: web::Data<R>,
+    lists: web::Data<L>,
+    user: AuthUser,
+    context: RequestContext,
+) -> ErrorOr<HttpResponse> {
+    user.require(TokenScope::WriteTodos)?;
+    let calendar =
+        Calendar::load(repo.get_ref(), lists.get_ref(), &user.id).await?;
+    let existing = calendar.todo(&name);
+    check_preconditions(&request, existing)?;
+    let vtodo = single_vtodo(&body)?;
+    let audit = context.audit(user.id);
+
+    let Some(existing) = existing else {
+        let list_id =
+            category_list(lists.get_ref(), &vtodo.categories, &user.id).await?;
+        let imported = CreateImportedTodo {
+            create_todo: CreateTodo {
+                title: vtodo.summary,
+                description: vtodo.description,
+                list_id,
+                due_at: vtodo.due_at,
+            },
+            is_done: vtodo.is_done,
+            external_id: Some(name.into_inner()),
+        };
+        repo.import_todos(&[imported], &[], &user.id, &audit).await?;
+
+        return HttpResponse::Created().finish().into();
+    };
+
+    ensure_editor(lists.get_ref(), &existing.todo, user.id).await?;
+    let update_todo = UpdateTodo {
+        id: existing.todo.id,
+        title: Some(vtodo.summary),
+        description: Some(vtodo.description),
+        is_done: Some(vtodo.is_done),
+        due_at: Some(vtodo.due_at),
+    };
+    repo.update_todo(&update_todo, &user.id, &audit).await?;
+
+    HttpResponse::NoContent().finish().into()
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn delete<
+    R: TodoRepository,
+    L: ListRepository,
+    A: AttachmentRepository,
+>(
+    name: web::Path<String>,
+    request: HttpRequest,
+    repo: web::Data<R>,
+    lists: web::Data<L>,
+    attachments: web::Data<A>,
+    store: web::Data<dyn BlobStore>,
+    user: AuthUser,
+    context: RequestContext,
+) -> ErrorOr<HttpResponse> {
+    user.require(TokenScope::WriteTodos)?;
+    let calendar =
+        Calendar::load(repo.get_ref(), lists.get_ref(), &user.id).await?;
+    let todo = calendar.todo(&name).ok_or_else(not_found)?;
+    check_preconditions(&request, Some(todo))?;
+
+    ensure_editor(lists.get_ref(), &todo.todo, user.id).await?;
+    repo.delete_todo(&todo.todo.id, &user.id, &context.audit(user.id)).await?;
+    blob::delete_in_background(purge_orphaned_blobs(attachments, store));
+
+    HttpResponse::NoContent().finish().into()
+}
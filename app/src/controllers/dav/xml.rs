@@ -0,0 +1,409 @@
+//! The XML bodies of WebDAV requests and of the multistatus responses to
+//! them.
+
+use actix_http::StatusCode;
+use roxmltree::{Document, Node};
+
+pub const DAV: &str = "DAV:";
+pub const CALDAV: &str = "urn:ietf:params:xml:ns:caldav";
+/// The namespace of `getctag`, which clients without sync support poll.
+pub const CALENDAR_SERVER: &str = "http://calendarserver.org/ns/";
+
+/// The namespaces of the responses with their prefixes.
+const PREFIXES: [(&str, &str); 3] =
+    [(DAV, "d"), (CALDAV, "c"), (CALENDAR_SERVER, "cs")];
+
+/// The name of a property, which is an element of its namespace.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PropertyName {
+    pub namespace: String,
+    pub name: String,
+}
+
+impl PropertyName {
+    pub fn new(namespace: &str, name: &str) -> Self {
+        Self { namespace: namespace.to_string(), name: name.to_string() }
+    }
+
+    fn of(node: Node) -> Self {
+        Self::new(
+            node.tag_name().namespace().unwrap_or_default(),
+            node.tag_name().name(),
+        )
+    }
+}
+
+/// The properties a PROPFIND or REPORT asks for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Properties {
+    /// The properties that are cheap to compute, for `allprop`, `propname`
+    /// and requests without a body.
+    All,
+    Named(Vec<PropertyName>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Report {
+    /// Only the component filter is applied, any filter on the properties
+    /// of the todos is left to the client.
+    CalendarQuery {
+        properties: Properties,
+        todos: bool,
+    },
+    CalendarMultiget {
+        properties: Properties,
+        hrefs: Vec<String>,
+    },
+    /// Starts with all todos if there is no token.
+    SyncCollection {
+        properties: Properties,
+        sync_token: Option<String>,
+    },
+}
+
+/// Reads the body of a PROPFIND, an empty one asks for all properties.
+pub fn parse_propfind(body: &str) -> Result<Properties, String> {
+    if body.trim().is_empty() {
+        return Ok(Properties::All);
+    }
+    let document = parse(body)?;
+    let root = document.root_element();
+    if !is(root, DAV, "propfind") {
+        return Err("The body is no `propfind` element.".into());
+    }
+
+    Ok(properties(root))
+}
+
+pub fn parse_report(body: &str) -> Result<Report, String> {
+    let document = parse(body)?;
+    let root = document.root_element();
+    let report = if is(root, CALDAV, "calendar-query") {
+        Report::CalendarQuery {
+            properties: properties(root),
+            todos: queries_todos(root),
+        }
+    } else if is(root, CALDAV, "calendar-multiget") {
+        Report::CalendarMultiget {
+            properties: properties(root),
+            hrefs: children(root, DAV, "href")
+                .filter_map(|href| href.text())
+                .map(|href| href.trim().to_string())
+                .collect(),
+        }
+    } else if is(root, DAV, "sync-collection") {
+        Report::SyncCollection {
+            properties: properties(root),
+            sync_token: children(root, DAV, "sync-token")
+                .filter_map(|token| token.text())
+                .map(|token| token.trim().to_string())
+                .find(|token| !token.is_empty()),
+        }
+    } else {
+        let PropertyName { namespace, name } = PropertyName::of(root);
+        return Err(format!(
+            "The report `{name}` of `{namespace}` is not supported."
+        ));
+    };
+
+    Ok(report)
+}
+
+fn parse(body: &str) -> Result<Document<'_>, String> {
+    Document::parse(body).map_err(|e| format!("Invalid XML: {e}"))
+}
+
+fn is(node: Node, namespace: &str, name: &str) -> bool {
+    node.is_element()
+        && node.tag_name().namespace() == Some(namespace)
+        && node.tag_name().name() == name
+}
+
+fn children<'a, 'input>(
+    node: Node<'a, 'input>,
+    namespace: &'a str,
+    name: &'a str,
+) -> impl Iterator<Item = Node<'a, 'input>> {
+    node.children().filter(move |child| is(*child, namespace, name))
+}
+
+fn properties(request: Node) -> Properties {
+    match children(request, DAV, "prop").next() {
+        Some(prop) => Properties::Named(
+            prop.children()
+                .filter(Node::is_element)
+                .map(PropertyName::of)
+                .collect(),
+        ),
+        None => Properties::All,
+    }
+}
+
+/// Whether the filter of a calendar query matches todos, which it does
+/// without a filter on the components of the calendar.
+fn queries_todos(query: Node) -> bool {
+    let Some(filter) = children(query, CALDAV, "filter").next() else {
+        return true;
+    };
+
+    children(filter, CALDAV, "comp-filter")
+        .filter(|calendar| calendar.attribute("name") == Some("VCALENDAR"))
+        .any(|calendar| {
+            let mut components =
+                children(calendar, CALDAV, "comp-filter").peekable();
+
+            components.peek().is_none()
+                || components.any(|component| {
+                    component.attribute("name") == Some("VTODO")
+                })
+        })
+}
+
+/// A resource of a multistatus response.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Response {
+    pub href: String,
+    /// Properties with their content, which is XML already.
+    pub found: Vec<(PropertyName, String)>,
+    /// Properties the resource does not have.
+    pub missing: Vec<PropertyName>,
+}
+
+/// Writes a multistatus of the resources, the hrefs that are missing have no
+/// properties at all.
+pub fn multistatus(
+    responses: &[Response],
+    missing_hrefs: &[String],
+    sync_token: Option<&str>,
+) -> String {
+    let mut xml = String::from(r#"<?xml version="1.0" encoding="utf-8"?>"#);
+    xml.push_str("<d:multistatus");
+    for (namespace, prefix) in PREFIXES {
+        xml.push_str(&format!(r#" xmlns:{prefix}="{namespace}""#));
+    }
+    xml.push('>');
+    for response in responses {
+        xml.push_str("<d:response>");
+        xml.push_str(&href(&response.href));
+        if !response.found.is_empty() {
+            propstat(&mut xml, &response.found, StatusCode::OK);
+        }
+        if !response.missing.is_empty() {
+            let missing = response
+                .missing
+                .iter()
+                .map(|name| (name.clone(), String::new()))
+                .collect::<Vec<_>>();
+            propstat(&mut xml, &missing, StatusCode::NOT_FOUND);
+        }
+        xml.push_str("</d:response>");
+    }
+    for missing_href in missing_hrefs {
+        xml.push_str("<d:response>");
+        xml.push_str(&href(missing_href));
+        xml.push_str(&status(StatusCode::NOT_FOUND));
+        xml.push_str("</d:response>");
+    }
+    if let Some(sync_token) = sync_token {
+        xml.push_str(&format!(
+            "<d:sync-token>{}</d:sync-token>",
+            escape(sync_token)
+        ));
+    }
+    xml.push_str("</d:multistatus>");
+
+    xml
+}
+
+fn propstat(
+    xml: &mut String,
+    properties: &[(PropertyName, String)],
+    code: StatusCode,
+) {
+    xml.push_str("<d:propstat><d:prop>");
+    for (name, content) in properties {
+        xml.push_str(&element(name, content));
+    }
+    xml.push_str("</d:prop>");
+    xml.push_str(&status(code));
+    xml.push_str("</d:propstat>");
+}
+
+fn status(code: StatusCode) -> String {
+    format!("<d:status>HTTP/1.1 {code}</d:status>")
+}
+
+/// Writes an element, with a namespace of its own if it is not one of the
+/// multistatus.
+pub fn element(name: &PropertyName, content: &str) -> String {
+    let PropertyName { namespace, name } = name;
+    let (tag, declaration) =
+        match PREFIXES.iter().find(|(known, _)| known == namespace) {
+            Some((_, prefix)) => (format!("{prefix}:{name}"), String::new()),
+            None => (
+                format!("x:{name}"),
+                format!(r#" xmlns:x="{}""#, escape(namespace)),
+            ),
+        };
+
+    if content.is_empty() {
+        format!("<{tag}{declaration}/>")
+    } else {
+        format!("<{tag}{declaration}>{content}</{tag}>")
+    }
+}
+
+pub fn href(href: &str) -> String {
+    format!("<d:href>{}</d:href>", escape(href))
+}
+
+/// Writes an error with the precondition that failed.
+pub fn error(namespace: &str, condition: &str) -> String {
+    let condition = element(&PropertyName::new(namespace, condition), "");
+
+    format!(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>\
+         <d:error xmlns:d=\"{DAV}\" xmlns:c=\"{CALDAV}\">{condition}</d:error>"
+    )
+}
+
+/// Escapes text for the content or an attribute of an element.
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for character in text.chars() {
+        match character {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            character => escaped.push(character),
+        }
+    }
+
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn propfinds_ask_for_named_or_all_properties() {
+        let propfind = r#"<?xml version="1.0"?>
+            <propfind xmlns="DAV:" xmlns:C="urn:ietf:params:xml:ns:caldav">
+                <prop><resourcetype/><C:calendar-home-set/></prop>
+            </propfind>"#;
+
+        assert_eq!(
+            parse_propfind(propfind),
+            Ok(Properties::Named(vec![
+                PropertyName::new(DAV, "resourcetype"),
+                PropertyName::new(CALDAV, "calendar-home-set"),
+            ]))
+        );
+        assert_eq!(parse_propfind(""), Ok(Properties::All));
+        assert_eq!(
+            parse_propfind(
+                r#"<d:propfind xmlns:d="DAV:"><d:allprop/></d:propfind>"#
+            ),
+            Ok(Properties::All)
+        );
+        assert!(parse_propfind("<propfind>").is_err());
+        assert!(parse_propfind(r#"<prop xmlns="DAV:"/>"#).is_err());
+    }
+
+    #[test]
+    fn reports_are_parsed() {
+        let query = r#"<c:calendar-query xmlns:d="DAV:"
+                xmlns:c="urn:ietf:params:xml:ns:caldav">
+                <d:prop><d:getetag/></d:prop>
+                <c:filter><c:comp-filter name="VCALENDAR">
+                    <c:comp-filter name="VEVENT"/>
+                </c:comp-filter></c:filter>
+            </c:calendar-query>"#;
+        let multiget = r#"<c:calendar-multiget xmlns:d="DAV:"
+                xmlns:c="urn:ietf:params:xml:ns:caldav">
+                <d:prop><c:calendar-data/></d:prop>
+                <d:href>/dav/calendars/todos/a.ics</d:href>
+                <d:href> /dav/calendars/todos/b.ics </d:href>
+            </c:calendar-multiget>"#;
+        let sync = r#"<sync-collection xmlns="DAV:">
+                <sync-token/><sync-level>1</sync-level>
+                <prop><getetag/></prop>
+            </sync-collection>"#;
+
+        assert_eq!(
+            parse_report(query),
+            Ok(Report::CalendarQuery {
+                properties: Properties::Named(vec![PropertyName::new(
+                    DAV, "getetag"
+                )]),
+                todos: false,
+            })
+        );
+        assert_eq!(
+            parse_report(&query.replace("VEVENT", "VTODO")),
+            Ok(Report::CalendarQuery {
+                properties: Properties::Named(vec![PropertyName::new(
+                    DAV, "getetag"
+                )]),
+                todos: true,
+            })
+        );
+        assert_eq!(
+            parse_report(multiget),
+            Ok(Report::CalendarMultiget {
+                properties: Properties::Named(vec![PropertyName::new(
+                    CALDAV,
+                    "calendar-data"
+                )]),
+                hrefs: vec![
+                    "/dav/calendars/todos/a.ics".to_string(),
+                    "/dav/calendars/todos/b.ics".to_string(),
+                ],
+            })
+        );
+        assert_eq!(
+            parse_report(sync),
+            Ok(Report::SyncCollection {
+                properties: Properties::Named(vec![PropertyName::new(
+                    DAV, "getetag"
+                )]),
+                sync_token: None,
+            })
+        );
+        assert!(parse_report(r#"<expand-property xmlns="DAV:"/>"#).is_err());
+    }
+
+    #[test]
+    fn multistatus_lists_found_and_missing_properties() {
+        let response = Response {
+            href: "/dav/calendars/todos/a&b.ics".to_string(),
+            found: vec![(PropertyName::new(DAV, "getetag"), escape("\"1\""))],
+            missing: vec![PropertyName::new("urn:other", "color")],
+        };
+
+        assert_eq!(
+            multistatus(
+                &[response],
+                &["/dav/c.ics".to_string()],
+                Some("urn:1")
+            ),
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?>\
+             <d:multistatus xmlns:d=\"DAV:\" \
+             xmlns:c=\"urn:ietf:params:xml:ns:caldav\" \
+             xmlns:cs=\"http://calendarserver.org/ns/\">\
+             <d:response><d:href>/dav/calendars/todos/a&amp;b.ics</d:href>\
+             <d:propstat><d:prop><d:getetag>&quot;1&quot;</d:getetag></d:prop>\
+             <d:status>HTTP/1.1 200 OK</d:status></d:propstat>\
+             <d:propstat><d:prop><x:color xmlns:x=\"urn:other\"/></d:prop>\
+             <d:status>HTTP/1.1 404 Not Found</d:status></d:propstat>\
+             </d:response>\
+             <d:response><d:href>/dav/c.ics</d:href>\
+             <d:status>HTTP/1.1 404 Not Found</d:status></d:response>\
+             <d:sync-token>urn:1</d:sync-token>\
+             </d:multistatus>"
+        );
+    }
+}
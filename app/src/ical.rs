@@ -0,0 +1,343 @@
+//! Todos as iCalendar (RFC 5545) VTODO components, which calendar apps show
+//! next to their events.
+
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use ical::{parser::ical::component::IcalTodo, property::Property};
+use shared::models::todo::Todo;
+
+/// Content type of iCalendar files.
+pub const CONTENT_TYPE: &str = "text/calendar; charset=utf-8";
+
+const PRODUCT_ID: &str = "-//lentos//lentos//EN";
+/// Octets of a content line, longer ones are folded.
+const MAX_LINE_LENGTH: usize = 75;
+const DATE_TIME_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+
+/// A todo with what it needs to become a VTODO component.
+#[derive(Debug, Clone, Copy)]
+pub struct VTodo<'a> {
+    /// Identifies the todo for calendar apps and when it is imported again.
+    pub uid: &'a str,
+    pub todo: &'a Todo,
+    /// The name of the list of the todo, personal todos have no category.
+    pub category: Option<&'a str>,
+}
+
+/// A VTODO component of an imported file.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ImportedVTodo {
+    pub uid: Option<String>,
+    pub summary: String,
+    pub description: String,
+    pub is_done: bool,
+    pub due_at: Option<DateTime<Utc>>,
+    pub categories: Vec<String>,
+}
+
+/// Serializes the todos to a calendar with a VTODO component for each.
+pub fn calendar<'a>(todos: impl IntoIterator<Item = VTodo<'a>>) -> String {
+    let mut calendar = String::new();
+    line(&mut calendar, "BEGIN:VCALENDAR");
+    line(&mut calendar, "VERSION:2.0");
+    line(&mut calendar, &format!("PRODID:{PRODUCT_ID}"));
+    for todo in todos {
+        vtodo(&mut calendar, todo);
+    }
+    line(&mut calendar, "END:VCALENDAR");
+
+    calendar
+}
+
+fn vtodo(calendar: &mut String, VTodo { uid, todo, category }: VTodo) {
+    line(calendar, "BEGIN:VTODO");
+    line(calendar, &format!("UID:{}", escape(uid)));
+    line(calendar, &format!("DTSTAMP:{}", date_time(todo.updated_at)));
+    line(calendar, &format!("CREATED:{}", date_time(todo.created_at)));
+    line(calendar, &format!("LAST-MODIFIED:{}", date_time(todo.updated_at)));
+    line(calendar, &format!("SUMMARY:{}", escape(&todo.title)));
+    if !todo.description.is_empty() {
+        line(calendar, &format!("DESCRIPTION:{}", escape(&todo.description)));
+    }
+    if todo.is_done {
+        line(calendar, "STATUS:COMPLETED");
+        // todos that were done before the completion was recorded were last
+        // changed when they were done at the latest
+        let completed_at = todo.completed_at.unwrap_or(todo.updated_at);
+        line(calendar, &format!("COMPLETED:{}", date_time(completed_at)));
+    } else {
+        line(calendar, "STATUS:NEEDS-ACTION");
+    }
+    if let Some(due_at) = todo.due_at {
+        line(calendar, &format!("DUE:{}", date_time(due_at)));
+    }
+    if let Some(category) = category {
+        line(calendar, &format!("CATEGORIES:{}", escape(category)));
+    }
+    line(calendar, "END:VTODO");
+}
+
+/// Appends a content line, folded after every 75 octets without splitting a
+/// character.
+fn line(calendar: &mut String, content: &str) {
+    let mut length = 0;
+    for character in content.chars() {
+        if length + character.len_utf8() > MAX_LINE_LENGTH {
+            calendar.push_str("\r\n ");
+            // the space counts towards the length of the continuation
+            length = 1;
+        }
+        calendar.push(character);
+        length += character.len_utf8();
+    }
+    calendar.push_str("\r\n");
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for character in text.chars() {
+        match character {
+            '\\' => escaped.push_str("\\\\"),
+            ';' => escaped.push_str("\\;"),
+            ',' => escaped.push_str("\\,"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => {}
+            character => escaped.push(character),
+        }
+    }
+
+    escaped
+}
+
+fn date_time(at: DateTime<Utc>) -> String {
+    at.format(DATE_TIME_FORMAT).to_string()
+}
+
+/// Reads the VTODO components of all calendars in the file, a component fails
+/// on its own if one of its properties is malformed.
+pub fn parse(
+    content: &str,
+) -> Result<Vec<Result<ImportedVTodo, String>>, String> {
+    let mut todos = Vec::new();
+    for calendar in ical::IcalParser::new(content.as_bytes()) {
+        let calendar =
+            calendar.map_err(|e| format!("Invalid iCalendar file: {e}"))?;
+        todos.extend(calendar.todos.iter().map(imported_vtodo));
+    }
+
+    Ok(todos)
+}
+
+fn imported_vtodo(vtodo: &IcalTodo) -> Result<ImportedVTodo, String> {
+    let mut todo = ImportedVTodo::default();
+    let mut status = None;
+    let mut completed = false;
+    for property in &vtodo.properties {
+        let value = property.value.as_deref().unwrap_or_default();
+        match property.name.to_uppercase().as_str() {
+            "UID" => todo.uid = Some(unescape(value)),
+            "SUMMARY" => todo.summary = unescape(value),
+            "DESCRIPTION" => todo.description = unescape(value),
+            "STATUS" => status = Some(value.to_uppercase()),
+            "COMPLETED" => completed = true,
+            "DUE" => todo.due_at = Some(parse_date_time(property)?),
+            "CATEGORIES" => todo.categories.extend(
+                split_list(value)
+                    .iter()
+                    .map(|category| unescape(category))
+                    .filter(|category| !category.is_empty()),
+            ),
+            _ => {}
+        }
+    }
+    todo.is_done = match status.as_deref() {
+        Some("COMPLETED") => true,
+        Some(_) => false,
+        None => completed,
+    };
+
+    Ok(todo)
+}
+
+/// Reads a DATE or DATE-TIME value, local times are taken as UTC.
+fn parse_date_time(property: &Property) -> Result<DateTime<Utc>, String> {
+    let value = property.value.as_deref().unwrap_or_default().trim();
+    let invalid = || format!("`{value}` is no date of {}.", property.name);
+    if value.len() == 8 {
+        let date = NaiveDate::parse_from_str(value, "%Y%m%d")
+            .map_err(|_| invalid())?;
+        return Ok(date.and_time(Default::default()).and_utc());
+    }
+
+    NaiveDateTime::parse_from_str(value.trim_end_matches('Z'), "%Y%m%dT%H%M%S")
+        .map(|at| at.and_utc())
+        .map_err(|_| invalid())
+}
+
+/// Splits a list value at the commas that are not escaped.
+fn split_list(value: &str) -> Vec<&str> {
+    let mut values = Vec::new();
+    let mut start = 0;
+    let mut escaped = false;
+    for (index, character) in value.char_indices() {
+        match character {
+            '\\' if !escaped => escaped = true,
+            ',' if !escaped => {
+                values.push(&value[start..index]);
+                start = index + 1;
+            }
+            _ => escaped = false,
+        }
+    }
+    values.push(&value[start..]);
+
+    values
+}
+
+fn unescape(text: &str) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut characters = text.chars();
+    while let Some(character) = characters.next() {
+        if character != '\\' {
+            unescaped.push(character);
+            continue;
+        }
+        match characters.next() {
+            Some('n' | 'N') => unescaped.push('\n'),
+            Some(character) => unescaped.push(character),
+            None => unescaped.push('\\'),
+        }
+    }
+
+    unescaped
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn todo() -> Todo {
+        Todo {
+            id: 7,
+            title: "Buy milk, eggs; butter".to_string(),
+            description: "From the market\nnot the shop".to_string(),
+            created_at: Utc.with_ymd_and_hms(2024, 1, 2, 9, 30, 0).unwrap(),
+            updated_at: Utc.with_ymd_and_hms(2024, 1, 3, 18, 0, 0).unwrap(),
+            due_at: Utc.with_ymd_and_hms(2024, 1, 5, 12, 0, 0).single(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn todos_are_serialized_as_vtodos() {
+        let todo = todo();
+        let calendar = calendar([VTodo {
+            uid: "lentos:7",
+            todo: &todo,
+            category: Some("Groceries"),
+        }]);
+
+        assert_eq!(
+            calendar,
+            "BEGIN:VCALENDAR\r\n\
+             VERSION:2.0\r\n\
+             PRODID:-//lentos//lentos//EN\r\n\
+             BEGIN:VTODO\r\n\
+             UID:lentos:7\r\n\
+             DTSTAMP:20240103T180000Z\r\n\
+             CREATED:20240102T093000Z\r\n\
+             LAST-MODIFIED:20240103T180000Z\r\n\
+             SUMMARY:Buy milk\\, eggs\\; butter\r\n\
+             DESCRIPTION:From the market\\nnot the shop\r\n\
+             STATUS:NEEDS-ACTION\r\n\
+             DUE:20240105T120000Z\r\n\
+             CATEGORIES:Groceries\r\n\
+             END:VTODO\r\n\
+             END:VCALENDAR\r\n"
+        );
+    }
+
+    #[test]
+    fn long_lines_are_folded_between_characters() {
+        let todo = Todo { title: "ä".repeat(40), ..todo() };
+        let calendar =
+            calendar([VTodo { uid: "lentos:7", todo: &todo, category: None }]);
+
+        assert!(calendar.split("\r\n").all(|line| line.len() <= 75));
+        assert!(calendar.contains(&format!(
+            "SUMMARY:{}\r\n {}\r\n",
+            "ä".repeat(33),
+            "ä".repeat(7)
+        )));
+        assert_eq!(
+            parse(&calendar).unwrap()[0].as_ref().unwrap().summary,
+            todo.title
+        );
+    }
+
+    #[test]
+    fn vtodos_are_parsed_back() {
+        let todo = Todo {
+            is_done: true,
+            completed_at: Utc.with_ymd_and_hms(2024, 1, 4, 8, 0, 0).single(),
+            ..todo()
+        };
+        let calendar = calendar([VTodo {
+            uid: "lentos:7",
+            todo: &todo,
+            category: Some("Home, Garden"),
+        }]);
+
+        assert_eq!(
+            parse(&calendar).unwrap(),
+            vec![Ok(ImportedVTodo {
+                uid: Some("lentos:7".to_string()),
+                summary: todo.title.clone(),
+                description: todo.description.clone(),
+                is_done: true,
+                due_at: todo.due_at,
+                categories: vec!["Home, Garden".to_string()],
+            })]
+        );
+    }
+
+    #[test]
+    fn vtodos_of_other_apps_are_parsed() {
+        let calendar = "BEGIN:VCALENDAR\r\n\
+                        VERSION:2.0\r\n\
+                        PRODID:-//Other//App//EN\r\n\
+                        BEGIN:VEVENT\r\n\
+                        UID:event\r\n\
+                        SUMMARY:Not a todo\r\n\
+                        END:VEVENT\r\n\
+                        BEGIN:VTODO\r\n\
+                        UID:a\r\n\
+                        SUMMARY:Call\r\n  mom\r\n\
+                        DUE;VALUE=DATE:20240105\r\n\
+                        COMPLETED:20240104T080000Z\r\n\
+                        CATEGORIES:Family,Phone\r\n\
+                        END:VTODO\r\n\
+                        BEGIN:VTODO\r\n\
+                        UID:b\r\n\
+                        DUE;TZID=Europe/Berlin:tomorrow\r\n\
+                        END:VTODO\r\n\
+                        END:VCALENDAR\r\n";
+
+        assert_eq!(
+            parse(calendar).unwrap(),
+            vec![
+                Ok(ImportedVTodo {
+                    uid: Some("a".to_string()),
+                    summary: "Call mom".to_string(),
+                    is_done: true,
+                    due_at: Utc.with_ymd_and_hms(2024, 1, 5, 0, 0, 0).single(),
+                    categories: vec!["Family".to_string(), "Phone".to_string()],
+                    ..Default::default()
+                }),
+                Err("`tomorrow` is no date of DUE.".to_string()),
+            ]
+        );
+        assert!(parse("SUMMARY:No calendar").is_err());
+    }
+}
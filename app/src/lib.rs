@@ -1,6 +1,13 @@
 #![feature(try_trait_v2)]
 #![feature(type_alias_impl_trait)]
 
+pub mod blob;
 pub mod controllers;
+pub mod ical;
+pub mod mail;
 pub mod repository;
+pub mod seed;
+pub mod server;
+#[cfg(feature = "test-support")]
+pub mod test_support;
 pub mod util;
@@ -0,0 +1,296 @@
+use std::{
+    fs::OpenOptions, future::Future, io::Write, path::PathBuf, sync::Mutex,
+};
+
+use actix_web::web;
+use chrono::Utc;
+use color_eyre::eyre::{self, WrapErr};
+use lettre::{
+    message::{header::ContentType, Mailbox},
+    transport::smtp::authentication::Credentials,
+    AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
+};
+
+use crate::util::{error::Error, error_or::ErrorOr};
+
+/// A plain text email to a single recipient.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mail {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+/// Delivers emails to users, e.g. tokens or notices that must not be part
+/// of an api response.
+#[async_trait::async_trait]
+pub trait Mailer: Send + Sync + 'static {
+    async fn send(&self, mail: Mail) -> ErrorOr<()>;
+}
+
+/// Runs `send` in the background, so that the response neither waits for
+/// the delivery nor takes longer than one without a mail.
+pub fn send_in_background(send: impl Future<Output = ErrorOr<()>> + 'static) {
+    actix_web::rt::spawn(async move {
+        if let Err(error) = send.await.0 {
+            tracing::error!("Failed to send a mail: {:?}", error);
+        }
+    });
+}
+
+/// How the connection to the SMTP server is secured.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SmtpTls {
+    /// Plain text, only for servers on the same host like a local stand-in.
+    None,
+    /// Upgrades a plain text connection, usually on port 587.
+    StartTls,
+    /// TLS from the start, usually on port 465.
+    Tls,
+}
+
+#[derive(Debug, Clone)]
+pub struct SmtpSettings {
+    pub host: String,
+    pub port: u16,
+    pub tls: SmtpTls,
+    /// Username and password, if the server requires a login.
+    pub credentials: Option<(String, String)>,
+}
+
+/// Delivers mails to an SMTP server.
+pub struct SmtpMailer {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+}
+
+impl SmtpMailer {
+    /// Creates a mailer that sends as `from`, e.g.
+    /// `lentos <noreply@example.com>`. Connects lazily on the first mail.
+    pub fn new(settings: &SmtpSettings, from: &str) -> eyre::Result<Self> {
+        let from = from.parse().wrap_err("Invalid sender address")?;
+        let builder = match settings.tls {
+            SmtpTls::None => {
+                AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(
+                    &settings.host,
+                )
+            }
+            SmtpTls::StartTls => {
+                AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(
+                    &settings.host,
+                )?
+            }
+            SmtpTls::Tls => {
+                AsyncSmtpTransport::<Tokio1Executor>::relay(&settings.host)?
+            }
+        };
+        let builder = match &settings.credentials {
+            Some((username, password)) => builder.credentials(
+                Credentials::new(username.clone(), password.clone()),
+            ),
+            None => builder,
+        };
+
+        Ok(Self { transport: builder.port(settings.port).build(), from })
+    }
+}
+
+#[async_trait::async_trait]
+impl Mailer for SmtpMailer {
+    async fn send(&self, mail: Mail) -> ErrorOr<()> {
+        let to: Mailbox = mail
+            .to
+            .parse()
+            .wrap_err_with(|| format!("Invalid recipient `{}`", mail.to))?;
+        let message = Message::builder()
+            .from(self.from.clone())
+            .to(to)
+            .subject(mail.subject)
+            .header(ContentType::TEXT_PLAIN)
+            .body(mail.body)
+            .map_err(|e| Error::Internal(e.into()))?;
+
+        self.transport
+            .send(message)
+            .await
+            .map_err(|e| Error::Internal(e.into()))?;
+
+        ().into()
+    }
+}
+
+/// Appends every mail to a file or prints it to stdout instead of delivering
+/// it, for development.
+pub struct FileMailer {
+    from: String,
+    path: Option<PathBuf>,
+}
+
+impl FileMailer {
+    /// Appends the mails to the file at `path`, creating it if necessary.
+    pub fn new(from: &str, path: PathBuf) -> Self {
+        Self { from: from.to_string(), path: Some(path) }
+    }
+
+    pub fn stdout(from: &str) -> Self {
+        Self { from: from.to_string(), path: None }
+    }
+
+    fn format(&self, mail: &Mail) -> String {
+        format!(
+            "Date: {}\nFrom: {}\nTo: {}\nSubject: {}\n\n{}\n\n",
+            Utc::now().to_rfc2822(),
+            self.from,
+            mail.to,
+            mail.subject,
+            mail.body.trim_end()
+        )
+    }
+}
+
+#[async_trait::async_trait]
+impl Mailer for FileMailer {
+    async fn send(&self, mail: Mail) -> ErrorOr<()> {
+        let mail = self.format(&mail);
+        let path = self.path.clone();
+
+        // a single write, so that concurrent mails do not interleave
+        web::block(move || match path {
+            Some(path) => OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)?
+                .write_all(mail.as_bytes()),
+            None => std::io::stdout().lock().write_all(mail.as_bytes()),
+        })
+        .await
+        .map_err(|e| Error::Internal(e.into()))?
+        .wrap_err("Failed to write a mail")?;
+
+        ().into()
+    }
+}
+
+/// Keeps all mails in memory, for tests.
+#[derive(Default)]
+pub struct MemoryMailer {
+    mails: Mutex<Vec<Mail>>,
+}
+
+impl MemoryMailer {
+    /// Returns the mails sent so far.
+    pub fn mails(&self) -> Vec<Mail> {
+        self.mails.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+}
+
+#[async_trait::async_trait]
+impl Mailer for MemoryMailer {
+    async fn send(&self, mail: Mail) -> ErrorOr<()> {
+        self.mails.lock().unwrap_or_else(|e| e.into_inner()).push(mail);
+
+        ().into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{BufRead, BufReader},
+        net::TcpListener,
+        thread,
+    };
+
+    use super::*;
+
+    fn mail() -> Mail {
+        Mail {
+            to: "jane@example.com".to_string(),
+            subject: "Hello".to_string(),
+            body: "Hi Jane".to_string(),
+        }
+    }
+
+    /// Accepts a single mail like an SMTP server and returns the commands
+    /// and data the client sent.
+    fn smtp_stand_in(listener: TcpListener) -> thread::JoinHandle<String> {
+        thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut writer = stream.try_clone().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut received = String::new();
+            let mut in_data = false;
+            writer.write_all(b"220 localhost ready\r\n").unwrap();
+
+            let mut line = String::new();
+            while reader.read_line(&mut line).unwrap() > 0 {
+                received.push_str(&line);
+                let reply: &[u8] = if in_data {
+                    if line != ".\r\n" {
+                        line.clear();
+                        continue;
+                    }
+                    in_data = false;
+                    b"250 queued\r\n"
+                } else if line.starts_with("EHLO") {
+                    b"250 localhost\r\n"
+                } else if line.starts_with("DATA") {
+                    in_data = true;
+                    b"354 go ahead\r\n"
+                } else if line.starts_with("QUIT") {
+                    writer.write_all(b"221 bye\r\n").unwrap();
+                    break;
+                } else {
+                    b"250 ok\r\n"
+                };
+                writer.write_all(reply).unwrap();
+                line.clear();
+            }
+
+            received
+        })
+    }
+
+    #[actix_rt::test]
+    async fn smtp_mailer_delivers_to_the_server() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = smtp_stand_in(listener);
+        let settings = SmtpSettings {
+            host: "127.0.0.1".to_string(),
+            port,
+            tls: SmtpTls::None,
+            credentials: None,
+        };
+        let mailer =
+            SmtpMailer::new(&settings, "lentos <noreply@example.com>").unwrap();
+
+        mailer.send(mail()).await.0.unwrap();
+        drop(mailer);
+
+        // the stand-in blocks, it must not be joined on the runtime thread
+        let received = actix_rt::task::spawn_blocking(move || server.join())
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(received.contains("MAIL FROM:<noreply@example.com>"));
+        assert!(received.contains("RCPT TO:<jane@example.com>"));
+        assert!(received.contains("Subject: Hello\r\n"));
+        assert!(received.contains("Hi Jane"));
+    }
+
+    #[actix_rt::test]
+    async fn file_mailer_appends_to_the_file() {
+        let path = std::env::temp_dir()
+            .join(format!("lentos-mails-{}.txt", std::process::id()));
+        let mailer = FileMailer::new("noreply@example.com", path.clone());
+
+        mailer.send(mail()).await.0.unwrap();
+        mailer.send(mail()).await.0.unwrap();
+
+        let mails = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(mails.matches("To: jane@example.com\n").count(), 2);
+        assert!(mails.contains("Subject: Hello\n\nHi Jane\n\n"));
+    }
+}
@@ -0,0 +1,224 @@
+use chrono::{DateTime, Utc};
+use shared::models::user::{AccessTokenSummary, TokenScope};
+use sqlx::types::Json;
+
+use super::error::RepositoryError;
+use crate::util::error_or::ErrorOr;
+
+pub(crate) const RELATION: &str = "AccessToken";
+
+/// A personal access token, only its hash is stored.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct AccessToken {
+    pub id: i64,
+    pub user_id: i64,
+    pub name: String,
+    pub scopes: Json<Vec<TokenScope>>,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub last_used_at: Option<DateTime<Utc>>,
+}
+
+impl From<AccessToken> for AccessTokenSummary {
+    fn from(token: AccessToken) -> Self {
+        Self {
+            id: token.id,
+            name: token.name,
+            scopes: token.scopes.0,
+            created_at: token.created_at,
+            expires_at: token.expires_at,
+            last_used_at: token.last_used_at,
+        }
+    }
+}
+
+/// Stores the personal access tokens of the users.
+#[async_trait::async_trait]
+pub trait AccessTokenRepository: Send + Sync + 'static {
+    /// Returns the tokens of a user, including expired ones, oldest first.
+    async fn get_access_tokens(
+        &self,
+        user_id: &i64,
+    ) -> ErrorOr<Vec<AccessToken>>;
+
+    async fn create_access_token(
+        &self,
+        user_id: &i64,
+        token_hash: &str,
+        name: &str,
+        scopes: &[TokenScope],
+        expires_at: Option<DateTime<Utc>>,
+    ) -> ErrorOr<AccessToken>;
+
+    /// Returns the unexpired token with `token_hash` and records that it was
+    /// used.
+    async fn use_access_token(
+        &self,
+        token_hash: &str,
+    ) -> ErrorOr<Option<AccessToken>>;
+
+    /// Fails with `NotFound` unless the token belongs to the user.
+    async fn delete_access_token(
+        &self,
+        token_id: &i64,
+        user_id: &i64,
+    ) -> ErrorOr<()>;
+
+    /// Deletes all tokens of the user and returns how many there were, e.g.
+    /// when their password is reset.
+    async fn delete_user_access_tokens(&self, user_id: &i64) -> ErrorOr<u64>;
+}
+
+pub struct PostgresAccessTokenRepository {
+    pool: sqlx::PgPool,
+}
+
+impl PostgresAccessTokenRepository {
+    pub fn new(pool: sqlx::PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl AccessTokenRepository for PostgresAccessTokenRepository {
+    async fn get_access_tokens(
+        &self,
+        user_id: &i64,
+    ) -> ErrorOr<Vec<AccessToken>> {
+        let db_response = sqlx::query_as!(
+            AccessToken,
+            r#"
+            SELECT
+                id,
+                user_id,
+                name,
+                scopes AS "scopes: Json<Vec<TokenScope>>",
+                created_at,
+                expires_at,
+                last_used_at
+            FROM access_tokens
+            WHERE user_id = $1
+            ORDER BY id
+            "#,
+            user_id
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        db_response.into()
+    }
+
+    async fn create_access_token(
+        &self,
+        user_id: &i64,
+        token_hash: &str,
+        name: &str,
+        scopes: &[TokenScope],
+        expires_at: Option<DateTime<Utc>>,
+    ) -> ErrorOr<AccessToken> {
+        let db_response = sqlx::query_as!(
+            AccessToken,
+            r#"
+            INSERT
+            INTO access_tokens (user_id, token_hash, name, scopes, expires_at)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING
+                id,
+                user_id,
+                name,
+                scopes AS "scopes: Json<Vec<TokenScope>>",
+                created_at,
+                expires_at,
+                last_used_at
+            "#,
+            user_id,
+            token_hash,
+            name,
+            Json(scopes) as _,
+            expires_at
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        db_response.into()
+    }
+
+    async fn use_access_token(
+        &self,
+        token_hash: &str,
+    ) -> ErrorOr<Option<AccessToken>> {
+        let db_response = sqlx::query_as!(
+            AccessToken,
+            r#"
+            UPDATE access_tokens
+            SET last_used_at = now()
+            WHERE token_hash = $1
+                AND (expires_at IS NULL OR expires_at > now())
+            RETURNING
+                id,
+                user_id,
+                name,
+                scopes AS "scopes: Json<Vec<TokenScope>>",
+                created_at,
+                expires_at,
+                last_used_at
+            "#,
+            token_hash
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        db_response.into()
+    }
+
+    async fn delete_access_token(
+        &self,
+        token_id: &i64,
+        user_id: &i64,
+    ) -> ErrorOr<()> {
+        let db_response = sqlx::query!(
+            r#"
+            DELETE
+            FROM access_tokens
+            WHERE id = $1 AND user_id = $2
+            "#,
+            token_id,
+            user_id
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        if db_response.rows_affected() == 0 {
+            Err(RepositoryError::NotFound {
+                relation_name: RELATION.to_string(),
+            })?;
+        }
+
+        ().into()
+    }
+
+    async fn delete_user_access_tokens(&self, user_id: &i64) -> ErrorOr<u64> {
+        let db_response = sqlx::query!(
+            r#"
+            DELETE
+            FROM access_tokens
+            WHERE user_id = $1
+            "#,
+            user_id
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        db_response.rows_affected().into()
+    }
+}
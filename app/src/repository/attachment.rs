@@ -0,0 +1,256 @@
+use shared::models::attachment::Attachment;
+
+use super::error::RepositoryError;
+use crate::util::error_or::ErrorOr;
+
+pub(crate) const RELATION: &str = "Attachment";
+
+/// The metadata of an upload whose content is already in the blob store.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NewAttachment {
+    pub todo_id: i64,
+    pub uploader_id: i64,
+    pub file_name: String,
+    pub size: i64,
+    pub content_type: String,
+    pub checksum: String,
+    pub storage_key: String,
+}
+
+/// Stores the metadata of the files attached to todos.
+///
+/// Who may see or change the todo is up to the controllers. Deleting an
+/// attachment, also along with its todo, leaves its content behind as an
+/// orphaned blob until it is removed from the blob store.
+#[async_trait::async_trait]
+pub trait AttachmentRepository: Send + Sync + 'static {
+    /// Returns the attachments of a todo, oldest first.
+    async fn get_attachments(&self, todo_id: &i64) -> ErrorOr<Vec<Attachment>>;
+
+    /// Fails with `NotFound` unless the attachment is on the todo.
+    async fn get_attachment(
+        &self,
+        todo_id: &i64,
+        attachment_id: &i64,
+    ) -> ErrorOr<Attachment>;
+
+    /// Stores the attachment unless the attachments the uploader uploaded
+    /// would take up more than `quota` bytes with it, which returns `None`.
+    async fn create_attachment(
+        &self,
+        attachment: &NewAttachment,
+        quota: i64,
+    ) -> ErrorOr<Option<Attachment>>;
+
+    async fn delete_attachment(&self, attachment_id: &i64) -> ErrorOr<()>;
+
+    /// Returns the bytes taken up by the attachments the user uploaded.
+    async fn get_usage(&self, user_id: &i64) -> ErrorOr<i64>;
+
+    /// Returns the storage keys of up to `limit` orphaned blobs.
+    async fn get_orphaned_blobs(&self, limit: i64) -> ErrorOr<Vec<String>>;
+
+    /// Forgets an orphaned blob once it was removed from the blob store.
+    async fn forget_orphaned_blob(&self, storage_key: &str) -> ErrorOr<()>;
+}
+
+pub struct PostgresAttachmentRepository {
+    pool: sqlx::PgPool,
+}
+
+impl PostgresAttachmentRepository {
+    pub fn new(pool: sqlx::PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl AttachmentRepository for PostgresAttachmentRepository {
+    async fn get_attachments(&self, todo_id: &i64) -> ErrorOr<Vec<Attachment>> {
+        let db_response = sqlx::query_as!(
+            Attachment,
+            r#"
+            SELECT *
+            FROM attachments
+            WHERE todo_id = $1
+            ORDER BY id
+            "#,
+            todo_id
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        db_response.into()
+    }
+
+    async fn get_attachment(
+        &self,
+        todo_id: &i64,
+        attachment_id: &i64,
+    ) -> ErrorOr<Attachment> {
+        let db_response = sqlx::query_as!(
+            Attachment,
+            r#"
+            SELECT *
+            FROM attachments
+            WHERE id = $1 AND todo_id = $2
+            "#,
+            attachment_id,
+            todo_id
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        match db_response {
+            Some(attachment) => attachment.into(),
+            None => Err(RepositoryError::NotFound {
+                relation_name: RELATION.to_string(),
+            })?,
+        }
+    }
+
+    async fn create_attachment(
+        &self,
+        attachment: &NewAttachment,
+        quota: i64,
+    ) -> ErrorOr<Option<Attachment>> {
+        let mut transaction = self
+            .pool
+            .begin()
+            .await
+            .map_err(Into::into)
+            .map_err(RepositoryError::Internal)?;
+
+        // concurrent uploads of the user wait here, so they cannot exceed
+        // the quota together
+        sqlx::query!(
+            r#"
+            SELECT id
+            FROM users
+            WHERE id = $1
+            FOR UPDATE
+            "#,
+            attachment.uploader_id
+        )
+        .fetch_optional(&mut *transaction)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        let db_response = sqlx::query_as!(
+            Attachment,
+            r#"
+            INSERT
+            INTO attachments (
+                todo_id,
+                uploader_id,
+                file_name,
+                size,
+                content_type,
+                checksum,
+                storage_key
+            )
+            SELECT $1, $2, $3, $4, $5, $6, $7
+            WHERE (
+                SELECT COALESCE(SUM(size), 0)::bigint
+                FROM attachments
+                WHERE uploader_id = $2
+            ) + $4 <= $8
+            RETURNING *
+            "#,
+            attachment.todo_id,
+            attachment.uploader_id,
+            attachment.file_name,
+            attachment.size,
+            attachment.content_type,
+            attachment.checksum,
+            attachment.storage_key,
+            quota
+        )
+        .fetch_optional(&mut *transaction)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        transaction
+            .commit()
+            .await
+            .map_err(Into::into)
+            .map_err(RepositoryError::Internal)?;
+
+        db_response.into()
+    }
+
+    async fn delete_attachment(&self, attachment_id: &i64) -> ErrorOr<()> {
+        sqlx::query!(
+            r#"
+            DELETE
+            FROM attachments
+            WHERE id = $1
+            "#,
+            attachment_id
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        ().into()
+    }
+
+    async fn get_usage(&self, user_id: &i64) -> ErrorOr<i64> {
+        let db_response = sqlx::query_scalar!(
+            r#"
+            SELECT COALESCE(SUM(size), 0)::bigint AS "usage!"
+            FROM attachments
+            WHERE uploader_id = $1
+            "#,
+            user_id
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        db_response.into()
+    }
+
+    async fn get_orphaned_blobs(&self, limit: i64) -> ErrorOr<Vec<String>> {
+        let db_response = sqlx::query_scalar!(
+            r#"
+            SELECT storage_key
+            FROM orphaned_blobs
+            ORDER BY orphaned_at
+            LIMIT $1
+            "#,
+            limit
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        db_response.into()
+    }
+
+    async fn forget_orphaned_blob(&self, storage_key: &str) -> ErrorOr<()> {
+        sqlx::query!(
+            r#"
+            DELETE
+            FROM orphaned_blobs
+            WHERE storage_key = $1
+            "#,
+            storage_key
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        ().into()
+    }
+}
@@ -0,0 +1,259 @@
+use serde::Serialize;
+use shared::models::audit::{
+    AuditAction, AuditEntity, AuditEvent, SecurityEvent, SecurityEventKind,
+};
+
+use super::error::RepositoryError;
+use crate::util::error_or::ErrorOr;
+
+/// Fields that change with every write or must never be recorded.
+const UNRECORDED_FIELDS: &[&str] = &["password", "updated_at"];
+
+/// Who made a change and in which request, recorded with its audit event.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AuditContext {
+    /// Unset for changes made by the server, e.g. by the admin commands.
+    pub actor_id: Option<i64>,
+    pub request_id: Option<String>,
+}
+
+/// An entry for the security stream, see [`SecurityEvent`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NewSecurityEvent {
+    pub kind: SecurityEventKind,
+    pub user_id: Option<i64>,
+    pub actor_id: Option<i64>,
+    pub ip: Option<String>,
+    pub request_id: Option<String>,
+}
+
+/// Returns the fields that differ between two versions of a record with
+/// their values before and after. `None` is a record that does not exist
+/// yet or anymore.
+pub fn changes<T: Serialize>(
+    before: Option<&T>,
+    after: Option<&T>,
+) -> serde_json::Value {
+    let fields = |record: Option<&T>| match record.map(serde_json::to_value) {
+        Some(Ok(serde_json::Value::Object(fields))) => fields,
+        _ => serde_json::Map::new(),
+    };
+    let (before, after) = (fields(before), fields(after));
+
+    let mut changes = serde_json::Map::new();
+    for field in before.keys().chain(after.keys()) {
+        if UNRECORDED_FIELDS.contains(&field.as_str()) {
+            continue;
+        }
+        let (old, new) = (before.get(field), after.get(field));
+        if old != new {
+            changes.insert(
+                field.clone(),
+                serde_json::json!({ "before": old, "after": new }),
+            );
+        }
+    }
+
+    changes.into()
+}
+
+/// Reads the audit events and keeps the security stream.
+///
+/// The audit events themselves are written by the todo and user repositories
+/// in the transaction of the change. Neither kind of event is ever changed
+/// or deleted.
+#[async_trait::async_trait]
+pub trait AuditRepository: Send + Sync + 'static {
+    /// Returns a page of the events of a todo or user, oldest first.
+    async fn get_history(
+        &self,
+        entity: AuditEntity,
+        entity_id: &i64,
+        offset: i64,
+        limit: i64,
+    ) -> ErrorOr<Vec<AuditEvent>>;
+
+    /// Returns a page of the changes the user made and the changes of their
+    /// account, newest first.
+    async fn get_account_activity(
+        &self,
+        user_id: &i64,
+        offset: i64,
+        limit: i64,
+    ) -> ErrorOr<Vec<AuditEvent>>;
+
+    async fn record_security_event(
+        &self,
+        event: &NewSecurityEvent,
+    ) -> ErrorOr<()>;
+
+    /// Returns a page of the security events of a user, or of everybody if
+    /// no user is given, newest first.
+    async fn get_security_events(
+        &self,
+        user_id: Option<i64>,
+        offset: i64,
+        limit: i64,
+    ) -> ErrorOr<Vec<SecurityEvent>>;
+}
+
+pub struct PostgresAuditRepository {
+    pool: sqlx::PgPool,
+}
+
+impl PostgresAuditRepository {
+    pub fn new(pool: sqlx::PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Appends an audit event in the transaction of the change.
+    pub(crate) async fn record(
+        transaction: &mut sqlx::PgConnection,
+        audit: &AuditContext,
+        action: AuditAction,
+        entity: AuditEntity,
+        entity_id: i64,
+        changes: serde_json::Value,
+    ) -> Result<(), RepositoryError> {
+        sqlx::query!(
+            r#"
+            INSERT
+            INTO audit_events (
+                actor_id,
+                action,
+                entity,
+                entity_id,
+                changes,
+                request_id
+            )
+            VALUES ($1, $2, $3, $4, $5, $6)
+            "#,
+            audit.actor_id,
+            action.as_str(),
+            entity.as_str(),
+            entity_id,
+            changes,
+            audit.request_id
+        )
+        .execute(&mut *transaction)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl AuditRepository for PostgresAuditRepository {
+    async fn get_history(
+        &self,
+        entity: AuditEntity,
+        entity_id: &i64,
+        offset: i64,
+        limit: i64,
+    ) -> ErrorOr<Vec<AuditEvent>> {
+        let db_response = sqlx::query_as!(
+            AuditEvent,
+            r#"
+            SELECT *
+            FROM audit_events
+            WHERE entity = $1 AND entity_id = $2
+            ORDER BY id
+            OFFSET $3
+            LIMIT $4
+            "#,
+            entity.as_str(),
+            entity_id,
+            offset,
+            limit
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        db_response.into()
+    }
+
+    async fn get_account_activity(
+        &self,
+        user_id: &i64,
+        offset: i64,
+        limit: i64,
+    ) -> ErrorOr<Vec<AuditEvent>> {
+        let db_response = sqlx::query_as!(
+            AuditEvent,
+            r#"
+            SELECT *
+            FROM audit_events
+            WHERE actor_id = $1 OR (entity = $2 AND entity_id = $1)
+            ORDER BY id DESC
+            OFFSET $3
+            LIMIT $4
+            "#,
+            user_id,
+            AuditEntity::User.as_str(),
+            offset,
+            limit
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        db_response.into()
+    }
+
+    async fn record_security_event(
+        &self,
+        event: &NewSecurityEvent,
+    ) -> ErrorOr<()> {
+        sqlx::query!(
+            r#"
+            INSERT
+            INTO security_events (user_id, actor_id, kind, ip, request_id)
+            VALUES ($1, $2, $3, $4, $5)
+            "#,
+            event.user_id,
+            event.actor_id,
+            event.kind.as_str(),
+            event.ip,
+            event.request_id
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        ().into()
+    }
+
+    async fn get_security_events(
+        &self,
+        user_id: Option<i64>,
+        offset: i64,
+        limit: i64,
+    ) -> ErrorOr<Vec<SecurityEvent>> {
+        let db_response = sqlx::query_as!(
+            SecurityEvent,
+            r#"
+            SELECT *
+            FROM security_events
+            WHERE $1::bigint IS NULL OR user_id = $1
+            ORDER BY id DESC
+            OFFSET $2
+            LIMIT $3
+            "#,
+            user_id,
+            offset,
+            limit
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        db_response.into()
+    }
+}
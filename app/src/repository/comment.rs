@@ -0,0 +1,333 @@
+use shared::models::comment::{Comment, CreateComment, UpdateComment};
+
+use super::error::{Operation, RepositoryError};
+use crate::util::error_or::ErrorOr;
+
+pub(crate) const RELATION: &str = "Comment";
+
+/// Stores the comments on todos and the users mentioned in them.
+///
+/// Whether a user may see the todo, and who may be mentioned, is up to the
+/// controllers. The repository makes sure users only change their own
+/// comments.
+#[async_trait::async_trait]
+pub trait CommentRepository: Send + Sync + 'static {
+    /// Returns a page of the comments on a todo, oldest first.
+    async fn get_comments(
+        &self,
+        todo_id: &i64,
+        offset: i64,
+        limit: i64,
+    ) -> ErrorOr<Vec<Comment>>;
+
+    async fn create_comment(
+        &self,
+        todo_id: &i64,
+        create_comment: &CreateComment,
+        author_id: &i64,
+    ) -> ErrorOr<Comment>;
+
+    /// Replaces the body and the mentions. Fails with `NotFound` unless the
+    /// comment is on the todo and with `Forbidden` unless the user wrote it.
+    async fn update_comment(
+        &self,
+        todo_id: &i64,
+        comment_id: &i64,
+        update_comment: &UpdateComment,
+        author_id: &i64,
+    ) -> ErrorOr<Comment>;
+
+    /// Fails with `NotFound` unless the comment is on the todo and with
+    /// `Forbidden` unless the user wrote it.
+    async fn delete_comment(
+        &self,
+        todo_id: &i64,
+        comment_id: &i64,
+        author_id: &i64,
+    ) -> ErrorOr<()>;
+}
+
+/// Fails unless `author_id` of a comment that may not exist is the user.
+pub(crate) fn check_author(
+    author_id: Option<i64>,
+    user_id: i64,
+    operation: Operation,
+) -> Result<(), RepositoryError> {
+    match author_id {
+        Some(author_id) if author_id == user_id => Ok(()),
+        Some(_) => Err(RepositoryError::Forbidden {
+            operation,
+            relation_name: RELATION.to_string(),
+        }),
+        None => Err(RepositoryError::NotFound {
+            relation_name: RELATION.to_string(),
+        }),
+    }
+}
+
+pub struct PostgresCommentRepository {
+    pool: sqlx::PgPool,
+}
+
+impl PostgresCommentRepository {
+    pub fn new(pool: sqlx::PgPool) -> Self {
+        Self { pool }
+    }
+
+    async fn get_comment(&self, comment_id: &i64) -> ErrorOr<Comment> {
+        let db_response = sqlx::query_as!(
+            Comment,
+            r#"
+            SELECT
+                comments.id,
+                comments.todo_id,
+                comments.author_id,
+                users.name AS author_name,
+                comments.body,
+                ARRAY(
+                    SELECT user_id
+                    FROM comment_mentions
+                    WHERE comment_id = comments.id
+                    ORDER BY user_id
+                ) AS "mentions!",
+                comments.created_at,
+                comments.updated_at
+            FROM comments
+            JOIN users ON users.id = comments.author_id
+            WHERE comments.id = $1
+            "#,
+            comment_id
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        db_response.into()
+    }
+
+    /// Replaces the mentions of a comment.
+    async fn set_mentions(
+        transaction: &mut sqlx::PgConnection,
+        comment_id: i64,
+        mentions: &[i64],
+    ) -> Result<(), RepositoryError> {
+        sqlx::query!(
+            r#"
+            DELETE
+            FROM comment_mentions
+            WHERE comment_id = $1
+            "#,
+            comment_id
+        )
+        .execute(&mut *transaction)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        sqlx::query!(
+            r#"
+            INSERT
+            INTO comment_mentions (comment_id, user_id)
+            SELECT $1, user_id
+            FROM unnest($2::bigint[]) AS user_id
+            ON CONFLICT (comment_id, user_id) DO NOTHING
+            "#,
+            comment_id,
+            mentions
+        )
+        .execute(&mut *transaction)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl CommentRepository for PostgresCommentRepository {
+    async fn get_comments(
+        &self,
+        todo_id: &i64,
+        offset: i64,
+        limit: i64,
+    ) -> ErrorOr<Vec<Comment>> {
+        let db_response = sqlx::query_as!(
+            Comment,
+            r#"
+            SELECT
+                comments.id,
+                comments.todo_id,
+                comments.author_id,
+                users.name AS author_name,
+                comments.body,
+                ARRAY(
+                    SELECT user_id
+                    FROM comment_mentions
+                    WHERE comment_id = comments.id
+                    ORDER BY user_id
+                ) AS "mentions!",
+                comments.created_at,
+                comments.updated_at
+            FROM comments
+            JOIN users ON users.id = comments.author_id
+            WHERE comments.todo_id = $1
+            ORDER BY comments.id
+            OFFSET $2
+            LIMIT $3
+            "#,
+            todo_id,
+            offset,
+            limit
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        db_response.into()
+    }
+
+    async fn create_comment(
+        &self,
+        todo_id: &i64,
+        create_comment: &CreateComment,
+        author_id: &i64,
+    ) -> ErrorOr<Comment> {
+        let mut transaction = self
+            .pool
+            .begin()
+            .await
+            .map_err(Into::into)
+            .map_err(RepositoryError::Internal)?;
+
+        let comment_id = sqlx::query_scalar!(
+            r#"
+            INSERT
+            INTO comments (todo_id, author_id, body)
+            VALUES ($1, $2, $3)
+            RETURNING id
+            "#,
+            todo_id,
+            author_id,
+            &create_comment.body
+        )
+        .fetch_one(&mut *transaction)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        Self::set_mentions(
+            &mut transaction,
+            comment_id,
+            &create_comment.mentions,
+        )
+        .await?;
+
+        transaction
+            .commit()
+            .await
+            .map_err(Into::into)
+            .map_err(RepositoryError::Internal)?;
+
+        self.get_comment(&comment_id).await
+    }
+
+    async fn update_comment(
+        &self,
+        todo_id: &i64,
+        comment_id: &i64,
+        update_comment: &UpdateComment,
+        author_id: &i64,
+    ) -> ErrorOr<Comment> {
+        let mut transaction = self
+            .pool
+            .begin()
+            .await
+            .map_err(Into::into)
+            .map_err(RepositoryError::Internal)?;
+
+        let comment_author_id = sqlx::query_scalar!(
+            r#"
+            SELECT author_id
+            FROM comments
+            WHERE id = $1 AND todo_id = $2
+            FOR UPDATE
+            "#,
+            comment_id,
+            todo_id
+        )
+        .fetch_optional(&mut *transaction)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+        check_author(comment_author_id, *author_id, Operation::Update)?;
+
+        sqlx::query!(
+            r#"
+            UPDATE comments
+            SET body = $2, updated_at = now()
+            WHERE id = $1
+            "#,
+            comment_id,
+            &update_comment.body
+        )
+        .execute(&mut *transaction)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        Self::set_mentions(
+            &mut transaction,
+            *comment_id,
+            &update_comment.mentions,
+        )
+        .await?;
+
+        transaction
+            .commit()
+            .await
+            .map_err(Into::into)
+            .map_err(RepositoryError::Internal)?;
+
+        self.get_comment(comment_id).await
+    }
+
+    async fn delete_comment(
+        &self,
+        todo_id: &i64,
+        comment_id: &i64,
+        author_id: &i64,
+    ) -> ErrorOr<()> {
+        let comment_author_id = sqlx::query_scalar!(
+            r#"
+            SELECT author_id
+            FROM comments
+            WHERE id = $1 AND todo_id = $2
+            "#,
+            comment_id,
+            todo_id
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+        check_author(comment_author_id, *author_id, Operation::Delete)?;
+
+        sqlx::query!(
+            r#"
+            DELETE
+            FROM comments
+            WHERE id = $1
+            "#,
+            comment_id
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        ().into()
+    }
+}
@@ -36,6 +36,11 @@ pub enum RepositoryError {
         relation_name: String,
     },
 
+    #[display(fmt = "This {} already exists", relation_name)]
+    Conflict {
+        relation_name: String,
+    },
+
     Internal(#[error(not(source))] eyre::Error),
 }
 
@@ -48,7 +53,27 @@ impl From<RepositoryError> for Error {
             RepositoryError::Forbidden { .. } => {
                 Error::External(StatusCode::FORBIDDEN, error.to_string().into())
             }
+            RepositoryError::Conflict { .. } => {
+                Error::External(StatusCode::CONFLICT, error.to_string().into())
+            }
             RepositoryError::Internal(error) => Error::Internal(error),
         }
     }
 }
+
+impl RepositoryError {
+    /// Reports the violation of a unique constraint as `Conflict`, any other
+    /// error as internal.
+    pub fn unique(error: sqlx::Error, relation_name: &str) -> Self {
+        match error {
+            sqlx::Error::Database(ref database_error)
+                if database_error.is_unique_violation() =>
+            {
+                RepositoryError::Conflict {
+                    relation_name: relation_name.to_string(),
+                }
+            }
+            error => RepositoryError::Internal(error.into()),
+        }
+    }
+}
@@ -0,0 +1,520 @@
+use shared::models::{
+    list::{CreateList, ListInvitation, ListMember, ListPermission, TodoList},
+    todo::TodoAction,
+};
+
+use super::error::RepositoryError;
+use crate::util::error_or::ErrorOr;
+
+pub(crate) const RELATION: &str = "List";
+pub(crate) const MEMBER_RELATION: &str = "ListMember";
+pub(crate) const INVITATION_RELATION: &str = "ListInvitation";
+
+/// Stores the shared lists, their members and the pending invitations.
+///
+/// The owner of a list is stored as a member with the admin permission, the
+/// controllers make sure they stay one.
+#[async_trait::async_trait]
+pub trait ListRepository: Send + Sync + 'static {
+    /// Returns the lists the user is a member of, oldest first.
+    async fn get_lists(&self, user_id: &i64) -> ErrorOr<Vec<TodoList>>;
+
+    /// Fails with `NotFound` unless the user is a member of the list, which
+    /// does not reveal whether it exists.
+    async fn get_list(&self, list_id: &i64, user_id: &i64)
+        -> ErrorOr<TodoList>;
+
+    /// Creates a list with the user as owner and admin.
+    async fn create_list(
+        &self,
+        create_list: &CreateList,
+        user_id: &i64,
+    ) -> ErrorOr<TodoList>;
+
+    async fn rename_list(&self, list_id: &i64, name: &str) -> ErrorOr<()>;
+
+    /// Deletes the list with its todos, members and invitations.
+    async fn delete_list(&self, list_id: &i64) -> ErrorOr<()>;
+
+    /// Returns the members of a list in the order they joined.
+    async fn get_members(&self, list_id: &i64) -> ErrorOr<Vec<ListMember>>;
+
+    /// Fails with `NotFound` unless the user is a member of the list.
+    async fn set_member_permission(
+        &self,
+        list_id: &i64,
+        user_id: &i64,
+        permission: ListPermission,
+    ) -> ErrorOr<()>;
+
+    /// Fails with `NotFound` unless the user is a member of the list. The
+    /// todos of the list assigned to them become unassigned, which is
+    /// recorded in their activity with `removed_by` as the actor.
+    async fn remove_member(
+        &self,
+        list_id: &i64,
+        user_id: &i64,
+        removed_by: &i64,
+    ) -> ErrorOr<()>;
+
+    /// Invites a user to a list, replacing an earlier invitation of them.
+    async fn create_invitation(
+        &self,
+        list_id: &i64,
+        user_id: &i64,
+        invited_by: &i64,
+        permission: ListPermission,
+    ) -> ErrorOr<()>;
+
+    /// Returns the pending invitations of a user, oldest first.
+    async fn get_invitations(
+        &self,
+        user_id: &i64,
+    ) -> ErrorOr<Vec<ListInvitation>>;
+
+    /// Turns the invitation into a membership with the offered permission.
+    /// Fails with `NotFound` unless the user is the invitee.
+    async fn accept_invitation(
+        &self,
+        invitation_id: &i64,
+        user_id: &i64,
+    ) -> ErrorOr<()>;
+
+    /// Fails with `NotFound` unless the user is the invitee.
+    async fn decline_invitation(
+        &self,
+        invitation_id: &i64,
+        user_id: &i64,
+    ) -> ErrorOr<()>;
+}
+
+fn not_found(relation_name: &str) -> RepositoryError {
+    RepositoryError::NotFound { relation_name: relation_name.to_string() }
+}
+
+pub struct PostgresListRepository {
+    pool: sqlx::PgPool,
+}
+
+impl PostgresListRepository {
+    pub fn new(pool: sqlx::PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl ListRepository for PostgresListRepository {
+    async fn get_lists(&self, user_id: &i64) -> ErrorOr<Vec<TodoList>> {
+        let db_response = sqlx::query_as!(
+            TodoList,
+            r#"
+            SELECT
+                lists.id,
+                lists.name,
+                lists.owner,
+                list_members.permission,
+                lists.created_at,
+                lists.updated_at
+            FROM lists
+            JOIN list_members ON list_members.list_id = lists.id
+            WHERE list_members.user_id = $1
+            ORDER BY lists.id
+            "#,
+            user_id
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        db_response.into()
+    }
+
+    async fn get_list(
+        &self,
+        list_id: &i64,
+        user_id: &i64,
+    ) -> ErrorOr<TodoList> {
+        let db_response = sqlx::query_as!(
+            TodoList,
+            r#"
+            SELECT
+                lists.id,
+                lists.name,
+                lists.owner,
+                list_members.permission,
+                lists.created_at,
+                lists.updated_at
+            FROM lists
+            JOIN list_members ON list_members.list_id = lists.id
+            WHERE lists.id = $1 AND list_members.user_id = $2
+            "#,
+            list_id,
+            user_id
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?
+        .ok_or_else(|| not_found(RELATION))?;
+
+        db_response.into()
+    }
+
+    async fn create_list(
+        &self,
+        create_list: &CreateList,
+        user_id: &i64,
+    ) -> ErrorOr<TodoList> {
+        let mut transaction = self
+            .pool
+            .begin()
+            .await
+            .map_err(Into::into)
+            .map_err(RepositoryError::Internal)?;
+
+        let list = sqlx::query_as!(
+            TodoList,
+            r#"
+            INSERT
+            INTO lists (name, owner)
+            VALUES ($1, $2)
+            RETURNING
+                id,
+                name,
+                owner,
+                'admin' AS "permission!",
+                created_at,
+                updated_at
+            "#,
+            &create_list.name,
+            user_id
+        )
+        .fetch_one(&mut *transaction)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        sqlx::query!(
+            r#"
+            INSERT
+            INTO list_members (list_id, user_id, permission)
+            VALUES ($1, $2, $3)
+            "#,
+            list.id,
+            user_id,
+            ListPermission::Admin.as_str()
+        )
+        .execute(&mut *transaction)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        transaction
+            .commit()
+            .await
+            .map_err(Into::into)
+            .map_err(RepositoryError::Internal)?;
+
+        list.into()
+    }
+
+    async fn rename_list(&self, list_id: &i64, name: &str) -> ErrorOr<()> {
+        sqlx::query!(
+            r#"
+            UPDATE lists
+            SET name = $2, updated_at = now()
+            WHERE id = $1
+            "#,
+            list_id,
+            name
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        ().into()
+    }
+
+    async fn delete_list(&self, list_id: &i64) -> ErrorOr<()> {
+        sqlx::query!(
+            r#"
+            DELETE
+            FROM lists
+            WHERE id = $1
+            "#,
+            list_id
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        ().into()
+    }
+
+    async fn get_members(&self, list_id: &i64) -> ErrorOr<Vec<ListMember>> {
+        let db_response = sqlx::query_as!(
+            ListMember,
+            r#"
+            SELECT
+                users.id AS user_id,
+                users.name,
+                users.email,
+                list_members.permission,
+                list_members.created_at AS joined_at
+            FROM list_members
+            JOIN users ON users.id = list_members.user_id
+            WHERE list_members.list_id = $1
+            ORDER BY list_members.created_at, users.id
+            "#,
+            list_id
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        db_response.into()
+    }
+
+    async fn set_member_permission(
+        &self,
+        list_id: &i64,
+        user_id: &i64,
+        permission: ListPermission,
+    ) -> ErrorOr<()> {
+        let db_response = sqlx::query!(
+            r#"
+            UPDATE list_members
+            SET permission = $3
+            WHERE list_id = $1 AND user_id = $2
+            "#,
+            list_id,
+            user_id,
+            permission.as_str()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        if db_response.rows_affected() == 0 {
+            Err(not_found(MEMBER_RELATION))?;
+        }
+
+        ().into()
+    }
+
+    async fn remove_member(
+        &self,
+        list_id: &i64,
+        user_id: &i64,
+        removed_by: &i64,
+    ) -> ErrorOr<()> {
+        let mut transaction = self
+            .pool
+            .begin()
+            .await
+            .map_err(Into::into)
+            .map_err(RepositoryError::Internal)?;
+
+        let db_response = sqlx::query!(
+            r#"
+            DELETE
+            FROM list_members
+            WHERE list_id = $1 AND user_id = $2
+            "#,
+            list_id,
+            user_id
+        )
+        .execute(&mut *transaction)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        if db_response.rows_affected() == 0 {
+            Err(not_found(MEMBER_RELATION))?;
+        }
+
+        // the todos of the list are out of their reach now
+        sqlx::query!(
+            r#"
+            WITH unassigned AS (
+                UPDATE todos
+                SET assignee_id = NULL
+                WHERE list_id = $1 AND assignee_id = $2
+                RETURNING id
+            )
+            INSERT
+            INTO todo_activities (
+                todo_id,
+                actor_id,
+                action,
+                previous_assignee_id,
+                assignee_id
+            )
+            SELECT id, $3, $4, $2, NULL
+            FROM unassigned
+            "#,
+            list_id,
+            user_id,
+            removed_by,
+            TodoAction::Assigned.as_str()
+        )
+        .execute(&mut *transaction)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        transaction
+            .commit()
+            .await
+            .map_err(Into::into)
+            .map_err(RepositoryError::Internal)?;
+
+        ().into()
+    }
+
+    async fn create_invitation(
+        &self,
+        list_id: &i64,
+        user_id: &i64,
+        invited_by: &i64,
+        permission: ListPermission,
+    ) -> ErrorOr<()> {
+        sqlx::query!(
+            r#"
+            INSERT
+            INTO list_invitations (list_id, user_id, invited_by, permission)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (list_id, user_id) DO UPDATE
+            SET
+                invited_by = excluded.invited_by,
+                permission = excluded.permission,
+                created_at = now()
+            "#,
+            list_id,
+            user_id,
+            invited_by,
+            permission.as_str()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        ().into()
+    }
+
+    async fn get_invitations(
+        &self,
+        user_id: &i64,
+    ) -> ErrorOr<Vec<ListInvitation>> {
+        let db_response = sqlx::query_as!(
+            ListInvitation,
+            r#"
+            SELECT
+                list_invitations.id,
+                list_invitations.list_id,
+                lists.name AS list_name,
+                users.name AS invited_by,
+                list_invitations.permission,
+                list_invitations.created_at
+            FROM list_invitations
+            JOIN lists ON lists.id = list_invitations.list_id
+            JOIN users ON users.id = list_invitations.invited_by
+            WHERE list_invitations.user_id = $1
+            ORDER BY list_invitations.id
+            "#,
+            user_id
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        db_response.into()
+    }
+
+    async fn accept_invitation(
+        &self,
+        invitation_id: &i64,
+        user_id: &i64,
+    ) -> ErrorOr<()> {
+        let mut transaction = self
+            .pool
+            .begin()
+            .await
+            .map_err(Into::into)
+            .map_err(RepositoryError::Internal)?;
+
+        let invitation = sqlx::query!(
+            r#"
+            DELETE
+            FROM list_invitations
+            WHERE id = $1 AND user_id = $2
+            RETURNING list_id, permission
+            "#,
+            invitation_id,
+            user_id
+        )
+        .fetch_optional(&mut *transaction)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?
+        .ok_or_else(|| not_found(INVITATION_RELATION))?;
+
+        // a member keeps their permission if they were invited again
+        sqlx::query!(
+            r#"
+            INSERT
+            INTO list_members (list_id, user_id, permission)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (list_id, user_id) DO NOTHING
+            "#,
+            invitation.list_id,
+            user_id,
+            invitation.permission
+        )
+        .execute(&mut *transaction)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        transaction
+            .commit()
+            .await
+            .map_err(Into::into)
+            .map_err(RepositoryError::Internal)?;
+
+        ().into()
+    }
+
+    async fn decline_invitation(
+        &self,
+        invitation_id: &i64,
+        user_id: &i64,
+    ) -> ErrorOr<()> {
+        let db_response = sqlx::query!(
+            r#"
+            DELETE
+            FROM list_invitations
+            WHERE id = $1 AND user_id = $2
+            "#,
+            invitation_id,
+            user_id
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        if db_response.rows_affected() == 0 {
+            Err(not_found(INVITATION_RELATION))?;
+        }
+
+        ().into()
+    }
+}
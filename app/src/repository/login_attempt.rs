@@ -0,0 +1,154 @@
+use chrono::{DateTime, Utc};
+
+use super::error::RepositoryError;
+use crate::util::error_or::ErrorOr;
+
+/// Failed logins recorded for one key, e.g. an email address or an ip.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, sqlx::FromRow)]
+pub struct FailedLogins {
+    pub failures: i32,
+    pub last_failure_at: DateTime<Utc>,
+}
+
+/// Keeps track of failed logins so that password guessing can be throttled.
+///
+/// The keys are arbitrary strings, the caller decides whether it counts per
+/// account, per ip or both.
+#[async_trait::async_trait]
+pub trait LoginAttemptRepository: Send + Sync + 'static {
+    async fn get_failed_logins(
+        &self,
+        key: &str,
+    ) -> ErrorOr<Option<FailedLogins>>;
+
+    /// Counts a login as failed before it is verified, so that parallel
+    /// guesses cannot all pass the throttle. Only succeeds if the failed
+    /// logins of the key are still `previous`, returns whether they were.
+    /// Failures that happened before `reset_before` are forgotten first.
+    async fn reserve_login_attempt(
+        &self,
+        key: &str,
+        previous: Option<&FailedLogins>,
+        reset_before: DateTime<Utc>,
+    ) -> ErrorOr<bool>;
+
+    /// Takes back a reserved login that did not fail.
+    async fn release_login_attempt(&self, key: &str) -> ErrorOr<()>;
+
+    async fn clear_failed_logins(&self, key: &str) -> ErrorOr<()>;
+}
+
+pub struct PostgresLoginAttemptRepository {
+    pool: sqlx::PgPool,
+}
+
+impl PostgresLoginAttemptRepository {
+    pub fn new(pool: sqlx::PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl LoginAttemptRepository for PostgresLoginAttemptRepository {
+    async fn get_failed_logins(
+        &self,
+        key: &str,
+    ) -> ErrorOr<Option<FailedLogins>> {
+        let db_response = sqlx::query_as!(
+            FailedLogins,
+            r#"
+            SELECT failures, last_failure_at
+            FROM login_attempts
+            WHERE key = $1
+            "#,
+            key
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        db_response.into()
+    }
+
+    async fn reserve_login_attempt(
+        &self,
+        key: &str,
+        previous: Option<&FailedLogins>,
+        reset_before: DateTime<Utc>,
+    ) -> ErrorOr<bool> {
+        let db_response = match previous {
+            None => {
+                sqlx::query!(
+                    r#"
+                INSERT
+                INTO login_attempts (key, failures, last_failure_at)
+                VALUES ($1, 1, now())
+                ON CONFLICT (key) DO NOTHING
+                "#,
+                    key
+                )
+                .execute(&self.pool)
+                .await
+            }
+            Some(previous) => {
+                sqlx::query!(
+                    r#"
+                UPDATE login_attempts
+                SET
+                    failures = CASE
+                        WHEN last_failure_at < $4 THEN 1
+                        ELSE failures + 1
+                    END,
+                    last_failure_at = now()
+                WHERE key = $1 AND failures = $2 AND last_failure_at = $3
+                "#,
+                    key,
+                    previous.failures,
+                    previous.last_failure_at,
+                    reset_before
+                )
+                .execute(&self.pool)
+                .await
+            }
+        }
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        (db_response.rows_affected() == 1).into()
+    }
+
+    async fn release_login_attempt(&self, key: &str) -> ErrorOr<()> {
+        sqlx::query!(
+            r#"
+            UPDATE login_attempts
+            SET failures = failures - 1
+            WHERE key = $1 AND failures > 0
+            "#,
+            key
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        ().into()
+    }
+
+    async fn clear_failed_logins(&self, key: &str) -> ErrorOr<()> {
+        sqlx::query!(
+            r#"
+            DELETE
+            FROM login_attempts
+            WHERE key = $1
+            "#,
+            key
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        ().into()
+    }
+}
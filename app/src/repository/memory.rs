@@ -0,0 +1,2509 @@
+//! Repositories that keep all data in memory, mainly to exercise the
+//! controllers in tests without a database.
+//!
+//! They mirror the behaviour of the Postgres repositories, including the
+//! errors for missing rows, foreign keys and unique constraints.
+
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use actix_session::storage::SessionKey;
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::eyre;
+use shared::models::{
+    attachment::Attachment,
+    audit::{AuditAction, AuditEntity, AuditEvent, SecurityEvent},
+    comment::{Comment, CreateComment, UpdateComment},
+    list::{CreateList, ListInvitation, ListMember, ListPermission, TodoList},
+    todo::{CreateTodo, Todo, TodoAction, TodoActivity, UpdateTodo},
+    user::{CreateUser, Role, TokenScope, UpdateUser, User},
+};
+use sqlx::types::Json;
+use webauthn_rs::prelude::Passkey;
+
+use super::{
+    access_token::{self, AccessToken, AccessTokenRepository},
+    attachment::{self, AttachmentRepository, NewAttachment},
+    audit::{self, AuditContext, AuditRepository, NewSecurityEvent},
+    comment::{self, CommentRepository},
+    error::{Operation, RepositoryError},
+    list::{self, ListRepository},
+    login_attempt::{FailedLogins, LoginAttemptRepository},
+    oidc::OidcIdentityRepository,
+    passkey::{self, PasskeyRepository, StoredPasskey},
+    session::{Session, SessionRepository},
+    todo::{
+        calendar_feed, CreateImportedTodo, ExternalId, TodoRepository,
+        TodoStats,
+    },
+    totp::{Totp, TotpRepository},
+    user::{UserRepository, UserStats},
+    user_token::{self, TokenPurpose, UserToken, UserTokenRepository},
+    Backend,
+};
+use crate::util::error_or::ErrorOr;
+
+const TODO_RELATION: &str = "Todo";
+const USER_RELATION: &str = "User";
+
+/// A row of `lists`, the permission of a member is kept with the membership.
+#[derive(Clone)]
+struct StoredList {
+    id: i64,
+    name: String,
+    owner: i64,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+}
+
+/// A row of `list_invitations`.
+#[derive(Clone)]
+struct StoredInvitation {
+    list_id: i64,
+    user_id: i64,
+    invited_by: i64,
+    permission: ListPermission,
+    created_at: DateTime<Utc>,
+}
+
+#[derive(Default)]
+struct MemoryState {
+    users: BTreeMap<i64, User>,
+    todos: BTreeMap<i64, Todo>,
+    todo_activities: BTreeMap<i64, TodoActivity>,
+    /// Comments with the name of their author left empty.
+    comments: BTreeMap<i64, Comment>,
+    attachments: BTreeMap<i64, Attachment>,
+    /// Storage keys of the content of deleted attachments.
+    orphaned_blobs: Vec<String>,
+    /// Imported todos by the importing user and their external id.
+    external_ids: BTreeMap<(i64, String), i64>,
+    /// Users and when the feed was created by the hash of the token of their
+    /// calendar feed.
+    feed_tokens: HashMap<String, (i64, DateTime<Utc>)>,
+    sessions: HashMap<String, Session>,
+    login_attempts: HashMap<String, FailedLogins>,
+    /// Tokens and their purpose by their hash.
+    user_tokens: HashMap<String, (TokenPurpose, UserToken)>,
+    user_totp: HashMap<i64, Totp>,
+    /// Users and the hashes of their recovery codes.
+    recovery_codes: HashSet<(i64, String)>,
+    passkeys: BTreeMap<i64, StoredPasskey>,
+    /// Linked users by issuer and subject.
+    oidc_identities: HashMap<(String, String), i64>,
+    /// Access tokens and their hash by their id.
+    access_tokens: BTreeMap<i64, (String, AccessToken)>,
+    lists: BTreeMap<i64, StoredList>,
+    /// Permissions and join times by list and user.
+    list_members: BTreeMap<(i64, i64), (ListPermission, DateTime<Utc>)>,
+    list_invitations: BTreeMap<i64, StoredInvitation>,
+    audit_events: BTreeMap<i64, AuditEvent>,
+    security_events: BTreeMap<i64, SecurityEvent>,
+    last_user_id: i64,
+    last_todo_id: i64,
+    last_todo_activity_id: i64,
+    last_comment_id: i64,
+    last_attachment_id: i64,
+    last_passkey_id: i64,
+    last_access_token_id: i64,
+    last_list_id: i64,
+    last_list_invitation_id: i64,
+    last_audit_event_id: i64,
+    last_security_event_id: i64,
+}
+
+impl MemoryState {
+    fn list_permission(
+        &self,
+        list_id: i64,
+        user_id: i64,
+    ) -> Option<ListPermission> {
+        self.list_members
+            .get(&(list_id, user_id))
+            .map(|(permission, _)| *permission)
+    }
+
+    /// Mirrors the visibility rules of the Postgres todo queries.
+    fn can_read(&self, todo: &Todo, user_id: i64) -> bool {
+        match todo.list_id {
+            Some(list_id) => self.list_permission(list_id, user_id).is_some(),
+            None => todo.owner == user_id,
+        }
+    }
+
+    fn can_edit(&self, todo: &Todo, user_id: i64) -> bool {
+        match todo.list_id {
+            Some(list_id) => self
+                .list_permission(list_id, user_id)
+                .is_some_and(|permission| permission >= ListPermission::Editor),
+            None => todo.owner == user_id,
+        }
+    }
+
+    /// Fails like the Postgres insert if the user may not create the todo.
+    fn check_create(
+        &self,
+        create_todo: &CreateTodo,
+        user_id: i64,
+    ) -> Result<(), RepositoryError> {
+        if !self.users.contains_key(&user_id) {
+            Err(RepositoryError::Internal(eyre!(
+                "todos_owner_fkey: user {user_id} does not exist"
+            )))?;
+        }
+        if let Some(list_id) = create_todo.list_id {
+            let permission = self.list_permission(list_id, user_id);
+            if permission
+                .is_none_or(|permission| permission < ListPermission::Editor)
+            {
+                Err(RepositoryError::Forbidden {
+                    operation: Operation::Post,
+                    relation_name: TODO_RELATION.to_string(),
+                })?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Creates a todo that passed `check_create`.
+    fn insert_todo(
+        &mut self,
+        create_todo: &CreateTodo,
+        is_done: bool,
+        user_id: i64,
+        audit: &AuditContext,
+    ) -> Todo {
+        self.last_todo_id += 1;
+        let now = Utc::now();
+        let todo = Todo {
+            id: self.last_todo_id,
+            title: create_todo.title.clone(),
+            description: create_todo.description.clone(),
+            is_done,
+            owner: user_id,
+            list_id: create_todo.list_id,
+            assignee_id: None,
+            due_at: create_todo.due_at,
+            completed_at: is_done.then_some(now),
+            created_at: now,
+            updated_at: now,
+        };
+        self.todos.insert(todo.id, todo.clone());
+        self.record(
+            audit,
+            AuditAction::Created,
+            AuditEntity::Todo,
+            todo.id,
+            (None, Some(&todo)),
+        );
+
+        todo
+    }
+
+    /// Fails like the Postgres update if the user may not edit the todo.
+    fn check_update(
+        &self,
+        todo_id: i64,
+        user_id: i64,
+    ) -> Result<(), RepositoryError> {
+        if !self
+            .todos
+            .get(&todo_id)
+            .is_some_and(|todo| self.can_edit(todo, user_id))
+        {
+            Err(RepositoryError::Forbidden {
+                operation: Operation::Update,
+                relation_name: TODO_RELATION.to_string(),
+            })?;
+        }
+
+        Ok(())
+    }
+
+    /// Updates a todo that passed `check_update`.
+    fn change_todo(
+        &mut self,
+        update_todo: &UpdateTodo,
+        audit: &AuditContext,
+    ) -> Result<Todo, RepositoryError> {
+        let todo = self.todos.get_mut(&update_todo.id).ok_or_else(|| {
+            RepositoryError::Internal(sqlx::Error::RowNotFound.into())
+        })?;
+
+        let before = todo.clone();
+        if let Some(title) = &update_todo.title {
+            todo.title = title.clone();
+        }
+        if let Some(description) = &update_todo.description {
+            todo.description = description.clone();
+        }
+        let now = Utc::now();
+        if let Some(is_done) = update_todo.is_done {
+            todo.is_done = is_done;
+        }
+        todo.completed_at =
+            todo.is_done.then(|| todo.completed_at.unwrap_or(now));
+        if let Some(due_at) = update_todo.due_at {
+            todo.due_at = due_at;
+        }
+        todo.updated_at = now;
+        let todo = todo.clone();
+        self.record(
+            audit,
+            AuditAction::Updated,
+            AuditEntity::Todo,
+            todo.id,
+            (Some(&before), Some(&todo)),
+        );
+
+        Ok(todo)
+    }
+
+    /// Deletes a list like `ON DELETE CASCADE` would.
+    fn delete_list(&mut self, list_id: i64) {
+        self.lists.remove(&list_id);
+        self.list_members.retain(|(id, _), _| *id != list_id);
+        self.list_invitations
+            .retain(|_, invitation| invitation.list_id != list_id);
+        self.todos.retain(|_, todo| todo.list_id != Some(list_id));
+        self.delete_orphans();
+    }
+
+    /// Deletes whatever refers to deleted todos, e.g. their comments and
+    /// attachments, like `ON DELETE CASCADE` would.
+    fn delete_orphans(&mut self) {
+        let todos = &self.todos;
+        self.todo_activities
+            .retain(|_, activity| todos.contains_key(&activity.todo_id));
+        self.comments.retain(|_, comment| todos.contains_key(&comment.todo_id));
+        self.external_ids.retain(|_, todo_id| todos.contains_key(todo_id));
+        let orphaned = self
+            .attachments
+            .values()
+            .filter(|attachment| !todos.contains_key(&attachment.todo_id))
+            .map(|attachment| attachment.id)
+            .collect::<Vec<_>>();
+        for attachment_id in orphaned {
+            self.delete_attachment(attachment_id);
+        }
+    }
+
+    /// Deletes an attachment and orphans its blob like the trigger of
+    /// `attachments` does.
+    fn delete_attachment(&mut self, attachment_id: i64) {
+        if let Some(attachment) = self.attachments.remove(&attachment_id) {
+            self.orphaned_blobs.push(attachment.storage_key);
+        }
+    }
+
+    /// Appends an audit event for a change from `before` to `after`.
+    fn record<T: serde::Serialize>(
+        &mut self,
+        audit: &AuditContext,
+        action: AuditAction,
+        entity: AuditEntity,
+        entity_id: i64,
+        (before, after): (Option<&T>, Option<&T>),
+    ) {
+        self.last_audit_event_id += 1;
+        let event = AuditEvent {
+            id: self.last_audit_event_id,
+            actor_id: audit.actor_id,
+            action,
+            entity,
+            entity_id,
+            changes: audit::changes(before, after),
+            request_id: audit.request_id.clone(),
+            created_at: Utc::now(),
+        };
+        self.audit_events.insert(event.id, event);
+    }
+
+    /// Maps the external id to the created todo, see `import_todos`.
+    fn insert_external_id(
+        &mut self,
+        external_id: &str,
+        todo_id: i64,
+        session_user_id: i64,
+        audit: &AuditContext,
+    ) {
+        self.external_ids
+            .insert((session_user_id, external_id.to_string()), todo_id);
+        self.record(
+            audit,
+            AuditAction::Updated,
+            AuditEntity::Todo,
+            todo_id,
+            (None, Some(&serde_json::json!({ "external_id": external_id }))),
+        );
+    }
+
+    /// Returns a stored comment with the current name of its author.
+    fn to_comment(&self, comment: &Comment) -> Comment {
+        let author_name = self
+            .users
+            .get(&comment.author_id)
+            .map(|user| user.name.clone())
+            .unwrap_or_default();
+
+        Comment { author_name, ..comment.clone() }
+    }
+}
+
+/// Shares one set of tables between all repositories created from it, just
+/// like a connection pool shares one database.
+#[derive(Clone, Default)]
+pub struct MemoryBackend {
+    state: Arc<Mutex<MemoryState>>,
+}
+
+impl MemoryBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Backend for MemoryBackend {
+    type Todo = MemoryTodoRepository;
+    type User = MemoryUserRepository;
+    type Session = MemorySessionRepository;
+    type LoginAttempt = MemoryLoginAttemptRepository;
+    type UserToken = MemoryUserTokenRepository;
+    type Totp = MemoryTotpRepository;
+    type Passkey = MemoryPasskeyRepository;
+    type OidcIdentity = MemoryOidcIdentityRepository;
+    type AccessToken = MemoryAccessTokenRepository;
+    type List = MemoryListRepository;
+    type Comment = MemoryCommentRepository;
+    type Audit = MemoryAuditRepository;
+    type Attachment = MemoryAttachmentRepository;
+
+    fn todo_repository(&self) -> Self::Todo {
+        MemoryTodoRepository { state: self.state.clone() }
+    }
+
+    fn user_repository(&self) -> Self::User {
+        MemoryUserRepository { state: self.state.clone() }
+    }
+
+    fn session_repository(&self) -> Self::Session {
+        MemorySessionRepository { state: self.state.clone() }
+    }
+
+    fn login_attempt_repository(&self) -> Self::LoginAttempt {
+        MemoryLoginAttemptRepository { state: self.state.clone() }
+    }
+
+    fn user_token_repository(&self) -> Self::UserToken {
+        MemoryUserTokenRepository { state: self.state.clone() }
+    }
+
+    fn totp_repository(&self) -> Self::Totp {
+        MemoryTotpRepository { state: self.state.clone() }
+    }
+
+    fn passkey_repository(&self) -> Self::Passkey {
+        MemoryPasskeyRepository { state: self.state.clone() }
+    }
+
+    fn oidc_identity_repository(&self) -> Self::OidcIdentity {
+        MemoryOidcIdentityRepository { state: self.state.clone() }
+    }
+
+    fn access_token_repository(&self) -> Self::AccessToken {
+        MemoryAccessTokenRepository { state: self.state.clone() }
+    }
+
+    fn list_repository(&self) -> Self::List {
+        MemoryListRepository { state: self.state.clone() }
+    }
+
+    fn comment_repository(&self) -> Self::Comment {
+        MemoryCommentRepository { state: self.state.clone() }
+    }
+
+    fn audit_repository(&self) -> Self::Audit {
+        MemoryAuditRepository { state: self.state.clone() }
+    }
+
+    fn attachment_repository(&self) -> Self::Attachment {
+        MemoryAttachmentRepository { state: self.state.clone() }
+    }
+}
+
+fn lock(state: &Mutex<MemoryState>) -> MutexGuard<'_, MemoryState> {
+    // a panicking test must not poison the tables of the other tests
+    state.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+#[derive(Clone)]
+pub struct MemoryTodoRepository {
+    state: Arc<Mutex<MemoryState>>,
+}
+
+#[async_trait::async_trait]
+impl TodoRepository for MemoryTodoRepository {
+    async fn get_todos(&self, session_user_id: &i64) -> ErrorOr<Vec<Todo>> {
+        let state = lock(&self.state);
+        let todos = state
+            .todos
+            .values()
+            .filter(|todo| state.can_read(todo, *session_user_id))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        todos.into()
+    }
+
+    async fn get_todo(
+        &self,
+        todo_id: &i64,
+        session_user_id: &i64,
+    ) -> ErrorOr<Todo> {
+        let state = lock(&self.state);
+        let todo = state.todos.get(todo_id).ok_or_else(|| {
+            RepositoryError::NotFound {
+                relation_name: TODO_RELATION.to_string(),
+            }
+        })?;
+
+        if !state.can_read(todo, *session_user_id) {
+            Err(RepositoryError::Forbidden {
+                operation: Operation::Receive,
+                relation_name: TODO_RELATION.to_string(),
+            })?;
+        }
+
+        todo.clone().into()
+    }
+
+    async fn create_todo(
+        &self,
+        create_todo: &CreateTodo,
+        session_user_id: &i64,
+        audit: &AuditContext,
+    ) -> ErrorOr<Todo> {
+        let mut state = lock(&self.state);
+        state.check_create(create_todo, *session_user_id)?;
+
+        state.insert_todo(create_todo, false, *session_user_id, audit).into()
+    }
+
+    async fn update_todo(
+        &self,
+        update_todo: &UpdateTodo,
+        session_user_id: &i64,
+        audit: &AuditContext,
+    ) -> ErrorOr<Todo> {
+        let mut state = lock(&self.state);
+        state.check_update(update_todo.id, *session_user_id)?;
+        let todo = state.change_todo(update_todo, audit)?;
+
+        todo.into()
+    }
+
+    async fn backdate_todo(
+        &self,
+        todo_id: &i64,
+        created_at: DateTime<Utc>,
+        updated_at: DateTime<Utc>,
+        audit: &AuditContext,
+    ) -> ErrorOr<Todo> {
+        let mut state = lock(&self.state);
+        let todo =
+            state.todos.get_mut(todo_id).ok_or(sqlx::Error::RowNotFound)?;
+
+        let before = todo.clone();
+        todo.created_at = created_at;
+        todo.updated_at = updated_at;
+        todo.completed_at = todo.is_done.then_some(updated_at);
+        let todo = todo.clone();
+        state.record(
+            audit,
+            AuditAction::Updated,
+            AuditEntity::Todo,
+            todo.id,
+            (Some(&before), Some(&todo)),
+        );
+
+        todo.into()
+    }
+
+    async fn delete_todo(
+        &self,
+        todo_id: &i64,
+        session_user_id: &i64,
+        audit: &AuditContext,
+    ) -> ErrorOr<()> {
+        // like the `DELETE` of the Postgres repository this is not an error
+        // when the user may not edit the todo
+        let mut state = lock(&self.state);
+        let editable = state
+            .todos
+            .get(todo_id)
+            .is_some_and(|todo| state.can_edit(todo, *session_user_id));
+        if editable {
+            let before = state.todos.remove(todo_id);
+            state.delete_orphans();
+            state.record(
+                audit,
+                AuditAction::Deleted,
+                AuditEntity::Todo,
+                *todo_id,
+                (before.as_ref(), None),
+            );
+        }
+
+        ().into()
+    }
+
+    async fn assign_todo(
+        &self,
+        todo_id: &i64,
+        assignee_id: Option<i64>,
+        session_user_id: &i64,
+        audit: &AuditContext,
+    ) -> ErrorOr<Todo> {
+        let mut state = lock(&self.state);
+        let before = state
+            .todos
+            .get(todo_id)
+            .filter(|todo| state.can_edit(todo, *session_user_id))
+            .cloned()
+            .ok_or_else(|| RepositoryError::Forbidden {
+                operation: Operation::Update,
+                relation_name: TODO_RELATION.to_string(),
+            })?;
+        if assignee_id.is_some_and(|id| !state.can_read(&before, id)) {
+            Err(RepositoryError::Conflict {
+                relation_name: TODO_RELATION.to_string(),
+            })?;
+        }
+
+        let now = Utc::now();
+        let previous_assignee_id = before.assignee_id;
+        let todo = Todo { assignee_id, updated_at: now, ..before.clone() };
+        state.todos.insert(todo.id, todo.clone());
+        state.record(
+            audit,
+            AuditAction::Assigned,
+            AuditEntity::Todo,
+            todo.id,
+            (Some(&before), Some(&todo)),
+        );
+
+        if previous_assignee_id != assignee_id {
+            state.last_todo_activity_id += 1;
+            let activity = TodoActivity {
+                id: state.last_todo_activity_id,
+                todo_id: *todo_id,
+                actor_id: Some(*session_user_id),
+                action: TodoAction::Assigned,
+                previous_assignee_id,
+                assignee_id,
+                created_at: now,
+            };
+            state.todo_activities.insert(activity.id, activity);
+        }
+
+        todo.into()
+    }
+
+    async fn get_activities(
+        &self,
+        todo_id: &i64,
+    ) -> ErrorOr<Vec<TodoActivity>> {
+        let activities = lock(&self.state)
+            .todo_activities
+            .values()
+            .filter(|activity| activity.todo_id == *todo_id)
+            .cloned()
+            .collect::<Vec<_>>();
+
+        activities.into()
+    }
+
+    async fn todo_stats(&self) -> ErrorOr<TodoStats> {
+        let state = lock(&self.state);
+
+        TodoStats {
+            total: state.todos.len() as i64,
+            done: state.todos.values().filter(|todo| todo.is_done).count()
+                as i64,
+        }
+        .into()
+    }
+
+    async fn get_external_ids(
+        &self,
+        session_user_id: &i64,
+    ) -> ErrorOr<Vec<ExternalId>> {
+        let state = lock(&self.state);
+        let external_ids = state
+            .external_ids
+            .iter()
+            .filter(|((user_id, _), _)| user_id == session_user_id)
+            .map(|((_, external_id), todo_id)| ExternalId {
+                external_id: external_id.clone(),
+                todo_id: *todo_id,
+            })
+            .collect::<Vec<_>>();
+
+        external_ids.into()
+    }
+
+    async fn import_todos(
+        &self,
+        create: &[CreateImportedTodo],
+        update: &[UpdateTodo],
+        session_user_id: &i64,
+        audit: &AuditContext,
+    ) -> ErrorOr<Vec<Todo>> {
+        let mut state = lock(&self.state);
+        // nothing is changed unless every row may be, like a rolled back
+        // transaction
+        for imported in create {
+            state.check_create(&imported.create_todo, *session_user_id)?;
+        }
+        for update_todo in update {
+            state.check_update(update_todo.id, *session_user_id)?;
+        }
+
+        let mut created = Vec::with_capacity(create.len());
+        for imported in create {
+            let todo = state.insert_todo(
+                &imported.create_todo,
+                imported.is_done,
+                *session_user_id,
+                audit,
+            );
+            if let Some(external_id) = &imported.external_id {
+                state.insert_external_id(
+                    external_id,
+                    todo.id,
+                    *session_user_id,
+                    audit,
+                );
+            }
+            created.push(todo);
+        }
+        for update_todo in update {
+            state.change_todo(update_todo, audit)?;
+        }
+
+        created.into()
+    }
+
+    async fn set_feed_token(
+        &self,
+        user_id: &i64,
+        token_hash: Option<&str>,
+        audit: &AuditContext,
+    ) -> ErrorOr<()> {
+        let mut state = lock(&self.state);
+        if !state.users.contains_key(user_id) {
+            Err(RepositoryError::Internal(eyre!(
+                "calendar_feeds_user_id_fkey: user {user_id} does not exist"
+            )))?;
+        }
+        let before = state
+            .feed_tokens
+            .values()
+            .find(|(feed_user_id, _)| feed_user_id == user_id)
+            .map(|(_, created_at)| *created_at);
+        state
+            .feed_tokens
+            .retain(|_, (feed_user_id, _)| feed_user_id != user_id);
+        let after = token_hash.map(|token_hash| {
+            let created_at = Utc::now();
+            state
+                .feed_tokens
+                .insert(token_hash.to_string(), (*user_id, created_at));
+            created_at
+        });
+        state.record(
+            audit,
+            AuditAction::Updated,
+            AuditEntity::User,
+            *user_id,
+            (Some(&calendar_feed(before)), Some(&calendar_feed(after))),
+        );
+
+        ().into()
+    }
+
+    async fn get_feed_user(&self, token_hash: &str) -> ErrorOr<Option<i64>> {
+        lock(&self.state)
+            .feed_tokens
+            .get(token_hash)
+            .map(|(user_id, _)| *user_id)
+            .into()
+    }
+}
+
+#[derive(Clone)]
+pub struct MemoryUserRepository {
+    state: Arc<Mutex<MemoryState>>,
+}
+
+impl MemoryUserRepository {
+    /// Changes the user, if they exist, and records the change.
+    fn change_user(
+        state: &mut MemoryState,
+        user_id: &i64,
+        audit: &AuditContext,
+        action: AuditAction,
+        change: impl FnOnce(&mut User),
+    ) {
+        let Some(user) = state.users.get_mut(user_id) else {
+            return;
+        };
+        let before = user.clone();
+        change(user);
+        let after = user.clone();
+
+        state.record(
+            audit,
+            action,
+            AuditEntity::User,
+            *user_id,
+            (Some(&before), Some(&after)),
+        );
+    }
+}
+
+fn ensure_unique_email(
+    state: &MemoryState,
+    email: &str,
+    except_id: Option<i64>,
+) -> Result<(), RepositoryError> {
+    let taken = state
+        .users
+        .values()
+        .any(|user| user.email == email && Some(user.id) != except_id);
+
+    if taken {
+        return Err(RepositoryError::Conflict {
+            relation_name: USER_RELATION.to_string(),
+        });
+    }
+
+    Ok(())
+}
+
+#[async_trait::async_trait]
+impl UserRepository for MemoryUserRepository {
+    async fn get_session_user(&self, session_user_id: &i64) -> ErrorOr<User> {
+        let user = lock(&self.state)
+            .users
+            .get(session_user_id)
+            .cloned()
+            .ok_or(sqlx::Error::RowNotFound)?;
+
+        user.into()
+    }
+
+    async fn get_user_by_email(&self, email: &str) -> ErrorOr<User> {
+        let user = lock(&self.state)
+            .users
+            .values()
+            .find(|user| user.email == email)
+            .cloned()
+            .ok_or_else(|| RepositoryError::NotFound {
+                relation_name: USER_RELATION.to_string(),
+            })?;
+
+        user.into()
+    }
+
+    async fn create_user(
+        &self,
+        create_user: &CreateUser,
+        audit: &AuditContext,
+    ) -> ErrorOr<()> {
+        let mut state = lock(&self.state);
+        ensure_unique_email(&state, &create_user.email, None)?;
+
+        state.last_user_id += 1;
+        let now = Utc::now();
+        let user = User {
+            id: state.last_user_id,
+            name: create_user.name.clone(),
+            email: create_user.email.clone(),
+            password: create_user.password.clone(),
+            created_at: now,
+            updated_at: now,
+            email_verified_at: None,
+            role: Role::User,
+            disabled_at: None,
+        };
+        state.users.insert(user.id, user.clone());
+        state.record(
+            audit,
+            AuditAction::Created,
+            AuditEntity::User,
+            user.id,
+            (None, Some(&user)),
+        );
+
+        ().into()
+    }
+
+    async fn update_user(
+        &self,
+        update_user: &UpdateUser,
+        session_user_id: &i64,
+        audit: &AuditContext,
+    ) -> ErrorOr<()> {
+        let mut state = lock(&self.state);
+        if let Some(email) = &update_user.email {
+            ensure_unique_email(&state, email, Some(*session_user_id))?;
+        }
+
+        Self::change_user(
+            &mut state,
+            session_user_id,
+            audit,
+            AuditAction::Updated,
+            |user| {
+                if let Some(name) = &update_user.name {
+                    user.name = name.clone();
+                }
+                if let Some(email) = &update_user.email {
+                    if *email != user.email {
+                        user.email_verified_at = None;
+                    }
+                    user.email = email.clone();
+                }
+                user.updated_at = Utc::now();
+            },
+        );
+
+        ().into()
+    }
+
+    async fn delete_user(
+        &self,
+        session_user_id: &i64,
+        audit: &AuditContext,
+    ) -> ErrorOr<()> {
+        let mut state = lock(&self.state);
+        let owned_lists = state
+            .lists
+            .values()
+            .filter(|list| list.owner == *session_user_id)
+            .map(|list| list.id)
+            .collect::<Vec<_>>();
+        // the todos in their own lists are deleted with the lists
+        let owns_todos = state.todos.values().any(|todo| {
+            todo.owner == *session_user_id
+                && todo.list_id.is_none_or(|id| !owned_lists.contains(&id))
+        });
+        if owns_todos {
+            Err(RepositoryError::Internal(eyre!(
+                "todos_owner_fkey: user {session_user_id} still owns todos"
+            )))?;
+        }
+
+        for list_id in owned_lists {
+            state.delete_list(list_id);
+        }
+        state.list_members.retain(|(_, user_id), _| user_id != session_user_id);
+        state.external_ids.retain(|(user_id, _), _| user_id != session_user_id);
+        state.feed_tokens.retain(|_, (user_id, _)| user_id != session_user_id);
+        // assignments and activity only lose the reference like
+        // `ON DELETE SET NULL`
+        for todo in state.todos.values_mut() {
+            if todo.assignee_id == Some(*session_user_id) {
+                todo.assignee_id = None;
+            }
+        }
+        state
+            .comments
+            .retain(|_, comment| comment.author_id != *session_user_id);
+        for comment in state.comments.values_mut() {
+            comment.mentions.retain(|user_id| user_id != session_user_id);
+        }
+        for attachment in state.attachments.values_mut() {
+            if attachment.uploader_id == Some(*session_user_id) {
+                attachment.uploader_id = None;
+            }
+        }
+        for activity in state.todo_activities.values_mut() {
+            for id in [
+                &mut activity.actor_id,
+                &mut activity.previous_assignee_id,
+                &mut activity.assignee_id,
+            ] {
+                if *id == Some(*session_user_id) {
+                    *id = None;
+                }
+            }
+        }
+        state.list_invitations.retain(|_, invitation| {
+            invitation.user_id != *session_user_id
+                && invitation.invited_by != *session_user_id
+        });
+        if let Some(before) = state.users.remove(session_user_id) {
+            state.record(
+                audit,
+                AuditAction::Deleted,
+                AuditEntity::User,
+                before.id,
+                (Some(&before), None),
+            );
+        }
+        state
+            .sessions
+            .retain(|_, session| session.user_id != Some(*session_user_id));
+        state
+            .user_tokens
+            .retain(|_, (_, token)| token.user_id != *session_user_id);
+        state.user_totp.remove(session_user_id);
+        state.recovery_codes.retain(|(user_id, _)| user_id != session_user_id);
+        state.passkeys.retain(|_, passkey| passkey.user_id != *session_user_id);
+        state.oidc_identities.retain(|_, user_id| user_id != session_user_id);
+
+        ().into()
+    }
+
+    async fn set_email_verified(
+        &self,
+        user_id: &i64,
+        audit: &AuditContext,
+    ) -> ErrorOr<()> {
+        Self::change_user(
+            &mut lock(&self.state),
+            user_id,
+            audit,
+            AuditAction::EmailVerified,
+            |user| user.email_verified_at = Some(Utc::now()),
+        );
+
+        ().into()
+    }
+
+    async fn set_password(
+        &self,
+        user_id: &i64,
+        password_hash: &str,
+        audit: &AuditContext,
+    ) -> ErrorOr<()> {
+        Self::change_user(
+            &mut lock(&self.state),
+            user_id,
+            audit,
+            AuditAction::PasswordChanged,
+            |user| {
+                user.password = password_hash.to_string();
+                user.updated_at = Utc::now();
+            },
+        );
+
+        ().into()
+    }
+
+    async fn count_users(&self) -> ErrorOr<i64> {
+        (lock(&self.state).users.len() as i64).into()
+    }
+
+    async fn search_users(
+        &self,
+        search: Option<&str>,
+        offset: i64,
+        limit: i64,
+    ) -> ErrorOr<Vec<User>> {
+        let search = search.map(str::to_lowercase);
+        let matches = |text: &str| {
+            search
+                .as_ref()
+                .is_none_or(|search| text.to_lowercase().contains(search))
+        };
+
+        lock(&self.state)
+            .users
+            .values()
+            .filter(|user| matches(&user.name) || matches(&user.email))
+            .skip(offset.max(0) as usize)
+            .take(limit.max(0) as usize)
+            .cloned()
+            .collect::<Vec<_>>()
+            .into()
+    }
+
+    async fn set_role(
+        &self,
+        user_id: &i64,
+        role: Role,
+        audit: &AuditContext,
+    ) -> ErrorOr<()> {
+        Self::change_user(
+            &mut lock(&self.state),
+            user_id,
+            audit,
+            AuditAction::RoleChanged,
+            |user| {
+                user.role = role;
+                user.updated_at = Utc::now();
+            },
+        );
+
+        ().into()
+    }
+
+    async fn set_disabled(
+        &self,
+        user_id: &i64,
+        disabled: bool,
+        audit: &AuditContext,
+    ) -> ErrorOr<()> {
+        let action = match disabled {
+            true => AuditAction::Disabled,
+            false => AuditAction::Enabled,
+        };
+        Self::change_user(
+            &mut lock(&self.state),
+            user_id,
+            audit,
+            action,
+            |user| {
+                user.disabled_at = match disabled {
+                    true => user.disabled_at.or(Some(Utc::now())),
+                    false => None,
+                };
+                user.updated_at = Utc::now();
+            },
+        );
+
+        ().into()
+    }
+
+    async fn user_stats(&self) -> ErrorOr<UserStats> {
+        let state = lock(&self.state);
+        let users = state.users.values();
+
+        UserStats {
+            total: state.users.len() as i64,
+            admins: users
+                .clone()
+                .filter(|user| user.role == Role::Admin)
+                .count() as i64,
+            disabled: users.filter(|user| user.disabled_at.is_some()).count()
+                as i64,
+        }
+        .into()
+    }
+}
+
+#[derive(Clone)]
+pub struct MemorySessionRepository {
+    state: Arc<Mutex<MemoryState>>,
+}
+
+#[async_trait::async_trait]
+impl SessionRepository for MemorySessionRepository {
+    async fn db_load(
+        &self,
+        session_key: &SessionKey,
+    ) -> Result<Option<Session>, sqlx::Error> {
+        let session = lock(&self.state)
+            .sessions
+            .get(session_key.as_ref())
+            .filter(|session| session.expires_at > Utc::now())
+            .cloned();
+
+        Ok(session)
+    }
+
+    async fn db_save(
+        &self,
+        session_key: &SessionKey,
+        session_state: &serde_json::Value,
+        user_id: Option<i64>,
+        expires_at: DateTime<Utc>,
+    ) -> Result<(), sqlx::Error> {
+        let mut state = lock(&self.state);
+        if state.sessions.contains_key(session_key.as_ref()) {
+            return Err(sqlx::Error::Protocol(
+                "sessions_pkey: the session key is already taken".to_string(),
+            ));
+        }
+
+        let session = Session {
+            key: session_key.as_ref().to_string(),
+            state: sqlx::types::Json(session_state.clone()),
+            user_id,
+            expires_at,
+        };
+        state.sessions.insert(session.key.clone(), session);
+
+        Ok(())
+    }
+
+    async fn db_update(
+        &self,
+        session_key: &SessionKey,
+        session_state: &serde_json::Value,
+        user_id: Option<i64>,
+        expires_at: DateTime<Utc>,
+    ) -> Result<(), sqlx::Error> {
+        if let Some(session) =
+            lock(&self.state).sessions.get_mut(session_key.as_ref())
+        {
+            session.state = sqlx::types::Json(session_state.clone());
+            session.user_id = user_id;
+            session.expires_at = expires_at;
+        }
+
+        Ok(())
+    }
+
+    async fn db_update_ttl(
+        &self,
+        session_key: &SessionKey,
+        expires_at: DateTime<Utc>,
+    ) -> Result<(), sqlx::Error> {
+        if let Some(session) =
+            lock(&self.state).sessions.get_mut(session_key.as_ref())
+        {
+            session.expires_at = expires_at;
+        }
+
+        Ok(())
+    }
+
+    async fn db_delete(
+        &self,
+        session_key: &SessionKey,
+    ) -> Result<(), sqlx::Error> {
+        lock(&self.state).sessions.remove(session_key.as_ref());
+
+        Ok(())
+    }
+
+    async fn list_sessions(
+        &self,
+        user_id: Option<i64>,
+    ) -> Result<Vec<Session>, sqlx::Error> {
+        let now = Utc::now();
+        let mut sessions = lock(&self.state)
+            .sessions
+            .values()
+            .filter(|session| session.expires_at > now)
+            .filter(|session| user_id.is_none() || session.user_id == user_id)
+            .cloned()
+            .collect::<Vec<_>>();
+        sessions.sort_by_key(|session| session.expires_at);
+
+        Ok(sessions)
+    }
+
+    async fn count_sessions(&self) -> Result<i64, sqlx::Error> {
+        let now = Utc::now();
+        let sessions = lock(&self.state)
+            .sessions
+            .values()
+            .filter(|session| session.expires_at > now)
+            .count();
+
+        Ok(sessions as i64)
+    }
+
+    async fn delete_user_sessions(
+        &self,
+        user_id: i64,
+    ) -> Result<u64, sqlx::Error> {
+        let sessions = &mut lock(&self.state).sessions;
+        let before = sessions.len();
+        sessions.retain(|_, session| session.user_id != Some(user_id));
+
+        Ok((before - sessions.len()) as u64)
+    }
+
+    async fn delete_expired_sessions(&self) -> Result<u64, sqlx::Error> {
+        let now = Utc::now();
+        let sessions = &mut lock(&self.state).sessions;
+        let before = sessions.len();
+        sessions.retain(|_, session| session.expires_at > now);
+
+        Ok((before - sessions.len()) as u64)
+    }
+}
+
+#[derive(Clone)]
+pub struct MemoryLoginAttemptRepository {
+    state: Arc<Mutex<MemoryState>>,
+}
+
+#[async_trait::async_trait]
+impl LoginAttemptRepository for MemoryLoginAttemptRepository {
+    async fn get_failed_logins(
+        &self,
+        key: &str,
+    ) -> ErrorOr<Option<FailedLogins>> {
+        lock(&self.state).login_attempts.get(key).copied().into()
+    }
+
+    async fn reserve_login_attempt(
+        &self,
+        key: &str,
+        previous: Option<&FailedLogins>,
+        reset_before: DateTime<Utc>,
+    ) -> ErrorOr<bool> {
+        let now = Utc::now();
+        let mut state = lock(&self.state);
+        let attempts = state.login_attempts.get_mut(key);
+        if attempts.as_deref() != previous {
+            return false.into();
+        }
+
+        match attempts {
+            Some(attempts) => {
+                if attempts.last_failure_at < reset_before {
+                    attempts.failures = 0;
+                }
+                attempts.failures += 1;
+                attempts.last_failure_at = now;
+            }
+            None => {
+                state.login_attempts.insert(
+                    key.to_string(),
+                    FailedLogins { failures: 1, last_failure_at: now },
+                );
+            }
+        }
+
+        true.into()
+    }
+
+    async fn release_login_attempt(&self, key: &str) -> ErrorOr<()> {
+        if let Some(attempts) = lock(&self.state).login_attempts.get_mut(key) {
+            attempts.failures = (attempts.failures - 1).max(0);
+        }
+
+        ().into()
+    }
+
+    async fn clear_failed_logins(&self, key: &str) -> ErrorOr<()> {
+        lock(&self.state).login_attempts.remove(key);
+
+        ().into()
+    }
+}
+
+#[derive(Clone)]
+pub struct MemoryUserTokenRepository {
+    state: Arc<Mutex<MemoryState>>,
+}
+
+#[async_trait::async_trait]
+impl UserTokenRepository for MemoryUserTokenRepository {
+    async fn create_token(
+        &self,
+        token_hash: &str,
+        purpose: TokenPurpose,
+        user_id: &i64,
+        email: &str,
+        expires_at: DateTime<Utc>,
+    ) -> ErrorOr<()> {
+        let mut state = lock(&self.state);
+        if !state.users.contains_key(user_id) {
+            Err(RepositoryError::Internal(eyre!(
+                "user_tokens_user_id_fkey: user {user_id} does not exist"
+            )))?;
+        }
+
+        state.user_tokens.retain(|_, (other_purpose, token)| {
+            token.user_id != *user_id || *other_purpose != purpose
+        });
+        let token = UserToken {
+            user_id: *user_id,
+            email: email.to_string(),
+            expires_at,
+        };
+        state.user_tokens.insert(token_hash.to_string(), (purpose, token));
+
+        ().into()
+    }
+
+    async fn consume_token(
+        &self,
+        token_hash: &str,
+        purpose: TokenPurpose,
+    ) -> ErrorOr<UserToken> {
+        let mut state = lock(&self.state);
+        let token = match state.user_tokens.get(token_hash) {
+            Some((other_purpose, _)) if *other_purpose == purpose => {
+                state.user_tokens.remove(token_hash).map(|(_, token)| token)
+            }
+            _ => None,
+        };
+
+        user_token::unexpired(token)
+    }
+}
+
+#[derive(Clone)]
+pub struct MemoryTotpRepository {
+    state: Arc<Mutex<MemoryState>>,
+}
+
+#[async_trait::async_trait]
+impl TotpRepository for MemoryTotpRepository {
+    async fn get_totp(&self, user_id: &i64) -> ErrorOr<Option<Totp>> {
+        lock(&self.state).user_totp.get(user_id).cloned().into()
+    }
+
+    async fn set_pending_totp(
+        &self,
+        user_id: &i64,
+        secret: &str,
+    ) -> ErrorOr<()> {
+        let mut state = lock(&self.state);
+        if !state.users.contains_key(user_id) {
+            Err(RepositoryError::Internal(eyre!(
+                "user_totp_user_id_fkey: user {user_id} does not exist"
+            )))?;
+        }
+
+        let totp = Totp {
+            user_id: *user_id,
+            secret: secret.to_string(),
+            confirmed_at: None,
+            last_used_step: None,
+        };
+        state.user_totp.insert(*user_id, totp);
+
+        ().into()
+    }
+
+    async fn confirm_totp(
+        &self,
+        user_id: &i64,
+        step: i64,
+        recovery_code_hashes: &[String],
+    ) -> ErrorOr<()> {
+        let mut state = lock(&self.state);
+        if let Some(totp) = state.user_totp.get_mut(user_id) {
+            totp.confirmed_at = Some(Utc::now());
+            totp.last_used_step = Some(step);
+        }
+
+        state.recovery_codes.retain(|(other_id, _)| other_id != user_id);
+        state.recovery_codes.extend(
+            recovery_code_hashes.iter().map(|hash| (*user_id, hash.clone())),
+        );
+
+        ().into()
+    }
+
+    async fn use_step(&self, user_id: &i64, step: i64) -> ErrorOr<bool> {
+        let mut state = lock(&self.state);
+        let totp = state
+            .user_totp
+            .get_mut(user_id)
+            .filter(|totp| totp.last_used_step.is_none_or(|last| last < step));
+
+        match totp {
+            Some(totp) => {
+                totp.last_used_step = Some(step);
+                true.into()
+            }
+            None => false.into(),
+        }
+    }
+
+    async fn use_recovery_code(
+        &self,
+        user_id: &i64,
+        code_hash: &str,
+    ) -> ErrorOr<bool> {
+        lock(&self.state)
+            .recovery_codes
+            .remove(&(*user_id, code_hash.to_string()))
+            .into()
+    }
+
+    async fn delete_totp(&self, user_id: &i64) -> ErrorOr<()> {
+        let mut state = lock(&self.state);
+        state.user_totp.remove(user_id);
+        state.recovery_codes.retain(|(other_id, _)| other_id != user_id);
+
+        ().into()
+    }
+}
+
+#[derive(Clone)]
+pub struct MemoryPasskeyRepository {
+    state: Arc<Mutex<MemoryState>>,
+}
+
+#[async_trait::async_trait]
+impl PasskeyRepository for MemoryPasskeyRepository {
+    async fn get_passkeys(&self, user_id: &i64) -> ErrorOr<Vec<StoredPasskey>> {
+        let passkeys = lock(&self.state)
+            .passkeys
+            .values()
+            .filter(|passkey| passkey.user_id == *user_id)
+            .cloned()
+            .collect::<Vec<_>>();
+
+        passkeys.into()
+    }
+
+    async fn create_passkey(
+        &self,
+        user_id: &i64,
+        name: &str,
+        passkey: &Passkey,
+    ) -> ErrorOr<StoredPasskey> {
+        let mut state = lock(&self.state);
+        if !state.users.contains_key(user_id) {
+            Err(RepositoryError::Internal(eyre!(
+                "passkeys_user_id_fkey: user {user_id} does not exist"
+            )))?;
+        }
+        let credential_id = passkey::credential_id(passkey);
+        let taken = state.passkeys.values().any(|other| {
+            passkey::credential_id(&other.passkey) == credential_id
+        });
+        if taken {
+            Err(RepositoryError::Internal(eyre!(
+                "passkeys_credential_id_key: the credential is already stored"
+            )))?;
+        }
+
+        state.last_passkey_id += 1;
+        let stored = StoredPasskey {
+            id: state.last_passkey_id,
+            user_id: *user_id,
+            name: name.to_string(),
+            passkey: Json(passkey.clone()),
+            created_at: Utc::now(),
+            last_used_at: None,
+        };
+        state.passkeys.insert(stored.id, stored.clone());
+
+        stored.into()
+    }
+
+    async fn record_passkey_use(
+        &self,
+        passkey_id: &i64,
+        passkey: &Passkey,
+    ) -> ErrorOr<()> {
+        if let Some(stored) = lock(&self.state).passkeys.get_mut(passkey_id) {
+            stored.passkey = Json(passkey.clone());
+            stored.last_used_at = Some(Utc::now());
+        }
+
+        ().into()
+    }
+
+    async fn delete_passkey(
+        &self,
+        passkey_id: &i64,
+        user_id: &i64,
+    ) -> ErrorOr<()> {
+        let mut state = lock(&self.state);
+        let owned = state
+            .passkeys
+            .get(passkey_id)
+            .is_some_and(|passkey| passkey.user_id == *user_id);
+        if !owned {
+            Err(RepositoryError::NotFound {
+                relation_name: passkey::RELATION.to_string(),
+            })?;
+        }
+
+        state.passkeys.remove(passkey_id);
+
+        ().into()
+    }
+}
+
+#[derive(Clone)]
+pub struct MemoryOidcIdentityRepository {
+    state: Arc<Mutex<MemoryState>>,
+}
+
+#[async_trait::async_trait]
+impl OidcIdentityRepository for MemoryOidcIdentityRepository {
+    async fn get_oidc_user_id(
+        &self,
+        issuer: &str,
+        subject: &str,
+    ) -> ErrorOr<Option<i64>> {
+        lock(&self.state)
+            .oidc_identities
+            .get(&(issuer.to_string(), subject.to_string()))
+            .copied()
+            .into()
+    }
+
+    async fn link_oidc_identity(
+        &self,
+        issuer: &str,
+        subject: &str,
+        user_id: &i64,
+    ) -> ErrorOr<()> {
+        let mut state = lock(&self.state);
+        if !state.users.contains_key(user_id) {
+            Err(RepositoryError::Internal(eyre!(
+                "oidc_identities_user_id_fkey: user {user_id} does not exist"
+            )))?;
+        }
+        let key = (issuer.to_string(), subject.to_string());
+        if state.oidc_identities.contains_key(&key) {
+            Err(RepositoryError::Internal(eyre!(
+                "oidc_identities_pkey: the account is already linked"
+            )))?;
+        }
+
+        state.oidc_identities.insert(key, *user_id);
+
+        ().into()
+    }
+}
+
+#[derive(Clone)]
+pub struct MemoryAccessTokenRepository {
+    state: Arc<Mutex<MemoryState>>,
+}
+
+#[async_trait::async_trait]
+impl AccessTokenRepository for MemoryAccessTokenRepository {
+    async fn get_access_tokens(
+        &self,
+        user_id: &i64,
+    ) -> ErrorOr<Vec<AccessToken>> {
+        let tokens = lock(&self.state)
+            .access_tokens
+            .values()
+            .filter(|(_, token)| token.user_id == *user_id)
+            .map(|(_, token)| token.clone())
+            .collect::<Vec<_>>();
+
+        tokens.into()
+    }
+
+    async fn create_access_token(
+        &self,
+        user_id: &i64,
+        token_hash: &str,
+        name: &str,
+        scopes: &[TokenScope],
+        expires_at: Option<DateTime<Utc>>,
+    ) -> ErrorOr<AccessToken> {
+        let mut state = lock(&self.state);
+        if !state.users.contains_key(user_id) {
+            Err(RepositoryError::Internal(eyre!(
+                "access_tokens_user_id_fkey: user {user_id} does not exist"
+            )))?;
+        }
+        if state.access_tokens.values().any(|(hash, _)| hash == token_hash) {
+            Err(RepositoryError::Internal(eyre!(
+                "access_tokens_token_hash_key: the token is already stored"
+            )))?;
+        }
+
+        state.last_access_token_id += 1;
+        let token = AccessToken {
+            id: state.last_access_token_id,
+            user_id: *user_id,
+            name: name.to_string(),
+            scopes: Json(scopes.to_vec()),
+            created_at: Utc::now(),
+            expires_at,
+            last_used_at: None,
+        };
+        state
+            .access_tokens
+            .insert(token.id, (token_hash.to_string(), token.clone()));
+
+        token.into()
+    }
+
+    async fn use_access_token(
+        &self,
+        token_hash: &str,
+    ) -> ErrorOr<Option<AccessToken>> {
+        let now = Utc::now();
+        let mut state = lock(&self.state);
+        let token = state
+            .access_tokens
+            .values_mut()
+            .find(|(hash, token)| {
+                hash == token_hash
+                    && token
+                        .expires_at
+                        .is_none_or(|expires_at| expires_at > now)
+            })
+            .map(|(_, token)| {
+                token.last_used_at = Some(now);
+                token.clone()
+            });
+
+        token.into()
+    }
+
+    async fn delete_access_token(
+        &self,
+        token_id: &i64,
+        user_id: &i64,
+    ) -> ErrorOr<()> {
+        let mut state = lock(&self.state);
+        let owned = state
+            .access_tokens
+            .get(token_id)
+            .is_some_and(|(_, token)| token.user_id == *user_id);
+        if !owned {
+            Err(RepositoryError::NotFound {
+                relation_name: access_token::RELATION.to_string(),
+            })?;
+        }
+
+        state.access_tokens.remove(token_id);
+
+        ().into()
+    }
+
+    async fn delete_user_access_tokens(&self, user_id: &i64) -> ErrorOr<u64> {
+        let mut state = lock(&self.state);
+        let before = state.access_tokens.len();
+        state.access_tokens.retain(|_, (_, token)| token.user_id != *user_id);
+
+        ((before - state.access_tokens.len()) as u64).into()
+    }
+}
+
+#[derive(Clone)]
+pub struct MemoryListRepository {
+    state: Arc<Mutex<MemoryState>>,
+}
+
+fn to_todo_list(list: &StoredList, permission: ListPermission) -> TodoList {
+    TodoList {
+        id: list.id,
+        name: list.name.clone(),
+        owner: list.owner,
+        permission,
+        created_at: list.created_at,
+        updated_at: list.updated_at,
+    }
+}
+
+#[async_trait::async_trait]
+impl ListRepository for MemoryListRepository {
+    async fn get_lists(&self, user_id: &i64) -> ErrorOr<Vec<TodoList>> {
+        let state = lock(&self.state);
+        let lists = state
+            .lists
+            .values()
+            .filter_map(|list| {
+                let permission = state.list_permission(list.id, *user_id)?;
+                Some(to_todo_list(list, permission))
+            })
+            .collect::<Vec<_>>();
+
+        lists.into()
+    }
+
+    async fn get_list(
+        &self,
+        list_id: &i64,
+        user_id: &i64,
+    ) -> ErrorOr<TodoList> {
+        let state = lock(&self.state);
+        let list = state
+            .lists
+            .get(list_id)
+            .zip(state.list_permission(*list_id, *user_id))
+            .map(|(list, permission)| to_todo_list(list, permission))
+            .ok_or_else(|| RepositoryError::NotFound {
+                relation_name: list::RELATION.to_string(),
+            })?;
+
+        list.into()
+    }
+
+    async fn create_list(
+        &self,
+        create_list: &CreateList,
+        user_id: &i64,
+    ) -> ErrorOr<TodoList> {
+        let mut state = lock(&self.state);
+        if !state.users.contains_key(user_id) {
+            Err(RepositoryError::Internal(eyre!(
+                "lists_owner_fkey: user {user_id} does not exist"
+            )))?;
+        }
+
+        state.last_list_id += 1;
+        let now = Utc::now();
+        let list = StoredList {
+            id: state.last_list_id,
+            name: create_list.name.clone(),
+            owner: *user_id,
+            created_at: now,
+            updated_at: now,
+        };
+        state.lists.insert(list.id, list.clone());
+        state
+            .list_members
+            .insert((list.id, *user_id), (ListPermission::Admin, now));
+
+        to_todo_list(&list, ListPermission::Admin).into()
+    }
+
+    async fn rename_list(&self, list_id: &i64, name: &str) -> ErrorOr<()> {
+        if let Some(list) = lock(&self.state).lists.get_mut(list_id) {
+            list.name = name.to_string();
+            list.updated_at = Utc::now();
+        }
+
+        ().into()
+    }
+
+    async fn delete_list(&self, list_id: &i64) -> ErrorOr<()> {
+        lock(&self.state).delete_list(*list_id);
+
+        ().into()
+    }
+
+    async fn get_members(&self, list_id: &i64) -> ErrorOr<Vec<ListMember>> {
+        let state = lock(&self.state);
+        let mut members = state
+            .list_members
+            .range((*list_id, i64::MIN)..=(*list_id, i64::MAX))
+            .filter_map(|((_, user_id), (permission, joined_at))| {
+                let user = state.users.get(user_id)?;
+                Some(ListMember {
+                    user_id: user.id,
+                    name: user.name.clone(),
+                    email: user.email.clone(),
+                    permission: *permission,
+                    joined_at: *joined_at,
+                })
+            })
+            .collect::<Vec<_>>();
+        members.sort_by_key(|member| (member.joined_at, member.user_id));
+
+        members.into()
+    }
+
+    async fn set_member_permission(
+        &self,
+        list_id: &i64,
+        user_id: &i64,
+        permission: ListPermission,
+    ) -> ErrorOr<()> {
+        let mut state = lock(&self.state);
+        let member = state
+            .list_members
+            .get_mut(&(*list_id, *user_id))
+            .ok_or_else(|| RepositoryError::NotFound {
+                relation_name: list::MEMBER_RELATION.to_string(),
+            })?;
+        member.0 = permission;
+
+        ().into()
+    }
+
+    async fn remove_member(
+        &self,
+        list_id: &i64,
+        user_id: &i64,
+        removed_by: &i64,
+    ) -> ErrorOr<()> {
+        let now = Utc::now();
+        let mut state = lock(&self.state);
+        state.list_members.remove(&(*list_id, *user_id)).ok_or_else(|| {
+            RepositoryError::NotFound {
+                relation_name: list::MEMBER_RELATION.to_string(),
+            }
+        })?;
+        let mut unassigned = Vec::new();
+        for todo in state.todos.values_mut() {
+            if todo.list_id == Some(*list_id)
+                && todo.assignee_id == Some(*user_id)
+            {
+                todo.assignee_id = None;
+                unassigned.push(todo.id);
+            }
+        }
+        for todo_id in unassigned {
+            state.last_todo_activity_id += 1;
+            let activity = TodoActivity {
+                id: state.last_todo_activity_id,
+                todo_id,
+                actor_id: Some(*removed_by),
+                action: TodoAction::Assigned,
+                previous_assignee_id: Some(*user_id),
+                assignee_id: None,
+                created_at: now,
+            };
+            state.todo_activities.insert(activity.id, activity);
+        }
+
+        ().into()
+    }
+
+    async fn create_invitation(
+        &self,
+        list_id: &i64,
+        user_id: &i64,
+        invited_by: &i64,
+        permission: ListPermission,
+    ) -> ErrorOr<()> {
+        let mut state = lock(&self.state);
+        if !state.lists.contains_key(list_id)
+            || !state.users.contains_key(user_id)
+            || !state.users.contains_key(invited_by)
+        {
+            Err(RepositoryError::Internal(eyre!(
+                "list_invitations: list {list_id} or user {user_id} does not \
+                 exist"
+            )))?;
+        }
+
+        let invitation = StoredInvitation {
+            list_id: *list_id,
+            user_id: *user_id,
+            invited_by: *invited_by,
+            permission,
+            created_at: Utc::now(),
+        };
+        // like `ON CONFLICT (list_id, user_id) DO UPDATE` it keeps the id
+        let existing = state.list_invitations.iter().find_map(|(id, other)| {
+            (other.list_id == *list_id && other.user_id == *user_id)
+                .then_some(*id)
+        });
+        let id = match existing {
+            Some(id) => id,
+            None => {
+                state.last_list_invitation_id += 1;
+                state.last_list_invitation_id
+            }
+        };
+        state.list_invitations.insert(id, invitation);
+
+        ().into()
+    }
+
+    async fn get_invitations(
+        &self,
+        user_id: &i64,
+    ) -> ErrorOr<Vec<ListInvitation>> {
+        let state = lock(&self.state);
+        let invitations = state
+            .list_invitations
+            .iter()
+            .filter(|(_, invitation)| invitation.user_id == *user_id)
+            .filter_map(|(id, invitation)| {
+                let list = state.lists.get(&invitation.list_id)?;
+                let inviter = state.users.get(&invitation.invited_by)?;
+                Some(ListInvitation {
+                    id: *id,
+                    list_id: list.id,
+                    list_name: list.name.clone(),
+                    invited_by: inviter.name.clone(),
+                    permission: invitation.permission,
+                    created_at: invitation.created_at,
+                })
+            })
+            .collect::<Vec<_>>();
+
+        invitations.into()
+    }
+
+    async fn accept_invitation(
+        &self,
+        invitation_id: &i64,
+        user_id: &i64,
+    ) -> ErrorOr<()> {
+        let mut state = lock(&self.state);
+        let invitation = state
+            .list_invitations
+            .get(invitation_id)
+            .filter(|invitation| invitation.user_id == *user_id)
+            .cloned()
+            .ok_or_else(|| RepositoryError::NotFound {
+                relation_name: list::INVITATION_RELATION.to_string(),
+            })?;
+
+        state.list_invitations.remove(invitation_id);
+        // a member keeps their permission if they were invited again
+        state
+            .list_members
+            .entry((invitation.list_id, *user_id))
+            .or_insert((invitation.permission, Utc::now()));
+
+        ().into()
+    }
+
+    async fn decline_invitation(
+        &self,
+        invitation_id: &i64,
+        user_id: &i64,
+    ) -> ErrorOr<()> {
+        let mut state = lock(&self.state);
+        let invited = state
+            .list_invitations
+            .get(invitation_id)
+            .is_some_and(|invitation| invitation.user_id == *user_id);
+        if !invited {
+            Err(RepositoryError::NotFound {
+                relation_name: list::INVITATION_RELATION.to_string(),
+            })?;
+        }
+
+        state.list_invitations.remove(invitation_id);
+
+        ().into()
+    }
+}
+
+#[derive(Clone)]
+pub struct MemoryCommentRepository {
+    state: Arc<Mutex<MemoryState>>,
+}
+
+/// Mentions are a set, like the primary key of `comment_mentions`.
+fn to_mentions(user_ids: &[i64]) -> Vec<i64> {
+    let mut mentions = user_ids.to_vec();
+    mentions.sort_unstable();
+    mentions.dedup();
+    mentions
+}
+
+#[async_trait::async_trait]
+impl CommentRepository for MemoryCommentRepository {
+    async fn get_comments(
+        &self,
+        todo_id: &i64,
+        offset: i64,
+        limit: i64,
+    ) -> ErrorOr<Vec<Comment>> {
+        let state = lock(&self.state);
+        let comments = state
+            .comments
+            .values()
+            .filter(|comment| comment.todo_id == *todo_id)
+            .skip(offset as usize)
+            .take(limit as usize)
+            .map(|comment| state.to_comment(comment))
+            .collect::<Vec<_>>();
+
+        comments.into()
+    }
+
+    async fn create_comment(
+        &self,
+        todo_id: &i64,
+        create_comment: &CreateComment,
+        author_id: &i64,
+    ) -> ErrorOr<Comment> {
+        let mut state = lock(&self.state);
+        let mentions = to_mentions(&create_comment.mentions);
+        if !state.todos.contains_key(todo_id)
+            || !state.users.contains_key(author_id)
+            || mentions.iter().any(|user_id| !state.users.contains_key(user_id))
+        {
+            Err(RepositoryError::Internal(eyre!(
+                "comments: todo {todo_id} or a user does not exist"
+            )))?;
+        }
+
+        state.last_comment_id += 1;
+        let now = Utc::now();
+        let comment = Comment {
+            id: state.last_comment_id,
+            todo_id: *todo_id,
+            author_id: *author_id,
+            author_name: String::new(),
+            body: create_comment.body.clone(),
+            mentions,
+            created_at: now,
+            updated_at: now,
+        };
+        state.comments.insert(comment.id, comment.clone());
+
+        state.to_comment(&comment).into()
+    }
+
+    async fn update_comment(
+        &self,
+        todo_id: &i64,
+        comment_id: &i64,
+        update_comment: &UpdateComment,
+        author_id: &i64,
+    ) -> ErrorOr<Comment> {
+        let mut state = lock(&self.state);
+        let mentions = to_mentions(&update_comment.mentions);
+        if mentions.iter().any(|user_id| !state.users.contains_key(user_id)) {
+            Err(RepositoryError::Internal(eyre!(
+                "comment_mentions_user_id_fkey: a user does not exist"
+            )))?;
+        }
+        let comment = match state.comments.get_mut(comment_id) {
+            Some(comment) if comment.todo_id == *todo_id => comment,
+            _ => Err(RepositoryError::NotFound {
+                relation_name: comment::RELATION.to_string(),
+            })?,
+        };
+        comment::check_author(
+            Some(comment.author_id),
+            *author_id,
+            Operation::Update,
+        )?;
+
+        comment.body = update_comment.body.clone();
+        comment.mentions = mentions;
+        comment.updated_at = Utc::now();
+        let comment = comment.clone();
+
+        state.to_comment(&comment).into()
+    }
+
+    async fn delete_comment(
+        &self,
+        todo_id: &i64,
+        comment_id: &i64,
+        author_id: &i64,
+    ) -> ErrorOr<()> {
+        let mut state = lock(&self.state);
+        let comment_author_id = state
+            .comments
+            .get(comment_id)
+            .filter(|comment| comment.todo_id == *todo_id)
+            .map(|comment| comment.author_id);
+        comment::check_author(
+            comment_author_id,
+            *author_id,
+            Operation::Delete,
+        )?;
+        state.comments.remove(comment_id);
+
+        ().into()
+    }
+}
+
+#[derive(Clone)]
+pub struct MemoryAuditRepository {
+    state: Arc<Mutex<MemoryState>>,
+}
+
+/// Returns a page of `events`, which are in the order of the page already.
+fn page<'a, T: Clone + 'a>(
+    events: impl Iterator<Item = &'a T>,
+    offset: i64,
+    limit: i64,
+) -> Vec<T> {
+    events
+        .skip(offset.max(0) as usize)
+        .take(limit.max(0) as usize)
+        .cloned()
+        .collect()
+}
+
+#[async_trait::async_trait]
+impl AuditRepository for MemoryAuditRepository {
+    async fn get_history(
+        &self,
+        entity: AuditEntity,
+        entity_id: &i64,
+        offset: i64,
+        limit: i64,
+    ) -> ErrorOr<Vec<AuditEvent>> {
+        let state = lock(&self.state);
+        let events = state.audit_events.values().filter(|event| {
+            event.entity == entity && event.entity_id == *entity_id
+        });
+
+        page(events, offset, limit).into()
+    }
+
+    async fn get_account_activity(
+        &self,
+        user_id: &i64,
+        offset: i64,
+        limit: i64,
+    ) -> ErrorOr<Vec<AuditEvent>> {
+        let state = lock(&self.state);
+        let events = state.audit_events.values().rev().filter(|event| {
+            event.actor_id == Some(*user_id)
+                || (event.entity == AuditEntity::User
+                    && event.entity_id == *user_id)
+        });
+
+        page(events, offset, limit).into()
+    }
+
+    async fn record_security_event(
+        &self,
+        event: &NewSecurityEvent,
+    ) -> ErrorOr<()> {
+        let mut state = lock(&self.state);
+        state.last_security_event_id += 1;
+        let event = SecurityEvent {
+            id: state.last_security_event_id,
+            user_id: event.user_id,
+            actor_id: event.actor_id,
+            kind: event.kind,
+            ip: event.ip.clone(),
+            request_id: event.request_id.clone(),
+            created_at: Utc::now(),
+        };
+        state.security_events.insert(event.id, event);
+
+        ().into()
+    }
+
+    async fn get_security_events(
+        &self,
+        user_id: Option<i64>,
+        offset: i64,
+        limit: i64,
+    ) -> ErrorOr<Vec<SecurityEvent>> {
+        let state = lock(&self.state);
+        let events = state
+            .security_events
+            .values()
+            .rev()
+            .filter(|event| user_id.is_none() || event.user_id == user_id);
+
+        page(events, offset, limit).into()
+    }
+}
+
+#[derive(Clone)]
+pub struct MemoryAttachmentRepository {
+    state: Arc<Mutex<MemoryState>>,
+}
+
+#[async_trait::async_trait]
+impl AttachmentRepository for MemoryAttachmentRepository {
+    async fn get_attachments(&self, todo_id: &i64) -> ErrorOr<Vec<Attachment>> {
+        let state = lock(&self.state);
+        let attachments = state
+            .attachments
+            .values()
+            .filter(|attachment| attachment.todo_id == *todo_id)
+            .cloned()
+            .collect::<Vec<_>>();
+
+        attachments.into()
+    }
+
+    async fn get_attachment(
+        &self,
+        todo_id: &i64,
+        attachment_id: &i64,
+    ) -> ErrorOr<Attachment> {
+        let state = lock(&self.state);
+        let attachment = state
+            .attachments
+            .get(attachment_id)
+            .filter(|attachment| attachment.todo_id == *todo_id)
+            .ok_or_else(|| RepositoryError::NotFound {
+                relation_name: attachment::RELATION.to_string(),
+            })?;
+
+        attachment.clone().into()
+    }
+
+    async fn create_attachment(
+        &self,
+        attachment: &NewAttachment,
+        quota: i64,
+    ) -> ErrorOr<Option<Attachment>> {
+        let mut state = lock(&self.state);
+        if !state.todos.contains_key(&attachment.todo_id) {
+            Err(RepositoryError::Internal(eyre!(
+                "attachments_todo_id_fkey: todo {} does not exist",
+                attachment.todo_id
+            )))?;
+        }
+        if state
+            .attachments
+            .values()
+            .any(|stored| stored.storage_key == attachment.storage_key)
+        {
+            Err(RepositoryError::Internal(eyre!(
+                "attachments_storage_key_key: `{}` is taken",
+                attachment.storage_key
+            )))?;
+        }
+
+        let usage = state
+            .attachments
+            .values()
+            .filter(|stored| stored.uploader_id == Some(attachment.uploader_id))
+            .map(|stored| stored.size)
+            .sum::<i64>();
+        if usage + attachment.size > quota {
+            return None.into();
+        }
+
+        state.last_attachment_id += 1;
+        let attachment = Attachment {
+            id: state.last_attachment_id,
+            todo_id: attachment.todo_id,
+            uploader_id: Some(attachment.uploader_id),
+            file_name: attachment.file_name.clone(),
+            size: attachment.size,
+            content_type: attachment.content_type.clone(),
+            checksum: attachment.checksum.clone(),
+            storage_key: attachment.storage_key.clone(),
+            created_at: Utc::now(),
+        };
+        state.attachments.insert(attachment.id, attachment.clone());
+
+        Some(attachment).into()
+    }
+
+    async fn delete_attachment(&self, attachment_id: &i64) -> ErrorOr<()> {
+        lock(&self.state).delete_attachment(*attachment_id);
+
+        ().into()
+    }
+
+    async fn get_usage(&self, user_id: &i64) -> ErrorOr<i64> {
+        let state = lock(&self.state);
+        let usage = state
+            .attachments
+            .values()
+            .filter(|attachment| attachment.uploader_id == Some(*user_id))
+            .map(|attachment| attachment.size)
+            .sum::<i64>();
+
+        usage.into()
+    }
+
+    async fn get_orphaned_blobs(&self, limit: i64) -> ErrorOr<Vec<String>> {
+        let state = lock(&self.state);
+        let storage_keys = state
+            .orphaned_blobs
+            .iter()
+            .take(limit.max(0) as usize)
+            .cloned()
+            .collect::<Vec<_>>();
+
+        storage_keys.into()
+    }
+
+    async fn forget_orphaned_blob(&self, storage_key: &str) -> ErrorOr<()> {
+        lock(&self.state).orphaned_blobs.retain(|key| key != storage_key);
+
+        ().into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_http::StatusCode;
+    use actix_web::ResponseError;
+
+    use super::*;
+
+    async fn create_user(backend: &MemoryBackend, email: &str) -> i64 {
+        let users = backend.user_repository();
+        users
+            .create_user(
+                &CreateUser {
+                    name: "Jane".to_string(),
+                    email: email.to_string(),
+                    password: "hash".to_string(),
+                },
+                &AuditContext::default(),
+            )
+            .await
+            .0
+            .unwrap();
+
+        users.get_user_by_email(email).await.0.unwrap().id
+    }
+
+    fn status<T: std::fmt::Debug>(result: ErrorOr<T>) -> StatusCode {
+        result.0.unwrap_err().status_code()
+    }
+
+    #[actix_rt::test]
+    async fn todos_follow_the_postgres_ownership_rules() {
+        let backend = MemoryBackend::new();
+        let todos = backend.todo_repository();
+        let owner = create_user(&backend, "owner@example.com").await;
+        let other = create_user(&backend, "other@example.com").await;
+
+        let todo = todos
+            .create_todo(
+                &CreateTodo {
+                    title: "Water the plants".to_string(),
+                    description: String::new(),
+                    list_id: None,
+                    due_at: None,
+                },
+                &owner,
+                &AuditContext::default(),
+            )
+            .await
+            .0
+            .unwrap();
+        let done = UpdateTodo {
+            id: todo.id,
+            title: None,
+            description: None,
+            is_done: Some(true),
+            due_at: None,
+        };
+
+        assert_eq!(status(todos.get_todo(&42, &owner).await), 404);
+        assert_eq!(status(todos.get_todo(&todo.id, &other).await), 403);
+        assert_eq!(
+            status(
+                todos
+                    .update_todo(&done, &other, &AuditContext::default())
+                    .await
+            ),
+            403
+        );
+        assert!(
+            todos
+                .update_todo(&done, &owner, &AuditContext::default())
+                .await
+                .0
+                .unwrap()
+                .is_done
+        );
+        assert_eq!(
+            status(
+                todos
+                    .create_todo(
+                        &CreateTodo::default(),
+                        &42,
+                        &AuditContext::default()
+                    )
+                    .await
+            ),
+            500
+        );
+
+        todos
+            .delete_todo(&todo.id, &other, &AuditContext::default())
+            .await
+            .0
+            .unwrap();
+        assert_eq!(todos.get_todos(&owner).await.0.unwrap().len(), 1);
+        todos
+            .delete_todo(&todo.id, &owner, &AuditContext::default())
+            .await
+            .0
+            .unwrap();
+        assert!(todos.get_todos(&owner).await.0.unwrap().is_empty());
+    }
+
+    #[actix_rt::test]
+    async fn users_follow_the_postgres_constraints() {
+        let backend = MemoryBackend::new();
+        let users = backend.user_repository();
+        let jane = create_user(&backend, "jane@example.com").await;
+        let john = create_user(&backend, "john@example.com").await;
+
+        let duplicate = CreateUser {
+            name: "Jane".to_string(),
+            email: "jane@example.com".to_string(),
+            password: "hash".to_string(),
+        };
+        assert_eq!(
+            status(
+                users.create_user(&duplicate, &AuditContext::default()).await
+            ),
+            409
+        );
+
+        let steal_email = UpdateUser {
+            name: None,
+            email: Some("jane@example.com".to_string()),
+            password: None,
+            current_password: None,
+        };
+        assert_eq!(
+            status(
+                users
+                    .update_user(&steal_email, &john, &AuditContext::default())
+                    .await
+            ),
+            409
+        );
+        users
+            .update_user(&steal_email, &jane, &AuditContext::default())
+            .await
+            .0
+            .unwrap();
+
+        backend
+            .todo_repository()
+            .create_todo(
+                &CreateTodo::default(),
+                &john,
+                &AuditContext::default(),
+            )
+            .await
+            .0
+            .unwrap();
+        assert_eq!(
+            status(users.delete_user(&john, &AuditContext::default()).await),
+            500
+        );
+        users.delete_user(&jane, &AuditContext::default()).await.0.unwrap();
+        assert_eq!(status(users.get_session_user(&jane).await), 404);
+        assert_eq!(users.count_users().await.0.unwrap(), 1);
+    }
+
+    #[actix_rt::test]
+    async fn lists_are_deleted_with_their_owner() {
+        let backend = MemoryBackend::new();
+        let lists = backend.list_repository();
+        let todos = backend.todo_repository();
+        let users = backend.user_repository();
+        let jane = create_user(&backend, "jane@example.com").await;
+        let john = create_user(&backend, "john@example.com").await;
+
+        let list = lists
+            .create_list(&CreateList { name: "Chores".to_string() }, &jane)
+            .await
+            .0
+            .unwrap();
+        lists
+            .create_invitation(&list.id, &john, &jane, ListPermission::Editor)
+            .await
+            .0
+            .unwrap();
+        let invitation = lists.get_invitations(&john).await.0.unwrap()[0].id;
+        lists.accept_invitation(&invitation, &john).await.0.unwrap();
+        let in_list =
+            CreateTodo { list_id: Some(list.id), ..CreateTodo::default() };
+        todos
+            .create_todo(&in_list, &jane, &AuditContext::default())
+            .await
+            .0
+            .unwrap();
+        todos
+            .create_todo(&in_list, &john, &AuditContext::default())
+            .await
+            .0
+            .unwrap();
+
+        // like `ON DELETE CASCADE` the todos of the list go with it, even
+        // those of other members
+        users.delete_user(&jane, &AuditContext::default()).await.0.unwrap();
+        assert!(todos.get_todos(&john).await.0.unwrap().is_empty());
+        assert!(lists.get_lists(&john).await.0.unwrap().is_empty());
+        assert_eq!(status(lists.get_list(&list.id, &john).await), 404);
+        users.delete_user(&john, &AuditContext::default()).await.0.unwrap();
+    }
+}
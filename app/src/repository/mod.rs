@@ -1,4 +1,156 @@
+use access_token::{AccessTokenRepository, PostgresAccessTokenRepository};
+use attachment::{AttachmentRepository, PostgresAttachmentRepository};
+use audit::{AuditRepository, PostgresAuditRepository};
+use comment::{CommentRepository, PostgresCommentRepository};
+use list::{ListRepository, PostgresListRepository};
+use login_attempt::{LoginAttemptRepository, PostgresLoginAttemptRepository};
+use oidc::{OidcIdentityRepository, PostgresOidcIdentityRepository};
+use passkey::{PasskeyRepository, PostgresPasskeyRepository};
+use session::{PostgresSessionRepository, SessionRepository};
+use todo::{PostgresTodoRepository, TodoRepository};
+use totp::{PostgresTotpRepository, TotpRepository};
+use user::{PostgresUserRepository, UserRepository};
+use user_token::{PostgresUserTokenRepository, UserTokenRepository};
+
+pub mod access_token;
+pub mod attachment;
+pub mod audit;
+pub mod comment;
 pub mod error;
+pub mod list;
+pub mod login_attempt;
+pub mod memory;
+pub mod oidc;
+pub mod passkey;
 pub mod session;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
 pub mod todo;
+pub mod totp;
 pub mod user;
+pub mod user_token;
+
+/// A storage backend, bundles one implementation of every repository.
+///
+/// The controllers and admin commands are generic over the backend, which
+/// allows the binary to pick the database at runtime.
+pub trait Backend: Clone + Send + Sync + 'static {
+    type Todo: TodoRepository;
+    type User: UserRepository;
+    type Session: SessionRepository;
+    type LoginAttempt: LoginAttemptRepository;
+    type UserToken: UserTokenRepository;
+    type Totp: TotpRepository;
+    type Passkey: PasskeyRepository;
+    type OidcIdentity: OidcIdentityRepository;
+    type AccessToken: AccessTokenRepository;
+    type List: ListRepository;
+    type Comment: CommentRepository;
+    type Audit: AuditRepository;
+    type Attachment: AttachmentRepository;
+
+    fn todo_repository(&self) -> Self::Todo;
+
+    fn user_repository(&self) -> Self::User;
+
+    fn session_repository(&self) -> Self::Session;
+
+    fn login_attempt_repository(&self) -> Self::LoginAttempt;
+
+    fn user_token_repository(&self) -> Self::UserToken;
+
+    fn totp_repository(&self) -> Self::Totp;
+
+    fn passkey_repository(&self) -> Self::Passkey;
+
+    fn oidc_identity_repository(&self) -> Self::OidcIdentity;
+
+    fn access_token_repository(&self) -> Self::AccessToken;
+
+    fn list_repository(&self) -> Self::List;
+
+    fn comment_repository(&self) -> Self::Comment;
+
+    fn audit_repository(&self) -> Self::Audit;
+
+    fn attachment_repository(&self) -> Self::Attachment;
+}
+
+#[derive(Clone)]
+pub struct PostgresBackend {
+    pool: sqlx::PgPool,
+}
+
+impl PostgresBackend {
+    pub fn new(pool: sqlx::PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+impl Backend for PostgresBackend {
+    type Todo = PostgresTodoRepository;
+    type User = PostgresUserRepository;
+    type Session = PostgresSessionRepository;
+    type LoginAttempt = PostgresLoginAttemptRepository;
+    type UserToken = PostgresUserTokenRepository;
+    type Totp = PostgresTotpRepository;
+    type Passkey = PostgresPasskeyRepository;
+    type OidcIdentity = PostgresOidcIdentityRepository;
+    type AccessToken = PostgresAccessTokenRepository;
+    type List = PostgresListRepository;
+    type Comment = PostgresCommentRepository;
+    type Audit = PostgresAuditRepository;
+    type Attachment = PostgresAttachmentRepository;
+
+    fn todo_repository(&self) -> Self::Todo {
+        PostgresTodoRepository::new(self.pool.clone())
+    }
+
+    fn user_repository(&self) -> Self::User {
+        PostgresUserRepository::new(self.pool.clone())
+    }
+
+    fn session_repository(&self) -> Self::Session {
+        PostgresSessionRepository::new(self.pool.clone())
+    }
+
+    fn login_attempt_repository(&self) -> Self::LoginAttempt {
+        PostgresLoginAttemptRepository::new(self.pool.clone())
+    }
+
+    fn user_token_repository(&self) -> Self::UserToken {
+        PostgresUserTokenRepository::new(self.pool.clone())
+    }
+
+    fn totp_repository(&self) -> Self::Totp {
+        PostgresTotpRepository::new(self.pool.clone())
+    }
+
+    fn passkey_repository(&self) -> Self::Passkey {
+        PostgresPasskeyRepository::new(self.pool.clone())
+    }
+
+    fn oidc_identity_repository(&self) -> Self::OidcIdentity {
+        PostgresOidcIdentityRepository::new(self.pool.clone())
+    }
+
+    fn access_token_repository(&self) -> Self::AccessToken {
+        PostgresAccessTokenRepository::new(self.pool.clone())
+    }
+
+    fn list_repository(&self) -> Self::List {
+        PostgresListRepository::new(self.pool.clone())
+    }
+
+    fn comment_repository(&self) -> Self::Comment {
+        PostgresCommentRepository::new(self.pool.clone())
+    }
+
+    fn audit_repository(&self) -> Self::Audit {
+        PostgresAuditRepository::new(self.pool.clone())
+    }
+
+    fn attachment_repository(&self) -> Self::Attachment {
+        PostgresAttachmentRepository::new(self.pool.clone())
+    }
+}
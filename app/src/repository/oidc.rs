@@ -0,0 +1,82 @@
+use super::error::RepositoryError;
+use crate::util::error_or::ErrorOr;
+
+/// Links the accounts of OpenID Connect providers to lentos users.
+///
+/// An account is identified by the issuer of the provider and the subject it
+/// assigned to the account, emails may change or be reassigned.
+#[async_trait::async_trait]
+pub trait OidcIdentityRepository: Send + Sync + 'static {
+    /// Returns the user the account is linked to, if any.
+    async fn get_oidc_user_id(
+        &self,
+        issuer: &str,
+        subject: &str,
+    ) -> ErrorOr<Option<i64>>;
+
+    async fn link_oidc_identity(
+        &self,
+        issuer: &str,
+        subject: &str,
+        user_id: &i64,
+    ) -> ErrorOr<()>;
+}
+
+pub struct PostgresOidcIdentityRepository {
+    pool: sqlx::PgPool,
+}
+
+impl PostgresOidcIdentityRepository {
+    pub fn new(pool: sqlx::PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl OidcIdentityRepository for PostgresOidcIdentityRepository {
+    async fn get_oidc_user_id(
+        &self,
+        issuer: &str,
+        subject: &str,
+    ) -> ErrorOr<Option<i64>> {
+        let db_response = sqlx::query_scalar!(
+            r#"
+            SELECT user_id
+            FROM oidc_identities
+            WHERE issuer = $1 AND subject = $2
+            "#,
+            issuer,
+            subject
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        db_response.into()
+    }
+
+    async fn link_oidc_identity(
+        &self,
+        issuer: &str,
+        subject: &str,
+        user_id: &i64,
+    ) -> ErrorOr<()> {
+        sqlx::query!(
+            r#"
+            INSERT
+            INTO oidc_identities (issuer, subject, user_id)
+            VALUES ($1, $2, $3)
+            "#,
+            issuer,
+            subject,
+            user_id
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        ().into()
+    }
+}
@@ -0,0 +1,187 @@
+use chrono::{DateTime, Utc};
+use data_encoding::BASE64URL_NOPAD;
+use shared::models::user::PasskeySummary;
+use sqlx::types::Json;
+use webauthn_rs::prelude::Passkey;
+
+use super::error::RepositoryError;
+use crate::util::error_or::ErrorOr;
+
+pub(crate) const RELATION: &str = "Passkey";
+
+/// A WebAuthn credential of a user.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct StoredPasskey {
+    pub id: i64,
+    pub user_id: i64,
+    pub name: String,
+    pub passkey: Json<Passkey>,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+}
+
+impl From<StoredPasskey> for PasskeySummary {
+    fn from(stored: StoredPasskey) -> Self {
+        Self {
+            id: stored.id,
+            name: stored.name,
+            created_at: stored.created_at,
+            last_used_at: stored.last_used_at,
+        }
+    }
+}
+
+/// Returns the id of the credential as stored in `credential_id`.
+pub(crate) fn credential_id(passkey: &Passkey) -> String {
+    BASE64URL_NOPAD.encode(passkey.cred_id().as_ref())
+}
+
+/// Stores the passkeys of the users.
+#[async_trait::async_trait]
+pub trait PasskeyRepository: Send + Sync + 'static {
+    /// Returns the passkeys of a user, oldest first.
+    async fn get_passkeys(&self, user_id: &i64) -> ErrorOr<Vec<StoredPasskey>>;
+
+    async fn create_passkey(
+        &self,
+        user_id: &i64,
+        name: &str,
+        passkey: &Passkey,
+    ) -> ErrorOr<StoredPasskey>;
+
+    /// Stores the updated signature counter of a passkey after a login with
+    /// it.
+    async fn record_passkey_use(
+        &self,
+        passkey_id: &i64,
+        passkey: &Passkey,
+    ) -> ErrorOr<()>;
+
+    /// Fails with `NotFound` unless the passkey belongs to the user.
+    async fn delete_passkey(
+        &self,
+        passkey_id: &i64,
+        user_id: &i64,
+    ) -> ErrorOr<()>;
+}
+
+pub struct PostgresPasskeyRepository {
+    pool: sqlx::PgPool,
+}
+
+impl PostgresPasskeyRepository {
+    pub fn new(pool: sqlx::PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl PasskeyRepository for PostgresPasskeyRepository {
+    async fn get_passkeys(&self, user_id: &i64) -> ErrorOr<Vec<StoredPasskey>> {
+        let db_response = sqlx::query_as!(
+            StoredPasskey,
+            r#"
+            SELECT
+                id,
+                user_id,
+                name,
+                passkey AS "passkey: Json<Passkey>",
+                created_at,
+                last_used_at
+            FROM passkeys
+            WHERE user_id = $1
+            ORDER BY id
+            "#,
+            user_id
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        db_response.into()
+    }
+
+    async fn create_passkey(
+        &self,
+        user_id: &i64,
+        name: &str,
+        passkey: &Passkey,
+    ) -> ErrorOr<StoredPasskey> {
+        let db_response = sqlx::query_as!(
+            StoredPasskey,
+            r#"
+            INSERT
+            INTO passkeys (user_id, credential_id, name, passkey)
+            VALUES ($1, $2, $3, $4)
+            RETURNING
+                id,
+                user_id,
+                name,
+                passkey AS "passkey: Json<Passkey>",
+                created_at,
+                last_used_at
+            "#,
+            user_id,
+            credential_id(passkey),
+            name,
+            Json(passkey) as _
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        db_response.into()
+    }
+
+    async fn record_passkey_use(
+        &self,
+        passkey_id: &i64,
+        passkey: &Passkey,
+    ) -> ErrorOr<()> {
+        sqlx::query!(
+            r#"
+            UPDATE passkeys
+            SET passkey = $2, last_used_at = now()
+            WHERE id = $1
+            "#,
+            passkey_id,
+            Json(passkey) as _
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        ().into()
+    }
+
+    async fn delete_passkey(
+        &self,
+        passkey_id: &i64,
+        user_id: &i64,
+    ) -> ErrorOr<()> {
+        let db_response = sqlx::query!(
+            r#"
+            DELETE
+            FROM passkeys
+            WHERE id = $1 AND user_id = $2
+            "#,
+            passkey_id,
+            user_id
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        if db_response.rows_affected() == 0 {
+            Err(RepositoryError::NotFound {
+                relation_name: RELATION.to_string(),
+            })?;
+        }
+
+        ().into()
+    }
+}
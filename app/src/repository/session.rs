@@ -3,6 +3,7 @@ use actix_session::storage::{
 };
 use actix_web::cookie::time::Duration;
 use anyhow::Context;
+use chrono::{DateTime, Utc};
 use rand::{distributions::Alphanumeric, rngs::OsRng, Rng};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -13,33 +14,61 @@ use std::collections::HashMap;
 /// CREATE TABLE sessions (
 ///   key char(64) NOT NULL UNIQUE,
 ///   state jsonb NOT NULL,
+///   user_id bigint NULL,
+///   expires_at timestamptz NOT NULL,
 ///   CONSTRAINT sessions_pkey PRIMARY KEY (key)
 /// );
 /// ```
 /// also one should use this index to increase query performance:
 /// CREATE INDEX session_key_index ON sessions USING hash (key);
-#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[derive(
+    Serialize, Deserialize, Debug, Clone, PartialEq, Eq, sqlx::FromRow,
+)]
 pub struct Session {
     pub key: String,
     pub state: sqlx::types::Json<serde_json::Value>,
+    /// Id of the user that is logged in with this session, if any.
+    pub user_id: Option<i64>,
+    pub expires_at: DateTime<Utc>,
 }
 
 impl Session {
     pub fn new(
         key: String,
         state: sqlx::types::Json<serde_json::Value>,
+        user_id: Option<i64>,
+        expires_at: DateTime<Utc>,
     ) -> Result<Self, &'static str> {
         if key.len() > 64 {
             return Err("Session key cannot be longer than 64 bytes");
         }
-        Ok(Self { key, state })
+        Ok(Self { key, state, user_id, expires_at })
     }
 }
 
 pub type SessionState = HashMap<String, String>;
 
+/// Key under which actix-identity stores the id of the logged in user in the
+/// session state.
+const IDENTITY_KEY: &str = "actix_identity.user_id";
+
+/// Extracts the id of the logged in user from a session state.
+///
+/// actix-identity stores the id as JSON encoded string.
+pub(crate) fn session_user_id(session_state: &SessionState) -> Option<i64> {
+    session_state
+        .get(IDENTITY_KEY)
+        .and_then(|id| serde_json::from_str::<String>(id).ok())
+        .and_then(|id| id.parse().ok())
+}
+
+pub(crate) fn expires_at(ttl: &Duration) -> DateTime<Utc> {
+    Utc::now() + chrono::Duration::seconds(ttl.whole_seconds())
+}
+
 #[async_trait::async_trait]
 pub trait SessionRepository: Send + Sync + 'static {
+    /// Loads a session unless it does not exist or is expired.
     async fn db_load(
         &self,
         session_key: &SessionKey,
@@ -49,12 +78,22 @@ pub trait SessionRepository: Send + Sync + 'static {
         &self,
         session_key: &SessionKey,
         session_state: &serde_json::Value,
+        user_id: Option<i64>,
+        expires_at: DateTime<Utc>,
     ) -> Result<(), sqlx::Error>;
 
     async fn db_update(
         &self,
         session_key: &SessionKey,
         session_state: &serde_json::Value,
+        user_id: Option<i64>,
+        expires_at: DateTime<Utc>,
+    ) -> Result<(), sqlx::Error>;
+
+    async fn db_update_ttl(
+        &self,
+        session_key: &SessionKey,
+        expires_at: DateTime<Utc>,
     ) -> Result<(), sqlx::Error>;
 
     async fn db_delete(
@@ -62,6 +101,25 @@ pub trait SessionRepository: Send + Sync + 'static {
         session_key: &SessionKey,
     ) -> Result<(), sqlx::Error>;
 
+    /// Lists all sessions that are not expired yet, optionally only the ones
+    /// of a single user.
+    async fn list_sessions(
+        &self,
+        user_id: Option<i64>,
+    ) -> Result<Vec<Session>, sqlx::Error>;
+
+    /// Counts the sessions that are not expired yet.
+    async fn count_sessions(&self) -> Result<i64, sqlx::Error>;
+
+    /// Deletes all sessions of a user and returns how many were deleted.
+    async fn delete_user_sessions(
+        &self,
+        user_id: i64,
+    ) -> Result<u64, sqlx::Error>;
+
+    /// Deletes all expired sessions and returns how many were deleted.
+    async fn delete_expired_sessions(&self) -> Result<u64, sqlx::Error>;
+
     async fn generate_session_key() -> SessionKey {
         let value = std::iter::repeat(())
             .map(|()| OsRng.sample(Alphanumeric))
@@ -91,9 +149,9 @@ impl SessionRepository for PostgresSessionRepository {
         let db_response = sqlx::query_as!(
             Session,
             r#"
-      SELECT key, state
+      SELECT key, state, user_id, expires_at
       FROM sessions
-      WHERE key = $1
+      WHERE key = $1 AND expires_at > now()
       "#,
             session_key.as_ref()
         )
@@ -107,15 +165,19 @@ impl SessionRepository for PostgresSessionRepository {
         &self,
         session_key: &SessionKey,
         session_state: &serde_json::Value,
+        user_id: Option<i64>,
+        expires_at: DateTime<Utc>,
     ) -> Result<(), sqlx::Error> {
         let db_response = sqlx::query!(
             r#"
       INSERT
-      INTO sessions (key, state)
-      VALUES ($1, $2)
+      INTO sessions (key, state, user_id, expires_at)
+      VALUES ($1, $2, $3, $4)
       "#,
             session_key.as_ref(),
-            session_state
+            session_state,
+            user_id,
+            expires_at
         )
         .execute(&self.pool)
         .await
@@ -128,14 +190,39 @@ impl SessionRepository for PostgresSessionRepository {
         &self,
         session_key: &SessionKey,
         session_state: &serde_json::Value,
+        user_id: Option<i64>,
+        expires_at: DateTime<Utc>,
     ) -> Result<(), sqlx::Error> {
         let db_response = sqlx::query!(
             r#"
       UPDATE sessions
-      SET state = $1
-      WHERE key = $2
+      SET state = $1, user_id = $2, expires_at = $3
+      WHERE key = $4
       "#,
             session_state,
+            user_id,
+            expires_at,
+            session_key.as_ref()
+        )
+        .execute(&self.pool)
+        .await
+        .map(|_| ());
+
+        return db_response;
+    }
+
+    async fn db_update_ttl(
+        &self,
+        session_key: &SessionKey,
+        expires_at: DateTime<Utc>,
+    ) -> Result<(), sqlx::Error> {
+        let db_response = sqlx::query!(
+            r#"
+      UPDATE sessions
+      SET expires_at = $1
+      WHERE key = $2
+      "#,
+            expires_at,
             session_key.as_ref()
         )
         .execute(&self.pool)
@@ -163,16 +250,90 @@ impl SessionRepository for PostgresSessionRepository {
 
         return db_response;
     }
+
+    async fn list_sessions(
+        &self,
+        user_id: Option<i64>,
+    ) -> Result<Vec<Session>, sqlx::Error> {
+        sqlx::query_as!(
+            Session,
+            r#"
+      SELECT key, state, user_id, expires_at
+      FROM sessions
+      WHERE expires_at > now() AND ($1::bigint IS NULL OR user_id = $1)
+      ORDER BY expires_at
+      "#,
+            user_id
+        )
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    async fn count_sessions(&self) -> Result<i64, sqlx::Error> {
+        sqlx::query_scalar!(
+            r#"
+      SELECT count(*) AS "count!"
+      FROM sessions
+      WHERE expires_at > now()
+      "#
+        )
+        .fetch_one(&self.pool)
+        .await
+    }
+
+    async fn delete_user_sessions(
+        &self,
+        user_id: i64,
+    ) -> Result<u64, sqlx::Error> {
+        sqlx::query!(
+            r#"
+      DELETE
+      FROM sessions
+      WHERE user_id = $1
+      "#,
+            user_id
+        )
+        .execute(&self.pool)
+        .await
+        .map(|result| result.rows_affected())
+    }
+
+    async fn delete_expired_sessions(&self) -> Result<u64, sqlx::Error> {
+        sqlx::query!(
+            r#"
+      DELETE
+      FROM sessions
+      WHERE expires_at <= now()
+      "#
+        )
+        .execute(&self.pool)
+        .await
+        .map(|result| result.rows_affected())
+    }
+}
+
+/// Makes any [`SessionRepository`] usable as the session store of
+/// actix-session.
+#[derive(Clone)]
+pub struct RepositorySessionStore<R: SessionRepository> {
+    repository: R,
+}
+
+impl<R: SessionRepository> RepositorySessionStore<R> {
+    pub fn new(repository: R) -> Self {
+        Self { repository }
+    }
 }
 
 #[async_trait::async_trait(?Send)]
-impl SessionStore for PostgresSessionRepository {
+impl<R: SessionRepository> SessionStore for RepositorySessionStore<R> {
     async fn load(
         &self,
         session_key: &SessionKey,
     ) -> Result<Option<SessionState>, LoadError> {
         // try to load session from db
         let db_response = self
+            .repository
             .db_load(session_key)
             .await
             .map_err(Into::into)
@@ -201,15 +362,17 @@ impl SessionStore for PostgresSessionRepository {
     async fn save(
         &self,
         session_state: SessionState,
-        _ttl: &Duration,
+        ttl: &Duration,
     ) -> Result<SessionKey, SaveError> {
-        let session_key: SessionKey = Self::generate_session_key().await;
+        let session_key: SessionKey = R::generate_session_key().await;
+        let user_id = session_user_id(&session_state);
 
         let session_state = serde_json::to_value(session_state)
             .map_err(Into::into)
             .map_err(SaveError::Serialization)?;
 
-        self.db_save(&session_key, &session_state)
+        self.repository
+            .db_save(&session_key, &session_state, user_id, expires_at(ttl))
             .await
             .map_err(Into::into)
             .map_err(SaveError::Other)?;
@@ -221,13 +384,16 @@ impl SessionStore for PostgresSessionRepository {
         &self,
         session_key: SessionKey,
         session_state: SessionState,
-        _ttl: &Duration,
+        ttl: &Duration,
     ) -> Result<SessionKey, UpdateError> {
+        let user_id = session_user_id(&session_state);
+
         let session_state = serde_json::to_value(session_state)
             .map_err(Into::into)
             .map_err(UpdateError::Serialization)?;
 
-        self.db_update(&session_key, &session_state)
+        self.repository
+            .db_update(&session_key, &session_state, user_id, expires_at(ttl))
             .await
             .map_err(Into::into)
             .map_err(UpdateError::Other)?;
@@ -237,10 +403,16 @@ impl SessionStore for PostgresSessionRepository {
 
     async fn update_ttl(
         &self,
-        _session_key: &SessionKey,
-        _ttl: &Duration,
+        session_key: &SessionKey,
+        ttl: &Duration,
     ) -> Result<(), anyhow::Error> {
-        Ok(())
+        self.repository
+            .db_update_ttl(session_key, expires_at(ttl))
+            .await
+            .map_err(anyhow::Error::from)
+            .context(
+                "Some database error occurred when trying to update the ttl.",
+            )
     }
 
     async fn delete(
@@ -248,10 +420,11 @@ impl SessionStore for PostgresSessionRepository {
         session_key: &SessionKey,
     ) -> Result<(), anyhow::Error> {
         let db_response = self
+            .repository
             .db_delete(session_key)
             .await
             .map_err(anyhow::Error::from)
-            .context("Some psql error occurred when trying to delete session from db.");
+            .context("Some database error occurred when trying to delete session from db.");
 
         return db_response;
     }
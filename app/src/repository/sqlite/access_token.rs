@@ -0,0 +1,150 @@
+use chrono::{DateTime, Utc};
+use shared::models::user::TokenScope;
+use sqlx::types::Json;
+
+use super::{fetch_returning, timestamp};
+use crate::{
+    repository::{
+        access_token::{self, AccessToken, AccessTokenRepository},
+        error::RepositoryError,
+    },
+    util::error_or::ErrorOr,
+};
+
+pub struct SqliteAccessTokenRepository {
+    pool: sqlx::SqlitePool,
+}
+
+impl SqliteAccessTokenRepository {
+    pub fn new(pool: sqlx::SqlitePool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl AccessTokenRepository for SqliteAccessTokenRepository {
+    async fn get_access_tokens(
+        &self,
+        user_id: &i64,
+    ) -> ErrorOr<Vec<AccessToken>> {
+        let db_response = sqlx::query_as::<_, AccessToken>(
+            r#"
+            SELECT
+                id, user_id, name, scopes, created_at, expires_at, last_used_at
+            FROM access_tokens
+            WHERE user_id = ?
+            ORDER BY id
+            "#,
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        db_response.into()
+    }
+
+    async fn create_access_token(
+        &self,
+        user_id: &i64,
+        token_hash: &str,
+        name: &str,
+        scopes: &[TokenScope],
+        expires_at: Option<DateTime<Utc>>,
+    ) -> ErrorOr<AccessToken> {
+        let query = sqlx::query_as::<_, AccessToken>(
+            r#"
+            INSERT
+            INTO access_tokens
+                (user_id, token_hash, name, scopes, created_at, expires_at)
+            VALUES (?, ?, ?, ?, ?, ?)
+            RETURNING
+                id, user_id, name, scopes, created_at, expires_at, last_used_at
+            "#,
+        )
+        .bind(user_id)
+        .bind(token_hash)
+        .bind(name)
+        .bind(Json(scopes))
+        .bind(timestamp(Utc::now()))
+        .bind(expires_at.map(timestamp));
+        let db_response = fetch_returning(query, &self.pool)
+            .await
+            .map_err(Into::into)
+            .map_err(RepositoryError::Internal)?;
+
+        db_response.into()
+    }
+
+    async fn use_access_token(
+        &self,
+        token_hash: &str,
+    ) -> ErrorOr<Option<AccessToken>> {
+        let now = Utc::now();
+        let query = sqlx::query_as::<_, AccessToken>(
+            r#"
+            UPDATE access_tokens
+            SET last_used_at = ?
+            WHERE token_hash = ? AND (expires_at IS NULL OR expires_at > ?)
+            RETURNING
+                id, user_id, name, scopes, created_at, expires_at, last_used_at
+            "#,
+        )
+        .bind(timestamp(now))
+        .bind(token_hash)
+        .bind(timestamp(now));
+        let db_response = match fetch_returning(query, &self.pool).await {
+            Ok(token) => Some(token),
+            Err(sqlx::Error::RowNotFound) => None,
+            Err(e) => Err(RepositoryError::Internal(e.into()))?,
+        };
+
+        db_response.into()
+    }
+
+    async fn delete_access_token(
+        &self,
+        token_id: &i64,
+        user_id: &i64,
+    ) -> ErrorOr<()> {
+        let db_response = sqlx::query(
+            r#"
+            DELETE
+            FROM access_tokens
+            WHERE id = ? AND user_id = ?
+            "#,
+        )
+        .bind(token_id)
+        .bind(user_id)
+        .execute(&self.pool)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        if db_response.rows_affected() == 0 {
+            Err(RepositoryError::NotFound {
+                relation_name: access_token::RELATION.to_string(),
+            })?;
+        }
+
+        ().into()
+    }
+
+    async fn delete_user_access_tokens(&self, user_id: &i64) -> ErrorOr<u64> {
+        let db_response = sqlx::query(
+            r#"
+            DELETE
+            FROM access_tokens
+            WHERE user_id = ?
+            "#,
+        )
+        .bind(user_id)
+        .execute(&self.pool)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        db_response.rows_affected().into()
+    }
+}
@@ -0,0 +1,185 @@
+use chrono::Utc;
+use shared::models::attachment::Attachment;
+
+use super::timestamp;
+use crate::{
+    repository::{
+        attachment::{AttachmentRepository, NewAttachment, RELATION},
+        error::RepositoryError,
+    },
+    util::error_or::ErrorOr,
+};
+
+pub struct SqliteAttachmentRepository {
+    pool: sqlx::SqlitePool,
+}
+
+impl SqliteAttachmentRepository {
+    pub fn new(pool: sqlx::SqlitePool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl AttachmentRepository for SqliteAttachmentRepository {
+    async fn get_attachments(&self, todo_id: &i64) -> ErrorOr<Vec<Attachment>> {
+        let db_response = sqlx::query_as::<_, Attachment>(
+            r#"
+            SELECT *
+            FROM attachments
+            WHERE todo_id = ?
+            ORDER BY id
+            "#,
+        )
+        .bind(todo_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        db_response.into()
+    }
+
+    async fn get_attachment(
+        &self,
+        todo_id: &i64,
+        attachment_id: &i64,
+    ) -> ErrorOr<Attachment> {
+        let db_response = sqlx::query_as::<_, Attachment>(
+            r#"
+            SELECT *
+            FROM attachments
+            WHERE id = ? AND todo_id = ?
+            "#,
+        )
+        .bind(attachment_id)
+        .bind(todo_id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        match db_response {
+            Some(attachment) => attachment.into(),
+            None => Err(RepositoryError::NotFound {
+                relation_name: RELATION.to_string(),
+            })?,
+        }
+    }
+
+    async fn create_attachment(
+        &self,
+        attachment: &NewAttachment,
+        quota: i64,
+    ) -> ErrorOr<Option<Attachment>> {
+        // a single statement, SQLite runs no other write in between
+        let db_response = sqlx::query_as::<_, Attachment>(
+            r#"
+            INSERT
+            INTO attachments (
+                todo_id,
+                uploader_id,
+                file_name,
+                size,
+                content_type,
+                checksum,
+                storage_key,
+                created_at
+            )
+            SELECT ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8
+            WHERE (
+                SELECT COALESCE(SUM(size), 0)
+                FROM attachments
+                WHERE uploader_id = ?2
+            ) + ?4 <= ?9
+            RETURNING *
+            "#,
+        )
+        .bind(attachment.todo_id)
+        .bind(attachment.uploader_id)
+        .bind(&attachment.file_name)
+        .bind(attachment.size)
+        .bind(&attachment.content_type)
+        .bind(&attachment.checksum)
+        .bind(&attachment.storage_key)
+        .bind(timestamp(Utc::now()))
+        .bind(quota)
+        // runs the statement to completion, like `fetch_returning`
+        .fetch_all(&self.pool)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?
+        .pop();
+
+        db_response.into()
+    }
+
+    async fn delete_attachment(&self, attachment_id: &i64) -> ErrorOr<()> {
+        sqlx::query(
+            r#"
+            DELETE
+            FROM attachments
+            WHERE id = ?
+            "#,
+        )
+        .bind(attachment_id)
+        .execute(&self.pool)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        ().into()
+    }
+
+    async fn get_usage(&self, user_id: &i64) -> ErrorOr<i64> {
+        let db_response = sqlx::query_scalar::<_, i64>(
+            r#"
+            SELECT COALESCE(SUM(size), 0)
+            FROM attachments
+            WHERE uploader_id = ?
+            "#,
+        )
+        .bind(user_id)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        db_response.into()
+    }
+
+    async fn get_orphaned_blobs(&self, limit: i64) -> ErrorOr<Vec<String>> {
+        let db_response = sqlx::query_scalar::<_, String>(
+            r#"
+            SELECT storage_key
+            FROM orphaned_blobs
+            ORDER BY orphaned_at
+            LIMIT ?
+            "#,
+        )
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        db_response.into()
+    }
+
+    async fn forget_orphaned_blob(&self, storage_key: &str) -> ErrorOr<()> {
+        sqlx::query(
+            r#"
+            DELETE
+            FROM orphaned_blobs
+            WHERE storage_key = ?
+            "#,
+        )
+        .bind(storage_key)
+        .execute(&self.pool)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        ().into()
+    }
+}
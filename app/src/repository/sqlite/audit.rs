@@ -0,0 +1,179 @@
+use chrono::Utc;
+use shared::models::audit::{
+    AuditAction, AuditEntity, AuditEvent, SecurityEvent,
+};
+use sqlx::SqliteConnection;
+
+use super::timestamp;
+use crate::{
+    repository::{
+        audit::{AuditContext, AuditRepository, NewSecurityEvent},
+        error::RepositoryError,
+    },
+    util::error_or::ErrorOr,
+};
+
+pub struct SqliteAuditRepository {
+    pool: sqlx::SqlitePool,
+}
+
+impl SqliteAuditRepository {
+    pub fn new(pool: sqlx::SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// Appends an audit event in the transaction of the change.
+    pub(crate) async fn record(
+        transaction: &mut SqliteConnection,
+        audit: &AuditContext,
+        action: AuditAction,
+        entity: AuditEntity,
+        entity_id: i64,
+        changes: serde_json::Value,
+    ) -> Result<(), RepositoryError> {
+        sqlx::query(
+            r#"
+            INSERT
+            INTO audit_events (
+                actor_id,
+                action,
+                entity,
+                entity_id,
+                changes,
+                request_id,
+                created_at
+            )
+            VALUES (?, ?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(audit.actor_id)
+        .bind(action.as_str())
+        .bind(entity.as_str())
+        .bind(entity_id)
+        .bind(changes)
+        .bind(&audit.request_id)
+        .bind(timestamp(Utc::now()))
+        .execute(&mut *transaction)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl AuditRepository for SqliteAuditRepository {
+    async fn get_history(
+        &self,
+        entity: AuditEntity,
+        entity_id: &i64,
+        offset: i64,
+        limit: i64,
+    ) -> ErrorOr<Vec<AuditEvent>> {
+        let db_response = sqlx::query_as::<_, AuditEvent>(
+            r#"
+            SELECT *
+            FROM audit_events
+            WHERE entity = ? AND entity_id = ?
+            ORDER BY id
+            LIMIT ? OFFSET ?
+            "#,
+        )
+        .bind(entity.as_str())
+        .bind(entity_id)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        db_response.into()
+    }
+
+    async fn get_account_activity(
+        &self,
+        user_id: &i64,
+        offset: i64,
+        limit: i64,
+    ) -> ErrorOr<Vec<AuditEvent>> {
+        let db_response = sqlx::query_as::<_, AuditEvent>(
+            r#"
+            SELECT *
+            FROM audit_events
+            WHERE actor_id = ?1 OR (entity = ?2 AND entity_id = ?1)
+            ORDER BY id DESC
+            LIMIT ?4 OFFSET ?3
+            "#,
+        )
+        .bind(user_id)
+        .bind(AuditEntity::User.as_str())
+        .bind(offset)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        db_response.into()
+    }
+
+    async fn record_security_event(
+        &self,
+        event: &NewSecurityEvent,
+    ) -> ErrorOr<()> {
+        sqlx::query(
+            r#"
+            INSERT
+            INTO security_events (
+                user_id,
+                actor_id,
+                kind,
+                ip,
+                request_id,
+                created_at
+            )
+            VALUES (?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(event.user_id)
+        .bind(event.actor_id)
+        .bind(event.kind.as_str())
+        .bind(&event.ip)
+        .bind(&event.request_id)
+        .bind(timestamp(Utc::now()))
+        .execute(&self.pool)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        ().into()
+    }
+
+    async fn get_security_events(
+        &self,
+        user_id: Option<i64>,
+        offset: i64,
+        limit: i64,
+    ) -> ErrorOr<Vec<SecurityEvent>> {
+        let db_response = sqlx::query_as::<_, SecurityEvent>(
+            r#"
+            SELECT *
+            FROM security_events
+            WHERE ?1 IS NULL OR user_id = ?1
+            ORDER BY id DESC
+            LIMIT ?3 OFFSET ?2
+            "#,
+        )
+        .bind(user_id)
+        .bind(offset)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        db_response.into()
+    }
+}
@@ -0,0 +1,327 @@
+use std::collections::HashMap;
+
+use chrono::Utc;
+use shared::models::comment::{Comment, CreateComment, UpdateComment};
+use sqlx::SqliteConnection;
+
+use super::timestamp;
+use crate::{
+    repository::{
+        comment::{self, CommentRepository},
+        error::{Operation, RepositoryError},
+    },
+    util::error_or::ErrorOr,
+};
+
+pub struct SqliteCommentRepository {
+    pool: sqlx::SqlitePool,
+}
+
+impl SqliteCommentRepository {
+    pub fn new(pool: sqlx::SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    async fn get_comment(&self, comment_id: &i64) -> ErrorOr<Comment> {
+        let comment = sqlx::query_as::<_, Comment>(
+            r#"
+            SELECT
+                comments.id,
+                comments.todo_id,
+                comments.author_id,
+                users.name AS author_name,
+                comments.body,
+                comments.created_at,
+                comments.updated_at
+            FROM comments
+            JOIN users ON users.id = comments.author_id
+            WHERE comments.id = ?
+            "#,
+        )
+        .bind(comment_id)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        let mut comments = self.with_mentions(vec![comment]).await?;
+        comments.remove(0).into()
+    }
+
+    /// Fills in the mentions, which SQLite cannot aggregate into an array.
+    async fn with_mentions(
+        &self,
+        mut comments: Vec<Comment>,
+    ) -> Result<Vec<Comment>, RepositoryError> {
+        let comment_ids =
+            comments.iter().map(|comment| comment.id).collect::<Vec<_>>();
+        let mentions = sqlx::query_as::<_, (i64, i64)>(
+            r#"
+            SELECT comment_id, user_id
+            FROM comment_mentions
+            WHERE comment_id IN (SELECT value FROM json_each(?))
+            ORDER BY user_id
+            "#,
+        )
+        .bind(serde_json::Value::from(comment_ids).to_string())
+        .fetch_all(&self.pool)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        let mut mentions_by_comment = HashMap::<i64, Vec<i64>>::new();
+        for (comment_id, user_id) in mentions {
+            mentions_by_comment.entry(comment_id).or_default().push(user_id);
+        }
+        for comment in &mut comments {
+            comment.mentions =
+                mentions_by_comment.remove(&comment.id).unwrap_or_default();
+        }
+
+        Ok(comments)
+    }
+
+    /// Replaces the mentions of a comment.
+    async fn set_mentions(
+        transaction: &mut SqliteConnection,
+        comment_id: i64,
+        mentions: &[i64],
+    ) -> Result<(), RepositoryError> {
+        sqlx::query(
+            r#"
+            DELETE
+            FROM comment_mentions
+            WHERE comment_id = ?
+            "#,
+        )
+        .bind(comment_id)
+        .execute(&mut *transaction)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        sqlx::query(
+            r#"
+            INSERT
+            INTO comment_mentions (comment_id, user_id)
+            SELECT ?, value
+            FROM json_each(?)
+            WHERE true
+            ON CONFLICT (comment_id, user_id) DO NOTHING
+            "#,
+        )
+        .bind(comment_id)
+        .bind(serde_json::Value::from(mentions).to_string())
+        .execute(&mut *transaction)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        Ok(())
+    }
+
+    async fn comment_author_id(
+        transaction: &mut SqliteConnection,
+        todo_id: &i64,
+        comment_id: &i64,
+    ) -> Result<Option<i64>, RepositoryError> {
+        sqlx::query_scalar::<_, i64>(
+            r#"
+            SELECT author_id
+            FROM comments
+            WHERE id = ? AND todo_id = ?
+            "#,
+        )
+        .bind(comment_id)
+        .bind(todo_id)
+        .fetch_optional(&mut *transaction)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)
+    }
+}
+
+#[async_trait::async_trait]
+impl CommentRepository for SqliteCommentRepository {
+    async fn get_comments(
+        &self,
+        todo_id: &i64,
+        offset: i64,
+        limit: i64,
+    ) -> ErrorOr<Vec<Comment>> {
+        let comments = sqlx::query_as::<_, Comment>(
+            r#"
+            SELECT
+                comments.id,
+                comments.todo_id,
+                comments.author_id,
+                users.name AS author_name,
+                comments.body,
+                comments.created_at,
+                comments.updated_at
+            FROM comments
+            JOIN users ON users.id = comments.author_id
+            WHERE comments.todo_id = ?
+            ORDER BY comments.id
+            LIMIT ? OFFSET ?
+            "#,
+        )
+        .bind(todo_id)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        let comments = self.with_mentions(comments).await?;
+
+        comments.into()
+    }
+
+    async fn create_comment(
+        &self,
+        todo_id: &i64,
+        create_comment: &CreateComment,
+        author_id: &i64,
+    ) -> ErrorOr<Comment> {
+        let now = Utc::now();
+        let mut transaction = self
+            .pool
+            .begin()
+            .await
+            .map_err(Into::into)
+            .map_err(RepositoryError::Internal)?;
+
+        let comment_id = sqlx::query(
+            r#"
+            INSERT
+            INTO comments (todo_id, author_id, body, created_at, updated_at)
+            VALUES (?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(todo_id)
+        .bind(author_id)
+        .bind(&create_comment.body)
+        .bind(timestamp(now))
+        .bind(timestamp(now))
+        .execute(&mut *transaction)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?
+        .last_insert_rowid();
+
+        Self::set_mentions(
+            &mut transaction,
+            comment_id,
+            &create_comment.mentions,
+        )
+        .await?;
+
+        transaction
+            .commit()
+            .await
+            .map_err(Into::into)
+            .map_err(RepositoryError::Internal)?;
+
+        self.get_comment(&comment_id).await
+    }
+
+    async fn update_comment(
+        &self,
+        todo_id: &i64,
+        comment_id: &i64,
+        update_comment: &UpdateComment,
+        author_id: &i64,
+    ) -> ErrorOr<Comment> {
+        let mut transaction = self
+            .pool
+            .begin()
+            .await
+            .map_err(Into::into)
+            .map_err(RepositoryError::Internal)?;
+
+        let comment_author_id =
+            Self::comment_author_id(&mut transaction, todo_id, comment_id)
+                .await?;
+        comment::check_author(
+            comment_author_id,
+            *author_id,
+            Operation::Update,
+        )?;
+
+        sqlx::query(
+            r#"
+            UPDATE comments
+            SET body = ?, updated_at = ?
+            WHERE id = ?
+            "#,
+        )
+        .bind(&update_comment.body)
+        .bind(timestamp(Utc::now()))
+        .bind(comment_id)
+        .execute(&mut *transaction)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        Self::set_mentions(
+            &mut transaction,
+            *comment_id,
+            &update_comment.mentions,
+        )
+        .await?;
+
+        transaction
+            .commit()
+            .await
+            .map_err(Into::into)
+            .map_err(RepositoryError::Internal)?;
+
+        self.get_comment(comment_id).await
+    }
+
+    async fn delete_comment(
+        &self,
+        todo_id: &i64,
+        comment_id: &i64,
+        author_id: &i64,
+    ) -> ErrorOr<()> {
+        let mut transaction = self
+            .pool
+            .begin()
+            .await
+            .map_err(Into::into)
+            .map_err(RepositoryError::Internal)?;
+
+        let comment_author_id =
+            Self::comment_author_id(&mut transaction, todo_id, comment_id)
+                .await?;
+        comment::check_author(
+            comment_author_id,
+            *author_id,
+            Operation::Delete,
+        )?;
+
+        sqlx::query(
+            r#"
+            DELETE
+            FROM comments
+            WHERE id = ?
+            "#,
+        )
+        .bind(comment_id)
+        .execute(&mut *transaction)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        transaction
+            .commit()
+            .await
+            .map_err(Into::into)
+            .map_err(RepositoryError::Internal)?;
+
+        ().into()
+    }
+}
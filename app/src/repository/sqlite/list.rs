@@ -0,0 +1,473 @@
+use chrono::Utc;
+use shared::models::{
+    list::{CreateList, ListInvitation, ListMember, ListPermission, TodoList},
+    todo::TodoAction,
+};
+
+use super::timestamp;
+use crate::{
+    repository::{
+        error::RepositoryError,
+        list::{self, ListRepository},
+    },
+    util::error_or::ErrorOr,
+};
+
+pub struct SqliteListRepository {
+    pool: sqlx::SqlitePool,
+}
+
+impl SqliteListRepository {
+    pub fn new(pool: sqlx::SqlitePool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl ListRepository for SqliteListRepository {
+    async fn get_lists(&self, user_id: &i64) -> ErrorOr<Vec<TodoList>> {
+        let db_response = sqlx::query_as::<_, TodoList>(
+            r#"
+            SELECT
+                lists.id,
+                lists.name,
+                lists.owner,
+                list_members.permission,
+                lists.created_at,
+                lists.updated_at
+            FROM lists
+            JOIN list_members ON list_members.list_id = lists.id
+            WHERE list_members.user_id = ?
+            ORDER BY lists.id
+            "#,
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        db_response.into()
+    }
+
+    async fn get_list(
+        &self,
+        list_id: &i64,
+        user_id: &i64,
+    ) -> ErrorOr<TodoList> {
+        let db_response = sqlx::query_as::<_, TodoList>(
+            r#"
+            SELECT
+                lists.id,
+                lists.name,
+                lists.owner,
+                list_members.permission,
+                lists.created_at,
+                lists.updated_at
+            FROM lists
+            JOIN list_members ON list_members.list_id = lists.id
+            WHERE lists.id = ? AND list_members.user_id = ?
+            "#,
+        )
+        .bind(list_id)
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?
+        .ok_or_else(|| RepositoryError::NotFound {
+            relation_name: list::RELATION.to_string(),
+        })?;
+
+        db_response.into()
+    }
+
+    async fn create_list(
+        &self,
+        create_list: &CreateList,
+        user_id: &i64,
+    ) -> ErrorOr<TodoList> {
+        let now = Utc::now();
+        let mut transaction = self
+            .pool
+            .begin()
+            .await
+            .map_err(Into::into)
+            .map_err(RepositoryError::Internal)?;
+
+        let list_id = sqlx::query(
+            r#"
+            INSERT
+            INTO lists (name, owner, created_at, updated_at)
+            VALUES (?, ?, ?, ?)
+            "#,
+        )
+        .bind(&create_list.name)
+        .bind(user_id)
+        .bind(timestamp(now))
+        .bind(timestamp(now))
+        .execute(&mut *transaction)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?
+        .last_insert_rowid();
+
+        sqlx::query(
+            r#"
+            INSERT
+            INTO list_members (list_id, user_id, permission, created_at)
+            VALUES (?, ?, ?, ?)
+            "#,
+        )
+        .bind(list_id)
+        .bind(user_id)
+        .bind(ListPermission::Admin.as_str())
+        .bind(timestamp(now))
+        .execute(&mut *transaction)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        transaction
+            .commit()
+            .await
+            .map_err(Into::into)
+            .map_err(RepositoryError::Internal)?;
+
+        self.get_list(&list_id, user_id).await
+    }
+
+    async fn rename_list(&self, list_id: &i64, name: &str) -> ErrorOr<()> {
+        sqlx::query(
+            r#"
+            UPDATE lists
+            SET name = ?, updated_at = ?
+            WHERE id = ?
+            "#,
+        )
+        .bind(name)
+        .bind(timestamp(Utc::now()))
+        .bind(list_id)
+        .execute(&self.pool)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        ().into()
+    }
+
+    async fn delete_list(&self, list_id: &i64) -> ErrorOr<()> {
+        sqlx::query(
+            r#"
+            DELETE
+            FROM lists
+            WHERE id = ?
+            "#,
+        )
+        .bind(list_id)
+        .execute(&self.pool)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        ().into()
+    }
+
+    async fn get_members(&self, list_id: &i64) -> ErrorOr<Vec<ListMember>> {
+        let db_response = sqlx::query_as::<_, ListMember>(
+            r#"
+            SELECT
+                users.id AS user_id,
+                users.name,
+                users.email,
+                list_members.permission,
+                list_members.created_at AS joined_at
+            FROM list_members
+            JOIN users ON users.id = list_members.user_id
+            WHERE list_members.list_id = ?
+            ORDER BY list_members.created_at, users.id
+            "#,
+        )
+        .bind(list_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        db_response.into()
+    }
+
+    async fn set_member_permission(
+        &self,
+        list_id: &i64,
+        user_id: &i64,
+        permission: ListPermission,
+    ) -> ErrorOr<()> {
+        let db_response = sqlx::query(
+            r#"
+            UPDATE list_members
+            SET permission = ?
+            WHERE list_id = ? AND user_id = ?
+            "#,
+        )
+        .bind(permission.as_str())
+        .bind(list_id)
+        .bind(user_id)
+        .execute(&self.pool)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        if db_response.rows_affected() == 0 {
+            Err(RepositoryError::NotFound {
+                relation_name: list::MEMBER_RELATION.to_string(),
+            })?;
+        }
+
+        ().into()
+    }
+
+    async fn remove_member(
+        &self,
+        list_id: &i64,
+        user_id: &i64,
+        removed_by: &i64,
+    ) -> ErrorOr<()> {
+        let now = Utc::now();
+        let mut transaction = self
+            .pool
+            .begin()
+            .await
+            .map_err(Into::into)
+            .map_err(RepositoryError::Internal)?;
+
+        let db_response = sqlx::query(
+            r#"
+            DELETE
+            FROM list_members
+            WHERE list_id = ? AND user_id = ?
+            "#,
+        )
+        .bind(list_id)
+        .bind(user_id)
+        .execute(&mut *transaction)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        if db_response.rows_affected() == 0 {
+            Err(RepositoryError::NotFound {
+                relation_name: list::MEMBER_RELATION.to_string(),
+            })?;
+        }
+
+        // the todos of the list are out of their reach now
+        let unassigned = sqlx::query_scalar::<_, i64>(
+            r#"
+            UPDATE todos
+            SET assignee_id = NULL
+            WHERE list_id = ? AND assignee_id = ?
+            RETURNING id
+            "#,
+        )
+        .bind(list_id)
+        .bind(user_id)
+        .fetch_all(&mut *transaction)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        for todo_id in unassigned {
+            sqlx::query(
+                r#"
+                INSERT
+                INTO todo_activities (
+                    todo_id,
+                    actor_id,
+                    action,
+                    previous_assignee_id,
+                    assignee_id,
+                    created_at
+                )
+                VALUES (?, ?, ?, ?, NULL, ?)
+                "#,
+            )
+            .bind(todo_id)
+            .bind(removed_by)
+            .bind(TodoAction::Assigned.as_str())
+            .bind(user_id)
+            .bind(timestamp(now))
+            .execute(&mut *transaction)
+            .await
+            .map_err(Into::into)
+            .map_err(RepositoryError::Internal)?;
+        }
+
+        transaction
+            .commit()
+            .await
+            .map_err(Into::into)
+            .map_err(RepositoryError::Internal)?;
+
+        ().into()
+    }
+
+    async fn create_invitation(
+        &self,
+        list_id: &i64,
+        user_id: &i64,
+        invited_by: &i64,
+        permission: ListPermission,
+    ) -> ErrorOr<()> {
+        sqlx::query(
+            r#"
+            INSERT
+            INTO list_invitations
+                (list_id, user_id, invited_by, permission, created_at)
+            VALUES (?, ?, ?, ?, ?)
+            ON CONFLICT (list_id, user_id) DO UPDATE
+            SET
+                invited_by = excluded.invited_by,
+                permission = excluded.permission,
+                created_at = excluded.created_at
+            "#,
+        )
+        .bind(list_id)
+        .bind(user_id)
+        .bind(invited_by)
+        .bind(permission.as_str())
+        .bind(timestamp(Utc::now()))
+        .execute(&self.pool)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        ().into()
+    }
+
+    async fn get_invitations(
+        &self,
+        user_id: &i64,
+    ) -> ErrorOr<Vec<ListInvitation>> {
+        let db_response = sqlx::query_as::<_, ListInvitation>(
+            r#"
+            SELECT
+                list_invitations.id,
+                list_invitations.list_id,
+                lists.name AS list_name,
+                users.name AS invited_by,
+                list_invitations.permission,
+                list_invitations.created_at
+            FROM list_invitations
+            JOIN lists ON lists.id = list_invitations.list_id
+            JOIN users ON users.id = list_invitations.invited_by
+            WHERE list_invitations.user_id = ?
+            ORDER BY list_invitations.id
+            "#,
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        db_response.into()
+    }
+
+    async fn accept_invitation(
+        &self,
+        invitation_id: &i64,
+        user_id: &i64,
+    ) -> ErrorOr<()> {
+        let mut transaction = self
+            .pool
+            .begin()
+            .await
+            .map_err(Into::into)
+            .map_err(RepositoryError::Internal)?;
+
+        let (list_id, permission) = sqlx::query_as::<_, (i64, String)>(
+            r#"
+            SELECT list_id, permission
+            FROM list_invitations
+            WHERE id = ? AND user_id = ?
+            "#,
+        )
+        .bind(invitation_id)
+        .bind(user_id)
+        .fetch_optional(&mut *transaction)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?
+        .ok_or_else(|| RepositoryError::NotFound {
+            relation_name: list::INVITATION_RELATION.to_string(),
+        })?;
+
+        sqlx::query(
+            r#"
+            DELETE
+            FROM list_invitations
+            WHERE id = ?
+            "#,
+        )
+        .bind(invitation_id)
+        .execute(&mut *transaction)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        // a member keeps their permission if they were invited again
+        sqlx::query(
+            r#"
+            INSERT
+            INTO list_members (list_id, user_id, permission, created_at)
+            VALUES (?, ?, ?, ?)
+            ON CONFLICT (list_id, user_id) DO NOTHING
+            "#,
+        )
+        .bind(list_id)
+        .bind(user_id)
+        .bind(permission)
+        .bind(timestamp(Utc::now()))
+        .execute(&mut *transaction)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        transaction
+            .commit()
+            .await
+            .map_err(Into::into)
+            .map_err(RepositoryError::Internal)?;
+
+        ().into()
+    }
+
+    async fn decline_invitation(
+        &self,
+        invitation_id: &i64,
+        user_id: &i64,
+    ) -> ErrorOr<()> {
+        let db_response = sqlx::query(
+            r#"
+            DELETE
+            FROM list_invitations
+            WHERE id = ? AND user_id = ?
+            "#,
+        )
+        .bind(invitation_id)
+        .bind(user_id)
+        .execute(&self.pool)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        if db_response.rows_affected() == 0 {
+            Err(RepositoryError::NotFound {
+                relation_name: list::INVITATION_RELATION.to_string(),
+            })?;
+        }
+
+        ().into()
+    }
+}
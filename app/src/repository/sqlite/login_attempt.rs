@@ -0,0 +1,121 @@
+use chrono::{DateTime, Utc};
+
+use super::timestamp;
+use crate::{
+    repository::{
+        error::RepositoryError,
+        login_attempt::{FailedLogins, LoginAttemptRepository},
+    },
+    util::error_or::ErrorOr,
+};
+
+pub struct SqliteLoginAttemptRepository {
+    pool: sqlx::SqlitePool,
+}
+
+impl SqliteLoginAttemptRepository {
+    pub fn new(pool: sqlx::SqlitePool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl LoginAttemptRepository for SqliteLoginAttemptRepository {
+    async fn get_failed_logins(
+        &self,
+        key: &str,
+    ) -> ErrorOr<Option<FailedLogins>> {
+        let db_response = sqlx::query_as::<_, FailedLogins>(
+            r#"
+            SELECT failures, last_failure_at
+            FROM login_attempts
+            WHERE key = ?
+            "#,
+        )
+        .bind(key)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        db_response.into()
+    }
+
+    async fn reserve_login_attempt(
+        &self,
+        key: &str,
+        previous: Option<&FailedLogins>,
+        reset_before: DateTime<Utc>,
+    ) -> ErrorOr<bool> {
+        let query = match previous {
+            None => sqlx::query(
+                r#"
+                INSERT
+                INTO login_attempts (key, failures, last_failure_at)
+                VALUES (?1, 1, ?2)
+                ON CONFLICT (key) DO NOTHING
+                "#,
+            )
+            .bind(key)
+            .bind(timestamp(Utc::now())),
+            Some(previous) => sqlx::query(
+                r#"
+                UPDATE login_attempts
+                SET
+                    failures = CASE
+                        WHEN last_failure_at < ?5 THEN 1
+                        ELSE failures + 1
+                    END,
+                    last_failure_at = ?2
+                WHERE key = ?1 AND failures = ?3 AND last_failure_at = ?4
+                "#,
+            )
+            .bind(key)
+            .bind(timestamp(Utc::now()))
+            .bind(previous.failures)
+            .bind(timestamp(previous.last_failure_at))
+            .bind(timestamp(reset_before)),
+        };
+        let db_response = query
+            .execute(&self.pool)
+            .await
+            .map_err(Into::into)
+            .map_err(RepositoryError::Internal)?;
+
+        (db_response.rows_affected() == 1).into()
+    }
+
+    async fn release_login_attempt(&self, key: &str) -> ErrorOr<()> {
+        sqlx::query(
+            r#"
+            UPDATE login_attempts
+            SET failures = failures - 1
+            WHERE key = ? AND failures > 0
+            "#,
+        )
+        .bind(key)
+        .execute(&self.pool)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        ().into()
+    }
+
+    async fn clear_failed_logins(&self, key: &str) -> ErrorOr<()> {
+        sqlx::query(
+            r#"
+            DELETE
+            FROM login_attempts
+            WHERE key = ?
+            "#,
+        )
+        .bind(key)
+        .execute(&self.pool)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        ().into()
+    }
+}
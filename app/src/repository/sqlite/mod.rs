@@ -0,0 +1,1193 @@
+//! Repositories backed by a single SQLite database file.
+//!
+//! SQLite has no server to check the queries against at compile time, so the
+//! repositories use the unchecked `sqlx::query*` functions and are covered by
+//! the tests below instead.
+
+use access_token::SqliteAccessTokenRepository;
+use attachment::SqliteAttachmentRepository;
+use audit::SqliteAuditRepository;
+use chrono::{DateTime, SecondsFormat, Utc};
+use comment::SqliteCommentRepository;
+use list::SqliteListRepository;
+use login_attempt::SqliteLoginAttemptRepository;
+use oidc::SqliteOidcIdentityRepository;
+use passkey::SqlitePasskeyRepository;
+use session::SqliteSessionRepository;
+use sqlx::{
+    query::QueryAs,
+    sqlite::{SqliteArguments, SqliteRow},
+    Executor, FromRow, Sqlite, SqlitePool,
+};
+use todo::SqliteTodoRepository;
+use totp::SqliteTotpRepository;
+use user::SqliteUserRepository;
+use user_token::SqliteUserTokenRepository;
+
+use super::Backend;
+
+pub mod access_token;
+pub mod attachment;
+pub mod audit;
+pub mod comment;
+pub mod list;
+pub mod login_attempt;
+pub mod oidc;
+pub mod passkey;
+pub mod session;
+pub mod todo;
+pub mod totp;
+pub mod user;
+pub mod user_token;
+
+#[derive(Clone)]
+pub struct SqliteBackend {
+    pool: SqlitePool,
+}
+
+impl SqliteBackend {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+}
+
+impl Backend for SqliteBackend {
+    type Todo = SqliteTodoRepository;
+    type User = SqliteUserRepository;
+    type Session = SqliteSessionRepository;
+    type LoginAttempt = SqliteLoginAttemptRepository;
+    type UserToken = SqliteUserTokenRepository;
+    type Totp = SqliteTotpRepository;
+    type Passkey = SqlitePasskeyRepository;
+    type OidcIdentity = SqliteOidcIdentityRepository;
+    type AccessToken = SqliteAccessTokenRepository;
+    type List = SqliteListRepository;
+    type Comment = SqliteCommentRepository;
+    type Audit = SqliteAuditRepository;
+    type Attachment = SqliteAttachmentRepository;
+
+    fn todo_repository(&self) -> Self::Todo {
+        SqliteTodoRepository::new(self.pool.clone())
+    }
+
+    fn user_repository(&self) -> Self::User {
+        SqliteUserRepository::new(self.pool.clone())
+    }
+
+    fn session_repository(&self) -> Self::Session {
+        SqliteSessionRepository::new(self.pool.clone())
+    }
+
+    fn login_attempt_repository(&self) -> Self::LoginAttempt {
+        SqliteLoginAttemptRepository::new(self.pool.clone())
+    }
+
+    fn user_token_repository(&self) -> Self::UserToken {
+        SqliteUserTokenRepository::new(self.pool.clone())
+    }
+
+    fn totp_repository(&self) -> Self::Totp {
+        SqliteTotpRepository::new(self.pool.clone())
+    }
+
+    fn passkey_repository(&self) -> Self::Passkey {
+        SqlitePasskeyRepository::new(self.pool.clone())
+    }
+
+    fn oidc_identity_repository(&self) -> Self::OidcIdentity {
+        SqliteOidcIdentityRepository::new(self.pool.clone())
+    }
+
+    fn access_token_repository(&self) -> Self::AccessToken {
+        SqliteAccessTokenRepository::new(self.pool.clone())
+    }
+
+    fn list_repository(&self) -> Self::List {
+        SqliteListRepository::new(self.pool.clone())
+    }
+
+    fn comment_repository(&self) -> Self::Comment {
+        SqliteCommentRepository::new(self.pool.clone())
+    }
+
+    fn audit_repository(&self) -> Self::Audit {
+        SqliteAuditRepository::new(self.pool.clone())
+    }
+
+    fn attachment_repository(&self) -> Self::Attachment {
+        SqliteAttachmentRepository::new(self.pool.clone())
+    }
+}
+
+/// Runs an `INSERT` or `UPDATE` with a `RETURNING` clause to completion.
+///
+/// `fetch_one` stops stepping the statement after the first row, which leaves
+/// the write uncommitted until the connection runs its next statement.
+async fn fetch_returning<'q, 'c, T, E>(
+    query: QueryAs<'q, Sqlite, T, SqliteArguments<'q>>,
+    executor: E,
+) -> Result<T, sqlx::Error>
+where
+    T: Send + Unpin + for<'r> FromRow<'r, SqliteRow>,
+    E: Executor<'c, Database = Sqlite>,
+{
+    query.fetch_all(executor).await?.pop().ok_or(sqlx::Error::RowNotFound)
+}
+
+/// Formats a timestamp like the column defaults,
+/// `strftime('%Y-%m-%dT%H:%M:%fZ')`.
+///
+/// Timestamps are stored as text and compared as strings, which only orders
+/// them correctly if all of them have the same format. sqlx would write them
+/// with a `+00:00` offset and up to nanoseconds, so they are bound as strings.
+fn timestamp(time: DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_http::StatusCode;
+    use actix_session::storage::SessionKey;
+    use chrono::{Duration, SubsecRound, Utc};
+    use shared::models::{
+        audit::{AuditAction, AuditEntity, SecurityEventKind},
+        comment::{CreateComment, UpdateComment},
+        list::{CreateList, ListPermission},
+        todo::{CreateTodo, UpdateTodo},
+        user::{CreateUser, Role, TokenScope, UpdateUser},
+    };
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    use super::*;
+    use crate::repository::{
+        access_token::AccessTokenRepository,
+        attachment::{AttachmentRepository, NewAttachment},
+        audit::{AuditContext, AuditRepository, NewSecurityEvent},
+        comment::CommentRepository,
+        list::ListRepository,
+        login_attempt::LoginAttemptRepository,
+        oidc::OidcIdentityRepository,
+        session::SessionRepository,
+        todo::{CreateImportedTodo, ExternalId, TodoRepository, TodoStats},
+        totp::TotpRepository,
+        user::{UserRepository, UserStats},
+        user_token::{TokenPurpose, UserTokenRepository},
+    };
+    use crate::util::error::Error;
+
+    async fn backend() -> SqliteBackend {
+        // every connection to `:memory:` opens a new database
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        sqlx::migrate!("../database/sqlite").run(&pool).await.unwrap();
+
+        SqliteBackend::new(pool)
+    }
+
+    async fn create_user(backend: &SqliteBackend, email: &str) -> i64 {
+        let users = backend.user_repository();
+        users
+            .create_user(
+                &CreateUser {
+                    name: "Jane".to_string(),
+                    email: email.to_string(),
+                    password: "hash".to_string(),
+                },
+                &AuditContext::default(),
+            )
+            .await
+            .0
+            .unwrap();
+
+        users.get_user_by_email(email).await.0.unwrap().id
+    }
+
+    #[actix_rt::test]
+    async fn users_round_trip() {
+        let backend = backend().await;
+        let users = backend.user_repository();
+        let id = create_user(&backend, "jane@example.com").await;
+        // a parallel registration of the same email is a conflict
+        let duplicate = users
+            .create_user(
+                &CreateUser {
+                    name: "Eve".to_string(),
+                    email: "jane@example.com".to_string(),
+                    password: "hash".to_string(),
+                },
+                &AuditContext::default(),
+            )
+            .await;
+        assert!(matches!(
+            duplicate.0,
+            Err(Error::External(StatusCode::CONFLICT, _))
+        ));
+
+        users
+            .update_user(
+                &UpdateUser {
+                    name: Some("Janet".to_string()),
+                    email: None,
+                    password: None,
+                    current_password: None,
+                },
+                &id,
+                &AuditContext::default(),
+            )
+            .await
+            .0
+            .unwrap();
+
+        let user = users.get_session_user(&id).await.0.unwrap();
+        assert_eq!(user.name, "Janet");
+        assert_eq!(user.email, "jane@example.com");
+        assert_eq!(users.count_users().await.0.unwrap(), 1);
+
+        users.delete_user(&id, &AuditContext::default()).await.0.unwrap();
+        assert!(users.get_session_user(&id).await.0.is_err());
+    }
+
+    #[actix_rt::test]
+    async fn users_are_searched_and_disabled() {
+        let backend = backend().await;
+        let users = backend.user_repository();
+        let jane = create_user(&backend, "jane@example.com").await;
+        let john = create_user(&backend, "john@example.org").await;
+
+        let emails = |search| {
+            let users = &users;
+            async move {
+                let found = users.search_users(search, 0, 10).await.0.unwrap();
+                found.into_iter().map(|user| user.email).collect::<Vec<_>>()
+            }
+        };
+        assert_eq!(emails(Some("EXAMPLE.org")).await, ["john@example.org"]);
+        assert_eq!(emails(None).await.len(), 2);
+        let page = users.search_users(None, 1, 1).await.0.unwrap();
+        assert_eq!(page[0].id, john);
+
+        users
+            .set_role(&jane, Role::Admin, &AuditContext::default())
+            .await
+            .0
+            .unwrap();
+        users
+            .set_disabled(&john, true, &AuditContext::default())
+            .await
+            .0
+            .unwrap();
+        let disabled_at = users.get_session_user(&john).await.0.unwrap();
+        users
+            .set_disabled(&john, true, &AuditContext::default())
+            .await
+            .0
+            .unwrap();
+        let user = users.get_session_user(&john).await.0.unwrap();
+        assert_eq!(user.disabled_at, disabled_at.disabled_at);
+        assert_eq!(
+            users.get_session_user(&jane).await.0.unwrap().role,
+            Role::Admin
+        );
+        assert_eq!(
+            users.user_stats().await.0.unwrap(),
+            UserStats { total: 2, admins: 1, disabled: 1 }
+        );
+
+        users
+            .set_disabled(&john, false, &AuditContext::default())
+            .await
+            .0
+            .unwrap();
+        let user = users.get_session_user(&john).await.0.unwrap();
+        assert_eq!(user.disabled_at, None);
+    }
+
+    #[actix_rt::test]
+    async fn todos_are_only_visible_to_their_owner() {
+        let backend = backend().await;
+        let todos = backend.todo_repository();
+        let owner = create_user(&backend, "owner@example.com").await;
+        let other = create_user(&backend, "other@example.com").await;
+
+        let todo = todos
+            .create_todo(
+                &CreateTodo {
+                    title: "Water the plants".to_string(),
+                    description: String::new(),
+                    list_id: None,
+                    due_at: None,
+                },
+                &owner,
+                &AuditContext::default(),
+            )
+            .await
+            .0
+            .unwrap();
+
+        let done = UpdateTodo {
+            id: todo.id,
+            title: None,
+            description: None,
+            is_done: Some(true),
+            due_at: None,
+        };
+        assert!(todos
+            .update_todo(&done, &other, &AuditContext::default())
+            .await
+            .0
+            .is_err());
+        let updated = todos
+            .update_todo(&done, &owner, &AuditContext::default())
+            .await
+            .0
+            .unwrap();
+        assert!(updated.is_done);
+        assert_eq!(updated.title, todo.title);
+
+        assert!(todos.get_todo(&todo.id, &other).await.0.is_err());
+        assert_eq!(todos.get_todo(&todo.id, &owner).await.0.unwrap(), updated);
+        assert!(todos.get_todos(&other).await.0.unwrap().is_empty());
+        assert_eq!(
+            todos.todo_stats().await.0.unwrap(),
+            TodoStats { total: 1, done: 1 }
+        );
+
+        todos
+            .delete_todo(&todo.id, &other, &AuditContext::default())
+            .await
+            .0
+            .unwrap();
+        assert_eq!(todos.get_todos(&owner).await.0.unwrap().len(), 1);
+        todos
+            .delete_todo(&todo.id, &owner, &AuditContext::default())
+            .await
+            .0
+            .unwrap();
+        assert!(todos.get_todos(&owner).await.0.unwrap().is_empty());
+    }
+
+    #[actix_rt::test]
+    async fn lists_share_their_todos_with_the_members() {
+        let backend = backend().await;
+        let lists = backend.list_repository();
+        let todos = backend.todo_repository();
+        let owner = create_user(&backend, "owner@example.com").await;
+        let member = create_user(&backend, "member@example.com").await;
+
+        let list = lists
+            .create_list(&CreateList { name: "Chores".to_string() }, &owner)
+            .await
+            .0
+            .unwrap();
+        assert_eq!(list.permission, ListPermission::Admin);
+        let create_todo = CreateTodo {
+            title: "Take out the trash".to_string(),
+            description: String::new(),
+            list_id: Some(list.id),
+            due_at: None,
+        };
+        let todo = todos
+            .create_todo(&create_todo, &owner, &AuditContext::default())
+            .await
+            .0
+            .unwrap();
+        assert!(todos
+            .create_todo(&create_todo, &member, &AuditContext::default())
+            .await
+            .0
+            .is_err());
+        assert!(lists.get_list(&list.id, &member).await.0.is_err());
+
+        lists
+            .create_invitation(
+                &list.id,
+                &member,
+                &owner,
+                ListPermission::Viewer,
+            )
+            .await
+            .0
+            .unwrap();
+        let invitations = lists.get_invitations(&member).await.0.unwrap();
+        assert_eq!(invitations[0].list_name, "Chores");
+        assert!(lists
+            .accept_invitation(&invitations[0].id, &owner)
+            .await
+            .0
+            .is_err());
+        lists.accept_invitation(&invitations[0].id, &member).await.0.unwrap();
+        assert!(lists.get_invitations(&member).await.0.unwrap().is_empty());
+
+        assert_eq!(todos.get_todo(&todo.id, &member).await.0.unwrap(), todo);
+        assert_eq!(
+            todos.get_todos(&member).await.0.unwrap(),
+            vec![todo.clone()]
+        );
+        let done = UpdateTodo {
+            id: todo.id,
+            title: None,
+            description: None,
+            is_done: Some(true),
+            due_at: None,
+        };
+        assert!(todos
+            .update_todo(&done, &member, &AuditContext::default())
+            .await
+            .0
+            .is_err());
+        lists
+            .set_member_permission(&list.id, &member, ListPermission::Editor)
+            .await
+            .0
+            .unwrap();
+        assert!(
+            todos
+                .update_todo(&done, &member, &AuditContext::default())
+                .await
+                .0
+                .unwrap()
+                .is_done
+        );
+        todos
+            .create_todo(&create_todo, &member, &AuditContext::default())
+            .await
+            .0
+            .unwrap();
+        assert_eq!(lists.get_members(&list.id).await.0.unwrap().len(), 2);
+
+        let assigned = todos
+            .assign_todo(
+                &todo.id,
+                Some(member),
+                &owner,
+                &AuditContext::default(),
+            )
+            .await
+            .0
+            .unwrap();
+        assert_eq!(assigned.assignee_id, Some(member));
+        todos
+            .assign_todo(
+                &todo.id,
+                Some(member),
+                &member,
+                &AuditContext::default(),
+            )
+            .await
+            .0
+            .unwrap();
+        let activities = todos.get_activities(&todo.id).await.0.unwrap();
+        assert_eq!(activities.len(), 1);
+        assert_eq!(
+            (activities[0].actor_id, activities[0].assignee_id),
+            (Some(owner), Some(member))
+        );
+
+        lists.remove_member(&list.id, &member, &owner).await.0.unwrap();
+        assert!(todos.get_todos(&member).await.0.unwrap().is_empty());
+        let todo = todos.get_todo(&todo.id, &owner).await.0.unwrap();
+        assert_eq!(todo.assignee_id, None);
+        let activities = todos.get_activities(&todo.id).await.0.unwrap();
+        assert_eq!(activities.len(), 2);
+        assert_eq!(
+            (
+                activities[1].actor_id,
+                activities[1].previous_assignee_id,
+                activities[1].assignee_id
+            ),
+            (Some(owner), Some(member), None)
+        );
+        assert!(todos
+            .assign_todo(&todo.id, None, &member, &AuditContext::default())
+            .await
+            .0
+            .is_err());
+        let former = todos
+            .assign_todo(
+                &todo.id,
+                Some(member),
+                &owner,
+                &AuditContext::default(),
+            )
+            .await;
+        assert!(matches!(
+            former.0,
+            Err(Error::External(StatusCode::CONFLICT, _))
+        ));
+        assert_eq!(todos.get_activities(&todo.id).await.0.unwrap().len(), 2);
+        assert!(lists
+            .remove_member(&list.id, &member, &owner)
+            .await
+            .0
+            .is_err());
+
+        lists.delete_list(&list.id).await.0.unwrap();
+        assert!(todos.get_todos(&owner).await.0.unwrap().is_empty());
+        assert!(lists.get_lists(&owner).await.0.unwrap().is_empty());
+    }
+
+    #[actix_rt::test]
+    async fn audit_events_are_append_only() {
+        let backend = backend().await;
+        let todos = backend.todo_repository();
+        let audits = backend.audit_repository();
+        let owner = create_user(&backend, "jane@example.com").await;
+        let audit = AuditContext {
+            actor_id: Some(owner),
+            request_id: Some("request".to_string()),
+        };
+
+        let todo = todos
+            .create_todo(
+                &CreateTodo {
+                    title: "Milk".to_string(),
+                    description: String::new(),
+                    list_id: None,
+                    due_at: None,
+                },
+                &owner,
+                &audit,
+            )
+            .await
+            .0
+            .unwrap();
+        todos
+            .update_todo(
+                &UpdateTodo {
+                    id: todo.id,
+                    title: Some("Oat milk".to_string()),
+                    description: None,
+                    is_done: None,
+                    due_at: None,
+                },
+                &owner,
+                &audit,
+            )
+            .await
+            .0
+            .unwrap();
+        todos.delete_todo(&todo.id, &owner, &audit).await.0.unwrap();
+
+        let history = audits
+            .get_history(AuditEntity::Todo, &todo.id, 0, 10)
+            .await
+            .0
+            .unwrap();
+        assert_eq!(
+            history.iter().map(|event| event.action).collect::<Vec<_>>(),
+            [AuditAction::Created, AuditAction::Updated, AuditAction::Deleted]
+        );
+        assert_eq!(
+            history[1].changes,
+            serde_json::json!({
+                "title": { "before": "Milk", "after": "Oat milk" }
+            })
+        );
+        assert_eq!(history[1].request_id.as_deref(), Some("request"));
+        let activity =
+            audits.get_account_activity(&owner, 0, 2).await.0.unwrap();
+        assert_eq!(activity, [history[2].clone(), history[1].clone()]);
+
+        let event = NewSecurityEvent {
+            kind: SecurityEventKind::LoginFailed,
+            user_id: Some(owner),
+            ip: Some("127.0.0.1".to_string()),
+            ..Default::default()
+        };
+        audits.record_security_event(&event).await.0.unwrap();
+        audits
+            .record_security_event(&NewSecurityEvent::default())
+            .await
+            .0
+            .unwrap();
+        let events =
+            audits.get_security_events(Some(owner), 0, 10).await.0.unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].kind, SecurityEventKind::LoginFailed);
+        assert_eq!(
+            audits.get_security_events(None, 0, 10).await.0.unwrap().len(),
+            2
+        );
+
+        for statement in [
+            "UPDATE audit_events SET action = 'created'",
+            "DELETE FROM audit_events",
+            "DELETE FROM security_events",
+        ] {
+            assert!(sqlx::query(statement)
+                .execute(&backend.pool)
+                .await
+                .is_err());
+        }
+    }
+
+    #[actix_rt::test]
+    async fn attachments_are_limited_by_the_quota() {
+        let backend = backend().await;
+        let todos = backend.todo_repository();
+        let attachments = backend.attachment_repository();
+        let uploader = create_user(&backend, "jane@example.com").await;
+        let todo = todos
+            .create_todo(
+                &CreateTodo {
+                    title: "Milk".to_string(),
+                    description: String::new(),
+                    list_id: None,
+                    due_at: None,
+                },
+                &uploader,
+                &AuditContext::default(),
+            )
+            .await
+            .0
+            .unwrap();
+        let attachment = |storage_key: &str| NewAttachment {
+            todo_id: todo.id,
+            uploader_id: uploader,
+            file_name: "receipt.txt".to_string(),
+            size: 6,
+            content_type: "text/plain".to_string(),
+            checksum: "checksum".to_string(),
+            storage_key: storage_key.to_string(),
+        };
+
+        let receipt = attachments
+            .create_attachment(&attachment("first"), 10)
+            .await
+            .0
+            .unwrap()
+            .unwrap();
+        assert_eq!(receipt.uploader_id, Some(uploader));
+        assert!(attachments
+            .create_attachment(&attachment("second"), 10)
+            .await
+            .0
+            .unwrap()
+            .is_none());
+        assert_eq!(attachments.get_usage(&uploader).await.0.unwrap(), 6);
+        attachments
+            .create_attachment(&attachment("second"), 12)
+            .await
+            .0
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            attachments.get_attachments(&todo.id).await.0.unwrap().len(),
+            2
+        );
+
+        // deleting them, also along with the todo, orphans their blobs
+        attachments.delete_attachment(&receipt.id).await.0.unwrap();
+        todos
+            .delete_todo(&todo.id, &uploader, &AuditContext::default())
+            .await
+            .0
+            .unwrap();
+        assert_eq!(attachments.get_usage(&uploader).await.0.unwrap(), 0);
+        let mut orphaned = attachments.get_orphaned_blobs(10).await.0.unwrap();
+        orphaned.sort();
+        assert_eq!(orphaned, ["first", "second"]);
+        attachments.forget_orphaned_blob("first").await.0.unwrap();
+        assert_eq!(
+            attachments.get_orphaned_blobs(10).await.0.unwrap(),
+            ["second"]
+        );
+    }
+
+    #[actix_rt::test]
+    async fn todos_are_due_and_completed() {
+        let backend = backend().await;
+        let todos = backend.todo_repository();
+        let owner = create_user(&backend, "owner@example.com").await;
+        // timestamps are stored with millisecond precision
+        let due_at = Some((Utc::now() + Duration::days(1)).trunc_subsecs(3));
+        let todo = todos
+            .create_todo(
+                &CreateTodo {
+                    title: "Milk".to_string(),
+                    description: String::new(),
+                    list_id: None,
+                    due_at,
+                },
+                &owner,
+                &AuditContext::default(),
+            )
+            .await
+            .0
+            .unwrap();
+        assert_eq!((todo.due_at, todo.completed_at), (due_at, None));
+
+        let update = |is_done, due_at| UpdateTodo {
+            id: todo.id,
+            is_done,
+            due_at,
+            ..Default::default()
+        };
+        let audit = AuditContext::default();
+        let done =
+            todos.update_todo(&update(Some(true), None), &owner, &audit).await;
+        let done = done.0.unwrap();
+        assert_eq!(done.due_at, due_at);
+        assert!(done.completed_at.is_some());
+        // later changes keep when it was completed
+        let renamed =
+            todos.update_todo(&update(None, Some(None)), &owner, &audit).await;
+        let renamed = renamed.0.unwrap();
+        assert_eq!(
+            (renamed.due_at, renamed.completed_at),
+            (None, done.completed_at)
+        );
+        let undone =
+            todos.update_todo(&update(Some(false), None), &owner, &audit).await;
+        assert_eq!(undone.0.unwrap().completed_at, None);
+    }
+
+    #[actix_rt::test]
+    async fn imports_are_applied_at_once() {
+        let backend = backend().await;
+        let todos = backend.todo_repository();
+        let owner = create_user(&backend, "owner@example.com").await;
+        let other = create_user(&backend, "other@example.com").await;
+        let audit = AuditContext::default();
+        let foreign = todos
+            .create_todo(&CreateTodo::default(), &other, &audit)
+            .await
+            .0
+            .unwrap();
+        let create = [CreateImportedTodo {
+            create_todo: CreateTodo {
+                title: "Milk".to_string(),
+                ..Default::default()
+            },
+            is_done: true,
+            external_id: Some("milk".to_string()),
+        }];
+
+        // the forbidden update rolls back the created todo
+        let update = [UpdateTodo { id: foreign.id, ..Default::default() }];
+        let failed = todos.import_todos(&create, &update, &owner, &audit).await;
+        assert!(matches!(
+            failed.0,
+            Err(Error::External(StatusCode::FORBIDDEN, _))
+        ));
+        assert!(todos.get_todos(&owner).await.0.unwrap().is_empty());
+        assert!(todos.get_external_ids(&owner).await.0.unwrap().is_empty());
+
+        let created =
+            todos.import_todos(&create, &[], &owner, &audit).await.0.unwrap();
+        assert_eq!(created.len(), 1);
+        assert!(created[0].is_done);
+        assert!(created[0].completed_at.is_some());
+        assert_eq!(
+            todos.get_external_ids(&owner).await.0.unwrap(),
+            [ExternalId {
+                external_id: "milk".to_string(),
+                todo_id: created[0].id
+            }]
+        );
+        let history = backend
+            .audit_repository()
+            .get_history(AuditEntity::Todo, &created[0].id, 0, 10)
+            .await
+            .0
+            .unwrap();
+        assert_eq!(
+            history.iter().map(|event| event.action).collect::<Vec<_>>(),
+            [AuditAction::Created, AuditAction::Updated]
+        );
+        assert_eq!(
+            history[1].changes,
+            serde_json::json!({
+                "external_id": { "before": null, "after": "milk" }
+            })
+        );
+    }
+
+    #[actix_rt::test]
+    async fn timestamps_are_written_like_the_defaults() {
+        let backend = backend().await;
+        let todos = backend.todo_repository();
+        let owner = create_user(&backend, "owner@example.com").await;
+        let audit = AuditContext::default();
+        let todo = todos
+            .create_todo(&CreateTodo::default(), &owner, &audit)
+            .await
+            .0
+            .unwrap();
+        let update = UpdateTodo {
+            id: todo.id,
+            is_done: Some(true),
+            due_at: Some(Some(Utc::now())),
+            ..Default::default()
+        };
+        todos.update_todo(&update, &owner, &audit).await.0.unwrap();
+
+        let default: String =
+            sqlx::query_scalar("SELECT strftime('%Y-%m-%dT%H:%M:%fZ', 'now')")
+                .fetch_one(&backend.pool)
+                .await
+                .unwrap();
+        let stored: Vec<String> = sqlx::query_scalar(
+            r#"
+            SELECT created_at FROM users
+            UNION ALL SELECT updated_at FROM todos
+            UNION ALL SELECT due_at FROM todos
+            UNION ALL SELECT completed_at FROM todos
+            "#,
+        )
+        .fetch_all(&backend.pool)
+        .await
+        .unwrap();
+
+        for timestamp in &stored {
+            assert_eq!(timestamp.len(), default.len(), "{timestamp}");
+            assert!(timestamp.ends_with('Z'), "{timestamp}");
+        }
+    }
+
+    #[actix_rt::test]
+    async fn login_attempts_are_reserved_once() {
+        let backend = backend().await;
+        let attempts = backend.login_attempt_repository();
+        let key = format!("email:{}@example.com", "a".repeat(320));
+        let reset_before = Utc::now() - Duration::hours(1);
+        let reserve = |previous| {
+            attempts.reserve_login_attempt(&key, previous, reset_before)
+        };
+
+        assert!(reserve(None).await.0.unwrap());
+        // a parallel login saw the same failures and has to check again
+        assert!(!reserve(None).await.0.unwrap());
+        let previous = attempts.get_failed_logins(&key).await.0.unwrap();
+        assert_eq!(previous.map(|previous| previous.failures), Some(1));
+        assert!(reserve(previous.as_ref()).await.0.unwrap());
+        assert!(!reserve(previous.as_ref()).await.0.unwrap());
+
+        attempts.release_login_attempt(&key).await.0.unwrap();
+        let released = attempts.get_failed_logins(&key).await.0.unwrap();
+        assert_eq!(released.map(|released| released.failures), Some(1));
+    }
+
+    #[actix_rt::test]
+    async fn feed_tokens_are_replaced() {
+        let backend = backend().await;
+        let todos = backend.todo_repository();
+        let jane = create_user(&backend, "jane@example.com").await;
+        let john = create_user(&backend, "john@example.com").await;
+        let audit = AuditContext::default();
+
+        todos.set_feed_token(&jane, Some("first"), &audit).await.0.unwrap();
+        todos.set_feed_token(&john, Some("other"), &audit).await.0.unwrap();
+        assert_eq!(todos.get_feed_user("first").await.0.unwrap(), Some(jane));
+        todos.set_feed_token(&jane, Some("second"), &audit).await.0.unwrap();
+        assert_eq!(todos.get_feed_user("first").await.0.unwrap(), None);
+        assert_eq!(todos.get_feed_user("second").await.0.unwrap(), Some(jane));
+        todos.set_feed_token(&jane, None, &audit).await.0.unwrap();
+        assert_eq!(todos.get_feed_user("second").await.0.unwrap(), None);
+        assert_eq!(todos.get_feed_user("other").await.0.unwrap(), Some(john));
+
+        // only when the secret token was created is audited
+        let history = backend
+            .audit_repository()
+            .get_history(AuditEntity::User, &jane, 0, 10)
+            .await
+            .0
+            .unwrap();
+        let feeds = history
+            .iter()
+            .filter(|event| event.action == AuditAction::Updated)
+            .map(|event| &event.changes["calendar_feed_created_at"])
+            .collect::<Vec<_>>();
+        assert_eq!(feeds.len(), 3);
+        assert!(feeds[0]["before"].is_null() && feeds[0]["after"].is_string());
+        assert!(
+            feeds[1]["before"].is_string() && feeds[1]["after"].is_string()
+        );
+        assert!(feeds[2]["before"].is_string() && feeds[2]["after"].is_null());
+        assert!(!history
+            .iter()
+            .any(|event| { event.changes.to_string().contains("second") }));
+    }
+
+    #[actix_rt::test]
+    async fn comments_mention_users() {
+        let backend = backend().await;
+        let todos = backend.todo_repository();
+        let comments = backend.comment_repository();
+        let author = create_user(&backend, "author@example.com").await;
+        let other = create_user(&backend, "other@example.com").await;
+        let todo = todos
+            .create_todo(
+                &CreateTodo {
+                    title: "Water the plants".to_string(),
+                    description: String::new(),
+                    list_id: None,
+                    due_at: None,
+                },
+                &author,
+                &AuditContext::default(),
+            )
+            .await
+            .0
+            .unwrap();
+
+        let comment = comments
+            .create_comment(
+                &todo.id,
+                &CreateComment {
+                    body: "Not the *cactus*".to_string(),
+                    mentions: vec![other, author, other],
+                },
+                &author,
+            )
+            .await
+            .0
+            .unwrap();
+        assert_eq!(comment.mentions, vec![author, other]);
+        let update = UpdateComment {
+            body: "Not the cactus".to_string(),
+            mentions: vec![other],
+        };
+        assert!(comments
+            .update_comment(&todo.id, &comment.id, &update, &other)
+            .await
+            .0
+            .is_err());
+        let comment = comments
+            .update_comment(&todo.id, &comment.id, &update, &author)
+            .await
+            .0
+            .unwrap();
+        assert_eq!(comment.mentions, vec![other]);
+        for _ in 0..2 {
+            comments
+                .create_comment(&todo.id, &CreateComment::default(), &author)
+                .await
+                .0
+                .unwrap();
+        }
+
+        let page = comments.get_comments(&todo.id, 0, 2).await.0.unwrap();
+        assert_eq!(page[0], comment);
+        assert_eq!(page.len(), 2);
+        assert_eq!(
+            comments.get_comments(&todo.id, 2, 2).await.0.unwrap().len(),
+            1
+        );
+        assert!(comments
+            .delete_comment(&todo.id, &comment.id, &other)
+            .await
+            .0
+            .is_err());
+        comments
+            .delete_comment(&todo.id, &comment.id, &author)
+            .await
+            .0
+            .unwrap();
+        todos
+            .delete_todo(&todo.id, &author, &AuditContext::default())
+            .await
+            .0
+            .unwrap();
+        assert!(comments
+            .get_comments(&todo.id, 0, 2)
+            .await
+            .0
+            .unwrap()
+            .is_empty());
+    }
+
+    #[actix_rt::test]
+    async fn user_tokens_are_single_use() {
+        let backend = backend().await;
+        let tokens = backend.user_token_repository();
+        let user_id = create_user(&backend, "jane@example.com").await;
+        let in_an_hour = Utc::now() + Duration::hours(1);
+        let create = |hash, purpose, expires_at| {
+            tokens.create_token(
+                hash,
+                purpose,
+                &user_id,
+                "jane@example.com",
+                expires_at,
+            )
+        };
+
+        create("old", TokenPurpose::VerifyEmail, in_an_hour).await.0.unwrap();
+        create("new", TokenPurpose::VerifyEmail, in_an_hour).await.0.unwrap();
+        create("reset", TokenPurpose::ResetPassword, in_an_hour)
+            .await
+            .0
+            .unwrap();
+        create("expired", TokenPurpose::ResetPassword, Utc::now())
+            .await
+            .0
+            .unwrap();
+
+        let verify = TokenPurpose::VerifyEmail;
+        // a new token replaces the older ones with the same purpose
+        assert!(tokens.consume_token("old", verify).await.0.is_err());
+        assert!(tokens.consume_token("reset", verify).await.0.is_err());
+        let token = tokens.consume_token("new", verify).await.0.unwrap();
+        assert_eq!(
+            (token.user_id, token.email.as_str()),
+            (user_id, "jane@example.com")
+        );
+        assert!(tokens.consume_token("new", verify).await.0.is_err());
+        assert!(tokens
+            .consume_token("expired", TokenPurpose::ResetPassword)
+            .await
+            .0
+            .is_err());
+    }
+
+    #[actix_rt::test]
+    async fn totp_steps_and_recovery_codes_are_single_use() {
+        let backend = backend().await;
+        let totp = backend.totp_repository();
+        let user_id = create_user(&backend, "jane@example.com").await;
+        let hashes = ["a".to_string(), "b".to_string()];
+
+        totp.set_pending_totp(&user_id, "OLD").await.0.unwrap();
+        totp.set_pending_totp(&user_id, "SECRET").await.0.unwrap();
+        let pending = totp.get_totp(&user_id).await.0.unwrap().unwrap();
+        assert_eq!(pending.secret, "SECRET");
+        assert_eq!(pending.confirmed_at, None);
+
+        totp.confirm_totp(&user_id, 10, &hashes).await.0.unwrap();
+        let confirmed = totp.get_totp(&user_id).await.0.unwrap().unwrap();
+        assert!(confirmed.confirmed_at.is_some());
+        assert_eq!(confirmed.last_used_step, Some(10));
+
+        assert!(!totp.use_step(&user_id, 10).await.0.unwrap());
+        assert!(!totp.use_step(&user_id, 9).await.0.unwrap());
+        assert!(totp.use_step(&user_id, 11).await.0.unwrap());
+        assert!(totp.use_recovery_code(&user_id, "a").await.0.unwrap());
+        assert!(!totp.use_recovery_code(&user_id, "a").await.0.unwrap());
+
+        totp.delete_totp(&user_id).await.0.unwrap();
+        assert_eq!(totp.get_totp(&user_id).await.0.unwrap(), None);
+        assert!(!totp.use_recovery_code(&user_id, "b").await.0.unwrap());
+    }
+
+    #[actix_rt::test]
+    async fn oidc_identities_are_unique_per_issuer() {
+        let backend = backend().await;
+        let identities = backend.oidc_identity_repository();
+        let jane = create_user(&backend, "jane@example.com").await;
+        let john = create_user(&backend, "john@example.com").await;
+
+        identities.link_oidc_identity("https://a", "1", &jane).await.0.unwrap();
+        identities.link_oidc_identity("https://b", "1", &john).await.0.unwrap();
+        assert!(identities
+            .link_oidc_identity("https://a", "1", &john)
+            .await
+            .0
+            .is_err());
+
+        let user_id = |issuer, subject| {
+            let identities = &identities;
+            async move {
+                identities.get_oidc_user_id(issuer, subject).await.0.unwrap()
+            }
+        };
+        assert_eq!(user_id("https://a", "1").await, Some(jane));
+        assert_eq!(user_id("https://b", "1").await, Some(john));
+        assert_eq!(user_id("https://a", "2").await, None);
+
+        backend
+            .user_repository()
+            .delete_user(&jane, &AuditContext::default())
+            .await
+            .0
+            .unwrap();
+        assert_eq!(user_id("https://a", "1").await, None);
+    }
+
+    #[actix_rt::test]
+    async fn expired_access_tokens_are_not_used() {
+        let backend = backend().await;
+        let tokens = backend.access_token_repository();
+        let jane = create_user(&backend, "jane@example.com").await;
+        let scopes = [TokenScope::ReadTodos, TokenScope::WriteTodos];
+
+        let active = tokens
+            .create_access_token(&jane, "active", "ci", &scopes, None)
+            .await
+            .0
+            .unwrap();
+        assert_eq!(active.scopes.0, scopes);
+        tokens
+            .create_access_token(
+                &jane,
+                "expired",
+                "ci",
+                &scopes,
+                Some(Utc::now() - Duration::minutes(1)),
+            )
+            .await
+            .0
+            .unwrap();
+
+        let used = tokens.use_access_token("active").await.0.unwrap().unwrap();
+        assert_eq!(used.id, active.id);
+        assert!(used.last_used_at.is_some());
+        assert!(tokens.use_access_token("expired").await.0.unwrap().is_none());
+        assert!(tokens.use_access_token("unknown").await.0.unwrap().is_none());
+
+        let john = create_user(&backend, "john@example.com").await;
+        assert!(tokens.delete_access_token(&active.id, &john).await.0.is_err());
+        tokens.delete_access_token(&active.id, &jane).await.0.unwrap();
+        assert!(tokens.use_access_token("active").await.0.unwrap().is_none());
+        assert_eq!(tokens.get_access_tokens(&jane).await.0.unwrap().len(), 1);
+    }
+
+    #[actix_rt::test]
+    async fn expired_sessions_are_ignored() {
+        let backend = backend().await;
+        let sessions = backend.session_repository();
+        let user_id = create_user(&backend, "jane@example.com").await;
+
+        let active = SqliteSessionRepository::generate_session_key().await;
+        let expired = SqliteSessionRepository::generate_session_key().await;
+        let state = serde_json::json!({ "key": "value" });
+
+        sessions
+            .db_save(
+                &active,
+                &state,
+                Some(user_id),
+                Utc::now() + Duration::hours(1),
+            )
+            .await
+            .unwrap();
+        sessions
+            .db_save(
+                &expired,
+                &state,
+                Some(user_id),
+                Utc::now() - Duration::hours(1),
+            )
+            .await
+            .unwrap();
+
+        let loaded = sessions.db_load(&active).await.unwrap().unwrap();
+        assert_eq!(loaded.state.0, state);
+        assert_eq!(loaded.user_id, Some(user_id));
+        assert_eq!(sessions.db_load(&expired).await.unwrap(), None);
+        assert_eq!(
+            sessions.list_sessions(Some(user_id)).await.unwrap().len(),
+            1
+        );
+        assert_eq!(sessions.count_sessions().await.unwrap(), 1);
+
+        assert_eq!(sessions.delete_expired_sessions().await.unwrap(), 1);
+        assert_eq!(sessions.delete_user_sessions(user_id).await.unwrap(), 1);
+
+        let unknown: SessionKey = "unknown".repeat(8).try_into().unwrap();
+        assert_eq!(sessions.db_load(&unknown).await.unwrap(), None);
+    }
+}
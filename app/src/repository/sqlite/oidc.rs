@@ -0,0 +1,67 @@
+use chrono::Utc;
+
+use super::timestamp;
+use crate::{
+    repository::{error::RepositoryError, oidc::OidcIdentityRepository},
+    util::error_or::ErrorOr,
+};
+
+pub struct SqliteOidcIdentityRepository {
+    pool: sqlx::SqlitePool,
+}
+
+impl SqliteOidcIdentityRepository {
+    pub fn new(pool: sqlx::SqlitePool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl OidcIdentityRepository for SqliteOidcIdentityRepository {
+    async fn get_oidc_user_id(
+        &self,
+        issuer: &str,
+        subject: &str,
+    ) -> ErrorOr<Option<i64>> {
+        let db_response = sqlx::query_scalar::<_, i64>(
+            r#"
+            SELECT user_id
+            FROM oidc_identities
+            WHERE issuer = ? AND subject = ?
+            "#,
+        )
+        .bind(issuer)
+        .bind(subject)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        db_response.into()
+    }
+
+    async fn link_oidc_identity(
+        &self,
+        issuer: &str,
+        subject: &str,
+        user_id: &i64,
+    ) -> ErrorOr<()> {
+        sqlx::query(
+            r#"
+            INSERT
+            INTO oidc_identities (issuer, subject, user_id, created_at)
+            VALUES (?, ?, ?, ?)
+            "#,
+        )
+        .bind(issuer)
+        .bind(subject)
+        .bind(user_id)
+        .bind(timestamp(Utc::now()))
+        .execute(&self.pool)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        ().into()
+    }
+}
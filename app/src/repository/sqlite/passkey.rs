@@ -0,0 +1,121 @@
+use chrono::Utc;
+use sqlx::types::Json;
+use webauthn_rs::prelude::Passkey;
+
+use super::{fetch_returning, timestamp};
+use crate::{
+    repository::{
+        error::RepositoryError,
+        passkey::{self, PasskeyRepository, StoredPasskey},
+    },
+    util::error_or::ErrorOr,
+};
+
+pub struct SqlitePasskeyRepository {
+    pool: sqlx::SqlitePool,
+}
+
+impl SqlitePasskeyRepository {
+    pub fn new(pool: sqlx::SqlitePool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl PasskeyRepository for SqlitePasskeyRepository {
+    async fn get_passkeys(&self, user_id: &i64) -> ErrorOr<Vec<StoredPasskey>> {
+        let db_response = sqlx::query_as::<_, StoredPasskey>(
+            r#"
+            SELECT id, user_id, name, passkey, created_at, last_used_at
+            FROM passkeys
+            WHERE user_id = ?
+            ORDER BY id
+            "#,
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        db_response.into()
+    }
+
+    async fn create_passkey(
+        &self,
+        user_id: &i64,
+        name: &str,
+        passkey: &Passkey,
+    ) -> ErrorOr<StoredPasskey> {
+        let query = sqlx::query_as::<_, StoredPasskey>(
+            r#"
+            INSERT
+            INTO passkeys (user_id, credential_id, name, passkey, created_at)
+            VALUES (?, ?, ?, ?, ?)
+            RETURNING id, user_id, name, passkey, created_at, last_used_at
+            "#,
+        )
+        .bind(user_id)
+        .bind(passkey::credential_id(passkey))
+        .bind(name)
+        .bind(Json(passkey))
+        .bind(timestamp(Utc::now()));
+        let db_response = fetch_returning(query, &self.pool)
+            .await
+            .map_err(Into::into)
+            .map_err(RepositoryError::Internal)?;
+
+        db_response.into()
+    }
+
+    async fn record_passkey_use(
+        &self,
+        passkey_id: &i64,
+        passkey: &Passkey,
+    ) -> ErrorOr<()> {
+        sqlx::query(
+            r#"
+            UPDATE passkeys
+            SET passkey = ?, last_used_at = ?
+            WHERE id = ?
+            "#,
+        )
+        .bind(Json(passkey))
+        .bind(timestamp(Utc::now()))
+        .bind(passkey_id)
+        .execute(&self.pool)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        ().into()
+    }
+
+    async fn delete_passkey(
+        &self,
+        passkey_id: &i64,
+        user_id: &i64,
+    ) -> ErrorOr<()> {
+        let db_response = sqlx::query(
+            r#"
+            DELETE
+            FROM passkeys
+            WHERE id = ? AND user_id = ?
+            "#,
+        )
+        .bind(passkey_id)
+        .bind(user_id)
+        .execute(&self.pool)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        if db_response.rows_affected() == 0 {
+            Err(RepositoryError::NotFound {
+                relation_name: passkey::RELATION.to_string(),
+            })?;
+        }
+
+        ().into()
+    }
+}
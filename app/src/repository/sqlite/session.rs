@@ -0,0 +1,183 @@
+use actix_session::storage::SessionKey;
+use chrono::{DateTime, Utc};
+
+use super::timestamp;
+use crate::repository::session::{Session, SessionRepository};
+
+#[derive(Clone)]
+pub struct SqliteSessionRepository {
+    pool: sqlx::SqlitePool,
+}
+
+impl SqliteSessionRepository {
+    pub fn new(pool: sqlx::SqlitePool) -> Self {
+        Self { pool }
+    }
+}
+
+// `expires_at` is stored as RFC 3339 text in UTC, which compares like the
+// timestamp itself, so `now` is bound as parameter in the same format instead
+// of using SQLite's differently formatted `datetime('now')`.
+#[async_trait::async_trait]
+impl SessionRepository for SqliteSessionRepository {
+    async fn db_load(
+        &self,
+        session_key: &SessionKey,
+    ) -> Result<Option<Session>, sqlx::Error> {
+        sqlx::query_as::<_, Session>(
+            r#"
+      SELECT key, state, user_id, expires_at
+      FROM sessions
+      WHERE key = ? AND expires_at > ?
+      "#,
+        )
+        .bind(session_key.as_ref())
+        .bind(timestamp(Utc::now()))
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    async fn db_save(
+        &self,
+        session_key: &SessionKey,
+        session_state: &serde_json::Value,
+        user_id: Option<i64>,
+        expires_at: DateTime<Utc>,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+      INSERT
+      INTO sessions (key, state, user_id, expires_at)
+      VALUES (?, ?, ?, ?)
+      "#,
+        )
+        .bind(session_key.as_ref())
+        .bind(session_state)
+        .bind(user_id)
+        .bind(timestamp(expires_at))
+        .execute(&self.pool)
+        .await
+        .map(|_| ())
+    }
+
+    async fn db_update(
+        &self,
+        session_key: &SessionKey,
+        session_state: &serde_json::Value,
+        user_id: Option<i64>,
+        expires_at: DateTime<Utc>,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+      UPDATE sessions
+      SET state = ?, user_id = ?, expires_at = ?
+      WHERE key = ?
+      "#,
+        )
+        .bind(session_state)
+        .bind(user_id)
+        .bind(timestamp(expires_at))
+        .bind(session_key.as_ref())
+        .execute(&self.pool)
+        .await
+        .map(|_| ())
+    }
+
+    async fn db_update_ttl(
+        &self,
+        session_key: &SessionKey,
+        expires_at: DateTime<Utc>,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+      UPDATE sessions
+      SET expires_at = ?
+      WHERE key = ?
+      "#,
+        )
+        .bind(timestamp(expires_at))
+        .bind(session_key.as_ref())
+        .execute(&self.pool)
+        .await
+        .map(|_| ())
+    }
+
+    async fn db_delete(
+        &self,
+        session_key: &SessionKey,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+      DELETE
+      FROM sessions
+      WHERE key = ?
+      "#,
+        )
+        .bind(session_key.as_ref())
+        .execute(&self.pool)
+        .await
+        .map(|_| ())
+    }
+
+    async fn list_sessions(
+        &self,
+        user_id: Option<i64>,
+    ) -> Result<Vec<Session>, sqlx::Error> {
+        sqlx::query_as::<_, Session>(
+            r#"
+      SELECT key, state, user_id, expires_at
+      FROM sessions
+      WHERE expires_at > ?1 AND (?2 IS NULL OR user_id = ?2)
+      ORDER BY expires_at
+      "#,
+        )
+        .bind(timestamp(Utc::now()))
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    async fn count_sessions(&self) -> Result<i64, sqlx::Error> {
+        sqlx::query_scalar::<_, i64>(
+            r#"
+      SELECT count(*)
+      FROM sessions
+      WHERE expires_at > ?
+      "#,
+        )
+        .bind(timestamp(Utc::now()))
+        .fetch_one(&self.pool)
+        .await
+    }
+
+    async fn delete_user_sessions(
+        &self,
+        user_id: i64,
+    ) -> Result<u64, sqlx::Error> {
+        sqlx::query(
+            r#"
+      DELETE
+      FROM sessions
+      WHERE user_id = ?
+      "#,
+        )
+        .bind(user_id)
+        .execute(&self.pool)
+        .await
+        .map(|result| result.rows_affected())
+    }
+
+    async fn delete_expired_sessions(&self) -> Result<u64, sqlx::Error> {
+        sqlx::query(
+            r#"
+      DELETE
+      FROM sessions
+      WHERE expires_at <= ?
+      "#,
+        )
+        .bind(timestamp(Utc::now()))
+        .execute(&self.pool)
+        .await
+        .map(|result| result.rows_affected())
+    }
+}
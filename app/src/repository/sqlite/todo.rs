@@ -0,0 +1,808 @@
+use chrono::{DateTime, Utc};
+use shared::models::{
+    audit::{AuditAction, AuditEntity},
+    todo::{CreateTodo, Todo, TodoAction, TodoActivity, UpdateTodo},
+};
+use sqlx::SqliteConnection;
+
+use super::{audit::SqliteAuditRepository, fetch_returning, timestamp};
+use crate::{
+    repository::{
+        audit::{self, AuditContext},
+        error::{Operation, RepositoryError},
+        todo::{
+            calendar_feed, external_id_changes, CreateImportedTodo, ExternalId,
+            TodoRepository, TodoStats,
+        },
+    },
+    util::error_or::ErrorOr,
+};
+
+const RELATION: &str = "Todo";
+
+pub struct SqliteTodoRepository {
+    pool: sqlx::SqlitePool,
+}
+
+impl SqliteTodoRepository {
+    pub fn new(pool: sqlx::SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// Returns the todo if the user may edit it.
+    async fn editable_todo(
+        transaction: &mut SqliteConnection,
+        todo_id: &i64,
+        session_user_id: &i64,
+    ) -> Result<Option<Todo>, RepositoryError> {
+        sqlx::query_as::<_, Todo>(
+            r#"
+            SELECT *
+            FROM todos
+            WHERE id = ?1
+                AND (
+                    (owner = ?2 AND list_id IS NULL)
+                    OR list_id IN (
+                        SELECT list_id
+                        FROM list_members
+                        WHERE user_id = ?2
+                            AND permission IN ('editor', 'admin')
+                    )
+                )
+            "#,
+        )
+        .bind(todo_id)
+        .bind(session_user_id)
+        .fetch_optional(&mut *transaction)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)
+    }
+
+    /// Whether the user sees the todo, as the owner of a personal todo or as
+    /// a member of its list.
+    async fn is_reader(
+        transaction: &mut SqliteConnection,
+        todo: &Todo,
+        user_id: &i64,
+    ) -> Result<bool, RepositoryError> {
+        let Some(list_id) = todo.list_id else {
+            return Ok(todo.owner == *user_id);
+        };
+
+        sqlx::query_scalar::<_, i64>(
+            r#"
+            SELECT user_id
+            FROM list_members
+            WHERE list_id = ? AND user_id = ?
+            "#,
+        )
+        .bind(list_id)
+        .bind(user_id)
+        .fetch_optional(&mut *transaction)
+        .await
+        .map(|member| member.is_some())
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)
+    }
+
+    /// Creates a todo in the transaction, see `create_todo`.
+    async fn insert_todo(
+        transaction: &mut SqliteConnection,
+        create_todo: &CreateTodo,
+        is_done: bool,
+        session_user_id: &i64,
+        audit: &AuditContext,
+    ) -> Result<Todo, RepositoryError> {
+        let now = Utc::now();
+        let query = sqlx::query_as::<_, Todo>(
+            r#"
+            INSERT
+            INTO todos
+                (title, description, owner, list_id, due_at, is_done,
+                completed_at, created_at, updated_at)
+            SELECT ?1, ?2, ?3, ?4, ?6, ?7, CASE WHEN ?7 THEN ?5 END, ?5, ?5
+            WHERE ?4 IS NULL
+                OR EXISTS (
+                    SELECT 1
+                    FROM list_members
+                    WHERE list_id = ?4
+                        AND user_id = ?3
+                        AND permission IN ('editor', 'admin')
+                )
+            RETURNING *
+            "#,
+        )
+        .bind(&create_todo.title)
+        .bind(&create_todo.description)
+        .bind(session_user_id)
+        .bind(create_todo.list_id)
+        .bind(timestamp(now))
+        .bind(create_todo.due_at.map(timestamp))
+        .bind(is_done);
+        let todo = fetch_returning(query, &mut *transaction).await.map_err(
+            |e| match e {
+                sqlx::Error::RowNotFound => RepositoryError::Forbidden {
+                    operation: Operation::Post,
+                    relation_name: RELATION.to_string(),
+                },
+                e => RepositoryError::Internal(e.into()),
+            },
+        )?;
+
+        SqliteAuditRepository::record(
+            &mut *transaction,
+            audit,
+            AuditAction::Created,
+            AuditEntity::Todo,
+            todo.id,
+            audit::changes(None, Some(&todo)),
+        )
+        .await?;
+
+        Ok(todo)
+    }
+
+    /// Maps the external id to the created todo in the transaction, see
+    /// `import_todos`.
+    async fn insert_external_id(
+        transaction: &mut SqliteConnection,
+        external_id: &str,
+        todo_id: &i64,
+        session_user_id: &i64,
+        audit: &AuditContext,
+    ) -> Result<(), RepositoryError> {
+        sqlx::query(
+            r#"
+            INSERT
+            INTO todo_external_ids (user_id, external_id, todo_id)
+            VALUES (?, ?, ?)
+            ON CONFLICT (user_id, external_id)
+            DO UPDATE SET todo_id = excluded.todo_id
+            "#,
+        )
+        .bind(session_user_id)
+        .bind(external_id)
+        .bind(todo_id)
+        .execute(&mut *transaction)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        SqliteAuditRepository::record(
+            &mut *transaction,
+            audit,
+            AuditAction::Updated,
+            AuditEntity::Todo,
+            *todo_id,
+            external_id_changes(external_id),
+        )
+        .await
+    }
+
+    /// Updates a todo in the transaction, see `update_todo`.
+    async fn change_todo(
+        transaction: &mut SqliteConnection,
+        update_todo: &UpdateTodo,
+        session_user_id: &i64,
+        audit: &AuditContext,
+    ) -> Result<Todo, RepositoryError> {
+        let before = Self::editable_todo(
+            &mut *transaction,
+            &update_todo.id,
+            session_user_id,
+        )
+        .await?
+        .ok_or_else(|| RepositoryError::Forbidden {
+            operation: Operation::Update,
+            relation_name: RELATION.to_string(),
+        })?;
+
+        let query = sqlx::query_as::<_, Todo>(
+            r#"
+            UPDATE todos
+            SET
+                title = COALESCE(?1, title),
+                description = COALESCE(?2, description),
+                is_done = COALESCE(?3, is_done),
+                completed_at = CASE
+                    WHEN NOT COALESCE(?3, is_done) THEN NULL
+                    ELSE COALESCE(completed_at, ?4)
+                END,
+                due_at = CASE WHEN ?6 THEN ?7 ELSE due_at END,
+                updated_at = ?4
+            WHERE id = ?5
+            RETURNING *
+            "#,
+        )
+        .bind(&update_todo.title)
+        .bind(&update_todo.description)
+        .bind(update_todo.is_done)
+        .bind(timestamp(Utc::now()))
+        .bind(update_todo.id)
+        .bind(update_todo.due_at.is_some())
+        .bind(update_todo.due_at.flatten().map(timestamp));
+        let todo = fetch_returning(query, &mut *transaction)
+            .await
+            .map_err(Into::into)
+            .map_err(RepositoryError::Internal)?;
+
+        SqliteAuditRepository::record(
+            &mut *transaction,
+            audit,
+            AuditAction::Updated,
+            AuditEntity::Todo,
+            todo.id,
+            audit::changes(Some(&before), Some(&todo)),
+        )
+        .await?;
+
+        Ok(todo)
+    }
+}
+
+#[async_trait::async_trait]
+impl TodoRepository for SqliteTodoRepository {
+    async fn get_todos(&self, session_user_id: &i64) -> ErrorOr<Vec<Todo>> {
+        let db_response = sqlx::query_as::<_, Todo>(
+            r#"
+            SELECT *
+            FROM todos
+            WHERE (owner = ?1 AND list_id IS NULL)
+                OR list_id IN (
+                    SELECT list_id
+                    FROM list_members
+                    WHERE user_id = ?1
+                )
+            ORDER BY id"#,
+        )
+        .bind(session_user_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        db_response.into()
+    }
+
+    async fn get_todo(
+        &self,
+        todo_id: &i64,
+        session_user_id: &i64,
+    ) -> ErrorOr<Todo> {
+        let todo = sqlx::query_as::<_, Todo>(
+            r#"
+            SELECT *
+            FROM todos
+            WHERE id = ?
+            "#,
+        )
+        .bind(todo_id)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::RowNotFound => RepositoryError::NotFound {
+                relation_name: RELATION.to_string(),
+            },
+            e => RepositoryError::Internal(e.into()),
+        })?;
+
+        // check if session user that made the request is the owner of the
+        // personal todo or a member of its list
+        let visible = match todo.list_id {
+            Some(list_id) => sqlx::query_scalar::<_, String>(
+                r#"
+                SELECT permission
+                FROM list_members
+                WHERE list_id = ? AND user_id = ?
+                "#,
+            )
+            .bind(list_id)
+            .bind(session_user_id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(Into::into)
+            .map_err(RepositoryError::Internal)?
+            .is_some(),
+            None => todo.owner == *session_user_id,
+        };
+        let todo = if visible {
+            Ok(todo)
+        } else {
+            Err(RepositoryError::Forbidden {
+                operation: Operation::Receive,
+                relation_name: RELATION.to_string(),
+            })
+        }?;
+
+        todo.into()
+    }
+
+    async fn create_todo(
+        &self,
+        create_todo: &CreateTodo,
+        session_user_id: &i64,
+        audit: &AuditContext,
+    ) -> ErrorOr<Todo> {
+        let mut transaction = self
+            .pool
+            .begin()
+            .await
+            .map_err(Into::into)
+            .map_err(RepositoryError::Internal)?;
+
+        let todo = Self::insert_todo(
+            &mut transaction,
+            create_todo,
+            false,
+            session_user_id,
+            audit,
+        )
+        .await?;
+
+        transaction
+            .commit()
+            .await
+            .map_err(Into::into)
+            .map_err(RepositoryError::Internal)?;
+
+        todo.into()
+    }
+
+    async fn update_todo(
+        &self,
+        update_todo: &UpdateTodo,
+        session_user_id: &i64,
+        audit: &AuditContext,
+    ) -> ErrorOr<Todo> {
+        let mut transaction = self
+            .pool
+            .begin()
+            .await
+            .map_err(Into::into)
+            .map_err(RepositoryError::Internal)?;
+
+        let todo = Self::change_todo(
+            &mut transaction,
+            update_todo,
+            session_user_id,
+            audit,
+        )
+        .await?;
+
+        transaction
+            .commit()
+            .await
+            .map_err(Into::into)
+            .map_err(RepositoryError::Internal)?;
+
+        todo.into()
+    }
+
+    async fn backdate_todo(
+        &self,
+        todo_id: &i64,
+        created_at: DateTime<Utc>,
+        updated_at: DateTime<Utc>,
+        audit: &AuditContext,
+    ) -> ErrorOr<Todo> {
+        let mut transaction = self
+            .pool
+            .begin()
+            .await
+            .map_err(Into::into)
+            .map_err(RepositoryError::Internal)?;
+
+        let before =
+            sqlx::query_as::<_, Todo>("SELECT * FROM todos WHERE id = ?")
+                .bind(todo_id)
+                .fetch_one(&mut *transaction)
+                .await
+                .map_err(Into::into)
+                .map_err(RepositoryError::Internal)?;
+
+        let query = sqlx::query_as::<_, Todo>(
+            r#"
+            UPDATE todos
+            SET
+                created_at = ?2,
+                updated_at = ?3,
+                completed_at = CASE WHEN is_done THEN ?3 END
+            WHERE id = ?1
+            RETURNING *
+            "#,
+        )
+        .bind(todo_id)
+        .bind(timestamp(created_at))
+        .bind(timestamp(updated_at));
+        let todo = fetch_returning(query, &mut *transaction)
+            .await
+            .map_err(Into::into)
+            .map_err(RepositoryError::Internal)?;
+
+        SqliteAuditRepository::record(
+            &mut transaction,
+            audit,
+            AuditAction::Updated,
+            AuditEntity::Todo,
+            todo.id,
+            audit::changes(Some(&before), Some(&todo)),
+        )
+        .await?;
+
+        transaction
+            .commit()
+            .await
+            .map_err(Into::into)
+            .map_err(RepositoryError::Internal)?;
+
+        todo.into()
+    }
+
+    async fn delete_todo(
+        &self,
+        todo_id: &i64,
+        session_user_id: &i64,
+        audit: &AuditContext,
+    ) -> ErrorOr<()> {
+        let mut transaction = self
+            .pool
+            .begin()
+            .await
+            .map_err(Into::into)
+            .map_err(RepositoryError::Internal)?;
+
+        // like a `DELETE` of no rows this is not an error when the user may
+        // not edit the todo
+        let Some(before) =
+            Self::editable_todo(&mut transaction, todo_id, session_user_id)
+                .await?
+        else {
+            return ().into();
+        };
+
+        sqlx::query(
+            r#"
+            DELETE
+            FROM todos
+            WHERE id = ?
+            "#,
+        )
+        .bind(todo_id)
+        .execute(&mut *transaction)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        SqliteAuditRepository::record(
+            &mut transaction,
+            audit,
+            AuditAction::Deleted,
+            AuditEntity::Todo,
+            before.id,
+            audit::changes(Some(&before), None),
+        )
+        .await?;
+
+        transaction
+            .commit()
+            .await
+            .map_err(Into::into)
+            .map_err(RepositoryError::Internal)?;
+
+        ().into()
+    }
+
+    async fn assign_todo(
+        &self,
+        todo_id: &i64,
+        assignee_id: Option<i64>,
+        session_user_id: &i64,
+        audit: &AuditContext,
+    ) -> ErrorOr<Todo> {
+        let now = Utc::now();
+        let mut transaction = self
+            .pool
+            .begin()
+            .await
+            .map_err(Into::into)
+            .map_err(RepositoryError::Internal)?;
+
+        let before =
+            Self::editable_todo(&mut transaction, todo_id, session_user_id)
+                .await?
+                .ok_or_else(|| RepositoryError::Forbidden {
+                    operation: Operation::Update,
+                    relation_name: RELATION.to_string(),
+                })?;
+        if let Some(assignee_id) = &assignee_id {
+            if !Self::is_reader(&mut transaction, &before, assignee_id).await? {
+                Err(RepositoryError::Conflict {
+                    relation_name: RELATION.to_string(),
+                })?;
+            }
+        }
+
+        let query = sqlx::query_as::<_, Todo>(
+            r#"
+            UPDATE todos
+            SET assignee_id = ?, updated_at = ?
+            WHERE id = ?
+            RETURNING *
+            "#,
+        )
+        .bind(assignee_id)
+        .bind(timestamp(now))
+        .bind(todo_id);
+        let todo = fetch_returning(query, &mut *transaction)
+            .await
+            .map_err(Into::into)
+            .map_err(RepositoryError::Internal)?;
+
+        if before.assignee_id != assignee_id {
+            sqlx::query(
+                r#"
+                INSERT
+                INTO todo_activities (
+                    todo_id,
+                    actor_id,
+                    action,
+                    previous_assignee_id,
+                    assignee_id,
+                    created_at
+                )
+                VALUES (?, ?, ?, ?, ?, ?)
+                "#,
+            )
+            .bind(todo_id)
+            .bind(session_user_id)
+            .bind(TodoAction::Assigned.as_str())
+            .bind(before.assignee_id)
+            .bind(assignee_id)
+            .bind(timestamp(now))
+            .execute(&mut *transaction)
+            .await
+            .map_err(Into::into)
+            .map_err(RepositoryError::Internal)?;
+        }
+
+        SqliteAuditRepository::record(
+            &mut transaction,
+            audit,
+            AuditAction::Assigned,
+            AuditEntity::Todo,
+            todo.id,
+            audit::changes(Some(&before), Some(&todo)),
+        )
+        .await?;
+
+        transaction
+            .commit()
+            .await
+            .map_err(Into::into)
+            .map_err(RepositoryError::Internal)?;
+
+        todo.into()
+    }
+
+    async fn get_activities(
+        &self,
+        todo_id: &i64,
+    ) -> ErrorOr<Vec<TodoActivity>> {
+        let db_response = sqlx::query_as::<_, TodoActivity>(
+            r#"
+            SELECT
+                id,
+                todo_id,
+                actor_id,
+                action,
+                previous_assignee_id,
+                assignee_id,
+                created_at
+            FROM todo_activities
+            WHERE todo_id = ?
+            ORDER BY id
+            "#,
+        )
+        .bind(todo_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        db_response.into()
+    }
+
+    async fn todo_stats(&self) -> ErrorOr<TodoStats> {
+        let db_response = sqlx::query_as::<_, TodoStats>(
+            r#"
+            SELECT
+                count(*) as total,
+                count(*) FILTER (WHERE is_done) as done
+            FROM todos
+            "#,
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        db_response.into()
+    }
+
+    async fn get_external_ids(
+        &self,
+        session_user_id: &i64,
+    ) -> ErrorOr<Vec<ExternalId>> {
+        let db_response = sqlx::query_as::<_, ExternalId>(
+            r#"
+            SELECT external_id, todo_id
+            FROM todo_external_ids
+            WHERE user_id = ?
+            "#,
+        )
+        .bind(session_user_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        db_response.into()
+    }
+
+    async fn import_todos(
+        &self,
+        create: &[CreateImportedTodo],
+        update: &[UpdateTodo],
+        session_user_id: &i64,
+        audit: &AuditContext,
+    ) -> ErrorOr<Vec<Todo>> {
+        let mut transaction = self
+            .pool
+            .begin()
+            .await
+            .map_err(Into::into)
+            .map_err(RepositoryError::Internal)?;
+
+        let mut created = Vec::with_capacity(create.len());
+        for imported in create {
+            let todo = Self::insert_todo(
+                &mut transaction,
+                &imported.create_todo,
+                imported.is_done,
+                session_user_id,
+                audit,
+            )
+            .await?;
+            if let Some(external_id) = &imported.external_id {
+                Self::insert_external_id(
+                    &mut transaction,
+                    external_id,
+                    &todo.id,
+                    session_user_id,
+                    audit,
+                )
+                .await?;
+            }
+            created.push(todo);
+        }
+        for update_todo in update {
+            Self::change_todo(
+                &mut transaction,
+                update_todo,
+                session_user_id,
+                audit,
+            )
+            .await?;
+        }
+
+        transaction
+            .commit()
+            .await
+            .map_err(Into::into)
+            .map_err(RepositoryError::Internal)?;
+
+        created.into()
+    }
+
+    async fn set_feed_token(
+        &self,
+        user_id: &i64,
+        token_hash: Option<&str>,
+        audit: &AuditContext,
+    ) -> ErrorOr<()> {
+        let mut transaction = self
+            .pool
+            .begin()
+            .await
+            .map_err(Into::into)
+            .map_err(RepositoryError::Internal)?;
+
+        let before = sqlx::query_scalar::<_, DateTime<Utc>>(
+            r#"
+            SELECT created_at
+            FROM calendar_feeds
+            WHERE user_id = ?
+            "#,
+        )
+        .bind(user_id)
+        .fetch_optional(&mut *transaction)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        let after = match token_hash {
+            Some(token_hash) => {
+                let created_at = Utc::now();
+                sqlx::query(
+                    r#"
+                    INSERT
+                    INTO calendar_feeds (user_id, token_hash, created_at)
+                    VALUES (?1, ?2, ?3)
+                    ON CONFLICT (user_id)
+                    DO UPDATE SET
+                        token_hash = excluded.token_hash,
+                        created_at = excluded.created_at
+                    "#,
+                )
+                .bind(user_id)
+                .bind(token_hash)
+                .bind(timestamp(created_at))
+                .execute(&mut *transaction)
+                .await
+                .map(|_| Some(created_at))
+            }
+            None => sqlx::query(
+                r#"
+                DELETE
+                FROM calendar_feeds
+                WHERE user_id = ?
+                "#,
+            )
+            .bind(user_id)
+            .execute(&mut *transaction)
+            .await
+            .map(|_| None),
+        }
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        SqliteAuditRepository::record(
+            &mut transaction,
+            audit,
+            AuditAction::Updated,
+            AuditEntity::User,
+            *user_id,
+            audit::changes(
+                Some(&calendar_feed(before)),
+                Some(&calendar_feed(after)),
+            ),
+        )
+        .await?;
+
+        transaction
+            .commit()
+            .await
+            .map_err(Into::into)
+            .map_err(RepositoryError::Internal)?;
+
+        ().into()
+    }
+
+    async fn get_feed_user(&self, token_hash: &str) -> ErrorOr<Option<i64>> {
+        let db_response = sqlx::query_scalar::<_, i64>(
+            r#"
+            SELECT user_id
+            FROM calendar_feeds
+            WHERE token_hash = ?
+            "#,
+        )
+        .bind(token_hash)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        db_response.into()
+    }
+}
@@ -0,0 +1,222 @@
+use chrono::Utc;
+
+use super::timestamp;
+use crate::{
+    repository::{
+        error::RepositoryError,
+        totp::{Totp, TotpRepository},
+    },
+    util::error_or::ErrorOr,
+};
+
+pub struct SqliteTotpRepository {
+    pool: sqlx::SqlitePool,
+}
+
+impl SqliteTotpRepository {
+    pub fn new(pool: sqlx::SqlitePool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl TotpRepository for SqliteTotpRepository {
+    async fn get_totp(&self, user_id: &i64) -> ErrorOr<Option<Totp>> {
+        let db_response = sqlx::query_as::<_, Totp>(
+            r#"
+            SELECT user_id, secret, confirmed_at, last_used_step
+            FROM user_totp
+            WHERE user_id = ?
+            "#,
+        )
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        db_response.into()
+    }
+
+    async fn set_pending_totp(
+        &self,
+        user_id: &i64,
+        secret: &str,
+    ) -> ErrorOr<()> {
+        sqlx::query(
+            r#"
+            INSERT
+            INTO user_totp (user_id, secret, created_at)
+            VALUES (?, ?, ?)
+            ON CONFLICT (user_id) DO UPDATE
+            SET
+                secret = excluded.secret,
+                confirmed_at = NULL,
+                last_used_step = NULL,
+                created_at = excluded.created_at
+            "#,
+        )
+        .bind(user_id)
+        .bind(secret)
+        .bind(timestamp(Utc::now()))
+        .execute(&self.pool)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        ().into()
+    }
+
+    async fn confirm_totp(
+        &self,
+        user_id: &i64,
+        step: i64,
+        recovery_code_hashes: &[String],
+    ) -> ErrorOr<()> {
+        let mut transaction = self
+            .pool
+            .begin()
+            .await
+            .map_err(Into::into)
+            .map_err(RepositoryError::Internal)?;
+
+        sqlx::query(
+            r#"
+            UPDATE user_totp
+            SET confirmed_at = ?, last_used_step = ?
+            WHERE user_id = ?
+            "#,
+        )
+        .bind(timestamp(Utc::now()))
+        .bind(step)
+        .bind(user_id)
+        .execute(&mut *transaction)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        sqlx::query(
+            r#"
+            DELETE
+            FROM recovery_codes
+            WHERE user_id = ?
+            "#,
+        )
+        .bind(user_id)
+        .execute(&mut *transaction)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        // SQLite has no arrays, a handful of codes is inserted one by one
+        for code_hash in recovery_code_hashes {
+            sqlx::query(
+                r#"
+                INSERT
+                INTO recovery_codes (user_id, code_hash)
+                VALUES (?, ?)
+                "#,
+            )
+            .bind(user_id)
+            .bind(code_hash)
+            .execute(&mut *transaction)
+            .await
+            .map_err(Into::into)
+            .map_err(RepositoryError::Internal)?;
+        }
+
+        transaction
+            .commit()
+            .await
+            .map_err(Into::into)
+            .map_err(RepositoryError::Internal)?;
+
+        ().into()
+    }
+
+    async fn use_step(&self, user_id: &i64, step: i64) -> ErrorOr<bool> {
+        let db_response = sqlx::query(
+            r#"
+            UPDATE user_totp
+            SET last_used_step = ?
+            WHERE
+                user_id = ?
+                AND (last_used_step IS NULL OR last_used_step < ?)
+            "#,
+        )
+        .bind(step)
+        .bind(user_id)
+        .bind(step)
+        .execute(&self.pool)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        (db_response.rows_affected() == 1).into()
+    }
+
+    async fn use_recovery_code(
+        &self,
+        user_id: &i64,
+        code_hash: &str,
+    ) -> ErrorOr<bool> {
+        let db_response = sqlx::query(
+            r#"
+            DELETE
+            FROM recovery_codes
+            WHERE user_id = ? AND code_hash = ?
+            "#,
+        )
+        .bind(user_id)
+        .bind(code_hash)
+        .execute(&self.pool)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        (db_response.rows_affected() == 1).into()
+    }
+
+    async fn delete_totp(&self, user_id: &i64) -> ErrorOr<()> {
+        let mut transaction = self
+            .pool
+            .begin()
+            .await
+            .map_err(Into::into)
+            .map_err(RepositoryError::Internal)?;
+
+        sqlx::query(
+            r#"
+            DELETE
+            FROM recovery_codes
+            WHERE user_id = ?
+            "#,
+        )
+        .bind(user_id)
+        .execute(&mut *transaction)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        sqlx::query(
+            r#"
+            DELETE
+            FROM user_totp
+            WHERE user_id = ?
+            "#,
+        )
+        .bind(user_id)
+        .execute(&mut *transaction)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        transaction
+            .commit()
+            .await
+            .map_err(Into::into)
+            .map_err(RepositoryError::Internal)?;
+
+        ().into()
+    }
+}
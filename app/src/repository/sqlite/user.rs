@@ -0,0 +1,475 @@
+use chrono::Utc;
+use shared::models::{
+    audit::{AuditAction, AuditEntity},
+    user::{CreateUser, Role, UpdateUser, User},
+};
+use sqlx::{Sqlite, SqliteConnection, Transaction};
+
+use super::{audit::SqliteAuditRepository, fetch_returning, timestamp};
+use crate::{
+    repository::{
+        audit::{self, AuditContext},
+        error::RepositoryError,
+        user::{UserRepository, UserStats},
+    },
+    util::error_or::ErrorOr,
+};
+
+const RELATION: &str = "User";
+
+pub struct SqliteUserRepository {
+    pool: sqlx::SqlitePool,
+}
+
+impl SqliteUserRepository {
+    pub fn new(pool: sqlx::SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    async fn begin(
+        &self,
+    ) -> Result<Transaction<'static, Sqlite>, RepositoryError> {
+        self.pool
+            .begin()
+            .await
+            .map_err(Into::into)
+            .map_err(RepositoryError::Internal)
+    }
+
+    async fn find_user(
+        transaction: &mut SqliteConnection,
+        user_id: &i64,
+    ) -> Result<Option<User>, RepositoryError> {
+        sqlx::query_as::<_, User>(
+            r#"
+            SELECT *
+            FROM users
+            WHERE id = ?
+            "#,
+        )
+        .bind(user_id)
+        .fetch_optional(&mut *transaction)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)
+    }
+
+    /// Records the change of a user and commits it.
+    async fn commit_change(
+        mut transaction: Transaction<'_, Sqlite>,
+        audit: &AuditContext,
+        action: AuditAction,
+        before: Option<&User>,
+        after: Option<&User>,
+    ) -> Result<(), RepositoryError> {
+        let Some(user_id) = after.or(before).map(|user| user.id) else {
+            return Ok(());
+        };
+        SqliteAuditRepository::record(
+            &mut transaction,
+            audit,
+            action,
+            AuditEntity::User,
+            user_id,
+            audit::changes(before, after),
+        )
+        .await?;
+
+        transaction
+            .commit()
+            .await
+            .map_err(Into::into)
+            .map_err(RepositoryError::Internal)
+    }
+}
+
+#[async_trait::async_trait]
+impl UserRepository for SqliteUserRepository {
+    async fn get_user_by_email(&self, email: &str) -> ErrorOr<User> {
+        let db_response = sqlx::query_as::<_, User>(
+            r#"
+            SELECT *
+            FROM users
+            WHERE email = ?
+            "#,
+        )
+        .bind(email)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::RowNotFound => RepositoryError::NotFound {
+                relation_name: RELATION.to_string(),
+            },
+            _ => RepositoryError::Internal(e.into()),
+        })?;
+
+        db_response.into()
+    }
+
+    async fn get_session_user(&self, session_user_id: &i64) -> ErrorOr<User> {
+        let db_response = sqlx::query_as::<_, User>(
+            r#"
+            SELECT *
+            FROM users
+            WHERE id = ?
+            "#,
+        )
+        .bind(session_user_id)
+        .fetch_one(&self.pool)
+        .await?;
+
+        db_response.into()
+    }
+
+    async fn create_user(
+        &self,
+        create_user: &CreateUser,
+        audit: &AuditContext,
+    ) -> ErrorOr<()> {
+        let now = Utc::now();
+        let mut transaction = self.begin().await?;
+
+        let query = sqlx::query_as::<_, User>(
+            r#"
+            INSERT
+            INTO users (name, email, password, created_at, updated_at)
+            VALUES (?, ?, ?, ?, ?)
+            RETURNING *
+            "#,
+        )
+        .bind(&create_user.name)
+        .bind(&create_user.email)
+        .bind(&create_user.password)
+        .bind(timestamp(now))
+        .bind(timestamp(now));
+        let user = fetch_returning(query, &mut *transaction)
+            .await
+            .map_err(|e| RepositoryError::unique(e, RELATION))?;
+
+        Self::commit_change(
+            transaction,
+            audit,
+            AuditAction::Created,
+            None,
+            Some(&user),
+        )
+        .await?;
+
+        ().into()
+    }
+
+    async fn update_user(
+        &self,
+        update_user: &UpdateUser,
+        session_user_id: &i64,
+        audit: &AuditContext,
+    ) -> ErrorOr<()> {
+        let mut transaction = self.begin().await?;
+        let Some(before) =
+            Self::find_user(&mut transaction, session_user_id).await?
+        else {
+            return ().into();
+        };
+
+        let query = sqlx::query_as::<_, User>(
+            r#"
+            UPDATE users
+            SET
+                name = COALESCE(?, name),
+                email = COALESCE(?, email),
+                email_verified_at = CASE
+                    WHEN ? IS NULL OR ? = email THEN email_verified_at
+                END,
+                updated_at = ?
+            WHERE id = ?
+            RETURNING *
+            "#,
+        )
+        .bind(&update_user.name)
+        .bind(&update_user.email)
+        .bind(&update_user.email)
+        .bind(&update_user.email)
+        .bind(timestamp(Utc::now()))
+        .bind(session_user_id);
+        let user = fetch_returning(query, &mut *transaction)
+            .await
+            .map_err(|e| RepositoryError::unique(e, RELATION))?;
+
+        Self::commit_change(
+            transaction,
+            audit,
+            AuditAction::Updated,
+            Some(&before),
+            Some(&user),
+        )
+        .await?;
+
+        ().into()
+    }
+
+    async fn delete_user(
+        &self,
+        session_user_id: &i64,
+        audit: &AuditContext,
+    ) -> ErrorOr<()> {
+        let mut transaction = self.begin().await?;
+        let Some(before) =
+            Self::find_user(&mut transaction, session_user_id).await?
+        else {
+            return ().into();
+        };
+
+        sqlx::query(
+            r#"
+            DELETE
+            FROM users
+            WHERE id = ?
+            "#,
+        )
+        .bind(session_user_id)
+        .execute(&mut *transaction)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        Self::commit_change(
+            transaction,
+            audit,
+            AuditAction::Deleted,
+            Some(&before),
+            None,
+        )
+        .await?;
+
+        ().into()
+    }
+
+    async fn set_email_verified(
+        &self,
+        user_id: &i64,
+        audit: &AuditContext,
+    ) -> ErrorOr<()> {
+        let mut transaction = self.begin().await?;
+        let Some(before) = Self::find_user(&mut transaction, user_id).await?
+        else {
+            return ().into();
+        };
+
+        let query = sqlx::query_as::<_, User>(
+            r#"
+            UPDATE users
+            SET email_verified_at = ?
+            WHERE id = ?
+            RETURNING *
+            "#,
+        )
+        .bind(timestamp(Utc::now()))
+        .bind(user_id);
+        let user = fetch_returning(query, &mut *transaction)
+            .await
+            .map_err(Into::into)
+            .map_err(RepositoryError::Internal)?;
+
+        Self::commit_change(
+            transaction,
+            audit,
+            AuditAction::EmailVerified,
+            Some(&before),
+            Some(&user),
+        )
+        .await?;
+
+        ().into()
+    }
+
+    async fn set_password(
+        &self,
+        user_id: &i64,
+        password_hash: &str,
+        audit: &AuditContext,
+    ) -> ErrorOr<()> {
+        let mut transaction = self.begin().await?;
+        let Some(before) = Self::find_user(&mut transaction, user_id).await?
+        else {
+            return ().into();
+        };
+
+        let query = sqlx::query_as::<_, User>(
+            r#"
+            UPDATE users
+            SET password = ?, updated_at = ?
+            WHERE id = ?
+            RETURNING *
+            "#,
+        )
+        .bind(password_hash)
+        .bind(timestamp(Utc::now()))
+        .bind(user_id);
+        let user = fetch_returning(query, &mut *transaction)
+            .await
+            .map_err(Into::into)
+            .map_err(RepositoryError::Internal)?;
+
+        Self::commit_change(
+            transaction,
+            audit,
+            AuditAction::PasswordChanged,
+            Some(&before),
+            Some(&user),
+        )
+        .await?;
+
+        ().into()
+    }
+
+    async fn count_users(&self) -> ErrorOr<i64> {
+        let db_response = sqlx::query_scalar::<_, i64>(
+            r#"
+            SELECT count(*)
+            FROM users
+            "#,
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        db_response.into()
+    }
+
+    async fn search_users(
+        &self,
+        search: Option<&str>,
+        offset: i64,
+        limit: i64,
+    ) -> ErrorOr<Vec<User>> {
+        // LIKE ignores the case of ascii letters
+        let db_response = sqlx::query_as::<_, User>(
+            r#"
+            SELECT *
+            FROM users
+            WHERE ?1 IS NULL
+                OR name LIKE '%' || ?1 || '%'
+                OR email LIKE '%' || ?1 || '%'
+            ORDER BY id
+            LIMIT ?3
+            OFFSET ?2
+            "#,
+        )
+        .bind(search)
+        .bind(offset)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        db_response.into()
+    }
+
+    async fn set_role(
+        &self,
+        user_id: &i64,
+        role: Role,
+        audit: &AuditContext,
+    ) -> ErrorOr<()> {
+        let mut transaction = self.begin().await?;
+        let Some(before) = Self::find_user(&mut transaction, user_id).await?
+        else {
+            return ().into();
+        };
+
+        let query = sqlx::query_as::<_, User>(
+            r#"
+            UPDATE users
+            SET role = ?, updated_at = ?
+            WHERE id = ?
+            RETURNING *
+            "#,
+        )
+        .bind(role.as_str())
+        .bind(timestamp(Utc::now()))
+        .bind(user_id);
+        let user = fetch_returning(query, &mut *transaction)
+            .await
+            .map_err(Into::into)
+            .map_err(RepositoryError::Internal)?;
+
+        Self::commit_change(
+            transaction,
+            audit,
+            AuditAction::RoleChanged,
+            Some(&before),
+            Some(&user),
+        )
+        .await?;
+
+        ().into()
+    }
+
+    async fn set_disabled(
+        &self,
+        user_id: &i64,
+        disabled: bool,
+        audit: &AuditContext,
+    ) -> ErrorOr<()> {
+        let now = Utc::now();
+        let mut transaction = self.begin().await?;
+        let Some(before) = Self::find_user(&mut transaction, user_id).await?
+        else {
+            return ().into();
+        };
+
+        let query = sqlx::query_as::<_, User>(
+            r#"
+            UPDATE users
+            SET
+                disabled_at = CASE WHEN ? THEN COALESCE(disabled_at, ?) END,
+                updated_at = ?
+            WHERE id = ?
+            RETURNING *
+            "#,
+        )
+        .bind(disabled)
+        .bind(timestamp(now))
+        .bind(timestamp(now))
+        .bind(user_id);
+        let user = fetch_returning(query, &mut *transaction)
+            .await
+            .map_err(Into::into)
+            .map_err(RepositoryError::Internal)?;
+
+        let action = match disabled {
+            true => AuditAction::Disabled,
+            false => AuditAction::Enabled,
+        };
+        Self::commit_change(
+            transaction,
+            audit,
+            action,
+            Some(&before),
+            Some(&user),
+        )
+        .await?;
+
+        ().into()
+    }
+
+    async fn user_stats(&self) -> ErrorOr<UserStats> {
+        let db_response = sqlx::query_as::<_, UserStats>(
+            r#"
+            SELECT
+                count(*) AS total,
+                count(*) FILTER (WHERE role = 'admin') AS admins,
+                count(disabled_at) AS disabled
+            FROM users
+            "#,
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        db_response.into()
+    }
+}
@@ -0,0 +1,104 @@
+use chrono::{DateTime, Utc};
+
+use super::{fetch_returning, timestamp};
+use crate::{
+    repository::{
+        error::RepositoryError,
+        user_token::{self, TokenPurpose, UserToken, UserTokenRepository},
+    },
+    util::error_or::ErrorOr,
+};
+
+pub struct SqliteUserTokenRepository {
+    pool: sqlx::SqlitePool,
+}
+
+impl SqliteUserTokenRepository {
+    pub fn new(pool: sqlx::SqlitePool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl UserTokenRepository for SqliteUserTokenRepository {
+    async fn create_token(
+        &self,
+        token_hash: &str,
+        purpose: TokenPurpose,
+        user_id: &i64,
+        email: &str,
+        expires_at: DateTime<Utc>,
+    ) -> ErrorOr<()> {
+        let mut transaction = self
+            .pool
+            .begin()
+            .await
+            .map_err(Into::into)
+            .map_err(RepositoryError::Internal)?;
+
+        sqlx::query(
+            r#"
+            DELETE
+            FROM user_tokens
+            WHERE user_id = ? AND purpose = ?
+            "#,
+        )
+        .bind(user_id)
+        .bind(purpose.as_str())
+        .execute(&mut *transaction)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        sqlx::query(
+            r#"
+            INSERT
+            INTO user_tokens
+                (token_hash, user_id, purpose, email, expires_at, created_at)
+            VALUES (?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(token_hash)
+        .bind(user_id)
+        .bind(purpose.as_str())
+        .bind(email)
+        .bind(timestamp(expires_at))
+        .bind(timestamp(Utc::now()))
+        .execute(&mut *transaction)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        transaction
+            .commit()
+            .await
+            .map_err(Into::into)
+            .map_err(RepositoryError::Internal)?;
+
+        ().into()
+    }
+
+    async fn consume_token(
+        &self,
+        token_hash: &str,
+        purpose: TokenPurpose,
+    ) -> ErrorOr<UserToken> {
+        let query = sqlx::query_as::<_, UserToken>(
+            r#"
+            DELETE
+            FROM user_tokens
+            WHERE token_hash = ? AND purpose = ?
+            RETURNING user_id, email, expires_at
+            "#,
+        )
+        .bind(token_hash)
+        .bind(purpose.as_str());
+        let db_response = match fetch_returning(query, &self.pool).await {
+            Ok(token) => Some(token),
+            Err(sqlx::Error::RowNotFound) => None,
+            Err(e) => Err(RepositoryError::Internal(e.into()))?,
+        };
+
+        user_token::unexpired(db_response)
+    }
+}
@@ -1,12 +1,66 @@
-use shared::models::todo::{CreateTodo, Todo, UpdateTodo};
+use chrono::{DateTime, Utc};
+use shared::models::{
+    audit::{AuditAction, AuditEntity},
+    todo::{CreateTodo, Todo, TodoAction, TodoActivity, UpdateTodo},
+};
 
-use super::error::{Operation, RepositoryError};
+use super::{
+    audit::{self, AuditContext, PostgresAuditRepository},
+    error::{Operation, RepositoryError},
+};
 use crate::util::error_or::ErrorOr;
 
 const RELATION: &str = "Todo";
 
+/// Number of todos across all users.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, sqlx::FromRow)]
+pub struct TodoStats {
+    pub total: i64,
+    pub done: i64,
+}
+
+/// A todo a user imported, under the id it has in the imported file.
+#[derive(Debug, Clone, PartialEq, Eq, Default, sqlx::FromRow)]
+pub struct ExternalId {
+    pub external_id: String,
+    pub todo_id: i64,
+}
+
+/// The audited state of the calendar feed of a user. The token is secret, so
+/// only when it was created is recorded.
+pub(crate) fn calendar_feed(
+    created_at: Option<DateTime<Utc>>,
+) -> serde_json::Value {
+    serde_json::json!({ "calendar_feed_created_at": created_at })
+}
+
+/// The audited change of a todo that is found under `external_id` on the
+/// next import.
+pub(crate) fn external_id_changes(external_id: &str) -> serde_json::Value {
+    audit::changes(
+        None,
+        Some(&serde_json::json!({ "external_id": external_id })),
+    )
+}
+
+/// A todo to create in the state it has in an imported file or calendar.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct CreateImportedTodo {
+    pub create_todo: CreateTodo,
+    pub is_done: bool,
+    /// The id in the imported file, the todo is found under it on the next
+    /// import.
+    pub external_id: Option<String>,
+}
+
+/// Access to the todos follows their list: personal todos are only visible to
+/// their owner, the todos of a shared list to its members, and only editors
+/// and admins of the list may change them.
+///
+/// Every change is recorded as an audit event in the same transaction.
 #[async_trait::async_trait]
 pub trait TodoRepository: Send + Sync + 'static {
+    /// Returns the personal todos of the user and the todos of their lists.
     async fn get_todos(&self, session_user_id: &i64) -> ErrorOr<Vec<Todo>>;
 
     async fn get_todo(
@@ -15,20 +69,87 @@ pub trait TodoRepository: Send + Sync + 'static {
         session_user_id: &i64,
     ) -> ErrorOr<Todo>;
 
+    /// Fails with `Forbidden` if the todo is created in a list the user may
+    /// not edit.
     async fn create_todo(
         &self,
         create_todo: &CreateTodo,
         session_user_id: &i64,
-    ) -> ErrorOr<()>;
+        audit: &AuditContext,
+    ) -> ErrorOr<Todo>;
 
     async fn update_todo(
         &self,
         update_todo: &UpdateTodo,
         session_user_id: &i64,
+        audit: &AuditContext,
+    ) -> ErrorOr<Todo>;
+
+    /// Moves the creation and last change of a todo into the past, a done
+    /// todo counts as completed at `updated_at`. Used to seed demo data, so
+    /// it does not check who may edit the todo.
+    async fn backdate_todo(
+        &self,
+        todo_id: &i64,
+        created_at: DateTime<Utc>,
+        updated_at: DateTime<Utc>,
+        audit: &AuditContext,
+    ) -> ErrorOr<Todo>;
+
+    async fn delete_todo(
+        &self,
+        id: &i64,
+        session_user_id: &i64,
+        audit: &AuditContext,
+    ) -> ErrorOr<()>;
+
+    /// Assigns the todo and records a reassignment in its activity. Fails
+    /// with `Forbidden` unless the user may edit the todo and with `Conflict`
+    /// unless the assignee owns the personal todo or is a member of its list.
+    async fn assign_todo(
+        &self,
+        todo_id: &i64,
+        assignee_id: Option<i64>,
+        session_user_id: &i64,
+        audit: &AuditContext,
     ) -> ErrorOr<Todo>;
 
-    async fn delete_todo(&self, id: &i64, session_user_id: &i64)
-    -> ErrorOr<()>;
+    /// Returns the activity of a todo, oldest first.
+    async fn get_activities(&self, todo_id: &i64)
+        -> ErrorOr<Vec<TodoActivity>>;
+
+    async fn todo_stats(&self) -> ErrorOr<TodoStats>;
+
+    /// Returns the external ids the user imported todos under. The user may
+    /// have lost access to some of the todos since.
+    async fn get_external_ids(
+        &self,
+        session_user_id: &i64,
+    ) -> ErrorOr<Vec<ExternalId>>;
+
+    /// Creates and updates the todos of an import in one transaction, so a
+    /// failing row leaves nothing behind. Fails with `Forbidden` like
+    /// `create_todo` and `update_todo`. The external ids of the created todos
+    /// replace the todos they referred to before. Returns the created todos.
+    async fn import_todos(
+        &self,
+        create: &[CreateImportedTodo],
+        update: &[UpdateTodo],
+        session_user_id: &i64,
+        audit: &AuditContext,
+    ) -> ErrorOr<Vec<Todo>>;
+
+    /// Replaces the secret token of the calendar feed of the user, `None`
+    /// turns the feed off. Audited as a change of the user.
+    async fn set_feed_token(
+        &self,
+        user_id: &i64,
+        token_hash: Option<&str>,
+        audit: &AuditContext,
+    ) -> ErrorOr<()>;
+
+    /// Returns the user whose calendar feed has the token.
+    async fn get_feed_user(&self, token_hash: &str) -> ErrorOr<Option<i64>>;
 }
 
 pub struct PostgresTodoRepository {
@@ -39,6 +160,227 @@ impl PostgresTodoRepository {
     pub fn new(pool: sqlx::PgPool) -> Self {
         Self { pool }
     }
+
+    /// Returns the todo if the user may edit it and locks it for the rest of
+    /// the transaction.
+    async fn editable_todo(
+        transaction: &mut sqlx::PgConnection,
+        todo_id: &i64,
+        session_user_id: &i64,
+    ) -> Result<Option<Todo>, RepositoryError> {
+        sqlx::query_as!(
+            Todo,
+            r#"
+            SELECT *
+            FROM todos
+            WHERE id = $1
+                AND (
+                    (owner = $2 AND list_id IS NULL)
+                    OR list_id IN (
+                        SELECT list_id
+                        FROM list_members
+                        WHERE user_id = $2
+                            AND permission IN ('editor', 'admin')
+                    )
+                )
+            FOR UPDATE
+            "#,
+            todo_id,
+            session_user_id
+        )
+        .fetch_optional(&mut *transaction)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)
+    }
+
+    /// Whether the user sees the todo, as the owner of a personal todo or as
+    /// a member of its list. The membership is locked until the transaction
+    /// ends, so it cannot be removed before a write that relies on it.
+    async fn is_reader(
+        transaction: &mut sqlx::PgConnection,
+        todo: &Todo,
+        user_id: &i64,
+    ) -> Result<bool, RepositoryError> {
+        let Some(list_id) = todo.list_id else {
+            return Ok(todo.owner == *user_id);
+        };
+
+        sqlx::query_scalar!(
+            r#"
+            SELECT user_id
+            FROM list_members
+            WHERE list_id = $1 AND user_id = $2
+            FOR SHARE
+            "#,
+            list_id,
+            user_id
+        )
+        .fetch_optional(&mut *transaction)
+        .await
+        .map(|member| member.is_some())
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)
+    }
+
+    /// Creates a todo in the transaction, see `create_todo`.
+    async fn insert_todo(
+        transaction: &mut sqlx::PgConnection,
+        create_todo: &CreateTodo,
+        is_done: bool,
+        session_user_id: &i64,
+        audit: &AuditContext,
+    ) -> Result<Todo, RepositoryError> {
+        let todo = sqlx::query_as!(
+            Todo,
+            r#"
+            INSERT
+            INTO todos
+                (title, description, owner, list_id, due_at, is_done,
+                completed_at)
+            SELECT
+                $1::text,
+                $2::text,
+                $3::bigint,
+                $4::bigint,
+                $5::timestamptz,
+                $6::boolean,
+                CASE WHEN $6 THEN now() END
+            WHERE $4::bigint IS NULL
+                OR EXISTS (
+                    SELECT 1
+                    FROM list_members
+                    WHERE list_id = $4
+                        AND user_id = $3
+                        AND permission IN ('editor', 'admin')
+                )
+            RETURNING *
+            "#,
+            &create_todo.title,
+            &create_todo.description,
+            session_user_id,
+            create_todo.list_id,
+            create_todo.due_at,
+            is_done
+        )
+        .fetch_one(&mut *transaction)
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::RowNotFound => RepositoryError::Forbidden {
+                operation: Operation::Post,
+                relation_name: RELATION.to_string(),
+            },
+            e => RepositoryError::Internal(e.into()),
+        })?;
+
+        PostgresAuditRepository::record(
+            &mut *transaction,
+            audit,
+            AuditAction::Created,
+            AuditEntity::Todo,
+            todo.id,
+            audit::changes(None, Some(&todo)),
+        )
+        .await?;
+
+        Ok(todo)
+    }
+
+    /// Maps the external id to the created todo in the transaction, see
+    /// `import_todos`.
+    async fn insert_external_id(
+        transaction: &mut sqlx::PgConnection,
+        external_id: &str,
+        todo_id: &i64,
+        session_user_id: &i64,
+        audit: &AuditContext,
+    ) -> Result<(), RepositoryError> {
+        sqlx::query!(
+            r#"
+            INSERT
+            INTO todo_external_ids (user_id, external_id, todo_id)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (user_id, external_id)
+            DO UPDATE SET todo_id = excluded.todo_id
+            "#,
+            session_user_id,
+            external_id,
+            todo_id
+        )
+        .execute(&mut *transaction)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        PostgresAuditRepository::record(
+            &mut *transaction,
+            audit,
+            AuditAction::Updated,
+            AuditEntity::Todo,
+            *todo_id,
+            external_id_changes(external_id),
+        )
+        .await
+    }
+
+    /// Updates a todo in the transaction, see `update_todo`.
+    async fn change_todo(
+        transaction: &mut sqlx::PgConnection,
+        update_todo: &UpdateTodo,
+        session_user_id: &i64,
+        audit: &AuditContext,
+    ) -> Result<Todo, RepositoryError> {
+        let before = Self::editable_todo(
+            &mut *transaction,
+            &update_todo.id,
+            session_user_id,
+        )
+        .await?
+        .ok_or_else(|| RepositoryError::Forbidden {
+            operation: Operation::Update,
+            relation_name: RELATION.to_string(),
+        })?;
+
+        let todo = sqlx::query_as::<_, Todo>(
+            r#"
+            UPDATE todos
+            SET
+                title = COALESCE($1, title),
+                description = COALESCE($2, description),
+                is_done = COALESCE($3, is_done),
+                completed_at = CASE
+                    WHEN NOT COALESCE($3, is_done) THEN NULL
+                    ELSE COALESCE(completed_at, NOW())
+                END,
+                due_at = CASE WHEN $5 THEN $6 ELSE due_at END,
+                updated_at = NOW()
+            WHERE id = $4
+            RETURNING *
+            "#,
+        )
+        .bind::<&Option<String>>(&update_todo.title)
+        .bind::<&Option<String>>(&update_todo.description)
+        .bind::<&Option<bool>>(&update_todo.is_done)
+        .bind::<&i64>(&update_todo.id)
+        .bind::<bool>(update_todo.due_at.is_some())
+        .bind::<Option<DateTime<Utc>>>(update_todo.due_at.flatten())
+        .fetch_one(&mut *transaction)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        PostgresAuditRepository::record(
+            &mut *transaction,
+            audit,
+            AuditAction::Updated,
+            AuditEntity::Todo,
+            todo.id,
+            audit::changes(Some(&before), Some(&todo)),
+        )
+        .await?;
+
+        Ok(todo)
+    }
 }
 
 #[async_trait::async_trait]
@@ -49,7 +391,12 @@ impl TodoRepository for PostgresTodoRepository {
             r#"
             SELECT *
             FROM todos
-            WHERE owner = $1
+            WHERE (owner = $1 AND list_id IS NULL)
+                OR list_id IN (
+                    SELECT list_id
+                    FROM list_members
+                    WHERE user_id = $1
+                )
             ORDER BY id"#,
             session_user_id
         )
@@ -88,9 +435,26 @@ impl TodoRepository for PostgresTodoRepository {
             e => RepositoryError::Internal(e.into()),
         })?;
 
-        // check if session user that made the request
-        // is the actual owner of the todo that was requested
-        let todo = if todo.owner == *session_user_id {
+        // check if session user that made the request is the owner of the
+        // personal todo or a member of its list
+        let visible = match todo.list_id {
+            Some(list_id) => sqlx::query_scalar!(
+                r#"
+                SELECT permission
+                FROM list_members
+                WHERE list_id = $1 AND user_id = $2
+                "#,
+                list_id,
+                session_user_id
+            )
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(Into::into)
+            .map_err(RepositoryError::Internal)?
+            .is_some(),
+            None => todo.owner == *session_user_id,
+        };
+        let todo = if visible {
             Ok(todo)
         } else {
             Err(RepositoryError::Forbidden {
@@ -106,85 +470,488 @@ impl TodoRepository for PostgresTodoRepository {
         &self,
         create_todo: &CreateTodo,
         session_user_id: &i64,
-    ) -> ErrorOr<()> {
-        let db_response = sqlx::query!(
-            r#"
-            INSERT
-            INTO todos (title, description, owner)
-            VALUES ($1, $2, $3)
-            "#,
-            &create_todo.title,
-            &create_todo.description,
-            session_user_id
+        audit: &AuditContext,
+    ) -> ErrorOr<Todo> {
+        let mut transaction = self
+            .pool
+            .begin()
+            .await
+            .map_err(Into::into)
+            .map_err(RepositoryError::Internal)?;
+
+        let todo = Self::insert_todo(
+            &mut transaction,
+            create_todo,
+            false,
+            session_user_id,
+            audit,
         )
-        .execute(&self.pool)
-        .await
-        .map(|_| ())
-        .map_err(Into::into)
-        .map_err(RepositoryError::Internal)?;
+        .await?;
 
-        db_response.into()
+        transaction
+            .commit()
+            .await
+            .map_err(Into::into)
+            .map_err(RepositoryError::Internal)?;
+
+        todo.into()
     }
 
     async fn update_todo(
         &self,
         update_todo: &UpdateTodo,
         session_user_id: &i64,
+        audit: &AuditContext,
     ) -> ErrorOr<Todo> {
-        let db_response = sqlx::query_as::<_, Todo>(
+        let mut transaction = self
+            .pool
+            .begin()
+            .await
+            .map_err(Into::into)
+            .map_err(RepositoryError::Internal)?;
+
+        let todo = Self::change_todo(
+            &mut transaction,
+            update_todo,
+            session_user_id,
+            audit,
+        )
+        .await?;
+
+        transaction
+            .commit()
+            .await
+            .map_err(Into::into)
+            .map_err(RepositoryError::Internal)?;
+
+        todo.into()
+    }
+
+    async fn backdate_todo(
+        &self,
+        todo_id: &i64,
+        created_at: DateTime<Utc>,
+        updated_at: DateTime<Utc>,
+        audit: &AuditContext,
+    ) -> ErrorOr<Todo> {
+        let mut transaction = self
+            .pool
+            .begin()
+            .await
+            .map_err(Into::into)
+            .map_err(RepositoryError::Internal)?;
+
+        let before = sqlx::query_as!(
+            Todo,
+            "SELECT * FROM todos WHERE id = $1 FOR UPDATE",
+            todo_id
+        )
+        .fetch_one(&mut *transaction)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        let todo = sqlx::query_as!(
+            Todo,
             r#"
             UPDATE todos
             SET
-                title = COALESCE($1, title),
-                description = COALESCE($2, description),
-                is_done = COALESCE($3, is_done),
-                updated_at = NOW()
-            WHERE id = $4 and owner = $5
+                created_at = $2,
+                updated_at = $3,
+                completed_at = CASE WHEN is_done THEN $3::timestamptz END
+            WHERE id = $1
             RETURNING *
             "#,
+            todo_id,
+            created_at,
+            updated_at
         )
-        .bind::<&Option<String>>(&update_todo.title)
-        .bind::<&Option<String>>(&update_todo.description)
-        .bind::<&Option<bool>>(&update_todo.is_done)
-        .bind::<&i64>(&update_todo.id)
-        .bind::<&i64>(session_user_id)
-        .fetch_one(&self.pool)
+        .fetch_one(&mut *transaction)
         .await
-        .map_err(|e| match e {
-            sqlx::Error::RowNotFound => RepositoryError::Forbidden {
-                operation: Operation::Update,
-                relation_name: RELATION.to_string(),
-            },
-            e => RepositoryError::Internal(e.into()),
-        })?;
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
 
-        db_response.into()
+        PostgresAuditRepository::record(
+            &mut transaction,
+            audit,
+            AuditAction::Updated,
+            AuditEntity::Todo,
+            todo.id,
+            audit::changes(Some(&before), Some(&todo)),
+        )
+        .await?;
+
+        transaction
+            .commit()
+            .await
+            .map_err(Into::into)
+            .map_err(RepositoryError::Internal)?;
+
+        todo.into()
     }
 
     async fn delete_todo(
         &self,
         todo_id: &i64,
         session_user_id: &i64,
+        audit: &AuditContext,
     ) -> ErrorOr<()> {
-        let db_response = sqlx::query!(
+        let mut transaction = self
+            .pool
+            .begin()
+            .await
+            .map_err(Into::into)
+            .map_err(RepositoryError::Internal)?;
+
+        // like a `DELETE` of no rows this is not an error when the user may
+        // not edit the todo
+        let Some(before) =
+            Self::editable_todo(&mut transaction, todo_id, session_user_id)
+                .await?
+        else {
+            return ().into();
+        };
+
+        sqlx::query!(
             r#"
             DELETE
             FROM todos
-            WHERE id = $1 and owner = $2
+            WHERE id = $1
+            "#,
+            todo_id
+        )
+        .execute(&mut *transaction)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        PostgresAuditRepository::record(
+            &mut transaction,
+            audit,
+            AuditAction::Deleted,
+            AuditEntity::Todo,
+            before.id,
+            audit::changes(Some(&before), None),
+        )
+        .await?;
+
+        transaction
+            .commit()
+            .await
+            .map_err(Into::into)
+            .map_err(RepositoryError::Internal)?;
+
+        ().into()
+    }
+
+    async fn assign_todo(
+        &self,
+        todo_id: &i64,
+        assignee_id: Option<i64>,
+        session_user_id: &i64,
+        audit: &AuditContext,
+    ) -> ErrorOr<Todo> {
+        let mut transaction = self
+            .pool
+            .begin()
+            .await
+            .map_err(Into::into)
+            .map_err(RepositoryError::Internal)?;
+
+        let before =
+            Self::editable_todo(&mut transaction, todo_id, session_user_id)
+                .await?
+                .ok_or_else(|| RepositoryError::Forbidden {
+                    operation: Operation::Update,
+                    relation_name: RELATION.to_string(),
+                })?;
+        if let Some(assignee_id) = &assignee_id {
+            if !Self::is_reader(&mut transaction, &before, assignee_id).await? {
+                Err(RepositoryError::Conflict {
+                    relation_name: RELATION.to_string(),
+                })?;
+            }
+        }
+
+        let todo = sqlx::query_as!(
+            Todo,
+            r#"
+            UPDATE todos
+            SET assignee_id = $2, updated_at = now()
+            WHERE id = $1
+            RETURNING *
             "#,
             todo_id,
+            assignee_id
+        )
+        .fetch_one(&mut *transaction)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        if before.assignee_id != assignee_id {
+            sqlx::query!(
+                r#"
+                INSERT
+                INTO todo_activities (
+                    todo_id,
+                    actor_id,
+                    action,
+                    previous_assignee_id,
+                    assignee_id
+                )
+                VALUES ($1, $2, $3, $4, $5)
+                "#,
+                todo_id,
+                session_user_id,
+                TodoAction::Assigned.as_str(),
+                before.assignee_id,
+                assignee_id
+            )
+            .execute(&mut *transaction)
+            .await
+            .map_err(Into::into)
+            .map_err(RepositoryError::Internal)?;
+        }
+
+        PostgresAuditRepository::record(
+            &mut transaction,
+            audit,
+            AuditAction::Assigned,
+            AuditEntity::Todo,
+            todo.id,
+            audit::changes(Some(&before), Some(&todo)),
+        )
+        .await?;
+
+        transaction
+            .commit()
+            .await
+            .map_err(Into::into)
+            .map_err(RepositoryError::Internal)?;
+
+        todo.into()
+    }
+
+    async fn get_activities(
+        &self,
+        todo_id: &i64,
+    ) -> ErrorOr<Vec<TodoActivity>> {
+        let db_response = sqlx::query_as!(
+            TodoActivity,
+            r#"
+            SELECT
+                id,
+                todo_id,
+                actor_id,
+                action,
+                previous_assignee_id,
+                assignee_id,
+                created_at
+            FROM todo_activities
+            WHERE todo_id = $1
+            ORDER BY id
+            "#,
+            todo_id
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        db_response.into()
+    }
+
+    async fn todo_stats(&self) -> ErrorOr<TodoStats> {
+        let db_response = sqlx::query_as!(
+            TodoStats,
+            r#"
+            SELECT
+                count(*) as "total!",
+                count(*) FILTER (WHERE is_done) as "done!"
+            FROM todos
+            "#
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        db_response.into()
+    }
+
+    async fn get_external_ids(
+        &self,
+        session_user_id: &i64,
+    ) -> ErrorOr<Vec<ExternalId>> {
+        let db_response = sqlx::query_as!(
+            ExternalId,
+            r#"
+            SELECT external_id, todo_id
+            FROM todo_external_ids
+            WHERE user_id = $1
+            "#,
             session_user_id
         )
-        .execute(&self.pool)
+        .fetch_all(&self.pool)
         .await
-        .map(|_| ())
-        .map_err(|e| match e {
-            sqlx::Error::RowNotFound => RepositoryError::Forbidden {
-                operation: Operation::Delete,
-                relation_name: RELATION.to_string(),
-            },
-            _ => RepositoryError::Internal(e.into()),
-        })?;
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        db_response.into()
+    }
+
+    async fn import_todos(
+        &self,
+        create: &[CreateImportedTodo],
+        update: &[UpdateTodo],
+        session_user_id: &i64,
+        audit: &AuditContext,
+    ) -> ErrorOr<Vec<Todo>> {
+        let mut transaction = self
+            .pool
+            .begin()
+            .await
+            .map_err(Into::into)
+            .map_err(RepositoryError::Internal)?;
+
+        let mut created = Vec::with_capacity(create.len());
+        for imported in create {
+            let todo = Self::insert_todo(
+                &mut transaction,
+                &imported.create_todo,
+                imported.is_done,
+                session_user_id,
+                audit,
+            )
+            .await?;
+            if let Some(external_id) = &imported.external_id {
+                Self::insert_external_id(
+                    &mut transaction,
+                    external_id,
+                    &todo.id,
+                    session_user_id,
+                    audit,
+                )
+                .await?;
+            }
+            created.push(todo);
+        }
+        for update_todo in update {
+            Self::change_todo(
+                &mut transaction,
+                update_todo,
+                session_user_id,
+                audit,
+            )
+            .await?;
+        }
+
+        transaction
+            .commit()
+            .await
+            .map_err(Into::into)
+            .map_err(RepositoryError::Internal)?;
+
+        created.into()
+    }
+
+    async fn set_feed_token(
+        &self,
+        user_id: &i64,
+        token_hash: Option<&str>,
+        audit: &AuditContext,
+    ) -> ErrorOr<()> {
+        let mut transaction = self
+            .pool
+            .begin()
+            .await
+            .map_err(Into::into)
+            .map_err(RepositoryError::Internal)?;
+
+        let before = sqlx::query_scalar!(
+            r#"
+            SELECT created_at
+            FROM calendar_feeds
+            WHERE user_id = $1
+            FOR UPDATE
+            "#,
+            user_id
+        )
+        .fetch_optional(&mut *transaction)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        let after = match token_hash {
+            Some(token_hash) => {
+                sqlx::query_scalar!(
+                    r#"
+                INSERT
+                INTO calendar_feeds (user_id, token_hash)
+                VALUES ($1, $2)
+                ON CONFLICT (user_id)
+                DO UPDATE SET
+                    token_hash = excluded.token_hash,
+                    created_at = now()
+                RETURNING created_at
+                "#,
+                    user_id,
+                    token_hash
+                )
+                .fetch_optional(&mut *transaction)
+                .await
+            }
+            None => sqlx::query!(
+                r#"
+                DELETE
+                FROM calendar_feeds
+                WHERE user_id = $1
+                "#,
+                user_id
+            )
+            .execute(&mut *transaction)
+            .await
+            .map(|_| None),
+        }
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        PostgresAuditRepository::record(
+            &mut transaction,
+            audit,
+            AuditAction::Updated,
+            AuditEntity::User,
+            *user_id,
+            audit::changes(
+                Some(&calendar_feed(before)),
+                Some(&calendar_feed(after)),
+            ),
+        )
+        .await?;
+
+        transaction
+            .commit()
+            .await
+            .map_err(Into::into)
+            .map_err(RepositoryError::Internal)?;
+
+        ().into()
+    }
+
+    async fn get_feed_user(&self, token_hash: &str) -> ErrorOr<Option<i64>> {
+        let db_response = sqlx::query_scalar!(
+            r#"
+            SELECT user_id
+            FROM calendar_feeds
+            WHERE token_hash = $1
+            "#,
+            token_hash
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
 
         db_response.into()
     }
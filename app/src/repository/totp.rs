@@ -0,0 +1,261 @@
+use chrono::{DateTime, Utc};
+
+use super::error::RepositoryError;
+use crate::util::error_or::ErrorOr;
+
+/// The TOTP secret of a user.
+#[derive(Debug, Clone, PartialEq, Eq, sqlx::FromRow)]
+pub struct Totp {
+    pub user_id: i64,
+    /// Base32 encoded.
+    pub secret: String,
+    /// Two-factor authentication is only enabled once the secret was
+    /// confirmed with a first code.
+    pub confirmed_at: Option<DateTime<Utc>>,
+    /// Time step of the last accepted code.
+    pub last_used_step: Option<i64>,
+}
+
+/// Stores TOTP secrets and the hashes of the recovery codes.
+#[async_trait::async_trait]
+pub trait TotpRepository: Send + Sync + 'static {
+    async fn get_totp(&self, user_id: &i64) -> ErrorOr<Option<Totp>>;
+
+    /// Stores an unconfirmed secret, replacing an earlier one.
+    async fn set_pending_totp(
+        &self,
+        user_id: &i64,
+        secret: &str,
+    ) -> ErrorOr<()>;
+
+    /// Confirms the secret, records `step` as used and replaces the recovery
+    /// codes of the user.
+    async fn confirm_totp(
+        &self,
+        user_id: &i64,
+        step: i64,
+        recovery_code_hashes: &[String],
+    ) -> ErrorOr<()>;
+
+    /// Records `step` as used and returns whether it was used for the first
+    /// time, i.e. whether no code of it or a later step was accepted yet.
+    async fn use_step(&self, user_id: &i64, step: i64) -> ErrorOr<bool>;
+
+    /// Deletes a recovery code and returns whether it existed.
+    async fn use_recovery_code(
+        &self,
+        user_id: &i64,
+        code_hash: &str,
+    ) -> ErrorOr<bool>;
+
+    /// Disables two-factor authentication, including the recovery codes.
+    async fn delete_totp(&self, user_id: &i64) -> ErrorOr<()>;
+}
+
+pub struct PostgresTotpRepository {
+    pool: sqlx::PgPool,
+}
+
+impl PostgresTotpRepository {
+    pub fn new(pool: sqlx::PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl TotpRepository for PostgresTotpRepository {
+    async fn get_totp(&self, user_id: &i64) -> ErrorOr<Option<Totp>> {
+        let db_response = sqlx::query_as!(
+            Totp,
+            r#"
+            SELECT user_id, secret, confirmed_at, last_used_step
+            FROM user_totp
+            WHERE user_id = $1
+            "#,
+            user_id
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        db_response.into()
+    }
+
+    async fn set_pending_totp(
+        &self,
+        user_id: &i64,
+        secret: &str,
+    ) -> ErrorOr<()> {
+        sqlx::query!(
+            r#"
+            INSERT
+            INTO user_totp (user_id, secret)
+            VALUES ($1, $2)
+            ON CONFLICT (user_id) DO UPDATE
+            SET
+                secret = $2,
+                confirmed_at = NULL,
+                last_used_step = NULL,
+                created_at = now()
+            "#,
+            user_id,
+            secret
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        ().into()
+    }
+
+    async fn confirm_totp(
+        &self,
+        user_id: &i64,
+        step: i64,
+        recovery_code_hashes: &[String],
+    ) -> ErrorOr<()> {
+        let mut transaction = self
+            .pool
+            .begin()
+            .await
+            .map_err(Into::into)
+            .map_err(RepositoryError::Internal)?;
+
+        sqlx::query!(
+            r#"
+            UPDATE user_totp
+            SET confirmed_at = now(), last_used_step = $2
+            WHERE user_id = $1
+            "#,
+            user_id,
+            step
+        )
+        .execute(&mut *transaction)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        sqlx::query!(
+            r#"
+            DELETE
+            FROM recovery_codes
+            WHERE user_id = $1
+            "#,
+            user_id
+        )
+        .execute(&mut *transaction)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        sqlx::query!(
+            r#"
+            INSERT
+            INTO recovery_codes (user_id, code_hash)
+            SELECT $1, code_hash
+            FROM unnest($2::text[]) AS code_hash
+            "#,
+            user_id,
+            recovery_code_hashes
+        )
+        .execute(&mut *transaction)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        transaction
+            .commit()
+            .await
+            .map_err(Into::into)
+            .map_err(RepositoryError::Internal)?;
+
+        ().into()
+    }
+
+    async fn use_step(&self, user_id: &i64, step: i64) -> ErrorOr<bool> {
+        let db_response = sqlx::query!(
+            r#"
+            UPDATE user_totp
+            SET last_used_step = $2
+            WHERE
+                user_id = $1
+                AND (last_used_step IS NULL OR last_used_step < $2)
+            "#,
+            user_id,
+            step
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        (db_response.rows_affected() == 1).into()
+    }
+
+    async fn use_recovery_code(
+        &self,
+        user_id: &i64,
+        code_hash: &str,
+    ) -> ErrorOr<bool> {
+        let db_response = sqlx::query!(
+            r#"
+            DELETE
+            FROM recovery_codes
+            WHERE user_id = $1 AND code_hash = $2
+            "#,
+            user_id,
+            code_hash
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        (db_response.rows_affected() == 1).into()
+    }
+
+    async fn delete_totp(&self, user_id: &i64) -> ErrorOr<()> {
+        let mut transaction = self
+            .pool
+            .begin()
+            .await
+            .map_err(Into::into)
+            .map_err(RepositoryError::Internal)?;
+
+        sqlx::query!(
+            r#"
+            DELETE
+            FROM recovery_codes
+            WHERE user_id = $1
+            "#,
+            user_id
+        )
+        .execute(&mut *transaction)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        sqlx::query!(
+            r#"
+            DELETE
+            FROM user_totp
+            WHERE user_id = $1
+            "#,
+            user_id
+        )
+        .execute(&mut *transaction)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        transaction
+            .commit()
+            .await
+            .map_err(Into::into)
+            .map_err(RepositoryError::Internal)?;
+
+        ().into()
+    }
+}
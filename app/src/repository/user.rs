@@ -1,30 +1,101 @@
-use shared::models::user::{CreateUser, UpdateUser, User};
+use shared::models::{
+    audit::{AuditAction, AuditEntity},
+    user::{CreateUser, Role, UpdateUser, User},
+};
 
 use crate::util::error_or::ErrorOr;
 
-use super::error::{Operation, RepositoryError};
+use super::{
+    audit::{self, AuditContext, PostgresAuditRepository},
+    error::RepositoryError,
+};
 
 const RELATION: &str = "User";
 
+/// Number of users across all users.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, sqlx::FromRow)]
+pub struct UserStats {
+    pub total: i64,
+    pub admins: i64,
+    pub disabled: i64,
+}
+
 /// SAFETY: never expose get_user_by_email to an endpoint directly
 /// also never state that an email exists or does not exist.
 /// One may state that both the email and password combination are invalid
 /// without leaking additional information.
+///
+/// Every change is recorded as an audit event in the same transaction, the
+/// password hashes are left out of it.
 #[async_trait::async_trait]
 pub trait UserRepository: Send + Sync + 'static {
     async fn get_session_user(&self, session_user_id: &i64) -> ErrorOr<User>;
 
     async fn get_user_by_email(&self, email: &str) -> ErrorOr<User>;
 
-    async fn create_user(&self, create_user: &CreateUser) -> ErrorOr<()>;
+    async fn create_user(
+        &self,
+        create_user: &CreateUser,
+        audit: &AuditContext,
+    ) -> ErrorOr<()>;
 
+    /// Changes the name and email of the user, the password is changed with
+    /// [`set_password`](Self::set_password).
     async fn update_user(
         &self,
         update_user: &UpdateUser,
         session_user_id: &i64,
+        audit: &AuditContext,
+    ) -> ErrorOr<()>;
+
+    async fn delete_user(
+        &self,
+        session_user_id: &i64,
+        audit: &AuditContext,
+    ) -> ErrorOr<()>;
+
+    /// Marks the current email of the user as verified.
+    async fn set_email_verified(
+        &self,
+        user_id: &i64,
+        audit: &AuditContext,
+    ) -> ErrorOr<()>;
+
+    /// Replaces the password hash of the user.
+    async fn set_password(
+        &self,
+        user_id: &i64,
+        password_hash: &str,
+        audit: &AuditContext,
+    ) -> ErrorOr<()>;
+
+    async fn count_users(&self) -> ErrorOr<i64>;
+
+    /// Lists the users whose name or email contains `search`, ordered by id.
+    async fn search_users(
+        &self,
+        search: Option<&str>,
+        offset: i64,
+        limit: i64,
+    ) -> ErrorOr<Vec<User>>;
+
+    async fn set_role(
+        &self,
+        user_id: &i64,
+        role: Role,
+        audit: &AuditContext,
+    ) -> ErrorOr<()>;
+
+    /// Disables or enables the user, disabling keeps the time it first
+    /// happened.
+    async fn set_disabled(
+        &self,
+        user_id: &i64,
+        disabled: bool,
+        audit: &AuditContext,
     ) -> ErrorOr<()>;
 
-    async fn delete_user(&self, session_user_id: &i64) -> ErrorOr<()>;
+    async fn user_stats(&self) -> ErrorOr<UserStats>;
 }
 
 pub struct PostgresUserRepository {
@@ -35,6 +106,67 @@ impl PostgresUserRepository {
     pub fn new(pool: sqlx::PgPool) -> Self {
         Self { pool }
     }
+
+    /// Returns the user, if they exist, and locks them for the rest of the
+    /// transaction.
+    async fn locked_user(
+        transaction: &mut sqlx::PgConnection,
+        user_id: &i64,
+    ) -> Result<Option<User>, RepositoryError> {
+        sqlx::query_as!(
+            User,
+            r#"
+            SELECT *
+            FROM users
+            WHERE id = $1
+            FOR UPDATE
+            "#,
+            user_id
+        )
+        .fetch_optional(&mut *transaction)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)
+    }
+
+    /// Records the change of a user and commits it.
+    async fn commit_change(
+        mut transaction: sqlx::Transaction<'_, sqlx::Postgres>,
+        audit: &AuditContext,
+        action: AuditAction,
+        before: Option<&User>,
+        after: Option<&User>,
+    ) -> Result<(), RepositoryError> {
+        let Some(user_id) = after.or(before).map(|user| user.id) else {
+            return Ok(());
+        };
+        PostgresAuditRepository::record(
+            &mut transaction,
+            audit,
+            action,
+            AuditEntity::User,
+            user_id,
+            audit::changes(before, after),
+        )
+        .await?;
+
+        transaction
+            .commit()
+            .await
+            .map_err(Into::into)
+            .map_err(RepositoryError::Internal)
+    }
+
+    async fn begin(
+        &self,
+    ) -> Result<sqlx::Transaction<'static, sqlx::Postgres>, RepositoryError>
+    {
+        self.pool
+            .begin()
+            .await
+            .map_err(Into::into)
+            .map_err(RepositoryError::Internal)
+    }
 }
 
 #[async_trait::async_trait]
@@ -77,24 +209,39 @@ impl UserRepository for PostgresUserRepository {
         db_response.into()
     }
 
-    async fn create_user(&self, create_user: &CreateUser) -> ErrorOr<()> {
-        let db_response = sqlx::query!(
+    async fn create_user(
+        &self,
+        create_user: &CreateUser,
+        audit: &AuditContext,
+    ) -> ErrorOr<()> {
+        let mut transaction = self.begin().await?;
+
+        let user = sqlx::query_as!(
+            User,
             r#"
             INSERT
             INTO users (name, email, password)
             VALUES ($1, $2, $3)
+            RETURNING *
             "#,
             &create_user.name,
             &create_user.email,
             &create_user.password,
         )
-        .execute(&self.pool)
+        .fetch_one(&mut *transaction)
         .await
-        .map(|_| ())
-        .map_err(Into::into)
-        .map_err(RepositoryError::Internal)?;
+        .map_err(|e| RepositoryError::unique(e, RELATION))?;
 
-        db_response.into()
+        Self::commit_change(
+            transaction,
+            audit,
+            AuditAction::Created,
+            None,
+            Some(&user),
+        )
+        .await?;
+
+        ().into()
     }
 
     // TODO apply same principle as in todo with optional type fields
@@ -102,38 +249,61 @@ impl UserRepository for PostgresUserRepository {
         &self,
         update_user: &UpdateUser,
         session_user_id: &i64,
+        audit: &AuditContext,
     ) -> ErrorOr<()> {
-        let db_response = sqlx::query(
+        let mut transaction = self.begin().await?;
+        let Some(before) =
+            Self::locked_user(&mut transaction, session_user_id).await?
+        else {
+            return ().into();
+        };
+
+        let user = sqlx::query_as::<_, User>(
             r#"
             UPDATE users
             SET
                 name = COALESCE($1, name),
                 email = COALESCE($2, email),
-                password = COALESCE($3, password),
+                email_verified_at = CASE
+                    WHEN $2 IS NULL OR $2 = email THEN email_verified_at
+                END,
                 updated_at = now()
-            WHERE id = $4
+            WHERE id = $3
+            RETURNING *
             "#,
         )
         .bind::<&Option<String>>(&update_user.name)
         .bind::<&Option<String>>(&update_user.email)
-        .bind::<&Option<String>>(&update_user.password)
         .bind::<&i64>(session_user_id)
-        .execute(&self.pool)
+        .fetch_one(&mut *transaction)
         .await
-        .map(|_| ())
-        .map_err(|e| match e {
-            sqlx::Error::RowNotFound => RepositoryError::Forbidden {
-                operation: Operation::Update,
-                relation_name: RELATION.to_string(),
-            },
-            e => RepositoryError::Internal(e.into()),
-        })?;
+        .map_err(|e| RepositoryError::unique(e, RELATION))?;
 
-        db_response.into()
+        Self::commit_change(
+            transaction,
+            audit,
+            AuditAction::Updated,
+            Some(&before),
+            Some(&user),
+        )
+        .await?;
+
+        ().into()
     }
 
-    async fn delete_user(&self, session_user_id: &i64) -> ErrorOr<()> {
-        let db_response = sqlx::query!(
+    async fn delete_user(
+        &self,
+        session_user_id: &i64,
+        audit: &AuditContext,
+    ) -> ErrorOr<()> {
+        let mut transaction = self.begin().await?;
+        let Some(before) =
+            Self::locked_user(&mut transaction, session_user_id).await?
+        else {
+            return ().into();
+        };
+
+        sqlx::query!(
             r#"
             DELETE
             FROM users
@@ -141,15 +311,249 @@ impl UserRepository for PostgresUserRepository {
             "#,
             session_user_id
         )
-        .execute(&self.pool)
+        .execute(&mut *transaction)
         .await
-        .map(|_| ())
-        .map_err(|e| match e {
-            sqlx::Error::RowNotFound => RepositoryError::NotFound {
-                relation_name: RELATION.to_string(),
-            },
-            e => RepositoryError::Internal(e.into()),
-        })?;
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        Self::commit_change(
+            transaction,
+            audit,
+            AuditAction::Deleted,
+            Some(&before),
+            None,
+        )
+        .await?;
+
+        ().into()
+    }
+
+    async fn set_email_verified(
+        &self,
+        user_id: &i64,
+        audit: &AuditContext,
+    ) -> ErrorOr<()> {
+        let mut transaction = self.begin().await?;
+        let Some(before) = Self::locked_user(&mut transaction, user_id).await?
+        else {
+            return ().into();
+        };
+
+        let user = sqlx::query_as!(
+            User,
+            r#"
+            UPDATE users
+            SET email_verified_at = now()
+            WHERE id = $1
+            RETURNING *
+            "#,
+            user_id
+        )
+        .fetch_one(&mut *transaction)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        Self::commit_change(
+            transaction,
+            audit,
+            AuditAction::EmailVerified,
+            Some(&before),
+            Some(&user),
+        )
+        .await?;
+
+        ().into()
+    }
+
+    async fn set_password(
+        &self,
+        user_id: &i64,
+        password_hash: &str,
+        audit: &AuditContext,
+    ) -> ErrorOr<()> {
+        let mut transaction = self.begin().await?;
+        let Some(before) = Self::locked_user(&mut transaction, user_id).await?
+        else {
+            return ().into();
+        };
+
+        let user = sqlx::query_as!(
+            User,
+            r#"
+            UPDATE users
+            SET password = $1, updated_at = now()
+            WHERE id = $2
+            RETURNING *
+            "#,
+            password_hash,
+            user_id
+        )
+        .fetch_one(&mut *transaction)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        Self::commit_change(
+            transaction,
+            audit,
+            AuditAction::PasswordChanged,
+            Some(&before),
+            Some(&user),
+        )
+        .await?;
+
+        ().into()
+    }
+
+    async fn count_users(&self) -> ErrorOr<i64> {
+        let db_response = sqlx::query_scalar!(
+            r#"
+            SELECT count(*) as "count!"
+            FROM users
+            "#
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        db_response.into()
+    }
+
+    async fn search_users(
+        &self,
+        search: Option<&str>,
+        offset: i64,
+        limit: i64,
+    ) -> ErrorOr<Vec<User>> {
+        let db_response = sqlx::query_as!(
+            User,
+            r#"
+            SELECT *
+            FROM users
+            WHERE $1::text IS NULL
+                OR name ILIKE '%' || $1 || '%'
+                OR email ILIKE '%' || $1 || '%'
+            ORDER BY id
+            OFFSET $2
+            LIMIT $3
+            "#,
+            search,
+            offset,
+            limit
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        db_response.into()
+    }
+
+    async fn set_role(
+        &self,
+        user_id: &i64,
+        role: Role,
+        audit: &AuditContext,
+    ) -> ErrorOr<()> {
+        let mut transaction = self.begin().await?;
+        let Some(before) = Self::locked_user(&mut transaction, user_id).await?
+        else {
+            return ().into();
+        };
+
+        let user = sqlx::query_as!(
+            User,
+            r#"
+            UPDATE users
+            SET role = $1, updated_at = now()
+            WHERE id = $2
+            RETURNING *
+            "#,
+            role.as_str(),
+            user_id
+        )
+        .fetch_one(&mut *transaction)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        Self::commit_change(
+            transaction,
+            audit,
+            AuditAction::RoleChanged,
+            Some(&before),
+            Some(&user),
+        )
+        .await?;
+
+        ().into()
+    }
+
+    async fn set_disabled(
+        &self,
+        user_id: &i64,
+        disabled: bool,
+        audit: &AuditContext,
+    ) -> ErrorOr<()> {
+        let mut transaction = self.begin().await?;
+        let Some(before) = Self::locked_user(&mut transaction, user_id).await?
+        else {
+            return ().into();
+        };
+
+        let user = sqlx::query_as!(
+            User,
+            r#"
+            UPDATE users
+            SET
+                disabled_at = CASE
+                    WHEN $1 THEN COALESCE(disabled_at, now())
+                END,
+                updated_at = now()
+            WHERE id = $2
+            RETURNING *
+            "#,
+            disabled,
+            user_id
+        )
+        .fetch_one(&mut *transaction)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        let action = match disabled {
+            true => AuditAction::Disabled,
+            false => AuditAction::Enabled,
+        };
+        Self::commit_change(
+            transaction,
+            audit,
+            action,
+            Some(&before),
+            Some(&user),
+        )
+        .await?;
+
+        ().into()
+    }
+
+    async fn user_stats(&self) -> ErrorOr<UserStats> {
+        let db_response = sqlx::query_as!(
+            UserStats,
+            r#"
+            SELECT
+                count(*) AS "total!",
+                count(*) FILTER (WHERE role = 'admin') AS "admins!",
+                count(disabled_at) AS "disabled!"
+            FROM users
+            "#
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
 
         db_response.into()
     }
@@ -0,0 +1,164 @@
+use chrono::{DateTime, Duration, Utc};
+
+use super::error::RepositoryError;
+use crate::util::error_or::ErrorOr;
+
+pub(crate) const RELATION: &str = "Token";
+
+/// What a token mailed to a user allows them to do.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenPurpose {
+    VerifyEmail,
+    ResetPassword,
+}
+
+impl TokenPurpose {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TokenPurpose::VerifyEmail => "verify_email",
+            TokenPurpose::ResetPassword => "reset_password",
+        }
+    }
+
+    /// How long a token is valid after it was mailed.
+    pub fn lifetime(&self) -> Duration {
+        match self {
+            TokenPurpose::VerifyEmail => Duration::days(1),
+            TokenPurpose::ResetPassword => Duration::hours(1),
+        }
+    }
+}
+
+/// A token that was consumed, the token itself is never stored.
+#[derive(Debug, Clone, PartialEq, Eq, sqlx::FromRow)]
+pub struct UserToken {
+    pub user_id: i64,
+    /// The address the token was mailed to.
+    pub email: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Stores the hashes of single use tokens that are mailed to users.
+#[async_trait::async_trait]
+pub trait UserTokenRepository: Send + Sync + 'static {
+    /// Stores a new token and invalidates the earlier tokens of the user
+    /// with the same purpose.
+    async fn create_token(
+        &self,
+        token_hash: &str,
+        purpose: TokenPurpose,
+        user_id: &i64,
+        email: &str,
+        expires_at: DateTime<Utc>,
+    ) -> ErrorOr<()>;
+
+    /// Deletes the token and returns it. Fails with `NotFound` if the token
+    /// does not exist, was used already or is expired.
+    async fn consume_token(
+        &self,
+        token_hash: &str,
+        purpose: TokenPurpose,
+    ) -> ErrorOr<UserToken>;
+}
+
+/// Rejects tokens that were consumed after they expired.
+pub(crate) fn unexpired(token: Option<UserToken>) -> ErrorOr<UserToken> {
+    match token {
+        Some(token) if token.expires_at > Utc::now() => token.into(),
+        _ => Err(RepositoryError::NotFound {
+            relation_name: RELATION.to_string(),
+        })?,
+    }
+}
+
+pub struct PostgresUserTokenRepository {
+    pool: sqlx::PgPool,
+}
+
+impl PostgresUserTokenRepository {
+    pub fn new(pool: sqlx::PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl UserTokenRepository for PostgresUserTokenRepository {
+    async fn create_token(
+        &self,
+        token_hash: &str,
+        purpose: TokenPurpose,
+        user_id: &i64,
+        email: &str,
+        expires_at: DateTime<Utc>,
+    ) -> ErrorOr<()> {
+        let mut transaction = self
+            .pool
+            .begin()
+            .await
+            .map_err(Into::into)
+            .map_err(RepositoryError::Internal)?;
+
+        sqlx::query!(
+            r#"
+            DELETE
+            FROM user_tokens
+            WHERE user_id = $1 AND purpose = $2
+            "#,
+            user_id,
+            purpose.as_str()
+        )
+        .execute(&mut *transaction)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        sqlx::query!(
+            r#"
+            INSERT
+            INTO user_tokens (token_hash, user_id, purpose, email, expires_at)
+            VALUES ($1, $2, $3, $4, $5)
+            "#,
+            token_hash,
+            user_id,
+            purpose.as_str(),
+            email,
+            expires_at
+        )
+        .execute(&mut *transaction)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        transaction
+            .commit()
+            .await
+            .map_err(Into::into)
+            .map_err(RepositoryError::Internal)?;
+
+        ().into()
+    }
+
+    async fn consume_token(
+        &self,
+        token_hash: &str,
+        purpose: TokenPurpose,
+    ) -> ErrorOr<UserToken> {
+        let db_response = sqlx::query_as!(
+            UserToken,
+            r#"
+            DELETE
+            FROM user_tokens
+            WHERE token_hash = $1 AND purpose = $2
+            RETURNING user_id, email, expires_at
+            "#,
+            token_hash,
+            purpose.as_str()
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(Into::into)
+        .map_err(RepositoryError::Internal)?;
+
+        unexpired(db_response)
+    }
+}
@@ -0,0 +1,278 @@
+use std::ops::Range;
+
+use chrono::{Duration, Utc};
+use fake::{
+    faker::{
+        company::en::{BsAdj, BsNoun, BsVerb},
+        internet::en::FreeEmailProvider,
+        lorem::en::Sentences,
+        name::en::{FirstName, LastName},
+    },
+    Fake,
+};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use shared::models::{
+    todo::{CreateTodo, UpdateTodo},
+    user::CreateUser,
+};
+
+use crate::{
+    controllers::common,
+    repository::{
+        audit::AuditContext, todo::TodoRepository, user::UserRepository,
+    },
+    util::error_or::ErrorOr,
+};
+
+/// Describes the demo data that `seed` generates.
+#[derive(Debug, Clone)]
+pub struct SeedOptions {
+    pub users: usize,
+    /// Number of todos per user, picked at random from this range.
+    pub todos_per_user: Range<usize>,
+    /// Probability that a generated todo is marked as done.
+    pub done_ratio: f64,
+    /// Todos are created at random times within this many days before the
+    /// seeding, none are backdated if it is zero.
+    pub history_days: u32,
+    /// Password of every generated user.
+    pub password: String,
+    /// Seed of the random number generator. The same seed generates the same
+    /// data as long as the database is empty.
+    pub seed: u64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SeedSummary {
+    pub users: usize,
+    pub todos: usize,
+    pub done: usize,
+}
+
+/// Fills the repositories with random but realistic looking users and todos.
+pub async fn seed<U: UserRepository, T: TodoRepository>(
+    user_repository: &U,
+    todo_repository: &T,
+    options: &SeedOptions,
+) -> ErrorOr<SeedSummary> {
+    let mut rng = StdRng::seed_from_u64(options.seed);
+    let mut summary = SeedSummary::default();
+
+    // hashing is deliberately slow, so all users share a single hash
+    let password = common::hash_password(&options.password).await?;
+    let audit = AuditContext::default();
+    let now = Utc::now();
+    let history = Duration::days(options.history_days.into());
+
+    for user_index in 0..options.users {
+        let create_user = fake_user(&mut rng, user_index, &password);
+        user_repository.create_user(&create_user, &audit).await?;
+        let user =
+            user_repository.get_user_by_email(&create_user.email).await?;
+        summary.users += 1;
+
+        let todo_count = if options.todos_per_user.is_empty() {
+            0
+        } else {
+            rng.gen_range(options.todos_per_user.clone())
+        };
+
+        for _ in 0..todo_count {
+            let mut create_todo = fake_todo(&mut rng);
+            let created_at = now - random_duration(&mut rng, history);
+            // about a third of the todos are due up to two weeks after they
+            // were created, so some of them are overdue
+            if rng.gen_bool(0.3) {
+                create_todo.due_at = Some(
+                    created_at + random_duration(&mut rng, Duration::days(14)),
+                );
+            }
+            let todo = todo_repository
+                .create_todo(&create_todo, &user.id, &audit)
+                .await?;
+            summary.todos += 1;
+
+            if rng.gen_bool(options.done_ratio) {
+                todo_repository
+                    .update_todo(
+                        &UpdateTodo {
+                            id: todo.id,
+                            title: None,
+                            description: None,
+                            is_done: Some(true),
+                            due_at: None,
+                        },
+                        &user.id,
+                        &audit,
+                    )
+                    .await?;
+                summary.done += 1;
+            }
+
+            if !history.is_zero() {
+                let updated_at =
+                    created_at + random_duration(&mut rng, now - created_at);
+                todo_repository
+                    .backdate_todo(&todo.id, created_at, updated_at, &audit)
+                    .await?;
+            }
+        }
+    }
+
+    summary.into()
+}
+
+/// Returns a random duration of at most `max`, in whole seconds.
+fn random_duration(rng: &mut StdRng, max: Duration) -> Duration {
+    Duration::seconds(rng.gen_range(0..=max.num_seconds()))
+}
+
+fn fake_user(rng: &mut StdRng, index: usize, password: &str) -> CreateUser {
+    let first_name: String = FirstName().fake_with_rng(rng);
+    let last_name: String = LastName().fake_with_rng(rng);
+    let provider: String = FreeEmailProvider().fake_with_rng(rng);
+
+    // the index keeps the emails unique even if a name is drawn twice
+    let email = format!("{first_name}.{last_name}{index}@{provider}")
+        .to_lowercase()
+        .replace(char::is_whitespace, "");
+
+    CreateUser {
+        name: format!("{first_name} {last_name}"),
+        email,
+        password: password.to_string(),
+    }
+}
+
+fn fake_todo(rng: &mut StdRng) -> CreateTodo {
+    let verb: String = BsVerb().fake_with_rng(rng);
+    let adjective: String = BsAdj().fake_with_rng(rng);
+    let noun: String = BsNoun().fake_with_rng(rng);
+
+    let mut title = format!("{verb} {adjective} {noun}");
+    title[..1].make_ascii_uppercase();
+
+    let description = if rng.gen_bool(0.6) {
+        Sentences(1..3).fake_with_rng::<Vec<String>, _>(rng).join(" ")
+    } else {
+        String::new()
+    };
+
+    CreateTodo { title, description, list_id: None, due_at: None }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shared::models::todo::Todo;
+
+    use crate::repository::{memory::MemoryBackend, Backend};
+
+    fn options(seed: u64) -> SeedOptions {
+        SeedOptions {
+            users: 3,
+            todos_per_user: 2..6,
+            done_ratio: 0.5,
+            history_days: 30,
+            password: "password".to_string(),
+            seed,
+        }
+    }
+
+    async fn seeded_todos(backend: &MemoryBackend) -> Vec<Vec<Todo>> {
+        let users = backend
+            .user_repository()
+            .search_users(None, 0, 100)
+            .await
+            .0
+            .unwrap();
+        let mut todos = Vec::new();
+        for user in users {
+            todos.push(
+                backend.todo_repository().get_todos(&user.id).await.0.unwrap(),
+            );
+        }
+        todos
+    }
+
+    #[actix_rt::test]
+    async fn seed_fills_the_repositories() {
+        let backend = MemoryBackend::new();
+        let start = Utc::now();
+        let summary = seed(
+            &backend.user_repository(),
+            &backend.todo_repository(),
+            &options(3),
+        )
+        .await
+        .0
+        .unwrap();
+
+        let todos = seeded_todos(&backend).await;
+        assert_eq!(todos.len(), 3);
+        assert_eq!(summary.users, 3);
+        assert!(todos.iter().all(|todos| (2..6).contains(&todos.len())));
+        let todos = todos.into_iter().flatten().collect::<Vec<_>>();
+        assert_eq!(summary.todos, todos.len());
+        assert_eq!(
+            summary.done,
+            todos.iter().filter(|todo| todo.is_done).count()
+        );
+
+        let earliest = start - Duration::days(30);
+        for todo in &todos {
+            assert!(earliest <= todo.created_at && todo.created_at <= start);
+            assert!(todo.created_at <= todo.updated_at);
+            assert_eq!(
+                todo.completed_at,
+                todo.is_done.then_some(todo.updated_at)
+            );
+        }
+        // the timestamps are spread over the history, not all set at once
+        let days = todos
+            .iter()
+            .map(|todo| todo.created_at.date_naive())
+            .collect::<std::collections::HashSet<_>>();
+        assert!(days.len() > 1);
+    }
+
+    #[actix_rt::test]
+    async fn same_seed_fills_the_same_data() {
+        let mut titles = Vec::new();
+        for _ in 0..2 {
+            let backend = MemoryBackend::new();
+            seed(
+                &backend.user_repository(),
+                &backend.todo_repository(),
+                &options(11),
+            )
+            .await
+            .0
+            .unwrap();
+            titles.push(
+                seeded_todos(&backend)
+                    .await
+                    .into_iter()
+                    .flatten()
+                    .map(|todo| (todo.title, todo.is_done))
+                    .collect::<Vec<_>>(),
+            );
+        }
+
+        assert_eq!(titles[0], titles[1]);
+    }
+
+    #[test]
+    fn same_seed_generates_same_data() {
+        let mut first = StdRng::seed_from_u64(7);
+        let mut second = StdRng::seed_from_u64(7);
+
+        for index in 0..10 {
+            assert_eq!(
+                fake_user(&mut first, index, "hash"),
+                fake_user(&mut second, index, "hash")
+            );
+            assert_eq!(fake_todo(&mut first), fake_todo(&mut second));
+        }
+    }
+}
@@ -0,0 +1,169 @@
+use std::sync::Arc;
+
+use actix_identity::{config::LogoutBehaviour, IdentityMiddleware};
+use actix_session::{
+    config::{CookieContentSecurity, PersistentSession},
+    SessionMiddleware,
+};
+use actix_web::{
+    body::MessageBody,
+    cookie::{Key, SameSite},
+    dev::{Service, ServiceFactory, ServiceRequest, ServiceResponse},
+    middleware::{self, Compat},
+    web, App,
+};
+use webauthn_rs::Webauthn;
+
+use crate::{
+    blob::BlobStore,
+    controllers::{
+        self,
+        api::attachment::AttachmentLimits,
+        common::{
+            login_throttle::LoginThrottle, oidc::OidcProvider,
+            rate_limit::RateLimiter, request_id::RequestId,
+        },
+    },
+    mail::Mailer,
+    repository::{
+        access_token::AccessTokenRepository, session::RepositorySessionStore,
+        user::UserRepository, Backend,
+    },
+};
+
+/// Settings of the application, everything but the storage backend.
+#[derive(Clone)]
+pub struct AppSettings {
+    pub session: SessionSettings,
+    pub login_throttle: LoginThrottle,
+    /// Shares its counters with all clones, see [`RateLimiter`].
+    pub rate_limiter: RateLimiter,
+    pub mailer: Arc<dyn Mailer>,
+    /// Where the content of attachments is kept.
+    pub blob_store: Arc<dyn BlobStore>,
+    pub attachment_limits: AttachmentLimits,
+    /// The relying party of the passkeys.
+    pub webauthn: Arc<Webauthn>,
+    /// The provider for single sign-on, if any.
+    pub oidc: Option<Arc<OidcProvider>>,
+}
+
+/// Settings of the session cookie that identifies a logged in user.
+#[derive(Clone)]
+pub struct SessionSettings {
+    /// Key used to sign and encrypt the session cookie.
+    pub key: Key,
+    pub cookie_name: String,
+    pub secure: bool,
+    pub same_site: SameSite,
+    pub domain: Option<String>,
+}
+
+/// Builds the api application with all of its middleware on top of the
+/// repositories of `backend`.
+///
+/// The server calls this once per worker, the integration tests once per
+/// client.
+pub fn app<B: Backend>(
+    backend: &B,
+    settings: &AppSettings,
+) -> App<
+    impl ServiceFactory<
+        ServiceRequest,
+        Config = (),
+        Response = ServiceResponse<impl MessageBody>,
+        Error = actix_web::Error,
+        InitError = (),
+    >,
+> {
+    let todo_repository = web::Data::new(backend.todo_repository());
+    let user_repository = web::Data::new(backend.user_repository());
+    let login_attempt_repository =
+        web::Data::new(backend.login_attempt_repository());
+    let user_token_repository = web::Data::new(backend.user_token_repository());
+    let totp_repository = web::Data::new(backend.totp_repository());
+    let passkey_repository = web::Data::new(backend.passkey_repository());
+    let oidc_identity_repository =
+        web::Data::new(backend.oidc_identity_repository());
+    let access_token_repository =
+        web::Data::new(backend.access_token_repository());
+    let list_repository = web::Data::new(backend.list_repository());
+    let comment_repository = web::Data::new(backend.comment_repository());
+    let audit_repository = web::Data::new(backend.audit_repository());
+    let attachment_repository = web::Data::new(backend.attachment_repository());
+    // for the `AuthUser` extractor, which is not generic over the backend
+    let dyn_user_repository = web::Data::from(Arc::new(
+        backend.user_repository(),
+    ) as Arc<dyn UserRepository>);
+    let dyn_access_token_repository =
+        web::Data::from(Arc::new(backend.access_token_repository())
+            as Arc<dyn AccessTokenRepository>);
+    let session_repository = web::Data::new(backend.session_repository());
+    let session_store =
+        RepositorySessionStore::new(backend.session_repository());
+
+    let session = &settings.session;
+    // hash it now instead of during the first login with an unknown email
+    controllers::common::dummy_password_hash();
+
+    let app = App::new()
+        .wrap(Compat::new(middleware::Logger::default()))
+        .wrap(Compat::new(middleware::Compress::default()))
+        // runs after the identity middleware to count users by their id
+        .wrap(Compat::new(settings.rate_limiter.clone()))
+        .wrap(Compat::new(
+            IdentityMiddleware::builder()
+                //.visit_deadline(Some(Duration::from_secs(config.cookie_timeout)))
+                .logout_behaviour(LogoutBehaviour::PurgeSession)
+                .build(),
+        ))
+        .wrap(Compat::new(
+            SessionMiddleware::builder(session_store, session.key.clone())
+                .session_lifecycle(PersistentSession::default())
+                .cookie_content_security(CookieContentSecurity::Private)
+                .cookie_name(session.cookie_name.clone())
+                .cookie_same_site(session.same_site)
+                .cookie_path("/".into())
+                .cookie_domain(session.domain.clone())
+                .cookie_secure(session.secure)
+                .cookie_http_only(true)
+                .build(),
+        ))
+        // outermost, so the audit events of every request can refer to it
+        .wrap_fn(|request, service| {
+            let request_id = RequestId::assign(&request);
+            let response = service.call(request);
+            async move {
+                let mut response = response.await?;
+                request_id.answer(&mut response);
+                Ok(response)
+            }
+        })
+        .app_data(todo_repository)
+        .app_data(user_repository)
+        .app_data(login_attempt_repository)
+        .app_data(user_token_repository)
+        .app_data(totp_repository)
+        .app_data(passkey_repository)
+        .app_data(oidc_identity_repository)
+        .app_data(access_token_repository)
+        .app_data(list_repository)
+        .app_data(comment_repository)
+        .app_data(audit_repository)
+        .app_data(attachment_repository)
+        .app_data(dyn_user_repository)
+        .app_data(dyn_access_token_repository)
+        .app_data(session_repository)
+        .app_data(web::Data::new(settings.login_throttle.clone()))
+        .app_data(web::Data::from(settings.mailer.clone()))
+        .app_data(web::Data::from(settings.blob_store.clone()))
+        .app_data(web::Data::new(settings.attachment_limits))
+        .app_data(web::Data::from(settings.webauthn.clone()));
+    let app = match &settings.oidc {
+        Some(oidc) => app.app_data(web::Data::from(oidc.clone())),
+        None => app,
+    };
+
+    app.configure(controllers::api::service::<B>)
+        .configure(controllers::dav::service::<B>)
+}
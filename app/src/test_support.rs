@@ -0,0 +1,886 @@
+//! Drives the complete api application in tests, without TLS and a database.
+//!
+//! ```ignore
+//! let backend = MemoryBackend::new();
+//! let mut jane = test_support::client(&backend).await;
+//! jane.register("Jane", "jane@example.com", "secret").await.ok();
+//! jane.login("jane@example.com", "secret").await.ok();
+//! assert!(jane.todos().await.ok().is_empty());
+//! ```
+
+use std::{
+    collections::HashMap, marker::PhantomData, net::SocketAddr, str::FromStr,
+    sync::Arc,
+};
+
+use actix_http::{
+    header::{HeaderMap, AUTHORIZATION, CONTENT_TYPE},
+    Request, StatusCode,
+};
+use actix_web::{
+    body::MessageBody,
+    cookie::{time::Duration, Cookie, Key, SameSite},
+    dev::{Service, ServiceResponse},
+    test::{self, TestRequest},
+};
+use bytes::Bytes;
+use serde::{de::DeserializeOwned, Serialize};
+use shared::models::{
+    attachment::{Attachment, StorageUsage},
+    audit::{AuditEvent, SecurityEvent},
+    comment::{Comment, CreateComment, UpdateComment},
+    list::{
+        CreateList, InviteMember, ListInvitation, ListMember, ListPermission,
+        SetPermission, TodoList, UpdateList,
+    },
+    todo::{AssignTodo, CreateTodo, Todo, TodoActivity, UpdateTodo},
+    transfer::{CalendarFeed, ImportReport, ImportTodos, TransferFormat},
+    user::{
+        AccessTokenSummary, AdminStats, CreateAccessToken, CreateUser,
+        CreatedAccessToken, PasskeySummary, RecoveryCodes,
+        RequestPasswordReset, ResetPassword, Role, SetRole, SignInResponse,
+        SignInUser, StartPasskeyLogin, StartPasskeyRegistration, TotpCode,
+        TotpEnrollment, UpdateUser, UserAccount, VerifyEmail,
+    },
+};
+use webauthn_rs::prelude::{
+    CreationChallengeResponse, PublicKeyCredential,
+    RegisterPublicKeyCredential, RequestChallengeResponse,
+};
+
+use crate::{
+    blob::MemoryBlobStore,
+    controllers::api::attachment::AttachmentLimits,
+    controllers::common::{
+        login_throttle::LoginThrottle, rate_limit::RateLimiter, webauthn,
+    },
+    mail::MemoryMailer,
+    repository::Backend,
+    server::{self, AppSettings, SessionSettings},
+    util::error,
+};
+
+/// Origin of the passkey ceremonies in tests.
+pub const ORIGIN: &str = "https://localhost:8443";
+
+/// Settings of the test clients, the cookie key is fixed so that failures
+/// are reproducible.
+pub fn settings() -> AppSettings {
+    AppSettings {
+        session: SessionSettings {
+            key: Key::from(&[7; 64]),
+            cookie_name: "id".to_string(),
+            secure: true,
+            same_site: SameSite::Strict,
+            domain: None,
+        },
+        login_throttle: LoginThrottle::default(),
+        rate_limiter: RateLimiter::default(),
+        mailer: Arc::new(MemoryMailer::default()),
+        blob_store: Arc::new(MemoryBlobStore::default()),
+        attachment_limits: AttachmentLimits::default(),
+        webauthn: Arc::new(
+            webauthn::relying_party("localhost", ORIGIN, "lentos")
+                .expect("a valid relying party"),
+        ),
+        oidc: None,
+    }
+}
+
+/// Builds the application on top of `backend` and returns a client with an
+/// empty cookie jar for it.
+///
+/// Clients created from the same backend share its data but not their
+/// sessions, just like two browsers.
+pub async fn client<B: Backend>(
+    backend: &B,
+) -> TestClient<
+    impl Service<
+        Request,
+        Response = ServiceResponse<impl MessageBody>,
+        Error = actix_web::Error,
+    >,
+> {
+    client_with(backend, settings()).await
+}
+
+/// Like [`client`], but with custom settings.
+pub async fn client_with<B: Backend>(
+    backend: &B,
+    settings: AppSettings,
+) -> TestClient<
+    impl Service<
+        Request,
+        Response = ServiceResponse<impl MessageBody>,
+        Error = actix_web::Error,
+    >,
+> {
+    let service = test::init_service(server::app(backend, &settings)).await;
+
+    TestClient {
+        service,
+        settings,
+        cookies: HashMap::new(),
+        peer_addr: None,
+        bearer: None,
+    }
+}
+
+pub struct TestClient<S> {
+    service: S,
+    settings: AppSettings,
+    cookies: HashMap<String, Cookie<'static>>,
+    peer_addr: Option<SocketAddr>,
+    bearer: Option<String>,
+}
+
+impl<S, B> TestClient<S>
+where
+    S: Service<
+        Request,
+        Response = ServiceResponse<B>,
+        Error = actix_web::Error,
+    >,
+    B: MessageBody,
+{
+    /// Sends a request with the cookies of earlier responses and remembers
+    /// the cookies the response sets or removes.
+    pub async fn send<T>(&mut self, request: TestRequest) -> ApiResponse<T> {
+        let mut request = self
+            .cookies
+            .values()
+            .fold(request, |request, cookie| request.cookie(cookie.clone()));
+        if let Some(peer_addr) = self.peer_addr {
+            request = request.peer_addr(peer_addr);
+        }
+        if let Some(token) = &self.bearer {
+            request = request
+                .insert_header((AUTHORIZATION, format!("Bearer {token}")));
+        }
+        let response =
+            test::call_service(&self.service, request.to_request()).await;
+
+        for cookie in response.response().cookies() {
+            if cookie.max_age() == Some(Duration::ZERO) {
+                self.cookies.remove(cookie.name());
+            } else {
+                self.cookies
+                    .insert(cookie.name().to_string(), cookie.into_owned());
+            }
+        }
+
+        let status = response.status();
+        let content_type = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .map(ToString::to_string);
+        let headers = response.headers().clone();
+        let body = test::read_body(response).await;
+
+        ApiResponse {
+            status,
+            content_type,
+            headers,
+            body,
+            response: PhantomData,
+        }
+    }
+
+    /// Whether the client holds a session cookie.
+    pub fn has_session(&self) -> bool {
+        self.cookies.contains_key(&self.settings.session.cookie_name)
+    }
+
+    /// Sends all further requests from `peer_addr`, requests have no peer
+    /// address by default.
+    pub fn set_peer_addr(&mut self, peer_addr: SocketAddr) {
+        self.peer_addr = Some(peer_addr);
+    }
+
+    /// Sends all further requests with the access `token` in the
+    /// `Authorization` header, or without one for `None`.
+    pub fn set_bearer(&mut self, token: Option<&str>) {
+        self.bearer = token.map(ToString::to_string);
+    }
+
+    pub async fn register(
+        &mut self,
+        name: &str,
+        email: &str,
+        password: &str,
+    ) -> ApiResponse<()> {
+        let create_user = CreateUser {
+            name: name.to_string(),
+            email: email.to_string(),
+            password: password.to_string(),
+        };
+
+        self.send(post("/api/v1/users/register", &create_user)).await
+    }
+
+    pub async fn login(
+        &mut self,
+        email: &str,
+        password: &str,
+    ) -> ApiResponse<SignInResponse> {
+        let sign_in_user = SignInUser {
+            email: email.to_string(),
+            password: password.to_string(),
+        };
+
+        self.send(post("/api/v1/users/login", &sign_in_user)).await
+    }
+
+    /// Completes a login that waits for the second factor.
+    pub async fn login_totp(&mut self, code: &str) -> ApiResponse<()> {
+        let totp_code = TotpCode { code: code.to_string() };
+
+        self.send(post("/api/v1/users/login/totp", &totp_code)).await
+    }
+
+    pub async fn enroll_totp(&mut self) -> ApiResponse<TotpEnrollment> {
+        self.send(TestRequest::post().uri("/api/v1/users/totp")).await
+    }
+
+    pub async fn confirm_totp(
+        &mut self,
+        code: &str,
+    ) -> ApiResponse<RecoveryCodes> {
+        let totp_code = TotpCode { code: code.to_string() };
+
+        self.send(post("/api/v1/users/totp/confirm", &totp_code)).await
+    }
+
+    pub async fn disable_totp(&mut self, code: &str) -> ApiResponse<()> {
+        let totp_code = TotpCode { code: code.to_string() };
+
+        self.send(
+            TestRequest::delete().uri("/api/v1/users/totp").set_json(totp_code),
+        )
+        .await
+    }
+
+    pub async fn passkeys(&mut self) -> ApiResponse<Vec<PasskeySummary>> {
+        self.send(TestRequest::get().uri("/api/v1/users/passkeys")).await
+    }
+
+    pub async fn start_passkey_registration(
+        &mut self,
+        name: &str,
+    ) -> ApiResponse<CreationChallengeResponse> {
+        let start = StartPasskeyRegistration { name: name.to_string() };
+
+        self.send(post("/api/v1/users/passkeys/register/start", &start)).await
+    }
+
+    pub async fn finish_passkey_registration(
+        &mut self,
+        credential: &RegisterPublicKeyCredential,
+    ) -> ApiResponse<PasskeySummary> {
+        self.send(post("/api/v1/users/passkeys/register/finish", credential))
+            .await
+    }
+
+    pub async fn start_passkey_login(
+        &mut self,
+        email: &str,
+    ) -> ApiResponse<RequestChallengeResponse> {
+        let start = StartPasskeyLogin { email: email.to_string() };
+
+        self.send(post("/api/v1/users/passkeys/login/start", &start)).await
+    }
+
+    pub async fn finish_passkey_login(
+        &mut self,
+        credential: &PublicKeyCredential,
+    ) -> ApiResponse<()> {
+        self.send(post("/api/v1/users/passkeys/login/finish", credential)).await
+    }
+
+    pub async fn delete_passkey(&mut self, passkey_id: i64) -> ApiResponse<()> {
+        self.send(
+            TestRequest::delete()
+                .uri(&format!("/api/v1/users/passkeys/{passkey_id}")),
+        )
+        .await
+    }
+
+    /// Starts a single sign-on, the response redirects to the provider.
+    pub async fn start_oidc_login(&mut self) -> ApiResponse<()> {
+        self.send(TestRequest::get().uri("/api/v1/users/oidc/login")).await
+    }
+
+    /// Calls the callback with the `query` the provider redirected to.
+    pub async fn finish_oidc_login(&mut self, query: &str) -> ApiResponse<()> {
+        self.send(
+            TestRequest::get()
+                .uri(&format!("/api/v1/users/oidc/callback?{query}")),
+        )
+        .await
+    }
+
+    pub async fn access_tokens(
+        &mut self,
+    ) -> ApiResponse<Vec<AccessTokenSummary>> {
+        self.send(TestRequest::get().uri("/api/v1/users/tokens")).await
+    }
+
+    pub async fn create_access_token(
+        &mut self,
+        create_token: &CreateAccessToken,
+    ) -> ApiResponse<CreatedAccessToken> {
+        self.send(post("/api/v1/users/tokens", create_token)).await
+    }
+
+    pub async fn delete_access_token(
+        &mut self,
+        token_id: i64,
+    ) -> ApiResponse<()> {
+        self.send(
+            TestRequest::delete()
+                .uri(&format!("/api/v1/users/tokens/{token_id}")),
+        )
+        .await
+    }
+
+    pub async fn request_email_verification(&mut self) -> ApiResponse<()> {
+        self.send(TestRequest::post().uri("/api/v1/users/verify-email/request"))
+            .await
+    }
+
+    pub async fn verify_email(&mut self, token: &str) -> ApiResponse<()> {
+        let verify_email = VerifyEmail { token: token.to_string() };
+
+        self.send(post("/api/v1/users/verify-email", &verify_email)).await
+    }
+
+    pub async fn request_password_reset(
+        &mut self,
+        email: &str,
+    ) -> ApiResponse<()> {
+        let request_reset = RequestPasswordReset { email: email.to_string() };
+
+        self.send(post("/api/v1/users/password-reset/request", &request_reset))
+            .await
+    }
+
+    pub async fn reset_password(
+        &mut self,
+        token: &str,
+        password: &str,
+    ) -> ApiResponse<()> {
+        let reset_password = ResetPassword {
+            token: token.to_string(),
+            password: password.to_string(),
+        };
+
+        self.send(post("/api/v1/users/password-reset", &reset_password)).await
+    }
+
+    pub async fn logout(&mut self) -> ApiResponse<()> {
+        self.send(TestRequest::post().uri("/api/v1/users/logout")).await
+    }
+
+    pub async fn user(&mut self) -> ApiResponse<UserAccount> {
+        self.send(TestRequest::get().uri("/api/v1/users")).await
+    }
+
+    pub async fn update_user(
+        &mut self,
+        update_user: &UpdateUser,
+    ) -> ApiResponse<()> {
+        self.send(TestRequest::put().uri("/api/v1/users").set_json(update_user))
+            .await
+    }
+
+    pub async fn delete_user(&mut self) -> ApiResponse<()> {
+        self.send(TestRequest::delete().uri("/api/v1/users")).await
+    }
+
+    /// Lists the users matching `query` with the admin api.
+    pub async fn admin_users(
+        &mut self,
+        query: &str,
+    ) -> ApiResponse<Vec<UserAccount>> {
+        self.send(
+            TestRequest::get().uri(&format!("/api/v1/admin/users?{query}")),
+        )
+        .await
+    }
+
+    pub async fn admin_user(
+        &mut self,
+        user_id: i64,
+    ) -> ApiResponse<UserAccount> {
+        self.send(
+            TestRequest::get().uri(&format!("/api/v1/admin/users/{user_id}")),
+        )
+        .await
+    }
+
+    pub async fn set_role(
+        &mut self,
+        user_id: i64,
+        role: Role,
+    ) -> ApiResponse<()> {
+        self.send(
+            TestRequest::put()
+                .uri(&format!("/api/v1/admin/users/{user_id}/role"))
+                .set_json(SetRole { role }),
+        )
+        .await
+    }
+
+    pub async fn disable_user(&mut self, user_id: i64) -> ApiResponse<()> {
+        self.send(
+            TestRequest::post()
+                .uri(&format!("/api/v1/admin/users/{user_id}/disable")),
+        )
+        .await
+    }
+
+    pub async fn enable_user(&mut self, user_id: i64) -> ApiResponse<()> {
+        self.send(
+            TestRequest::post()
+                .uri(&format!("/api/v1/admin/users/{user_id}/enable")),
+        )
+        .await
+    }
+
+    pub async fn force_password_reset(
+        &mut self,
+        user_id: i64,
+    ) -> ApiResponse<()> {
+        self.send(
+            TestRequest::post()
+                .uri(&format!("/api/v1/admin/users/{user_id}/password-reset")),
+        )
+        .await
+    }
+
+    pub async fn admin_stats(&mut self) -> ApiResponse<AdminStats> {
+        self.send(TestRequest::get().uri("/api/v1/admin/stats")).await
+    }
+
+    /// Returns a page of the security stream, the query holds the user,
+    /// offset and limit.
+    pub async fn admin_security_events(
+        &mut self,
+        query: &str,
+    ) -> ApiResponse<Vec<SecurityEvent>> {
+        self.send(
+            TestRequest::get()
+                .uri(&format!("/api/v1/admin/security-events?{query}")),
+        )
+        .await
+    }
+
+    pub async fn todos(&mut self) -> ApiResponse<Vec<Todo>> {
+        self.send(TestRequest::get().uri("/api/v1/todos")).await
+    }
+
+    pub async fn assigned_todos(&mut self) -> ApiResponse<Vec<Todo>> {
+        self.send(TestRequest::get().uri("/api/v1/todos?assigned_to_me=true"))
+            .await
+    }
+
+    pub async fn todo(&mut self, todo_id: i64) -> ApiResponse<Todo> {
+        self.send(TestRequest::get().uri(&format!("/api/v1/todos/{todo_id}")))
+            .await
+    }
+
+    pub async fn create_todo(
+        &mut self,
+        create_todo: &CreateTodo,
+    ) -> ApiResponse<()> {
+        self.send(post("/api/v1/todos", create_todo)).await
+    }
+
+    pub async fn update_todo(
+        &mut self,
+        update_todo: &UpdateTodo,
+    ) -> ApiResponse<()> {
+        self.send(TestRequest::put().uri("/api/v1/todos").set_json(update_todo))
+            .await
+    }
+
+    pub async fn delete_todo(&mut self, todo_id: i64) -> ApiResponse<()> {
+        self.send(
+            TestRequest::delete().uri(&format!("/api/v1/todos/{todo_id}")),
+        )
+        .await
+    }
+
+    pub async fn assign_todo(
+        &mut self,
+        todo_id: i64,
+        assignee_id: Option<i64>,
+    ) -> ApiResponse<Todo> {
+        self.send(
+            TestRequest::put()
+                .uri(&format!("/api/v1/todos/{todo_id}/assignee"))
+                .set_json(AssignTodo { assignee_id }),
+        )
+        .await
+    }
+
+    pub async fn todo_activity(
+        &mut self,
+        todo_id: i64,
+    ) -> ApiResponse<Vec<TodoActivity>> {
+        self.send(
+            TestRequest::get()
+                .uri(&format!("/api/v1/todos/{todo_id}/activity")),
+        )
+        .await
+    }
+
+    /// Returns a page of the changes, the query holds offset and limit.
+    pub async fn todo_history(
+        &mut self,
+        todo_id: i64,
+        query: &str,
+    ) -> ApiResponse<Vec<AuditEvent>> {
+        self.send(
+            TestRequest::get()
+                .uri(&format!("/api/v1/todos/{todo_id}/history?{query}")),
+        )
+        .await
+    }
+
+    pub async fn account_activity(&mut self) -> ApiResponse<Vec<AuditEvent>> {
+        self.send(TestRequest::get().uri("/api/v1/users/activity")).await
+    }
+
+    pub async fn security_events(&mut self) -> ApiResponse<Vec<SecurityEvent>> {
+        self.send(TestRequest::get().uri("/api/v1/users/security-events")).await
+    }
+
+    /// Returns a page of the comments, the query holds offset and limit.
+    pub async fn comments(
+        &mut self,
+        todo_id: i64,
+        query: &str,
+    ) -> ApiResponse<Vec<Comment>> {
+        self.send(
+            TestRequest::get()
+                .uri(&format!("/api/v1/todos/{todo_id}/comments?{query}")),
+        )
+        .await
+    }
+
+    pub async fn create_comment(
+        &mut self,
+        todo_id: i64,
+        body: &str,
+        mentions: &[i64],
+    ) -> ApiResponse<Comment> {
+        self.send(post(
+            &format!("/api/v1/todos/{todo_id}/comments"),
+            &CreateComment {
+                body: body.to_string(),
+                mentions: mentions.to_vec(),
+            },
+        ))
+        .await
+    }
+
+    pub async fn update_comment(
+        &mut self,
+        todo_id: i64,
+        comment_id: i64,
+        body: &str,
+        mentions: &[i64],
+    ) -> ApiResponse<Comment> {
+        self.send(
+            TestRequest::put()
+                .uri(&format!("/api/v1/todos/{todo_id}/comments/{comment_id}"))
+                .set_json(UpdateComment {
+                    body: body.to_string(),
+                    mentions: mentions.to_vec(),
+                }),
+        )
+        .await
+    }
+
+    pub async fn delete_comment(
+        &mut self,
+        todo_id: i64,
+        comment_id: i64,
+    ) -> ApiResponse<()> {
+        self.send(
+            TestRequest::delete()
+                .uri(&format!("/api/v1/todos/{todo_id}/comments/{comment_id}")),
+        )
+        .await
+    }
+
+    pub async fn attachments(
+        &mut self,
+        todo_id: i64,
+    ) -> ApiResponse<Vec<Attachment>> {
+        self.send(
+            TestRequest::get()
+                .uri(&format!("/api/v1/todos/{todo_id}/attachments")),
+        )
+        .await
+    }
+
+    /// Uploads `content` as the `file` field of a multipart form.
+    pub async fn upload_attachment(
+        &mut self,
+        todo_id: i64,
+        file_name: &str,
+        content_type: &str,
+        content: &[u8],
+    ) -> ApiResponse<Attachment> {
+        const BOUNDARY: &str = "lentos-test-boundary";
+        let mut body = format!(
+            "--{BOUNDARY}\r\nContent-Disposition: form-data; name=\"file\"; \
+             filename=\"{file_name}\"\r\nContent-Type: {content_type}\r\n\r\n"
+        )
+        .into_bytes();
+        body.extend_from_slice(content);
+        body.extend_from_slice(format!("\r\n--{BOUNDARY}--\r\n").as_bytes());
+
+        self.send(
+            TestRequest::post()
+                .uri(&format!("/api/v1/todos/{todo_id}/attachments"))
+                .insert_header((
+                    CONTENT_TYPE,
+                    format!("multipart/form-data; boundary={BOUNDARY}"),
+                ))
+                .set_payload(body),
+        )
+        .await
+    }
+
+    /// Downloads the content, it is the raw body of the response.
+    pub async fn download_attachment(
+        &mut self,
+        todo_id: i64,
+        attachment_id: i64,
+    ) -> ApiResponse<()> {
+        self.send(TestRequest::get().uri(&format!(
+            "/api/v1/todos/{todo_id}/attachments/{attachment_id}"
+        )))
+        .await
+    }
+
+    pub async fn delete_attachment(
+        &mut self,
+        todo_id: i64,
+        attachment_id: i64,
+    ) -> ApiResponse<()> {
+        self.send(TestRequest::delete().uri(&format!(
+            "/api/v1/todos/{todo_id}/attachments/{attachment_id}"
+        )))
+        .await
+    }
+
+    pub async fn storage_usage(&mut self) -> ApiResponse<StorageUsage> {
+        self.send(TestRequest::get().uri("/api/v1/users/storage")).await
+    }
+
+    pub async fn export_todos(
+        &mut self,
+        format: TransferFormat,
+    ) -> ApiResponse<()> {
+        self.send(
+            TestRequest::get().uri(&format!(
+                "/api/v1/todos/export?format={}",
+                format.as_str()
+            )),
+        )
+        .await
+    }
+
+    pub async fn create_calendar_feed(&mut self) -> ApiResponse<CalendarFeed> {
+        self.send(TestRequest::post().uri("/api/v1/users/calendar")).await
+    }
+
+    pub async fn delete_calendar_feed(&mut self) -> ApiResponse<()> {
+        self.send(TestRequest::delete().uri("/api/v1/users/calendar")).await
+    }
+
+    /// Fetches the feed at `path`, which needs no login.
+    pub async fn calendar_feed(&mut self, path: &str) -> ApiResponse<()> {
+        self.send(TestRequest::get().uri(path)).await
+    }
+
+    pub async fn import_todos(
+        &mut self,
+        import: &ImportTodos,
+    ) -> ApiResponse<ImportReport> {
+        self.send(post("/api/v1/todos/import", import)).await
+    }
+
+    pub async fn lists(&mut self) -> ApiResponse<Vec<TodoList>> {
+        self.send(TestRequest::get().uri("/api/v1/lists")).await
+    }
+
+    pub async fn list(&mut self, list_id: i64) -> ApiResponse<TodoList> {
+        self.send(TestRequest::get().uri(&format!("/api/v1/lists/{list_id}")))
+            .await
+    }
+
+    pub async fn create_list(&mut self, name: &str) -> ApiResponse<TodoList> {
+        self.send(post("/api/v1/lists", &CreateList { name: name.to_string() }))
+            .await
+    }
+
+    pub async fn rename_list(
+        &mut self,
+        list_id: i64,
+        name: &str,
+    ) -> ApiResponse<()> {
+        self.send(
+            TestRequest::put()
+                .uri(&format!("/api/v1/lists/{list_id}"))
+                .set_json(UpdateList { name: name.to_string() }),
+        )
+        .await
+    }
+
+    pub async fn delete_list(&mut self, list_id: i64) -> ApiResponse<()> {
+        self.send(
+            TestRequest::delete().uri(&format!("/api/v1/lists/{list_id}")),
+        )
+        .await
+    }
+
+    pub async fn list_members(
+        &mut self,
+        list_id: i64,
+    ) -> ApiResponse<Vec<ListMember>> {
+        self.send(
+            TestRequest::get().uri(&format!("/api/v1/lists/{list_id}/members")),
+        )
+        .await
+    }
+
+    pub async fn set_list_permission(
+        &mut self,
+        list_id: i64,
+        user_id: i64,
+        permission: ListPermission,
+    ) -> ApiResponse<()> {
+        self.send(
+            TestRequest::put()
+                .uri(&format!("/api/v1/lists/{list_id}/members/{user_id}"))
+                .set_json(SetPermission { permission }),
+        )
+        .await
+    }
+
+    pub async fn remove_list_member(
+        &mut self,
+        list_id: i64,
+        user_id: i64,
+    ) -> ApiResponse<()> {
+        self.send(
+            TestRequest::delete()
+                .uri(&format!("/api/v1/lists/{list_id}/members/{user_id}")),
+        )
+        .await
+    }
+
+    pub async fn invite(
+        &mut self,
+        list_id: i64,
+        email: &str,
+        permission: ListPermission,
+    ) -> ApiResponse<()> {
+        self.send(post(
+            &format!("/api/v1/lists/{list_id}/invitations"),
+            &InviteMember { email: email.to_string(), permission },
+        ))
+        .await
+    }
+
+    pub async fn invitations(&mut self) -> ApiResponse<Vec<ListInvitation>> {
+        self.send(TestRequest::get().uri("/api/v1/lists/invitations")).await
+    }
+
+    pub async fn accept_invitation(
+        &mut self,
+        invitation_id: i64,
+    ) -> ApiResponse<()> {
+        self.send(
+            TestRequest::post().uri(&format!(
+                "/api/v1/lists/invitations/{invitation_id}/accept"
+            )),
+        )
+        .await
+    }
+
+    pub async fn decline_invitation(
+        &mut self,
+        invitation_id: i64,
+    ) -> ApiResponse<()> {
+        self.send(
+            TestRequest::post().uri(&format!(
+                "/api/v1/lists/invitations/{invitation_id}/decline"
+            )),
+        )
+        .await
+    }
+}
+
+fn post(uri: &str, body: &impl Serialize) -> TestRequest {
+    TestRequest::post().uri(uri).set_json(body)
+}
+
+/// A response whose body deserializes to `T` if the request succeeded.
+#[derive(Debug)]
+pub struct ApiResponse<T> {
+    pub status: StatusCode,
+    pub content_type: Option<String>,
+    pub headers: HeaderMap,
+    pub body: Bytes,
+    response: PhantomData<T>,
+}
+
+impl<T: DeserializeOwned> ApiResponse<T> {
+    /// Returns the deserialized body and panics with the status and body if
+    /// the request failed.
+    #[track_caller]
+    pub fn ok(&self) -> T {
+        assert!(
+            self.status.is_success(),
+            "expected a successful response, got {}: {}",
+            self.status,
+            self.text()
+        );
+
+        // handlers without a body answer with an empty one instead of `null`
+        let body: &[u8] =
+            if self.body.is_empty() { b"null" } else { &self.body };
+        serde_json::from_slice(body).unwrap_or_else(|e| {
+            panic!("failed to deserialize `{}`: {e}", self.text())
+        })
+    }
+
+    /// Returns the message of an error that the api reported with `status`.
+    #[track_caller]
+    pub fn err(&self, status: StatusCode) -> String {
+        assert_eq!(
+            (self.status, self.content_type.as_deref()),
+            (status, Some(error::CONTENT_TYPE)),
+            "unexpected response: {}",
+            self.text()
+        );
+
+        self.text()
+    }
+
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.body).into_owned()
+    }
+
+    /// Parses the header `name`, if the response has one.
+    pub fn header<H: FromStr>(&self, name: &str) -> Option<H> {
+        self.headers.get(name)?.to_str().ok()?.parse().ok()
+    }
+}
@@ -1,9 +1,17 @@
 use std::borrow::Cow;
 
 use actix_http::StatusCode;
-use actix_web::{http::header::ContentType, HttpResponse, ResponseError};
+use actix_web::{
+    http::header::{ContentType, RETRY_AFTER},
+    HttpResponse, ResponseError,
+};
 use color_eyre::eyre;
 
+/// Content type of the messages of [`Error::External`] and
+/// [`Error::TooManyRequests`], which tells them apart from the plain text
+/// errors of actix.
+pub const CONTENT_TYPE: &str = "ExternalError";
+
 #[derive(Debug, derive_more::Display, derive_more::Error)]
 pub enum Error {
     #[display(fmt = "Error {}: {}", _0, _1)]
@@ -11,6 +19,10 @@ pub enum Error {
 
     #[display(fmt = "{}", _0)]
     Internal(#[error(not(source))] eyre::Error),
+
+    /// The client has to wait `retry_after` seconds before trying again.
+    #[display(fmt = "Error 429: {}", message)]
+    TooManyRequests { message: Cow<'static, str>, retry_after: u64 },
 }
 
 // TODO register custom errorhandler middleware to always respond with
@@ -20,6 +32,7 @@ impl ResponseError for Error {
         match self {
             Error::External(status_code, _) => *status_code,
             Error::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            Error::TooManyRequests { .. } => StatusCode::TOO_MANY_REQUESTS,
         }
     }
 
@@ -32,7 +45,7 @@ impl ResponseError for Error {
                     // produced by this very Error type and
                     // internal errors that are produced by actix that we need
                     // to map to an Error::Internal later
-                    .content_type("ExternalError")
+                    .content_type(CONTENT_TYPE)
                     .body::<String>(error_message.to_string())
             }
             Error::Internal(error) => {
@@ -45,6 +58,12 @@ impl ResponseError for Error {
                         StatusCode::INTERNAL_SERVER_ERROR
                     ))
             }
+            Error::TooManyRequests { message, retry_after } => {
+                HttpResponse::build(StatusCode::TOO_MANY_REQUESTS)
+                    .content_type(CONTENT_TYPE)
+                    .insert_header((RETRY_AFTER, retry_after.to_string()))
+                    .body::<String>(message.to_string())
+            }
         }
     }
 }
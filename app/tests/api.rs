@@ -0,0 +1,1753 @@
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use actix_http::StatusCode;
+use actix_web::test::TestRequest;
+use app::{
+    blob::MemoryBlobStore,
+    controllers::{
+        api::attachment::AttachmentLimits,
+        common::{
+            login_throttle::LoginThrottle,
+            rate_limit::{RateLimit, RateLimiter, RouteRateLimit},
+            totp,
+        },
+    },
+    mail::{Mail, MemoryMailer},
+    repository::{
+        audit::AuditContext, memory::MemoryBackend, session::SessionRepository,
+        user::UserRepository, Backend,
+    },
+    server::AppSettings,
+    test_support::{self, ApiResponse},
+};
+use chrono::Utc;
+use shared::models::{
+    audit::{AuditAction, AuditEntity, SecurityEventKind},
+    list::ListPermission,
+    todo::{CreateTodo, TodoAction, UpdateTodo},
+    transfer::{ExportedTodo, ImportTodos, TransferFormat},
+    user::{CreateAccessToken, Role, TokenScope, UpdateUser},
+};
+use webauthn_authenticator_rs::{
+    softpasskey::SoftPasskey, WebauthnAuthenticator,
+};
+use webauthn_rs::prelude::Url;
+
+const NOT_LOGGED_IN: &str =
+    "You do not seem to be logged in. Please log in first.";
+
+/// Polls `done` until the work in the background is finished, fails the test
+/// if that takes too long.
+async fn wait_until(mut done: impl FnMut() -> bool) {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while !done() {
+        assert!(
+            Instant::now() < deadline,
+            "timed out waiting for the background"
+        );
+        actix_rt::time::sleep(Duration::from_millis(10)).await;
+    }
+}
+
+/// Waits until `count` mails with `subject` were sent in the background and
+/// returns them.
+async fn sent_mails(
+    mailer: &MemoryMailer,
+    subject: &str,
+    count: usize,
+) -> Vec<Mail> {
+    let mails = || {
+        let mails = mailer.mails().into_iter();
+        mails.filter(|mail| mail.subject == subject).collect::<Vec<_>>()
+    };
+    wait_until(|| mails().len() >= count).await;
+
+    mails()
+}
+
+/// Extracts the token from a mail.
+fn token(mail: &Mail) -> &str {
+    mail.body
+        .lines()
+        .find(|line| line.len() == 64)
+        .unwrap_or_else(|| panic!("no token in {mail:?}"))
+}
+
+fn create_todo(title: &str) -> CreateTodo {
+    CreateTodo {
+        title: title.to_string(),
+        description: String::new(),
+        list_id: None,
+        due_at: None,
+    }
+}
+
+fn create_access_token(name: &str, scopes: &[TokenScope]) -> CreateAccessToken {
+    CreateAccessToken {
+        name: name.to_string(),
+        scopes: scopes.to_vec(),
+        expires_at: None,
+    }
+}
+
+#[actix_rt::test]
+async fn register_login_crud_logout() {
+    let backend = MemoryBackend::new();
+    let mut client = test_support::client(&backend).await;
+
+    client.register("Jane", "jane@example.com", "secret").await.ok();
+    assert!(!client.has_session());
+    client.login("jane@example.com", "secret").await.ok();
+    assert!(client.has_session());
+
+    let user = client.user().await.ok();
+    assert_eq!(user.email, "jane@example.com");
+    let stored = backend.user_repository().get_session_user(&user.id).await;
+    assert_ne!(stored.0.unwrap().password, "secret", "it has to be hashed");
+
+    client.create_todo(&create_todo("Water the plants")).await.ok();
+    let todos = client.todos().await.ok();
+    assert_eq!(todos.len(), 1);
+    assert_eq!(todos[0].owner, user.id);
+
+    client
+        .update_todo(&UpdateTodo {
+            id: todos[0].id,
+            title: None,
+            description: Some("Twice a week".to_string()),
+            is_done: Some(true),
+            due_at: None,
+        })
+        .await
+        .ok();
+    let todo = client.todo(todos[0].id).await.ok();
+    assert_eq!(todo.title, "Water the plants");
+    assert_eq!(todo.description, "Twice a week");
+    assert!(todo.is_done);
+
+    client.delete_todo(todo.id).await.ok();
+    assert!(client.todos().await.ok().is_empty());
+
+    client.logout().await.ok();
+    assert!(!client.has_session());
+    assert_eq!(
+        client.user().await.err(StatusCode::UNAUTHORIZED),
+        NOT_LOGGED_IN
+    );
+    let sessions = backend.session_repository().list_sessions(None).await;
+    assert!(sessions.unwrap().is_empty(), "logout has to purge the session");
+}
+
+#[actix_rt::test]
+async fn protected_routes_require_a_session() {
+    let backend = MemoryBackend::new();
+    let mut client = test_support::client(&backend).await;
+    let update_todo = UpdateTodo {
+        id: 1,
+        title: None,
+        description: None,
+        is_done: None,
+        due_at: None,
+    };
+
+    let responses = [
+        client.update_user(&UpdateUser::default()).await,
+        client.delete_user().await,
+        client.logout().await,
+        client.create_todo(&create_todo("Sneaky")).await,
+        client.update_todo(&update_todo).await,
+        client.delete_todo(1).await,
+    ];
+    for response in responses {
+        assert_eq!(response.err(StatusCode::UNAUTHORIZED), NOT_LOGGED_IN);
+    }
+    assert_eq!(
+        client.user().await.err(StatusCode::UNAUTHORIZED),
+        NOT_LOGGED_IN
+    );
+    assert_eq!(
+        client.todos().await.err(StatusCode::UNAUTHORIZED),
+        NOT_LOGGED_IN
+    );
+    assert_eq!(
+        client.todo(1).await.err(StatusCode::UNAUTHORIZED),
+        NOT_LOGGED_IN
+    );
+}
+
+#[actix_rt::test]
+async fn invalid_credentials_are_rejected() {
+    let backend = MemoryBackend::new();
+    let mut client = test_support::client(&backend).await;
+    client.register("Jane", "jane@example.com", "secret").await.ok();
+
+    let wrong_password = client.login("jane@example.com", "guess").await;
+    let unknown_email = client.login("john@example.com", "secret").await;
+
+    for response in [wrong_password, unknown_email] {
+        assert_eq!(
+            response.err(StatusCode::UNAUTHORIZED),
+            "Invalid email or password provided. Try again."
+        );
+    }
+    assert!(!client.has_session());
+}
+
+#[actix_rt::test]
+async fn failed_logins_are_throttled() {
+    let backend = MemoryBackend::new();
+    let settings = AppSettings {
+        login_throttle: LoginThrottle {
+            free_attempts: 2,
+            base_delay: Duration::from_secs(60),
+            ..Default::default()
+        },
+        ..test_support::settings()
+    };
+    let mut client = test_support::client_with(&backend, settings).await;
+    client.register("Jane", "jane@example.com", "secret").await.ok();
+
+    for email in ["jane@example.com", "john@example.com"] {
+        for _ in 0..3 {
+            client.login(email, "guess").await.err(StatusCode::UNAUTHORIZED);
+        }
+
+        // even the right password is refused until the delay has passed
+        let response = client.login(email, "secret").await;
+        assert_eq!(
+            response.err(StatusCode::TOO_MANY_REQUESTS),
+            "Too many failed logins. Try again later."
+        );
+        assert_eq!(response.header("retry-after"), Some(60));
+    }
+    assert!(!client.has_session());
+}
+
+#[actix_rt::test]
+async fn parallel_guesses_are_throttled() {
+    let backend = MemoryBackend::new();
+    let settings = AppSettings {
+        login_throttle: LoginThrottle {
+            free_attempts: 2,
+            base_delay: Duration::from_secs(60),
+            ..Default::default()
+        },
+        ..test_support::settings()
+    };
+    let mut clients = Vec::new();
+    for _ in 0..6 {
+        clients
+            .push(test_support::client_with(&backend, settings.clone()).await);
+    }
+    clients[0].register("Jane", "jane@example.com", "secret").await.ok();
+
+    let responses = futures_util::future::join_all(
+        clients
+            .iter_mut()
+            .map(|client| client.login("jane@example.com", "guess")),
+    )
+    .await;
+
+    // only the guesses the throttle allows one after the other are verified
+    let mut statuses =
+        responses.iter().map(|response| response.status).collect::<Vec<_>>();
+    statuses.sort();
+    assert_eq!(
+        statuses,
+        [[StatusCode::UNAUTHORIZED; 3], [StatusCode::TOO_MANY_REQUESTS; 3]]
+            .concat()
+    );
+}
+
+#[actix_rt::test]
+async fn failed_logins_are_counted_per_ip() {
+    let backend = MemoryBackend::new();
+    let settings = AppSettings {
+        login_throttle: LoginThrottle {
+            base_delay: Duration::from_secs(60),
+            ..Default::default()
+        },
+        ..test_support::settings()
+    };
+    let mut client = test_support::client_with(&backend, settings).await;
+    client.set_peer_addr(([192, 0, 2, 1], 4711).into());
+    client.register("Jane", "jane@example.com", "secret").await.ok();
+
+    for i in 0..4 {
+        client
+            .login(&format!("user{i}@example.com"), "guess")
+            .await
+            .err(StatusCode::UNAUTHORIZED);
+    }
+    client
+        .login("jane@example.com", "secret")
+        .await
+        .err(StatusCode::TOO_MANY_REQUESTS);
+
+    let mut other = test_support::client(&backend).await;
+    other.set_peer_addr(([192, 0, 2, 2], 4711).into());
+    other.login("jane@example.com", "secret").await.ok();
+}
+
+#[actix_rt::test]
+async fn successful_logins_reset_the_failures() {
+    let backend = MemoryBackend::new();
+    let mut client = test_support::client(&backend).await;
+    client.register("Jane", "jane@example.com", "secret").await.ok();
+
+    for _ in 0..2 {
+        for _ in 0..3 {
+            client
+                .login("jane@example.com", "guess")
+                .await
+                .err(StatusCode::UNAUTHORIZED);
+        }
+        client.login("jane@example.com", "secret").await.ok();
+    }
+}
+
+#[actix_rt::test]
+async fn requests_are_rate_limited_per_user() {
+    let backend = MemoryBackend::new();
+    let settings = AppSettings {
+        rate_limiter: RateLimiter::new(
+            RateLimit { burst: 3, per_minute: 1 },
+            vec![RouteRateLimit {
+                path: "/api/v1/users/register".to_string(),
+                method: None,
+                limit: RateLimit { burst: 2, per_minute: 1 },
+            }],
+        ),
+        ..test_support::settings()
+    };
+    let mut jane = test_support::client_with(&backend, settings.clone()).await;
+    let mut john = test_support::client_with(&backend, settings).await;
+    jane.register("Jane", "jane@example.com", "secret").await.ok();
+    john.register("John", "john@example.com", "secret").await.ok();
+    // both are logged out and share the register bucket of their ip
+    assert_eq!(
+        john.register("Jim", "jim@example.com", "secret")
+            .await
+            .err(StatusCode::TOO_MANY_REQUESTS),
+        "Too many requests. Try again later."
+    );
+
+    jane.login("jane@example.com", "secret").await.ok();
+    let response = jane.todos().await;
+    response.ok();
+    assert_eq!(response.header("x-ratelimit-limit"), Some(3));
+    assert_eq!(response.header("x-ratelimit-remaining"), Some(2));
+    jane.todos().await.ok();
+    jane.todos().await.ok();
+    let response = jane.todos().await;
+    response.err(StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(response.header("x-ratelimit-remaining"), Some(0));
+    assert_eq!(response.header("retry-after"), Some(60));
+
+    // john logs in from the same ip, but gets a bucket of their own
+    john.login("john@example.com", "secret").await.ok();
+    john.todos().await.ok();
+}
+
+#[actix_rt::test]
+async fn access_tokens_are_rate_limited_per_user() {
+    let backend = MemoryBackend::new();
+    let settings = AppSettings {
+        rate_limiter: RateLimiter::new(
+            RateLimit { burst: 2, per_minute: 1 },
+            // keeps the logins and tokens out of the buckets of the todos
+            vec![RouteRateLimit {
+                path: "/api/v1/users".to_string(),
+                method: None,
+                limit: RateLimit { burst: 10, per_minute: 1 },
+            }],
+        ),
+        ..test_support::settings()
+    };
+    let mut jane = test_support::client_with(&backend, settings.clone()).await;
+    let mut john = test_support::client_with(&backend, settings).await;
+    for (client, name) in [(&mut jane, "jane"), (&mut john, "john")] {
+        let email = format!("{name}@example.com");
+        client.register(name, &email, "secret").await.ok();
+        client.login(&email, "secret").await.ok();
+        let token = client
+            .create_access_token(&create_access_token(
+                "Script",
+                &[TokenScope::ReadTodos],
+            ))
+            .await
+            .ok()
+            .token;
+        client.set_bearer(Some(&token));
+    }
+
+    // both scripts share the ip, but not their buckets
+    jane.todos().await.ok();
+    jane.todos().await.ok();
+    jane.todos().await.err(StatusCode::TOO_MANY_REQUESTS);
+    john.todos().await.ok();
+
+    // the token counts against the user, like their session
+    jane.set_bearer(None);
+    jane.todos().await.err(StatusCode::TOO_MANY_REQUESTS);
+}
+
+#[actix_rt::test]
+async fn registering_a_taken_email_mails_its_owner() {
+    let backend = MemoryBackend::new();
+    let mailer = Arc::new(MemoryMailer::default());
+    let settings =
+        AppSettings { mailer: mailer.clone(), ..test_support::settings() };
+    let mut client = test_support::client_with(&backend, settings).await;
+
+    let first = client.register("Jane", "jane@example.com", "secret").await;
+    let second = client.register("Eve", "jane@example.com", "guess").await;
+    assert_eq!((first.status, first.body), (second.status, second.body));
+
+    let mails = sent_mails(&mailer, "Your lentos account", 1).await;
+    assert_eq!(mails.len(), 1);
+    assert_eq!(mails[0].to, "jane@example.com");
+    assert!(mails[0].body.starts_with("Hi Jane,"));
+
+    client
+        .login("jane@example.com", "guess")
+        .await
+        .err(StatusCode::UNAUTHORIZED);
+    client.login("jane@example.com", "secret").await.ok();
+}
+
+#[actix_rt::test]
+async fn emails_are_verified_with_the_mailed_token() {
+    let backend = MemoryBackend::new();
+    let mailer = Arc::new(MemoryMailer::default());
+    let settings =
+        AppSettings { mailer: mailer.clone(), ..test_support::settings() };
+    let mut client = test_support::client_with(&backend, settings).await;
+    client.register("Jane", "jane@example.com", "secret").await.ok();
+    client.login("jane@example.com", "secret").await.ok();
+    assert_eq!(client.user().await.ok().email_verified_at, None);
+
+    let mails = sent_mails(&mailer, "Verify your email address", 1).await;
+    assert_eq!(mails.len(), 1);
+    let first_token = token(&mails[0]).to_string();
+    client.request_email_verification().await.ok();
+    let mails = sent_mails(&mailer, "Verify your email address", 2).await;
+    let token = token(&mails[1]);
+
+    assert_eq!(
+        client.verify_email(&first_token).await.err(StatusCode::BAD_REQUEST),
+        "The token is invalid or has expired.",
+        "a new token replaces the earlier ones"
+    );
+    client.verify_email(token).await.ok();
+    assert!(client.user().await.ok().email_verified_at.is_some());
+    client.verify_email(token).await.err(StatusCode::BAD_REQUEST);
+
+    // changing the email requires a new verification
+    client
+        .update_user(&UpdateUser {
+            email: Some("janet@example.com".to_string()),
+            ..Default::default()
+        })
+        .await
+        .ok();
+    assert_eq!(client.user().await.ok().email_verified_at, None);
+}
+
+#[actix_rt::test]
+async fn passwords_are_reset_with_the_mailed_token() {
+    let backend = MemoryBackend::new();
+    let mailer = Arc::new(MemoryMailer::default());
+    let settings =
+        AppSettings { mailer: mailer.clone(), ..test_support::settings() };
+    let mut jane = test_support::client_with(&backend, settings.clone()).await;
+    let mut other = test_support::client_with(&backend, settings).await;
+    jane.register("Jane", "jane@example.com", "secret").await.ok();
+    jane.login("jane@example.com", "secret").await.ok();
+    let created = jane
+        .create_access_token(&create_access_token(
+            "ci",
+            &[TokenScope::ReadTodos],
+        ))
+        .await
+        .ok();
+    let mut script = test_support::client(&backend).await;
+    script.set_bearer(Some(&created.token));
+    script.todos().await.ok();
+
+    // unknown emails get the same answer, but no mail
+    other.request_password_reset("john@example.com").await.ok();
+    other.request_password_reset("jane@example.com").await.ok();
+    let mails = sent_mails(&mailer, "Reset your password", 1).await;
+    assert_eq!(mails.len(), 1);
+    assert_eq!(mails[0].to, "jane@example.com");
+
+    assert_eq!(
+        other
+            .reset_password("guess", "hacked")
+            .await
+            .err(StatusCode::BAD_REQUEST),
+        "The token is invalid or has expired."
+    );
+    other.reset_password(token(&mails[0]), "new secret").await.ok();
+    other
+        .reset_password(token(&mails[0]), "again")
+        .await
+        .err(StatusCode::BAD_REQUEST);
+
+    jane.user().await.err(StatusCode::UNAUTHORIZED);
+    script.todos().await.err(StatusCode::UNAUTHORIZED);
+    other
+        .login("jane@example.com", "secret")
+        .await
+        .err(StatusCode::UNAUTHORIZED);
+    other.login("jane@example.com", "new secret").await.ok();
+}
+
+#[actix_rt::test]
+async fn passwords_are_changed_with_the_current_one() {
+    let backend = MemoryBackend::new();
+    let mut jane = test_support::client(&backend).await;
+    let mut laptop = test_support::client(&backend).await;
+    jane.register("Jane", "jane@example.com", "secret").await.ok();
+    jane.login("jane@example.com", "secret").await.ok();
+    laptop.login("jane@example.com", "secret").await.ok();
+    let created = jane
+        .create_access_token(&create_access_token(
+            "ci",
+            &[TokenScope::ReadTodos],
+        ))
+        .await
+        .ok();
+    let mut script = test_support::client(&backend).await;
+    script.set_bearer(Some(&created.token));
+
+    let change = |current_password: &str| UpdateUser {
+        password: Some("new secret".to_string()),
+        current_password: Some(current_password.to_string()),
+        ..Default::default()
+    };
+    assert_eq!(
+        jane.update_user(&change("guess")).await.err(StatusCode::FORBIDDEN),
+        "The current password is wrong. Try again."
+    );
+    jane.update_user(&UpdateUser { current_password: None, ..change("") })
+        .await
+        .err(StatusCode::FORBIDDEN);
+    laptop.user().await.ok();
+
+    jane.update_user(&change("secret")).await.ok();
+    // the other sessions and the tokens are revoked, this one goes on
+    jane.user().await.ok();
+    laptop.user().await.err(StatusCode::UNAUTHORIZED);
+    script.todos().await.err(StatusCode::UNAUTHORIZED);
+    let events = jane.security_events().await.ok();
+    assert!(events
+        .iter()
+        .any(|event| event.kind == SecurityEventKind::AccessTokensRevoked));
+
+    laptop
+        .login("jane@example.com", "secret")
+        .await
+        .err(StatusCode::UNAUTHORIZED);
+    laptop.login("jane@example.com", "new secret").await.ok();
+}
+
+#[actix_rt::test]
+async fn logins_with_totp_require_a_code() {
+    let backend = MemoryBackend::new();
+    let mut jane = test_support::client(&backend).await;
+    jane.register("Jane", "jane@example.com", "secret").await.ok();
+    jane.login("jane@example.com", "secret").await.ok();
+
+    let enrollment = jane.enroll_totp().await.ok();
+    assert!(enrollment
+        .otpauth_uri
+        .starts_with("otpauth://totp/lentos:jane@example.com?secret="));
+    assert!(enrollment.qr_code_svg.contains("<svg"));
+    let step = totp::step(Utc::now());
+    let code = |step| totp::code(&enrollment.secret, step).unwrap();
+
+    jane.confirm_totp("000000").await.err(StatusCode::BAD_REQUEST);
+    let recovery_codes = jane.confirm_totp(&code(step)).await.ok().codes;
+    assert_eq!(recovery_codes.len(), 10);
+    jane.enroll_totp().await.err(StatusCode::CONFLICT);
+    jane.logout().await.ok();
+
+    let mut other = test_support::client(&backend).await;
+    other.login_totp(&code(step + 1)).await.err(StatusCode::UNAUTHORIZED);
+    assert!(
+        jane.login("jane@example.com", "secret").await.ok().two_factor_required
+    );
+    jane.user().await.err(StatusCode::UNAUTHORIZED);
+
+    // the code of the confirmation must not be accepted again
+    assert_eq!(
+        jane.login_totp(&code(step)).await.err(StatusCode::UNAUTHORIZED),
+        "Invalid code provided. Try again."
+    );
+    jane.login_totp(&code(step + 1)).await.ok();
+    assert_eq!(jane.user().await.ok().email, "jane@example.com");
+
+    jane.logout().await.ok();
+    jane.login("jane@example.com", "secret").await.ok();
+    jane.login_totp(&code(step + 1)).await.err(StatusCode::UNAUTHORIZED);
+    jane.login_totp(&recovery_codes[0].to_uppercase()).await.ok();
+
+    jane.logout().await.ok();
+    jane.login("jane@example.com", "secret").await.ok();
+    jane.login_totp(&recovery_codes[0]).await.err(StatusCode::UNAUTHORIZED);
+    jane.login_totp(&recovery_codes[1]).await.ok();
+
+    jane.disable_totp(&recovery_codes[1]).await.err(StatusCode::BAD_REQUEST);
+    jane.disable_totp(&recovery_codes[2]).await.ok();
+    jane.logout().await.ok();
+    let signed_in = jane.login("jane@example.com", "secret").await.ok();
+    assert!(!signed_in.two_factor_required);
+    jane.user().await.ok();
+}
+
+#[actix_rt::test]
+async fn invalid_totp_codes_are_throttled() {
+    let backend = MemoryBackend::new();
+    let settings = AppSettings {
+        login_throttle: LoginThrottle {
+            free_attempts: 2,
+            base_delay: Duration::from_secs(60),
+            ..Default::default()
+        },
+        ..test_support::settings()
+    };
+    let mut jane = test_support::client_with(&backend, settings).await;
+    jane.register("Jane", "jane@example.com", "secret").await.ok();
+    jane.login("jane@example.com", "secret").await.ok();
+    let secret = jane.enroll_totp().await.ok().secret;
+    let step = totp::step(Utc::now());
+    jane.confirm_totp(&totp::code(&secret, step).unwrap()).await.ok();
+    jane.logout().await.ok();
+
+    jane.login("jane@example.com", "secret").await.ok();
+    for _ in 0..3 {
+        jane.login_totp("guess").await.err(StatusCode::UNAUTHORIZED);
+    }
+
+    // even the right code is refused until the delay has passed
+    let code = totp::code(&secret, step + 1).unwrap();
+    let response = jane.login_totp(&code).await;
+    response.err(StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(response.header("retry-after"), Some(60));
+    jane.user().await.err(StatusCode::UNAUTHORIZED);
+}
+
+#[actix_rt::test]
+async fn logins_with_passkeys() {
+    let backend = MemoryBackend::new();
+    let origin = Url::parse(test_support::ORIGIN).unwrap();
+    let mut authenticator = WebauthnAuthenticator::new(SoftPasskey::new(true));
+    let mut jane = test_support::client(&backend).await;
+    jane.register("Jane", "jane@example.com", "secret").await.ok();
+    jane.start_passkey_login("jane@example.com")
+        .await
+        .err(StatusCode::UNAUTHORIZED);
+    jane.login("jane@example.com", "secret").await.ok();
+
+    jane.start_passkey_registration(" ").await.err(StatusCode::BAD_REQUEST);
+    let challenge = jane.start_passkey_registration("Laptop").await.ok();
+    let credential =
+        authenticator.do_registration(origin.clone(), challenge).unwrap();
+    let passkey = jane.finish_passkey_registration(&credential).await.ok();
+    assert_eq!(passkey.name, "Laptop");
+    assert!(passkey.last_used_at.is_none());
+    // every challenge can only be answered once
+    jane.finish_passkey_registration(&credential)
+        .await
+        .err(StatusCode::BAD_REQUEST);
+    jane.logout().await.ok();
+
+    let mut other = test_support::client(&backend).await;
+    other
+        .start_passkey_login("nobody@example.com")
+        .await
+        .err(StatusCode::UNAUTHORIZED);
+    let challenge = other.start_passkey_login("jane@example.com").await.ok();
+    let credential =
+        authenticator.do_authentication(origin.clone(), challenge).unwrap();
+    other.finish_passkey_login(&credential).await.ok();
+    assert_eq!(other.user().await.ok().email, "jane@example.com");
+    let passkeys = other.passkeys().await.ok();
+    assert_eq!(passkeys.len(), 1);
+    assert!(passkeys[0].last_used_at.is_some());
+
+    jane.finish_passkey_login(&credential).await.err(StatusCode::UNAUTHORIZED);
+    jane.user().await.err(StatusCode::UNAUTHORIZED);
+
+    let mut john = test_support::client(&backend).await;
+    john.register("John", "john@example.com", "secret").await.ok();
+    john.login("john@example.com", "secret").await.ok();
+    john.delete_passkey(passkey.id).await.err(StatusCode::NOT_FOUND);
+    other.delete_passkey(passkey.id).await.ok();
+    assert!(other.passkeys().await.ok().is_empty());
+    other.delete_passkey(passkey.id).await.err(StatusCode::NOT_FOUND);
+}
+
+#[actix_rt::test]
+async fn todos_of_other_users_are_forbidden() {
+    let backend = MemoryBackend::new();
+    let mut jane = test_support::client(&backend).await;
+    let mut john = test_support::client(&backend).await;
+    jane.register("Jane", "jane@example.com", "secret").await.ok();
+    jane.login("jane@example.com", "secret").await.ok();
+    john.register("John", "john@example.com", "secret").await.ok();
+    john.login("john@example.com", "secret").await.ok();
+
+    jane.create_todo(&create_todo("Jane's todo")).await.ok();
+    let todo = jane.todos().await.ok().remove(0);
+
+    assert!(john.todos().await.ok().is_empty());
+    assert_eq!(
+        john.todo(todo.id).await.err(StatusCode::FORBIDDEN),
+        "You have no permission to receive this Todo"
+    );
+    assert_eq!(
+        john.update_todo(&UpdateTodo {
+            id: todo.id,
+            title: Some("John's todo".to_string()),
+            description: None,
+            is_done: None,
+            due_at: None,
+        })
+        .await
+        .err(StatusCode::FORBIDDEN),
+        "You have no permission to update this Todo"
+    );
+    assert_eq!(
+        john.todo(todo.id + 1).await.err(StatusCode::NOT_FOUND),
+        "Todo was not found"
+    );
+
+    // deleting someone else's todo is silently ignored
+    john.delete_todo(todo.id).await.ok();
+    assert_eq!(jane.todo(todo.id).await.ok(), todo);
+}
+
+#[actix_rt::test]
+async fn access_tokens_are_limited_to_their_scopes() {
+    let backend = MemoryBackend::new();
+    let mut jane = test_support::client(&backend).await;
+    jane.register("Jane", "jane@example.com", "secret").await.ok();
+    jane.login("jane@example.com", "secret").await.ok();
+    jane.create_todo(&create_todo("Jane's todo")).await.ok();
+
+    let read = jane
+        .create_access_token(&create_access_token(
+            "ci",
+            &[TokenScope::ReadTodos],
+        ))
+        .await
+        .ok();
+    assert_eq!(read.access_token.scopes, vec![TokenScope::ReadTodos]);
+    assert!(read.access_token.last_used_at.is_none());
+
+    let mut script = test_support::client(&backend).await;
+    script.set_bearer(Some(&read.token));
+    assert_eq!(script.todos().await.ok().len(), 1);
+    script
+        .create_todo(&create_todo("Script's todo"))
+        .await
+        .err(StatusCode::FORBIDDEN);
+    script.user().await.err(StatusCode::FORBIDDEN);
+    // tokens never manage credentials, not even other tokens
+    script
+        .create_access_token(&create_access_token(
+            "escalated",
+            &[TokenScope::ManageAccount],
+        ))
+        .await
+        .err(StatusCode::FORBIDDEN);
+    script.enroll_totp().await.err(StatusCode::FORBIDDEN);
+    script.passkeys().await.err(StatusCode::FORBIDDEN);
+
+    let tokens = jane.access_tokens().await.ok();
+    assert_eq!(tokens.len(), 1);
+    assert!(tokens[0].last_used_at.is_some());
+
+    let write = jane
+        .create_access_token(&create_access_token(
+            "bot",
+            &[TokenScope::WriteTodos, TokenScope::ManageAccount],
+        ))
+        .await
+        .ok();
+    script.set_bearer(Some(&write.token));
+    script.create_todo(&create_todo("Bot's todo")).await.ok();
+    script.todos().await.err(StatusCode::FORBIDDEN);
+    assert_eq!(script.user().await.ok().email, "jane@example.com");
+    assert_eq!(jane.todos().await.ok().len(), 2);
+
+    // managing the account does not reach the credentials
+    for update_user in [
+        UpdateUser {
+            password: Some("hacked".to_string()),
+            ..Default::default()
+        },
+        UpdateUser {
+            email: Some("script@example.com".to_string()),
+            ..Default::default()
+        },
+    ] {
+        script.update_user(&update_user).await.err(StatusCode::FORBIDDEN);
+    }
+    script
+        .update_user(&UpdateUser {
+            name: Some("Janet".to_string()),
+            ..Default::default()
+        })
+        .await
+        .ok();
+    jane.login("jane@example.com", "secret").await.ok();
+}
+
+#[actix_rt::test]
+async fn invalid_access_tokens_are_rejected() {
+    let backend = MemoryBackend::new();
+    let mut jane = test_support::client(&backend).await;
+    jane.register("Jane", "jane@example.com", "secret").await.ok();
+    jane.login("jane@example.com", "secret").await.ok();
+
+    for create_token in [
+        create_access_token(" ", &[TokenScope::ReadTodos]),
+        create_access_token("ci", &[]),
+        CreateAccessToken {
+            expires_at: Some(Utc::now() - chrono::Duration::minutes(1)),
+            ..create_access_token("ci", &[TokenScope::ReadTodos])
+        },
+    ] {
+        jane.create_access_token(&create_token)
+            .await
+            .err(StatusCode::BAD_REQUEST);
+    }
+
+    let created = jane
+        .create_access_token(&create_access_token(
+            "ci",
+            &[TokenScope::ReadTodos],
+        ))
+        .await
+        .ok();
+    let mut script = test_support::client(&backend).await;
+    for token in ["lentos_pat_guessed", "guessed", ""] {
+        script.set_bearer(Some(token));
+        script.todos().await.err(StatusCode::UNAUTHORIZED);
+    }
+
+    // a bearer header is never answered with the session of the cookie
+    jane.set_bearer(Some("lentos_pat_guessed"));
+    jane.todos().await.err(StatusCode::UNAUTHORIZED);
+    jane.set_bearer(None);
+
+    script.set_bearer(Some(&created.token));
+    script.todos().await.ok();
+    let mut john = test_support::client(&backend).await;
+    john.register("John", "john@example.com", "secret").await.ok();
+    john.login("john@example.com", "secret").await.ok();
+    john.delete_access_token(created.access_token.id)
+        .await
+        .err(StatusCode::NOT_FOUND);
+    jane.delete_access_token(created.access_token.id).await.ok();
+    script.todos().await.err(StatusCode::UNAUTHORIZED);
+    assert!(jane.access_tokens().await.ok().is_empty());
+}
+
+#[actix_rt::test]
+async fn admin_api_requires_an_admin() {
+    let backend = MemoryBackend::new();
+    let mut admin = test_support::client(&backend).await;
+    let mut jane = test_support::client(&backend).await;
+    admin.register("Admin", "admin@example.com", "secret").await.ok();
+    jane.register("Jane", "jane@example.com", "secret").await.ok();
+    jane.login("jane@example.com", "secret").await.ok();
+
+    jane.admin_stats().await.err(StatusCode::FORBIDDEN);
+    let mut anonymous = test_support::client(&backend).await;
+    anonymous.admin_stats().await.err(StatusCode::UNAUTHORIZED);
+
+    let users = backend.user_repository();
+    let admin_id =
+        users.get_user_by_email("admin@example.com").await.0.unwrap().id;
+    users
+        .set_role(&admin_id, Role::Admin, &AuditContext::default())
+        .await
+        .0
+        .unwrap();
+    admin.login("admin@example.com", "secret").await.ok();
+    let stats = admin.admin_stats().await.ok();
+    assert_eq!((stats.users, stats.admins, stats.disabled_users), (2, 1, 0));
+
+    // no token scope covers the admin api
+    let created = admin
+        .create_access_token(&create_access_token(
+            "ci",
+            &[TokenScope::ReadTodos, TokenScope::ManageAccount],
+        ))
+        .await
+        .ok();
+    let mut script = test_support::client(&backend).await;
+    script.set_bearer(Some(&created.token));
+    script.admin_stats().await.err(StatusCode::FORBIDDEN);
+
+    let found = admin.admin_users("query=JANE").await.ok();
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].email, "jane@example.com");
+    assert_eq!(found[0].role, Role::User);
+    assert_eq!(admin.admin_users("").await.ok().len(), 2);
+    assert_eq!(admin.admin_users("offset=1&limit=1").await.ok().len(), 1);
+    admin.admin_users("limit=0").await.err(StatusCode::BAD_REQUEST);
+    admin.admin_user(found[0].id + 100).await.err(StatusCode::NOT_FOUND);
+
+    admin.set_role(found[0].id, Role::Admin).await.ok();
+    jane.admin_stats().await.ok();
+    admin.set_role(admin_id, Role::User).await.err(StatusCode::CONFLICT);
+    jane.set_role(admin_id, Role::User).await.ok();
+    admin.admin_stats().await.err(StatusCode::FORBIDDEN);
+}
+
+#[actix_rt::test]
+async fn disabled_users_are_locked_out() {
+    let backend = MemoryBackend::new();
+    let mut admin = test_support::client(&backend).await;
+    let mut jane = test_support::client(&backend).await;
+    admin.register("Admin", "admin@example.com", "secret").await.ok();
+    jane.register("Jane", "jane@example.com", "secret").await.ok();
+    let users = backend.user_repository();
+    let admin_id =
+        users.get_user_by_email("admin@example.com").await.0.unwrap().id;
+    users
+        .set_role(&admin_id, Role::Admin, &AuditContext::default())
+        .await
+        .0
+        .unwrap();
+    admin.login("admin@example.com", "secret").await.ok();
+
+    jane.login("jane@example.com", "secret").await.ok();
+    let jane_id = jane.user().await.ok().id;
+    let created = jane
+        .create_access_token(&create_access_token(
+            "ci",
+            &[TokenScope::ReadTodos],
+        ))
+        .await
+        .ok();
+    let mut script = test_support::client(&backend).await;
+    script.set_bearer(Some(&created.token));
+    script.todos().await.ok();
+
+    admin.disable_user(admin_id).await.err(StatusCode::CONFLICT);
+    admin.disable_user(jane_id).await.ok();
+    assert!(admin.admin_user(jane_id).await.ok().disabled_at.is_some());
+    assert_eq!(admin.admin_stats().await.ok().disabled_users, 1);
+
+    jane.todos().await.err(StatusCode::UNAUTHORIZED);
+    script.todos().await.err(StatusCode::FORBIDDEN);
+    // only told after the right password
+    jane.login("jane@example.com", "wrong").await.err(StatusCode::UNAUTHORIZED);
+    assert_eq!(
+        jane.login("jane@example.com", "secret")
+            .await
+            .err(StatusCode::FORBIDDEN),
+        "This account is disabled. Contact an administrator."
+    );
+    jane.todos().await.err(StatusCode::UNAUTHORIZED);
+
+    admin.enable_user(jane_id).await.ok();
+    script.todos().await.ok();
+    jane.login("jane@example.com", "secret").await.ok();
+    jane.todos().await.ok();
+}
+
+#[actix_rt::test]
+async fn admins_force_password_resets() {
+    let backend = MemoryBackend::new();
+    let mailer = Arc::new(MemoryMailer::default());
+    let settings =
+        AppSettings { mailer: mailer.clone(), ..test_support::settings() };
+    let mut admin = test_support::client_with(&backend, settings.clone()).await;
+    let mut jane = test_support::client_with(&backend, settings).await;
+    admin.register("Admin", "admin@example.com", "secret").await.ok();
+    jane.register("Jane", "jane@example.com", "secret").await.ok();
+    let users = backend.user_repository();
+    let admin_id =
+        users.get_user_by_email("admin@example.com").await.0.unwrap().id;
+    users
+        .set_role(&admin_id, Role::Admin, &AuditContext::default())
+        .await
+        .0
+        .unwrap();
+    admin.login("admin@example.com", "secret").await.ok();
+    jane.login("jane@example.com", "secret").await.ok();
+    let jane_id = jane.user().await.ok().id;
+    let created = jane
+        .create_access_token(&create_access_token(
+            "ci",
+            &[TokenScope::ReadTodos],
+        ))
+        .await
+        .ok();
+    let mut script = test_support::client(&backend).await;
+    script.set_bearer(Some(&created.token));
+
+    jane.force_password_reset(admin_id).await.err(StatusCode::FORBIDDEN);
+    admin.force_password_reset(jane_id).await.ok();
+
+    jane.user().await.err(StatusCode::UNAUTHORIZED);
+    script.todos().await.err(StatusCode::UNAUTHORIZED);
+    jane.login("jane@example.com", "secret")
+        .await
+        .err(StatusCode::UNAUTHORIZED);
+    let mails = sent_mails(&mailer, "Reset your password", 1).await;
+    assert_eq!(mails.len(), 1);
+    assert_eq!(mails[0].to, "jane@example.com");
+    jane.reset_password(token(&mails[0]), "new secret").await.ok();
+    jane.login("jane@example.com", "new secret").await.ok();
+}
+
+#[actix_rt::test]
+async fn lists_are_shared_by_invitation() {
+    let backend = MemoryBackend::new();
+    let mut jane = test_support::client(&backend).await;
+    let mut john = test_support::client(&backend).await;
+    jane.register("Jane", "jane@example.com", "secret").await.ok();
+    jane.login("jane@example.com", "secret").await.ok();
+    john.register("John", "john@example.com", "secret").await.ok();
+    john.login("john@example.com", "secret").await.ok();
+    let john_id = john.user().await.ok().id;
+
+    let list = jane.create_list("  Groceries ").await.ok();
+    assert_eq!(
+        (list.name.as_str(), list.permission),
+        ("Groceries", ListPermission::Admin)
+    );
+    jane.create_list("").await.err(StatusCode::BAD_REQUEST);
+    jane.create_todo(&CreateTodo {
+        list_id: Some(list.id),
+        ..create_todo("Milk")
+    })
+    .await
+    .ok();
+    let milk = jane.todos().await.ok().remove(0);
+    assert_eq!(milk.list_id, Some(list.id));
+
+    // lists are not revealed to non-members
+    john.list(list.id).await.err(StatusCode::NOT_FOUND);
+    john.todo(milk.id).await.err(StatusCode::FORBIDDEN);
+    assert!(john.todos().await.ok().is_empty());
+
+    // unknown emails and members are not revealed either
+    jane.invite(list.id, "nobody@example.com", ListPermission::Editor)
+        .await
+        .ok();
+    jane.invite(list.id, "jane@example.com", ListPermission::Editor).await.ok();
+    john.invite(list.id, "john@example.com", ListPermission::Admin)
+        .await
+        .err(StatusCode::NOT_FOUND);
+    jane.invite(list.id, "john@example.com", ListPermission::Viewer).await.ok();
+
+    let invitations = john.invitations().await.ok();
+    assert_eq!(invitations.len(), 1);
+    assert_eq!(
+        (invitations[0].list_name.as_str(), invitations[0].invited_by.as_str()),
+        ("Groceries", "Jane")
+    );
+    assert!(jane.invitations().await.ok().is_empty());
+    jane.accept_invitation(invitations[0].id).await.err(StatusCode::NOT_FOUND);
+    john.accept_invitation(invitations[0].id).await.ok();
+    assert!(john.invitations().await.ok().is_empty());
+    jane.invite(list.id, "john@example.com", ListPermission::Admin).await.ok();
+    assert!(john.invitations().await.ok().is_empty());
+
+    // viewers may only read
+    assert_eq!(john.todos().await.ok(), vec![milk.clone()]);
+    assert_eq!(john.todo(milk.id).await.ok(), milk);
+    assert_eq!(john.lists().await.ok()[0].permission, ListPermission::Viewer);
+    let done = UpdateTodo {
+        id: milk.id,
+        title: None,
+        description: None,
+        is_done: Some(true),
+        due_at: None,
+    };
+    john.update_todo(&done).await.err(StatusCode::FORBIDDEN);
+    john.create_todo(&CreateTodo {
+        list_id: Some(list.id),
+        ..create_todo("Beer")
+    })
+    .await
+    .err(StatusCode::FORBIDDEN);
+    john.delete_todo(milk.id).await.ok();
+    assert_eq!(jane.todos().await.ok().len(), 1);
+    john.rename_list(list.id, "Beer").await.err(StatusCode::FORBIDDEN);
+    john.invite(list.id, "john@example.com", ListPermission::Admin)
+        .await
+        .err(StatusCode::FORBIDDEN);
+
+    // editors may change the todos, but not the list
+    jane.set_list_permission(list.id, john_id, ListPermission::Editor)
+        .await
+        .ok();
+    john.update_todo(&done).await.ok();
+    john.create_todo(&CreateTodo {
+        list_id: Some(list.id),
+        ..create_todo("Beer")
+    })
+    .await
+    .ok();
+    assert_eq!(jane.todos().await.ok().len(), 2);
+    john.rename_list(list.id, "Beer").await.err(StatusCode::FORBIDDEN);
+    john.delete_list(list.id).await.err(StatusCode::FORBIDDEN);
+
+    // admins manage the members, but the owner stays
+    jane.set_list_permission(list.id, john_id, ListPermission::Admin)
+        .await
+        .ok();
+    john.rename_list(list.id, "Party").await.ok();
+    assert_eq!(jane.list(list.id).await.ok().name, "Party");
+    let jane_id = jane.user().await.ok().id;
+    john.set_list_permission(list.id, jane_id, ListPermission::Viewer)
+        .await
+        .err(StatusCode::CONFLICT);
+    john.remove_list_member(list.id, jane_id).await.err(StatusCode::CONFLICT);
+    john.delete_list(list.id).await.err(StatusCode::FORBIDDEN);
+    let members = jane.list_members(list.id).await.ok();
+    assert_eq!(
+        members.iter().map(|member| member.user_id).collect::<Vec<_>>(),
+        [jane_id, john_id]
+    );
+
+    // members may leave, which hides the todos again
+    john.remove_list_member(list.id, john_id).await.ok();
+    assert!(john.todos().await.ok().is_empty());
+    john.list(list.id).await.err(StatusCode::NOT_FOUND);
+
+    jane.delete_list(list.id).await.ok();
+    assert!(jane.todos().await.ok().is_empty());
+    assert!(jane.lists().await.ok().is_empty());
+}
+
+#[actix_rt::test]
+async fn list_invitations_are_declined() {
+    let backend = MemoryBackend::new();
+    let mut jane = test_support::client(&backend).await;
+    let mut john = test_support::client(&backend).await;
+    jane.register("Jane", "jane@example.com", "secret").await.ok();
+    jane.login("jane@example.com", "secret").await.ok();
+    john.register("John", "john@example.com", "secret").await.ok();
+    john.login("john@example.com", "secret").await.ok();
+
+    let list = jane.create_list("Chores").await.ok();
+    jane.invite(list.id, "john@example.com", ListPermission::Viewer).await.ok();
+    // inviting again replaces the invitation
+    jane.invite(list.id, "john@example.com", ListPermission::Editor).await.ok();
+    let invitations = john.invitations().await.ok();
+    assert_eq!(invitations.len(), 1);
+    assert_eq!(invitations[0].permission, ListPermission::Editor);
+
+    john.decline_invitation(invitations[0].id).await.ok();
+    john.accept_invitation(invitations[0].id).await.err(StatusCode::NOT_FOUND);
+    john.list(list.id).await.err(StatusCode::NOT_FOUND);
+
+    // only sessions manage memberships
+    let created = john
+        .create_access_token(&create_access_token(
+            "ci",
+            &[TokenScope::ReadTodos, TokenScope::WriteTodos],
+        ))
+        .await
+        .ok();
+    let mut script = test_support::client(&backend).await;
+    script.set_bearer(Some(&created.token));
+    script.invitations().await.err(StatusCode::FORBIDDEN);
+    script.lists().await.ok();
+}
+
+#[actix_rt::test]
+async fn todos_are_assigned_to_members() {
+    let backend = MemoryBackend::new();
+    let mut jane = test_support::client(&backend).await;
+    let mut john = test_support::client(&backend).await;
+    jane.register("Jane", "jane@example.com", "secret").await.ok();
+    jane.login("jane@example.com", "secret").await.ok();
+    john.register("John", "john@example.com", "secret").await.ok();
+    john.login("john@example.com", "secret").await.ok();
+    let jane_id = jane.user().await.ok().id;
+    let john_id = john.user().await.ok().id;
+
+    // personal todos can only be assigned to their owner
+    jane.create_todo(&create_todo("Water the plants")).await.ok();
+    let plants = jane.todos().await.ok().remove(0);
+    jane.assign_todo(plants.id, Some(john_id))
+        .await
+        .err(StatusCode::BAD_REQUEST);
+    john.assign_todo(plants.id, Some(john_id)).await.err(StatusCode::FORBIDDEN);
+    let plants = jane.assign_todo(plants.id, Some(jane_id)).await.ok();
+    assert_eq!(plants.assignee_id, Some(jane_id));
+
+    let list = jane.create_list("Groceries").await.ok();
+    jane.create_todo(&CreateTodo {
+        list_id: Some(list.id),
+        ..create_todo("Milk")
+    })
+    .await
+    .ok();
+    let milk = jane.todos().await.ok().remove(1);
+    jane.assign_todo(milk.id, Some(john_id)).await.err(StatusCode::BAD_REQUEST);
+    jane.invite(list.id, "john@example.com", ListPermission::Viewer).await.ok();
+    let invitations = john.invitations().await.ok();
+    john.accept_invitation(invitations[0].id).await.ok();
+
+    // viewers may be assigned, but not assign
+    let milk = jane.assign_todo(milk.id, Some(john_id)).await.ok();
+    assert_eq!(milk.assignee_id, Some(john_id));
+    john.assign_todo(milk.id, None).await.err(StatusCode::FORBIDDEN);
+    assert_eq!(john.assigned_todos().await.ok(), vec![milk.clone()]);
+    assert_eq!(jane.assigned_todos().await.ok(), vec![plants.clone()]);
+    assert_eq!(jane.todos().await.ok().len(), 2);
+
+    // assigning the same member again is no reassignment
+    jane.assign_todo(milk.id, Some(john_id)).await.ok();
+    let activity = john.todo_activity(milk.id).await.ok();
+    assert_eq!(activity.len(), 1);
+    assert_eq!(
+        (
+            activity[0].action,
+            activity[0].actor_id,
+            activity[0].previous_assignee_id,
+            activity[0].assignee_id
+        ),
+        (TodoAction::Assigned, Some(jane_id), None, Some(john_id))
+    );
+    john.todo_activity(plants.id).await.err(StatusCode::FORBIDDEN);
+
+    // leaving the list gives up the assignment
+    john.remove_list_member(list.id, john_id).await.ok();
+    assert_eq!(jane.todo(milk.id).await.ok().assignee_id, None);
+    let activity = jane.todo_activity(milk.id).await.ok();
+    assert_eq!(activity.len(), 2);
+    assert_eq!(
+        (
+            activity[1].action,
+            activity[1].actor_id,
+            activity[1].previous_assignee_id,
+            activity[1].assignee_id
+        ),
+        (TodoAction::Assigned, Some(john_id), Some(john_id), None)
+    );
+    let milk = jane.assign_todo(milk.id, Some(jane_id)).await.ok();
+    assert_eq!(milk.assignee_id, Some(jane_id));
+    assert_eq!(jane.todo_activity(milk.id).await.ok().len(), 3);
+}
+
+#[actix_rt::test]
+async fn todos_are_discussed_in_comments() {
+    let backend = MemoryBackend::new();
+    let mut jane = test_support::client(&backend).await;
+    let mut john = test_support::client(&backend).await;
+    jane.register("Jane", "jane@example.com", "secret").await.ok();
+    jane.login("jane@example.com", "secret").await.ok();
+    john.register("John", "john@example.com", "secret").await.ok();
+    john.login("john@example.com", "secret").await.ok();
+    let jane_id = jane.user().await.ok().id;
+    let john_id = john.user().await.ok().id;
+
+    let list = jane.create_list("Groceries").await.ok();
+    jane.create_todo(&CreateTodo {
+        list_id: Some(list.id),
+        ..create_todo("Milk")
+    })
+    .await
+    .ok();
+    let milk = jane.todos().await.ok().remove(0);
+    john.create_comment(milk.id, "Oat milk?", &[])
+        .await
+        .err(StatusCode::FORBIDDEN);
+    jane.create_comment(milk.id, "Ask @John", &[john_id])
+        .await
+        .err(StatusCode::BAD_REQUEST);
+    jane.create_comment(milk.id, "  ", &[]).await.err(StatusCode::BAD_REQUEST);
+
+    // viewers may discuss the todos of a list
+    jane.invite(list.id, "john@example.com", ListPermission::Viewer).await.ok();
+    let invitations = john.invitations().await.ok();
+    john.accept_invitation(invitations[0].id).await.ok();
+    let question = jane
+        .create_comment(milk.id, "**Which** one, @John?", &[john_id, john_id])
+        .await
+        .ok();
+    assert_eq!(
+        (question.author_name.as_str(), question.mentions.as_slice()),
+        ("Jane", [john_id].as_slice())
+    );
+    let answer =
+        john.create_comment(milk.id, "Oat milk", &[jane_id]).await.ok();
+
+    let comments = john.comments(milk.id, "").await.ok();
+    assert_eq!(comments, vec![question.clone(), answer.clone()]);
+    assert_eq!(
+        john.comments(milk.id, "offset=1&limit=1").await.ok(),
+        vec![answer.clone()]
+    );
+    john.comments(milk.id, "limit=0").await.err(StatusCode::BAD_REQUEST);
+
+    // only the author changes a comment
+    john.update_comment(milk.id, question.id, "Whatever", &[])
+        .await
+        .err(StatusCode::FORBIDDEN);
+    john.delete_comment(milk.id, question.id).await.err(StatusCode::FORBIDDEN);
+    let answer = john
+        .update_comment(milk.id, answer.id, "Oat milk, please", &[])
+        .await
+        .ok();
+    assert!(answer.mentions.is_empty());
+    jane.create_todo(&create_todo("Water the plants")).await.ok();
+    let plants = jane.todos().await.ok().remove(1);
+    jane.update_comment(plants.id, question.id, "Whatever", &[])
+        .await
+        .err(StatusCode::NOT_FOUND);
+    jane.delete_comment(milk.id, question.id).await.ok();
+    jane.delete_comment(milk.id, question.id).await.err(StatusCode::NOT_FOUND);
+    assert_eq!(jane.comments(milk.id, "").await.ok(), vec![answer]);
+
+    // the comments go with their todo
+    jane.delete_todo(milk.id).await.ok();
+    john.comments(milk.id, "").await.err(StatusCode::NOT_FOUND);
+}
+
+#[actix_rt::test]
+async fn changes_are_audited() {
+    let backend = MemoryBackend::new();
+    let mut jane = test_support::client(&backend).await;
+    let mut john = test_support::client(&backend).await;
+    jane.register("Jane", "jane@example.com", "secret").await.ok();
+    jane.login("jane@example.com", "secret").await.ok();
+    john.register("John", "john@example.com", "secret").await.ok();
+    john.login("john@example.com", "secret").await.ok();
+    let jane_id = jane.user().await.ok().id;
+    let john_id = john.user().await.ok().id;
+
+    jane.create_todo(&create_todo("Milk")).await.ok();
+    let milk = jane.todos().await.ok().remove(0);
+    let response: ApiResponse<()> = jane
+        .send(
+            TestRequest::put()
+                .uri("/api/v1/todos")
+                .insert_header(("X-Request-Id", "rename-milk"))
+                .set_json(UpdateTodo {
+                    id: milk.id,
+                    title: Some("Oat milk".to_string()),
+                    description: None,
+                    is_done: None,
+                    due_at: None,
+                }),
+        )
+        .await;
+    response.ok();
+    assert_eq!(
+        response.header::<String>("x-request-id").as_deref(),
+        Some("rename-milk")
+    );
+    // ids of other requests are made up by the server
+    let response = jane.todos().await;
+    assert_eq!(response.header::<String>("x-request-id").unwrap().len(), 32);
+
+    let history = jane.todo_history(milk.id, "").await.ok();
+    assert_eq!(
+        history.iter().map(|event| event.action).collect::<Vec<_>>(),
+        [AuditAction::Created, AuditAction::Updated]
+    );
+    assert_eq!(
+        (history[1].actor_id, history[1].request_id.as_deref()),
+        (Some(jane_id), Some("rename-milk"))
+    );
+    assert_eq!(
+        history[1].changes,
+        serde_json::json!({
+            "title": { "before": "Milk", "after": "Oat milk" }
+        })
+    );
+    assert_eq!(jane.todo_history(milk.id, "offset=1").await.ok(), history[1..]);
+    jane.todo_history(milk.id, "limit=0").await.err(StatusCode::BAD_REQUEST);
+    john.todo_history(milk.id, "").await.err(StatusCode::FORBIDDEN);
+
+    // the events outlive the todo
+    jane.delete_todo(milk.id).await.ok();
+    let activity = jane.account_activity().await.ok();
+    assert_eq!(
+        activity
+            .iter()
+            .map(|event| (event.action, event.entity))
+            .collect::<Vec<_>>(),
+        [
+            (AuditAction::Deleted, AuditEntity::Todo),
+            (AuditAction::Updated, AuditEntity::Todo),
+            (AuditAction::Created, AuditEntity::Todo),
+            (AuditAction::Created, AuditEntity::User),
+        ]
+    );
+    assert!(activity[0].changes["title"]["after"].is_null());
+    assert!(activity[3].changes.get("password").is_none());
+    assert!(!john.account_activity().await.ok().contains(&activity[0]));
+
+    // the security stream is kept apart
+    jane.login("jane@example.com", "wrong").await.err(StatusCode::UNAUTHORIZED);
+    let events = jane.security_events().await.ok();
+    assert_eq!(
+        events.iter().map(|event| event.kind).collect::<Vec<_>>(),
+        [SecurityEventKind::LoginFailed, SecurityEventKind::LoginSucceeded]
+    );
+    assert!(events.iter().all(|event| event.user_id == Some(jane_id)));
+    john.admin_security_events("").await.err(StatusCode::FORBIDDEN);
+
+    let users = backend.user_repository();
+    users
+        .set_role(&john_id, Role::Admin, &AuditContext::default())
+        .await
+        .0
+        .unwrap();
+    assert_eq!(
+        john.admin_security_events(&format!("user_id={jane_id}")).await.ok(),
+        events
+    );
+    jane.logout().await.ok();
+    let events = john.admin_security_events("limit=1").await.ok();
+    assert_eq!(
+        (events[0].kind, events[0].user_id, events[0].actor_id),
+        (SecurityEventKind::SessionsRevoked, Some(jane_id), Some(jane_id))
+    );
+}
+
+#[actix_rt::test]
+async fn files_are_attached_to_todos() {
+    let backend = MemoryBackend::new();
+    let store = Arc::new(MemoryBlobStore::default());
+    let settings = AppSettings {
+        blob_store: store.clone(),
+        attachment_limits: AttachmentLimits { max_file_size: 8, quota: 11 },
+        ..test_support::settings()
+    };
+    let mut jane = test_support::client_with(&backend, settings.clone()).await;
+    let mut john = test_support::client_with(&backend, settings).await;
+    jane.register("Jane", "jane@example.com", "secret").await.ok();
+    jane.login("jane@example.com", "secret").await.ok();
+    john.register("John", "john@example.com", "secret").await.ok();
+    john.login("john@example.com", "secret").await.ok();
+
+    let list = jane.create_list("Groceries").await.ok();
+    jane.create_todo(&CreateTodo {
+        list_id: Some(list.id),
+        ..create_todo("Milk")
+    })
+    .await
+    .ok();
+    let milk = jane.todos().await.ok().remove(0);
+    let receipt = jane
+        .upload_attachment(milk.id, "Bon März.txt", "text/plain", b"1,29")
+        .await
+        .ok();
+    assert_eq!(
+        (receipt.size, receipt.content_type.as_str()),
+        (4, "text/plain")
+    );
+    assert_eq!(
+        receipt.checksum,
+        "23d3a08a55eb637a79f50b6de270771b4385409407de5b0247cf6c50a9027e6c"
+    );
+    assert_eq!(jane.attachments(milk.id).await.ok(), vec![receipt.clone()]);
+
+    let download = jane.download_attachment(milk.id, receipt.id).await;
+    assert_eq!(download.status, StatusCode::OK);
+    assert_eq!(download.text(), "1,29");
+    assert_eq!(download.content_type.as_deref(), Some("text/plain"));
+    assert_eq!(
+        download.header::<String>("content-disposition").as_deref(),
+        Some(
+            "attachment; filename=\"Bon M_rz.txt\"; \
+             filename*=UTF-8''Bon%20M%C3%A4rz.txt"
+        )
+    );
+    assert_eq!(
+        download.header::<String>("x-content-type-options").as_deref(),
+        Some("nosniff")
+    );
+
+    // viewers may download, but not attach
+    john.attachments(milk.id).await.err(StatusCode::FORBIDDEN);
+    jane.invite(list.id, "john@example.com", ListPermission::Viewer).await.ok();
+    let invitations = john.invitations().await.ok();
+    john.accept_invitation(invitations[0].id).await.ok();
+    assert_eq!(
+        john.download_attachment(milk.id, receipt.id).await.text(),
+        "1,29"
+    );
+    john.upload_attachment(milk.id, "a.txt", "text/plain", b"a")
+        .await
+        .err(StatusCode::FORBIDDEN);
+    john.delete_attachment(milk.id, receipt.id)
+        .await
+        .err(StatusCode::FORBIDDEN);
+
+    // the limits count the bytes of the uploader
+    jane.upload_attachment(milk.id, "big.bin", "application/pdf", &[0; 9])
+        .await
+        .err(StatusCode::PAYLOAD_TOO_LARGE);
+    jane.upload_attachment(milk.id, "list.txt", "text/plain", b"12345678")
+        .await
+        .err(StatusCode::PAYLOAD_TOO_LARGE);
+    let usage = jane.storage_usage().await.ok();
+    assert_eq!((usage.used, usage.quota), (4, 11));
+    assert_eq!(store.keys().len(), 1);
+    jane.set_list_permission(
+        list.id,
+        john.user().await.ok().id,
+        ListPermission::Editor,
+    )
+    .await
+    .ok();
+    john.upload_attachment(milk.id, "list.txt", "text/plain", b"12345678")
+        .await
+        .ok();
+
+    jane.delete_attachment(milk.id, receipt.id).await.ok();
+    jane.download_attachment(milk.id, receipt.id)
+        .await
+        .err(StatusCode::NOT_FOUND);
+    assert_eq!(jane.storage_usage().await.ok().used, 0);
+
+    // the content goes along with the todo
+    jane.delete_todo(milk.id).await.ok();
+    wait_until(|| store.keys().is_empty()).await;
+}
+
+#[actix_rt::test]
+async fn todos_are_exported_and_imported() {
+    let backend = MemoryBackend::new();
+    let mut client = test_support::client(&backend).await;
+    client.register("Jane", "jane@example.com", "secret").await.ok();
+    client.login("jane@example.com", "secret").await.ok();
+    client.create_todo(&create_todo("Milk")).await.ok();
+    let milk = client.todos().await.ok().remove(0);
+
+    let csv = client.export_todos(TransferFormat::Csv).await;
+    assert_eq!(csv.content_type.as_deref(), Some("text/csv; charset=utf-8"));
+    assert_eq!(
+        csv.header::<String>("content-disposition").as_deref(),
+        Some("attachment; filename=\"lentos-todos.csv\"")
+    );
+    let text = csv.text();
+    let mut lines = text.lines();
+    assert_eq!(
+        lines.next(),
+        Some(
+            "external_id,title,description,is_done,due_at,completed_at,\
+             list_id,created_at,updated_at"
+        )
+    );
+    assert!(lines
+        .next()
+        .unwrap()
+        .starts_with(&format!("lentos:{},Milk,,false,,,,", milk.id)));
+
+    // nothing is imported if a single row has an error
+    let import = ImportTodos {
+        content: "Id,Task,Done\nshop-1,Eggs,x\nshop-2,,\nshop-3,Butter,maybe\n"
+            .to_string(),
+        columns: [
+            ("external_id", "Id"),
+            ("title", "Task"),
+            ("is_done", "Done"),
+        ]
+        .map(|(field, column)| (field.to_string(), column.to_string()))
+        .into(),
+        ..Default::default()
+    };
+    let report = client.import_todos(&import).await.ok();
+    assert!(!report.applied);
+    assert_eq!(
+        report.errors.iter().map(|e| e.row).collect::<Vec<_>>(),
+        vec![2, 3]
+    );
+    assert_eq!(client.todos().await.ok().len(), 1);
+
+    let import = ImportTodos {
+        content: "Id,Task,Done\nshop-1,Eggs,x\nshop-2,Butter,no\n".to_string(),
+        ..import
+    };
+    let report = client
+        .import_todos(&ImportTodos { dry_run: true, ..import.clone() })
+        .await
+        .ok();
+    assert_eq!((report.applied, report.created), (false, 2));
+    assert_eq!(client.todos().await.ok().len(), 1);
+    let report = client.import_todos(&import).await.ok();
+    assert_eq!((report.applied, report.created), (true, 2));
+    let eggs = client
+        .todos()
+        .await
+        .ok()
+        .into_iter()
+        .find(|todo| todo.title == "Eggs")
+        .unwrap();
+    assert!(eggs.is_done);
+
+    // importing again recognizes the todos by their external ids
+    let report = client.import_todos(&import).await.ok();
+    assert_eq!((report.created, report.unchanged), (0, 2));
+    assert_eq!(client.todos().await.ok().len(), 3);
+
+    // so does importing an export
+    let json = client.export_todos(TransferFormat::Json).await;
+    let mut exported: Vec<ExportedTodo> =
+        serde_json::from_str(&json.text()).unwrap();
+    assert!(exported.iter().any(|todo| todo.external_id == "shop-1"));
+    for todo in &mut exported {
+        if todo.title == "Milk" {
+            todo.title = "Oat milk".to_string();
+        }
+    }
+    let report = client
+        .import_todos(&ImportTodos {
+            format: TransferFormat::Json,
+            content: serde_json::to_string(&exported).unwrap(),
+            ..Default::default()
+        })
+        .await
+        .ok();
+    assert_eq!((report.created, report.updated, report.unchanged), (0, 1, 2));
+    assert_eq!(client.todo(milk.id).await.ok().title, "Oat milk");
+
+    client
+        .import_todos(&ImportTodos {
+            columns: [("due".to_string(), "Due".to_string())].into(),
+            ..import.clone()
+        })
+        .await
+        .err(StatusCode::BAD_REQUEST);
+    client
+        .import_todos(&ImportTodos {
+            content: "Name\nEggs\n".to_string(),
+            ..Default::default()
+        })
+        .await
+        .err(StatusCode::BAD_REQUEST);
+}
+
+#[actix_rt::test]
+async fn todos_are_published_as_calendar_feed() {
+    let backend = MemoryBackend::new();
+    let mut jane = test_support::client(&backend).await;
+    let mut calendar_app = test_support::client(&backend).await;
+    jane.register("Jane", "jane@example.com", "secret").await.ok();
+    jane.login("jane@example.com", "secret").await.ok();
+
+    let due_at = "2024-01-05T12:00:00Z".parse().ok();
+    jane.create_todo(&CreateTodo { due_at, ..create_todo("Milk") }).await.ok();
+    let list = jane.create_list("Garden").await.ok();
+    jane.create_todo(&CreateTodo {
+        list_id: Some(list.id),
+        ..create_todo("Water the plants")
+    })
+    .await
+    .ok();
+    let todos = jane.todos().await.ok();
+    let (milk, plants) = (&todos[0], &todos[1]);
+    assert_eq!(milk.due_at, due_at);
+    jane.update_todo(&UpdateTodo {
+        id: plants.id,
+        is_done: Some(true),
+        ..Default::default()
+    })
+    .await
+    .ok();
+    assert!(jane.todo(plants.id).await.ok().completed_at.is_some());
+
+    let feed = jane.create_calendar_feed().await.ok();
+    let response = calendar_app.calendar_feed(&feed.path).await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(
+        response.content_type.as_deref(),
+        Some("text/calendar; charset=utf-8")
+    );
+    let calendar = response.text();
+    for line in [
+        format!("UID:lentos:{}", milk.id),
+        "DUE:20240105T120000Z".to_string(),
+        "STATUS:NEEDS-ACTION".to_string(),
+        format!("UID:lentos:{}", plants.id),
+        "STATUS:COMPLETED".to_string(),
+        "CATEGORIES:Garden".to_string(),
+    ] {
+        assert!(calendar.contains(&format!("\r\n{line}\r\n")), "{line}");
+    }
+
+    // `null` removes the due date
+    jane.update_todo(&UpdateTodo {
+        id: milk.id,
+        due_at: Some(None),
+        ..Default::default()
+    })
+    .await
+    .ok();
+    assert_eq!(jane.todo(milk.id).await.ok().due_at, None);
+
+    // a new token replaces the old one
+    let new_feed = jane.create_calendar_feed().await.ok();
+    calendar_app.calendar_feed(&feed.path).await.err(StatusCode::NOT_FOUND);
+    assert_eq!(
+        calendar_app.calendar_feed(&new_feed.path).await.status,
+        StatusCode::OK
+    );
+    jane.delete_calendar_feed().await.ok();
+    calendar_app.calendar_feed(&new_feed.path).await.err(StatusCode::NOT_FOUND);
+    calendar_app.create_calendar_feed().await.err(StatusCode::UNAUTHORIZED);
+    let feed_changes = jane
+        .security_events()
+        .await
+        .ok()
+        .into_iter()
+        .filter(|event| event.kind == SecurityEventKind::FeedTokenChanged)
+        .count();
+    assert_eq!(feed_changes, 3);
+
+    // the feed imports as an update of the todos
+    let import = ImportTodos {
+        format: TransferFormat::Ical,
+        content: calendar,
+        ..Default::default()
+    };
+    let report = jane.import_todos(&import).await.ok();
+    assert_eq!((report.created, report.updated, report.unchanged), (0, 1, 1));
+    assert_eq!(jane.todo(milk.id).await.ok().due_at, due_at);
+
+    let import = ImportTodos {
+        content: "BEGIN:VCALENDAR\r\n\
+                  VERSION:2.0\r\n\
+                  PRODID:-//Other//App//EN\r\n\
+                  BEGIN:VTODO\r\n\
+                  UID:other-app-1\r\n\
+                  SUMMARY:Call mom\r\n\
+                  DUE;VALUE=DATE:20240106\r\n\
+                  END:VTODO\r\n\
+                  END:VCALENDAR\r\n"
+            .to_string(),
+        ..import
+    };
+    let report = jane.import_todos(&import).await.ok();
+    assert_eq!((report.applied, report.created), (true, 1));
+    let call = jane.todos().await.ok().remove(2);
+    assert_eq!(
+        (call.title.as_str(), call.due_at),
+        ("Call mom", "2024-01-06T00:00:00Z".parse().ok())
+    );
+    let report = jane.import_todos(&import).await.ok();
+    assert_eq!((report.created, report.unchanged), (0, 1));
+}
@@ -0,0 +1,469 @@
+//! Two-way sync with calendar apps over CalDAV, which send an access token
+//! as the password of Basic auth.
+
+use actix_http::{
+    header::{AUTHORIZATION, ETAG, IF_MATCH, IF_NONE_MATCH, LOCATION},
+    Method, StatusCode,
+};
+use actix_web::test::TestRequest;
+use app::{repository::memory::MemoryBackend, test_support};
+use data_encoding::BASE64;
+use shared::models::{
+    list::ListPermission,
+    todo::{CreateTodo, UpdateTodo},
+    user::{CreateAccessToken, TokenScope},
+};
+
+const CALENDAR: &str = "/dav/calendars/todos/";
+
+/// A request of a calendar app, which is never logged in.
+fn dav(method: &str, path: &str, token: &str) -> TestRequest {
+    let credentials = BASE64.encode(format!("jane:{token}").as_bytes());
+
+    TestRequest::default()
+        .method(Method::from_bytes(method.as_bytes()).unwrap())
+        .uri(path)
+        .insert_header((AUTHORIZATION, format!("Basic {credentials}")))
+}
+
+fn vtodo(uid: &str, summary: &str, status: &str) -> String {
+    format!(
+        "BEGIN:VCALENDAR\r\n\
+         VERSION:2.0\r\n\
+         PRODID:-//Other//App//EN\r\n\
+         BEGIN:VTODO\r\n\
+         UID:{uid}\r\n\
+         SUMMARY:{summary}\r\n\
+         STATUS:{status}\r\n\
+         DUE:20240105T120000Z\r\n\
+         END:VTODO\r\n\
+         END:VCALENDAR\r\n"
+    )
+}
+
+fn create_todo(title: &str) -> CreateTodo {
+    CreateTodo {
+        title: title.to_string(),
+        description: String::new(),
+        list_id: None,
+        due_at: None,
+    }
+}
+
+/// Returns the sync token of a multistatus.
+fn sync_token(multistatus: &str) -> String {
+    let start = multistatus.rfind("<d:sync-token>").unwrap() + 14;
+    let end = multistatus.rfind("</d:sync-token>").unwrap();
+
+    multistatus[start..end].to_string()
+}
+
+fn sync_collection(sync_token: &str) -> String {
+    format!(
+        r#"<d:sync-collection xmlns:d="DAV:">
+            <d:sync-token>{sync_token}</d:sync-token>
+            <d:sync-level>1</d:sync-level>
+            <d:prop><d:getetag/></d:prop>
+        </d:sync-collection>"#
+    )
+}
+
+#[actix_web::test]
+async fn calendar_apps_discover_the_todos() {
+    let backend = MemoryBackend::new();
+    let mut jane = test_support::client(&backend).await;
+    let mut calendar_app = test_support::client(&backend).await;
+    jane.register("Jane", "jane@example.com", "secret").await.ok();
+    jane.login("jane@example.com", "secret").await.ok();
+    let token = jane
+        .create_access_token(&CreateAccessToken {
+            name: "Phone".to_string(),
+            scopes: vec![TokenScope::ReadTodos],
+            expires_at: None,
+        })
+        .await
+        .ok()
+        .token;
+    jane.create_todo(&create_todo("Milk")).await.ok();
+    let milk = jane.todos().await.ok().remove(0);
+
+    let response = calendar_app
+        .send::<()>(TestRequest::get().uri("/.well-known/caldav"))
+        .await;
+    assert_eq!(response.status, StatusCode::MOVED_PERMANENTLY);
+    assert_eq!(response.header::<String>(LOCATION.as_str()).unwrap(), "/dav/");
+
+    let response = calendar_app.send::<()>(dav("OPTIONS", CALENDAR, "")).await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(
+        response.header::<String>("DAV").unwrap(),
+        "1, 3, calendar-access"
+    );
+
+    // apps only send credentials once they are asked for them
+    let response = calendar_app
+        .send::<()>(
+            TestRequest::default()
+                .method(Method::from_bytes(b"PROPFIND").unwrap())
+                .uri("/dav/"),
+        )
+        .await;
+    assert_eq!(response.status, StatusCode::UNAUTHORIZED);
+    assert_eq!(
+        response.header::<String>("WWW-Authenticate").unwrap(),
+        r#"Basic realm="lentos", charset="UTF-8""#
+    );
+    let response = calendar_app
+        .send::<()>(dav("PROPFIND", "/dav/", "lentos_pat_invalid"))
+        .await;
+    assert_eq!(response.status, StatusCode::UNAUTHORIZED);
+    assert!(response.headers.contains_key("WWW-Authenticate"));
+
+    let response = calendar_app
+        .send::<()>(
+            dav("PROPFIND", "/dav/", &token)
+                .insert_header(("Depth", "0"))
+                .set_payload(
+                    r#"<d:propfind xmlns:d="DAV:"
+                        xmlns:c="urn:ietf:params:xml:ns:caldav">
+                        <d:prop>
+                            <d:current-user-principal/>
+                            <c:calendar-home-set/>
+                            <d:quota-used-bytes/>
+                        </d:prop>
+                    </d:propfind>"#,
+                ),
+        )
+        .await;
+    assert_eq!(response.status, StatusCode::MULTI_STATUS);
+    let multistatus = response.text();
+    assert!(multistatus.contains(
+        "<d:current-user-principal><d:href>/dav/</d:href>\
+         </d:current-user-principal>"
+    ));
+    assert!(multistatus.contains(
+        "<c:calendar-home-set><d:href>/dav/calendars/</d:href>\
+         </c:calendar-home-set>"
+    ));
+    assert!(multistatus.contains(
+        "<d:propstat><d:prop><d:quota-used-bytes/></d:prop>\
+         <d:status>HTTP/1.1 404 Not Found</d:status></d:propstat>"
+    ));
+
+    let response = calendar_app
+        .send::<()>(
+            dav("PROPFIND", "/dav/calendars/", &token)
+                .insert_header(("Depth", "1")),
+        )
+        .await;
+    let multistatus = response.text();
+    assert!(multistatus.contains(
+        "<d:href>/dav/calendars/todos/</d:href><d:propstat><d:prop>\
+         <d:resourcetype><d:collection/><c:calendar/></d:resourcetype>\
+         <d:displayname>Todos</d:displayname>"
+    ));
+    assert!(multistatus.contains(r#"<c:comp name="VTODO"/>"#));
+    // the token only grants reading
+    assert!(!multistatus.contains("<d:write/>"));
+
+    let response = calendar_app
+        .send::<()>(dav("REPORT", CALENDAR, &token).set_payload(
+            r#"<c:calendar-query xmlns:d="DAV:"
+                    xmlns:c="urn:ietf:params:xml:ns:caldav">
+                    <d:prop><d:getetag/><c:calendar-data/></d:prop>
+                    <c:filter><c:comp-filter name="VCALENDAR">
+                        <c:comp-filter name="VTODO"/>
+                    </c:comp-filter></c:filter>
+                </c:calendar-query>"#,
+        ))
+        .await;
+    let href = format!("{CALENDAR}lentos%3A{}.ics", milk.id);
+    let multistatus = response.text();
+    assert!(multistatus.contains(&format!("<d:href>{href}</d:href>")));
+    assert!(multistatus.contains("SUMMARY:Milk\r\n"));
+
+    let response = calendar_app.send::<()>(dav("GET", &href, &token)).await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(
+        response.content_type.as_deref(),
+        Some("text/calendar; charset=utf-8; component=VTODO")
+    );
+    assert!(response.text().contains(&format!("UID:lentos:{}\r\n", milk.id)));
+    let etag = response.header::<String>(ETAG.as_str()).unwrap();
+    assert!(multistatus.contains(&format!(
+        "<d:getetag>{}</d:getetag>",
+        etag.replace('"', "&quot;")
+    )));
+
+    let missing = format!("{CALENDAR}missing.ics");
+    let response = calendar_app
+        .send::<()>(dav("REPORT", CALENDAR, &token).set_payload(format!(
+            r#"<c:calendar-multiget xmlns:d="DAV:"
+                xmlns:c="urn:ietf:params:xml:ns:caldav">
+                <d:prop><d:getetag/></d:prop>
+                <d:href>http://localhost{href}</d:href>
+                <d:href>{missing}</d:href>
+            </c:calendar-multiget>"#
+        )))
+        .await;
+    let multistatus = response.text();
+    assert!(multistatus.contains(&format!("<d:href>{href}</d:href>")));
+    assert!(multistatus.contains(&format!(
+        "<d:response><d:href>{missing}</d:href>\
+         <d:status>HTTP/1.1 404 Not Found</d:status></d:response>"
+    )));
+
+    // changing todos needs the scope to write them
+    let response = calendar_app
+        .send::<()>(
+            dav("PUT", &href, &token).set_payload(vtodo("x", "Oat milk", "")),
+        )
+        .await;
+    assert_eq!(response.status, StatusCode::FORBIDDEN);
+}
+
+#[actix_web::test]
+async fn calendar_apps_change_todos() {
+    let backend = MemoryBackend::new();
+    let mut jane = test_support::client(&backend).await;
+    let mut calendar_app = test_support::client(&backend).await;
+    jane.register("Jane", "jane@example.com", "secret").await.ok();
+    jane.login("jane@example.com", "secret").await.ok();
+    let token = jane
+        .create_access_token(&CreateAccessToken {
+            name: "Phone".to_string(),
+            scopes: vec![TokenScope::ReadTodos, TokenScope::WriteTodos],
+            expires_at: None,
+        })
+        .await
+        .ok()
+        .token;
+
+    let href = format!("{CALENDAR}2f1d-app.ics");
+    let response = calendar_app
+        .send::<()>(
+            dav("PUT", &href, &token)
+                .insert_header((IF_NONE_MATCH, "*"))
+                .set_payload(vtodo("2f1d-app", "Call mom", "NEEDS-ACTION")),
+        )
+        .await;
+    assert_eq!(response.status, StatusCode::CREATED);
+    let todo = jane.todos().await.ok().remove(0);
+    assert_eq!(todo.title, "Call mom");
+    assert_eq!(todo.due_at, "2024-01-05T12:00:00Z".parse().ok());
+    assert_eq!(todo.list_id, None);
+
+    // the todo keeps the name the app gave it
+    let response = calendar_app.send::<()>(dav("GET", &href, &token)).await;
+    assert!(response.text().contains("UID:2f1d-app\r\n"));
+    let etag = response.header::<String>(ETAG.as_str()).unwrap();
+    let response = calendar_app
+        .send::<()>(
+            dav("PUT", &href, &token)
+                .insert_header((IF_NONE_MATCH, "*"))
+                .set_payload(vtodo("2f1d-app", "Call mom", "NEEDS-ACTION")),
+        )
+        .await;
+    assert_eq!(response.status, StatusCode::PRECONDITION_FAILED);
+
+    let response = calendar_app
+        .send::<()>(
+            dav("PUT", &href, &token)
+                .insert_header((IF_MATCH, etag.clone()))
+                .set_payload(vtodo("2f1d-app", "Call dad", "COMPLETED")),
+        )
+        .await;
+    assert_eq!(response.status, StatusCode::NO_CONTENT);
+    let todo = jane.todo(todo.id).await.ok();
+    assert_eq!((todo.title.as_str(), todo.is_done), ("Call dad", true));
+
+    // the app has not seen the change yet
+    let response = calendar_app
+        .send::<()>(
+            dav("PUT", &href, &token)
+                .insert_header((IF_MATCH, etag.clone()))
+                .set_payload(vtodo("2f1d-app", "Call mom", "NEEDS-ACTION")),
+        )
+        .await;
+    assert_eq!(response.status, StatusCode::PRECONDITION_FAILED);
+    let response = calendar_app
+        .send::<()>(
+            dav("PUT", &href, &token).set_payload("BEGIN:VCALENDAR\r\n"),
+        )
+        .await;
+    assert_eq!(response.status, StatusCode::BAD_REQUEST);
+    let response = calendar_app
+        .send::<()>(
+            dav("DELETE", &href, &token).insert_header((IF_MATCH, etag)),
+        )
+        .await;
+    assert_eq!(response.status, StatusCode::PRECONDITION_FAILED);
+
+    let response = calendar_app.send::<()>(dav("DELETE", &href, &token)).await;
+    assert_eq!(response.status, StatusCode::NO_CONTENT);
+    assert!(jane.todos().await.ok().is_empty());
+    let response = calendar_app.send::<()>(dav("GET", &href, &token)).await;
+    assert_eq!(response.status, StatusCode::NOT_FOUND);
+}
+
+#[actix_web::test]
+async fn calendar_apps_create_todos_in_lists() {
+    let backend = MemoryBackend::new();
+    let mut jane = test_support::client(&backend).await;
+    let mut john = test_support::client(&backend).await;
+    let mut calendar_app = test_support::client(&backend).await;
+    jane.register("Jane", "jane@example.com", "secret").await.ok();
+    jane.login("jane@example.com", "secret").await.ok();
+    john.register("John", "john@example.com", "secret").await.ok();
+    john.login("john@example.com", "secret").await.ok();
+    let garden = jane.create_list("Garden").await.ok();
+    let work = john.create_list("Work").await.ok();
+    john.invite(work.id, "jane@example.com", ListPermission::Viewer).await.ok();
+    let invitation = jane.invitations().await.ok().remove(0);
+    jane.accept_invitation(invitation.id).await.ok();
+    let token = jane
+        .create_access_token(&CreateAccessToken {
+            name: "Phone".to_string(),
+            scopes: vec![TokenScope::ReadTodos, TokenScope::WriteTodos],
+            expires_at: None,
+        })
+        .await
+        .ok()
+        .token;
+
+    // the first category that is a list jane may edit
+    let categorized = |uid: &str, categories: &str| {
+        vtodo(uid, "Water the plants", "COMPLETED").replace(
+            "END:VTODO",
+            &format!("CATEGORIES:{categories}\r\nEND:VTODO"),
+        )
+    };
+    let response = calendar_app
+        .send::<()>(
+            dav("PUT", &format!("{CALENDAR}plants.ics"), &token)
+                .set_payload(categorized("plants", "Work,Garden,Home")),
+        )
+        .await;
+    assert_eq!(response.status, StatusCode::CREATED);
+    let todos = jane.todos().await.ok();
+    let plants = todos.iter().find(|todo| todo.title == "Water the plants");
+    let plants = plants.unwrap();
+    assert_eq!(plants.list_id, Some(garden.id));
+    assert!(plants.is_done && plants.completed_at.is_some());
+    let response = calendar_app
+        .send::<()>(dav("GET", &format!("{CALENDAR}plants.ics"), &token))
+        .await;
+    assert!(response.text().contains("CATEGORIES:Garden\r\n"));
+
+    // viewers only get personal todos
+    let response = calendar_app
+        .send::<()>(
+            dav("PUT", &format!("{CALENDAR}report.ics"), &token)
+                .set_payload(categorized("report", "Work")),
+        )
+        .await;
+    assert_eq!(response.status, StatusCode::CREATED);
+    let todos = jane.todos().await.ok();
+    assert_eq!(todos.len(), 2);
+    assert!(todos.iter().any(|todo| todo.list_id.is_none()));
+}
+
+#[actix_web::test]
+async fn calendar_apps_sync_changes() {
+    let backend = MemoryBackend::new();
+    let mut jane = test_support::client(&backend).await;
+    let mut calendar_app = test_support::client(&backend).await;
+    jane.register("Jane", "jane@example.com", "secret").await.ok();
+    jane.login("jane@example.com", "secret").await.ok();
+    let token = jane
+        .create_access_token(&CreateAccessToken {
+            name: "Phone".to_string(),
+            scopes: vec![TokenScope::ReadTodos],
+            expires_at: None,
+        })
+        .await
+        .ok()
+        .token;
+    jane.create_todo(&create_todo("Milk")).await.ok();
+    jane.create_todo(&create_todo("Eggs")).await.ok();
+    let todos = jane.todos().await.ok();
+    let (milk, eggs) = (&todos[0], &todos[1]);
+    let milk_href = format!("{CALENDAR}lentos%3A{}.ics", milk.id);
+    let eggs_href = format!("{CALENDAR}lentos%3A{}.ics", eggs.id);
+
+    // the first sync has no token and gets all todos
+    let response = calendar_app
+        .send::<()>(dav("REPORT", CALENDAR, &token).set_payload(
+            r#"<d:sync-collection xmlns:d="DAV:">
+                    <d:sync-token/>
+                    <d:sync-level>1</d:sync-level>
+                    <d:prop><d:getetag/></d:prop>
+                </d:sync-collection>"#,
+        ))
+        .await;
+    assert_eq!(response.status, StatusCode::MULTI_STATUS);
+    let multistatus = response.text();
+    assert!(multistatus.contains(&milk_href));
+    assert!(multistatus.contains(&eggs_href));
+    let first_token = sync_token(&multistatus);
+
+    // the token is the one of the calendar
+    let response = calendar_app
+        .send::<()>(
+            dav("PROPFIND", CALENDAR, &token)
+                .insert_header(("Depth", "0"))
+                .set_payload(
+                    r#"<d:propfind xmlns:d="DAV:"
+                        xmlns:cs="http://calendarserver.org/ns/">
+                        <d:prop><d:sync-token/><cs:getctag/></d:prop>
+                    </d:propfind>"#,
+                ),
+        )
+        .await;
+    assert!(response.text().contains(&format!(
+        "<d:sync-token>{first_token}</d:sync-token>\
+         <cs:getctag>{first_token}</cs:getctag>"
+    )));
+
+    jane.update_todo(&UpdateTodo {
+        id: eggs.id,
+        is_done: Some(true),
+        ..Default::default()
+    })
+    .await
+    .ok();
+    let response = calendar_app
+        .send::<()>(
+            dav("REPORT", CALENDAR, &token)
+                .set_payload(sync_collection(&first_token)),
+        )
+        .await;
+    let multistatus = response.text();
+    assert!(!multistatus.contains(&milk_href));
+    assert!(multistatus.contains(&eggs_href));
+    let second_token = sync_token(&multistatus);
+    assert_ne!(second_token, first_token);
+
+    let response = calendar_app
+        .send::<()>(
+            dav("REPORT", CALENDAR, &token)
+                .set_payload(sync_collection(&second_token)),
+        )
+        .await;
+    let multistatus = response.text();
+    assert!(!multistatus.contains("<d:response>"));
+    assert_eq!(sync_token(&multistatus), second_token);
+
+    // apps learn about deleted todos by syncing all todos again
+    jane.delete_todo(milk.id).await.ok();
+    for sync_token in [first_token, second_token, "other".to_string()] {
+        let response = calendar_app
+            .send::<()>(
+                dav("REPORT", CALENDAR, &token)
+                    .set_payload(sync_collection(&sync_token)),
+            )
+            .await;
+        assert_eq!(response.status, StatusCode::FORBIDDEN);
+        assert!(response.text().contains("<d:valid-sync-token/>"));
+    }
+}
@@ -0,0 +1,396 @@
+//! Single sign-on against a mock OpenID Connect provider that listens on a
+//! local port.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use actix_http::StatusCode;
+use actix_web::{web, App, HttpRequest, HttpResponse, HttpServer};
+use app::{
+    controllers::common::{
+        oidc::{OidcProvider, OidcSettings},
+        totp,
+    },
+    repository::memory::MemoryBackend,
+    server::AppSettings,
+    test_support,
+};
+use chrono::Utc;
+use data_encoding::{BASE64, BASE64URL_NOPAD};
+use jsonwebtoken::{Algorithm, EncodingKey, Header};
+use p256::{
+    pkcs8::{EncodePrivateKey, LineEnding},
+    SecretKey,
+};
+use rand::rngs::OsRng;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use url::Url;
+
+const CLIENT_ID: &str = "lentos";
+const CLIENT_SECRET: &str = "secret";
+const KEY_ID: &str = "mock-key";
+
+/// A code issued by the mock provider and what it was issued for.
+struct Grant {
+    code_challenge: String,
+    claims: Value,
+}
+
+struct MockState {
+    issuer: String,
+    signing_key: EncodingKey,
+    jwk: Value,
+    grants: Mutex<HashMap<String, Grant>>,
+}
+
+/// Issues ES256 signed ID tokens for whatever claims a test asks for.
+struct MockProvider {
+    state: Arc<MockState>,
+}
+
+impl MockProvider {
+    async fn start() -> Self {
+        let secret = SecretKey::random(&mut OsRng);
+        let pem = secret.to_pkcs8_pem(LineEnding::LF).unwrap();
+        let mut jwk =
+            serde_json::to_value(secret.public_key().to_jwk()).unwrap();
+        jwk["kid"] = json!(KEY_ID);
+        jwk["alg"] = json!("ES256");
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let issuer = format!("http://{}", listener.local_addr().unwrap());
+        let state = Arc::new(MockState {
+            issuer,
+            signing_key: EncodingKey::from_ec_pem(pem.as_bytes()).unwrap(),
+            jwk,
+            grants: Mutex::default(),
+        });
+
+        let data = web::Data::from(state.clone());
+        let server = HttpServer::new(move || {
+            App::new()
+                .app_data(data.clone())
+                .route(
+                    "/.well-known/openid-configuration",
+                    web::get().to(discovery),
+                )
+                .route("/jwks", web::get().to(jwks))
+                .route("/token", web::post().to(token))
+        })
+        .workers(1)
+        .disable_signals()
+        .listen(listener)
+        .unwrap()
+        .run();
+        actix_rt::spawn(server);
+
+        Self { state }
+    }
+
+    fn settings(&self) -> AppSettings {
+        let settings = OidcSettings {
+            issuer: self.state.issuer.clone(),
+            client_id: CLIENT_ID.to_string(),
+            client_secret: Some(CLIENT_SECRET.to_string()),
+            redirect_url: format!(
+                "{}/api/v1/users/oidc/callback",
+                test_support::ORIGIN
+            )
+            .parse()
+            .unwrap(),
+            scopes: vec!["openid".to_string(), "email".to_string()],
+            post_login_redirect: "/todos".to_string(),
+        };
+
+        AppSettings {
+            oidc: Some(Arc::new(OidcProvider::new(settings).unwrap())),
+            ..test_support::settings()
+        }
+    }
+
+    /// Plays the user logging in at the provider: answers the authorization
+    /// request in `location` with a code for an ID token with `claims` and
+    /// returns the query of the callback.
+    fn authorize(&self, location: &str, claims: Value) -> String {
+        let url = Url::parse(location).unwrap();
+        assert!(url.as_str().starts_with(&self.state.issuer));
+        let query: HashMap<_, _> = url.query_pairs().into_owned().collect();
+        assert_eq!(query["response_type"], "code");
+        assert_eq!(query["client_id"], CLIENT_ID);
+        assert_eq!(query["scope"], "openid email");
+        assert_eq!(query["code_challenge_method"], "S256");
+
+        let mut id_token = json!({
+            "iss": self.state.issuer,
+            "aud": CLIENT_ID,
+            "exp": Utc::now().timestamp() + 300,
+            "iat": Utc::now().timestamp(),
+            "nonce": query["nonce"],
+        });
+        for (claim, value) in claims.as_object().unwrap() {
+            id_token[claim] = value.clone();
+        }
+
+        let code = format!("code-{}", rand::random::<u64>());
+        let grant = Grant {
+            code_challenge: query["code_challenge"].clone(),
+            claims: id_token,
+        };
+        self.state.grants.lock().unwrap().insert(code.clone(), grant);
+
+        format!("code={code}&state={}", query["state"])
+    }
+}
+
+async fn discovery(state: web::Data<MockState>) -> HttpResponse {
+    let issuer = &state.issuer;
+
+    HttpResponse::Ok().json(json!({
+        "issuer": issuer,
+        "authorization_endpoint": format!("{issuer}/authorize"),
+        "token_endpoint": format!("{issuer}/token"),
+        "jwks_uri": format!("{issuer}/jwks"),
+    }))
+}
+
+async fn jwks(state: web::Data<MockState>) -> HttpResponse {
+    HttpResponse::Ok().json(json!({ "keys": [state.jwk] }))
+}
+
+#[derive(Deserialize)]
+struct TokenRequest {
+    grant_type: String,
+    code: String,
+    code_verifier: String,
+}
+
+async fn token(
+    request: HttpRequest,
+    form: web::Form<TokenRequest>,
+    state: web::Data<MockState>,
+) -> HttpResponse {
+    let credentials = format!(
+        "Basic {}",
+        BASE64.encode(format!("{CLIENT_ID}:{CLIENT_SECRET}").as_bytes())
+    );
+    let authenticated = request
+        .headers()
+        .get("authorization")
+        .is_some_and(|value| value.as_bytes() == credentials.as_bytes());
+    let grant = state.grants.lock().unwrap().remove(&form.code);
+
+    match grant {
+        Some(grant)
+            if authenticated
+                && form.grant_type == "authorization_code"
+                && grant.code_challenge
+                    == BASE64URL_NOPAD
+                        .encode(&Sha256::digest(&form.code_verifier)) =>
+        {
+            let mut header = Header::new(Algorithm::ES256);
+            header.kid = Some(KEY_ID.to_string());
+            let id_token = jsonwebtoken::encode(
+                &header,
+                &grant.claims,
+                &state.signing_key,
+            )
+            .unwrap();
+
+            HttpResponse::Ok().json(json!({
+                "access_token": "access",
+                "token_type": "Bearer",
+                "id_token": id_token,
+            }))
+        }
+        _ => {
+            HttpResponse::BadRequest().json(json!({ "error": "invalid_grant" }))
+        }
+    }
+}
+
+/// The claims of an account at the provider.
+fn account(subject: &str, email: &str, email_verified: bool) -> Value {
+    json!({ "sub": subject, "email": email, "email_verified": email_verified })
+}
+
+/// Returns where a response redirects to.
+fn location(response: &test_support::ApiResponse<()>) -> String {
+    assert_eq!(response.status, StatusCode::SEE_OTHER, "{}", response.text());
+
+    response.header("location").unwrap()
+}
+
+#[actix_rt::test]
+async fn oidc_logins_create_and_link_users() {
+    let provider = MockProvider::start().await;
+    let backend = MemoryBackend::new();
+    let mut jane =
+        test_support::client_with(&backend, provider.settings()).await;
+
+    let mut claims = account("1", "jane@example.com", true);
+    claims["name"] = json!("Jane");
+    let redirect = jane.start_oidc_login().await;
+    let callback = provider.authorize(&location(&redirect), claims);
+    assert_eq!(location(&jane.finish_oidc_login(&callback).await), "/todos");
+    let user = jane.user().await.ok();
+    assert_eq!(
+        (user.name.as_str(), user.email.as_str()),
+        ("Jane", "jane@example.com")
+    );
+    assert!(user.email_verified_at.is_some());
+
+    // linked by the subject, the email at the provider may change
+    jane.logout().await.ok();
+    let redirect = jane.start_oidc_login().await;
+    let callback = provider.authorize(
+        &location(&redirect),
+        account("1", "jane@example.org", true),
+    );
+    location(&jane.finish_oidc_login(&callback).await);
+    assert_eq!(jane.user().await.ok().id, user.id);
+
+    let mut john =
+        test_support::client_with(&backend, provider.settings()).await;
+    john.register("John", "john@example.com", "secret").await.ok();
+    // anyone could have registered the unverified email, only its user may
+    // link it
+    let redirect = john.start_oidc_login().await;
+    let callback = provider.authorize(
+        &location(&redirect),
+        account("2", "john@example.com", true),
+    );
+    john.finish_oidc_login(&callback).await.err(StatusCode::FORBIDDEN);
+    john.user().await.err(StatusCode::UNAUTHORIZED);
+
+    john.login("john@example.com", "secret").await.ok();
+    let registered = john.user().await.ok();
+    let redirect = john.start_oidc_login().await;
+    let callback = provider.authorize(
+        &location(&redirect),
+        account("2", "john@example.com", true),
+    );
+    location(&john.finish_oidc_login(&callback).await);
+    let linked = john.user().await.ok();
+    assert_eq!(linked.id, registered.id);
+    assert!(linked.email_verified_at.is_some());
+    // the password keeps working
+    john.logout().await.ok();
+    john.login("john@example.com", "secret").await.ok();
+
+    let mut mallory =
+        test_support::client_with(&backend, provider.settings()).await;
+    let redirect = mallory.start_oidc_login().await;
+    let callback = provider.authorize(
+        &location(&redirect),
+        account("3", "jane@example.com", false),
+    );
+    mallory.finish_oidc_login(&callback).await.err(StatusCode::FORBIDDEN);
+    mallory.user().await.err(StatusCode::UNAUTHORIZED);
+}
+
+#[actix_rt::test]
+async fn oidc_logins_keep_the_second_factor() {
+    let provider = MockProvider::start().await;
+    let backend = MemoryBackend::new();
+    let mut jane =
+        test_support::client_with(&backend, provider.settings()).await;
+    let redirect = jane.start_oidc_login().await;
+    let callback = provider.authorize(
+        &location(&redirect),
+        account("1", "jane@example.com", true),
+    );
+    location(&jane.finish_oidc_login(&callback).await);
+    let secret = jane.enroll_totp().await.ok().secret;
+    let code = totp::code(&secret, totp::step(Utc::now())).unwrap();
+    jane.confirm_totp(&code).await.ok();
+    jane.logout().await.ok();
+
+    // another account at the provider would skip the second factor
+    let mut mallory =
+        test_support::client_with(&backend, provider.settings()).await;
+    let redirect = mallory.start_oidc_login().await;
+    let callback = provider.authorize(
+        &location(&redirect),
+        account("2", "jane@example.com", true),
+    );
+    mallory.finish_oidc_login(&callback).await.err(StatusCode::FORBIDDEN);
+    mallory.user().await.err(StatusCode::UNAUTHORIZED);
+
+    // the linked account was trusted before the second factor was added
+    let redirect = jane.start_oidc_login().await;
+    let callback = provider.authorize(
+        &location(&redirect),
+        account("1", "jane@example.com", true),
+    );
+    location(&jane.finish_oidc_login(&callback).await);
+    jane.user().await.ok();
+}
+
+#[actix_rt::test]
+async fn oidc_callbacks_are_verified() {
+    let provider = MockProvider::start().await;
+    let backend = MemoryBackend::new();
+    let mut jane =
+        test_support::client_with(&backend, provider.settings()).await;
+    let claims = account("1", "jane@example.com", true);
+
+    // without a login started by this browser
+    let redirect = jane.start_oidc_login().await;
+    let callback = provider.authorize(&location(&redirect), claims.clone());
+    let mut other =
+        test_support::client_with(&backend, provider.settings()).await;
+    other.finish_oidc_login(&callback).await.err(StatusCode::UNAUTHORIZED);
+
+    let state = callback.split("state=").nth(1).unwrap();
+    jane.finish_oidc_login(&format!("error=access_denied&state={state}"))
+        .await
+        .err(StatusCode::UNAUTHORIZED);
+
+    for tampered in [
+        json!({ "aud": "another-client" }),
+        json!({ "iss": "https://evil.example.com" }),
+        json!({ "nonce": "replayed" }),
+        json!({ "exp": Utc::now().timestamp() - 300 }),
+    ] {
+        let redirect = jane.start_oidc_login().await;
+        let mut claims = claims.clone();
+        claims
+            .as_object_mut()
+            .unwrap()
+            .extend(tampered.as_object().unwrap().clone());
+        let callback = provider.authorize(&location(&redirect), claims);
+        jane.finish_oidc_login(&callback).await.err(StatusCode::UNAUTHORIZED);
+    }
+
+    let redirect = jane.start_oidc_login().await;
+    let callback = provider.authorize(&location(&redirect), claims.clone());
+    let (code, _) = callback.split_once('&').unwrap();
+    jane.finish_oidc_login(&format!("{code}&state=guessed"))
+        .await
+        .err(StatusCode::UNAUTHORIZED);
+    jane.user().await.err(StatusCode::UNAUTHORIZED);
+
+    let redirect = jane.start_oidc_login().await;
+    let callback = provider.authorize(&location(&redirect), claims);
+    location(&jane.finish_oidc_login(&callback).await);
+    jane.user().await.ok();
+    // every callback is only accepted once
+    jane.logout().await.ok();
+    jane.finish_oidc_login(&callback).await.err(StatusCode::UNAUTHORIZED);
+}
+
+#[actix_rt::test]
+async fn oidc_logins_require_a_provider() {
+    let backend = MemoryBackend::new();
+    let mut client = test_support::client(&backend).await;
+
+    client.start_oidc_login().await.err(StatusCode::NOT_FOUND);
+    client
+        .finish_oidc_login("code=code&state=state")
+        .await
+        .err(StatusCode::NOT_FOUND);
+}
@@ -0,0 +1,286 @@
+use app::{
+    controllers::common,
+    repository::{
+        audit::{AuditContext, AuditRepository, NewSecurityEvent},
+        session::SessionRepository,
+        todo::TodoRepository,
+        user::UserRepository,
+        Backend,
+    },
+    seed::SeedOptions,
+};
+use clap::{Args, Subcommand};
+use color_eyre::eyre::{self, bail, eyre, WrapErr};
+use shared::models::{
+    audit::SecurityEventKind,
+    user::{CreateUser, Role, User},
+};
+
+#[derive(Subcommand, Debug)]
+pub enum UserCommand {
+    /// Create a new user, the password is read from the terminal
+    Create {
+        #[arg(long)]
+        name: String,
+        #[arg(long)]
+        email: String,
+    },
+    /// Set a new password for a user, the password is read from the terminal
+    ResetPassword {
+        #[arg(long)]
+        email: String,
+    },
+    /// Give a user a role, e.g. to make the first administrator
+    SetRole {
+        #[arg(long)]
+        email: String,
+        /// Either `user` or `admin`
+        #[arg(long)]
+        role: Role,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum SessionCommand {
+    /// List all sessions that are not expired
+    List {
+        /// Only list the sessions of this user
+        #[arg(long)]
+        email: Option<String>,
+    },
+    /// Revoke a single session or all sessions of a user
+    Revoke {
+        /// Key of the session to revoke
+        #[arg(
+            long,
+            required_unless_present = "email",
+            conflicts_with = "email"
+        )]
+        key: Option<String>,
+        /// Revoke all sessions of this user
+        #[arg(long)]
+        email: Option<String>,
+    },
+    /// Delete all expired sessions
+    PurgeExpired,
+}
+
+#[derive(Args, Debug)]
+pub struct SeedArgs {
+    /// Number of users to create
+    #[arg(long, default_value_t = 10)]
+    users: usize,
+    /// Minimum number of todos per user
+    #[arg(long, default_value_t = 0)]
+    min_todos: usize,
+    /// Maximum number of todos per user
+    #[arg(long, default_value_t = 20)]
+    max_todos: usize,
+    /// Probability that a todo is marked as done
+    #[arg(long, default_value_t = 0.4)]
+    done_ratio: f64,
+    /// Spread the creation of the todos over this many days in the past
+    #[arg(long, default_value_t = 90)]
+    days: u32,
+    /// Password of all created users
+    #[arg(long, default_value = "lentos")]
+    password: String,
+    /// Seed of the random generator, a random seed is used if omitted
+    #[arg(long)]
+    seed: Option<u64>,
+}
+
+pub async fn user<B: Backend>(
+    command: &UserCommand,
+    backend: &B,
+) -> eyre::Result<()> {
+    let users = backend.user_repository();
+    // changes made here have no actor and no request
+    let audit = AuditContext::default();
+
+    match command {
+        UserCommand::Create { name, email } => {
+            let password = common::hash_password(&read_password()?).await.0?;
+
+            users
+                .create_user(
+                    &CreateUser {
+                        name: name.clone(),
+                        email: email.clone(),
+                        password,
+                    },
+                    &audit,
+                )
+                .await
+                .0
+                .wrap_err_with(|| format!("Failed to create user {email}"))?;
+
+            println!("Created user {email}.");
+        }
+        UserCommand::ResetPassword { email } => {
+            let user = find_user(&users, email).await?;
+            let password = common::hash_password(&read_password()?).await.0?;
+
+            users.set_password(&user.id, &password, &audit).await.0?;
+            backend
+                .audit_repository()
+                .record_security_event(&NewSecurityEvent {
+                    kind: SecurityEventKind::PasswordChanged,
+                    user_id: Some(user.id),
+                    ..Default::default()
+                })
+                .await
+                .0?;
+
+            println!("Changed the password of {email}.");
+        }
+        UserCommand::SetRole { email, role } => {
+            let user = find_user(&users, email).await?;
+            users.set_role(&user.id, *role, &audit).await.0?;
+
+            println!("{email} is now a {}.", role.as_str());
+        }
+    }
+
+    Ok(())
+}
+
+pub async fn session<B: Backend>(
+    command: &SessionCommand,
+    backend: &B,
+) -> eyre::Result<()> {
+    let users = backend.user_repository();
+    let sessions = backend.session_repository();
+
+    match command {
+        SessionCommand::List { email } => {
+            let user_id = match email {
+                Some(email) => Some(find_user(&users, email).await?.id),
+                None => None,
+            };
+
+            for session in sessions.list_sessions(user_id).await? {
+                let user_id = session
+                    .user_id
+                    .map_or_else(|| "-".to_string(), |id| id.to_string());
+                println!(
+                    "{}  user {:<8} expires {}",
+                    session.key, user_id, session.expires_at
+                );
+            }
+        }
+        SessionCommand::Revoke { key: Some(key), .. } => {
+            let key = key
+                .clone()
+                .try_into()
+                .map_err(|_| eyre!("`{key}` is not a valid session key"))?;
+            sessions.db_delete(&key).await?;
+
+            println!("Revoked the session.");
+        }
+        SessionCommand::Revoke { email: Some(email), .. } => {
+            let user = find_user(&users, email).await?;
+            let revoked = sessions.delete_user_sessions(user.id).await?;
+            backend
+                .audit_repository()
+                .record_security_event(&NewSecurityEvent {
+                    kind: SecurityEventKind::SessionsRevoked,
+                    user_id: Some(user.id),
+                    ..Default::default()
+                })
+                .await
+                .0?;
+
+            println!("Revoked {revoked} session(s) of {email}.");
+        }
+        SessionCommand::Revoke { .. } => {
+            bail!("Pass either --key or --email")
+        }
+        SessionCommand::PurgeExpired => {
+            let purged = sessions.delete_expired_sessions().await?;
+
+            println!("Purged {purged} expired session(s).");
+        }
+    }
+
+    Ok(())
+}
+
+pub async fn stats<B: Backend>(backend: &B) -> eyre::Result<()> {
+    let users = backend.user_repository();
+    let todos = backend.todo_repository();
+    let sessions = backend.session_repository();
+
+    let user_count = users.count_users().await.0?;
+    let todo_stats = todos.todo_stats().await.0?;
+    let session_count = sessions.list_sessions(None).await?.len();
+
+    println!("users:           {user_count}");
+    println!("todos:           {}", todo_stats.total);
+    println!("  done:          {}", todo_stats.done);
+    println!("active sessions: {session_count}");
+
+    Ok(())
+}
+
+pub async fn seed<B: Backend>(
+    args: &SeedArgs,
+    backend: &B,
+) -> eyre::Result<()> {
+    if args.min_todos > args.max_todos {
+        bail!("--min-todos must not exceed --max-todos");
+    }
+    if !(0.0..=1.0).contains(&args.done_ratio) {
+        bail!("--done-ratio has to be between 0 and 1");
+    }
+
+    let options = SeedOptions {
+        users: args.users,
+        todos_per_user: args.min_todos..args.max_todos + 1,
+        done_ratio: args.done_ratio,
+        history_days: args.days,
+        password: args.password.clone(),
+        seed: args.seed.unwrap_or_else(rand::random),
+    };
+    println!("Seeding with --seed {}", options.seed);
+
+    let summary = app::seed::seed(
+        &backend.user_repository(),
+        &backend.todo_repository(),
+        &options,
+    )
+    .await
+    .0?;
+
+    println!(
+        "Created {} users and {} todos of which {} are done.",
+        summary.users, summary.todos, summary.done
+    );
+
+    Ok(())
+}
+
+async fn find_user(
+    users: &impl UserRepository,
+    email: &str,
+) -> eyre::Result<User> {
+    users
+        .get_user_by_email(email)
+        .await
+        .0
+        .wrap_err_with(|| format!("There is no user with the email {email}"))
+}
+
+fn read_password() -> eyre::Result<String> {
+    let password = rpassword::prompt_password("Password: ")?;
+    let confirmation = rpassword::prompt_password("Repeat password: ")?;
+
+    if password.is_empty() {
+        bail!("The password must not be empty");
+    }
+    if password != confirmation {
+        bail!("The passwords do not match");
+    }
+
+    Ok(password)
+}
@@ -0,0 +1,772 @@
+use std::{net::SocketAddr, path::PathBuf, sync::Arc, time::Duration};
+
+use actix_web::{cookie::SameSite, http::Method};
+use app::{
+    blob::{BlobStore, LocalBlobStore, S3BlobStore, S3Settings},
+    controllers::{
+        api::attachment::AttachmentLimits,
+        common::{
+            login_throttle::LoginThrottle,
+            oidc::{OidcProvider, OidcSettings},
+            rate_limit::{RateLimit, RateLimiter, RouteRateLimit},
+            webauthn::{self, Webauthn},
+        },
+    },
+    mail::{FileMailer, Mailer, SmtpMailer, SmtpSettings, SmtpTls},
+};
+use clap::{Parser, Subcommand};
+use color_eyre::eyre::{self, bail, ensure, WrapErr};
+use serde::Deserialize;
+use tracing_subscriber::filter::LevelFilter;
+
+use crate::{
+    admin::{SeedArgs, SessionCommand, UserCommand},
+    migrate::MigrateCommand,
+};
+
+/// Name of the config file that is picked up from the working directory when
+/// no file was passed explicitly.
+const DEFAULT_CONFIG_FILE: &str = "lentos.toml";
+
+/// Prefix of the environment variables that override the config file,
+/// e.g. `LENTOS_DATABASE__URL` overrides `database.url`.
+const ENV_PREFIX: &str = "LENTOS";
+
+/// Every setting can be given in the config file, overridden by an
+/// environment variable and finally by one of the flags below.
+#[derive(Parser, Debug)]
+#[command(version, about = "The lentos backend server.")]
+pub struct Cli {
+    /// Path to a TOML config file [default: ./lentos.toml if it exists]
+    #[arg(short, long, env = "LENTOS_CONFIG", global = true)]
+    pub config: Option<PathBuf>,
+
+    /// Socket address the server listens on
+    #[arg(long, global = true)]
+    pub bind_address: Option<SocketAddr>,
+
+    /// Database url, either postgres://… or sqlite://…
+    #[arg(long, global = true)]
+    pub database_url: Option<String>,
+
+    /// Path to the PEM encoded TLS certificate chain
+    #[arg(long, global = true)]
+    pub cert_path: Option<PathBuf>,
+
+    /// Path to the PEM encoded PKCS#8 TLS private key
+    #[arg(long, global = true)]
+    pub key_path: Option<PathBuf>,
+
+    /// Default log level (error, warn, info, debug or trace)
+    #[arg(long, global = true)]
+    pub log_level: Option<String>,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Run the api server (default)
+    Serve,
+    /// Manage the database schema
+    #[command(subcommand)]
+    Migrate(MigrateCommand),
+    /// Manage user accounts
+    #[command(subcommand)]
+    User(UserCommand),
+    /// Manage login sessions
+    #[command(subcommand)]
+    Session(SessionCommand),
+    /// Print usage statistics
+    Stats,
+    /// Fill the database with generated demo data
+    Seed(SeedArgs),
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub server: ServerConfig,
+    pub tls: TlsConfig,
+    pub database: DatabaseConfig,
+    pub cookie: CookieConfig,
+    pub login_throttle: LoginThrottleConfig,
+    pub rate_limit: RateLimitConfig,
+    pub mail: MailConfig,
+    pub attachments: AttachmentsConfig,
+    pub webauthn: WebauthnConfig,
+    /// Single sign-on is disabled unless a provider is configured.
+    pub oidc: Option<OidcConfig>,
+    pub log: LogConfig,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    pub bind_address: SocketAddr,
+    /// Number of actix workers, defaults to the number of available cores.
+    pub workers: Option<usize>,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self { bind_address: ([127, 0, 0, 1], 8443).into(), workers: None }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct TlsConfig {
+    pub cert_path: PathBuf,
+    pub key_path: PathBuf,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct DatabaseConfig {
+    /// The scheme of the url selects the backend, e.g.
+    /// `postgres://user@localhost/lentos` or `sqlite://lentos.db`.
+    pub url: String,
+    pub min_connections: u32,
+    pub max_connections: u32,
+    pub acquire_timeout_secs: u64,
+    /// Apply pending migrations when the server starts.
+    pub auto_migrate: bool,
+}
+
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        Self {
+            url: String::new(),
+            min_connections: 0,
+            max_connections: 10,
+            acquire_timeout_secs: 30,
+            auto_migrate: false,
+        }
+    }
+}
+
+/// Storage backends that `database.url` can point to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DatabaseKind {
+    Postgres,
+    Sqlite,
+}
+
+impl DatabaseConfig {
+    pub fn kind(&self) -> eyre::Result<DatabaseKind> {
+        // the url is not part of the errors, it may contain a password
+        match self.url.split_once(':').map(|(scheme, _)| scheme) {
+            Some("postgres" | "postgresql") => Ok(DatabaseKind::Postgres),
+            Some("sqlite") if cfg!(feature = "sqlite") => {
+                Ok(DatabaseKind::Sqlite)
+            }
+            Some("sqlite") => bail!(
+                "database.url points to a SQLite database, but this binary \
+                 was built without the `sqlite` feature"
+            ),
+            _ => {
+                bail!("database.url has to start with postgres:// or sqlite://")
+            }
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct CookieConfig {
+    /// Key used to sign and encrypt the session cookie. Has to be at least 64
+    /// bytes long.
+    pub signing_key: String,
+    pub name: String,
+    pub secure: bool,
+    pub same_site: SameSitePolicy,
+    pub domain: Option<String>,
+}
+
+impl Default for CookieConfig {
+    fn default() -> Self {
+        Self {
+            signing_key: String::new(),
+            name: "id".to_string(),
+            secure: true,
+            same_site: SameSitePolicy::Strict,
+            domain: None,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum SameSitePolicy {
+    Strict,
+    Lax,
+    None,
+}
+
+impl From<SameSitePolicy> for SameSite {
+    fn from(policy: SameSitePolicy) -> Self {
+        match policy {
+            SameSitePolicy::Strict => SameSite::Strict,
+            SameSitePolicy::Lax => SameSite::Lax,
+            SameSitePolicy::None => SameSite::None,
+        }
+    }
+}
+
+/// Throttling of failed logins, counted per email address and per ip. Behind
+/// a reverse proxy all clients share the ip of the proxy.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct LoginThrottleConfig {
+    /// Failed logins that are not delayed at all.
+    pub free_attempts: i32,
+    /// Delay after the first delayed failure, doubled with every further one.
+    pub base_delay_secs: u64,
+    pub max_delay_secs: u64,
+    /// Failed logins after which logins are locked for `lockout_secs`.
+    pub lockout_after: i32,
+    pub lockout_secs: u64,
+    /// Failures are forgotten once there was none for this long.
+    pub reset_after_secs: u64,
+}
+
+impl Default for LoginThrottleConfig {
+    fn default() -> Self {
+        LoginThrottle::default().into()
+    }
+}
+
+impl From<LoginThrottle> for LoginThrottleConfig {
+    fn from(throttle: LoginThrottle) -> Self {
+        Self {
+            free_attempts: throttle.free_attempts,
+            base_delay_secs: throttle.base_delay.as_secs(),
+            max_delay_secs: throttle.max_delay.as_secs(),
+            lockout_after: throttle.lockout_after,
+            lockout_secs: throttle.lockout.as_secs(),
+            reset_after_secs: throttle.reset_after.as_secs(),
+        }
+    }
+}
+
+impl From<&LoginThrottleConfig> for LoginThrottle {
+    fn from(config: &LoginThrottleConfig) -> Self {
+        Self {
+            free_attempts: config.free_attempts,
+            base_delay: Duration::from_secs(config.base_delay_secs),
+            max_delay: Duration::from_secs(config.max_delay_secs),
+            lockout_after: config.lockout_after,
+            lockout: Duration::from_secs(config.lockout_secs),
+            reset_after: Duration::from_secs(config.reset_after_secs),
+        }
+    }
+}
+
+/// Token bucket limit of the requests of each user, or ip when logged out.
+/// Behind a reverse proxy all logged out clients share the ip of the proxy.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct RateLimitConfig {
+    /// Requests a client can send at once.
+    pub burst: u32,
+    /// Requests a client can send per minute in the long run.
+    pub per_minute: u32,
+    /// Overrides for single routes, the first match wins.
+    pub routes: Vec<RouteRateLimitConfig>,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        let limit = RateLimit::default();
+
+        Self {
+            burst: limit.burst,
+            per_minute: limit.per_minute,
+            routes: Vec::new(),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct RouteRateLimitConfig {
+    /// Prefix of the paths this limit applies to, e.g. `/api/v1/todos`.
+    pub path: String,
+    /// Restricts the limit to one http method, e.g. `POST`.
+    pub method: Option<String>,
+    pub burst: u32,
+    pub per_minute: u32,
+}
+
+impl RateLimitConfig {
+    /// Builds the limiter, every call returns one with counters of its own.
+    pub fn limiter(&self) -> eyre::Result<RateLimiter> {
+        let limit = |name: &str, burst, per_minute| {
+            ensure!(
+                burst > 0 && per_minute > 0,
+                "{name}.burst and {name}.per_minute must be greater than 0"
+            );
+            Ok(RateLimit { burst, per_minute })
+        };
+
+        let default = limit("rate_limit", self.burst, self.per_minute)?;
+        let routes = self
+            .routes
+            .iter()
+            .map(|route| {
+                let name = format!("rate_limit.routes `{}`", route.path);
+                let method = route
+                    .method
+                    .as_deref()
+                    .map(|method| {
+                        Method::from_bytes(method.to_uppercase().as_bytes())
+                            .wrap_err_with(|| {
+                                format!("{name} has an invalid method")
+                            })
+                    })
+                    .transpose()?;
+
+                Ok(RouteRateLimit {
+                    path: route.path.clone(),
+                    method,
+                    limit: limit(&name, route.burst, route.per_minute)?,
+                })
+            })
+            .collect::<eyre::Result<_>>()?;
+
+        Ok(RateLimiter::new(default, routes))
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct MailConfig {
+    pub transport: MailTransport,
+    /// Sender of all mails, e.g. `lentos <noreply@example.com>`.
+    pub from: String,
+    /// File the `file` transport appends the mails to.
+    pub path: PathBuf,
+    pub smtp: SmtpConfig,
+}
+
+impl Default for MailConfig {
+    fn default() -> Self {
+        Self {
+            transport: MailTransport::Stdout,
+            from: "lentos <noreply@localhost>".to_string(),
+            path: PathBuf::from("mails.txt"),
+            smtp: SmtpConfig::default(),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum MailTransport {
+    /// Prints the mails, for development.
+    Stdout,
+    /// Appends the mails to `mail.path`, for development.
+    File,
+    Smtp,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct SmtpConfig {
+    pub host: String,
+    pub port: u16,
+    pub tls: SmtpTlsPolicy,
+    pub username: Option<String>,
+    pub password: Option<String>,
+}
+
+impl Default for SmtpConfig {
+    fn default() -> Self {
+        Self {
+            host: "localhost".to_string(),
+            port: 587,
+            tls: SmtpTlsPolicy::StartTls,
+            username: None,
+            password: None,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum SmtpTlsPolicy {
+    None,
+    StartTls,
+    Tls,
+}
+
+impl From<SmtpTlsPolicy> for SmtpTls {
+    fn from(policy: SmtpTlsPolicy) -> Self {
+        match policy {
+            SmtpTlsPolicy::None => SmtpTls::None,
+            SmtpTlsPolicy::StartTls => SmtpTls::StartTls,
+            SmtpTlsPolicy::Tls => SmtpTls::Tls,
+        }
+    }
+}
+
+impl MailConfig {
+    pub fn mailer(&self) -> eyre::Result<Arc<dyn Mailer>> {
+        Ok(match self.transport {
+            MailTransport::Stdout => Arc::new(FileMailer::stdout(&self.from)),
+            MailTransport::File => {
+                Arc::new(FileMailer::new(&self.from, self.path.clone()))
+            }
+            MailTransport::Smtp => {
+                let credentials =
+                    match (&self.smtp.username, &self.smtp.password) {
+                        (Some(username), Some(password)) => {
+                            Some((username.clone(), password.clone()))
+                        }
+                        (None, None) => None,
+                        _ => bail!(
+                        "mail.smtp.username and mail.smtp.password have to be \
+                         set together"
+                    ),
+                    };
+                let settings = SmtpSettings {
+                    host: self.smtp.host.clone(),
+                    port: self.smtp.port,
+                    tls: self.smtp.tls.into(),
+                    credentials,
+                };
+
+                Arc::new(
+                    SmtpMailer::new(&settings, &self.from)
+                        .wrap_err("mail.smtp is invalid")?,
+                )
+            }
+        })
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct AttachmentsConfig {
+    pub store: BlobStoreKind,
+    /// Directory the `local` store keeps the files in.
+    pub path: PathBuf,
+    pub max_file_size_mb: i64,
+    /// Megabytes all attachments a user uploaded may take up.
+    pub quota_mb: i64,
+    /// Required by the `s3` store.
+    pub s3: Option<S3Config>,
+}
+
+impl Default for AttachmentsConfig {
+    fn default() -> Self {
+        Self {
+            store: BlobStoreKind::Local,
+            path: PathBuf::from("attachments"),
+            max_file_size_mb: 10,
+            quota_mb: 100,
+            s3: None,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum BlobStoreKind {
+    /// Files below `attachments.path`.
+    Local,
+    /// Objects in a bucket of an S3-compatible object storage.
+    S3,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct S3Config {
+    /// E.g. `https://s3.eu-central-1.amazonaws.com`.
+    pub endpoint: String,
+    pub bucket: String,
+    #[serde(default = "S3Config::default_region")]
+    pub region: String,
+    pub access_key_id: String,
+    pub secret_access_key: String,
+    /// Addresses the bucket in the path, e.g. for MinIO.
+    #[serde(default)]
+    pub path_style: bool,
+}
+
+impl S3Config {
+    fn default_region() -> String {
+        "us-east-1".to_string()
+    }
+}
+
+impl AttachmentsConfig {
+    pub fn blob_store(&self) -> eyre::Result<Arc<dyn BlobStore>> {
+        Ok(match self.store {
+            BlobStoreKind::Local => {
+                Arc::new(LocalBlobStore::new(self.path.clone()))
+            }
+            BlobStoreKind::S3 => {
+                let Some(s3) = &self.s3 else {
+                    bail!("attachments.store = \"s3\" requires attachments.s3");
+                };
+                let settings = S3Settings {
+                    endpoint: s3
+                        .endpoint
+                        .parse()
+                        .wrap_err("attachments.s3.endpoint is not a url")?,
+                    bucket: s3.bucket.clone(),
+                    region: s3.region.clone(),
+                    access_key_id: s3.access_key_id.clone(),
+                    secret_access_key: s3.secret_access_key.clone(),
+                    path_style: s3.path_style,
+                };
+
+                Arc::new(
+                    S3BlobStore::new(settings)
+                        .wrap_err("attachments.s3 is invalid")?,
+                )
+            }
+        })
+    }
+
+    pub fn limits(&self) -> eyre::Result<AttachmentLimits> {
+        ensure!(
+            self.max_file_size_mb > 0 && self.quota_mb >= self.max_file_size_mb,
+            "attachments.max_file_size_mb must be greater than 0 and must not \
+             exceed attachments.quota_mb"
+        );
+
+        Ok(AttachmentLimits {
+            max_file_size: self.max_file_size_mb << 20,
+            quota: self.quota_mb << 20,
+        })
+    }
+}
+
+/// The relying party that passkeys are registered with.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct WebauthnConfig {
+    /// Domain the passkeys are bound to, the domain of `origin` or one of
+    /// its parents. Changing it invalidates all registered passkeys.
+    pub rp_id: String,
+    /// Url the frontend is served from, e.g. `https://lentos.example.com`.
+    pub origin: String,
+    /// Shown by the authenticator when a passkey is created.
+    pub rp_name: String,
+}
+
+impl Default for WebauthnConfig {
+    fn default() -> Self {
+        Self {
+            rp_id: "localhost".to_string(),
+            origin: "https://localhost:8443".to_string(),
+            rp_name: "lentos".to_string(),
+        }
+    }
+}
+
+impl WebauthnConfig {
+    pub fn relying_party(&self) -> eyre::Result<Arc<Webauthn>> {
+        webauthn::relying_party(&self.rp_id, &self.origin, &self.rp_name)
+            .map(Arc::new)
+            .wrap_err("webauthn is invalid")
+    }
+}
+
+/// An OpenID Connect provider for single sign-on.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct OidcConfig {
+    /// e.g. `https://sso.example.com/realms/acme`, exactly as the provider
+    /// states it in its metadata.
+    pub issuer: String,
+    pub client_id: String,
+    /// Left out for public clients.
+    pub client_secret: Option<String>,
+    /// The callback url registered at the provider, e.g.
+    /// `https://lentos.example.com/api/v1/users/oidc/callback`.
+    pub redirect_url: String,
+    #[serde(default = "OidcConfig::default_scopes")]
+    pub scopes: Vec<String>,
+    /// Where the browser is sent after a successful login.
+    #[serde(default = "OidcConfig::default_post_login_redirect")]
+    pub post_login_redirect: String,
+}
+
+impl OidcConfig {
+    fn default_scopes() -> Vec<String> {
+        ["openid", "email", "profile"].map(String::from).to_vec()
+    }
+
+    fn default_post_login_redirect() -> String {
+        "/".to_string()
+    }
+
+    pub fn provider(&self) -> eyre::Result<Arc<OidcProvider>> {
+        ensure!(
+            self.scopes.iter().any(|scope| scope == "openid"),
+            "oidc.scopes has to contain `openid`"
+        );
+        let redirect_url = self
+            .redirect_url
+            .parse()
+            .wrap_err("oidc.redirect_url is not a valid url")?;
+        let settings = OidcSettings {
+            issuer: self.issuer.clone(),
+            client_id: self.client_id.clone(),
+            client_secret: self.client_secret.clone(),
+            redirect_url,
+            scopes: self.scopes.clone(),
+            post_login_redirect: self.post_login_redirect.clone(),
+        };
+
+        Ok(Arc::new(OidcProvider::new(settings)?))
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct LogConfig {
+    pub level: String,
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        Self { level: "debug".to_string() }
+    }
+}
+
+impl LogConfig {
+    pub fn level_filter(&self) -> eyre::Result<LevelFilter> {
+        self.level.parse().wrap_err_with(|| {
+            format!(
+                "log.level `{}` is invalid, use one of off, error, warn, \
+                 info, debug or trace",
+                self.level
+            )
+        })
+    }
+}
+
+impl Config {
+    /// Loads the config by layering the config file, the `LENTOS_*`
+    /// environment variables and the command line flags on top of each other
+    /// and validates the settings shared by all commands.
+    pub fn load(cli: &Cli) -> eyre::Result<Self> {
+        let file = match &cli.config {
+            Some(path) => config::File::from(path.as_path()).required(true),
+            None => config::File::with_name(DEFAULT_CONFIG_FILE)
+                .format(config::FileFormat::Toml)
+                .required(false),
+        };
+
+        let config: Config = config::Config::builder()
+            .add_source(file)
+            .add_source(
+                config::Environment::with_prefix(ENV_PREFIX)
+                    .prefix_separator("_")
+                    .separator("__")
+                    // the config file location is not part of the config
+                    .source(Some(
+                        std::env::vars()
+                            .filter(|(key, _)| key != "LENTOS_CONFIG")
+                            .collect(),
+                    )),
+            )
+            .set_override_option(
+                "server.bind_address",
+                cli.bind_address.map(|address| address.to_string()),
+            )?
+            .set_override_option("database.url", cli.database_url.clone())?
+            .set_override_option(
+                "tls.cert_path",
+                cli.cert_path.as_ref().map(|p| p.display().to_string()),
+            )?
+            .set_override_option(
+                "tls.key_path",
+                cli.key_path.as_ref().map(|p| p.display().to_string()),
+            )?
+            .set_override_option("log.level", cli.log_level.clone())?
+            .build()
+            .wrap_err("Failed to read the configuration")?
+            .try_deserialize()
+            .wrap_err("The configuration is invalid")?;
+
+        config.validate()?;
+
+        Ok(config)
+    }
+
+    fn validate(&self) -> eyre::Result<()> {
+        ensure!(
+            !self.database.url.is_empty(),
+            "database.url is not set, pass --database-url or set \
+             LENTOS_DATABASE__URL"
+        );
+        self.database.kind()?;
+        ensure!(
+            self.database.min_connections <= self.database.max_connections,
+            "database.min_connections ({}) must not exceed \
+             database.max_connections ({})",
+            self.database.min_connections,
+            self.database.max_connections
+        );
+        ensure!(
+            self.database.max_connections > 0,
+            "database.max_connections must be greater than 0"
+        );
+        self.log.level_filter()?;
+
+        Ok(())
+    }
+
+    /// Validates the settings that are only needed to run the server.
+    pub fn validate_server(&self) -> eyre::Result<()> {
+        ensure!(
+            self.cookie.signing_key.len() >= 64,
+            "cookie.signing_key has to be at least 64 bytes long, but is {} \
+             bytes long",
+            self.cookie.signing_key.len()
+        );
+        ensure!(!self.cookie.name.is_empty(), "cookie.name must not be empty");
+        ensure!(
+            !matches!(self.cookie.same_site, SameSitePolicy::None)
+                || self.cookie.secure,
+            "cookie.same_site = \"none\" requires cookie.secure = true"
+        );
+        ensure!(
+            self.server.workers != Some(0),
+            "server.workers must be greater than 0"
+        );
+        ensure!(
+            self.login_throttle.free_attempts >= 0,
+            "login_throttle.free_attempts must not be negative"
+        );
+        ensure!(
+            self.login_throttle.lockout_after
+                > self.login_throttle.free_attempts,
+            "login_throttle.lockout_after must exceed \
+             login_throttle.free_attempts"
+        );
+        self.rate_limit.limiter()?;
+        self.mail.mailer()?;
+        self.attachments.blob_store()?;
+        self.attachments.limits()?;
+        self.webauthn.relying_party()?;
+        if let Some(oidc) = &self.oidc {
+            // the callback is a cross-site navigation from the provider
+            ensure!(
+                !matches!(self.cookie.same_site, SameSitePolicy::Strict),
+                "oidc requires cookie.same_site = \"lax\", strict session \
+                 cookies are not sent along with the callback"
+            );
+            oidc.provider()?;
+        }
+
+        Ok(())
+    }
+}
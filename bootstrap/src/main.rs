@@ -1,34 +1,30 @@
-use std::io::{BufReader, Error, ErrorKind};
+use std::{fs::File, io::BufReader, path::Path, time::Duration};
 
-use actix_identity::IdentityMiddleware;
-use actix_session::{
-    config::{CookieContentSecurity, PersistentSession},
-    SessionMiddleware,
-};
-use actix_web::{
-    cookie::{Key, SameSite},
-    middleware::{self, Compat},
-    App, HttpServer,
-};
+use actix_web::{cookie::Key, HttpServer};
 
 use app::{
-    controllers::{self},
-    repository::{
-        session::PostgresSessionRepository,
-        todo::{self},
-        user,
-    },
+    repository::{Backend, PostgresBackend},
+    server::{AppSettings, SessionSettings},
 };
+use clap::Parser;
+use color_eyre::eyre::{self, eyre, WrapErr};
 use rustls::{Certificate, PrivateKey, ServerConfig};
 use rustls_pemfile::pkcs8_private_keys;
-use sqlx::{Pool, Postgres};
+use sqlx::{
+    migrate::{Migrate, Migrator},
+    pool::PoolOptions,
+    Database, Pool,
+};
 use tracing::subscriber::set_global_default;
-use tracing_subscriber::Registry;
+use tracing_subscriber::{filter::LevelFilter, Registry};
 
-#[macro_use]
-extern crate dotenv_codegen;
+mod admin;
+mod config;
+mod migrate;
 
-fn install_tracing() {
+use config::{Cli, Command, Config, DatabaseConfig, DatabaseKind};
+
+fn install_tracing(level: LevelFilter) {
     use tracing_error::ErrorLayer;
     use tracing_log::LogTracer;
     use tracing_subscriber::filter::*;
@@ -42,7 +38,7 @@ fn install_tracing() {
     let lib_filter_layer = Targets::new()
         .with_target("h2", LevelFilter::ERROR)
         .with_target("hyper", LevelFilter::ERROR)
-        .with_default(LevelFilter::DEBUG);
+        .with_default(level);
 
     let subscriber = Registry::default()
         .with(lib_filter_layer)
@@ -53,79 +49,176 @@ fn install_tracing() {
 }
 
 #[actix_web::main]
-async fn main() -> std::io::Result<()> {
-    install_tracing();
-    color_eyre::install().unwrap();
-    let pool =
-        Pool::<Postgres>::connect(dotenv!("DATABASE_URL")).await.unwrap();
-
-    HttpServer::new(move || {
-        let todo_repository = todo::PostgresTodoRepository::new(pool.clone());
-        let todo_repository = actix_web::web::Data::new(todo_repository);
-
-        let user_repository = user::PostgresUserRepository::new(pool.clone());
-        let user_repository = actix_web::web::Data::new(user_repository);
-
-        let session_repository = PostgresSessionRepository::new(pool.clone());
-
-        let cookie_priv_key = Key::from(dotenv!("SIGNING_KEY").as_bytes());
-
-        App::new()
-            .wrap(Compat::new(middleware::Logger::default()))
-            .wrap(Compat::new(middleware::Compress::default()))
-            .wrap(Compat::new(
-                IdentityMiddleware::builder()
-                    //.visit_deadline(Some(Duration::from_secs(config.cookie_timeout)))
-                    .logout_behaviour(
-                        actix_identity::config::LogoutBehaviour::PurgeSession,
-                    )
-                    .build(),
-            ))
-            .wrap(Compat::new(
-                SessionMiddleware::builder(session_repository, cookie_priv_key)
-                    .session_lifecycle(PersistentSession::default())
-                    .cookie_content_security(CookieContentSecurity::Private)
-                    .cookie_same_site(SameSite::Strict)
-                    .cookie_path("/".into())
-                    .cookie_domain(None)
-                    .cookie_secure(true)
-                    .cookie_http_only(true)
-                    .build(),
-            ))
-            .app_data(todo_repository)
-            .app_data(user_repository)
-            .configure(controllers::api::service)
-    })
-    .bind_rustls("127.0.0.1:8443", rustls_setup())
-    .map_err(|e| Error::new(ErrorKind::Other, e))?
-    .run()
-    .await
+async fn main() -> eyre::Result<()> {
+    color_eyre::install()?;
+
+    let cli = Cli::parse();
+    let config = Config::load(&cli)?;
+
+    install_tracing(config.log.level_filter()?);
+
+    match config.database.kind()? {
+        DatabaseKind::Postgres => {
+            let pool = pool_options(&config.database)
+                .connect(&config.database.url)
+                .await
+                .wrap_err(
+                    "Failed to connect to the database at database.url",
+                )?;
+            let backend = PostgresBackend::new(pool.clone());
+
+            run(cli.command, config, backend, pool, &migrate::POSTGRES_MIGRATOR)
+                .await
+        }
+        #[cfg(feature = "sqlite")]
+        DatabaseKind::Sqlite => {
+            use std::str::FromStr;
+
+            use app::repository::sqlite::SqliteBackend;
+            use sqlx::sqlite::SqliteConnectOptions;
+
+            let options = SqliteConnectOptions::from_str(&config.database.url)
+                .wrap_err("database.url is not a valid SQLite url")?
+                .create_if_missing(true);
+            let pool = pool_options(&config.database)
+                .connect_with(options)
+                .await
+                .wrap_err("Failed to open the database at database.url")?;
+            let backend = SqliteBackend::new(pool.clone());
+
+            run(cli.command, config, backend, pool, &migrate::SQLITE_MIGRATOR)
+                .await
+        }
+        #[cfg(not(feature = "sqlite"))]
+        DatabaseKind::Sqlite => unreachable!("rejected by Config::load"),
+    }
+}
+
+fn pool_options<DB: Database>(config: &DatabaseConfig) -> PoolOptions<DB> {
+    PoolOptions::new()
+        .min_connections(config.min_connections)
+        .max_connections(config.max_connections)
+        .acquire_timeout(Duration::from_secs(config.acquire_timeout_secs))
 }
 
-fn rustls_setup() -> ServerConfig {
+async fn run<B, DB>(
+    command: Option<Command>,
+    config: Config,
+    backend: B,
+    pool: Pool<DB>,
+    migrator: &'static Migrator,
+) -> eyre::Result<()>
+where
+    B: Backend,
+    DB: Database,
+    DB::Connection: Migrate,
+{
+    match &command {
+        None | Some(Command::Serve) => {
+            serve(config, backend, pool, migrator).await
+        }
+        Some(Command::Migrate(command)) => {
+            migrate::run(command, migrator, &pool).await
+        }
+        Some(Command::User(command)) => admin::user(command, &backend).await,
+        Some(Command::Session(command)) => {
+            admin::session(command, &backend).await
+        }
+        Some(Command::Stats) => admin::stats(&backend).await,
+        Some(Command::Seed(args)) => admin::seed(args, &backend).await,
+    }
+}
+
+async fn serve<B, DB>(
+    config: Config,
+    backend: B,
+    pool: Pool<DB>,
+    migrator: &'static Migrator,
+) -> eyre::Result<()>
+where
+    B: Backend,
+    DB: Database,
+    DB::Connection: Migrate,
+{
+    config.validate_server()?;
+
+    let tls_config = rustls_setup(&config.tls.cert_path, &config.tls.key_path)?;
+
+    migrate::ensure_schema(migrator, &pool, config.database.auto_migrate)
+        .await?;
+
+    let settings = AppSettings {
+        session: SessionSettings {
+            key: Key::from(config.cookie.signing_key.as_bytes()),
+            cookie_name: config.cookie.name.clone(),
+            secure: config.cookie.secure,
+            same_site: config.cookie.same_site.into(),
+            domain: config.cookie.domain.clone(),
+        },
+        login_throttle: (&config.login_throttle).into(),
+        // built once, all workers share its counters
+        rate_limiter: config.rate_limit.limiter()?,
+        mailer: config.mail.mailer()?,
+        blob_store: config.attachments.blob_store()?,
+        attachment_limits: config.attachments.limits()?,
+        webauthn: config.webauthn.relying_party()?,
+        oidc: config.oidc.as_ref().map(|oidc| oidc.provider()).transpose()?,
+    };
+
+    let mut server =
+        HttpServer::new(move || app::server::app(&backend, &settings));
+
+    if let Some(workers) = config.server.workers {
+        server = server.workers(workers);
+    }
+
+    tracing::info!("Listening on https://{}", config.server.bind_address);
+
+    server
+        .bind_rustls(config.server.bind_address, tls_config)
+        .wrap_err_with(|| {
+            format!("Failed to bind to {}", config.server.bind_address)
+        })?
+        .run()
+        .await
+        .map_err(Into::into)
+}
+
+fn rustls_setup(
+    cert_path: &Path,
+    key_path: &Path,
+) -> eyre::Result<ServerConfig> {
     // init server config builder with safe defaults
     let config =
         ServerConfig::builder().with_safe_defaults().with_no_client_auth();
 
-    let cert_path = dotenv!("CERT_PATH");
-    let key_path = dotenv!("KEY_PATH");
-
     // load TLS key/cert files
     let cert_file =
-        &mut BufReader::new(std::fs::File::open(cert_path).unwrap());
-    let key_file = &mut BufReader::new(std::fs::File::open(key_path).unwrap());
+        &mut BufReader::new(File::open(cert_path).wrap_err_with(|| {
+            format!("Failed to open tls.cert_path `{}`", cert_path.display())
+        })?);
+    let key_file =
+        &mut BufReader::new(File::open(key_path).wrap_err_with(|| {
+            format!("Failed to open tls.key_path `{}`", key_path.display())
+        })?);
 
     // convert files to key/cert objects
     let cert_chain = rustls_pemfile::certs(cert_file)
-        .unwrap()
+        .wrap_err("Failed to parse the certificates in tls.cert_path")?
         .into_iter()
         .map(Certificate)
         .collect();
     let mut keys: Vec<PrivateKey> = pkcs8_private_keys(key_file)
-        .unwrap()
+        .wrap_err("Failed to parse the private key in tls.key_path")?
         .into_iter()
         .map(PrivateKey)
         .collect();
 
-    config.with_single_cert(cert_chain, keys.remove(0)).unwrap()
+    if keys.is_empty() {
+        return Err(eyre!("tls.key_path contains no PKCS#8 private key"));
+    }
+
+    config
+        .with_single_cert(cert_chain, keys.remove(0))
+        .wrap_err("The configured TLS certificate or key is invalid")
 }
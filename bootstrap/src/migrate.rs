@@ -0,0 +1,187 @@
+use clap::Subcommand;
+use color_eyre::eyre::{self, bail, WrapErr};
+use sqlx::{
+    migrate::{Migrate, Migration, Migrator},
+    Database, Pool,
+};
+
+/// The migrations in `database/postgres/` embedded into the binary at compile
+/// time.
+pub static POSTGRES_MIGRATOR: Migrator = sqlx::migrate!("../database/postgres");
+
+/// The migrations in `database/sqlite/` embedded into the binary at compile
+/// time.
+#[cfg(feature = "sqlite")]
+pub static SQLITE_MIGRATOR: Migrator = sqlx::migrate!("../database/sqlite");
+
+#[derive(Subcommand, Debug)]
+pub enum MigrateCommand {
+    /// Apply all pending migrations
+    Up,
+    /// Revert the most recently applied migration
+    Down,
+    /// List all migrations and whether they are applied
+    Status,
+}
+
+/// State of the database schema compared to the migrations embedded into this
+/// binary.
+struct SchemaStatus {
+    applied: Vec<i64>,
+    pending: Vec<&'static Migration>,
+    /// Versions that were applied by a newer binary.
+    unknown: Vec<i64>,
+}
+
+impl SchemaStatus {
+    async fn load<DB>(
+        migrator: &'static Migrator,
+        pool: &Pool<DB>,
+    ) -> eyre::Result<Self>
+    where
+        DB: Database,
+        DB::Connection: Migrate,
+    {
+        let mut conn = pool.acquire().await?;
+        conn.ensure_migrations_table().await?;
+
+        let applied: Vec<i64> = conn
+            .list_applied_migrations()
+            .await?
+            .into_iter()
+            .map(|migration| migration.version)
+            .collect();
+
+        let pending = up_migrations(migrator)
+            .filter(|migration| !applied.contains(&migration.version))
+            .collect();
+
+        let unknown = applied
+            .iter()
+            .copied()
+            .filter(|version| !migrator.version_exists(*version))
+            .collect();
+
+        Ok(Self { applied, pending, unknown })
+    }
+}
+
+fn up_migrations(
+    migrator: &'static Migrator,
+) -> impl Iterator<Item = &'static Migration> {
+    migrator
+        .iter()
+        .filter(|migration| !migration.migration_type.is_down_migration())
+}
+
+pub async fn run<DB>(
+    command: &MigrateCommand,
+    migrator: &'static Migrator,
+    pool: &Pool<DB>,
+) -> eyre::Result<()>
+where
+    DB: Database,
+    DB::Connection: Migrate,
+{
+    match command {
+        MigrateCommand::Up => {
+            refuse_unknown(&SchemaStatus::load(migrator, pool).await?)?;
+            migrator.run(pool).await.wrap_err("Failed to apply migrations")?;
+            println!("The database schema is up to date.");
+        }
+        MigrateCommand::Down => {
+            let status = SchemaStatus::load(migrator, pool).await?;
+            refuse_unknown(&status)?;
+
+            let Some(latest) = status.applied.iter().max().copied() else {
+                println!("There is no migration to revert.");
+                return Ok(());
+            };
+            let target = status
+                .applied
+                .iter()
+                .copied()
+                .filter(|version| *version < latest)
+                .max()
+                .unwrap_or(0);
+
+            migrator
+                .undo(pool, target)
+                .await
+                .wrap_err_with(|| format!("Failed to revert {latest}"))?;
+            println!("Reverted migration {latest}.");
+        }
+        MigrateCommand::Status => {
+            let status = SchemaStatus::load(migrator, pool).await?;
+
+            for migration in up_migrations(migrator) {
+                let state = if status.applied.contains(&migration.version) {
+                    "applied"
+                } else {
+                    "pending"
+                };
+                println!(
+                    "{:<16} {:<8} {}",
+                    migration.version, state, migration.description
+                );
+            }
+            for version in &status.unknown {
+                println!(
+                    "{version:<16} {:<8} applied by a newer binary",
+                    "unknown"
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Makes sure that the database schema matches the embedded migrations before
+/// the server starts. Pending migrations are applied if `auto_migrate` is set.
+pub async fn ensure_schema<DB>(
+    migrator: &'static Migrator,
+    pool: &Pool<DB>,
+    auto_migrate: bool,
+) -> eyre::Result<()>
+where
+    DB: Database,
+    DB::Connection: Migrate,
+{
+    let status = SchemaStatus::load(migrator, pool).await?;
+    refuse_unknown(&status)?;
+
+    if status.pending.is_empty() {
+        return Ok(());
+    }
+
+    if !auto_migrate {
+        bail!(
+            "The database schema is behind this binary, {} migration(s) are \
+             pending. Run `bootstrap migrate up` or set \
+             database.auto_migrate = true.",
+            status.pending.len()
+        );
+    }
+
+    for migration in &status.pending {
+        tracing::info!(
+            "Applying migration {} {}",
+            migration.version,
+            migration.description
+        );
+    }
+    migrator.run(pool).await.wrap_err("Failed to apply migrations")
+}
+
+fn refuse_unknown(status: &SchemaStatus) -> eyre::Result<()> {
+    if !status.unknown.is_empty() {
+        bail!(
+            "The database schema is ahead of this binary, migration(s) {:?} \
+             are unknown. Upgrade lentos before using this database.",
+            status.unknown
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,6 @@
+DROP INDEX session_expires_at_index;
+DROP INDEX session_user_id_index;
+ALTER TABLE sessions DROP CONSTRAINT sessions_user_id_fkey;
+ALTER TABLE sessions
+	DROP COLUMN expires_at,
+	DROP COLUMN user_id;
@@ -0,0 +1,8 @@
+-- sessions expire after the ttl handed to the session store and remember the
+-- logged in user so that they can be listed and revoked per user
+ALTER TABLE sessions
+	ADD COLUMN user_id bigint NULL,
+	ADD COLUMN expires_at timestamptz NOT NULL DEFAULT now() + interval '1 day';
+ALTER TABLE sessions ADD CONSTRAINT sessions_user_id_fkey FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE;
+CREATE INDEX session_user_id_index ON sessions (user_id);
+CREATE INDEX session_expires_at_index ON sessions (expires_at);
@@ -0,0 +1,8 @@
+-- failed logins per email address and per ip, used to throttle password
+-- guessing
+CREATE TABLE login_attempts (
+	key varchar(320) NOT NULL,
+	failures integer NOT NULL,
+	last_failure_at timestamptz NOT NULL,
+	CONSTRAINT login_attempts_pkey PRIMARY KEY (key)
+);
@@ -0,0 +1,16 @@
+-- set once the user proved to own their email address
+ALTER TABLE users ADD COLUMN email_verified_at timestamptz NULL;
+
+-- single use tokens mailed to users, e.g. to verify their email address or
+-- to reset their password, only the sha-256 hash of a token is stored
+CREATE TABLE user_tokens (
+	token_hash char(64) NOT NULL,
+	user_id bigint NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+	purpose varchar(32) NOT NULL,
+	-- the address the token was mailed to
+	email varchar(256) NOT NULL,
+	expires_at timestamptz NOT NULL,
+	created_at timestamptz NOT NULL DEFAULT now(),
+	CONSTRAINT user_tokens_pkey PRIMARY KEY (token_hash)
+);
+CREATE INDEX user_token_user_id_index ON user_tokens (user_id, purpose);
@@ -0,0 +1,20 @@
+-- the TOTP secret of a user, two-factor authentication is enabled once it is
+-- confirmed with a first code
+CREATE TABLE user_totp (
+	user_id bigint NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+	-- base32 encoded, it has to be readable to compute the codes
+	secret varchar(64) NOT NULL,
+	confirmed_at timestamptz NULL,
+	-- the time step of the last accepted code, codes must not be replayed
+	last_used_step bigint NULL,
+	created_at timestamptz NOT NULL DEFAULT now(),
+	CONSTRAINT user_totp_pkey PRIMARY KEY (user_id)
+);
+
+-- one-time recovery codes for users who lost their authenticator, only the
+-- sha-256 hash of a code is stored and used codes are deleted
+CREATE TABLE recovery_codes (
+	user_id bigint NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+	code_hash char(64) NOT NULL,
+	CONSTRAINT recovery_codes_pkey PRIMARY KEY (user_id, code_hash)
+);
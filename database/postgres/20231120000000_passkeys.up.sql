@@ -0,0 +1,16 @@
+-- WebAuthn credentials for passwordless logins
+CREATE TABLE passkeys (
+	id bigserial NOT NULL,
+	user_id bigint NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+	-- base64url encoded, unique across all authenticators
+	credential_id text NOT NULL,
+	-- chosen by the user to tell their passkeys apart
+	name varchar(255) NOT NULL,
+	-- the public key and signature counter as serialized by webauthn-rs
+	passkey jsonb NOT NULL,
+	created_at timestamptz NOT NULL DEFAULT now(),
+	last_used_at timestamptz NULL,
+	CONSTRAINT passkeys_pkey PRIMARY KEY (id),
+	CONSTRAINT passkeys_credential_id_key UNIQUE (credential_id)
+);
+CREATE INDEX passkey_user_id_index ON passkeys (user_id);
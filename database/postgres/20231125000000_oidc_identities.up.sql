@@ -0,0 +1,10 @@
+-- accounts of an OpenID Connect provider that log in as a lentos user
+CREATE TABLE oidc_identities (
+	-- the `iss` and `sub` claims, together they identify the account
+	issuer text NOT NULL,
+	subject text NOT NULL,
+	user_id bigint NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+	created_at timestamptz NOT NULL DEFAULT now(),
+	CONSTRAINT oidc_identities_pkey PRIMARY KEY (issuer, subject)
+);
+CREATE INDEX oidc_identity_user_id_index ON oidc_identities (user_id);
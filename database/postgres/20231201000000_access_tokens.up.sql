@@ -0,0 +1,17 @@
+-- personal access tokens for scripts and integrations
+CREATE TABLE access_tokens (
+	id bigserial NOT NULL,
+	user_id bigint NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+	-- sha-256 of the token, the token itself is only shown once
+	token_hash text NOT NULL,
+	-- chosen by the user to tell their tokens apart
+	name varchar(255) NOT NULL,
+	-- the granted scopes as json array, e.g. ["read_todos"]
+	scopes jsonb NOT NULL,
+	created_at timestamptz NOT NULL DEFAULT now(),
+	expires_at timestamptz NULL,
+	last_used_at timestamptz NULL,
+	CONSTRAINT access_tokens_pkey PRIMARY KEY (id),
+	CONSTRAINT access_tokens_token_hash_key UNIQUE (token_hash)
+);
+CREATE INDEX access_token_user_id_index ON access_tokens (user_id);
@@ -0,0 +1,5 @@
+-- admins may manage all users through the admin api
+ALTER TABLE users ADD COLUMN role varchar(16) NOT NULL DEFAULT 'user'
+	CHECK (role IN ('user', 'admin'));
+-- disabled users can neither log in nor use their sessions and tokens
+ALTER TABLE users ADD COLUMN disabled_at timestamptz NULL;
@@ -0,0 +1,4 @@
+ALTER TABLE todos DROP COLUMN list_id;
+DROP TABLE list_invitations;
+DROP TABLE list_members;
+DROP TABLE lists;
@@ -0,0 +1,40 @@
+-- todo lists shared by their members, the owner is a member with the admin
+-- permission that cannot be removed
+CREATE TABLE lists (
+	id bigserial NOT NULL,
+	name varchar(255) NOT NULL,
+	owner bigint NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+	created_at timestamptz NOT NULL DEFAULT now(),
+	updated_at timestamptz NOT NULL DEFAULT now(),
+	CONSTRAINT lists_pkey PRIMARY KEY (id)
+);
+CREATE INDEX list_owner_index ON lists (owner);
+
+CREATE TABLE list_members (
+	list_id bigint NOT NULL REFERENCES lists(id) ON DELETE CASCADE,
+	user_id bigint NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+	permission varchar(16) NOT NULL
+		CHECK (permission IN ('viewer', 'editor', 'admin')),
+	created_at timestamptz NOT NULL DEFAULT now(),
+	CONSTRAINT list_members_pkey PRIMARY KEY (list_id, user_id)
+);
+CREATE INDEX list_member_user_id_index ON list_members (user_id);
+
+-- pending invitations, accepting one turns it into a membership
+CREATE TABLE list_invitations (
+	id bigserial NOT NULL,
+	list_id bigint NOT NULL REFERENCES lists(id) ON DELETE CASCADE,
+	user_id bigint NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+	invited_by bigint NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+	permission varchar(16) NOT NULL
+		CHECK (permission IN ('viewer', 'editor', 'admin')),
+	created_at timestamptz NOT NULL DEFAULT now(),
+	CONSTRAINT list_invitations_pkey PRIMARY KEY (id),
+	CONSTRAINT list_invitations_list_id_user_id_key UNIQUE (list_id, user_id)
+);
+CREATE INDEX list_invitation_user_id_index ON list_invitations (user_id);
+
+-- todos without a list are personal todos of their owner
+ALTER TABLE todos ADD COLUMN list_id bigint NULL
+	REFERENCES lists(id) ON DELETE CASCADE;
+CREATE INDEX todo_list_id_index ON todos (list_id);
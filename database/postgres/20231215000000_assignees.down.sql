@@ -0,0 +1,2 @@
+DROP TABLE todo_activities;
+ALTER TABLE todos DROP COLUMN assignee_id;
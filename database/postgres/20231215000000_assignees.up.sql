@@ -0,0 +1,17 @@
+-- the member who should get a todo done, unassigned when they leave
+ALTER TABLE todos ADD COLUMN assignee_id bigint NULL
+	REFERENCES users(id) ON DELETE SET NULL;
+CREATE INDEX todo_assignee_id_index ON todos (assignee_id);
+
+-- what happened to the todos and who did it
+CREATE TABLE todo_activities (
+	id bigserial NOT NULL,
+	todo_id bigint NOT NULL REFERENCES todos(id) ON DELETE CASCADE,
+	actor_id bigint NULL REFERENCES users(id) ON DELETE SET NULL,
+	action varchar(32) NOT NULL CHECK (action IN ('assigned')),
+	previous_assignee_id bigint NULL REFERENCES users(id) ON DELETE SET NULL,
+	assignee_id bigint NULL REFERENCES users(id) ON DELETE SET NULL,
+	created_at timestamptz NOT NULL DEFAULT now(),
+	CONSTRAINT todo_activities_pkey PRIMARY KEY (id)
+);
+CREATE INDEX todo_activity_todo_id_index ON todo_activities (todo_id);
@@ -0,0 +1,18 @@
+-- the discussion of a todo, the bodies are markdown
+CREATE TABLE comments (
+	id bigserial NOT NULL,
+	todo_id bigint NOT NULL REFERENCES todos(id) ON DELETE CASCADE,
+	author_id bigint NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+	body text NOT NULL,
+	created_at timestamptz NOT NULL DEFAULT now(),
+	updated_at timestamptz NOT NULL DEFAULT now(),
+	CONSTRAINT comments_pkey PRIMARY KEY (id)
+);
+CREATE INDEX comment_todo_id_index ON comments (todo_id);
+
+-- users mentioned in a comment, who could see the todo when it was written
+CREATE TABLE comment_mentions (
+	comment_id bigint NOT NULL REFERENCES comments(id) ON DELETE CASCADE,
+	user_id bigint NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+	CONSTRAINT comment_mentions_pkey PRIMARY KEY (comment_id, user_id)
+);
@@ -0,0 +1,3 @@
+DROP TABLE security_events;
+DROP TABLE audit_events;
+DROP FUNCTION reject_event_change;
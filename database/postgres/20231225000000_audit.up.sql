@@ -0,0 +1,43 @@
+-- every change of a todo or a user, kept after the todo or user is deleted
+CREATE TABLE audit_events (
+	id bigserial NOT NULL,
+	-- the user who made the change, unset for changes made by the server
+	actor_id bigint,
+	action text NOT NULL,
+	entity text NOT NULL,
+	entity_id bigint NOT NULL,
+	-- the changed fields with their values before and after
+	changes jsonb NOT NULL,
+	request_id text,
+	created_at timestamptz NOT NULL DEFAULT now(),
+	CONSTRAINT audit_events_pkey PRIMARY KEY (id)
+);
+CREATE INDEX audit_event_entity_index ON audit_events (entity, entity_id);
+CREATE INDEX audit_event_actor_id_index ON audit_events (actor_id);
+
+-- logins and changes of credentials, apart from the changes of the data
+CREATE TABLE security_events (
+	id bigserial NOT NULL,
+	-- the account, unset for failed logins with an unknown email
+	user_id bigint,
+	actor_id bigint,
+	kind text NOT NULL,
+	ip text,
+	request_id text,
+	created_at timestamptz NOT NULL DEFAULT now(),
+	CONSTRAINT security_events_pkey PRIMARY KEY (id)
+);
+CREATE INDEX security_event_user_id_index ON security_events (user_id);
+
+-- both are append-only
+CREATE FUNCTION reject_event_change() RETURNS trigger AS $$
+BEGIN
+	RAISE EXCEPTION '% is append-only', TG_TABLE_NAME;
+END;
+$$ LANGUAGE plpgsql;
+CREATE TRIGGER audit_events_append_only
+	BEFORE UPDATE OR DELETE ON audit_events
+	FOR EACH ROW EXECUTE FUNCTION reject_event_change();
+CREATE TRIGGER security_events_append_only
+	BEFORE UPDATE OR DELETE ON security_events
+	FOR EACH ROW EXECUTE FUNCTION reject_event_change();
@@ -0,0 +1,3 @@
+DROP TABLE orphaned_blobs;
+DROP TABLE attachments;
+DROP FUNCTION orphan_attachment_blob;
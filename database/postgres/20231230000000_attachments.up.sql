@@ -0,0 +1,37 @@
+-- files attached to todos, the content is kept in the blob store under
+-- `storage_key`
+CREATE TABLE attachments (
+	id bigserial NOT NULL,
+	todo_id bigint NOT NULL REFERENCES todos(id) ON DELETE CASCADE,
+	-- shared todos keep their attachments when the uploader leaves
+	uploader_id bigint REFERENCES users(id) ON DELETE SET NULL,
+	file_name text NOT NULL,
+	size bigint NOT NULL,
+	content_type text NOT NULL,
+	checksum text NOT NULL,
+	storage_key text NOT NULL,
+	created_at timestamptz NOT NULL DEFAULT now(),
+	CONSTRAINT attachments_pkey PRIMARY KEY (id),
+	CONSTRAINT attachments_storage_key_key UNIQUE (storage_key)
+);
+CREATE INDEX attachment_todo_id_index ON attachments (todo_id);
+CREATE INDEX attachment_uploader_id_index ON attachments (uploader_id);
+
+-- content of deleted attachments that is still to be removed from the blob
+-- store, including that of attachments deleted with their todo
+CREATE TABLE orphaned_blobs (
+	storage_key text NOT NULL,
+	orphaned_at timestamptz NOT NULL DEFAULT now(),
+	CONSTRAINT orphaned_blobs_pkey PRIMARY KEY (storage_key)
+);
+
+CREATE FUNCTION orphan_attachment_blob() RETURNS trigger AS $$
+BEGIN
+	INSERT INTO orphaned_blobs (storage_key) VALUES (OLD.storage_key);
+	RETURN OLD;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER attachments_orphan_blob
+	AFTER DELETE ON attachments
+	FOR EACH ROW EXECUTE FUNCTION orphan_attachment_blob();
@@ -0,0 +1,14 @@
+-- when a todo should be done and when it was done, for calendar apps
+ALTER TABLE todos ADD COLUMN due_at timestamptz NULL;
+ALTER TABLE todos ADD COLUMN completed_at timestamptz NULL;
+UPDATE todos SET completed_at = updated_at WHERE is_done;
+
+-- secret tokens of the iCalendar feeds calendar apps subscribe to, at most
+-- one per user, only the sha-256 hash of a token is stored
+CREATE TABLE calendar_feeds (
+	user_id bigint NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+	token_hash char(64) NOT NULL,
+	created_at timestamptz NOT NULL DEFAULT now(),
+	CONSTRAINT calendar_feeds_pkey PRIMARY KEY (user_id),
+	CONSTRAINT calendar_feeds_token_hash_key UNIQUE (token_hash)
+);
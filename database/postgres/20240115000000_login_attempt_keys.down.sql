@@ -0,0 +1,2 @@
+DELETE FROM login_attempts WHERE length(key) > 320;
+ALTER TABLE login_attempts ALTER COLUMN key TYPE varchar(320);
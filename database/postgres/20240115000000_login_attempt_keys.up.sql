@@ -0,0 +1,2 @@
+-- keys are prefixed, so an email address of the maximum length did not fit
+ALTER TABLE login_attempts ALTER COLUMN key TYPE text;
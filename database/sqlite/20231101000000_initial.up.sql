@@ -0,0 +1,34 @@
+-- the sqlite schema mirrors the postgres schema after all of its migrations,
+-- timestamps are stored as RFC 3339 text which sorts chronologically
+
+-- users relation
+CREATE TABLE users (
+	id integer PRIMARY KEY AUTOINCREMENT,
+	name text NOT NULL,
+	email text NOT NULL UNIQUE,
+	password text NOT NULL,
+	created_at text NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+	updated_at text NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
+
+-- session relation used for authentication and session specific data
+CREATE TABLE sessions (
+	key text PRIMARY KEY NOT NULL,
+	state text NOT NULL,
+	user_id integer NULL REFERENCES users(id) ON DELETE CASCADE,
+	expires_at text NOT NULL
+);
+CREATE INDEX session_user_id_index ON sessions (user_id);
+CREATE INDEX session_expires_at_index ON sessions (expires_at);
+
+-- todos relation
+CREATE TABLE todos (
+	id integer PRIMARY KEY AUTOINCREMENT,
+	title text NOT NULL,
+	description text NOT NULL DEFAULT '',
+	is_done boolean NOT NULL DEFAULT false,
+	created_at text NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+	updated_at text NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+	owner integer NOT NULL REFERENCES users(id)
+);
+CREATE INDEX todo_owner_index ON todos (owner);
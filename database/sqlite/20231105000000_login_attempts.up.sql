@@ -0,0 +1,7 @@
+-- failed logins per email address and per ip, used to throttle password
+-- guessing
+CREATE TABLE login_attempts (
+	key text PRIMARY KEY NOT NULL,
+	failures integer NOT NULL,
+	last_failure_at text NOT NULL
+);
@@ -0,0 +1,15 @@
+-- set once the user proved to own their email address
+ALTER TABLE users ADD COLUMN email_verified_at text NULL;
+
+-- single use tokens mailed to users, e.g. to verify their email address or
+-- to reset their password, only the sha-256 hash of a token is stored
+CREATE TABLE user_tokens (
+	token_hash text PRIMARY KEY NOT NULL,
+	user_id integer NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+	purpose text NOT NULL,
+	-- the address the token was mailed to
+	email text NOT NULL,
+	expires_at text NOT NULL,
+	created_at text NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
+CREATE INDEX user_token_user_id_index ON user_tokens (user_id, purpose);
@@ -0,0 +1,19 @@
+-- the TOTP secret of a user, two-factor authentication is enabled once it is
+-- confirmed with a first code
+CREATE TABLE user_totp (
+	user_id integer PRIMARY KEY NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+	-- base32 encoded, it has to be readable to compute the codes
+	secret text NOT NULL,
+	confirmed_at text NULL,
+	-- the time step of the last accepted code, codes must not be replayed
+	last_used_step integer NULL,
+	created_at text NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
+
+-- one-time recovery codes for users who lost their authenticator, only the
+-- sha-256 hash of a code is stored and used codes are deleted
+CREATE TABLE recovery_codes (
+	user_id integer NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+	code_hash text NOT NULL,
+	PRIMARY KEY (user_id, code_hash)
+);
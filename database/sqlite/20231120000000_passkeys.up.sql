@@ -0,0 +1,14 @@
+-- WebAuthn credentials for passwordless logins
+CREATE TABLE passkeys (
+	id integer PRIMARY KEY AUTOINCREMENT,
+	user_id integer NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+	-- base64url encoded, unique across all authenticators
+	credential_id text NOT NULL UNIQUE,
+	-- chosen by the user to tell their passkeys apart
+	name text NOT NULL,
+	-- the public key and signature counter as serialized by webauthn-rs
+	passkey text NOT NULL,
+	created_at text NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+	last_used_at text NULL
+);
+CREATE INDEX passkey_user_id_index ON passkeys (user_id);
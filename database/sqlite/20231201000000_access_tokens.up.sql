@@ -0,0 +1,15 @@
+-- personal access tokens for scripts and integrations
+CREATE TABLE access_tokens (
+	id integer PRIMARY KEY AUTOINCREMENT,
+	user_id integer NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+	-- sha-256 of the token, the token itself is only shown once
+	token_hash text NOT NULL UNIQUE,
+	-- chosen by the user to tell their tokens apart
+	name text NOT NULL,
+	-- the granted scopes as json array, e.g. ["read_todos"]
+	scopes text NOT NULL,
+	created_at text NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+	expires_at text NULL,
+	last_used_at text NULL
+);
+CREATE INDEX access_token_user_id_index ON access_tokens (user_id);
@@ -0,0 +1,5 @@
+DROP INDEX todo_list_id_index;
+ALTER TABLE todos DROP COLUMN list_id;
+DROP TABLE list_invitations;
+DROP TABLE list_members;
+DROP TABLE lists;
@@ -0,0 +1,38 @@
+-- todo lists shared by their members, the owner is a member with the admin
+-- permission that cannot be removed
+CREATE TABLE lists (
+	id integer PRIMARY KEY AUTOINCREMENT,
+	name text NOT NULL,
+	owner integer NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+	created_at text NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+	updated_at text NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
+CREATE INDEX list_owner_index ON lists (owner);
+
+CREATE TABLE list_members (
+	list_id integer NOT NULL REFERENCES lists(id) ON DELETE CASCADE,
+	user_id integer NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+	permission text NOT NULL
+		CHECK (permission IN ('viewer', 'editor', 'admin')),
+	created_at text NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+	PRIMARY KEY (list_id, user_id)
+);
+CREATE INDEX list_member_user_id_index ON list_members (user_id);
+
+-- pending invitations, accepting one turns it into a membership
+CREATE TABLE list_invitations (
+	id integer PRIMARY KEY AUTOINCREMENT,
+	list_id integer NOT NULL REFERENCES lists(id) ON DELETE CASCADE,
+	user_id integer NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+	invited_by integer NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+	permission text NOT NULL
+		CHECK (permission IN ('viewer', 'editor', 'admin')),
+	created_at text NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+	UNIQUE (list_id, user_id)
+);
+CREATE INDEX list_invitation_user_id_index ON list_invitations (user_id);
+
+-- todos without a list are personal todos of their owner
+ALTER TABLE todos ADD COLUMN list_id integer NULL
+	REFERENCES lists(id) ON DELETE CASCADE;
+CREATE INDEX todo_list_id_index ON todos (list_id);
@@ -0,0 +1,3 @@
+DROP TABLE todo_activities;
+DROP INDEX todo_assignee_id_index;
+ALTER TABLE todos DROP COLUMN assignee_id;
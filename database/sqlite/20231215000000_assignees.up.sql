@@ -0,0 +1,16 @@
+-- the member who should get a todo done, unassigned when they leave
+ALTER TABLE todos ADD COLUMN assignee_id integer NULL
+	REFERENCES users(id) ON DELETE SET NULL;
+CREATE INDEX todo_assignee_id_index ON todos (assignee_id);
+
+-- what happened to the todos and who did it
+CREATE TABLE todo_activities (
+	id integer PRIMARY KEY AUTOINCREMENT,
+	todo_id integer NOT NULL REFERENCES todos(id) ON DELETE CASCADE,
+	actor_id integer NULL REFERENCES users(id) ON DELETE SET NULL,
+	action text NOT NULL CHECK (action IN ('assigned')),
+	previous_assignee_id integer NULL REFERENCES users(id) ON DELETE SET NULL,
+	assignee_id integer NULL REFERENCES users(id) ON DELETE SET NULL,
+	created_at text NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
+CREATE INDEX todo_activity_todo_id_index ON todo_activities (todo_id);
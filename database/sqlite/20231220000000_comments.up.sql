@@ -0,0 +1,17 @@
+-- the discussion of a todo, the bodies are markdown
+CREATE TABLE comments (
+	id integer PRIMARY KEY AUTOINCREMENT,
+	todo_id integer NOT NULL REFERENCES todos(id) ON DELETE CASCADE,
+	author_id integer NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+	body text NOT NULL,
+	created_at text NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+	updated_at text NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
+CREATE INDEX comment_todo_id_index ON comments (todo_id);
+
+-- users mentioned in a comment, who could see the todo when it was written
+CREATE TABLE comment_mentions (
+	comment_id integer NOT NULL REFERENCES comments(id) ON DELETE CASCADE,
+	user_id integer NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+	PRIMARY KEY (comment_id, user_id)
+);
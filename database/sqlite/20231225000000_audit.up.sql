@@ -0,0 +1,46 @@
+-- every change of a todo or a user, kept after the todo or user is deleted
+CREATE TABLE audit_events (
+	id integer PRIMARY KEY AUTOINCREMENT,
+	-- the user who made the change, unset for changes made by the server
+	actor_id integer,
+	action text NOT NULL,
+	entity text NOT NULL,
+	entity_id integer NOT NULL,
+	-- the changed fields with their values before and after, as json
+	changes text NOT NULL,
+	request_id text,
+	created_at text NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
+CREATE INDEX audit_event_entity_index ON audit_events (entity, entity_id);
+CREATE INDEX audit_event_actor_id_index ON audit_events (actor_id);
+
+-- logins and changes of credentials, apart from the changes of the data
+CREATE TABLE security_events (
+	id integer PRIMARY KEY AUTOINCREMENT,
+	-- the account, unset for failed logins with an unknown email
+	user_id integer,
+	actor_id integer,
+	kind text NOT NULL,
+	ip text,
+	request_id text,
+	created_at text NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
+CREATE INDEX security_event_user_id_index ON security_events (user_id);
+
+-- both are append-only
+CREATE TRIGGER audit_events_no_update BEFORE UPDATE ON audit_events
+BEGIN
+	SELECT RAISE(ABORT, 'audit_events is append-only');
+END;
+CREATE TRIGGER audit_events_no_delete BEFORE DELETE ON audit_events
+BEGIN
+	SELECT RAISE(ABORT, 'audit_events is append-only');
+END;
+CREATE TRIGGER security_events_no_update BEFORE UPDATE ON security_events
+BEGIN
+	SELECT RAISE(ABORT, 'security_events is append-only');
+END;
+CREATE TRIGGER security_events_no_delete BEFORE DELETE ON security_events
+BEGIN
+	SELECT RAISE(ABORT, 'security_events is append-only');
+END;
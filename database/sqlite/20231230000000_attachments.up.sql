@@ -0,0 +1,29 @@
+-- files attached to todos, the content is kept in the blob store under
+-- `storage_key`
+CREATE TABLE attachments (
+	id integer PRIMARY KEY AUTOINCREMENT,
+	todo_id integer NOT NULL REFERENCES todos(id) ON DELETE CASCADE,
+	-- shared todos keep their attachments when the uploader leaves
+	uploader_id integer REFERENCES users(id) ON DELETE SET NULL,
+	file_name text NOT NULL,
+	size integer NOT NULL,
+	content_type text NOT NULL,
+	checksum text NOT NULL,
+	storage_key text NOT NULL UNIQUE,
+	created_at text NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
+CREATE INDEX attachment_todo_id_index ON attachments (todo_id);
+CREATE INDEX attachment_uploader_id_index ON attachments (uploader_id);
+
+-- content of deleted attachments that is still to be removed from the blob
+-- store, including that of attachments deleted with their todo
+CREATE TABLE orphaned_blobs (
+	storage_key text PRIMARY KEY,
+	orphaned_at text NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
+
+CREATE TRIGGER attachments_orphan_blob
+	AFTER DELETE ON attachments
+BEGIN
+	INSERT INTO orphaned_blobs (storage_key) VALUES (OLD.storage_key);
+END;
@@ -0,0 +1,9 @@
+-- ids the todos have in the files a user imported them from, so that
+-- importing a file again updates the todos instead of duplicating them
+CREATE TABLE todo_external_ids (
+	user_id integer NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+	external_id text NOT NULL,
+	todo_id integer NOT NULL REFERENCES todos(id) ON DELETE CASCADE,
+	PRIMARY KEY (user_id, external_id)
+);
+CREATE INDEX todo_external_id_todo_id_index ON todo_external_ids (todo_id);
@@ -0,0 +1,3 @@
+DROP TABLE calendar_feeds;
+ALTER TABLE todos DROP COLUMN completed_at;
+ALTER TABLE todos DROP COLUMN due_at;
@@ -0,0 +1,12 @@
+-- when a todo should be done and when it was done, for calendar apps
+ALTER TABLE todos ADD COLUMN due_at text NULL;
+ALTER TABLE todos ADD COLUMN completed_at text NULL;
+UPDATE todos SET completed_at = updated_at WHERE is_done;
+
+-- secret tokens of the iCalendar feeds calendar apps subscribe to, at most
+-- one per user, only the sha-256 hash of a token is stored
+CREATE TABLE calendar_feeds (
+	user_id integer PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+	token_hash text NOT NULL UNIQUE,
+	created_at text NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
@@ -0,0 +1 @@
+-- the previous format was inconsistent, there is nothing to restore
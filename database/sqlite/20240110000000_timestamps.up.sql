@@ -0,0 +1,72 @@
+-- timestamps written by the application used a +00:00 offset and up to
+-- nanoseconds, rewrite them in the format of the column defaults so that
+-- they compare correctly as strings
+UPDATE users SET
+	created_at = strftime('%Y-%m-%dT%H:%M:%fZ', created_at),
+	updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', updated_at),
+	email_verified_at = strftime('%Y-%m-%dT%H:%M:%fZ', email_verified_at),
+	disabled_at = strftime('%Y-%m-%dT%H:%M:%fZ', disabled_at);
+
+UPDATE sessions SET
+	expires_at = strftime('%Y-%m-%dT%H:%M:%fZ', expires_at);
+
+UPDATE todos SET
+	created_at = strftime('%Y-%m-%dT%H:%M:%fZ', created_at),
+	updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', updated_at),
+	due_at = strftime('%Y-%m-%dT%H:%M:%fZ', due_at),
+	completed_at = strftime('%Y-%m-%dT%H:%M:%fZ', completed_at);
+
+UPDATE login_attempts SET
+	last_failure_at = strftime('%Y-%m-%dT%H:%M:%fZ', last_failure_at);
+
+UPDATE user_tokens SET
+	expires_at = strftime('%Y-%m-%dT%H:%M:%fZ', expires_at),
+	created_at = strftime('%Y-%m-%dT%H:%M:%fZ', created_at);
+
+UPDATE user_totp SET
+	confirmed_at = strftime('%Y-%m-%dT%H:%M:%fZ', confirmed_at),
+	created_at = strftime('%Y-%m-%dT%H:%M:%fZ', created_at);
+
+UPDATE passkeys SET
+	created_at = strftime('%Y-%m-%dT%H:%M:%fZ', created_at),
+	last_used_at = strftime('%Y-%m-%dT%H:%M:%fZ', last_used_at);
+
+UPDATE oidc_identities SET
+	created_at = strftime('%Y-%m-%dT%H:%M:%fZ', created_at);
+
+UPDATE access_tokens SET
+	created_at = strftime('%Y-%m-%dT%H:%M:%fZ', created_at),
+	expires_at = strftime('%Y-%m-%dT%H:%M:%fZ', expires_at),
+	last_used_at = strftime('%Y-%m-%dT%H:%M:%fZ', last_used_at);
+
+UPDATE lists SET
+	created_at = strftime('%Y-%m-%dT%H:%M:%fZ', created_at),
+	updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', updated_at);
+
+UPDATE list_members SET
+	created_at = strftime('%Y-%m-%dT%H:%M:%fZ', created_at);
+
+UPDATE list_invitations SET
+	created_at = strftime('%Y-%m-%dT%H:%M:%fZ', created_at);
+
+UPDATE todo_activities SET
+	created_at = strftime('%Y-%m-%dT%H:%M:%fZ', created_at);
+
+UPDATE comments SET
+	created_at = strftime('%Y-%m-%dT%H:%M:%fZ', created_at),
+	updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', updated_at);
+
+UPDATE audit_events SET
+	created_at = strftime('%Y-%m-%dT%H:%M:%fZ', created_at);
+
+UPDATE security_events SET
+	created_at = strftime('%Y-%m-%dT%H:%M:%fZ', created_at);
+
+UPDATE attachments SET
+	created_at = strftime('%Y-%m-%dT%H:%M:%fZ', created_at);
+
+UPDATE orphaned_blobs SET
+	orphaned_at = strftime('%Y-%m-%dT%H:%M:%fZ', orphaned_at);
+
+UPDATE calendar_feeds SET
+	created_at = strftime('%Y-%m-%dT%H:%M:%fZ', created_at);
@@ -1,10 +1,20 @@
 use crate::handler::api_handler::ApiHandler;
-use shared::models::user::{CreateUser, SignInUser};
+use shared::models::user::{CreateUser, SignInResponse, SignInUser, TotpCode};
+
+/// How far a sign in got.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SignInStep {
+    SignedIn,
+    /// The password was right, the sign in has to be completed with
+    /// [`sign_in_with_code`].
+    CodeRequired,
+    Failed,
+}
 
 pub(crate) async fn sign_in(
     api_handler: &ApiHandler,
     sign_in_user: SignInUser,
-) {
+) -> SignInStep {
     tracing::debug!("Trying to sign in with provided data...");
 
     let sign_in_response =
@@ -15,7 +25,7 @@ pub(crate) async fn sign_in(
             "Sign in failed. Server responded with: {:?}",
             sign_in_response
         );
-        return;
+        return SignInStep::Failed;
     }
 
     tracing::debug!(
@@ -23,7 +33,45 @@ pub(crate) async fn sign_in(
         sign_in_response
     );
 
+    // the session cookie also carries a sign in that waits for a code
+    api_handler.cookie_store.save();
+
+    match sign_in_response.json::<SignInResponse>().await {
+        Ok(response) if response.two_factor_required => {
+            tracing::debug!("Sign in requires a code of the second factor.");
+            SignInStep::CodeRequired
+        }
+        Ok(_) => SignInStep::SignedIn,
+        Err(error) => {
+            tracing::error!("Failed to parse the sign in response: {error}");
+            SignInStep::Failed
+        }
+    }
+}
+
+/// Completes a sign in with a code of the authenticator app or a recovery
+/// code and returns whether it succeeded.
+pub(crate) async fn sign_in_with_code(
+    api_handler: &ApiHandler,
+    totp_code: TotpCode,
+) -> bool {
+    tracing::debug!("Trying to complete the sign in with a code...");
+
+    let response = api_handler.post("/users/login/totp", &totp_code).await;
+
+    if !response.status().is_success() {
+        tracing::error!(
+            "Code was not accepted. Server responded with: {:?}",
+            response
+        );
+        return false;
+    }
+
+    tracing::debug!("Signed in successfully. Server responded: {:?}", response);
+
     api_handler.cookie_store.save();
+
+    true
 }
 
 pub(crate) async fn sign_up(
@@ -0,0 +1,116 @@
+use crate::handler::api_handler::{ApiHandler, BASE_URL};
+use reqwest::StatusCode;
+use shared::models::comment::{Comment, CreateComment, UpdateComment};
+
+/// Comments loaded at once, the most the server hands out per page.
+pub(crate) const PAGE_SIZE: i64 = 200;
+
+pub(crate) async fn get_comments(
+    api_handler: &ApiHandler,
+    todo_id: &i64,
+    offset: i64,
+) -> Vec<Comment> {
+    tracing::debug!("Trying to get the comments on todo {todo_id}...");
+
+    let response = api_handler
+        .get(&format!(
+            "/todos/{todo_id}/comments?offset={offset}&limit={PAGE_SIZE}"
+        ))
+        .await;
+
+    if !response.status().is_success() {
+        tracing::error!(
+            "Failed to get the comments on todo {todo_id}. Server responded: \
+             {:?}",
+            response
+        );
+    } else {
+        tracing::debug!("Got comments on todo {todo_id}.");
+    }
+
+    let comments = response
+        .json::<Vec<Comment>>()
+        .await
+        .expect("Failed to parse response");
+
+    tracing::debug!("Parsed comments: {:?}", comments);
+
+    comments
+}
+
+pub(crate) async fn create_comment(
+    api_handler: &ApiHandler,
+    todo_id: &i64,
+    create_comment: CreateComment,
+) -> StatusCode {
+    tracing::debug!("Trying to comment on todo {todo_id}...");
+
+    let response = api_handler
+        .post(&format!("/todos/{todo_id}/comments"), &create_comment)
+        .await;
+
+    if !response.status().is_success() {
+        tracing::error!(
+            "Failed to comment on todo {todo_id}. Server responded: {:?}",
+            response
+        );
+    } else {
+        tracing::debug!("Commented on todo {todo_id}.");
+    }
+
+    response.status()
+}
+
+pub(crate) async fn update_comment(
+    api_handler: &ApiHandler,
+    todo_id: &i64,
+    comment_id: &i64,
+    update_comment: UpdateComment,
+) -> StatusCode {
+    tracing::debug!("Trying to update comment {comment_id}...");
+
+    let response = api_handler
+        .client
+        .put(&format!("{BASE_URL}/todos/{todo_id}/comments/{comment_id}"))
+        .json(&update_comment)
+        .send()
+        .await
+        .expect("Failed to send request");
+
+    if !response.status().is_success() {
+        tracing::error!(
+            "Failed to update comment {comment_id}. Server responded: {:?}",
+            response
+        );
+    } else {
+        tracing::debug!("Updated comment {comment_id}.");
+    }
+
+    response.status()
+}
+
+pub(crate) async fn delete_comment(
+    api_handler: &ApiHandler,
+    todo_id: &i64,
+    comment_id: &i64,
+) -> StatusCode {
+    tracing::debug!("Trying to delete comment {comment_id}...");
+
+    let response = api_handler
+        .client
+        .delete(&format!("{BASE_URL}/todos/{todo_id}/comments/{comment_id}"))
+        .send()
+        .await
+        .expect("Failed to send request");
+
+    if !response.status().is_success() {
+        tracing::error!(
+            "Failed to delete comment {comment_id}. Server responded: {:?}",
+            response
+        );
+    } else {
+        tracing::debug!("Deleted comment {comment_id}.");
+    }
+
+    response.status()
+}
@@ -0,0 +1,107 @@
+use crate::handler::api_handler::ApiHandler;
+use reqwest::StatusCode;
+use shared::models::list::{ListInvitation, ListMember, TodoList};
+
+pub(crate) async fn get_lists(api_handler: &ApiHandler) -> Vec<TodoList> {
+    tracing::debug!("Trying to get all lists...");
+
+    let response = api_handler.get("/lists").await;
+
+    if !response.status().is_success() {
+        tracing::error!(
+            "Failed to get all lists. Server responded: {:?}",
+            response
+        );
+    } else {
+        tracing::debug!("Got lists.");
+    }
+
+    let lists = response
+        .json::<Vec<TodoList>>()
+        .await
+        .expect("Failed to parse response");
+
+    tracing::debug!("Parsed lists: {:?}", lists);
+
+    lists
+}
+
+pub(crate) async fn get_members(
+    api_handler: &ApiHandler,
+    list_id: &i64,
+) -> Vec<ListMember> {
+    tracing::debug!("Trying to get the members of list {list_id}...");
+
+    let response = api_handler.get(&format!("/lists/{list_id}/members")).await;
+
+    if !response.status().is_success() {
+        tracing::error!(
+            "Failed to get the members of list {list_id}. Server responded: \
+             {:?}",
+            response
+        );
+    } else {
+        tracing::debug!("Got members of list {list_id}.");
+    }
+
+    let members = response
+        .json::<Vec<ListMember>>()
+        .await
+        .expect("Failed to parse response");
+
+    tracing::debug!("Parsed list members: {:?}", members);
+
+    members
+}
+
+pub(crate) async fn get_invitations(
+    api_handler: &ApiHandler,
+) -> Vec<ListInvitation> {
+    tracing::debug!("Trying to get the list invitations...");
+
+    let response = api_handler.get("/lists/invitations").await;
+
+    if !response.status().is_success() {
+        tracing::error!(
+            "Failed to get the list invitations. Server responded: {:?}",
+            response
+        );
+    } else {
+        tracing::debug!("Got list invitations.");
+    }
+
+    let invitations = response
+        .json::<Vec<ListInvitation>>()
+        .await
+        .expect("Failed to parse response");
+
+    tracing::debug!("Parsed list invitations: {:?}", invitations);
+
+    invitations
+}
+
+/// Accepts the invitation if `accept` is set and declines it otherwise.
+pub(crate) async fn answer_invitation(
+    api_handler: &ApiHandler,
+    invitation_id: &i64,
+    accept: bool,
+) -> StatusCode {
+    let answer = if accept { "accept" } else { "decline" };
+    tracing::debug!("Trying to {answer} invitation {invitation_id}...");
+
+    let response = api_handler
+        .post(&format!("/lists/invitations/{invitation_id}/{answer}"), &())
+        .await;
+
+    if !response.status().is_success() {
+        tracing::error!(
+            "Failed to {answer} invitation {invitation_id}. Server \
+             responded: {:?}",
+            response
+        );
+    } else {
+        tracing::debug!("Answered invitation {invitation_id}.");
+    }
+
+    response.status()
+}
@@ -1,2 +1,6 @@
 pub(crate) mod auth;
+pub(crate) mod comment;
+pub(crate) mod list;
 pub(crate) mod todo;
+pub(crate) mod transfer;
+pub(crate) mod user;
@@ -144,6 +144,8 @@ mod tests {
         let todo = CreateTodo {
             title: "Title".to_string(),
             description: "Description".to_string(),
+            list_id: None,
+            due_at: None,
         };
         rt.block_on(create_todo(&api_handler, todo));
     }
@@ -156,6 +158,8 @@ mod tests {
         let todo = CreateTodo {
             title: "Title".to_string(),
             description: "Description".to_string(),
+            list_id: None,
+            due_at: None,
         };
         // create a todo to delete
         rt.block_on(create_todo(&api_handler, todo));
@@ -175,6 +179,8 @@ mod tests {
         let create_todo_data = CreateTodo {
             title: "Title".to_string(),
             description: "Description".to_string(),
+            list_id: None,
+            due_at: None,
         };
 
         // create a todo to update
@@ -189,6 +195,7 @@ mod tests {
             title: Some("Updated title".to_string()),
             description: Some("Updated description".to_string()),
             is_done: Some(true),
+            due_at: None,
         };
 
         rt.block_on(update_todo(&api_handler, &update_todo_data.id));
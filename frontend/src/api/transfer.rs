@@ -0,0 +1,56 @@
+use crate::handler::api_handler::ApiHandler;
+use reqwest::StatusCode;
+use shared::models::transfer::{ImportReport, ImportTodos, TransferFormat};
+
+/// Returns the content of the exported file.
+pub(crate) async fn export_todos(
+    api_handler: &ApiHandler,
+    format: TransferFormat,
+) -> Result<String, StatusCode> {
+    tracing::debug!("Trying to export the todos as {}...", format.label());
+
+    let response = api_handler
+        .get(&format!("/todos/export?format={}", format.as_str()))
+        .await;
+
+    if !response.status().is_success() {
+        tracing::error!(
+            "Failed to export the todos. Server responded: {:?}",
+            response
+        );
+        return Err(response.status());
+    }
+    tracing::debug!("Exported todos.");
+
+    Ok(response.text().await.expect("Failed to read response"))
+}
+
+/// Imports a file, nothing is imported if the report has errors.
+pub(crate) async fn import_todos(
+    api_handler: &ApiHandler,
+    import_todos: ImportTodos,
+) -> Result<ImportReport, StatusCode> {
+    tracing::debug!(
+        "Trying to import a {} file...",
+        import_todos.format.label()
+    );
+
+    let response = api_handler.post("/todos/import", &import_todos).await;
+
+    if !response.status().is_success() {
+        tracing::error!(
+            "Failed to import the todos. Server responded: {:?}",
+            response
+        );
+        return Err(response.status());
+    }
+
+    let report = response
+        .json::<ImportReport>()
+        .await
+        .expect("Failed to parse response");
+
+    tracing::debug!("Parsed import report: {:?}", report);
+
+    Ok(report)
+}
@@ -0,0 +1,19 @@
+use crate::handler::api_handler::ApiHandler;
+use shared::models::user::UserAccount;
+
+pub(crate) async fn get_user(api_handler: &ApiHandler) -> UserAccount {
+    tracing::debug!("Trying to get the session user...");
+
+    let response = api_handler.get("/users").await;
+
+    if !response.status().is_success() {
+        tracing::error!(
+            "Failed to get the session user. Server responded: {:?}",
+            response
+        );
+    } else {
+        tracing::debug!("Got session user.");
+    }
+
+    response.json::<UserAccount>().await.expect("Failed to parse response")
+}
@@ -0,0 +1,317 @@
+use std::collections::HashMap;
+
+use crate::api::*;
+use crate::handler::api_handler::ApiHandler;
+use dioxus::prelude::*;
+use dioxus_signals::{use_signal, Signal};
+use pulldown_cmark::{html, CowStr, Event, LinkType, Parser, Tag};
+use shared::models::comment::{Comment, CreateComment, UpdateComment};
+
+/// Keeps the destination of a link or image if it is on the web or an email
+/// address, anything else, e.g. `javascript:`, would run in the page.
+fn safe_destination(link_type: LinkType, destination: CowStr) -> CowStr {
+    // autolinked addresses get their `mailto:` when they are rendered
+    let lowercase = destination.trim_start().to_ascii_lowercase();
+    if link_type == LinkType::Email
+        || ["http:", "https:", "mailto:"]
+            .iter()
+            .any(|scheme| lowercase.starts_with(scheme))
+    {
+        destination
+    } else {
+        CowStr::Borrowed("")
+    }
+}
+
+/// Renders a markdown body, raw HTML in it is shown as text and links and
+/// images only lead to the web or email addresses.
+fn to_html(body: &str) -> String {
+    let parser = Parser::new(body).map(|event| match event {
+        Event::Html(html) => Event::Text(html),
+        Event::Start(Tag::Link(link_type, destination, title)) => {
+            Event::Start(Tag::Link(
+                link_type,
+                safe_destination(link_type, destination),
+                title,
+            ))
+        }
+        Event::Start(Tag::Image(link_type, destination, title)) => {
+            Event::Start(Tag::Image(
+                link_type,
+                safe_destination(link_type, destination),
+                title,
+            ))
+        }
+        event => event,
+    });
+    let mut html = String::new();
+    html::push_html(&mut html, parser);
+    html
+}
+
+fn written_at(comment: &Comment) -> String {
+    comment.created_at.format("%Y-%m-%d %H:%M").to_string()
+}
+
+/// The users mentioned as `@name` in a body, out of the ones who can see the
+/// todo.
+fn mentions(body: &str, users: &HashMap<i64, String>) -> Vec<i64> {
+    users
+        .iter()
+        .filter(|(_, name)| body.contains(&format!("@{name}")))
+        .map(|(user_id, _)| *user_id)
+        .collect()
+}
+
+#[component]
+pub(crate) fn Discussion(
+    cx: Scope,
+    todo_id: i64,
+    assignees: Signal<HashMap<i64, String>>,
+) -> Element {
+    let error_handler: &Coroutine<crate::error::Error> =
+        use_coroutine_handle(cx)?;
+    let api_handler: &ApiHandler = use_context(cx).unwrap();
+    let todo_id = *todo_id;
+    let comments: Signal<Vec<Comment>> = use_signal(cx, Vec::new);
+    let has_more: Signal<bool> = use_signal(cx, || false);
+    let draft: Signal<String> = use_signal(cx, String::new);
+    // the comment that is edited and its new body
+    let edited: Signal<Option<(i64, String)>> = use_signal(cx, || None);
+
+    let discussion_future = use_future(cx, (), |_| {
+        to_owned![api_handler, comments, has_more];
+        async move {
+            let page = comment::get_comments(&api_handler, &todo_id, 0).await;
+            *has_more.write() = page.len() as i64 == comment::PAGE_SIZE;
+            *comments.write() = page;
+            user::get_user(&api_handler).await.id
+        }
+    });
+
+    let load_more_handler = move || {
+        to_owned![api_handler, comments, has_more];
+
+        cx.spawn(async move {
+            let offset = comments.peek().len() as i64;
+            let page =
+                comment::get_comments(&api_handler, &todo_id, offset).await;
+            *has_more.write() = page.len() as i64 == comment::PAGE_SIZE;
+            comments.write().extend(page);
+        });
+    };
+
+    let create_comment_handler = move |body: String| {
+        to_owned![api_handler, error_handler, draft, discussion_future];
+        let mentions = mentions(&body, &assignees.read());
+
+        cx.spawn(async move {
+            let status_code = comment::create_comment(
+                &api_handler,
+                &todo_id,
+                CreateComment { body, mentions },
+            )
+            .await;
+            if status_code.is_success() {
+                draft.write().clear();
+                discussion_future.restart();
+            } else {
+                error_handler.send(crate::error::Error(
+                    status_code,
+                    "Failed to comment on the todo.".into(),
+                ));
+            }
+        });
+    };
+
+    let edit_comment_handler = move |comment_id: i64| {
+        let body = comments
+            .peek()
+            .iter()
+            .find(|comment| comment.id == comment_id)
+            .map(|comment| comment.body.clone());
+        *edited.write() = body.map(|body| (comment_id, body));
+    };
+
+    let edited_body = move || {
+        edited.read().as_ref().map(|(_, body)| body.clone()).unwrap_or_default()
+    };
+
+    let update_comment_handler = move |comment_id: i64, body: String| {
+        to_owned![api_handler, error_handler, edited, discussion_future];
+        let mentions = mentions(&body, &assignees.read());
+
+        cx.spawn(async move {
+            let status_code = comment::update_comment(
+                &api_handler,
+                &todo_id,
+                &comment_id,
+                UpdateComment { body, mentions },
+            )
+            .await;
+            if status_code.is_success() {
+                *edited.write() = None;
+                discussion_future.restart();
+            } else {
+                error_handler.send(crate::error::Error(
+                    status_code,
+                    "Failed to update the comment.".into(),
+                ));
+            }
+        });
+    };
+
+    let delete_comment_handler = move |comment_id: i64| {
+        to_owned![api_handler, error_handler, comments];
+
+        cx.spawn(async move {
+            let status_code =
+                comment::delete_comment(&api_handler, &todo_id, &comment_id)
+                    .await;
+            if status_code.is_success() {
+                comments.write().retain(|comment| comment.id != comment_id);
+            } else {
+                error_handler.send(crate::error::Error(
+                    status_code,
+                    "Failed to delete the comment.".into(),
+                ));
+            }
+        });
+    };
+
+    render! {
+        match discussion_future.value() {
+            Some(user_id) => render! {
+                div {
+                    class: "mt-2 space-y-2 border-l pl-3 dark:border-zinc-600",
+                    onclick: move |event| event.stop_propagation(),
+                    for comment in comments.read().iter().cloned() {
+                        div {
+                            class: "text-sm",
+                            p {
+                                class: "text-xs dark:text-zinc-500",
+                                "{comment.author_name} · {written_at(&comment)}"
+                            }
+                            if edited.read().as_ref().is_some_and(|(id, _)| *id == comment.id) {
+                                render! {
+                                    form {
+                                        onsubmit: move |_| {
+                                            if let Some((id, body)) = edited.peek().clone() {
+                                                update_comment_handler(id, body);
+                                            }
+                                        },
+                                        textarea {
+                                            class: "w-full rounded bg-transparent p-1 focus:outline-none dark:border dark:border-zinc-500",
+                                            value: "{edited_body()}",
+                                            oninput: move |evt| {
+                                                *edited.write() = Some((comment.id, evt.value.clone()));
+                                            },
+                                        }
+                                        div {
+                                            class: "flex justify-end space-x-2",
+                                            button {
+                                                class: "rounded px-3 py-1 dark:hover:bg-zinc-700",
+                                                r#type: "button",
+                                                onclick: move |_| *edited.write() = None,
+                                                "Cancel"
+                                            }
+                                            button {
+                                                class: "rounded bg-sky-600 px-3 py-1 text-white hover:bg-sky-500",
+                                                r#type: "submit",
+                                                "Save"
+                                            }
+                                        }
+                                    }
+                                }
+                            } else {
+                                render! {
+                                    div {
+                                        class: "prose prose-sm dark:prose-invert",
+                                        dangerous_inner_html: "{to_html(&comment.body)}"
+                                    }
+                                    if comment.author_id == *user_id {
+                                        render! {
+                                            div {
+                                                class: "flex space-x-2 text-xs dark:text-zinc-500",
+                                                button {
+                                                    class: "hover:underline",
+                                                    onclick: move |_| edit_comment_handler(comment.id),
+                                                    "Edit"
+                                                }
+                                                button {
+                                                    class: "hover:underline",
+                                                    onclick: move |_| delete_comment_handler(comment.id),
+                                                    "Delete"
+                                                }
+                                            }
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                    if *has_more.read() {
+                        render! {
+                            button {
+                                class: "text-xs dark:text-zinc-500 hover:underline",
+                                onclick: move |_| load_more_handler(),
+                                "Show more comments"
+                            }
+                        }
+                    }
+                    form {
+                        onsubmit: move |_| {
+                            let body = draft.peek().clone();
+                            if !body.trim().is_empty() {
+                                create_comment_handler(body);
+                            }
+                        },
+                        textarea {
+                            class: "w-full rounded bg-transparent p-1 text-sm focus:outline-none dark:border dark:border-zinc-500 dark:placeholder:text-zinc-500",
+                            placeholder: "Write a comment, markdown and @mentions work",
+                            value: "{draft}",
+                            oninput: move |evt| {
+                                *draft.write() = evt.value.clone();
+                            },
+                        }
+                        div {
+                            class: "flex justify-end",
+                            button {
+                                class: "rounded bg-sky-600 px-3 py-1 text-sm text-white hover:bg-sky-500",
+                                r#type: "submit",
+                                "Comment"
+                            }
+                        }
+                    }
+                }
+            },
+            None => render! { div { class: "text-sm", "Loading discussion..." } },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn to_html_drops_script_destinations() {
+        assert_eq!(
+            to_html("[click](javascript:alert(1)) ![x](data:text/html,y)"),
+            "<p><a href=\"\">click</a> <img src=\"\" alt=\"x\" /></p>\n"
+        );
+        assert_eq!(
+            to_html("<JavaScript:alert(1)>"),
+            "<p><a href=\"\">JavaScript:alert(1)</a></p>\n"
+        );
+    }
+
+    #[test]
+    fn to_html_keeps_web_and_email_destinations() {
+        assert_eq!(
+            to_html("[docs](https://example.com) <jane@example.com>"),
+            "<p><a href=\"https://example.com\">docs</a> \
+             <a href=\"mailto:jane@example.com\">jane@example.com</a></p>\n"
+        );
+    }
+}
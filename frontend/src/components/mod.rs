@@ -1,7 +1,9 @@
 pub(crate) mod check_box;
+pub(crate) mod discussion;
 pub(crate) mod popup;
 pub(crate) mod sign_in;
 pub(crate) mod sign_up;
 pub(crate) mod todo;
 pub(crate) mod todo_list;
+pub(crate) mod transfer;
 pub(crate) mod user;
@@ -1,24 +1,82 @@
+use crate::api::auth::SignInStep;
 use crate::handler::api_handler::ApiHandler;
 use crate::Route;
 use dioxus::prelude::*;
 use dioxus_router::prelude::use_navigator;
 use dioxus_router::prelude::Link;
-use shared::models::user::SignInUser;
+use shared::models::user::{SignInUser, TotpCode};
 
 #[component]
 pub(crate) fn SignIn(cx: Scope) -> Element {
     let api_handler: &ApiHandler = use_context(cx).unwrap();
     let navigator = use_navigator(cx);
+    // set once the password was accepted, but a code is still missing
+    let code_required = use_state(cx, || false);
 
     let sign_in_handler = move |sign_in_user: SignInUser| {
+        to_owned![api_handler, navigator, code_required];
+
+        cx.spawn(async move {
+            let step =
+                crate::api::auth::sign_in(&api_handler, sign_in_user).await;
+
+            if step == SignInStep::CodeRequired {
+                code_required.set(true);
+            } else {
+                navigator.replace(Route::TodoList {});
+            }
+        });
+    };
+
+    let code_handler = move |totp_code: TotpCode| {
         to_owned![api_handler, navigator];
 
         cx.spawn(async move {
-            crate::api::auth::sign_in(&api_handler, sign_in_user).await;
-            navigator.replace(Route::TodoList {});
+            // a wrong code keeps the prompt open for another try
+            if crate::api::auth::sign_in_with_code(&api_handler, totp_code)
+                .await
+            {
+                navigator.replace(Route::TodoList {});
+            }
         });
     };
 
+    if *code_required.get() {
+        return render! {
+            form {
+                onsubmit: move |event| {
+                    tracing::debug!("Encountered event: {:?}", event);
+                    event.stop_propagation();
+                    let code = event.values["code"].first().unwrap().to_string();
+                    code_handler(TotpCode { code });
+                },
+                class: "p-6 grid",
+                label { class: "block mb-1", r#for: "code", "Code:" }
+                input {
+                    class: "row dark:bg-zinc-800 mb-4 shadow appearance-none rounded py-3 px-4 leading-tight focus:outline-none focus:shadow-outline",
+                    r#type: "text",
+                    id: "code",
+                    name: "code",
+                    placeholder: "Enter the code of your authenticator app or a recovery code...",
+                    required: true
+                }
+                div { class: "flex flex-row justify-between items-center",
+                    button {
+                        r#type: "submit",
+                        class: "dark:bg-zinc-700
+                                dark:hover:bg-zinc-600
+                                bg-zinc-400
+                                hover:bg-zinc-500
+                                py-2
+                                px-4
+                                rounded",
+                        "Verify"
+                    }
+                }
+            }
+        };
+    }
+
     render! {
         form {
             onsubmit: move |event| {
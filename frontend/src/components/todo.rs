@@ -1,25 +1,44 @@
 use crate::api::*;
 use crate::components::check_box::CheckBox;
+use crate::components::discussion::Discussion;
 use crate::handler::api_handler::ApiHandler;
 use dioxus::prelude::*;
-use dioxus_signals::Signal;
+use dioxus_signals::{use_signal, Signal};
 use shared::models::todo::{Todo, UpdateTodo};
+use std::collections::HashMap;
 
 #[component]
 pub(crate) fn Todo(
     cx: Scope,
     todo: Signal<Todo>,
     is_edited: Signal<Option<i64>>,
+    assignees: Signal<HashMap<i64, String>>,
 ) -> Element {
     let error_handler: &Coroutine<crate::error::Error> =
         use_coroutine_handle(cx)?;
     let api_handler: &ApiHandler = use_context(cx).unwrap();
     let todo_reader = todo.read().clone();
+    let is_discussed: Signal<bool> = use_signal(cx, || false);
     let is_edited_reader = match *is_edited.read() {
         Some(id) => id == todo_reader.id,
         None => false,
     };
 
+    // personal todos can only be assigned to the user themselves
+    let assignee = match (todo_reader.assignee_id, todo_reader.list_id) {
+        (Some(assignee_id), Some(_)) => {
+            assignees.read().get(&assignee_id).cloned().unwrap_or_default()
+        }
+        (Some(_), None) => "you".to_string(),
+        (None, _) => String::new(),
+    };
+
+    let discussion_label = if *is_discussed.read() {
+        "💬 Hide discussion"
+    } else {
+        "💬 Discussion"
+    };
+
     let line_through_css_class =
         if todo_reader.is_done { "line-through" } else { "" };
 
@@ -69,6 +88,7 @@ pub(crate) fn Todo(
                         title: None,
                         description: None,
                         is_done: Some(!todo_reader.is_done),
+                        due_at: None,
                     });
                 },
                 r#type: "checkbox",
@@ -93,6 +113,14 @@ pub(crate) fn Todo(
                                 class: "text-sm dark:text-zinc-400",
                                 "{todo_reader.description}"
                             }
+                            if todo_reader.assignee_id.is_some() {
+                                render! {
+                                    p {
+                                        class: "text-xs dark:text-zinc-500",
+                                        "👤 {assignee}"
+                                    }
+                                }
+                            }
                         }
                     }
                 } else {
@@ -106,6 +134,7 @@ pub(crate) fn Todo(
                                     title: Some(todo_reader.title.clone()),
                                     description: Some(todo_reader.description.clone()),
                                     is_done: None,
+                                    due_at: None,
                                 });
                             },
                             input {
@@ -151,6 +180,20 @@ pub(crate) fn Todo(
                         }
                     }
                 }
+                button {
+                    class: "self-start text-xs dark:text-zinc-500 hover:underline",
+                    onclick: move |event| {
+                        event.stop_propagation();
+                        let is_open = *is_discussed.peek();
+                        *is_discussed.write() = !is_open;
+                    },
+                    "{discussion_label}"
+                }
+                if *is_discussed.read() {
+                    render! {
+                        Discussion { todo_id: todo_reader.id, assignees: *assignees }
+                    }
+                }
             }
         }
     }
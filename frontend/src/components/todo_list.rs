@@ -1,26 +1,71 @@
+use std::collections::HashMap;
+
 use dioxus::prelude::*;
 use dioxus_signals::{use_signal, Signal};
-use shared::models::todo::Todo;
+use shared::models::{
+    list::{ListInvitation, TodoList},
+    todo::Todo,
+};
 
 use crate::{api, components, handler::api_handler::ApiHandler, Popup};
 
 #[component]
 pub(crate) fn TodoList(cx: Scope) -> Element {
+    let error_handler: &Coroutine<crate::error::Error> =
+        use_coroutine_handle(cx)?;
     let api_handler: &ApiHandler = use_context(cx).unwrap();
     let todo_list: Signal<Vec<Signal<Todo>>> = use_signal(cx, Vec::new);
+    let lists: Signal<Vec<TodoList>> = use_signal(cx, Vec::new);
+    let invitations: Signal<Vec<ListInvitation>> = use_signal(cx, Vec::new);
+    // names of the list members by their id, to show who a todo is assigned to
+    let assignees: Signal<HashMap<i64, String>> = use_signal(cx, HashMap::new);
     let todo_item_is_edited: Signal<Option<i64>> = use_signal(cx, || None);
 
     let todo_list_future = use_future(cx, (), |_| {
-        to_owned![api_handler, todo_list];
+        to_owned![api_handler, todo_list, lists, invitations, assignees];
         async move {
             *todo_list.write() = api::todo::get_all_todos(&api_handler)
                 .await
                 .into_iter()
                 .map(Signal::new)
                 .collect();
+            *lists.write() = api::list::get_lists(&api_handler).await;
+            let mut names = HashMap::new();
+            for list in lists.peek().iter() {
+                for member in
+                    api::list::get_members(&api_handler, &list.id).await
+                {
+                    names.insert(member.user_id, member.name);
+                }
+            }
+            *assignees.write() = names;
+            *invitations.write() =
+                api::list::get_invitations(&api_handler).await;
         }
     });
 
+    let answer_invitation_handler = move |invitation_id: i64, accept: bool| {
+        to_owned![api_handler, error_handler, todo_list_future];
+
+        cx.spawn(async move {
+            let status_code = api::list::answer_invitation(
+                &api_handler,
+                &invitation_id,
+                accept,
+            )
+            .await;
+            if status_code.is_success() {
+                // an accepted invitation adds a list and its todos
+                todo_list_future.restart();
+            } else {
+                error_handler.send(crate::error::Error(
+                    status_code,
+                    "Failed to answer the invitation.".into(),
+                ));
+            }
+        });
+    };
+
     render! {
         match todo_list_future.value() {
             Some(_) => render! {
@@ -30,15 +75,73 @@ pub(crate) fn TodoList(cx: Scope) -> Element {
                         event.stop_propagation();
                         *todo_item_is_edited.write() = None;
                     },
+                    if !invitations.read().is_empty() {
+                        render! {
+                            h1 { class: "relative flex justify-left pt-8 pl-4 text-lg", "✉️ Invitations" }
+                            span {
+                                class: "flex items-center",
+                                span { class: "h-px flex-1 bg-white" }
+                            }
+                            ul {
+                                for invitation in invitations.read().iter().cloned() {
+                                    li {
+                                        class: "flex items-center space-x-2 px-4 py-3",
+                                        p {
+                                            class: "flex-1",
+                                            "{invitation.invited_by} invited you to {invitation.list_name} as {invitation.permission.as_str()}"
+                                        }
+                                        button {
+                                            class: "rounded bg-zinc-300 px-3 py-1 text-zinc-950 hover:bg-gray-200",
+                                            onclick: move |event| {
+                                                event.stop_propagation();
+                                                answer_invitation_handler(invitation.id, true);
+                                            },
+                                            "Accept"
+                                        }
+                                        button {
+                                            class: "rounded px-3 py-1 dark:hover:bg-zinc-700",
+                                            onclick: move |event| {
+                                                event.stop_propagation();
+                                                answer_invitation_handler(invitation.id, false);
+                                            },
+                                            "Decline"
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
                     h1 { class: "relative flex justify-left pt-8 pl-4 text-lg", "📥 Today" }
                     span {
                         class: "flex items-center",
                         span { class: "h-px flex-1 bg-white" }
                     }
                     ul {
-                        for todo in todo_list.read().iter().filter(|todo| !todo.read().is_done) {
+                        for todo in todo_list.read().iter().filter(|todo| {
+                            let todo = todo.read();
+                            todo.list_id.is_none() && !todo.is_done
+                        }) {
                             li {
-                                components::todo::Todo { todo: *todo, is_edited: todo_item_is_edited }
+                                components::todo::Todo { todo: *todo, is_edited: todo_item_is_edited, assignees: assignees }
+                            }
+                        }
+                    }
+                    // the personal todos come first, every shared list gets
+                    // its own section
+                    for list in lists.read().iter().cloned() {
+                        h1 { class: "relative flex justify-left pt-8 pl-4 text-lg", "👥 {list.name}" }
+                        span {
+                            class: "flex items-center",
+                            span { class: "h-px flex-1 bg-white" }
+                        }
+                        ul {
+                            for todo in todo_list.read().iter().filter(|todo| {
+                                let todo = todo.read();
+                                todo.list_id == Some(list.id) && !todo.is_done
+                            }) {
+                                li {
+                                    components::todo::Todo { todo: *todo, is_edited: todo_item_is_edited, assignees: assignees }
+                                }
                             }
                         }
                     }
@@ -50,7 +153,7 @@ pub(crate) fn TodoList(cx: Scope) -> Element {
                     ul {
                         for todo in todo_list.read().iter().filter(|todo| todo.read().is_done) {
                             li {
-                                components::todo::Todo { todo: *todo, is_edited: todo_item_is_edited }
+                                components::todo::Todo { todo: *todo, is_edited: todo_item_is_edited, assignees: assignees }
                             }
                         }
                     }
@@ -0,0 +1,160 @@
+use async_std::stream::StreamExt;
+use dioxus::prelude::*;
+use dioxus_desktop::tao::event::Event;
+use dioxus_desktop::tao::menu::{MenuBar, MenuId, MenuItemAttributes};
+use dioxus_desktop::use_wry_event_handler;
+use dioxus_router::prelude::Outlet;
+use reqwest::StatusCode;
+use rfd::AsyncFileDialog;
+use shared::models::transfer::{ImportReport, ImportTodos, TransferFormat};
+
+use crate::{api, handler::api_handler::ApiHandler, Popup, Route};
+
+/// The entry of the export menu for `format`.
+fn export_menu_id(format: TransferFormat) -> MenuId {
+    MenuId::new(&format!("export_{}", format.as_str()))
+}
+
+fn import_menu_id() -> MenuId {
+    MenuId::new("import")
+}
+
+/// The "Todos" menu with an "Export…" entry for every format and "Import…".
+pub(crate) fn menu() -> MenuBar {
+    let mut menu = MenuBar::new();
+    for format in TransferFormat::ALL {
+        let title = format!("Export as {}…", format.label());
+        menu.add_item(
+            MenuItemAttributes::new(&title).with_id(export_menu_id(format)),
+        );
+    }
+    menu.add_item(MenuItemAttributes::new("Import…").with_id(import_menu_id()));
+
+    menu
+}
+
+fn summary(report: &ImportReport) -> String {
+    match report.errors.first() {
+        Some(error) => format!(
+            "Nothing was imported, row {}: {}",
+            error.row, error.message
+        ),
+        None => format!(
+            "Imported {} new and {} changed todos.",
+            report.created, report.updated
+        ),
+    }
+}
+
+async fn export(
+    api_handler: &ApiHandler,
+    format: TransferFormat,
+) -> Result<Option<String>, crate::error::Error> {
+    let content = api::transfer::export_todos(api_handler, format)
+        .await
+        .map_err(|status_code| {
+            crate::error::Error(
+                status_code,
+                "Failed to export the todos.".into(),
+            )
+        })?;
+    let Some(file) = AsyncFileDialog::new()
+        .add_filter(format.label(), &[format.file_extension()])
+        .set_file_name(format!("todos.{}", format.file_extension()))
+        .save_file()
+        .await
+    else {
+        return Ok(None);
+    };
+    file.write(content.as_bytes()).await.map_err(|e| {
+        crate::error::Error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to write {}: {e}", file.file_name()).into(),
+        )
+    })?;
+
+    Ok(Some(format!("Exported the todos to {}.", file.file_name())))
+}
+
+async fn import(
+    api_handler: &ApiHandler,
+) -> Result<Option<String>, crate::error::Error> {
+    let extensions = TransferFormat::ALL.map(|format| format.file_extension());
+    let Some(file) = AsyncFileDialog::new()
+        .add_filter("Todos", &extensions)
+        .pick_file()
+        .await
+    else {
+        return Ok(None);
+    };
+    // the format follows the extension, like the one of an exported file
+    let format = file
+        .file_name()
+        .rsplit_once('.')
+        .and_then(|(_, extension)| {
+            extension.to_lowercase().parse::<TransferFormat>().ok()
+        })
+        .unwrap_or_default();
+    let content = String::from_utf8(file.read().await).map_err(|_| {
+        crate::error::Error(
+            StatusCode::BAD_REQUEST,
+            format!("{} is not a text file.", file.file_name()).into(),
+        )
+    })?;
+
+    let import_todos = ImportTodos { format, content, ..Default::default() };
+    let report = api::transfer::import_todos(api_handler, import_todos)
+        .await
+        .map_err(|status_code| {
+        crate::error::Error(status_code, "Failed to import the todos.".into())
+    })?;
+
+    Ok(Some(summary(&report)))
+}
+
+/// Exports and imports the todos when their menu entries are chosen.
+#[component]
+pub(crate) fn TransferLayer(cx: Scope) -> Element {
+    let api_handler: &ApiHandler = use_context(cx).unwrap();
+    let error_handler: &Coroutine<crate::error::Error> =
+        use_coroutine_handle(cx)?;
+    let popup_handler: &Coroutine<Popup> = use_coroutine_handle(cx)?;
+
+    let transfer_handler =
+        use_coroutine(cx, |mut receiver: UnboundedReceiver<MenuId>| {
+            to_owned![api_handler, error_handler, popup_handler];
+            async move {
+                while let Some(menu_id) = receiver.next().await {
+                    let result = if menu_id == import_menu_id() {
+                        import(&api_handler).await
+                    } else {
+                        match TransferFormat::ALL
+                            .into_iter()
+                            .find(|format| export_menu_id(*format) == menu_id)
+                        {
+                            Some(format) => export(&api_handler, format).await,
+                            None => Ok(None),
+                        }
+                    };
+                    match result {
+                        Ok(Some(message)) => {
+                            popup_handler.send(Popup::Push(message))
+                        }
+                        Ok(None) => {}
+                        Err(error) => error_handler.send(error),
+                    }
+                }
+            }
+        });
+
+    use_wry_event_handler(cx, {
+        to_owned![transfer_handler];
+        move |event, _| {
+            if let Event::MenuEvent { menu_id, .. } = event {
+                transfer_handler.send(*menu_id);
+            }
+        }
+    });
+
+    render! { Outlet::<Route> {} }
+}
@@ -1,6 +1,6 @@
 use crate::handler::api_handler::ApiHandler;
 use dioxus::prelude::*;
-use shared::models::user::User;
+use shared::models::user::UserAccount;
 
 #[component]
 pub(crate) fn User(cx: Scope) -> Element {
@@ -10,7 +10,7 @@ pub(crate) fn User(cx: Scope) -> Element {
         to_owned![api_handler];
         async move {
             let response = api_handler.get("/users").await;
-            response.json::<User>().await
+            response.json::<UserAccount>().await
         }
     });
 
@@ -20,6 +20,7 @@ mod handler;
 use components::sign_in::SignIn;
 use components::sign_up::SignUp;
 use components::todo_list::TodoList;
+use components::transfer::TransferLayer;
 use components::user::User;
 use handler::api_handler::ApiHandler;
 
@@ -72,6 +73,7 @@ fn main() {
     window_menu.add_native_item(MenuItem::Separator);
     window_menu.add_native_item(MenuItem::Quit);
     menu_bar.add_submenu("Window", true, window_menu);
+    menu_bar.add_submenu("Todos", true, components::transfer::menu());
 
     // since tao supports none of the below items on linux we should only add
     // them on macos/windows
@@ -148,6 +150,7 @@ enum Route {
     #[layout(BaseLayer)]
     #[layout(PopupLayer)]
     #[layout(ErrorLayer)]
+    #[layout(TransferLayer)]
     #[route("/")]
     AuthCheck {},
     #[route("/signin")]
@@ -161,6 +164,7 @@ enum Route {
     #[end_layout]
     #[end_layout]
     #[end_layout]
+    #[end_layout]
     #[route("/:..route")]
     PageNotFound { route: Vec<String> },
 }
@@ -275,7 +279,7 @@ fn AuthCheck(cx: Scope) -> Element {
 
             if response.status().is_success() {
                 let user = response
-                    .json::<shared::models::user::User>()
+                    .json::<shared::models::user::UserAccount>()
                     .await
                     .expect("Unable to read user data after login.");
                 message_handler.send(Popup::Push(format!(
@@ -0,0 +1,43 @@
+use serde::{Deserialize, Serialize};
+
+/// A file attached to a todo. Only the metadata is kept in the database,
+/// the content is in the blob store.
+#[cfg_attr(feature = "backend", derive(sqlx::FromRow, utoipa::ToSchema))]
+#[derive(
+    Serialize,
+    Deserialize,
+    Debug,
+    Clone,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Default,
+)]
+pub struct Attachment {
+    pub id: i64,
+    pub todo_id: i64,
+    /// Unset once the uploader deleted their account.
+    pub uploader_id: Option<i64>,
+    pub file_name: String,
+    /// Size of the content in bytes.
+    pub size: i64,
+    /// The MIME type the uploader sent, e.g. `image/png`.
+    pub content_type: String,
+    /// Hex encoded SHA-256 hash of the content.
+    pub checksum: String,
+    /// Where the blob store keeps the content.
+    #[serde(skip)]
+    pub storage_key: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// How much of their quota the attachments a user uploaded take up.
+#[cfg_attr(feature = "backend", derive(utoipa::ToSchema))]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+pub struct StorageUsage {
+    /// Bytes taken up by the attachments the user uploaded.
+    pub used: i64,
+    /// Bytes the user may upload in total.
+    pub quota: i64,
+}
@@ -0,0 +1,248 @@
+use serde::{Deserialize, Serialize};
+
+/// What kind of record an audit event is about.
+#[cfg_attr(feature = "backend", derive(utoipa::ToSchema))]
+#[derive(
+    Serialize,
+    Deserialize,
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Default,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditEntity {
+    #[default]
+    Todo,
+    User,
+}
+
+impl AuditEntity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditEntity::Todo => "todo",
+            AuditEntity::User => "user",
+        }
+    }
+}
+
+impl std::str::FromStr for AuditEntity {
+    type Err = String;
+
+    fn from_str(entity: &str) -> Result<Self, Self::Err> {
+        match entity {
+            "todo" => Ok(AuditEntity::Todo),
+            "user" => Ok(AuditEntity::User),
+            _ => Err(format!("`{entity}` is not an audited entity")),
+        }
+    }
+}
+
+/// Reads the `entity` column, which only holds the names of the entities.
+impl From<String> for AuditEntity {
+    fn from(entity: String) -> Self {
+        entity.parse().unwrap_or_default()
+    }
+}
+
+/// The change an audit event records.
+#[cfg_attr(feature = "backend", derive(utoipa::ToSchema))]
+#[derive(
+    Serialize,
+    Deserialize,
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Default,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditAction {
+    #[default]
+    Created,
+    Updated,
+    Deleted,
+    /// A todo was assigned to someone else or unassigned.
+    Assigned,
+    EmailVerified,
+    /// The password of a user was replaced, the hashes are never recorded.
+    PasswordChanged,
+    RoleChanged,
+    Disabled,
+    Enabled,
+}
+
+impl AuditAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditAction::Created => "created",
+            AuditAction::Updated => "updated",
+            AuditAction::Deleted => "deleted",
+            AuditAction::Assigned => "assigned",
+            AuditAction::EmailVerified => "email_verified",
+            AuditAction::PasswordChanged => "password_changed",
+            AuditAction::RoleChanged => "role_changed",
+            AuditAction::Disabled => "disabled",
+            AuditAction::Enabled => "enabled",
+        }
+    }
+}
+
+impl std::str::FromStr for AuditAction {
+    type Err = String;
+
+    fn from_str(action: &str) -> Result<Self, Self::Err> {
+        match action {
+            "created" => Ok(AuditAction::Created),
+            "updated" => Ok(AuditAction::Updated),
+            "deleted" => Ok(AuditAction::Deleted),
+            "assigned" => Ok(AuditAction::Assigned),
+            "email_verified" => Ok(AuditAction::EmailVerified),
+            "password_changed" => Ok(AuditAction::PasswordChanged),
+            "role_changed" => Ok(AuditAction::RoleChanged),
+            "disabled" => Ok(AuditAction::Disabled),
+            "enabled" => Ok(AuditAction::Enabled),
+            _ => Err(format!("`{action}` is not an audited action")),
+        }
+    }
+}
+
+/// Reads the `action` column, which only holds the names of the actions.
+impl From<String> for AuditAction {
+    fn from(action: String) -> Self {
+        action.parse().unwrap_or_default()
+    }
+}
+
+/// A change of a todo or a user. Events are never changed or deleted, not
+/// even with the todo or user.
+#[cfg_attr(feature = "backend", derive(sqlx::FromRow, utoipa::ToSchema))]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct AuditEvent {
+    pub id: i64,
+    /// The user who made the change, unset for changes made by the server.
+    pub actor_id: Option<i64>,
+    #[cfg_attr(feature = "backend", sqlx(try_from = "String"))]
+    pub action: AuditAction,
+    #[cfg_attr(feature = "backend", sqlx(try_from = "String"))]
+    pub entity: AuditEntity,
+    pub entity_id: i64,
+    /// The changed fields with their values, e.g.
+    /// `{"title": {"before": "Milk", "after": "Oat milk"}}`. Fields of a
+    /// created record have no value before, those of a deleted one none
+    /// after.
+    #[cfg_attr(feature = "backend", schema(value_type = Object))]
+    pub changes: serde_json::Value,
+    /// The `X-Request-Id` of the request that made the change.
+    pub request_id: Option<String>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// What happened to the credentials or sessions of an account.
+#[cfg_attr(feature = "backend", derive(utoipa::ToSchema))]
+#[derive(
+    Serialize,
+    Deserialize,
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Default,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum SecurityEventKind {
+    /// A login was completed, with a password, a second factor, a passkey or
+    /// single sign-on.
+    #[default]
+    LoginSucceeded,
+    /// A login failed for an invalid password, code or passkey.
+    LoginFailed,
+    PasswordChanged,
+    /// One session was logged out or all sessions of the user were revoked.
+    SessionsRevoked,
+    /// All personal access tokens of the user were revoked, e.g. by a
+    /// password reset.
+    AccessTokensRevoked,
+    /// The calendar feed of the user got a new token or was turned off.
+    FeedTokenChanged,
+}
+
+impl SecurityEventKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SecurityEventKind::LoginSucceeded => "login_succeeded",
+            SecurityEventKind::LoginFailed => "login_failed",
+            SecurityEventKind::PasswordChanged => "password_changed",
+            SecurityEventKind::SessionsRevoked => "sessions_revoked",
+            SecurityEventKind::AccessTokensRevoked => "access_tokens_revoked",
+            SecurityEventKind::FeedTokenChanged => "feed_token_changed",
+        }
+    }
+}
+
+impl std::str::FromStr for SecurityEventKind {
+    type Err = String;
+
+    fn from_str(kind: &str) -> Result<Self, Self::Err> {
+        match kind {
+            "login_succeeded" => Ok(SecurityEventKind::LoginSucceeded),
+            "login_failed" => Ok(SecurityEventKind::LoginFailed),
+            "password_changed" => Ok(SecurityEventKind::PasswordChanged),
+            "sessions_revoked" => Ok(SecurityEventKind::SessionsRevoked),
+            "access_tokens_revoked" => {
+                Ok(SecurityEventKind::AccessTokensRevoked)
+            }
+            "feed_token_changed" => Ok(SecurityEventKind::FeedTokenChanged),
+            _ => Err(format!("`{kind}` is not a kind of security event")),
+        }
+    }
+}
+
+/// Reads the `kind` column, which only holds the names of the kinds.
+impl From<String> for SecurityEventKind {
+    fn from(kind: String) -> Self {
+        kind.parse().unwrap_or_default()
+    }
+}
+
+/// An entry of the security stream, which is kept apart from the audit
+/// events of the data.
+#[cfg_attr(feature = "backend", derive(sqlx::FromRow, utoipa::ToSchema))]
+#[derive(
+    Serialize,
+    Deserialize,
+    Debug,
+    Clone,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Default,
+)]
+pub struct SecurityEvent {
+    pub id: i64,
+    /// The account, unset for failed logins with an unknown email.
+    pub user_id: Option<i64>,
+    /// The logged in user who caused the event, e.g. an administrator who
+    /// reset the password. Unset for logins and mailed password resets.
+    pub actor_id: Option<i64>,
+    #[cfg_attr(feature = "backend", sqlx(try_from = "String"))]
+    pub kind: SecurityEventKind,
+    /// The address the request came from.
+    pub ip: Option<String>,
+    pub request_id: Option<String>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
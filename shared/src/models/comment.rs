@@ -0,0 +1,69 @@
+use serde::{Deserialize, Serialize};
+
+/// A comment in the discussion of a todo.
+#[cfg_attr(feature = "backend", derive(sqlx::FromRow, utoipa::ToSchema))]
+#[derive(
+    Serialize,
+    Deserialize,
+    Debug,
+    Clone,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Default,
+)]
+pub struct Comment {
+    pub id: i64,
+    pub todo_id: i64,
+    pub author_id: i64,
+    pub author_name: String,
+    /// Markdown, clients have to render it without raw HTML.
+    pub body: String,
+    /// The users mentioned in the body.
+    #[cfg_attr(feature = "backend", sqlx(skip))]
+    pub mentions: Vec<i64>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[cfg_attr(feature = "backend", derive(utoipa::ToSchema))]
+#[derive(
+    Serialize,
+    Deserialize,
+    Debug,
+    Clone,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Default,
+)]
+pub struct CreateComment {
+    /// Markdown.
+    pub body: String,
+    /// Users to mention, who have to be able to see the todo.
+    #[serde(default)]
+    pub mentions: Vec<i64>,
+}
+
+/// Replaces the body and the mentions of a comment.
+#[cfg_attr(feature = "backend", derive(utoipa::ToSchema))]
+#[derive(
+    Serialize,
+    Deserialize,
+    Debug,
+    Clone,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Default,
+)]
+pub struct UpdateComment {
+    /// Markdown.
+    pub body: String,
+    /// Users to mention, who have to be able to see the todo.
+    #[serde(default)]
+    pub mentions: Vec<i64>,
+}
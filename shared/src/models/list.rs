@@ -0,0 +1,198 @@
+use serde::{Deserialize, Serialize};
+
+/// What the members of a list may do with it, each permission includes the
+/// ones before it.
+#[cfg_attr(feature = "backend", derive(utoipa::ToSchema))]
+#[derive(
+    Serialize,
+    Deserialize,
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Default,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum ListPermission {
+    /// May read the list and its todos.
+    #[default]
+    Viewer,
+    /// May also create, update and delete todos of the list.
+    Editor,
+    /// May also rename the list and manage its members.
+    Admin,
+}
+
+impl ListPermission {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ListPermission::Viewer => "viewer",
+            ListPermission::Editor => "editor",
+            ListPermission::Admin => "admin",
+        }
+    }
+}
+
+impl std::str::FromStr for ListPermission {
+    type Err = String;
+
+    fn from_str(permission: &str) -> Result<Self, Self::Err> {
+        match permission {
+            "viewer" => Ok(ListPermission::Viewer),
+            "editor" => Ok(ListPermission::Editor),
+            "admin" => Ok(ListPermission::Admin),
+            _ => Err(format!(
+                "`{permission}` is not a permission, use viewer, editor or \
+                 admin"
+            )),
+        }
+    }
+}
+
+/// Reads the `permission` columns, which only hold the names of the
+/// permissions. An unknown permission only grants reading.
+impl From<String> for ListPermission {
+    fn from(permission: String) -> Self {
+        permission.parse().unwrap_or_default()
+    }
+}
+
+/// A list the session user is a member of.
+#[cfg_attr(feature = "backend", derive(sqlx::FromRow, utoipa::ToSchema))]
+#[derive(
+    Serialize,
+    Deserialize,
+    Debug,
+    Clone,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Default,
+)]
+pub struct TodoList {
+    pub id: i64,
+    pub name: String,
+    pub owner: i64,
+    /// The permission of the session user, always `admin` for the owner.
+    #[cfg_attr(feature = "backend", sqlx(try_from = "String"))]
+    pub permission: ListPermission,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[cfg_attr(feature = "backend", derive(utoipa::ToSchema))]
+#[derive(
+    Serialize,
+    Deserialize,
+    Debug,
+    Clone,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Default,
+)]
+pub struct CreateList {
+    pub name: String,
+}
+
+#[cfg_attr(feature = "backend", derive(utoipa::ToSchema))]
+#[derive(
+    Serialize,
+    Deserialize,
+    Debug,
+    Clone,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Default,
+)]
+pub struct UpdateList {
+    pub name: String,
+}
+
+#[cfg_attr(feature = "backend", derive(sqlx::FromRow, utoipa::ToSchema))]
+#[derive(
+    Serialize,
+    Deserialize,
+    Debug,
+    Clone,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Default,
+)]
+pub struct ListMember {
+    pub user_id: i64,
+    pub name: String,
+    pub email: String,
+    #[cfg_attr(feature = "backend", sqlx(try_from = "String"))]
+    pub permission: ListPermission,
+    pub joined_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[cfg_attr(feature = "backend", derive(utoipa::ToSchema))]
+#[derive(
+    Serialize,
+    Deserialize,
+    Debug,
+    Clone,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Default,
+)]
+pub struct InviteMember {
+    /// The email of a registered user.
+    pub email: String,
+    pub permission: ListPermission,
+}
+
+#[cfg_attr(feature = "backend", derive(utoipa::ToSchema))]
+#[derive(
+    Serialize,
+    Deserialize,
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Default,
+)]
+pub struct SetPermission {
+    pub permission: ListPermission,
+}
+
+/// An invitation of the session user to join a list.
+#[cfg_attr(feature = "backend", derive(sqlx::FromRow, utoipa::ToSchema))]
+#[derive(
+    Serialize,
+    Deserialize,
+    Debug,
+    Clone,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Default,
+)]
+pub struct ListInvitation {
+    pub id: i64,
+    pub list_id: i64,
+    pub list_name: String,
+    /// The name of the member who sent the invitation.
+    pub invited_by: String,
+    #[cfg_attr(feature = "backend", sqlx(try_from = "String"))]
+    pub permission: ListPermission,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
@@ -1,2 +1,7 @@
+pub mod attachment;
+pub mod audit;
+pub mod comment;
+pub mod list;
 pub mod todo;
+pub mod transfer;
 pub mod user;
@@ -1,7 +1,7 @@
 use dioxus::prelude::Props;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 
-#[cfg_attr(feature = "backend", derive(sqlx::FromRow))]
+#[cfg_attr(feature = "backend", derive(sqlx::FromRow, utoipa::ToSchema))]
 #[derive(
     Serialize,
     Deserialize,
@@ -20,10 +20,24 @@ pub struct Todo {
     pub description: String,
     pub is_done: bool,
     pub owner: i64,
+    /// The shared list of the todo, personal todos are in no list.
+    #[serde(default)]
+    pub list_id: Option<i64>,
+    /// The user who should get the todo done, the owner is the one who
+    /// created it.
+    #[serde(default)]
+    pub assignee_id: Option<i64>,
+    /// When the todo should be done.
+    #[serde(default)]
+    pub due_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// When the todo was marked as done, it is unset while the todo is not.
+    #[serde(default)]
+    pub completed_at: Option<chrono::DateTime<chrono::Utc>>,
     pub created_at: chrono::DateTime<chrono::Utc>,
     pub updated_at: chrono::DateTime<chrono::Utc>,
 }
 
+#[cfg_attr(feature = "backend", derive(utoipa::ToSchema))]
 #[derive(
     Serialize,
     Deserialize,
@@ -38,8 +52,14 @@ pub struct Todo {
 pub struct CreateTodo {
     pub title: String,
     pub description: String,
+    /// Creates the todo in a shared list instead of as a personal todo.
+    #[serde(default)]
+    pub list_id: Option<i64>,
+    #[serde(default)]
+    pub due_at: Option<chrono::DateTime<chrono::Utc>>,
 }
 
+#[cfg_attr(feature = "backend", derive(utoipa::ToSchema))]
 #[derive(
     Serialize,
     Deserialize,
@@ -56,6 +76,121 @@ pub struct UpdateTodo {
     pub title: Option<String>,
     pub description: Option<String>,
     pub is_done: Option<bool>,
+    /// Leaves the due date as it is if missing, `null` removes it.
+    #[serde(
+        default,
+        deserialize_with = "present",
+        skip_serializing_if = "Option::is_none"
+    )]
+    #[cfg_attr(
+        feature = "backend",
+        schema(value_type = Option<chrono::DateTime<chrono::Utc>>)
+    )]
+    pub due_at: Option<Option<chrono::DateTime<chrono::Utc>>>,
+}
+
+/// Deserializes a field that is present, even if it is `null`, to `Some`, so
+/// that a missing field can be told apart from `null`.
+fn present<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    T::deserialize(deserializer).map(Some)
+}
+
+#[cfg_attr(feature = "backend", derive(utoipa::ToSchema))]
+#[derive(
+    Serialize,
+    Deserialize,
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Default,
+)]
+pub struct AssignTodo {
+    /// The owner of a personal todo or a member of the list of the todo,
+    /// the todo is unassigned if this is not set.
+    pub assignee_id: Option<i64>,
+}
+
+/// What happened to a todo.
+#[cfg_attr(feature = "backend", derive(utoipa::ToSchema))]
+#[derive(
+    Serialize,
+    Deserialize,
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Default,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum TodoAction {
+    /// The todo was assigned to someone else or unassigned.
+    #[default]
+    Assigned,
+}
+
+impl TodoAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TodoAction::Assigned => "assigned",
+        }
+    }
+}
+
+impl std::str::FromStr for TodoAction {
+    type Err = String;
+
+    fn from_str(action: &str) -> Result<Self, Self::Err> {
+        match action {
+            "assigned" => Ok(TodoAction::Assigned),
+            _ => Err(format!("`{action}` is not an action on todos")),
+        }
+    }
+}
+
+/// Reads the `action` column, which only holds the names of the actions.
+impl From<String> for TodoAction {
+    fn from(action: String) -> Self {
+        action.parse().unwrap_or_default()
+    }
+}
+
+/// An entry of the activity of a todo.
+#[cfg_attr(feature = "backend", derive(sqlx::FromRow, utoipa::ToSchema))]
+#[derive(
+    Serialize,
+    Deserialize,
+    Debug,
+    Clone,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Default,
+)]
+pub struct TodoActivity {
+    pub id: i64,
+    pub todo_id: i64,
+    /// The user who did it, unset once their account is deleted.
+    pub actor_id: Option<i64>,
+    #[cfg_attr(feature = "backend", sqlx(try_from = "String"))]
+    pub action: TodoAction,
+    /// The assignee before the todo was assigned.
+    pub previous_assignee_id: Option<i64>,
+    /// The assignee after the todo was assigned.
+    pub assignee_id: Option<i64>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
 }
 
 #[derive(
@@ -94,6 +229,7 @@ impl From<FormUpdateTodo> for UpdateTodo {
                 }
                 None => None,
             },
+            due_at: None,
         }
     }
 }
@@ -105,6 +241,7 @@ impl From<Todo> for UpdateTodo {
             title: Some(todo.title),
             description: Some(todo.description),
             is_done: Some(todo.is_done),
+            due_at: Some(todo.due_at),
         }
     }
 }
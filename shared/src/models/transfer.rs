@@ -0,0 +1,194 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+/// Prefix of the external ids of exported todos that were not imported,
+/// followed by the id of the todo.
+pub const EXTERNAL_ID_PREFIX: &str = "lentos:";
+
+/// The fields read from every row of an imported file.
+pub const IMPORTED_FIELDS: [&str; 5] =
+    ["external_id", "title", "description", "is_done", "due_at"];
+
+/// File formats todos are exported to and imported from.
+#[cfg_attr(feature = "backend", derive(utoipa::ToSchema))]
+#[derive(
+    Serialize,
+    Deserialize,
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Default,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum TransferFormat {
+    /// Comma separated values with a header row, the columns are named like
+    /// the fields of [`ExportedTodo`].
+    #[default]
+    Csv,
+    /// An array of [`ExportedTodo`] objects.
+    Json,
+    /// An iCalendar file with a VTODO component for every todo, its UID is
+    /// the external id and its categories the name of the list of the todo.
+    #[serde(rename = "ics")]
+    Ical,
+}
+
+impl TransferFormat {
+    /// All formats, e.g. for the entries of an "Export…" menu.
+    pub const ALL: [TransferFormat; 3] =
+        [TransferFormat::Csv, TransferFormat::Json, TransferFormat::Ical];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TransferFormat::Csv => "csv",
+            TransferFormat::Json => "json",
+            TransferFormat::Ical => "ics",
+        }
+    }
+
+    /// The name to show to users.
+    pub fn label(&self) -> &'static str {
+        match self {
+            TransferFormat::Csv => "CSV",
+            TransferFormat::Json => "JSON",
+            TransferFormat::Ical => "iCalendar",
+        }
+    }
+
+    pub fn file_extension(&self) -> &'static str {
+        self.as_str()
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            TransferFormat::Csv => "text/csv; charset=utf-8",
+            TransferFormat::Json => "application/json",
+            TransferFormat::Ical => "text/calendar; charset=utf-8",
+        }
+    }
+}
+
+impl std::str::FromStr for TransferFormat {
+    type Err = String;
+
+    fn from_str(format: &str) -> Result<Self, Self::Err> {
+        match format {
+            "csv" => Ok(TransferFormat::Csv),
+            "json" => Ok(TransferFormat::Json),
+            "ics" => Ok(TransferFormat::Ical),
+            _ => {
+                Err(format!("`{format}` is not a format, use csv, json or ics"))
+            }
+        }
+    }
+}
+
+/// A todo in an exported file.
+#[cfg_attr(feature = "backend", derive(utoipa::ToSchema))]
+#[derive(
+    Serialize,
+    Deserialize,
+    Debug,
+    Clone,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Default,
+)]
+pub struct ExportedTodo {
+    /// The id the todo was imported under, `lentos:` and the id of the todo
+    /// otherwise. Importing the file again updates the todo.
+    pub external_id: String,
+    pub title: String,
+    pub description: String,
+    pub is_done: bool,
+    /// RFC 3339 in CSV files, a date like `2024-01-31` is imported as its
+    /// midnight in UTC.
+    pub due_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// Only exported, importing a todo that is done completes it now.
+    pub completed_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// The shared list of the todo, personal todos are in no list.
+    pub list_id: Option<i64>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[cfg_attr(feature = "backend", derive(utoipa::ToSchema))]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+pub struct ImportTodos {
+    pub format: TransferFormat,
+    /// The content of the file.
+    pub content: String,
+    /// The column to read a field from by the name of the field, e.g.
+    /// `{"title": "Task"}`. Fields are read from the column of the same name
+    /// by default, only `title` is required. iCalendar files are read from
+    /// the properties of their VTODO components instead.
+    #[serde(default)]
+    pub columns: BTreeMap<String, String>,
+    /// Only validates the rows and reports what an import would do.
+    #[serde(default)]
+    pub dry_run: bool,
+    /// Creates the new todos in a shared list instead of as personal todos.
+    #[serde(default)]
+    pub list_id: Option<i64>,
+}
+
+/// Why a row of an imported file cannot be imported.
+#[cfg_attr(feature = "backend", derive(utoipa::ToSchema))]
+#[derive(
+    Serialize,
+    Deserialize,
+    Debug,
+    Clone,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Default,
+)]
+pub struct ImportError {
+    /// Number of the row, starting at 1 with the first todo.
+    pub row: usize,
+    pub message: String,
+}
+
+/// What an import did, or would do for a dry run. Nothing is imported if a
+/// single row has an error.
+#[cfg_attr(feature = "backend", derive(utoipa::ToSchema))]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+pub struct ImportReport {
+    /// Whether the todos were changed.
+    pub applied: bool,
+    pub created: usize,
+    pub updated: usize,
+    pub unchanged: usize,
+    pub errors: Vec<ImportError>,
+}
+
+/// The iCalendar feed calendar apps subscribe to, it needs no login.
+#[cfg_attr(feature = "backend", derive(utoipa::ToSchema))]
+#[derive(
+    Serialize,
+    Deserialize,
+    Debug,
+    Clone,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Default,
+)]
+pub struct CalendarFeed {
+    /// Secret token in the path of the feed. It is only shown once, only its
+    /// hash is stored.
+    pub token: String,
+    /// Path of the feed on the server, e.g. `/api/v1/calendar/<token>.ics`.
+    pub path: String,
+}
@@ -1,6 +1,6 @@
 use serde::{Deserialize, Serialize};
 
-#[cfg_attr(feature = "backend", derive(sqlx::FromRow))]
+#[cfg_attr(feature = "backend", derive(sqlx::FromRow, utoipa::ToSchema))]
 #[derive(
     Serialize,
     Deserialize,
@@ -19,8 +19,70 @@ pub struct User {
     pub password: String,
     pub created_at: chrono::DateTime<chrono::Utc>,
     pub updated_at: chrono::DateTime<chrono::Utc>,
+    /// Set once the user proved to own `email`.
+    #[serde(default)]
+    pub email_verified_at: Option<chrono::DateTime<chrono::Utc>>,
+    #[serde(default)]
+    #[cfg_attr(feature = "backend", sqlx(try_from = "String"))]
+    pub role: Role,
+    /// Disabled users can neither log in nor use their sessions and tokens.
+    #[serde(default)]
+    pub disabled_at: Option<chrono::DateTime<chrono::Utc>>,
 }
 
+/// What a user may do besides managing their own todos and account.
+#[cfg_attr(feature = "backend", derive(utoipa::ToSchema))]
+#[derive(
+    Serialize,
+    Deserialize,
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Default,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    #[default]
+    User,
+    /// May use the admin api to manage all users.
+    Admin,
+}
+
+impl Role {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::User => "user",
+            Role::Admin => "admin",
+        }
+    }
+}
+
+impl std::str::FromStr for Role {
+    type Err = String;
+
+    fn from_str(role: &str) -> Result<Self, Self::Err> {
+        match role {
+            "user" => Ok(Role::User),
+            "admin" => Ok(Role::Admin),
+            _ => Err(format!("`{role}` is not a role, use user or admin")),
+        }
+    }
+}
+
+/// Reads the `role` column, which only holds the names of the roles. An
+/// unknown role grants nothing.
+impl From<String> for Role {
+    fn from(role: String) -> Self {
+        role.parse().unwrap_or_default()
+    }
+}
+
+#[cfg_attr(feature = "backend", derive(utoipa::ToSchema))]
 #[derive(
     Serialize,
     Deserialize,
@@ -38,6 +100,7 @@ pub struct CreateUser {
     pub password: String,
 }
 
+#[cfg_attr(feature = "backend", derive(utoipa::ToSchema))]
 #[derive(
     Serialize,
     Deserialize,
@@ -52,9 +115,12 @@ pub struct CreateUser {
 pub struct UpdateUser {
     pub name: Option<String>,
     pub email: Option<String>,
+    /// The new password, which needs `current_password`.
     pub password: Option<String>,
+    pub current_password: Option<String>,
 }
 
+#[cfg_attr(feature = "backend", derive(utoipa::ToSchema))]
 #[derive(
     Serialize,
     Deserialize,
@@ -70,3 +136,341 @@ pub struct SignInUser {
     pub email: String,
     pub password: String,
 }
+
+#[cfg_attr(feature = "backend", derive(utoipa::ToSchema))]
+#[derive(
+    Serialize,
+    Deserialize,
+    Debug,
+    Clone,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Default,
+)]
+pub struct SignInResponse {
+    /// The password was right, but the login has to be completed with a
+    /// code from `/users/login/totp`.
+    pub two_factor_required: bool,
+}
+
+#[cfg_attr(feature = "backend", derive(utoipa::ToSchema))]
+#[derive(
+    Serialize,
+    Deserialize,
+    Debug,
+    Clone,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Default,
+)]
+pub struct TotpCode {
+    /// A code from the authenticator app or one of the recovery codes.
+    pub code: String,
+}
+
+#[cfg_attr(feature = "backend", derive(utoipa::ToSchema))]
+#[derive(
+    Serialize,
+    Deserialize,
+    Debug,
+    Clone,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Default,
+)]
+pub struct TotpEnrollment {
+    /// Base32 encoded secret, for authenticators that cannot scan the code.
+    pub secret: String,
+    pub otpauth_uri: String,
+    /// The `otpauth_uri` as QR code in SVG format.
+    pub qr_code_svg: String,
+}
+
+#[cfg_attr(feature = "backend", derive(utoipa::ToSchema))]
+#[derive(
+    Serialize,
+    Deserialize,
+    Debug,
+    Clone,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Default,
+)]
+pub struct RecoveryCodes {
+    /// One-time codes that replace a code from the authenticator, they are
+    /// shown only once.
+    pub codes: Vec<String>,
+}
+
+#[cfg_attr(feature = "backend", derive(utoipa::ToSchema))]
+#[derive(
+    Serialize,
+    Deserialize,
+    Debug,
+    Clone,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Default,
+)]
+pub struct VerifyEmail {
+    /// The token from the verification mail.
+    pub token: String,
+}
+
+#[cfg_attr(feature = "backend", derive(utoipa::ToSchema))]
+#[derive(
+    Serialize,
+    Deserialize,
+    Debug,
+    Clone,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Default,
+)]
+pub struct RequestPasswordReset {
+    pub email: String,
+}
+
+#[cfg_attr(feature = "backend", derive(utoipa::ToSchema))]
+#[derive(
+    Serialize,
+    Deserialize,
+    Debug,
+    Clone,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Default,
+)]
+pub struct ResetPassword {
+    /// The token from the password reset mail.
+    pub token: String,
+    pub password: String,
+}
+
+/// A WebAuthn credential of the session user, without its public key.
+#[cfg_attr(feature = "backend", derive(utoipa::ToSchema))]
+#[derive(
+    Serialize,
+    Deserialize,
+    Debug,
+    Clone,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Default,
+)]
+pub struct PasskeySummary {
+    pub id: i64,
+    pub name: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub last_used_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+#[cfg_attr(feature = "backend", derive(utoipa::ToSchema))]
+#[derive(
+    Serialize,
+    Deserialize,
+    Debug,
+    Clone,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Default,
+)]
+pub struct StartPasskeyRegistration {
+    /// Tells the passkeys of a user apart, e.g. the name of the device.
+    pub name: String,
+}
+
+#[cfg_attr(feature = "backend", derive(utoipa::ToSchema))]
+#[derive(
+    Serialize,
+    Deserialize,
+    Debug,
+    Clone,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Default,
+)]
+pub struct StartPasskeyLogin {
+    pub email: String,
+}
+
+/// What a personal access token may be used for.
+#[cfg_attr(feature = "backend", derive(utoipa::ToSchema))]
+#[derive(
+    Serialize,
+    Deserialize,
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenScope {
+    ReadTodos,
+    WriteTodos,
+    /// Reading, updating and deleting the account of the token owner.
+    ManageAccount,
+}
+
+#[cfg_attr(feature = "backend", derive(utoipa::ToSchema))]
+#[derive(
+    Serialize,
+    Deserialize,
+    Debug,
+    Clone,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Default,
+)]
+pub struct CreateAccessToken {
+    /// Tells the tokens of a user apart, e.g. the script that uses it.
+    pub name: String,
+    pub scopes: Vec<TokenScope>,
+    /// The token never expires if this is not set.
+    pub expires_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// A personal access token of the session user, without the token itself.
+#[cfg_attr(feature = "backend", derive(utoipa::ToSchema))]
+#[derive(
+    Serialize,
+    Deserialize,
+    Debug,
+    Clone,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Default,
+)]
+pub struct AccessTokenSummary {
+    pub id: i64,
+    pub name: String,
+    pub scopes: Vec<TokenScope>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub expires_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub last_used_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+#[cfg_attr(feature = "backend", derive(utoipa::ToSchema))]
+#[derive(
+    Serialize,
+    Deserialize,
+    Debug,
+    Clone,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Default,
+)]
+pub struct CreatedAccessToken {
+    /// Sent as `Authorization: Bearer <token>` or as the password of Basic
+    /// auth. It is only shown once, only its hash is stored.
+    pub token: String,
+    pub access_token: AccessTokenSummary,
+}
+
+/// A user as the admin api shows them, without the password hash.
+#[cfg_attr(feature = "backend", derive(utoipa::ToSchema))]
+#[derive(
+    Serialize,
+    Deserialize,
+    Debug,
+    Clone,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Default,
+)]
+pub struct UserAccount {
+    pub id: i64,
+    pub name: String,
+    pub email: String,
+    pub role: Role,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub email_verified_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub disabled_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+impl From<User> for UserAccount {
+    fn from(user: User) -> Self {
+        Self {
+            id: user.id,
+            name: user.name,
+            email: user.email,
+            role: user.role,
+            created_at: user.created_at,
+            email_verified_at: user.email_verified_at,
+            disabled_at: user.disabled_at,
+        }
+    }
+}
+
+#[cfg_attr(feature = "backend", derive(utoipa::ToSchema))]
+#[derive(
+    Serialize,
+    Deserialize,
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Default,
+)]
+pub struct SetRole {
+    pub role: Role,
+}
+
+/// Numbers across all users for the admin api.
+#[cfg_attr(feature = "backend", derive(utoipa::ToSchema))]
+#[derive(
+    Serialize,
+    Deserialize,
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Default,
+)]
+pub struct AdminStats {
+    pub users: i64,
+    pub admins: i64,
+    pub disabled_users: i64,
+    pub todos: i64,
+    pub done_todos: i64,
+    /// Sessions that are not expired yet, including those without a login.
+    pub active_sessions: i64,
+}